@@ -2,25 +2,39 @@
 
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::{Key, NamedKey};
 use winit::window::{Window, WindowId};
 
-use wolia_platform::window::WindowConfig;
-use wolia_render::{Quad, QuadRenderer};
+use deck_engine::shape::ShapeKind;
+use deck_engine::slide::Background;
+use deck_engine::transition::Layer;
+use deck_engine::{Shape, Slide};
+use wolia_math::{Point, Rect, Size};
+use wolia_platform::window::{ScaleFactor, WindowConfig};
+use wolia_render::{DamageTracker, OffscreenTarget, Quad, QuadRenderer, Redraw};
+
+use crate::slides::SlideWorkspace;
+use crate::transitions::ActiveTransition;
+use crate::ui::slide_editor::SlideEditor;
+use crate::ui::slide_panel::SlidePanel;
 
 /// UI layout constants
 const TOOLBAR_HEIGHT: f32 = 48.0;
-const SLIDE_PANEL_WIDTH: f32 = 200.0;
 const STATUS_BAR_HEIGHT: f32 = 24.0;
-const SLIDE_ASPECT_RATIO: f32 = 16.0 / 9.0;
-const SLIDE_THUMBNAIL_MARGIN: f32 = 12.0;
+const SELECTION_COLOR: [f32; 4] = [0.26, 0.52, 0.96, 1.0];
+/// Pixels the thumbnail strip scrolls for each line a mouse wheel reports.
+const PIXELS_PER_LINE: f32 = 40.0;
+/// Time between frames while a transition plays.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
 
 /// Run the Wolia Deck application.
 pub fn run() -> Result<()> {
     let event_loop = EventLoop::new()?;
-    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.set_control_flow(ControlFlow::Wait);
 
     let mut app = DeckApp::new();
     event_loop.run_app(&mut app)?;
@@ -42,8 +56,25 @@ struct DeckApp {
     surface_config: Option<wgpu::SurfaceConfiguration>,
     /// Quad renderer for UI.
     quad_renderer: Option<QuadRenderer>,
-    /// Current window size.
+    /// Current window size, in physical pixels.
     window_size: (u32, u32),
+    /// Physical pixels per logical unit on the window's display.
+    scale: ScaleFactor,
+    /// Last known mouse position.
+    mouse_position: (f32, f32),
+    /// The presentation and the slide being shown.
+    workspace: SlideWorkspace,
+    /// Slide thumbnails.
+    panel: SlidePanel,
+    /// The canvas showing the current slide.
+    editor: SlideEditor,
+    /// The transition playing on the canvas, if any.
+    transition: Option<ActiveTransition>,
+    /// Regions of the window that changed since the last frame.
+    damage: DamageTracker,
+    /// The window's contents, kept between frames so that each need only
+    /// redraw its damage. `None` when the surface cannot be copied into.
+    back_buffer: Option<OffscreenTarget>,
 }
 
 impl DeckApp {
@@ -56,11 +87,138 @@ impl DeckApp {
             surface_config: None,
             quad_renderer: None,
             window_size: (1400, 900),
+            scale: ScaleFactor::ONE,
+            mouse_position: (0.0, 0.0),
+            workspace: SlideWorkspace::new(),
+            panel: SlidePanel::new(),
+            editor: SlideEditor::new(),
+            transition: None,
+            damage: DamageTracker::new(Size::new(1400.0, 900.0)),
+            back_buffer: None,
         }
     }
 
+    /// The window size in logical units, which the UI is laid out in.
+    fn logical_size(&self) -> (f32, f32) {
+        let size = self
+            .scale
+            .logical_size(self.window_size.0, self.window_size.1);
+        (size.width, size.height)
+    }
+
+    /// Configure the surface for a window of `width` by `height` physical
+    /// pixels.
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        self.window_size = (width, height);
+        let (w, h) = self.logical_size();
+        self.damage.resize(Size::new(w, h));
+        if let (Some(surface), Some(device), Some(config)) =
+            (&self.surface, &self.device, &mut self.surface_config)
+        {
+            config.width = width.max(1);
+            config.height = height.max(1);
+            surface.configure(device, config);
+            if let Some(back_buffer) = &mut self.back_buffer {
+                *back_buffer =
+                    OffscreenTarget::new(device, config.width, config.height, config.format);
+            }
+        }
+        self.sync_layout();
+    }
+
+    /// The slide panel, between the toolbar and status bar.
+    fn panel_area(&self) -> Rect {
+        let (_, h) = self.logical_size();
+        Rect::new(
+            0.0,
+            TOOLBAR_HEIGHT,
+            self.panel.width,
+            h - TOOLBAR_HEIGHT - STATUS_BAR_HEIGHT,
+        )
+    }
+
+    /// Fit the panel and canvas to the window and the presentation.
+    fn sync_layout(&mut self) {
+        let (w, h) = self.logical_size();
+        let size = self.workspace.presentation.slide_size;
+        self.panel.aspect_ratio = size.width / size.height;
+        self.editor.viewport = Rect::new(
+            self.panel.width,
+            TOOLBAR_HEIGHT,
+            w - self.panel.width,
+            h - TOOLBAR_HEIGHT - STATUS_BAR_HEIGHT,
+        );
+    }
+
+    /// Show the slide at `index`, scrolling its thumbnail into view.
+    fn select_slide(&mut self, index: usize) {
+        self.workspace.go_to_slide(index);
+        self.show_current_thumbnail();
+    }
+
+    /// Scroll the thumbnail of the current slide into view after the
+    /// slide changed.
+    fn show_current_thumbnail(&mut self) {
+        let height = self.panel_area().height;
+        let count = self.workspace.presentation.slide_count();
+        self.panel
+            .scroll_into_view(self.workspace.current_slide, height, count);
+        self.damage.invalidate_all();
+    }
+
+    /// Move between slides with the arrow, page and home/end keys.
+    fn handle_key(&mut self, key: &Key) {
+        let from = self.workspace.current_slide;
+        match key {
+            Key::Named(NamedKey::ArrowRight | NamedKey::ArrowDown | NamedKey::PageDown) => {
+                self.workspace.next_slide();
+            }
+            Key::Named(NamedKey::ArrowLeft | NamedKey::ArrowUp | NamedKey::PageUp) => {
+                self.workspace.prev_slide();
+            }
+            Key::Named(NamedKey::Home) => self.workspace.first_slide(),
+            Key::Named(NamedKey::End) => self.workspace.last_slide(),
+            _ => return,
+        }
+        self.transition = ActiveTransition::start(
+            &self.workspace.presentation,
+            from,
+            self.workspace.current_slide,
+            Instant::now(),
+        );
+        self.show_current_thumbnail();
+    }
+
+    /// Select the slide whose thumbnail was clicked.
+    fn handle_mouse_press(&mut self) {
+        let (mx, my) = self.mouse_position;
+        let count = self.workspace.presentation.slide_count();
+        if let Some(index) = self
+            .panel
+            .thumbnail_at(self.panel_area(), Point::new(mx, my), count)
+        {
+            self.select_slide(index);
+        }
+    }
+
+    /// Scroll the thumbnail strip when the wheel turns over it.
+    fn handle_wheel(&mut self, delta: MouseScrollDelta) {
+        let area = self.panel_area();
+        let (mx, my) = self.mouse_position;
+        if !area.contains(Point::new(mx, my)) {
+            return;
+        }
+        let pixels = match delta {
+            MouseScrollDelta::LineDelta(_, lines) => -lines * PIXELS_PER_LINE,
+            MouseScrollDelta::PixelDelta(position) => -position.y as f32,
+        };
+        let count = self.workspace.presentation.slide_count();
+        self.panel.scroll(pixels, area.height, count);
+        self.damage.add(area);
+    }
+
     fn build_ui(&self) -> Vec<Quad> {
-        let (w, h) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        let (w, h) = self.logical_size();
         let mut quads = Vec::new();
 
         // Toolbar background (dark theme)
@@ -89,161 +247,47 @@ impl DeckApp {
         }
 
         // Slide panel background (left sidebar)
-        let panel_y = TOOLBAR_HEIGHT;
-        let panel_height = h - TOOLBAR_HEIGHT - STATUS_BAR_HEIGHT;
+        let panel = self.panel_area();
         quads.push(Quad::new(
-            0.0,
-            panel_y,
-            SLIDE_PANEL_WIDTH,
-            panel_height,
+            panel.x,
+            panel.y,
+            panel.width,
+            panel.height,
             [0.12, 0.12, 0.14, 1.0],
         ));
 
         // Slide panel right border
         quads.push(Quad::new(
-            SLIDE_PANEL_WIDTH - 1.0,
-            panel_y,
+            panel.right() - 1.0,
+            panel.y,
             1.0,
-            panel_height,
+            panel.height,
             [0.25, 0.25, 0.28, 1.0],
         ));
 
-        // Slide thumbnails
-        let thumb_width = SLIDE_PANEL_WIDTH - SLIDE_THUMBNAIL_MARGIN * 2.0;
-        let thumb_height = thumb_width / SLIDE_ASPECT_RATIO;
-        let mut thumb_y = panel_y + SLIDE_THUMBNAIL_MARGIN;
-
-        for i in 0..5 {
-            // Thumbnail background
-            quads.push(Quad::new(
-                SLIDE_THUMBNAIL_MARGIN,
-                thumb_y,
-                thumb_width,
-                thumb_height,
-                [0.2, 0.2, 0.22, 1.0],
-            ));
-
-            // Slide content (white slide preview)
-            quads.push(Quad::new(
-                SLIDE_THUMBNAIL_MARGIN + 4.0,
-                thumb_y + 4.0,
-                thumb_width - 8.0,
-                thumb_height - 8.0,
-                [0.95, 0.95, 0.95, 1.0],
-            ));
-
-            // First slide selected indicator
-            if i == 0 {
-                quads.push(Quad::new(
-                    SLIDE_THUMBNAIL_MARGIN - 2.0,
-                    thumb_y - 2.0,
-                    thumb_width + 4.0,
-                    2.0,
-                    [0.26, 0.52, 0.96, 1.0],
-                )); // top
-                quads.push(Quad::new(
-                    SLIDE_THUMBNAIL_MARGIN - 2.0,
-                    thumb_y + thumb_height,
-                    thumb_width + 4.0,
-                    2.0,
-                    [0.26, 0.52, 0.96, 1.0],
-                )); // bottom
-                quads.push(Quad::new(
-                    SLIDE_THUMBNAIL_MARGIN - 2.0,
-                    thumb_y - 2.0,
-                    2.0,
-                    thumb_height + 4.0,
-                    [0.26, 0.52, 0.96, 1.0],
-                )); // left
-                quads.push(Quad::new(
-                    SLIDE_THUMBNAIL_MARGIN + thumb_width,
-                    thumb_y - 2.0,
-                    2.0,
-                    thumb_height + 4.0,
-                    [0.26, 0.52, 0.96, 1.0],
-                )); // right
-            }
-
-            thumb_y += thumb_height + SLIDE_THUMBNAIL_MARGIN;
-        }
-
         // Canvas area background (dark)
-        let canvas_x = SLIDE_PANEL_WIDTH;
-        let canvas_y = TOOLBAR_HEIGHT;
-        let canvas_w = w - SLIDE_PANEL_WIDTH;
-        let canvas_h = h - TOOLBAR_HEIGHT - STATUS_BAR_HEIGHT;
+        let canvas = self.editor.viewport;
         quads.push(Quad::new(
-            canvas_x,
-            canvas_y,
-            canvas_w,
-            canvas_h,
+            canvas.x,
+            canvas.y,
+            canvas.width,
+            canvas.height,
             [0.15, 0.15, 0.18, 1.0],
         ));
 
-        // Main slide (centered, 16:9 aspect ratio)
-        let slide_margin = 40.0;
-        let available_w = canvas_w - slide_margin * 2.0;
-        let available_h = canvas_h - slide_margin * 2.0;
-
-        // Calculate slide size maintaining aspect ratio
-        let slide_w;
-        let slide_h;
-        if available_w / SLIDE_ASPECT_RATIO <= available_h {
-            slide_w = available_w;
-            slide_h = available_w / SLIDE_ASPECT_RATIO;
-        } else {
-            slide_h = available_h;
-            slide_w = available_h * SLIDE_ASPECT_RATIO;
-        }
-
-        let slide_x = canvas_x + (canvas_w - slide_w) / 2.0;
-        let slide_y = canvas_y + (canvas_h - slide_h) / 2.0;
+        // Main slide (centered, keeping the presentation's aspect ratio)
+        let slide_size = self.workspace.presentation.slide_size;
+        let slide = self.editor.slide_rect(slide_size);
 
         // Slide shadow
         quads.push(Quad::new(
-            slide_x + 4.0,
-            slide_y + 4.0,
-            slide_w,
-            slide_h,
+            slide.x + 4.0,
+            slide.y + 4.0,
+            slide.width,
+            slide.height,
             [0.0, 0.0, 0.0, 0.3],
         ));
 
-        // Main slide background
-        quads.push(Quad::new(
-            slide_x,
-            slide_y,
-            slide_w,
-            slide_h,
-            [1.0, 1.0, 1.0, 1.0],
-        ));
-
-        // Placeholder content on slide (title area)
-        quads.push(Quad::new(
-            slide_x + slide_w * 0.1,
-            slide_y + slide_h * 0.15,
-            slide_w * 0.8,
-            slide_h * 0.08,
-            [0.9, 0.9, 0.9, 1.0],
-        ));
-
-        // Placeholder content on slide (subtitle area)
-        quads.push(Quad::new(
-            slide_x + slide_w * 0.15,
-            slide_y + slide_h * 0.28,
-            slide_w * 0.7,
-            slide_h * 0.04,
-            [0.92, 0.92, 0.92, 1.0],
-        ));
-
-        // Placeholder content on slide (body area)
-        quads.push(Quad::new(
-            slide_x + slide_w * 0.1,
-            slide_y + slide_h * 0.4,
-            slide_w * 0.8,
-            slide_h * 0.45,
-            [0.96, 0.96, 0.96, 1.0],
-        ));
-
         // Status bar background
         quads.push(Quad::new(
             0.0,
@@ -265,7 +309,105 @@ impl DeckApp {
         quads
     }
 
+    /// Thumbnails of every slide, with the panel they are clipped to as
+    /// the strip scrolls.
+    fn build_thumbnails(&self) -> (Rect, Vec<Quad>) {
+        let area = self.panel_area();
+        let slide_size = self.workspace.presentation.slide_size;
+        let mut quads = Vec::new();
+
+        for (index, slide) in self.workspace.presentation.slides().iter().enumerate() {
+            let thumb = self.panel.thumbnail_rect(area, index);
+            if !thumb.intersects(&area) {
+                continue;
+            }
+
+            // Current slide indicator
+            if index == self.workspace.current_slide {
+                quads.push(Quad::new(
+                    thumb.x - 2.0,
+                    thumb.y - 2.0,
+                    thumb.width + 4.0,
+                    thumb.height + 4.0,
+                    SELECTION_COLOR,
+                ));
+            }
+
+            // Thumbnail background
+            quads.push(Quad::new(
+                thumb.x,
+                thumb.y,
+                thumb.width,
+                thumb.height,
+                [0.2, 0.2, 0.22, 1.0],
+            ));
+
+            // Slide preview
+            let preview = Rect::new(
+                thumb.x + 4.0,
+                thumb.y + 4.0,
+                thumb.width - 8.0,
+                thumb.height - 8.0,
+            );
+            slide_quads(slide, slide_size, preview, &mut quads);
+        }
+
+        (area, quads)
+    }
+
+    /// The slides on the canvas, each with the region it is clipped to, in
+    /// drawing order. A transition shows two slides at once.
+    fn build_slide_layers(&self, now: Instant) -> Vec<(Rect, Vec<Quad>)> {
+        let presentation = &self.workspace.presentation;
+        let slide_size = presentation.slide_size;
+        let canvas = self.editor.viewport;
+        let rect = self.editor.slide_rect(slide_size);
+
+        let layers = match &self.transition {
+            Some(transition) => {
+                let compositing = transition.compositing(now);
+                let from = (transition.from, compositing.from);
+                let to = (transition.to, compositing.to);
+                if compositing.to_on_top {
+                    vec![from, to]
+                } else {
+                    vec![to, from]
+                }
+            }
+            None => vec![(self.workspace.current_slide, Layer::SHOWN)],
+        };
+
+        layers
+            .into_iter()
+            .filter(|(_, layer)| layer.opacity > 0.0)
+            .filter_map(|(index, layer)| {
+                let slide = presentation.slide(index)?;
+                let placed = Rect::new(
+                    rect.x + layer.offset.x * rect.width,
+                    rect.y + layer.offset.y * rect.height,
+                    rect.width,
+                    rect.height,
+                );
+                let visible = Rect::new(
+                    placed.x + layer.visible.x * placed.width,
+                    placed.y + layer.visible.y * placed.height,
+                    layer.visible.width * placed.width,
+                    layer.visible.height * placed.height,
+                );
+                let clip = visible.intersection(&canvas)?;
+
+                let mut quads = Vec::new();
+                slide_quads(slide, slide_size, placed, &mut quads);
+                for quad in &mut quads {
+                    quad.color[3] *= layer.opacity;
+                }
+                Some((clip, quads))
+            })
+            .collect()
+    }
+
     fn render(&mut self) {
+        self.sync_layout();
         let Some(surface) = &self.surface else { return };
         let Some(device) = &self.device else { return };
         let Some(queue) = &self.queue else { return };
@@ -290,29 +432,66 @@ impl DeckApp {
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        // Only the damage is redrawn when the last frame was kept.
+        let (target, redraw) = match &self.back_buffer {
+            Some(back_buffer) => (&back_buffer.view, self.damage.take_redraw()),
+            None => {
+                self.damage.take();
+                (&view, Redraw::full())
+            }
+        };
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
-        // Build and render UI
-        let quads = self.build_ui();
-        let (w, h) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        // Build and render UI, over a background painted rather than
+        // cleared, as clears are not clipped.
+        let (w, h) = self.logical_size();
+        let mut quads = vec![Quad::new(0.0, 0.0, w, h, [0.15, 0.15, 0.18, 1.0])];
+        quads.extend(self.build_ui());
 
         quad_renderer.render(
             &mut encoder,
-            &view,
+            target,
             queue,
             &quads,
             w,
             h,
-            Some(wgpu::Color {
-                r: 0.15,
-                g: 0.15,
-                b: 0.18,
-                a: 1.0,
-            }),
+            None,
+            redraw.clip(None),
+        );
+
+        // The slide on the canvas, or both slides during a transition.
+        for (clip, layer) in self.build_slide_layers(Instant::now()) {
+            quad_renderer.render_instanced(
+                device,
+                &mut encoder,
+                target,
+                &layer,
+                w,
+                h,
+                None,
+                redraw.clip(Some(clip)),
+            );
+        }
+
+        // Thumbnails, clipped to the panel. They get their own instance
+        // buffer, as the quads above still use the shared one.
+        let (clip, thumbnails) = self.build_thumbnails();
+        quad_renderer.render_instanced(
+            device,
+            &mut encoder,
+            target,
+            &thumbnails,
+            w,
+            h,
+            None,
+            redraw.clip(Some(clip)),
         );
 
+        if let Some(back_buffer) = &self.back_buffer {
+            back_buffer.copy_to(&mut encoder, &frame.texture);
+        }
         queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
@@ -323,6 +502,7 @@ impl ApplicationHandler for DeckApp {
         if self.window.is_none() {
             let config = WindowConfig::new("Wolia Deck")
                 .with_size(1400.0, 900.0)
+                .with_min_size(800.0, 500.0)
                 .with_icon("wolia.png");
             let attrs = config.to_window_attributes();
 
@@ -361,12 +541,22 @@ impl ApplicationHandler for DeckApp {
 
                     let size = window.inner_size();
                     self.window_size = (size.width, size.height);
+                    self.scale = ScaleFactor::new(window.scale_factor());
+                    let (w, h) = self.logical_size();
+                    self.damage.resize(Size::new(w, h));
 
                     let surface_caps = surface.get_capabilities(&adapter);
                     let format = surface_caps.formats[0];
+                    // Frames are kept to redraw only their damage when
+                    // they can be copied to the surface.
+                    let keep_frames = surface_caps.usages.contains(wgpu::TextureUsages::COPY_DST);
 
                     let surface_config = wgpu::SurfaceConfiguration {
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                        usage: if keep_frames {
+                            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST
+                        } else {
+                            wgpu::TextureUsages::RENDER_ATTACHMENT
+                        },
                         format,
                         width: size.width.max(1),
                         height: size.height.max(1),
@@ -378,14 +568,25 @@ impl ApplicationHandler for DeckApp {
                     surface.configure(&device, &surface_config);
 
                     // Create quad renderer
-                    let quad_renderer = QuadRenderer::new(&device, format);
+                    let mut quad_renderer = QuadRenderer::new(&device, format);
+                    quad_renderer.set_scale_factor(self.scale.get() as f32);
+                    let back_buffer = keep_frames.then(|| {
+                        OffscreenTarget::new(
+                            &device,
+                            surface_config.width,
+                            surface_config.height,
+                            format,
+                        )
+                    });
 
                     self.surface = Some(surface);
                     self.device = Some(device);
                     self.queue = Some(queue);
+                    self.back_buffer = back_buffer;
                     self.surface_config = Some(surface_config);
                     self.quad_renderer = Some(quad_renderer);
                     self.window = Some(window);
+                    self.damage.invalidate_all();
                 }
                 Err(e) => {
                     tracing::error!("Failed to create window: {}", e);
@@ -402,25 +603,173 @@ impl ApplicationHandler for DeckApp {
             }
             WindowEvent::Resized(size) => {
                 tracing::debug!("Window resized to {:?}", size);
-                self.window_size = (size.width, size.height);
-                if let (Some(surface), Some(device), Some(config)) =
-                    (&self.surface, &self.device, &mut self.surface_config)
-                {
-                    config.width = size.width.max(1);
-                    config.height = size.height.max(1);
-                    surface.configure(device, config);
+                // Resizing the surface damages the whole window, which is
+                // redrawn at once: some platforms send no other events
+                // while a resize is dragged.
+                self.resize_surface(size.width, size.height);
+                if let Some(window) = &self.window {
+                    window.request_redraw();
                 }
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                tracing::debug!("Scale factor changed to {}", scale_factor);
+                self.scale = ScaleFactor::new(scale_factor);
+                if let Some(quad_renderer) = &mut self.quad_renderer {
+                    quad_renderer.set_scale_factor(scale_factor as f32);
+                }
+                if let Some(size) = self.window.as_ref().map(|window| window.inner_size()) {
+                    self.resize_surface(size.width, size.height);
+                }
+                self.damage.invalidate_all();
+            }
             WindowEvent::RedrawRequested => {
                 self.render();
             }
+            WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() => {
+                self.handle_key(&event.logical_key);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = self
+                    .scale
+                    .point_to_logical(Point::new(position.x as f32, position.y as f32));
+                self.mouse_position = (position.x, position.y);
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.sync_layout();
+                self.handle_mouse_press();
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.sync_layout();
+                self.handle_wheel(delta);
+            }
             _ => {}
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        if let Some(window) = &self.window {
-            window.request_redraw();
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // A transition redraws the canvas every frame until it finishes.
+        let now = Instant::now();
+        match &self.transition {
+            Some(transition) if transition.is_finished(now) => {
+                self.transition = None;
+                self.damage.add(self.editor.viewport);
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            Some(_) => {
+                self.damage.add(self.editor.viewport);
+                event_loop.set_control_flow(ControlFlow::WaitUntil(now + FRAME_INTERVAL));
+            }
+            None => {}
+        }
+
+        if self.damage.is_dirty() {
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
         }
     }
 }
+
+/// Convert a color to floats, folding in a shape opacity. An opacity of
+/// zero is the unset default and leaves the color untouched.
+fn color(rgba: [u8; 4], opacity: f32) -> [f32; 4] {
+    let alpha = rgba[3] as f32 / 255.0;
+    let alpha = if opacity > 0.0 && opacity < 1.0 {
+        alpha * opacity
+    } else {
+        alpha
+    };
+    [
+        rgba[0] as f32 / 255.0,
+        rgba[1] as f32 / 255.0,
+        rgba[2] as f32 / 255.0,
+        alpha,
+    ]
+}
+
+/// Draw a slide of `slide_size` scaled into `rect`: its background, then
+/// its shapes from back to front.
+fn slide_quads(slide: &Slide, slide_size: Size, rect: Rect, quads: &mut Vec<Quad>) {
+    let background = match &slide.background {
+        Background::Solid(rgba) => color(*rgba, 1.0),
+        // Gradients are drawn in their starting color and images as
+        // white until they can be rendered.
+        Background::Gradient { start, .. } => color(*start, 1.0),
+        Background::Image { .. } => [1.0, 1.0, 1.0, 1.0],
+    };
+    quads.push(Quad::new(
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
+        background,
+    ));
+
+    let scale = rect.width / slide_size.width;
+    for shape in slide.shapes.iter().filter(|shape| !shape.hidden) {
+        shape_quads(shape, rect, scale, quads);
+    }
+}
+
+/// Draw a shape on a slide drawn at `origin` and `scale`. Shapes are drawn
+/// as their filled and stroked bounds; text, media and rotation are not
+/// rendered yet.
+fn shape_quads(shape: &Shape, origin: Rect, scale: f32, quads: &mut Vec<Quad>) {
+    let bounds = Rect::new(
+        origin.x + shape.bounds.x * scale,
+        origin.y + shape.bounds.y * scale,
+        shape.bounds.width * scale,
+        shape.bounds.height * scale,
+    );
+    let style = &shape.style;
+    let stroke_width = (style.stroke_width * scale).max(1.0);
+
+    // Lines are drawn across the middle of their bounds.
+    if matches!(shape.kind, ShapeKind::Line | ShapeKind::Arrow) {
+        let stroke = style.stroke.or(style.fill).unwrap_or([0, 0, 0, 255]);
+        quads.push(Quad::new(
+            bounds.x,
+            bounds.y + (bounds.height - stroke_width) / 2.0,
+            bounds.width,
+            stroke_width,
+            color(stroke, style.opacity),
+        ));
+        return;
+    }
+
+    let fill = match (&shape.kind, style.fill) {
+        (_, Some(fill)) => Some(color(fill, style.opacity)),
+        // Placeholders for content that is not rendered yet.
+        (ShapeKind::TextBox(_), None) => Some([0.9, 0.9, 0.9, 1.0]),
+        (
+            ShapeKind::Image { .. }
+            | ShapeKind::Video { .. }
+            | ShapeKind::Table { .. }
+            | ShapeKind::Chart { .. },
+            None,
+        ) => Some([0.85, 0.85, 0.85, 1.0]),
+        (_, None) => None,
+    };
+    if let Some(fill) = fill {
+        quads.push(Quad::new(
+            bounds.x,
+            bounds.y,
+            bounds.width,
+            bounds.height,
+            fill,
+        ));
+    }
+
+    if let Some(stroke) = style.stroke.filter(|_| style.stroke_width > 0.0) {
+        let stroke = color(stroke, style.opacity);
+        let (x, y, w, h) = (bounds.x, bounds.y, bounds.width, bounds.height);
+        quads.push(Quad::new(x, y, w, stroke_width, stroke));
+        quads.push(Quad::new(x, y + h - stroke_width, w, stroke_width, stroke));
+        quads.push(Quad::new(x, y, stroke_width, h, stroke));
+        quads.push(Quad::new(x + w - stroke_width, y, stroke_width, h, stroke));
+    }
+}
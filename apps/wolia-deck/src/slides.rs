@@ -1,6 +1,6 @@
 //! Slide management.
 
-use deck_engine::Presentation;
+use deck_engine::{Presentation, Slide};
 
 /// Slide workspace.
 pub struct SlideWorkspace {
@@ -22,6 +22,34 @@ impl SlideWorkspace {
         }
     }
 
+    /// Create a workspace for a presentation, showing its first slide.
+    pub fn with_presentation(presentation: Presentation) -> Self {
+        Self {
+            presentation,
+            ..Self::new()
+        }
+    }
+
+    /// The slide being shown.
+    pub fn current(&self) -> Option<&Slide> {
+        self.presentation.slide(self.current_slide)
+    }
+
+    /// Go to the slide at `index`, or the last slide if there are fewer.
+    pub fn go_to_slide(&mut self, index: usize) {
+        self.current_slide = index.min(self.presentation.slide_count().saturating_sub(1));
+    }
+
+    /// Go to the first slide.
+    pub fn first_slide(&mut self) {
+        self.current_slide = 0;
+    }
+
+    /// Go to the last slide.
+    pub fn last_slide(&mut self) {
+        self.go_to_slide(usize::MAX);
+    }
+
     /// Go to the next slide.
     pub fn next_slide(&mut self) {
         if self.current_slide < self.presentation.slide_count().saturating_sub(1) {
@@ -51,3 +79,49 @@ impl Default for SlideWorkspace {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(slides: usize) -> SlideWorkspace {
+        let mut presentation = Presentation::new();
+        for _ in 1..slides {
+            presentation.add_slide();
+        }
+        SlideWorkspace::with_presentation(presentation)
+    }
+
+    #[test]
+    fn test_next_and_prev_clamp() {
+        let mut workspace = workspace(3);
+        workspace.prev_slide();
+        assert_eq!(workspace.current_slide, 0);
+
+        workspace.next_slide();
+        workspace.next_slide();
+        workspace.next_slide();
+        assert_eq!(workspace.current_slide, 2);
+        workspace.prev_slide();
+        assert_eq!(workspace.current_slide, 1);
+
+        // A single slide has nowhere to go.
+        let mut single = self::workspace(1);
+        single.next_slide();
+        assert_eq!(single.current_slide, 0);
+    }
+
+    #[test]
+    fn test_go_to_slide_clamps() {
+        let mut workspace = workspace(4);
+        workspace.go_to_slide(2);
+        assert_eq!(workspace.current_slide, 2);
+        workspace.go_to_slide(10);
+        assert_eq!(workspace.current_slide, 3);
+        workspace.first_slide();
+        assert_eq!(workspace.current_slide, 0);
+        workspace.last_slide();
+        assert_eq!(workspace.current_slide, 3);
+        assert!(workspace.current().is_some());
+    }
+}
@@ -1,67 +1,95 @@
-//! Slide transitions.
+//! Slide transitions played on the canvas.
 
-/// Transition type.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum TransitionType {
-    #[default]
-    None,
-    Fade,
-    Push,
-    Wipe,
-    Split,
-    Reveal,
-    Cover,
-    Dissolve,
-    Zoom,
-}
+use std::time::Instant;
 
-/// Transition direction.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum TransitionDirection {
-    Left,
-    #[default]
-    Right,
-    Up,
-    Down,
-}
+use deck_engine::Presentation;
+use deck_engine::transition::{Compositing, TransitionState};
 
-/// Slide transition settings.
-#[derive(Debug, Clone)]
-pub struct Transition {
-    /// Transition type.
-    pub kind: TransitionType,
-    /// Transition direction.
-    pub direction: TransitionDirection,
-    /// Duration in seconds.
-    pub duration: f32,
+/// A transition playing while the canvas moves between two slides.
+pub struct ActiveTransition {
+    /// The slide being left.
+    pub from: usize,
+    /// The slide being shown.
+    pub to: usize,
+    /// The transition and the way it plays.
+    state: TransitionState,
+    /// When it started.
+    started: Instant,
 }
 
-impl Default for Transition {
-    fn default() -> Self {
-        Self {
-            kind: TransitionType::None,
-            direction: TransitionDirection::Right,
-            duration: 0.5,
+impl ActiveTransition {
+    /// Start moving from slide `from` to slide `to` at `now`. Moving
+    /// forward plays the transition into `to`; moving back plays the
+    /// transition into `from` in reverse. Returns `None` when the slides
+    /// are the same or the transition is instant.
+    pub fn start(
+        presentation: &Presentation,
+        from: usize,
+        to: usize,
+        now: Instant,
+    ) -> Option<Self> {
+        if from == to {
+            return None;
         }
+        let transition = presentation.slide(from.max(to))?.transition.clone()?;
+        let state = if to > from {
+            TransitionState::new(transition)
+        } else {
+            TransitionState::reversed(transition)
+        };
+        if state.is_finished(0.0) {
+            return None;
+        }
+        Some(Self {
+            from,
+            to,
+            state,
+            started: now,
+        })
     }
-}
 
-impl Transition {
-    /// Create a fade transition.
-    pub fn fade(duration: f32) -> Self {
-        Self {
-            kind: TransitionType::Fade,
-            direction: TransitionDirection::Right,
-            duration,
-        }
+    /// Seconds since the transition started.
+    fn elapsed(&self, now: Instant) -> f32 {
+        now.saturating_duration_since(self.started).as_secs_f32()
     }
 
-    /// Create a push transition.
-    pub fn push(direction: TransitionDirection, duration: f32) -> Self {
-        Self {
-            kind: TransitionType::Push,
-            direction,
-            duration,
-        }
+    /// How to draw both slides at `now`.
+    pub fn compositing(&self, now: Instant) -> Compositing {
+        self.state.compositing(self.elapsed(now))
+    }
+
+    /// Whether the transition has finished at `now`.
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.state.is_finished(self.elapsed(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use deck_engine::Transition;
+    use deck_engine::transition::TransitionDirection;
+
+    use super::*;
+
+    #[test]
+    fn test_plays_the_later_slides_transition() {
+        let mut presentation = Presentation::new();
+        presentation.add_slide();
+        let now = Instant::now();
+        // Neither slide has a transition yet.
+        assert!(ActiveTransition::start(&presentation, 0, 1, now).is_none());
+
+        presentation.slide_mut(1).unwrap().transition =
+            Some(Transition::push(TransitionDirection::Left, 1.0));
+        let forward = ActiveTransition::start(&presentation, 0, 1, now).unwrap();
+        assert!(!forward.state.reverse);
+        let back = ActiveTransition::start(&presentation, 1, 0, now).unwrap();
+        assert!(back.state.reverse);
+        assert!(back.is_finished(now + Duration::from_secs(1)));
+
+        presentation.slide_mut(1).unwrap().transition = Some(Transition::fade(0.0));
+        assert!(ActiveTransition::start(&presentation, 0, 1, now).is_none());
     }
 }
@@ -1,6 +1,9 @@
 //! Slide editor view.
 
-use wolia_math::Rect;
+use wolia_math::{Rect, Size};
+
+/// Space kept around the slide in the canvas.
+const SLIDE_MARGIN: f32 = 40.0;
 
 /// Slide editor.
 pub struct SlideEditor {
@@ -23,6 +26,12 @@ impl SlideEditor {
             snap_to_grid: true,
         }
     }
+
+    /// The screen rectangle of a slide of `slide_size`, as large as fits
+    /// in the viewport with a margin and centered in it.
+    pub fn slide_rect(&self, slide_size: Size) -> Rect {
+        fit(self.viewport, slide_size, SLIDE_MARGIN)
+    }
 }
 
 impl Default for SlideEditor {
@@ -30,3 +39,20 @@ impl Default for SlideEditor {
         Self::new()
     }
 }
+
+/// The largest rectangle with the proportions of `size` that fits in
+/// `area` less `margin` on every side, centered in `area`.
+pub fn fit(area: Rect, size: Size, margin: f32) -> Rect {
+    let available = Size::new(
+        (area.width - margin * 2.0).max(0.0),
+        (area.height - margin * 2.0).max(0.0),
+    );
+    let scale = (available.width / size.width).min(available.height / size.height);
+    let (width, height) = (size.width * scale, size.height * scale);
+    Rect::new(
+        area.x + (area.width - width) / 2.0,
+        area.y + (area.height - height) / 2.0,
+        width,
+        height,
+    )
+}
@@ -1,6 +1,14 @@
 //! Slide panel (thumbnail view).
 
+use wolia_math::{Point, Rect};
+
+/// Space around and between thumbnails.
+const THUMBNAIL_MARGIN: f32 = 12.0;
+
 /// Slide panel for navigation.
+///
+/// Thumbnails are stacked top to bottom and the strip scrolls when they
+/// do not all fit in the panel.
 pub struct SlidePanel {
     /// Panel width.
     pub width: f32,
@@ -8,6 +16,10 @@ pub struct SlidePanel {
     pub visible: bool,
     /// Thumbnail size.
     pub thumbnail_width: f32,
+    /// Width over height of the slides.
+    pub aspect_ratio: f32,
+    /// Scroll offset of the thumbnail strip in pixels.
+    pub scroll_y: f32,
 }
 
 impl SlidePanel {
@@ -16,7 +28,59 @@ impl SlidePanel {
             width: 200.0,
             visible: true,
             thumbnail_width: 180.0,
+            aspect_ratio: 16.0 / 9.0,
+            scroll_y: 0.0,
+        }
+    }
+
+    /// Distance from the top of one thumbnail to the top of the next.
+    fn stride(&self) -> f32 {
+        let width = self.width - THUMBNAIL_MARGIN * 2.0;
+        width / self.aspect_ratio + THUMBNAIL_MARGIN
+    }
+
+    /// The screen rectangle of the thumbnail at `index` in a panel
+    /// occupying `area`, scrolled.
+    pub fn thumbnail_rect(&self, area: Rect, index: usize) -> Rect {
+        let width = self.width - THUMBNAIL_MARGIN * 2.0;
+        Rect::new(
+            area.x + THUMBNAIL_MARGIN,
+            area.y + THUMBNAIL_MARGIN + index as f32 * self.stride() - self.scroll_y,
+            width,
+            width / self.aspect_ratio,
+        )
+    }
+
+    /// The thumbnail under `point`, out of `count`.
+    pub fn thumbnail_at(&self, area: Rect, point: Point, count: usize) -> Option<usize> {
+        if !area.contains(point) {
+            return None;
+        }
+        (0..count).find(|&index| self.thumbnail_rect(area, index).contains(point))
+    }
+
+    /// The farthest the strip of `count` thumbnails scrolls in a panel
+    /// `height` pixels tall.
+    pub fn max_scroll(&self, height: f32, count: usize) -> f32 {
+        let content = THUMBNAIL_MARGIN + count as f32 * self.stride();
+        (content - height).max(0.0)
+    }
+
+    /// Scroll the strip by `delta` pixels, keeping it within its content.
+    pub fn scroll(&mut self, delta: f32, height: f32, count: usize) {
+        self.scroll_y = (self.scroll_y + delta).clamp(0.0, self.max_scroll(height, count));
+    }
+
+    /// Scroll just enough to show the whole thumbnail at `index`.
+    pub fn scroll_into_view(&mut self, index: usize, height: f32, count: usize) {
+        let top = index as f32 * self.stride();
+        let bottom = top + self.stride() + THUMBNAIL_MARGIN;
+        if top < self.scroll_y {
+            self.scroll_y = top;
+        } else if bottom > self.scroll_y + height {
+            self.scroll_y = bottom - height;
         }
+        self.scroll(0.0, height, count);
     }
 }
 
@@ -25,3 +89,36 @@ impl Default for SlidePanel {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_strip_scrolls() {
+        // 176 by 99 thumbnails, 111 pixels apart, in a 300 pixel panel.
+        let mut panel = SlidePanel::new();
+        let area = Rect::new(0.0, 48.0, 200.0, 300.0);
+        assert_eq!(
+            panel.thumbnail_at(area, Point::new(100.0, 100.0), 10),
+            Some(0)
+        );
+        assert_eq!(
+            panel.thumbnail_at(area, Point::new(100.0, 180.0), 10),
+            Some(1)
+        );
+        assert_eq!(panel.thumbnail_at(area, Point::new(5.0, 100.0), 10), None);
+
+        panel.scroll(10_000.0, 300.0, 10);
+        assert_eq!(panel.scroll_y, 12.0 + 1110.0 - 300.0);
+        panel.scroll_into_view(0, 300.0, 10);
+        assert_eq!(panel.scroll_y, 0.0);
+        panel.scroll_into_view(4, 300.0, 10);
+        let last = panel.thumbnail_rect(area, 4);
+        assert_eq!(last.y + last.height + THUMBNAIL_MARGIN, area.y + 300.0);
+
+        // Three thumbnails fit without scrolling.
+        panel.scroll(500.0, 400.0, 3);
+        assert_eq!(panel.scroll_y, 0.0);
+    }
+}
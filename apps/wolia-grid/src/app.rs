@@ -7,8 +7,9 @@ use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::window::{Window, WindowId};
 
-use wolia_platform::window::WindowConfig;
-use wolia_render::{Quad, QuadRenderer};
+use wolia_math::Size;
+use wolia_platform::window::{ScaleFactor, WindowConfig};
+use wolia_render::{DamageTracker, OffscreenTarget, Quad, QuadRenderer, Redraw};
 
 /// UI layout constants
 const TOOLBAR_HEIGHT: f32 = 48.0;
@@ -22,7 +23,7 @@ const CELL_HEIGHT: f32 = 24.0;
 /// Run the Wolia Grid application.
 pub fn run() -> Result<()> {
     let event_loop = EventLoop::new()?;
-    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.set_control_flow(ControlFlow::Wait);
 
     let mut app = GridApp::new();
     event_loop.run_app(&mut app)?;
@@ -44,8 +45,15 @@ struct GridApp {
     surface_config: Option<wgpu::SurfaceConfiguration>,
     /// Quad renderer for UI.
     quad_renderer: Option<QuadRenderer>,
-    /// Current window size.
+    /// Current window size, in physical pixels.
     window_size: (u32, u32),
+    /// Physical pixels per logical unit on the window's display.
+    scale: ScaleFactor,
+    /// Regions of the window that changed since the last frame.
+    damage: DamageTracker,
+    /// The window's contents, kept between frames so that each need only
+    /// redraw its damage. `None` when the surface cannot be copied into.
+    back_buffer: Option<OffscreenTarget>,
 }
 
 impl GridApp {
@@ -58,11 +66,41 @@ impl GridApp {
             surface_config: None,
             quad_renderer: None,
             window_size: (1400, 900),
+            scale: ScaleFactor::ONE,
+            damage: DamageTracker::new(Size::new(1400.0, 900.0)),
+            back_buffer: None,
+        }
+    }
+
+    /// The window size in logical units, which the UI is laid out in.
+    fn logical_size(&self) -> (f32, f32) {
+        let size = self
+            .scale
+            .logical_size(self.window_size.0, self.window_size.1);
+        (size.width, size.height)
+    }
+
+    /// Configure the surface for a window of `width` by `height` physical
+    /// pixels.
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        self.window_size = (width, height);
+        let (w, h) = self.logical_size();
+        self.damage.resize(Size::new(w, h));
+        if let (Some(surface), Some(device), Some(config)) =
+            (&self.surface, &self.device, &mut self.surface_config)
+        {
+            config.width = width.max(1);
+            config.height = height.max(1);
+            surface.configure(device, config);
+            if let Some(back_buffer) = &mut self.back_buffer {
+                *back_buffer =
+                    OffscreenTarget::new(device, config.width, config.height, config.format);
+            }
         }
     }
 
     fn build_ui(&self) -> Vec<Quad> {
-        let (w, h) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        let (w, h) = self.logical_size();
         let mut quads = Vec::new();
 
         // Toolbar background
@@ -323,29 +361,38 @@ impl GridApp {
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        // Only the damage is redrawn when the last frame was kept.
+        let (target, redraw) = match &self.back_buffer {
+            Some(back_buffer) => (&back_buffer.view, self.damage.take_redraw()),
+            None => {
+                self.damage.take();
+                (&view, Redraw::full())
+            }
+        };
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
-        // Build and render UI
-        let quads = self.build_ui();
-        let (w, h) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        // Build and render UI, over a background painted rather than
+        // cleared, as clears are not clipped.
+        let (w, h) = self.logical_size();
+        let mut quads = vec![Quad::new(0.0, 0.0, w, h, [0.94, 0.94, 0.94, 1.0])];
+        quads.extend(self.build_ui());
 
         quad_renderer.render(
             &mut encoder,
-            &view,
+            target,
             queue,
             &quads,
             w,
             h,
-            Some(wgpu::Color {
-                r: 0.94,
-                g: 0.94,
-                b: 0.94,
-                a: 1.0,
-            }),
+            None,
+            redraw.clip(None),
         );
 
+        if let Some(back_buffer) = &self.back_buffer {
+            back_buffer.copy_to(&mut encoder, &frame.texture);
+        }
         queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
@@ -356,6 +403,7 @@ impl ApplicationHandler for GridApp {
         if self.window.is_none() {
             let config = WindowConfig::new("Wolia Grid")
                 .with_size(1400.0, 900.0)
+                .with_min_size(800.0, 500.0)
                 .with_icon("wolia.png");
             let attrs = config.to_window_attributes();
 
@@ -394,12 +442,22 @@ impl ApplicationHandler for GridApp {
 
                     let size = window.inner_size();
                     self.window_size = (size.width, size.height);
+                    self.scale = ScaleFactor::new(window.scale_factor());
+                    let (w, h) = self.logical_size();
+                    self.damage.resize(Size::new(w, h));
 
                     let surface_caps = surface.get_capabilities(&adapter);
                     let format = surface_caps.formats[0];
+                    // Frames are kept to redraw only their damage when
+                    // they can be copied to the surface.
+                    let keep_frames = surface_caps.usages.contains(wgpu::TextureUsages::COPY_DST);
 
                     let surface_config = wgpu::SurfaceConfiguration {
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                        usage: if keep_frames {
+                            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST
+                        } else {
+                            wgpu::TextureUsages::RENDER_ATTACHMENT
+                        },
                         format,
                         width: size.width.max(1),
                         height: size.height.max(1),
@@ -411,14 +469,25 @@ impl ApplicationHandler for GridApp {
                     surface.configure(&device, &surface_config);
 
                     // Create quad renderer
-                    let quad_renderer = QuadRenderer::new(&device, format);
+                    let mut quad_renderer = QuadRenderer::new(&device, format);
+                    quad_renderer.set_scale_factor(self.scale.get() as f32);
+                    let back_buffer = keep_frames.then(|| {
+                        OffscreenTarget::new(
+                            &device,
+                            surface_config.width,
+                            surface_config.height,
+                            format,
+                        )
+                    });
 
                     self.surface = Some(surface);
                     self.device = Some(device);
                     self.queue = Some(queue);
+                    self.back_buffer = back_buffer;
                     self.surface_config = Some(surface_config);
                     self.quad_renderer = Some(quad_renderer);
                     self.window = Some(window);
+                    self.damage.invalidate_all();
                 }
                 Err(e) => {
                     tracing::error!("Failed to create window: {}", e);
@@ -435,15 +504,25 @@ impl ApplicationHandler for GridApp {
             }
             WindowEvent::Resized(size) => {
                 tracing::debug!("Window resized to {:?}", size);
-                self.window_size = (size.width, size.height);
-                if let (Some(surface), Some(device), Some(config)) =
-                    (&self.surface, &self.device, &mut self.surface_config)
-                {
-                    config.width = size.width.max(1);
-                    config.height = size.height.max(1);
-                    surface.configure(device, config);
+                // Resizing the surface damages the whole window, which is
+                // redrawn at once: some platforms send no other events
+                // while a resize is dragged.
+                self.resize_surface(size.width, size.height);
+                if let Some(window) = &self.window {
+                    window.request_redraw();
                 }
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                tracing::debug!("Scale factor changed to {}", scale_factor);
+                self.scale = ScaleFactor::new(scale_factor);
+                if let Some(quad_renderer) = &mut self.quad_renderer {
+                    quad_renderer.set_scale_factor(scale_factor as f32);
+                }
+                if let Some(size) = self.window.as_ref().map(|window| window.inner_size()) {
+                    self.resize_surface(size.width, size.height);
+                }
+                self.damage.invalidate_all();
+            }
             WindowEvent::RedrawRequested => {
                 self.render();
             }
@@ -452,8 +531,10 @@ impl ApplicationHandler for GridApp {
     }
 
     fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        if let Some(window) = &self.window {
-            window.request_redraw();
+        if self.damage.is_dirty() {
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
         }
     }
 }
@@ -42,20 +42,27 @@ impl GridEditor {
         sheet.get(cell).map(|c| c.value.clone())
     }
 
-    /// Set the value of a cell.
+    /// Set the value of a cell, recalculating the cells that depend on it.
     pub fn set_cell_value(&mut self, cell: CellRef, value: CellValue) {
-        let sheet = self.spreadsheet.active_mut();
-        let cell_obj = Cell::with_value(value);
-        sheet.set(cell, cell_obj);
+        self.spreadsheet.set_cell(cell, value);
+    }
+
+    /// The text to edit a cell as: its formula, or its value.
+    fn cell_input(&self, cell: CellRef) -> String {
+        match self.spreadsheet.active().get(cell) {
+            Some(Cell {
+                formula: Some(formula),
+                ..
+            }) => formula.clone(),
+            Some(cell) => cell.value.to_display_string(),
+            None => String::new(),
+        }
     }
 
     /// Start editing the selected cell.
     pub fn start_editing(&mut self) {
         let cell = self.selected_cell();
-        let value = self
-            .get_cell_value(cell)
-            .map(|v| v.to_display_string())
-            .unwrap_or_default();
+        let value = self.cell_input(cell);
 
         self.view.start_edit(cell, value);
         self.mode = EditMode::Edit;
@@ -65,18 +72,15 @@ impl GridEditor {
     pub fn finish_editing(&mut self) {
         if let Some((cell, value)) = self.view.finish_edit() {
             // Parse the value based on its content
-            let cell_value = if value.starts_with('=') {
-                // Formula
-                CellValue::Text(value) // TODO: Parse as formula
+            let input = if value.starts_with('=') {
+                Cell::with_formula(value)
             } else if let Ok(n) = value.parse::<f64>() {
-                // Number
-                CellValue::Number(n)
+                Cell::with_value(CellValue::Number(n))
             } else {
-                // Text
-                CellValue::Text(value)
+                Cell::with_value(CellValue::Text(value))
             };
 
-            self.set_cell_value(cell, cell_value);
+            self.spreadsheet.set_cell(cell, input);
         }
 
         self.mode = EditMode::Normal;
@@ -148,9 +152,7 @@ impl GridEditor {
         if let Some(edit_text) = self.get_edit_text() {
             edit_text.to_string()
         } else {
-            self.get_selected_value()
-                .map(|v| v.to_display_string())
-                .unwrap_or_default()
+            self.cell_input(self.selected_cell())
         }
     }
 
@@ -261,4 +263,22 @@ mod tests {
         let text = editor.get_formula_bar_text();
         assert_eq!(text, "Test");
     }
+
+    #[test]
+    fn test_formula_recalculates() {
+        let mut editor = GridEditor::new();
+        let a1 = CellRef::new(0, 0);
+        let b1 = CellRef::new(0, 1);
+        editor.set_cell_value(a1, CellValue::Number(2.0));
+
+        editor.goto_cell(b1);
+        editor.start_editing();
+        editor.update_edit_text("=A1*10");
+        editor.finish_editing();
+        assert_eq!(editor.get_cell_value(b1), Some(CellValue::Number(20.0)));
+        assert_eq!(editor.get_formula_bar_text(), "=A1*10");
+
+        editor.set_cell_value(a1, CellValue::Number(3.0));
+        assert_eq!(editor.get_cell_value(b1), Some(CellValue::Number(30.0)));
+    }
 }
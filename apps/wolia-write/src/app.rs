@@ -2,6 +2,7 @@
 
 use anyhow::Result;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
@@ -9,24 +10,78 @@ use winit::window::{Window, WindowId};
 
 use wolia_assets::icons::IconManager;
 use wolia_core::Document;
-use wolia_platform::window::WindowConfig;
-use wolia_render::{IconRenderer, Quad, QuadRenderer};
+use wolia_core::style::RevisionKind;
+use wolia_edit::autosave;
+use wolia_math::{Point, Rect, Size};
+use wolia_platform::dialog;
+use wolia_platform::window::{ScaleFactor, WindowConfig};
+use wolia_render::{
+    DEFAULT_SAMPLE_COUNT, DamageTracker, IconInstance, IconRenderer, MsaaTarget, OffscreenTarget,
+    Quad, QuadRenderer, Redraw, msaa,
+};
 
 use crate::automation::AutomationDriver;
+use crate::editor::CaretBlink;
+use crate::keyboard::{self, KeyMap};
+use crate::ruler::{self, RULER_HEIGHT};
+use crate::sidebar::DocumentOutline;
+use crate::toolbar::{ButtonState, DropdownInput, ToolbarAction};
 use crate::workspace::Workspace;
 
 /// UI layout constants
 const TOOLBAR_HEIGHT: f32 = 48.0;
 const SIDEBAR_WIDTH: f32 = 250.0;
 const STATUS_BAR_HEIGHT: f32 = 24.0;
-const PAPER_WIDTH: f32 = 816.0; // US Letter width in pixels at 96 DPI
-const PAPER_HEIGHT: f32 = 1056.0; // US Letter height in pixels at 96 DPI
-const PAPER_MARGIN: f32 = 40.0;
+const CARET_WIDTH: f32 = 2.0;
+const CARET_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+const SELECTION_COLOR: [f32; 4] = [0.26, 0.52, 0.96, 0.3];
+const LINK_COLOR: [f32; 4] = [0.02, 0.39, 0.76, 1.0];
+const INSERTION_COLOR: [f32; 4] = [0.0, 0.5, 0.25, 1.0];
+const DELETION_COLOR: [f32; 4] = [0.78, 0.16, 0.16, 1.0];
+const RULER_MARKER_COLOR: [f32; 4] = [0.35, 0.35, 0.35, 1.0];
+/// Size of the indent markers on the ruler, in logical units.
+const RULER_MARKER_SIZE: f32 = 8.0;
+/// Time between frames while the document is scrolling smoothly.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+/// Time between looks for the answer to an open file dialog.
+const DIALOG_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Size icons are drawn at on toolbar buttons, in logical units.
+const ICON_SIZE: f32 = 20.0;
+/// Icons shown on the toolbar.
+const TOOLBAR_ICONS: [&str; 21] = [
+    "file-plus",
+    "folder-open",
+    "save",
+    "undo",
+    "redo",
+    "scissors",
+    "copy",
+    "clipboard-paste",
+    "bold",
+    "italic",
+    "underline",
+    "strikethrough",
+    "text-align-start",
+    "text-align-center",
+    "text-align-end",
+    "text-align-justify",
+    "list",
+    "list-ordered",
+    "image",
+    "table",
+    "link",
+];
 
 /// Run the Wolia Write application.
 pub fn run(enable_automation: bool) -> Result<()> {
     let event_loop = EventLoop::new()?;
-    event_loop.set_control_flow(ControlFlow::Poll);
+    // Redraws are requested only when something changed, so the loop can
+    // sleep between events unless an automation script is driving it.
+    event_loop.set_control_flow(if enable_automation {
+        ControlFlow::Poll
+    } else {
+        ControlFlow::Wait
+    });
 
     let mut app = WriteApp::new(enable_automation);
     event_loop.run_app(&mut app)?;
@@ -52,12 +107,27 @@ struct WriteApp {
     quad_renderer: Option<QuadRenderer>,
     /// Icon renderer for SVG icons.
     icon_renderer: Option<IconRenderer>,
-    /// Current window size.
+    /// Multisampled target resolved into the surface, for smooth edges.
+    msaa: Option<MsaaTarget>,
+    /// Current window size, in physical pixels.
     window_size: (u32, u32),
-    /// Current mouse position.
+    /// Physical pixels per logical unit on the window's display.
+    scale: ScaleFactor,
+    /// Current mouse position, in logical units.
     mouse_position: (f32, f32),
     /// Whether mouse button is pressed.
     mouse_pressed: bool,
+    /// Translation of key events for the editor.
+    keys: KeyMap,
+    /// Blinking of the text caret.
+    caret_blink: CaretBlink,
+    /// Whether the last frame drew the caret.
+    caret_shown: bool,
+    /// Regions of the window that changed since the last frame.
+    damage: DamageTracker,
+    /// The window's contents, kept between frames so that each need only
+    /// redraw its damage. `None` when the surface cannot be copied into.
+    back_buffer: Option<OffscreenTarget>,
     /// Automation driver for testing.
     automation: AutomationDriver,
 }
@@ -73,58 +143,217 @@ impl WriteApp {
             surface_config: None,
             quad_renderer: None,
             icon_renderer: None,
+            msaa: None,
             window_size: (1400, 900),
+            scale: ScaleFactor::ONE,
             mouse_position: (0.0, 0.0),
             mouse_pressed: false,
+            keys: KeyMap::new(),
+            caret_blink: CaretBlink::new(Instant::now()),
+            caret_shown: false,
+            damage: DamageTracker::new(Size::new(1400.0, 900.0)),
+            back_buffer: None,
             automation: AutomationDriver::new(enable_automation),
         }
     }
 
-    /// Handle mouse movement - update button hover states.
+    /// The window size in logical units, which the UI is laid out in.
+    fn logical_size(&self) -> (f32, f32) {
+        let size = self
+            .scale
+            .logical_size(self.window_size.0, self.window_size.1);
+        (size.width, size.height)
+    }
+
+    /// The document area between the toolbar, ruler, sidebar and status
+    /// bar.
+    fn document_area(&self) -> Rect {
+        let (w, h) = self.logical_size();
+        let sidebar_width = match &self.workspace {
+            Some(workspace) if workspace.sidebar.visible => workspace.sidebar.width,
+            _ => 0.0,
+        };
+        let ruler_height = self.ruler_area().map_or(0.0, |area| area.height);
+        Rect::new(
+            sidebar_width,
+            TOOLBAR_HEIGHT + ruler_height,
+            w - sidebar_width,
+            h - TOOLBAR_HEIGHT - ruler_height - STATUS_BAR_HEIGHT,
+        )
+    }
+
+    /// The strip above the document area the ruler is shown in, if shown.
+    fn ruler_area(&self) -> Option<Rect> {
+        let workspace = self.workspace.as_ref()?;
+        if !workspace.view.show_ruler {
+            return None;
+        }
+        let (w, _) = self.logical_size();
+        let sidebar_width = if workspace.sidebar.visible {
+            workspace.sidebar.width
+        } else {
+            0.0
+        };
+        Some(Rect::new(
+            sidebar_width,
+            TOOLBAR_HEIGHT,
+            w - sidebar_width,
+            RULER_HEIGHT,
+        ))
+    }
+
+    /// Fit the document view to the document area, which changes with
+    /// the window size and the sidebar.
+    fn sync_viewport(&mut self) {
+        let area = self.document_area();
+        if let Some(workspace) = &mut self.workspace {
+            workspace.view.set_viewport(area);
+        }
+    }
+
+    /// Handle mouse movement - update button hover states and extend a
+    /// selection being dragged out.
     fn handle_mouse_move(&mut self) {
         let (mx, my) = self.mouse_position;
+        self.sync_viewport();
         if let Some(workspace) = &mut self.workspace {
-            // Reset all buttons to Normal (unless they're Active)
-            for category in workspace.toolbar.buttons.values_mut() {
-                for button in category.iter_mut() {
-                    if button.state != crate::toolbar::ButtonState::Active {
-                        if button.contains_point(mx, my) {
-                            if button.state != crate::toolbar::ButtonState::Hovered {
-                                button.state = crate::toolbar::ButtonState::Hovered;
-                            }
-                        } else if button.state == crate::toolbar::ButtonState::Hovered {
-                            button.state = crate::toolbar::ButtonState::Normal;
-                        }
-                    }
+            for rect in workspace.toolbar.update_hover(mx, my) {
+                self.damage.add(rect);
+            }
+            if self.mouse_pressed && workspace.ruler_move(mx) {
+                self.damage.invalidate_all();
+            } else if self.mouse_pressed {
+                workspace.mouse_drag(Point::new(mx, my));
+                for rect in workspace.sync_toolbar() {
+                    self.damage.add(rect);
+                }
+                // The ruler shows the indents of the paragraph at the caret.
+                for rect in [Some(self.document_area()), self.ruler_area()]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.damage.add(rect);
                 }
             }
         }
     }
 
-    /// Handle mouse press - activate buttons.
+    /// Handle mouse press - perform the action of a toolbar button, or
+    /// place the caret and select in the document.
     fn handle_mouse_press(&mut self) {
         let (mx, my) = self.mouse_position;
+        let area = self.document_area();
+        self.sync_viewport();
         if let Some(workspace) = &mut self.workspace {
-            for category in workspace.toolbar.buttons.values_mut() {
-                for button in category.iter_mut() {
-                    if button.contains_point(mx, my) {
-                        // Toggle Active state for formatting buttons
-                        if button.state == crate::toolbar::ButtonState::Active {
-                            button.state = crate::toolbar::ButtonState::Normal;
-                        } else {
-                            button.state = crate::toolbar::ButtonState::Active;
-                        }
-                        tracing::info!("Button clicked: {} ({})", button.id, button.tooltip);
-                    }
+            // An expanded dropdown takes the click wherever it lands.
+            match workspace.toolbar.click_dropdowns(mx, my) {
+                DropdownInput::Ignored => {}
+                DropdownInput::Handled => {
+                    self.damage.invalidate_all();
+                    return;
+                }
+                DropdownInput::Chose(choice) => {
+                    workspace.apply_choice(choice);
+                    self.damage.invalidate_all();
+                    return;
                 }
             }
         }
+        if let Some(ruler) = self.ruler_area().filter(|r| r.contains(Point::new(mx, my))) {
+            if let Some(workspace) = &mut self.workspace
+                && workspace.ruler_down(mx, my - ruler.y)
+            {
+                self.damage.add(ruler);
+            }
+            return;
+        }
+        if area.contains(Point::new(mx, my)) {
+            if let Some(workspace) = &mut self.workspace {
+                let point = Point::new(mx, my);
+                // Ctrl-click, or Cmd-click on macOS, opens a link.
+                if self.keys.command_held() && workspace.open_link_at(point) {
+                    return;
+                }
+                let clicks = workspace.view.register_click(point, Instant::now());
+                workspace.mouse_down(point, clicks);
+                for rect in workspace.sync_toolbar() {
+                    self.damage.add(rect);
+                }
+                self.caret_blink.reset(Instant::now());
+                self.damage.add(area);
+            }
+            if let Some(ruler) = self.ruler_area() {
+                self.damage.add(ruler);
+            }
+            return;
+        }
+        if let Some(workspace) = &mut self.workspace {
+            let action = workspace
+                .toolbar
+                .button_at(mx, my)
+                .filter(|button| button.state != ButtonState::Disabled)
+                .and_then(|button| ToolbarAction::for_button(&button.id));
+            if let Some(action) = action {
+                workspace.handle_action(action);
+                self.damage.invalidate_all();
+            }
+        }
     }
 
     /// Handle mouse release.
     fn handle_mouse_release(&mut self) {
-        // For now, keep active state until clicked again
-        // This is toggle behavior for formatting buttons like Bold, Italic
+        if let Some(workspace) = &mut self.workspace {
+            if workspace.ruler_up() {
+                self.damage.invalidate_all();
+            }
+            workspace.mouse_up();
+        }
+    }
+
+    /// Configure the surface for a window of `width` by `height` physical
+    /// pixels.
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        self.window_size = (width, height);
+        let (w, h) = self.logical_size();
+        self.damage.resize(Size::new(w, h));
+        if let (Some(surface), Some(device), Some(config)) =
+            (&self.surface, &self.device, &mut self.surface_config)
+        {
+            config.width = width.max(1);
+            config.height = height.max(1);
+            surface.configure(device, config);
+            if let Some(msaa) = &mut self.msaa {
+                msaa.resize(device, config.width, config.height);
+            }
+            if let Some(back_buffer) = &mut self.back_buffer {
+                *back_buffer =
+                    OffscreenTarget::new(device, config.width, config.height, config.format);
+            }
+        }
+    }
+
+    /// Move to a display with another scale factor: draw at its pixel
+    /// size, rasterize icons for it, and lay the document out again.
+    fn set_scale(&mut self, scale: ScaleFactor) {
+        self.scale = scale;
+        if let Some(quad_renderer) = &mut self.quad_renderer {
+            quad_renderer.set_scale_factor(scale.get() as f32);
+        }
+        if let (Some(icon_renderer), Some(device), Some(queue)) =
+            (&mut self.icon_renderer, &self.device, &self.queue)
+        {
+            icon_renderer.set_scale_factor(scale.get() as f32);
+            icon_renderer.clear_icons();
+            load_toolbar_icons(icon_renderer, device, queue, scale);
+        }
+        if let Some(window) = &self.window {
+            let size = window.inner_size();
+            self.resize_surface(size.width, size.height);
+        }
+        if let Some(workspace) = &mut self.workspace {
+            workspace.layout = None;
+        }
+        self.damage.invalidate_all();
     }
 
     /// Clean up GPU resources in the correct order to prevent segfaults.
@@ -133,6 +362,8 @@ impl WriteApp {
         // Drop in correct order: renderers -> surface -> device -> window
         self.icon_renderer = None;
         self.quad_renderer = None;
+        self.msaa = None;
+        self.back_buffer = None;
         self.surface_config = None;
         self.surface = None;
         self.queue = None;
@@ -143,7 +374,7 @@ impl WriteApp {
     }
 
     fn build_ui(&self) -> Vec<Quad> {
-        let (w, h) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        let (w, h) = self.logical_size();
         let mut quads = Vec::new();
 
         // 1. Toolbar Background
@@ -166,7 +397,6 @@ impl WriteApp {
 
         // 2. Toolbar Buttons
         if let Some(workspace) = &self.workspace {
-            use crate::toolbar::ButtonState;
             for button in workspace.toolbar.all_buttons() {
                 // Determine color based on state
                 let color = match button.state {
@@ -186,11 +416,22 @@ impl WriteApp {
             }
         }
 
+        // Dropdowns, their lists drawn over the document separately
+        if let Some(workspace) = &self.workspace {
+            for menu in workspace.toolbar.dropdowns.values() {
+                let color = if menu.expanded {
+                    [0.80, 0.80, 0.90, 1.0]
+                } else {
+                    [0.92, 0.92, 0.92, 1.0]
+                };
+                quads.push(Quad::new(menu.x, menu.y, menu.width, menu.height, color));
+            }
+        }
+
         // 3. Sidebar
-        let mut sidebar_width = 0.0;
         if let Some(workspace) = &self.workspace {
             if workspace.sidebar.visible {
-                sidebar_width = workspace.sidebar.width;
+                let sidebar_width = workspace.sidebar.width;
                 let sidebar_height = h - TOOLBAR_HEIGHT - STATUS_BAR_HEIGHT;
 
                 // Background
@@ -219,28 +460,6 @@ impl WriteApp {
                     40.0,
                     [0.92, 0.92, 0.92, 1.0],
                 ));
-
-                // Render outline items as placeholders (since we can't render text yet)
-                let items = workspace.sidebar.outline.flatten();
-                for (item, item_y) in items {
-                    // Start below header (40px)
-                    let y_pos = TOOLBAR_HEIGHT + 40.0 + item_y;
-                    if y_pos > h - STATUS_BAR_HEIGHT {
-                        break; // Clip to bottom
-                    }
-
-                    // Highlight selected item (if we knew which one was selected, but flatten returns item ref)
-                    // For now just render simple line markers
-                    let indent = 16.0 + (item.level as f32) * 16.0;
-
-                    quads.push(Quad::new(
-                        indent,
-                        y_pos + 4.0,
-                        120.0, // Placeholder text width
-                        16.0,
-                        [0.8, 0.8, 0.8, 1.0],
-                    ));
-                }
             }
         }
 
@@ -267,54 +486,364 @@ impl WriteApp {
             }
         }
 
-        // 5. Document Area
-        let doc_x = sidebar_width;
-        let doc_y = TOOLBAR_HEIGHT;
-        let doc_w = w - sidebar_width;
-        let doc_h = h - TOOLBAR_HEIGHT - STATUS_BAR_HEIGHT;
+        // 5. Ruler
+        quads.extend(self.build_ruler());
+
+        // 6. Document Area
+        let doc = self.document_area();
         quads.push(Quad::new(
-            doc_x,
-            doc_y,
-            doc_w,
-            doc_h,
+            doc.x,
+            doc.y,
+            doc.width,
+            doc.height,
             [0.85, 0.85, 0.85, 1.0],
         ));
 
-        // Paper (centered in document area)
-        let paper_scale = 0.6; // Scale down for display
-        let paper_w = PAPER_WIDTH * paper_scale;
-        let paper_h = PAPER_HEIGHT * paper_scale;
-        let paper_x = doc_x + (doc_w - paper_w) / 2.0;
-        let paper_y = doc_y + PAPER_MARGIN;
+        quads
+    }
+
+    /// The ruler: the page with its margins shaded, ticks counted from the
+    /// left margin, and the indent markers of the paragraph at the caret.
+    fn build_ruler(&self) -> Vec<Quad> {
+        let (Some(workspace), Some(area)) = (&self.workspace, self.ruler_area()) else {
+            return Vec::new();
+        };
+        let markers = workspace.ruler_markers();
+        let (page_x, zoom) = (workspace.view.page_rect(0).x, workspace.view.zoom);
+        let x = |points: f32| ruler::to_pixels(points, page_x, zoom);
+        let left = area.x;
+        let right = area.x + area.width;
+        // A span of the ruler, cut to the ruler's area.
+        let span = |from: f32, to: f32, y: f32, height: f32, color: [f32; 4]| {
+            let (from, to) = (from.max(left), to.min(right));
+            (from < to).then(|| Quad::new(from, y, to - from, height, color))
+        };
+        let mut quads = Vec::new();
 
-        // Paper shadow
+        // Background and bottom border
         quads.push(Quad::new(
-            paper_x + 3.0,
-            paper_y + 3.0,
-            paper_w,
-            paper_h,
-            [0.0, 0.0, 0.0, 0.15],
+            area.x,
+            area.y,
+            area.width,
+            area.height,
+            [0.96, 0.96, 0.96, 1.0],
         ));
-
-        // Paper background
         quads.push(Quad::new(
-            paper_x,
-            paper_y,
-            paper_w,
-            paper_h,
+            area.x,
+            area.y + area.height - 1.0,
+            area.width,
+            1.0,
+            [0.85, 0.85, 0.85, 1.0],
+        ));
+
+        // The page, with its margins shaded
+        let (strip_y, strip_height) = (area.y + 4.0, area.height - 8.0);
+        let margin_color = [0.85, 0.85, 0.85, 1.0];
+        quads.extend(span(
+            x(0.0),
+            x(markers.page_width),
+            strip_y,
+            strip_height,
             [1.0, 1.0, 1.0, 1.0],
         ));
+        quads.extend(span(
+            x(0.0),
+            x(markers.left_margin),
+            strip_y,
+            strip_height,
+            margin_color,
+        ));
+        quads.extend(span(
+            x(markers.right_margin),
+            x(markers.page_width),
+            strip_y,
+            strip_height,
+            margin_color,
+        ));
+
+        // Ticks, longest at whole units
+        for tick in workspace.ruler.ticks(&markers) {
+            let length = if tick.label.is_some() {
+                10.0
+            } else if tick.half {
+                6.0
+            } else {
+                3.0
+            };
+            let tick_x = x(tick.position);
+            quads.extend(span(
+                tick_x,
+                tick_x + 1.0,
+                area.y + (area.height - length) / 2.0,
+                length,
+                [0.45, 0.45, 0.45, 1.0],
+            ));
+        }
+
+        // Markers: the first line indent hangs from the top, the others
+        // stand on the bottom.
+        let size = RULER_MARKER_SIZE;
+        let marker = |points: f32, y: f32| {
+            let center = x(points);
+            span(
+                center - size / 2.0,
+                center + size / 2.0,
+                y,
+                size / 2.0,
+                RULER_MARKER_COLOR,
+            )
+        };
+        quads.extend(marker(markers.first_line_indent, area.y));
+        quads.extend(marker(
+            markers.left_indent,
+            area.y + area.height - size / 2.0,
+        ));
+        quads.extend(marker(
+            markers.right_indent,
+            area.y + area.height - size / 2.0,
+        ));
 
         quads
     }
 
+    /// Pages, selection and caret, with the document area they are
+    /// clipped to as they scroll.
+    fn build_document(&self) -> Option<(Rect, Vec<Quad>)> {
+        let workspace = self.workspace.as_ref()?;
+        let area = self.document_area();
+        let mut quads = Vec::new();
+
+        // Pages, centered in the document area and scrolled with the view
+        for index in 0..workspace.view.page_count {
+            let page = workspace.view.page_rect(index);
+            if !page.intersects(&area) {
+                continue;
+            }
+
+            // Paper shadow
+            quads.push(Quad::new(
+                page.x + 3.0,
+                page.y + 3.0,
+                page.width,
+                page.height,
+                [0.0, 0.0, 0.0, 0.15],
+            ));
+
+            // Paper background
+            quads.push(Quad::new(
+                page.x,
+                page.y,
+                page.width,
+                page.height,
+                [1.0, 1.0, 1.0, 1.0],
+            ));
+        }
+
+        // Link underlines, suggested changes, selection and caret
+        for rect in workspace.link_underlines() {
+            quads.push(Quad::new(
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height.max(1.0),
+                LINK_COLOR,
+            ));
+        }
+        for (rect, kind) in workspace.revision_marks() {
+            let color = match kind {
+                RevisionKind::Insertion => INSERTION_COLOR,
+                RevisionKind::Deletion => DELETION_COLOR,
+            };
+            quads.push(Quad::new(
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height.max(1.0),
+                color,
+            ));
+        }
+        for rect in workspace.selection_rects() {
+            quads.push(Quad::new(
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                SELECTION_COLOR,
+            ));
+        }
+        if let Some(caret) = self.visible_caret() {
+            quads.push(Quad::new(
+                caret.x,
+                caret.y,
+                caret.width,
+                caret.height,
+                CARET_COLOR,
+            ));
+        }
+
+        Some((area, quads))
+    }
+
+    /// The caret as drawn now, or `None` while it is blinked off or
+    /// text is selected.
+    fn visible_caret(&self) -> Option<Rect> {
+        let workspace = self.workspace.as_ref()?;
+        if workspace
+            .editor
+            .selection
+            .is_some_and(|sel| !sel.is_empty())
+            || !self.caret_blink.is_visible(Instant::now())
+        {
+            return None;
+        }
+        let caret = workspace.caret_rect()?;
+        Some(Rect::new(caret.x, caret.y, CARET_WIDTH, caret.height))
+    }
+
+    /// Outline items in the sidebar, with the region they are clipped to.
+    fn build_outline(&self) -> Option<(Rect, Vec<Quad>)> {
+        let (_, h) = self.logical_size();
+        let workspace = self.workspace.as_ref()?;
+        if !workspace.sidebar.visible {
+            return None;
+        }
+
+        // Items scroll below the header (40px) and are clipped above the
+        // status bar.
+        let top = TOOLBAR_HEIGHT + 40.0;
+        let clip = Rect::new(
+            0.0,
+            top,
+            workspace.sidebar.width - 1.0,
+            h - STATUS_BAR_HEIGHT - top,
+        );
+
+        // Render outline items as placeholders (since we can't render text yet)
+        let quads = workspace
+            .sidebar
+            .outline
+            .flatten()
+            .into_iter()
+            .map(|(item, item_y)| {
+                let indent = 32.0 + DocumentOutline::indent_pixels(&item);
+                Quad::new(
+                    indent,
+                    top + item_y + 4.0,
+                    120.0, // Placeholder text width
+                    16.0,
+                    [0.8, 0.8, 0.8, 1.0],
+                )
+            })
+            .collect();
+        Some((clip, quads))
+    }
+
+    /// Upload the pixels of images in the layout that are not yet on the
+    /// GPU. They are drawn as icons, under their `src`.
+    fn load_images(&mut self) {
+        let (Some(icon_renderer), Some(device), Some(queue), Some(workspace)) = (
+            &mut self.icon_renderer,
+            &self.device,
+            &self.queue,
+            &self.workspace,
+        ) else {
+            return;
+        };
+        let Some(layout) = &workspace.layout else {
+            return;
+        };
+        for (src, _) in layout.images() {
+            if icon_renderer.has_icon(src) {
+                continue;
+            }
+            if let Some(image) = workspace.images.get(src) {
+                icon_renderer.load_image(
+                    device,
+                    queue,
+                    src,
+                    image.width,
+                    image.height,
+                    &image.pixels,
+                );
+            }
+        }
+    }
+
+    /// Images on the pages, with the document area they are clipped to.
+    fn build_images(&self) -> Option<(Rect, Vec<IconInstance<'_>>)> {
+        let workspace = self.workspace.as_ref()?;
+        let area = self.document_area();
+        let images = workspace
+            .layout
+            .as_ref()?
+            .images()
+            .into_iter()
+            .map(|(src, rect)| (src, workspace.view.rect_to_screen(rect)))
+            .filter(|(_, rect)| rect.intersects(&area))
+            .map(|(src, rect)| {
+                IconInstance::new(src, rect.x, rect.y, rect.width, [1.0; 4])
+                    .with_height(rect.height)
+            })
+            .collect();
+        Some((area, images))
+    }
+
+    /// The options of an expanded toolbar dropdown, drawn over everything
+    /// else.
+    fn build_dropdown_list(&self) -> Option<Vec<Quad>> {
+        let menu = self.workspace.as_ref()?.toolbar.expanded_dropdown()?;
+        let list = menu.list_bounds();
+        let mut quads = vec![
+            // Shadow and border
+            Quad::new(
+                list.x + 2.0,
+                list.y + 2.0,
+                list.width,
+                list.height,
+                [0.0, 0.0, 0.0, 0.15],
+            ),
+            Quad::new(
+                list.x - 1.0,
+                list.y - 1.0,
+                list.width + 2.0,
+                list.height + 2.0,
+                [0.75, 0.75, 0.75, 1.0],
+            ),
+        ];
+        for index in 0..menu.options.len() {
+            let option = menu.option_bounds(index);
+            let color = if menu.highlighted == Some(index) {
+                [0.88, 0.88, 0.95, 1.0]
+            } else if menu.selected_index == index {
+                [0.94, 0.94, 0.98, 1.0]
+            } else {
+                [1.0, 1.0, 1.0, 1.0]
+            };
+            quads.push(Quad::new(
+                option.x,
+                option.y,
+                option.width,
+                option.height,
+                color,
+            ));
+        }
+        Some(quads)
+    }
+
     fn render(&mut self) {
+        self.sync_viewport();
+        if let Some(workspace) = &mut self.workspace {
+            workspace.ensure_layout();
+        }
+        self.load_images();
+        self.caret_shown = self.visible_caret().is_some();
         let Some(surface) = &self.surface else { return };
         let Some(device) = &self.device else { return };
         let Some(queue) = &self.queue else { return };
         let Some(quad_renderer) = &self.quad_renderer else {
             return;
         };
+        let Some(msaa) = &self.msaa else { return };
 
         let frame = match surface.get_current_texture() {
             Ok(frame) => frame,
@@ -333,35 +862,105 @@ impl WriteApp {
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        // Only the damage is redrawn when the last frame was kept. The
+        // multisampled texture, when there is one, is kept as well, and
+        // resolved whole into the back buffer.
+        let (target, redraw) = match &self.back_buffer {
+            Some(back_buffer) => (msaa.target(&back_buffer.view), self.damage.take_redraw()),
+            None => {
+                self.damage.take();
+                (msaa.target(&view), Redraw::full())
+            }
+        };
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
-        // Build and render UI quads
-        let quads = self.build_ui();
-        let (w, h) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        // Build and render UI quads, over a background painted rather than
+        // cleared, as clears are not clipped.
+        let (w, h) = self.logical_size();
+        let mut quads = vec![Quad::new(0.0, 0.0, w, h, [0.9, 0.9, 0.9, 1.0])];
+        quads.extend(self.build_ui());
 
         quad_renderer.render(
             &mut encoder,
-            &view,
+            target,
             queue,
             &quads,
             w,
             h,
-            Some(wgpu::Color {
-                r: 0.9,
-                g: 0.9,
-                b: 0.9,
-                a: 1.0,
-            }),
+            None,
+            redraw.clip(None),
         );
 
+        // The document, clipped to its area so scrolled pages stay below
+        // the toolbar.
+        if let Some((clip, document)) = self.build_document() {
+            quad_renderer.render_instanced(
+                device,
+                &mut encoder,
+                target,
+                &document,
+                w,
+                h,
+                None,
+                redraw.clip(Some(clip)),
+            );
+        }
+
+        // Images, over their pages but clipped like them.
+        if let (Some(icon_renderer), Some((clip, images))) =
+            (&self.icon_renderer, self.build_images())
+        {
+            icon_renderer.render_icons_clipped(
+                device,
+                &mut encoder,
+                target,
+                &images,
+                w,
+                h,
+                redraw.clip(Some(clip)),
+            );
+        }
+
+        // Outline items, clipped to the sidebar. They get their own
+        // instance buffer, as the quads above still use the shared one.
+        if let Some((clip, outline)) = self.build_outline() {
+            quad_renderer.render_instanced(
+                device,
+                &mut encoder,
+                target,
+                &outline,
+                w,
+                h,
+                None,
+                redraw.clip(Some(clip)),
+            );
+        }
+
+        if let Some(list) = self.build_dropdown_list() {
+            quad_renderer.render_instanced(
+                device,
+                &mut encoder,
+                target,
+                &list,
+                w,
+                h,
+                None,
+                redraw.clip(None),
+            );
+        }
+
         // Render icons on toolbar buttons
         if let (Some(icon_renderer), Some(workspace)) = (&self.icon_renderer, &self.workspace) {
-            use crate::toolbar::ButtonState;
-
-            for button in workspace.toolbar.all_buttons() {
-                if icon_renderer.has_icon(&button.icon) {
+            // Center icons in buttons
+            let icon_size = ICON_SIZE;
+            let icons: Vec<IconInstance<'_>> = workspace
+                .toolbar
+                .all_buttons()
+                .into_iter()
+                .filter(|button| icon_renderer.has_icon(&button.icon))
+                .map(|button| {
                     // Choose tint based on button state
                     let tint = match button.state {
                         ButtonState::Normal => [0.3, 0.3, 0.3, 1.0],
@@ -369,28 +968,29 @@ impl WriteApp {
                         ButtonState::Active => [0.0, 0.0, 0.6, 1.0],
                         ButtonState::Disabled => [0.6, 0.6, 0.6, 0.5],
                     };
-
-                    // Center icon in button (icon size = 20, button size = 32)
-                    let icon_size = 20.0;
-                    let icon_x = button.x + (button.width - icon_size) / 2.0;
-                    let icon_y = button.y + (button.height - icon_size) / 2.0;
-
-                    icon_renderer.render_icon(
-                        &mut encoder,
-                        &view,
-                        queue,
+                    IconInstance::new(
                         &button.icon,
-                        icon_x,
-                        icon_y,
+                        button.x + (button.width - icon_size) / 2.0,
+                        button.y + (button.height - icon_size) / 2.0,
                         icon_size,
-                        w,
-                        h,
                         tint,
-                    );
-                }
-            }
+                    )
+                })
+                .collect();
+            icon_renderer.render_icons_clipped(
+                device,
+                &mut encoder,
+                target,
+                &icons,
+                w,
+                h,
+                redraw.clip(None),
+            );
         }
 
+        if let Some(back_buffer) = &self.back_buffer {
+            back_buffer.copy_to(&mut encoder, &frame.texture);
+        }
         queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
@@ -401,6 +1001,7 @@ impl ApplicationHandler for WriteApp {
         if self.window.is_none() {
             let config = WindowConfig::new("Wolia Write")
                 .with_size(1400.0, 900.0)
+                .with_min_size(800.0, 500.0)
                 .with_icon("wolia.png");
             let attrs = config.to_window_attributes();
 
@@ -408,6 +1009,8 @@ impl ApplicationHandler for WriteApp {
                 Ok(window) => {
                     tracing::info!("Window created");
                     let window = Arc::new(window);
+                    // Receive composed text from input methods.
+                    window.set_ime_allowed(true);
 
                     // Initialize wgpu
                     let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
@@ -469,12 +1072,22 @@ impl ApplicationHandler for WriteApp {
 
                     let size = window.inner_size();
                     self.window_size = (size.width, size.height);
+                    self.scale = ScaleFactor::new(window.scale_factor());
+                    let (w, h) = self.logical_size();
+                    self.damage.resize(Size::new(w, h));
 
                     let surface_caps = surface.get_capabilities(&adapter);
                     let format = surface_caps.formats[0];
+                    // Frames are kept to redraw only their damage when
+                    // they can be copied to the surface.
+                    let keep_frames = surface_caps.usages.contains(wgpu::TextureUsages::COPY_DST);
 
                     let surface_config = wgpu::SurfaceConfiguration {
-                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                        usage: if keep_frames {
+                            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST
+                        } else {
+                            wgpu::TextureUsages::RENDER_ATTACHMENT
+                        },
                         format,
                         width: size.width.max(1),
                         height: size.height.max(1),
@@ -485,54 +1098,59 @@ impl ApplicationHandler for WriteApp {
                     };
                     surface.configure(&device, &surface_config);
 
+                    // Draw with MSAA where the adapter supports it
+                    let sample_count = msaa::supported_sample_count(
+                        &adapter,
+                        &device,
+                        format,
+                        DEFAULT_SAMPLE_COUNT,
+                    );
+                    tracing::info!("Rendering with {}x MSAA", sample_count);
+                    let msaa = MsaaTarget::new(
+                        &device,
+                        format,
+                        surface_config.width,
+                        surface_config.height,
+                        sample_count,
+                    );
+                    let back_buffer = keep_frames.then(|| {
+                        OffscreenTarget::new(
+                            &device,
+                            surface_config.width,
+                            surface_config.height,
+                            format,
+                        )
+                    });
+
                     // Create quad renderer
-                    let quad_renderer = QuadRenderer::new(&device, format);
+                    let mut quad_renderer =
+                        QuadRenderer::new_multisampled(&device, format, sample_count);
+                    quad_renderer.set_scale_factor(self.scale.get() as f32);
 
                     // Create icon renderer and load toolbar icons
-                    let mut icon_renderer = IconRenderer::new(&device, format);
-                    let icon_manager = IconManager::new();
-
-                    // Load icons for all toolbar buttons
-                    let toolbar_icons = [
-                        "file-plus",
-                        "folder-open",
-                        "save",
-                        "undo",
-                        "redo",
-                        "scissors",
-                        "copy",
-                        "clipboard-paste",
-                        "bold",
-                        "italic",
-                        "underline",
-                        "strikethrough",
-                        "text-align-start",
-                        "text-align-center",
-                        "text-align-end",
-                        "text-align-justify",
-                        "list",
-                        "list-ordered",
-                        "image",
-                        "table",
-                        "link",
-                    ];
-
-                    let mut loaded_count = 0;
-                    for icon_name in &toolbar_icons {
-                        if let Some(svg_data) = icon_manager.get(icon_name) {
-                            if icon_renderer.load_icon(&device, &queue, icon_name, &svg_data, 24) {
-                                loaded_count += 1;
-                            }
+                    let mut icon_renderer =
+                        IconRenderer::new_multisampled(&device, format, sample_count);
+                    icon_renderer.set_scale_factor(self.scale.get() as f32);
+
+                    load_toolbar_icons(&mut icon_renderer, &device, &queue, self.scale);
+
+                    // Offer to reopen the unsaved work of sessions that
+                    // ended without saving, or start with an empty document.
+                    let recovery = recovery_dir();
+                    let mut workspace = if self.automation.enabled {
+                        Workspace::new(Document::new())
+                    } else {
+                        let workspace =
+                            Workspace::recover(&recovery, |file| dialog::ask_restore(&file.title));
+                        if workspace.is_some() {
+                            tracing::info!("Recovered unsaved changes");
                         }
-                    }
-                    tracing::info!(
-                        "Loaded {}/{} toolbar icons",
-                        loaded_count,
-                        toolbar_icons.len()
-                    );
-
-                    // Create a new workspace with an empty document
-                    let workspace = Workspace::new(Document::new());
+                        let mut workspace =
+                            workspace.unwrap_or_else(|| Workspace::new(Document::new()));
+                        workspace.enable_autosave(recovery, autosave::DEFAULT_INTERVAL);
+                        workspace
+                    };
+                    workspace.view.smooth_scrolling = true;
                     tracing::info!("Workspace initialized");
                     tracing::info!(
                         "UI: Toolbar mounted ({} buttons)",
@@ -548,7 +1166,10 @@ impl ApplicationHandler for WriteApp {
                     self.surface_config = Some(surface_config);
                     self.quad_renderer = Some(quad_renderer);
                     self.icon_renderer = Some(icon_renderer);
+                    self.msaa = Some(msaa);
+                    self.back_buffer = back_buffer;
                     self.window = Some(window);
+                    self.damage.invalidate_all();
 
                     if self.automation.enabled {
                         self.automation.load_scenario("smoke_test");
@@ -564,26 +1185,39 @@ impl ApplicationHandler for WriteApp {
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
+                if self
+                    .workspace
+                    .as_mut()
+                    .is_some_and(|workspace| !workspace.confirm_close())
+                {
+                    return;
+                }
                 tracing::info!("Close requested, exiting");
                 self.cleanup();
                 event_loop.exit();
             }
             WindowEvent::Resized(size) => {
                 tracing::debug!("Window resized to {:?}", size);
-                self.window_size = (size.width, size.height);
-                if let (Some(surface), Some(device), Some(config)) =
-                    (&self.surface, &self.device, &mut self.surface_config)
-                {
-                    config.width = size.width.max(1);
-                    config.height = size.height.max(1);
-                    surface.configure(device, config);
+                // Resizing the surface damages the whole window, which is
+                // redrawn at once: some platforms send no other events
+                // while a resize is dragged.
+                self.resize_surface(size.width, size.height);
+                if let Some(window) = &self.window {
+                    window.request_redraw();
                 }
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                tracing::debug!("Scale factor changed to {}", scale_factor);
+                self.set_scale(ScaleFactor::new(scale_factor));
+            }
             WindowEvent::RedrawRequested => {
                 self.render();
             }
             WindowEvent::CursorMoved { position, .. } => {
-                self.mouse_position = (position.x as f32, position.y as f32);
+                let position = self
+                    .scale
+                    .point_to_logical(Point::new(position.x as f32, position.y as f32));
+                self.mouse_position = (position.x, position.y);
                 self.handle_mouse_move();
             }
             WindowEvent::MouseInput { state, button, .. } => {
@@ -601,8 +1235,51 @@ impl ApplicationHandler for WriteApp {
                     }
                 }
             }
-            WindowEvent::KeyboardInput { .. } => {
-                // TODO: Handle keyboard input
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.sync_viewport();
+                if let Some(workspace) = &mut self.workspace {
+                    workspace.scroll_wheel(delta, Instant::now());
+                    self.damage.invalidate_all();
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.keys.set_modifiers(modifiers.state());
+            }
+            // Synthetic presses report keys already held when the window
+            // gained focus, which should not be typed.
+            WindowEvent::KeyboardInput {
+                event,
+                is_synthetic: false,
+                ..
+            } => {
+                let Some(workspace) = &mut self.workspace else {
+                    return;
+                };
+                let events = self.keys.translate(
+                    &event.logical_key,
+                    event.text.as_deref(),
+                    event.state.is_pressed(),
+                );
+                for event in events {
+                    match workspace.toolbar.dropdown_key(&event) {
+                        DropdownInput::Ignored => match workspace.shortcuts.action(&event) {
+                            Some(action) => workspace.handle_action(action),
+                            None => workspace.handle_key(event),
+                        },
+                        DropdownInput::Handled => {}
+                        DropdownInput::Chose(choice) => workspace.apply_choice(choice),
+                    }
+                }
+                if event.state.is_pressed() {
+                    self.caret_blink.reset(Instant::now());
+                    self.damage.invalidate_all();
+                }
+            }
+            WindowEvent::Ime(ime) => {
+                if let Some(workspace) = &mut self.workspace {
+                    workspace.handle_ime(keyboard::translate_ime(&ime));
+                    self.damage.invalidate_all();
+                }
             }
             _ => {}
         }
@@ -615,8 +1292,132 @@ impl ApplicationHandler for WriteApp {
             event_loop.exit();
         }
 
-        if let Some(window) = &self.window {
-            window.request_redraw();
+        // Smooth scrolling moves the document on every frame until it
+        // reaches its target.
+        let now = Instant::now();
+        if let Some(workspace) = &mut self.workspace {
+            if workspace.animate_scroll(now) {
+                self.damage.invalidate_all();
+            }
+            workspace.autosave_if_due(now);
+            if workspace.poll_dialog() {
+                self.damage.invalidate_all();
+            }
+            // Statistics are recounted a little after typing.
+            if workspace.refresh_statistics(now) {
+                let (w, h) = self.logical_size();
+                self.damage
+                    .add(Rect::new(0.0, h - STATUS_BAR_HEIGHT, w, STATUS_BAR_HEIGHT));
+            }
+        }
+        // A document saved on the way to closing lets the window close.
+        if self
+            .workspace
+            .as_mut()
+            .is_some_and(|w| w.take_close_ready())
+        {
+            tracing::info!("Document saved, exiting");
+            self.cleanup();
+            event_loop.exit();
+            return;
+        }
+
+        // Wake to recount statistics, and to look for the answer to a file
+        // dialog while one is open.
+        let dialog = self
+            .workspace
+            .as_ref()
+            .is_some_and(|w| w.has_open_dialog())
+            .then(|| now + DIALOG_POLL_INTERVAL);
+        let due = self
+            .workspace
+            .as_ref()
+            .and_then(|w| w.statusbar.statistics_due())
+            .into_iter()
+            .chain(dialog)
+            .min();
+        let scrolling = self
+            .workspace
+            .as_ref()
+            .is_some_and(|w| w.view.is_scrolling());
+
+        // Redraw the caret when it blinks, and sleep until it next does.
+        let caret = self.workspace.as_ref().and_then(|w| w.caret_rect());
+        match caret {
+            Some(caret) => {
+                if self.visible_caret().is_some() != self.caret_shown {
+                    self.damage
+                        .add(Rect::new(caret.x, caret.y, CARET_WIDTH, caret.height));
+                }
+                if !self.automation.enabled {
+                    let wake = if scrolling {
+                        now + FRAME_INTERVAL
+                    } else {
+                        self.caret_blink.next_change(now)
+                    };
+                    let wake = due.map_or(wake, |due| due.min(wake));
+                    event_loop.set_control_flow(ControlFlow::WaitUntil(wake));
+                }
+            }
+            None if self.automation.enabled => {}
+            None if scrolling => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(now + FRAME_INTERVAL))
+            }
+            None => event_loop.set_control_flow(match due {
+                Some(due) => ControlFlow::WaitUntil(due),
+                None => ControlFlow::Wait,
+            }),
+        }
+
+        if self.damage.is_dirty() {
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
+        }
+    }
+}
+
+/// Directory recovery copies of unsaved documents are kept in: the
+/// platform's per-user application data, or the temporary directory if it
+/// cannot be found.
+fn recovery_dir() -> std::path::PathBuf {
+    let home = std::env::var_os("HOME").map(std::path::PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("LOCALAPPDATA").map(std::path::PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_STATE_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".local/state")))
+    };
+    base.unwrap_or_else(std::env::temp_dir)
+        .join("wolia-write")
+        .join("recovery")
+}
+
+/// Rasterize the toolbar icons at the pixel size they are drawn at on a
+/// display with `scale`, so they stay crisp.
+fn load_toolbar_icons(
+    icon_renderer: &mut IconRenderer,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scale: ScaleFactor,
+) {
+    let icon_manager = IconManager::new();
+    let size = scale.to_physical(ICON_SIZE).ceil() as u32;
+    let mut loaded_count = 0;
+    for icon_name in TOOLBAR_ICONS {
+        if let Some(svg_data) = icon_manager.get(icon_name) {
+            if icon_renderer.load_icon(device, queue, icon_name, &svg_data, size) {
+                loaded_count += 1;
+            }
         }
     }
+    tracing::info!(
+        "Loaded {}/{} toolbar icons at {}px",
+        loaded_count,
+        TOOLBAR_ICONS.len(),
+        size
+    );
 }
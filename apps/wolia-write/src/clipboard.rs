@@ -0,0 +1,83 @@
+//! The system clipboard as the editor's clipboard.
+//!
+//! Copying puts every representation of a fragment on the system clipboard.
+//! Other applications see its text; pasting back into Wolia gets the native
+//! fragment for as long as nothing else has been copied since.
+
+use std::cell::RefCell;
+
+use wolia_edit::clipboard::{ClipboardContent, MIME_NATIVE, MIME_TEXT};
+use wolia_edit::{Clipboard, ClipboardData, Error, Fragment};
+use wolia_platform::clipboard::Clipboard as PlatformClipboard;
+
+/// The system clipboard, used through the edit layer's [`Clipboard`] trait.
+#[derive(Debug)]
+pub struct SystemClipboard {
+    inner: RefCell<PlatformClipboard>,
+}
+
+impl SystemClipboard {
+    /// Open the system clipboard.
+    pub fn new() -> Self {
+        Self {
+            inner: RefCell::new(PlatformClipboard::new()),
+        }
+    }
+
+    /// Paste from the richest representation on the clipboard.
+    pub fn paste_fragment(&self) -> Option<Fragment> {
+        let available = self.inner.borrow_mut().available_mimes();
+        let available: Vec<&str> = available.iter().map(String::as_str).collect();
+        self.paste(&available)
+    }
+}
+
+impl Default for SystemClipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn get_text(&self) -> Option<String> {
+        self.inner.borrow_mut().get_text()
+    }
+
+    fn set_text(&self, text: &str) -> wolia_edit::Result<()> {
+        self.inner
+            .borrow_mut()
+            .set_text(text)
+            .map_err(|e| Error::Clipboard(e.to_string()))
+    }
+
+    fn get_content(&self) -> Option<ClipboardContent> {
+        self.get_data(MIME_NATIVE)
+            .map(ClipboardContent::Native)
+            .or_else(|| self.get_text().map(ClipboardContent::Text))
+    }
+
+    fn set_content(&self, content: ClipboardContent) -> wolia_edit::Result<()> {
+        let mut data = ClipboardData::new();
+        match content {
+            ClipboardContent::Text(text) => data.insert(MIME_TEXT, text.into_bytes()),
+            ClipboardContent::RichText(html) => data.insert("text/html", html.into_bytes()),
+            ClipboardContent::Native(native) => data.insert(MIME_NATIVE, native),
+        }
+        self.set_data(data)
+    }
+
+    fn set_data(&self, data: ClipboardData) -> wolia_edit::Result<()> {
+        let representations: Vec<(&str, &[u8])> = data
+            .mimes()
+            .filter_map(|mime| Some((mime, data.get(mime)?)))
+            .collect();
+        self.inner
+            .borrow_mut()
+            .set_data(&representations)
+            .map_err(|e| Error::Clipboard(e.to_string()))
+    }
+
+    fn get_data(&self, mime: &str) -> Option<Vec<u8>> {
+        self.inner.borrow_mut().get_data(mime)
+    }
+}
@@ -1,22 +1,105 @@
 //! Text editor component.
 
+use std::time::{Duration, Instant};
+use winit::event::MouseScrollDelta;
 use wolia_core::text::Text;
 use wolia_edit::{Cursor, Selection};
 use wolia_layout::ParagraphLayout;
-use wolia_math::Rect;
+
+use wolia_math::{Point, Rect, Size};
+
+/// Longest time between clicks counted as one double or triple click.
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(500);
+
+/// Farthest distance, in pixels, between clicks counted as one double or
+/// triple click.
+const MULTI_CLICK_DISTANCE: f32 = 4.0;
+
+/// Space, in screen pixels, above the first page and below the last.
+pub const PAGE_MARGIN: f32 = 40.0;
+
+/// Screen pixels scrolled for each line a mouse wheel reports.
+const PIXELS_PER_LINE: f32 = 40.0;
+
+/// Time for smooth scrolling to cover about two thirds of the distance
+/// left to its target.
+const SMOOTH_SCROLL_TIME: Duration = Duration::from_millis(60);
+
+/// Time the caret stays shown or hidden while blinking.
+const BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+/// The blinking of the caret, which stays shown for a moment after each
+/// input so it can be seen while typing.
+#[derive(Debug, Clone, Copy)]
+pub struct CaretBlink {
+    /// When the caret was last shown by an input.
+    since: Instant,
+}
+
+impl CaretBlink {
+    /// Start blinking at `now`, with the caret shown.
+    pub fn new(now: Instant) -> Self {
+        Self { since: now }
+    }
+
+    /// Show the caret again after an input at `now`.
+    pub fn reset(&mut self, now: Instant) {
+        self.since = now;
+    }
+
+    /// Whether the caret is shown at `now`.
+    pub fn is_visible(&self, now: Instant) -> bool {
+        let phase =
+            now.saturating_duration_since(self.since).as_millis() / BLINK_INTERVAL.as_millis();
+        phase % 2 == 0
+    }
+
+    /// When the caret next turns on or off after `now`.
+    pub fn next_change(&self, now: Instant) -> Instant {
+        let elapsed = now.saturating_duration_since(self.since).as_millis();
+        let interval = BLINK_INTERVAL.as_millis();
+        let phases = (elapsed / interval + 1) as u32;
+        self.since + BLINK_INTERVAL * phases
+    }
+}
+
+/// The screen pixels a mouse wheel movement scrolls down by, negative
+/// when scrolling up.
+pub fn wheel_pixels(delta: MouseScrollDelta) -> f32 {
+    // Wheels report the direction the content moves, the opposite of the
+    // direction the view scrolls.
+    match delta {
+        MouseScrollDelta::LineDelta(_, lines) => -lines * PIXELS_PER_LINE,
+        MouseScrollDelta::PixelDelta(position) => -position.y as f32,
+    }
+}
 
 /// The document editor view.
+///
+/// Pages are stacked top to bottom, centered horizontally in the viewport
+/// with a margin above the first page and below the last. The view scrolls
+/// between the top of that margin and the bottom of the last one.
 pub struct Editor {
-    /// Viewport rectangle.
+    /// Screen rectangle the document is shown in.
     pub viewport: Rect,
-    /// Scroll offset.
+    /// Scroll offset in screen pixels.
     pub scroll_y: f32,
     /// Zoom level (1.0 = 100%).
     pub zoom: f32,
+    /// Size of a page in document points.
+    pub page_size: Size,
+    /// Number of pages in the document.
+    pub page_count: usize,
+    /// Ease wheel scrolling by lines over a few frames instead of jumping.
+    pub smooth_scrolling: bool,
     /// Show page boundaries.
     pub show_pages: bool,
     /// Show ruler.
     pub show_ruler: bool,
+    /// The last click and the number of clicks in the run it ended.
+    last_click: Option<(Instant, Point, u32)>,
+    /// Where smooth scrolling is heading, and when it last moved.
+    scroll_target: Option<(f32, Instant)>,
 }
 
 impl Editor {
@@ -26,24 +109,97 @@ impl Editor {
             viewport: Rect::ZERO,
             scroll_y: 0.0,
             zoom: 1.0,
+            page_size: Size::new(595.0, 842.0),
+            page_count: 1,
+            smooth_scrolling: false,
             show_pages: true,
             show_ruler: true,
+            last_click: None,
+            scroll_target: None,
         }
     }
 
     /// Set the viewport size.
     pub fn set_viewport(&mut self, viewport: Rect) {
         self.viewport = viewport;
+        self.clamp_scroll();
+    }
+
+    /// Set the size and number of the pages shown, as laid out.
+    pub fn set_pages(&mut self, page_size: Size, page_count: usize) {
+        self.page_size = page_size;
+        self.page_count = page_count.max(1);
+        self.clamp_scroll();
+    }
+
+    /// Height of the pages and the margins around them, in screen pixels.
+    pub fn content_height(&self) -> f32 {
+        self.page_count as f32 * self.page_size.height * self.zoom + 2.0 * PAGE_MARGIN
     }
 
-    /// Scroll by a delta.
+    /// The farthest the view scrolls, which shows the bottom margin at the
+    /// bottom of the viewport. Content shorter than the viewport does not
+    /// scroll.
+    pub fn max_scroll(&self) -> f32 {
+        (self.content_height() - self.viewport.height).max(0.0)
+    }
+
+    /// Scroll by a delta at once, stopping any smooth scrolling.
     pub fn scroll(&mut self, delta: f32) {
-        self.scroll_y = (self.scroll_y + delta).max(0.0);
+        self.scroll_to(self.scroll_y + delta);
+    }
+
+    /// Scroll to an offset at once, stopping any smooth scrolling.
+    pub fn scroll_to(&mut self, scroll_y: f32) {
+        self.scroll_target = None;
+        self.scroll_y = scroll_y.clamp(0.0, self.max_scroll());
+    }
+
+    /// Scroll for a mouse wheel movement at `now`. Wheels scrolling by
+    /// lines are eased when smooth scrolling is on; pixel deltas come from
+    /// touchpads, which already send them smoothly.
+    pub fn scroll_wheel(&mut self, delta: MouseScrollDelta, now: Instant) {
+        let pixels = wheel_pixels(delta);
+        if !self.smooth_scrolling || matches!(delta, MouseScrollDelta::PixelDelta(_)) {
+            self.scroll(pixels);
+            return;
+        }
+        // Further notches add to the distance left rather than restarting.
+        let (target, since) = self.scroll_target.unwrap_or((self.scroll_y, now));
+        let target = (target + pixels).clamp(0.0, self.max_scroll());
+        self.scroll_target = Some((target, since));
+    }
+
+    /// Whether smooth scrolling has further to go.
+    pub fn is_scrolling(&self) -> bool {
+        self.scroll_target.is_some()
+    }
+
+    /// Move smooth scrolling on to `now`, returning whether the view
+    /// scrolled.
+    pub fn animate(&mut self, now: Instant) -> bool {
+        let Some((target, since)) = self.scroll_target else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(since).as_secs_f32();
+        let progress = 1.0 - (-elapsed / SMOOTH_SCROLL_TIME.as_secs_f32()).exp();
+        let scroll_y = self.scroll_y + (target - self.scroll_y) * progress;
+        if (target - scroll_y).abs() < 0.5 {
+            self.scroll_y = target;
+            self.scroll_target = None;
+        } else {
+            self.scroll_y = scroll_y;
+            self.scroll_target = Some((target, now));
+        }
+        true
     }
 
-    /// Set zoom level.
+    /// Set zoom level, keeping the document position at the top of the
+    /// viewport in place.
     pub fn set_zoom(&mut self, zoom: f32) {
+        let top = (self.scroll_y - PAGE_MARGIN) / self.zoom;
         self.zoom = zoom.clamp(0.25, 4.0);
+        self.scroll_to(top * self.zoom + PAGE_MARGIN);
     }
 
     /// Zoom in.
@@ -58,7 +214,87 @@ impl Editor {
 
     /// Reset zoom to 100%.
     pub fn reset_zoom(&mut self) {
-        self.zoom = 1.0;
+        self.set_zoom(1.0);
+    }
+
+    /// The screen position of the top left of the first page when not
+    /// scrolled. Pages narrower than the viewport are centered in it.
+    fn origin(&self) -> Point {
+        let spare = self.viewport.width - self.page_size.width * self.zoom;
+        Point::new(
+            self.viewport.x + (spare / 2.0).max(0.0),
+            self.viewport.y + PAGE_MARGIN,
+        )
+    }
+
+    /// The screen rectangle of the page at `index`.
+    pub fn page_rect(&self, index: usize) -> Rect {
+        let top = Point::new(0.0, index as f32 * self.page_size.height);
+        self.rect_to_screen(Rect::new(
+            top.x,
+            top.y,
+            self.page_size.width,
+            self.page_size.height,
+        ))
+    }
+
+    /// The page in the middle of the viewport, counting from 1.
+    pub fn current_page(&self) -> usize {
+        let middle = self.viewport.y + self.viewport.height / 2.0;
+        let y = self.to_document(Point::new(self.viewport.x, middle)).y;
+        let index = (y / self.page_size.height).floor().max(0.0) as usize;
+        index.min(self.page_count - 1) + 1
+    }
+
+    /// The document position shown at screen position `point`, undoing
+    /// the scroll and then the zoom.
+    pub fn to_document(&self, point: Point) -> Point {
+        let scrolled = point - self.origin() + Point::new(0.0, self.scroll_y);
+        scrolled / self.zoom
+    }
+
+    /// The screen position showing document position `point`, applying
+    /// the zoom and then the scroll.
+    pub fn to_screen(&self, point: Point) -> Point {
+        point * self.zoom + self.origin() - Point::new(0.0, self.scroll_y)
+    }
+
+    /// The screen rectangle showing a rectangle of the document.
+    pub fn rect_to_screen(&self, rect: Rect) -> Rect {
+        let origin = self.to_screen(Point::new(rect.x, rect.y));
+        Rect::new(
+            origin.x,
+            origin.y,
+            rect.width * self.zoom,
+            rect.height * self.zoom,
+        )
+    }
+
+    /// Record a click at `point`, returning how many clicks it makes in a
+    /// row: 1 for a single click, 2 for a double click and 3 for a triple
+    /// click, after which counting starts again.
+    pub fn register_click(&mut self, point: Point, now: Instant) -> u32 {
+        let count = match self.last_click {
+            Some((time, last, count))
+                if count < 3
+                    && now.duration_since(time) <= MULTI_CLICK_TIME
+                    && last.distance(point) <= MULTI_CLICK_DISTANCE =>
+            {
+                count + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, point, count));
+        count
+    }
+
+    /// Keep the scroll offset within the content after it changed size.
+    fn clamp_scroll(&mut self) {
+        let max = self.max_scroll();
+        self.scroll_y = self.scroll_y.clamp(0.0, max);
+        if let Some((target, _)) = &mut self.scroll_target {
+            *target = target.clamp(0.0, max);
+        }
     }
 }
 
@@ -67,3 +303,136 @@ impl Default for Editor {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use winit::dpi::PhysicalPosition;
+
+    /// A view of three 500 by 1000 point pages in an 800 by 600 viewport.
+    fn view() -> Editor {
+        let mut view = Editor::new();
+        view.set_viewport(Rect::new(250.0, 88.0, 800.0, 600.0));
+        view.set_pages(Size::new(500.0, 1000.0), 3);
+        view
+    }
+
+    #[test]
+    fn test_to_document_applies_scroll_then_zoom() {
+        let mut view = view();
+        // The page is centered with 150px either side, below the margin.
+        assert_eq!(
+            view.to_document(Point::new(400.0, 128.0)),
+            Point::new(0.0, 0.0)
+        );
+
+        view.set_zoom(2.0);
+        view.scroll_to(140.0);
+        // The wider page now fills the viewport from its left edge, and
+        // 100 screen pixels of scroll past the margin are 50 points.
+        assert_eq!(
+            view.to_document(Point::new(270.0, 88.0)),
+            Point::new(10.0, 50.0)
+        );
+        let point = Point::new(123.0, 456.0);
+        assert_eq!(view.to_document(view.to_screen(point)), point);
+    }
+
+    #[test]
+    fn test_scroll_clamps_to_content() {
+        let mut view = view();
+        view.scroll(-50.0);
+        assert_eq!(view.scroll_y, 0.0);
+
+        // 3000px of pages and 80px of margins, less the 600px viewport.
+        view.scroll(10_000.0);
+        assert_eq!(view.scroll_y, 2480.0);
+        assert_eq!(view.current_page(), 3);
+        let last = view.page_rect(2);
+        assert_eq!(last.y + last.height + PAGE_MARGIN, 88.0 + 600.0);
+
+        // Losing pages pulls the view back, and a short document does not
+        // scroll at all.
+        view.set_pages(Size::new(500.0, 1000.0), 2);
+        assert_eq!(view.scroll_y, 1480.0);
+        view.set_pages(Size::new(500.0, 200.0), 1);
+        assert_eq!(view.max_scroll(), 0.0);
+        assert_eq!(view.scroll_y, 0.0);
+    }
+
+    #[test]
+    fn test_zoom_keeps_top_in_place() {
+        let mut view = view();
+        view.scroll_to(1040.0);
+        assert_eq!(view.current_page(), 2);
+        view.set_zoom(0.5);
+        // 1000 points down is 500px at 50%, plus the margin.
+        assert_eq!(view.scroll_y, 540.0);
+        assert_eq!(view.page_rect(0).x, 250.0 + 275.0);
+    }
+
+    #[test]
+    fn test_wheel_delta_to_pixels() {
+        assert_eq!(wheel_pixels(MouseScrollDelta::LineDelta(0.0, -3.0)), 120.0);
+        assert_eq!(wheel_pixels(MouseScrollDelta::LineDelta(0.0, 1.0)), -40.0);
+        let pixels = MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, 12.5));
+        assert_eq!(wheel_pixels(pixels), -12.5);
+    }
+
+    #[test]
+    fn test_smooth_scrolling_eases_to_target() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut view = view();
+        view.smooth_scrolling = true;
+        view.scroll_wheel(MouseScrollDelta::LineDelta(0.0, -2.0), at(0));
+        assert_eq!(view.scroll_y, 0.0);
+        assert!(view.animate(at(16)));
+        assert!(view.scroll_y > 0.0 && view.scroll_y < 80.0);
+        while view.is_scrolling() {
+            view.animate(at(1000));
+        }
+        assert_eq!(view.scroll_y, 80.0);
+
+        // Touchpads scroll at once, and the target stays within bounds.
+        let pixels = MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, -20.0));
+        view.scroll_wheel(pixels, at(1000));
+        assert_eq!(view.scroll_y, 100.0);
+        view.scroll_wheel(MouseScrollDelta::LineDelta(0.0, 10.0), at(1000));
+        view.animate(at(2000));
+        assert_eq!(view.scroll_y, 0.0);
+        assert!(!view.animate(at(2000)));
+    }
+
+    #[test]
+    fn test_caret_blink_pauses_after_input() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut blink = CaretBlink::new(start);
+        assert!(blink.is_visible(at(500)));
+        assert!(!blink.is_visible(at(600)));
+        assert_eq!(blink.next_change(at(600)), at(1060));
+
+        // A keystroke shows the caret at once for a full interval.
+        blink.reset(at(600));
+        assert!(blink.is_visible(at(600)));
+        assert!(blink.is_visible(at(1100)));
+        assert!(!blink.is_visible(at(1140)));
+    }
+
+    #[test]
+    fn test_click_counting() {
+        let mut view = Editor::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let point = Point::new(10.0, 10.0);
+        assert_eq!(view.register_click(point, at(0)), 1);
+        assert_eq!(view.register_click(point, at(200)), 2);
+        assert_eq!(view.register_click(Point::new(12.0, 11.0), at(400)), 3);
+        assert_eq!(view.register_click(point, at(500)), 1);
+        // Too slow, and too far away.
+        assert_eq!(view.register_click(point, at(1200)), 1);
+        assert_eq!(view.register_click(Point::new(30.0, 10.0), at(1300)), 1);
+    }
+}
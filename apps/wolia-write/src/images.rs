@@ -0,0 +1,164 @@
+//! Images embedded in the document.
+//!
+//! Image nodes refer to their pictures by an `asset:` source. The encoded
+//! data is kept to be saved with the document, and the decoded pixels to
+//! size and draw the images.
+
+use std::collections::{BTreeMap, HashMap};
+
+use format_wolia::{ASSET_SCHEME, Asset, Package};
+use wolia_assets::{DecodedImage, ImageLoader, content_hash};
+use wolia_core::Document;
+use wolia_core::node::{Node, NodeKind};
+use wolia_math::Size;
+
+/// Largest width or height of an image that can be inserted, in pixels,
+/// which is as large as a GPU texture is sure to be.
+const MAX_DIMENSION: u32 = 8192;
+
+/// Points per image pixel, at 96 pixels per inch.
+const POINTS_PER_PIXEL: f32 = 0.75;
+
+/// Extensions offered when picking an image to insert.
+pub const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+/// The images a document embeds, by the `src` image nodes use.
+pub struct DocumentImages {
+    /// Decoder for inserted and loaded images.
+    loader: ImageLoader,
+    /// Encoded images, by asset id.
+    assets: BTreeMap<String, Asset>,
+    /// Decoded images, by `src`.
+    decoded: HashMap<String, DecodedImage>,
+}
+
+impl DocumentImages {
+    /// No images.
+    pub fn new() -> Self {
+        Self {
+            loader: ImageLoader::new().with_max_dimension(MAX_DIMENSION),
+            assets: BTreeMap::new(),
+            decoded: HashMap::new(),
+        }
+    }
+
+    /// The images among assets read with a document. Assets that are not
+    /// images, or cannot be decoded, are kept to be saved again but not
+    /// drawn.
+    pub fn from_assets(assets: BTreeMap<String, Asset>) -> Self {
+        let mut images = Self::new();
+        for (id, asset) in &assets {
+            if !asset.media_type.starts_with("image/") {
+                continue;
+            }
+            match images.loader.decode(&asset.data) {
+                Ok(decoded) => {
+                    images
+                        .decoded
+                        .insert(format!("{ASSET_SCHEME}{id}"), decoded);
+                }
+                Err(e) => tracing::warn!("Cannot decode image {}: {}", id, e),
+            }
+        }
+        images.assets = assets;
+        images
+    }
+
+    /// Decode and embed an encoded image, returning the `src` an image node
+    /// refers to it by. The same image added twice is embedded once.
+    pub fn add(&mut self, data: Vec<u8>) -> wolia_assets::Result<String> {
+        let decoded = self.loader.decode(&data)?;
+        let id = format!("image-{:016x}", content_hash(&data));
+        let src = format!("{ASSET_SCHEME}{id}");
+        self.assets.insert(
+            id,
+            Asset {
+                media_type: decoded.format.mime_type().to_string(),
+                data,
+            },
+        );
+        self.decoded.insert(src.clone(), decoded);
+        Ok(src)
+    }
+
+    /// The decoded image with `src`, if embedded.
+    pub fn get(&self, src: &str) -> Option<&DecodedImage> {
+        self.decoded.get(src)
+    }
+
+    /// The `src` of every decoded image with its natural size in points.
+    pub fn sizes(&self) -> impl Iterator<Item = (&str, Size)> {
+        self.decoded.iter().map(|(src, image)| {
+            let size = Size::new(
+                image.width as f32 * POINTS_PER_PIXEL,
+                image.height as f32 * POINTS_PER_PIXEL,
+            );
+            (src.as_str(), size)
+        })
+    }
+
+    /// `document` with the assets its image nodes refer to, to be saved.
+    /// Images no longer in the document are left out.
+    pub fn package(&self, document: &Document) -> Package {
+        let mut package = Package::new(document.clone());
+        let mut sources = Vec::new();
+        collect_sources(&document.root, &mut sources);
+        for src in sources {
+            let Some(id) = src.strip_prefix(ASSET_SCHEME) else {
+                continue;
+            };
+            if let Some(asset) = self.assets.get(id) {
+                package.assets.insert(id.to_string(), asset.clone());
+            }
+        }
+        package
+    }
+}
+
+impl Default for DocumentImages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collect the `src` of every image under `node`.
+fn collect_sources<'a>(node: &'a Node, sources: &mut Vec<&'a str>) {
+    if let NodeKind::Image { src, .. } = &node.kind {
+        sources.push(src);
+    }
+    for child in &node.children {
+        collect_sources(child, sources);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = include_bytes!("../../../test-suite/images/palette.png");
+
+    #[test]
+    fn test_only_referenced_images_are_packaged() {
+        let mut images = DocumentImages::new();
+        let src = images.add(PNG.to_vec()).unwrap();
+        assert_eq!(images.add(PNG.to_vec()).unwrap(), src);
+        assert!(images.add(b"not an image".to_vec()).is_err());
+        assert_eq!(images.get(&src).map(|image| image.width), Some(2));
+        assert_eq!(
+            images.sizes().collect::<Vec<_>>(),
+            [(src.as_str(), Size::new(1.5, 1.5))]
+        );
+
+        let mut document = Document::new();
+        assert!(images.package(&document).assets.is_empty());
+        document.root.add_child(Node::new(NodeKind::Image {
+            src: src.clone(),
+            alt: None,
+        }));
+        let package = images.package(&document);
+        assert_eq!(package.resolve(&src).unwrap().media_type, "image/png");
+
+        let loaded = DocumentImages::from_assets(package.assets);
+        assert_eq!(loaded.get(&src).map(|image| image.height), Some(2));
+    }
+}
@@ -0,0 +1,322 @@
+//! Translation of winit keyboard input into editor events.
+
+use winit::keyboard::{Key as WinitKey, ModifiersState, NamedKey};
+use wolia_edit::input::ImeEvent;
+use wolia_edit::{Key, KeyModifiers, KeyboardEvent};
+
+/// Maps winit keys and modifiers to editor keyboard events.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    /// Modifiers currently held.
+    modifiers: KeyModifiers,
+    /// Whether shortcuts use Cmd, as on macOS, rather than Ctrl.
+    command_is_meta: bool,
+}
+
+impl KeyMap {
+    /// Create a key map using the current platform's shortcut modifier.
+    pub fn new() -> Self {
+        Self {
+            modifiers: KeyModifiers::new(),
+            command_is_meta: cfg!(target_os = "macos"),
+        }
+    }
+
+    /// Use Cmd (true) or Ctrl (false) as the shortcut modifier.
+    pub fn with_command_is_meta(mut self, command_is_meta: bool) -> Self {
+        self.command_is_meta = command_is_meta;
+        self
+    }
+
+    /// Record the modifiers winit reports as held.
+    pub fn set_modifiers(&mut self, state: ModifiersState) {
+        self.modifiers = KeyModifiers {
+            shift: state.shift_key(),
+            control: state.control_key(),
+            alt: state.alt_key(),
+            meta: state.super_key(),
+        };
+    }
+
+    /// Whether the shortcut modifier is held in `modifiers`.
+    pub fn is_command(&self, modifiers: &KeyModifiers) -> bool {
+        if self.command_is_meta {
+            modifiers.meta
+        } else {
+            modifiers.control
+        }
+    }
+
+    /// Whether the shortcut modifier is held now.
+    pub fn command_held(&self) -> bool {
+        self.is_command(&self.modifiers)
+    }
+
+    /// Translate a winit key event, given as its logical key, the text it
+    /// produces, and whether it is a press. Each typed character becomes
+    /// its own event. Nothing is typed while the shortcut modifier is
+    /// held, and control characters are only typed for Enter and Tab, as
+    /// a line break and a tab.
+    pub fn translate(
+        &self,
+        key: &WinitKey,
+        text: Option<&str>,
+        pressed: bool,
+    ) -> Vec<KeyboardEvent> {
+        let mut modifiers = self.modifiers;
+        let mut key = map_key(key);
+
+        // Cmd+Left and Cmd+Right move to the ends of the line on macOS.
+        if self.command_is_meta && modifiers.meta {
+            let line_end = match key {
+                Key::ArrowLeft => Some(Key::Home),
+                Key::ArrowRight => Some(Key::End),
+                _ => None,
+            };
+            if let Some(line_end) = line_end {
+                key = line_end;
+                modifiers.meta = false;
+            }
+        }
+
+        // Ctrl+Alt is AltGr on Windows, which types rather than being a
+        // shortcut.
+        let shortcut = self.is_command(&modifiers) && !modifiers.alt;
+        let typed: Vec<char> = match (key, text) {
+            _ if !pressed || shortcut => Vec::new(),
+            // Enter and Tab report control characters as their text.
+            (Key::Enter, _) => vec!['\n'],
+            (Key::Tab, _) => vec!['\t'],
+            (_, Some(text)) => text.chars().filter(|c| !c.is_control()).collect(),
+            (_, None) => Vec::new(),
+        };
+        let Some((&first, rest)) = typed.split_first() else {
+            return vec![KeyboardEvent::new(key, pressed, modifiers)];
+        };
+        // The editor does not type while Ctrl or Alt is held, but AltGr
+        // and Option produce characters that should be typed.
+        let typing = KeyModifiers {
+            shift: modifiers.shift,
+            ..KeyModifiers::new()
+        };
+        let mut events = vec![KeyboardEvent::new(key, pressed, typing).with_char(first)];
+        events.extend(
+            rest.iter()
+                .map(|&c| KeyboardEvent::new(Key::Unknown, pressed, typing).with_char(c)),
+        );
+        events
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Translate a winit IME event.
+pub fn translate_ime(ime: &winit::event::Ime) -> ImeEvent {
+    use winit::event::Ime;
+
+    match ime {
+        Ime::Enabled => ImeEvent::Start,
+        Ime::Preedit(text, cursor) => ImeEvent::Preedit(text.clone(), cursor.map(|(_, end)| end)),
+        Ime::Commit(text) => ImeEvent::Commit(text.clone()),
+        Ime::Disabled => ImeEvent::End,
+    }
+}
+
+/// The editor key for a winit logical key.
+fn map_key(key: &WinitKey) -> Key {
+    match key {
+        WinitKey::Named(named) => match named {
+            NamedKey::Enter => Key::Enter,
+            NamedKey::Escape => Key::Escape,
+            NamedKey::Backspace => Key::Backspace,
+            NamedKey::Tab => Key::Tab,
+            NamedKey::Space => Key::Space,
+            NamedKey::Shift => Key::Shift,
+            NamedKey::Control => Key::Control,
+            NamedKey::Alt | NamedKey::AltGraph => Key::Alt,
+            NamedKey::Super | NamedKey::Meta => Key::Meta,
+            NamedKey::CapsLock => Key::CapsLock,
+            NamedKey::F1 => Key::F1,
+            NamedKey::F2 => Key::F2,
+            NamedKey::F3 => Key::F3,
+            NamedKey::F4 => Key::F4,
+            NamedKey::F5 => Key::F5,
+            NamedKey::F6 => Key::F6,
+            NamedKey::F7 => Key::F7,
+            NamedKey::F8 => Key::F8,
+            NamedKey::F9 => Key::F9,
+            NamedKey::F10 => Key::F10,
+            NamedKey::F11 => Key::F11,
+            NamedKey::F12 => Key::F12,
+            NamedKey::Insert => Key::Insert,
+            NamedKey::Delete => Key::Delete,
+            NamedKey::Home => Key::Home,
+            NamedKey::End => Key::End,
+            NamedKey::PageUp => Key::PageUp,
+            NamedKey::PageDown => Key::PageDown,
+            NamedKey::ArrowUp => Key::ArrowUp,
+            NamedKey::ArrowDown => Key::ArrowDown,
+            NamedKey::ArrowLeft => Key::ArrowLeft,
+            NamedKey::ArrowRight => Key::ArrowRight,
+            _ => Key::Unknown,
+        },
+        WinitKey::Character(text) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => map_char(c.to_ascii_lowercase()),
+                _ => Key::Unknown,
+            }
+        }
+        _ => Key::Unknown,
+    }
+}
+
+/// The editor key for a character on an unshifted US layout.
+fn map_char(c: char) -> Key {
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Digit0,
+        Key::Digit1,
+        Key::Digit2,
+        Key::Digit3,
+        Key::Digit4,
+        Key::Digit5,
+        Key::Digit6,
+        Key::Digit7,
+        Key::Digit8,
+        Key::Digit9,
+    ];
+    match c {
+        'a'..='z' => LETTERS[(c as u8 - b'a') as usize],
+        '0'..='9' => DIGITS[(c as u8 - b'0') as usize],
+        ' ' => Key::Space,
+        '-' => Key::Minus,
+        '=' => Key::Equal,
+        '[' => Key::BracketLeft,
+        ']' => Key::BracketRight,
+        '\\' => Key::Backslash,
+        ';' => Key::Semicolon,
+        '\'' => Key::Quote,
+        '`' => Key::Backquote,
+        ',' => Key::Comma,
+        '.' => Key::Period,
+        '/' => Key::Slash,
+        _ => Key::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shortcuts::Shortcuts;
+    use crate::toolbar::{Toolbar, ToolbarAction};
+
+    fn shortcuts(command_is_meta: bool) -> Shortcuts {
+        Shortcuts::for_toolbar(&Toolbar::new()).with_command_is_meta(command_is_meta)
+    }
+
+    fn character(text: &str) -> WinitKey {
+        WinitKey::Character(text.into())
+    }
+
+    #[test]
+    fn test_ctrl_shortcut_on_linux_and_windows() {
+        let mut keys = KeyMap::new().with_command_is_meta(false);
+        let shortcuts = shortcuts(false);
+        keys.set_modifiers(ModifiersState::CONTROL);
+        // Ctrl+B reports a control character as its text.
+        let events = keys.translate(&character("b"), Some("\u{2}"), true);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.key, Key::B);
+        assert!(event.modifiers.control && !event.modifiers.meta);
+        assert_eq!(event.char_code, None);
+        assert_eq!(shortcuts.action(event), Some(ToolbarAction::Bold));
+
+        keys.set_modifiers(ModifiersState::CONTROL | ModifiersState::SHIFT);
+        let event = &keys.translate(&character("Z"), None, true)[0];
+        assert_eq!(shortcuts.action(event), Some(ToolbarAction::Redo));
+    }
+
+    #[test]
+    fn test_cmd_shortcut_on_macos() {
+        let mut keys = KeyMap::new().with_command_is_meta(true);
+        let shortcuts = shortcuts(true);
+        keys.set_modifiers(ModifiersState::SUPER);
+        let event = &keys.translate(&character("s"), Some("s"), true)[0];
+        assert!(event.modifiers.meta);
+        assert_eq!(event.char_code, None);
+        assert_eq!(shortcuts.action(event), Some(ToolbarAction::Save));
+
+        // Ctrl is not the shortcut modifier there.
+        keys.set_modifiers(ModifiersState::CONTROL);
+        let event = &keys.translate(&character("s"), Some("\u{13}"), true)[0];
+        assert_eq!(shortcuts.action(event), None);
+        assert_eq!(event.char_code, None);
+
+        // Cmd+Left goes to the start of the line.
+        keys.set_modifiers(ModifiersState::SUPER);
+        let event = &keys.translate(&WinitKey::Named(NamedKey::ArrowLeft), None, true)[0];
+        assert_eq!(event.key, Key::Home);
+    }
+
+    #[test]
+    fn test_typed_text() {
+        let mut keys = KeyMap::new().with_command_is_meta(false);
+        let shortcuts = shortcuts(false);
+        keys.set_modifiers(ModifiersState::SHIFT);
+        let event = &keys.translate(&character("A"), Some("A"), true)[0];
+        assert_eq!((event.key, event.char_code), (Key::A, Some('A')));
+        assert!(event.modifiers.shift);
+        assert_eq!(shortcuts.action(event), None);
+
+        // AltGr reports Ctrl and Alt, but types its character.
+        keys.set_modifiers(ModifiersState::CONTROL | ModifiersState::ALT);
+        let event = &keys.translate(&character("@"), Some("@"), true)[0];
+        assert_eq!(event.char_code, Some('@'));
+        assert!(!event.modifiers.control && !event.modifiers.alt);
+        assert_eq!(shortcuts.action(event), None);
+
+        // Enter is typed as a line break rather than a carriage return,
+        // and composed text is typed a character at a time.
+        keys.set_modifiers(ModifiersState::empty());
+        let enter = keys.translate(&WinitKey::Named(NamedKey::Enter), Some("\r"), true);
+        assert_eq!((enter[0].key, enter[0].char_code), (Key::Enter, Some('\n')));
+        let escape = keys.translate(&WinitKey::Named(NamedKey::Escape), Some("\u{1b}"), true);
+        assert_eq!(escape[0].char_code, None);
+        let composed = keys.translate(&WinitKey::Dead(Some('`')), Some("`a"), true);
+        let chars: Vec<_> = composed.iter().filter_map(|e| e.char_code).collect();
+        assert_eq!(chars, vec!['`', 'a']);
+    }
+}
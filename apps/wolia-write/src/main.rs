@@ -9,7 +9,12 @@ use tracing_subscriber::prelude::*;
 
 mod app;
 mod automation;
+mod clipboard;
 mod editor;
+mod images;
+mod keyboard;
+mod ruler;
+mod shortcuts;
 mod sidebar;
 mod statusbar;
 mod toolbar;
@@ -0,0 +1,417 @@
+//! Horizontal ruler above the pages.
+//!
+//! The ruler measures the page in inches or centimeters from the left
+//! margin, and carries markers for the page margins and for the indents of
+//! the paragraph at the caret. Positions are kept in points from the left
+//! edge of the page, and shown at the view's zoom. Dragged markers snap to
+//! the ruler's smallest ticks.
+
+use wolia_core::style::ParagraphStyle;
+use wolia_layout::Margins;
+
+/// Height of the ruler in screen pixels.
+pub const RULER_HEIGHT: f32 = 24.0;
+
+/// Distance, in screen pixels, within which a press picks up a marker.
+const GRAB_DISTANCE: f32 = 5.0;
+
+/// Narrowest the text between the margins or the indents can be, in
+/// points.
+const MIN_WIDTH: f32 = 36.0;
+
+/// Unit the ruler measures in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulerUnit {
+    /// Inches, divided into eighths.
+    Inches,
+    /// Centimeters, divided into quarters.
+    Centimeters,
+}
+
+impl RulerUnit {
+    /// Points in one unit.
+    pub fn points(self) -> f32 {
+        match self {
+            Self::Inches => 72.0,
+            Self::Centimeters => 72.0 / 2.54,
+        }
+    }
+
+    /// Number of ticks each unit is divided into.
+    pub fn divisions(self) -> u32 {
+        match self {
+            Self::Inches => 8,
+            Self::Centimeters => 4,
+        }
+    }
+
+    /// Points between neighbouring ticks, which markers snap to.
+    pub fn step(self) -> f32 {
+        self.points() / self.divisions() as f32
+    }
+
+    /// `points` rounded to the nearest tick.
+    pub fn snap(self, points: f32) -> f32 {
+        (points / self.step()).round() * self.step()
+    }
+
+    /// `points` in this unit.
+    pub fn in_units(self, points: f32) -> f32 {
+        points / self.points()
+    }
+
+    /// A length in this unit, in points.
+    pub fn to_points(self, value: f32) -> f32 {
+        value * self.points()
+    }
+}
+
+/// A tick on the ruler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    /// Position in points from the left edge of the page.
+    pub position: f32,
+    /// Whole units from the left margin, on ticks that mark a whole unit.
+    pub label: Option<i32>,
+    /// Whether the tick marks a half unit.
+    pub half: bool,
+}
+
+/// A marker that can be dragged along the ruler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    /// The left page margin.
+    LeftMargin,
+    /// The right page margin.
+    RightMargin,
+    /// The indent of every line of the paragraph but the first.
+    LeftIndent,
+    /// The indent of the first line of the paragraph.
+    FirstLineIndent,
+    /// The indent of the right edge of the paragraph.
+    RightIndent,
+}
+
+impl Marker {
+    /// Whether the marker sets a paragraph indent rather than a page
+    /// margin.
+    pub fn is_indent(self) -> bool {
+        !matches!(self, Self::LeftMargin | Self::RightMargin)
+    }
+}
+
+/// Where the markers are, in points from the left edge of the page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarkerPositions {
+    /// Width of the page.
+    pub page_width: f32,
+    /// The left margin.
+    pub left_margin: f32,
+    /// The right margin.
+    pub right_margin: f32,
+    /// The left indent.
+    pub left_indent: f32,
+    /// The first line indent.
+    pub first_line_indent: f32,
+    /// The right indent.
+    pub right_indent: f32,
+}
+
+impl MarkerPositions {
+    /// The markers for a page `page_width` wide with `margins`, and a
+    /// paragraph formatted with `style`.
+    pub fn new(page_width: f32, margins: &Margins, style: &ParagraphStyle) -> Self {
+        let left_indent = margins.left + style.margin_left.unwrap_or(0.0);
+        let right_margin = page_width - margins.right;
+        Self {
+            page_width,
+            left_margin: margins.left,
+            right_margin,
+            left_indent,
+            first_line_indent: left_indent + style.first_line_indent.unwrap_or(0.0),
+            right_indent: right_margin - style.margin_right.unwrap_or(0.0),
+        }
+    }
+
+    /// The position of `marker`.
+    pub fn get(&self, marker: Marker) -> f32 {
+        match marker {
+            Marker::LeftMargin => self.left_margin,
+            Marker::RightMargin => self.right_margin,
+            Marker::LeftIndent => self.left_indent,
+            Marker::FirstLineIndent => self.first_line_indent,
+            Marker::RightIndent => self.right_indent,
+        }
+    }
+}
+
+/// A value set by dragging a marker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RulerChange {
+    /// Set the left page margin, in points.
+    LeftMargin(f32),
+    /// Set the right page margin, in points.
+    RightMargin(f32),
+    /// Set the paragraph's left indent, in points from the left margin.
+    LeftIndent(f32),
+    /// Set the paragraph's first line indent, in points from its left
+    /// indent.
+    FirstLineIndent(f32),
+    /// Set the paragraph's right indent, in points from the right margin.
+    RightIndent(f32),
+}
+
+impl RulerChange {
+    /// Apply an indent change to a paragraph style. Margin changes are
+    /// ignored.
+    pub fn apply(&self, style: &mut ParagraphStyle) {
+        let value = |points: f32| (points != 0.0).then_some(points);
+        match *self {
+            Self::LeftIndent(points) => style.margin_left = value(points),
+            Self::FirstLineIndent(points) => style.first_line_indent = value(points),
+            Self::RightIndent(points) => style.margin_right = value(points),
+            Self::LeftMargin(_) | Self::RightMargin(_) => {}
+        }
+    }
+
+    /// Apply a margin change to page margins. Indent changes are ignored.
+    pub fn apply_margins(&self, margins: &mut Margins) {
+        match *self {
+            Self::LeftMargin(points) => margins.left = points,
+            Self::RightMargin(points) => margins.right = points,
+            _ => {}
+        }
+    }
+}
+
+/// The ruler's unit and the marker being dragged.
+#[derive(Debug, Clone)]
+pub struct Ruler {
+    /// Unit the ruler measures in.
+    pub unit: RulerUnit,
+    /// The marker being dragged.
+    dragging: Option<Marker>,
+}
+
+impl Ruler {
+    /// Create a ruler measuring in `unit`.
+    pub fn new(unit: RulerUnit) -> Self {
+        Self {
+            unit,
+            dragging: None,
+        }
+    }
+
+    /// The marker being dragged, if any.
+    pub fn dragging(&self) -> Option<Marker> {
+        self.dragging
+    }
+
+    /// The ticks across a page, numbered from the left margin.
+    pub fn ticks(&self, markers: &MarkerPositions) -> Vec<Tick> {
+        let step = self.unit.step();
+        let divisions = self.unit.divisions() as i32;
+        let first = -(markers.left_margin / step).floor() as i32;
+        let last = ((markers.page_width - markers.left_margin) / step).floor() as i32;
+        (first..=last)
+            .map(|index| Tick {
+                position: markers.left_margin + index as f32 * step,
+                label: (index % divisions == 0).then_some(index / divisions),
+                half: index % divisions == divisions / 2,
+            })
+            .collect()
+    }
+
+    /// The marker within reach of a press at screen `x`, `y` pixels below
+    /// the top of the ruler, on a page whose left edge is at `page_x` and
+    /// is drawn at `zoom`. The first line indent is picked up in the top
+    /// third of the ruler, the margins in the middle and the other indents
+    /// in the bottom third.
+    pub fn marker_at(
+        &self,
+        x: f32,
+        y: f32,
+        markers: &MarkerPositions,
+        page_x: f32,
+        zoom: f32,
+    ) -> Option<Marker> {
+        let candidates: &[Marker] = if y < RULER_HEIGHT / 3.0 {
+            &[Marker::FirstLineIndent]
+        } else if y < RULER_HEIGHT * 2.0 / 3.0 {
+            &[Marker::LeftMargin, Marker::RightMargin]
+        } else {
+            &[Marker::LeftIndent, Marker::RightIndent]
+        };
+        candidates.iter().copied().find(|&marker| {
+            (to_pixels(markers.get(marker), page_x, zoom) - x).abs() <= GRAB_DISTANCE
+        })
+    }
+
+    /// Start dragging `marker`.
+    pub fn start_drag(&mut self, marker: Marker) {
+        self.dragging = Some(marker);
+    }
+
+    /// Stop dragging, returning the marker that was dragged.
+    pub fn end_drag(&mut self) -> Option<Marker> {
+        self.dragging.take()
+    }
+
+    /// The change made by dragging the marker to screen `x`, snapped to
+    /// the ticks and kept clear of the other markers.
+    pub fn drag_to(
+        &self,
+        x: f32,
+        markers: &MarkerPositions,
+        page_x: f32,
+        zoom: f32,
+    ) -> Option<RulerChange> {
+        let marker = self.dragging?;
+        let position = to_points(x, page_x, zoom);
+        let snap = |points: f32| self.unit.snap(points);
+        let m = markers;
+        Some(match marker {
+            Marker::LeftMargin => {
+                let max = m.right_margin - MIN_WIDTH;
+                RulerChange::LeftMargin(snap(position).clamp(0.0, max.max(0.0)))
+            }
+            Marker::RightMargin => {
+                let max = m.page_width - m.left_margin - MIN_WIDTH;
+                RulerChange::RightMargin(snap(m.page_width - position).clamp(0.0, max.max(0.0)))
+            }
+            Marker::LeftIndent => {
+                let max = m.right_indent - MIN_WIDTH - m.left_margin;
+                RulerChange::LeftIndent(snap(position - m.left_margin).clamp(0.0, max.max(0.0)))
+            }
+            Marker::FirstLineIndent => {
+                // The first line may hang left of the others, but not past
+                // the margin.
+                let min = m.left_margin - m.left_indent;
+                let max = m.right_indent - MIN_WIDTH - m.left_indent;
+                RulerChange::FirstLineIndent(
+                    snap(position - m.left_indent).clamp(min, max.max(min)),
+                )
+            }
+            Marker::RightIndent => {
+                let max = m.right_margin - m.left_indent.max(m.first_line_indent) - MIN_WIDTH;
+                RulerChange::RightIndent(snap(m.right_margin - position).clamp(0.0, max.max(0.0)))
+            }
+        })
+    }
+}
+
+impl Default for Ruler {
+    fn default() -> Self {
+        Self::new(RulerUnit::Inches)
+    }
+}
+
+/// The page position, in points, shown at screen `x` on a page whose left
+/// edge is at `page_x` and is drawn at `zoom`.
+pub fn to_points(x: f32, page_x: f32, zoom: f32) -> f32 {
+    (x - page_x) / zoom
+}
+
+/// The screen x showing page position `points` on a page whose left edge
+/// is at `page_x` and is drawn at `zoom`.
+pub fn to_pixels(points: f32, page_x: f32, zoom: f32) -> f32 {
+    page_x + points * zoom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markers() -> MarkerPositions {
+        let style = ParagraphStyle {
+            margin_left: Some(18.0),
+            first_line_indent: Some(18.0),
+            ..ParagraphStyle::default()
+        };
+        MarkerPositions::new(612.0, &Margins::uniform(72.0), &style)
+    }
+
+    #[test]
+    fn test_pixel_conversion_and_snapping() {
+        assert_eq!(to_points(300.0, 100.0, 2.0), 100.0);
+        assert_eq!(to_pixels(100.0, 100.0, 2.0), 300.0);
+        assert_eq!(to_points(to_pixels(37.5, 20.0, 0.75), 20.0, 0.75), 37.5);
+
+        let inches = RulerUnit::Inches;
+        assert_eq!(inches.step(), 9.0);
+        assert_eq!(inches.snap(40.0), 36.0);
+        assert_eq!(inches.snap(41.0), 45.0);
+        assert_eq!(inches.in_units(108.0), 1.5);
+
+        let cm = RulerUnit::Centimeters;
+        assert!((cm.to_points(2.54) - 72.0).abs() < 1e-4);
+        assert!((cm.snap(30.0) - 4.0 * cm.step()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_ticks_count_from_left_margin() {
+        let ruler = Ruler::default();
+        let ticks = ruler.ticks(&markers());
+        let zero = ticks.iter().find(|tick| tick.label == Some(0)).unwrap();
+        assert_eq!(zero.position, 72.0);
+        assert_eq!(ticks.first().unwrap().label, Some(-1));
+        assert_eq!(ticks.first().unwrap().position, 0.0);
+        assert_eq!(ticks.iter().filter(|tick| tick.label.is_some()).count(), 9);
+        assert!(ticks.iter().any(|tick| tick.half && tick.position == 108.0));
+    }
+
+    #[test]
+    fn test_marker_hit_testing_respects_zoom() {
+        let ruler = Ruler::default();
+        let markers = markers();
+        // At 200% from x = 50, the left indent (90pt) is at 230px and the
+        // first line indent (108pt) at 266px.
+        let (top, middle, bottom) = (2.0, RULER_HEIGHT / 2.0, RULER_HEIGHT - 2.0);
+        assert_eq!(
+            ruler.marker_at(232.0, bottom, &markers, 50.0, 2.0),
+            Some(Marker::LeftIndent)
+        );
+        assert_eq!(
+            ruler.marker_at(266.0, top, &markers, 50.0, 2.0),
+            Some(Marker::FirstLineIndent)
+        );
+        assert_eq!(
+            ruler.marker_at(194.0, middle, &markers, 50.0, 2.0),
+            Some(Marker::LeftMargin)
+        );
+        assert_eq!(ruler.marker_at(194.0, bottom, &markers, 50.0, 2.0), None);
+        assert_eq!(ruler.marker_at(250.0, bottom, &markers, 50.0, 2.0), None);
+    }
+
+    #[test]
+    fn test_drag_snaps_and_clamps() {
+        let mut ruler = Ruler::default();
+        let markers = markers();
+        assert_eq!(ruler.drag_to(0.0, &markers, 0.0, 1.0), None);
+
+        ruler.start_drag(Marker::FirstLineIndent);
+        // 130pt is 40pt past the left indent, snapping to 36pt.
+        assert_eq!(
+            ruler.drag_to(130.0, &markers, 0.0, 1.0),
+            Some(RulerChange::FirstLineIndent(36.0))
+        );
+        // A hanging indent stops at the margin.
+        assert_eq!(
+            ruler.drag_to(10.0, &markers, 0.0, 1.0),
+            Some(RulerChange::FirstLineIndent(-18.0))
+        );
+
+        ruler.start_drag(Marker::RightMargin);
+        assert_eq!(
+            ruler.drag_to(500.0, &markers, 0.0, 1.0),
+            Some(RulerChange::RightMargin(108.0))
+        );
+        assert_eq!(ruler.end_drag(), Some(Marker::RightMargin));
+
+        let mut style = ParagraphStyle::default();
+        RulerChange::FirstLineIndent(36.0).apply(&mut style);
+        RulerChange::LeftIndent(0.0).apply(&mut style);
+        assert_eq!(style.first_line_indent, Some(36.0));
+        assert_eq!(style.margin_left, None);
+    }
+}
@@ -0,0 +1,489 @@
+//! Keyboard shortcuts.
+//!
+//! Shortcuts are written as a key after any modifiers, joined with `+`, as
+//! in "Ctrl+Shift+X". "Ctrl" names the platform's shortcut modifier, which
+//! is Cmd on macOS, so one string serves every platform. A shortcut
+//! matches a key press only with exactly its modifiers held: Ctrl+Shift+Z
+//! does not trigger Ctrl+Z.
+
+use std::fmt;
+use std::str::FromStr;
+
+use wolia_edit::{Key, KeyModifiers, KeyboardEvent};
+
+use crate::toolbar::{Toolbar, ToolbarAction};
+
+/// Shortcuts not shown on a toolbar button.
+const EXTRA_SHORTCUTS: &[(&str, ToolbarAction)] = &[
+    ("Ctrl+Shift+S", ToolbarAction::SaveAs),
+    ("Ctrl+P", ToolbarAction::Print),
+    ("Ctrl+Shift+Z", ToolbarAction::Redo),
+    ("Ctrl+F", ToolbarAction::Find),
+    ("Ctrl+H", ToolbarAction::Replace),
+    ("Ctrl+Enter", ToolbarAction::InsertPageBreak),
+    ("Ctrl+=", ToolbarAction::ZoomIn),
+    ("Ctrl+-", ToolbarAction::ZoomOut),
+    ("Ctrl+0", ToolbarAction::ResetZoom),
+];
+
+/// A key and the modifiers held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    /// The key pressed.
+    pub key: Key,
+    /// Whether the platform's shortcut modifier is held: Cmd on macOS and
+    /// Ctrl elsewhere.
+    pub command: bool,
+    /// Whether Shift is held.
+    pub shift: bool,
+    /// Whether Alt, or Option, is held.
+    pub alt: bool,
+    /// Whether the Super, Windows or Cmd key is held.
+    pub meta: bool,
+}
+
+impl Shortcut {
+    /// Create a shortcut for `key` with no modifiers.
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            command: false,
+            shift: false,
+            alt: false,
+            meta: false,
+        }
+    }
+
+    /// Parse a shortcut such as "Ctrl+Shift+X".
+    pub fn parse(text: &str) -> Result<Self, ShortcutError> {
+        let invalid = || ShortcutError::Invalid(text.to_string());
+        let (key, modifiers) = match text.rsplit_once('+') {
+            Some((modifiers, key)) => (key, Some(modifiers)),
+            None => (text, None),
+        };
+        let mut shortcut = Self::new(parse_key(key.trim()).ok_or_else(invalid)?);
+        for modifier in modifiers.into_iter().flat_map(|m| m.split('+')) {
+            let held = match modifier.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" | "cmdorctrl" => &mut shortcut.command,
+                "shift" => &mut shortcut.shift,
+                "alt" | "option" | "opt" => &mut shortcut.alt,
+                "meta" | "super" | "win" => &mut shortcut.meta,
+                _ => return Err(invalid()),
+            };
+            // Naming a modifier twice is a typo, not a chord.
+            if std::mem::replace(held, true) {
+                return Err(invalid());
+            }
+        }
+        Ok(shortcut)
+    }
+
+    /// The modifiers held, as the keyboard reports them on a platform whose
+    /// shortcut modifier is Cmd if `command_is_meta`.
+    pub fn modifiers(&self, command_is_meta: bool) -> KeyModifiers {
+        KeyModifiers {
+            shift: self.shift,
+            control: self.command && !command_is_meta,
+            alt: self.alt,
+            meta: self.meta || (self.command && command_is_meta),
+        }
+    }
+
+    /// Whether a key event presses this shortcut, with no other modifiers.
+    pub fn matches(&self, event: &KeyboardEvent, command_is_meta: bool) -> bool {
+        event.pressed
+            && event.key == self.key
+            && same_modifiers(&event.modifiers, &self.modifiers(command_is_meta))
+    }
+
+    /// Whether the two shortcuts are the same keys on some platform.
+    fn overlaps(&self, other: &Shortcut) -> bool {
+        self.key == other.key
+            && [false, true].into_iter().any(|command_is_meta| {
+                same_modifiers(
+                    &self.modifiers(command_is_meta),
+                    &other.modifiers(command_is_meta),
+                )
+            })
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = ShortcutError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.command, "Ctrl+"),
+            (self.meta, "Meta+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Errors binding shortcuts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShortcutError {
+    /// The text is not a key after distinct modifiers.
+    Invalid(String),
+    /// The keys already trigger another action.
+    Conflict {
+        shortcut: Shortcut,
+        existing: ToolbarAction,
+        action: ToolbarAction,
+    },
+}
+
+impl fmt::Display for ShortcutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(text) => write!(f, "Invalid shortcut: {text:?}"),
+            Self::Conflict {
+                shortcut,
+                existing,
+                action,
+            } => write!(
+                f,
+                "{shortcut} is already bound to {existing:?}, so cannot trigger {action:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShortcutError {}
+
+/// Shortcuts and the toolbar actions they trigger.
+#[derive(Debug, Clone)]
+pub struct Shortcuts {
+    bindings: Vec<(Shortcut, ToolbarAction)>,
+    /// Whether shortcuts use Cmd, as on macOS, rather than Ctrl.
+    command_is_meta: bool,
+}
+
+impl Shortcuts {
+    /// Create an empty registry using the current platform's shortcut
+    /// modifier.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            command_is_meta: cfg!(target_os = "macos"),
+        }
+    }
+
+    /// The shortcuts shown on a toolbar's buttons, and the standard ones
+    /// that no button shows. Shortcuts that fail to bind are logged and
+    /// left out.
+    pub fn for_toolbar(toolbar: &Toolbar) -> Self {
+        let mut shortcuts = Self::new();
+        let buttons = toolbar.all_buttons().into_iter().filter_map(|button| {
+            let action = ToolbarAction::for_button(&button.id)?;
+            Some((button.shortcut.as_deref()?, action))
+        });
+        for (shortcut, action) in buttons.chain(EXTRA_SHORTCUTS.iter().copied()) {
+            if let Err(e) = shortcuts.bind(shortcut, action) {
+                tracing::warn!("{}", e);
+            }
+        }
+        shortcuts
+    }
+
+    /// Use Cmd (true) or Ctrl (false) as the shortcut modifier.
+    pub fn with_command_is_meta(mut self, command_is_meta: bool) -> Self {
+        self.command_is_meta = command_is_meta;
+        self
+    }
+
+    /// Bind a shortcut to an action. Fails if the shortcut does not parse,
+    /// or if it is the same keys as one bound to another action on any
+    /// platform. An action may have several shortcuts.
+    pub fn bind(&mut self, shortcut: &str, action: ToolbarAction) -> Result<(), ShortcutError> {
+        let shortcut = Shortcut::parse(shortcut)?;
+        if let Some(&(existing_shortcut, existing)) = self
+            .bindings
+            .iter()
+            .find(|(bound, _)| bound.overlaps(&shortcut))
+        {
+            if existing == action && existing_shortcut == shortcut {
+                return Ok(());
+            }
+            return Err(ShortcutError::Conflict {
+                shortcut,
+                existing,
+                action,
+            });
+        }
+        self.bindings.push((shortcut, action));
+        Ok(())
+    }
+
+    /// The action a key event triggers, if any.
+    pub fn action(&self, event: &KeyboardEvent) -> Option<ToolbarAction> {
+        self.bindings
+            .iter()
+            .find(|(shortcut, _)| shortcut.matches(event, self.command_is_meta))
+            .map(|&(_, action)| action)
+    }
+
+    /// The first shortcut bound to an action.
+    pub fn shortcut(&self, action: ToolbarAction) -> Option<Shortcut> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|&(shortcut, _)| shortcut)
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn same_modifiers(a: &KeyModifiers, b: &KeyModifiers) -> bool {
+    (a.shift, a.control, a.alt, a.meta) == (b.shift, b.control, b.alt, b.meta)
+}
+
+/// The key a shortcut names, ignoring case.
+fn parse_key(name: &str) -> Option<Key> {
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Digit0,
+        Key::Digit1,
+        Key::Digit2,
+        Key::Digit3,
+        Key::Digit4,
+        Key::Digit5,
+        Key::Digit6,
+        Key::Digit7,
+        Key::Digit8,
+        Key::Digit9,
+    ];
+    const FUNCTION: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+
+    let name = name.to_ascii_lowercase();
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'a'..='z' => Some(LETTERS[(c as u8 - b'a') as usize]),
+            '0'..='9' => Some(DIGITS[(c as u8 - b'0') as usize]),
+            '-' => Some(Key::Minus),
+            '=' => Some(Key::Equal),
+            '[' => Some(Key::BracketLeft),
+            ']' => Some(Key::BracketRight),
+            '\\' => Some(Key::Backslash),
+            ';' => Some(Key::Semicolon),
+            '\'' => Some(Key::Quote),
+            '`' => Some(Key::Backquote),
+            ',' => Some(Key::Comma),
+            '.' => Some(Key::Period),
+            '/' => Some(Key::Slash),
+            _ => None,
+        };
+    }
+    if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        return FUNCTION.get(number.checked_sub(1)?).copied();
+    }
+    Some(match name.as_str() {
+        "enter" | "return" => Key::Enter,
+        "esc" | "escape" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "tab" => Key::Tab,
+        "space" => Key::Space,
+        "plus" => Key::Equal,
+        "insert" | "ins" => Key::Insert,
+        "delete" | "del" => Key::Delete,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" | "arrowup" => Key::ArrowUp,
+        "down" | "arrowdown" => Key::ArrowDown,
+        "left" | "arrowleft" => Key::ArrowLeft,
+        "right" | "arrowright" => Key::ArrowRight,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: Key, modifiers: KeyModifiers) -> KeyboardEvent {
+        KeyboardEvent::new(key, true, modifiers)
+    }
+
+    fn held(shift: bool, control: bool, alt: bool, meta: bool) -> KeyModifiers {
+        KeyModifiers {
+            shift,
+            control,
+            alt,
+            meta,
+        }
+    }
+
+    #[test]
+    fn test_parse_and_match_chord() {
+        let shortcut = Shortcut::parse("Ctrl+Shift+X").unwrap();
+        assert_eq!(shortcut.key, Key::X);
+        assert!(shortcut.command && shortcut.shift && !shortcut.alt && !shortcut.meta);
+        assert_eq!(shortcut.to_string(), "Ctrl+Shift+X");
+        assert_eq!("shift + ctrl + x".parse::<Shortcut>(), Ok(shortcut));
+
+        // Ctrl on Linux and Windows, Cmd on macOS.
+        assert!(shortcut.matches(&press(Key::X, held(true, true, false, false)), false));
+        assert!(shortcut.matches(&press(Key::X, held(true, false, false, true)), true));
+        assert!(!shortcut.matches(&press(Key::X, held(true, true, false, false)), true));
+        // Exactly these modifiers, on a press.
+        assert!(!shortcut.matches(&press(Key::X, held(false, true, false, false)), false));
+        assert!(!shortcut.matches(&press(Key::X, held(true, true, true, false)), false));
+        assert!(!shortcut.matches(
+            &KeyboardEvent::new(Key::X, false, held(true, true, false, false)),
+            false
+        ));
+
+        let mut shortcuts = Shortcuts::new().with_command_is_meta(false);
+        shortcuts
+            .bind("Ctrl+Shift+X", ToolbarAction::Strikethrough)
+            .unwrap();
+        shortcuts.bind("Ctrl+X", ToolbarAction::Cut).unwrap();
+        let ctrl = held(false, true, false, false);
+        let ctrl_shift = held(true, true, false, false);
+        assert_eq!(
+            shortcuts.action(&press(Key::X, ctrl_shift)),
+            Some(ToolbarAction::Strikethrough)
+        );
+        assert_eq!(
+            shortcuts.action(&press(Key::X, ctrl)),
+            Some(ToolbarAction::Cut)
+        );
+        assert_eq!(shortcuts.action(&press(Key::X, KeyModifiers::new())), None);
+    }
+
+    #[test]
+    fn test_invalid_shortcuts() {
+        for text in [
+            "",
+            "Ctrl+",
+            "Ctrl+Shift",
+            "Hyper+X",
+            "Ctrl+Ctrl+X",
+            "F13",
+            "XY",
+        ] {
+            assert_eq!(
+                Shortcut::parse(text),
+                Err(ShortcutError::Invalid(text.to_string())),
+                "{text}"
+            );
+        }
+        assert_eq!(Shortcut::parse("F5").unwrap().key, Key::F5);
+        assert_eq!(Shortcut::parse("Ctrl+Enter").unwrap().key, Key::Enter);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.bind("Ctrl+B", ToolbarAction::Bold).unwrap();
+        // Binding the same action again is harmless, and an action may
+        // have more than one shortcut.
+        shortcuts.bind("ctrl+b", ToolbarAction::Bold).unwrap();
+        shortcuts.bind("Ctrl+Y", ToolbarAction::Redo).unwrap();
+        shortcuts.bind("Ctrl+Shift+Z", ToolbarAction::Redo).unwrap();
+
+        let conflict = shortcuts.bind("Cmd+B", ToolbarAction::InsertTable);
+        assert_eq!(
+            conflict,
+            Err(ShortcutError::Conflict {
+                shortcut: Shortcut::parse("Ctrl+B").unwrap(),
+                existing: ToolbarAction::Bold,
+                action: ToolbarAction::InsertTable,
+            })
+        );
+        // Meta+B is Cmd+B on macOS.
+        assert!(matches!(
+            shortcuts.bind("Meta+B", ToolbarAction::BulletList),
+            Err(ShortcutError::Conflict { .. })
+        ));
+        // The failed bindings left the registry as it was.
+        let ctrl = held(false, true, false, false);
+        let shortcuts = shortcuts.with_command_is_meta(false);
+        assert_eq!(
+            shortcuts.action(&press(Key::B, ctrl)),
+            Some(ToolbarAction::Bold)
+        );
+    }
+
+    #[test]
+    fn test_toolbar_shortcuts() {
+        let toolbar = Toolbar::new();
+        let shortcuts = Shortcuts::for_toolbar(&toolbar);
+        // Every button's shortcut is bound without conflict.
+        for button in toolbar.all_buttons() {
+            let Some(text) = &button.shortcut else {
+                continue;
+            };
+            let action = ToolbarAction::for_button(&button.id).unwrap();
+            assert_eq!(
+                shortcuts.shortcut(action),
+                Shortcut::parse(text).ok(),
+                "{}",
+                button.id
+            );
+        }
+        assert_eq!(
+            shortcuts.shortcut(ToolbarAction::SaveAs),
+            Shortcut::parse("Ctrl+Shift+S").ok()
+        );
+    }
+}
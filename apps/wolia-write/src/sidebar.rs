@@ -1,6 +1,9 @@
 //! Sidebar component for document navigation and structure.
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+
+use uuid::Uuid;
+use wolia_core::outline::{self, FlatEntry, OutlineEntry};
 
 /// Sidebar panel type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -27,53 +30,15 @@ impl SidebarPanel {
     }
 }
 
-/// Outline item representing a heading or section.
-#[derive(Debug, Clone)]
-pub struct OutlineItem {
-    /// Item title/text.
-    pub title: String,
-    /// Heading level (1-6).
-    pub level: usize,
-    /// Line number in document.
-    pub line: usize,
-    /// Character offset in document.
-    pub offset: usize,
-    /// Child items for nested headings.
-    pub children: Vec<OutlineItem>,
-}
-
-impl OutlineItem {
-    /// Create a new outline item.
-    pub fn new(title: impl Into<String>, level: usize, line: usize, offset: usize) -> Self {
-        Self {
-            title: title.into(),
-            level,
-            line,
-            offset,
-            children: Vec::new(),
-        }
-    }
-
-    /// Add a child item.
-    pub fn add_child(&mut self, child: OutlineItem) {
-        self.children.push(child);
-    }
-
-    /// Get indentation level for display.
-    pub fn indent_pixels(&self) -> f32 {
-        ((self.level - 1) as f32) * 16.0
-    }
-}
-
 /// Document outline/navigator.
 #[derive(Debug, Clone)]
 pub struct DocumentOutline {
-    /// Root outline items.
-    pub items: Vec<OutlineItem>,
+    /// Top-level headings, from [`Document::outline`](wolia_core::Document::outline).
+    pub items: Vec<OutlineEntry>,
     /// Currently selected item index.
     pub selected_index: Option<usize>,
-    /// Expanded items (by their offset).
-    pub expanded: std::collections::HashSet<usize>,
+    /// Expanded items, by heading node ID.
+    pub expanded: HashSet<Uuid>,
 }
 
 impl DocumentOutline {
@@ -82,12 +47,12 @@ impl DocumentOutline {
         Self {
             items: Vec::new(),
             selected_index: None,
-            expanded: std::collections::HashSet::new(),
+            expanded: HashSet::new(),
         }
     }
 
     /// Add an item to the outline.
-    pub fn add_item(&mut self, item: OutlineItem) {
+    pub fn add_item(&mut self, item: OutlineEntry) {
         self.items.push(item);
     }
 
@@ -105,41 +70,38 @@ impl DocumentOutline {
         }
     }
 
-    /// Toggle item expansion by offset.
-    pub fn toggle_item(&mut self, offset: usize) {
-        if self.expanded.contains(&offset) {
-            self.expanded.remove(&offset);
-        } else {
-            self.expanded.insert(offset);
+    /// Toggle item expansion by heading node ID.
+    pub fn toggle_item(&mut self, id: Uuid) {
+        if !self.expanded.remove(&id) {
+            self.expanded.insert(id);
         }
     }
 
-    /// Flatten outline to a list for rendering.
-    pub fn flatten(&self) -> Vec<(&OutlineItem, f32)> {
+    /// Flatten outline to a list for rendering, skipping the children of
+    /// collapsed items.
+    pub fn flatten(&self) -> Vec<(FlatEntry<'_>, f32)> {
         let mut result = Vec::new();
         let mut y = 24.0; // Start below header
+        let mut collapsed_depth = None;
 
-        for item in &self.items {
-            self.flatten_recursive(item, &mut result, &mut y);
+        for flat in outline::flatten(&self.items) {
+            match collapsed_depth {
+                Some(depth) if flat.depth > depth => continue,
+                _ => collapsed_depth = None,
+            }
+            if !self.expanded.contains(&flat.entry.id) {
+                collapsed_depth = Some(flat.depth);
+            }
+            result.push((flat, y));
+            y += 24.0;
         }
 
         result
     }
 
-    fn flatten_recursive<'a>(
-        &'a self,
-        item: &'a OutlineItem,
-        result: &mut Vec<(&'a OutlineItem, f32)>,
-        y: &mut f32,
-    ) {
-        result.push((item, *y));
-        *y += 24.0;
-
-        if self.expanded.contains(&item.offset) {
-            for child in &item.children {
-                self.flatten_recursive(child, result, y);
-            }
-        }
+    /// Indentation of a flattened item for display.
+    pub fn indent_pixels(flat: &FlatEntry<'_>) -> f32 {
+        flat.depth as f32 * 16.0
     }
 }
 
@@ -209,11 +171,20 @@ impl Sidebar {
     }
 
     /// Update the document outline from current document structure.
-    pub fn update_outline(&mut self, headings: Vec<OutlineItem>) {
+    ///
+    /// Items that were expanded stay expanded if their heading is still in
+    /// the document.
+    pub fn update_outline(&mut self, headings: Vec<OutlineEntry>) {
+        let expanded = std::mem::take(&mut self.outline.expanded);
         self.outline.clear();
         for item in headings {
             self.outline.add_item(item);
         }
+        let present: HashSet<Uuid> = outline::flatten(&self.outline.items)
+            .iter()
+            .map(|flat| flat.entry.id)
+            .collect();
+        self.outline.expanded = expanded.intersection(&present).copied().collect();
     }
 
     /// Get pixel position for outline item at flattened index.
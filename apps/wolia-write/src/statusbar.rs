@@ -1,6 +1,10 @@
 //! Status bar component for displaying document statistics and status.
 
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long after an edit the statistics are counted again, so that typing
+/// recounts them at most this often.
+pub const STATISTICS_DELAY: Duration = Duration::from_millis(500);
 
 /// Document statistics displayed in the status bar.
 #[derive(Debug, Clone, Copy)]
@@ -116,8 +120,10 @@ pub struct StatusBar {
     pub status: StatusIndicator,
     /// Document statistics.
     pub stats: DocumentStats,
-    /// Current cursor position (line, column).
+    /// Current cursor position (line on its page, column).
     pub cursor_position: (usize, usize),
+    /// Page the cursor is on, once laid out.
+    pub cursor_page: Option<usize>,
     /// File path being edited (if any).
     pub file_path: Option<String>,
     /// Last save time.
@@ -126,6 +132,10 @@ pub struct StatusBar {
     pub read_only: bool,
     /// Current zoom level (as percentage).
     pub zoom_level: u32,
+    /// Page in view and number of laid out pages, once laid out.
+    pub page: Option<(usize, usize)>,
+    /// When the statistics are next to be counted, after an edit.
+    statistics_due: Option<Instant>,
 }
 
 impl StatusBar {
@@ -137,10 +147,13 @@ impl StatusBar {
             status: StatusIndicator::Ready,
             stats: DocumentStats::new(),
             cursor_position: (1, 1),
+            cursor_page: None,
             file_path: None,
             last_save_time: None,
             read_only: false,
             zoom_level: 100,
+            page: None,
+            statistics_due: None,
         }
     }
 
@@ -154,9 +167,27 @@ impl StatusBar {
         self.status = status;
     }
 
+    /// Update the page the cursor is on.
+    pub fn set_cursor_page(&mut self, page: usize) {
+        self.cursor_page = Some(page.max(1));
+    }
+
     /// Update document statistics.
     pub fn update_statistics(&mut self, content: &str) {
         self.stats.update(content);
+        self.statistics_due = None;
+    }
+
+    /// Count the statistics again once [`STATISTICS_DELAY`] has passed
+    /// since `now`, unless a count is already due sooner.
+    pub fn schedule_statistics(&mut self, now: Instant) {
+        self.statistics_due.get_or_insert(now + STATISTICS_DELAY);
+    }
+
+    /// When the statistics are next to be counted, if an edit made them
+    /// stale.
+    pub fn statistics_due(&self) -> Option<Instant> {
+        self.statistics_due
     }
 
     /// Set file path.
@@ -177,7 +208,13 @@ impl StatusBar {
 
     /// Set zoom level (as percentage).
     pub fn set_zoom_level(&mut self, level: u32) {
-        self.zoom_level = level.clamp(50, 200);
+        self.zoom_level = level.clamp(25, 400);
+    }
+
+    /// Update the page in view out of the laid out pages.
+    pub fn set_page(&mut self, current: usize, total: usize) {
+        let total = total.max(1);
+        self.page = Some((current.clamp(1, total), total));
     }
 
     /// Get formatted status text for display.
@@ -186,6 +223,9 @@ impl StatusBar {
 
         // Add cursor position.
         let (line, col) = self.cursor_position;
+        if let Some(page) = self.cursor_page {
+            text.push_str(&format!("Page {}, ", page));
+        }
         text.push_str(&format!("Line {}, Column {} | ", line, col));
 
         // Add word count.
@@ -197,8 +237,12 @@ impl StatusBar {
             self.stats.character_count_with_spaces
         ));
 
-        // Add page count.
-        text.push_str(&format!("Pages: {:.1} | ", self.stats.page_count));
+        // Add the page in view, or the estimated page count before the
+        // document is laid out.
+        match self.page {
+            Some((current, total)) => text.push_str(&format!("Page {current} of {total} | ")),
+            None => text.push_str(&format!("Pages: {:.1} | ", self.stats.page_count)),
+        }
 
         // Add read-only indicator.
         if self.read_only {
@@ -262,13 +306,88 @@ mod tests {
         assert!(text.contains("Words: 2"));
     }
 
+    #[test]
+    fn test_page_indicator() {
+        let mut statusbar = StatusBar::new();
+        assert!(statusbar.format_status_text().contains("Pages: "));
+
+        statusbar.set_page(2, 3);
+        assert!(statusbar.format_status_text().contains("Page 2 of 3"));
+        statusbar.set_page(5, 3);
+        assert_eq!(statusbar.page, Some((3, 3)));
+    }
+
     #[test]
     fn test_zoom_level_clamp() {
         let mut statusbar = StatusBar::new();
-        statusbar.set_zoom_level(300);
-        assert_eq!(statusbar.zoom_level, 200);
+        statusbar.set_zoom_level(500);
+        assert_eq!(statusbar.zoom_level, 400);
+
+        statusbar.set_zoom_level(10);
+        assert_eq!(statusbar.zoom_level, 25);
+    }
+
+    #[test]
+    fn test_statistics_debounce() {
+        let mut statusbar = StatusBar::new();
+        let now = Instant::now();
+        assert_eq!(statusbar.statistics_due(), None);
+        statusbar.schedule_statistics(now);
+        // Later edits do not put the count off.
+        statusbar.schedule_statistics(now + Duration::from_millis(300));
+        assert_eq!(statusbar.statistics_due(), Some(now + STATISTICS_DELAY));
+        statusbar.update_statistics("one two");
+        assert_eq!(statusbar.statistics_due(), None);
+        assert_eq!(statusbar.stats.word_count, 2);
+    }
 
-        statusbar.set_zoom_level(25);
-        assert_eq!(statusbar.zoom_level, 50);
+    #[test]
+    fn test_scrolling_updates_page() {
+        use winit::event::MouseScrollDelta;
+        use wolia_core::{Document, Node, Text};
+        use wolia_math::Rect;
+
+        use crate::workspace::Workspace;
+
+        let mut document = Document::new();
+        for _ in 0..120 {
+            document
+                .root
+                .add_child(Node::paragraph(Text::new("A line of text")));
+        }
+        let mut workspace = Workspace::new(document);
+        workspace
+            .view
+            .set_viewport(Rect::new(0.0, 0.0, 800.0, 600.0));
+        workspace.update_layout();
+        let pages = workspace.view.page_count;
+        assert!(pages > 1);
+        assert_eq!(workspace.statusbar.page, Some((1, pages)));
+
+        let now = Instant::now();
+        workspace.scroll_wheel(MouseScrollDelta::LineDelta(0.0, -1000.0), now);
+        assert_eq!(workspace.view.scroll_y, workspace.view.max_scroll());
+        assert_eq!(workspace.statusbar.page, Some((pages, pages)));
+        assert!(
+            workspace
+                .statusbar
+                .format_status_text()
+                .contains(&format!("Page {pages} of {pages}"))
+        );
+
+        // Smooth scrolling shows the page reached on each frame.
+        workspace.view.smooth_scrolling = true;
+        workspace.scroll_wheel(MouseScrollDelta::LineDelta(0.0, 1000.0), now);
+        let mut frame = now;
+        while workspace.view.is_scrolling() {
+            frame += Duration::from_millis(16);
+            workspace.animate_scroll(frame);
+            assert_eq!(
+                workspace.statusbar.page,
+                Some((workspace.view.current_page(), pages))
+            );
+            assert!(frame < now + Duration::from_secs(5));
+        }
+        assert_eq!(workspace.statusbar.page, Some((1, pages)));
     }
 }
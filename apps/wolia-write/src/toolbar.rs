@@ -1,6 +1,19 @@
 //! Toolbar component for formatting and editing operations.
 
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use wolia_edit::{Key, KeyboardEvent};
+use wolia_math::{Point, Rect};
+
+/// Height of an option in an expanded dropdown.
+pub const OPTION_HEIGHT: f32 = 24.0;
+
+/// Font sizes that can be typed into the size dropdown, in points.
+const FONT_SIZES: RangeInclusive<f32> = 1.0..=1638.0;
+
+/// Longest font size that can be typed.
+const MAX_TYPED: usize = 6;
 
 /// Button state in the toolbar.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -77,6 +90,11 @@ impl FormatButton {
         self
     }
 
+    /// The button's rectangle.
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
     /// Check if a point is inside the button.
     pub fn contains_point(&self, px: f32, py: f32) -> bool {
         px >= self.x && px < self.x + self.width && py >= self.y && py < self.y + self.height
@@ -247,12 +265,19 @@ impl Toolbar {
 
     /// Initialize dropdown menus.
     fn init_dropdowns(&mut self) {
+        // Dropdowns follow the buttons.
+        let x = self
+            .all_buttons()
+            .iter()
+            .map(|button| button.x + button.width)
+            .fold(0.0, f32::max)
+            + 16.0;
         self.dropdowns.insert(
             "font".to_string(),
             DropdownMenu {
                 id: "font".to_string(),
                 label: "Font Family".to_string(),
-                x: 400.0,
+                x,
                 y: 8.0,
                 width: 120.0,
                 height: 32.0,
@@ -266,6 +291,9 @@ impl Toolbar {
                 ],
                 selected_index: 0,
                 expanded: false,
+                highlighted: None,
+                editable: false,
+                typed: None,
             },
         );
 
@@ -274,7 +302,7 @@ impl Toolbar {
             DropdownMenu {
                 id: "size".to_string(),
                 label: "Size".to_string(),
-                x: 530.0,
+                x: x + 130.0,
                 y: 8.0,
                 width: 60.0,
                 height: 32.0,
@@ -292,6 +320,9 @@ impl Toolbar {
                 ],
                 selected_index: 2, // 12pt selected by default
                 expanded: false,
+                highlighted: None,
+                editable: true,
+                typed: None,
             },
         );
     }
@@ -309,6 +340,180 @@ impl Toolbar {
             .collect()
     }
 
+    /// Hover the button under the pointer and unhover the rest, leaving
+    /// active buttons alone, and highlight the option under the pointer in
+    /// an expanded dropdown. Returns the rectangles whose look changed.
+    pub fn update_hover(&mut self, x: f32, y: f32) -> Vec<Rect> {
+        let mut changed = Vec::new();
+        if let Some(menu) = self.expanded_dropdown_mut() {
+            if let Some(index) = menu.option_at(x, y) {
+                if menu.highlighted != Some(index) {
+                    menu.highlighted = Some(index);
+                    changed.push(menu.list_bounds());
+                }
+            }
+        }
+        for button in self
+            .buttons
+            .values_mut()
+            .flat_map(|buttons| buttons.iter_mut())
+        {
+            let state = match button.state {
+                ButtonState::Normal if button.contains_point(x, y) => ButtonState::Hovered,
+                ButtonState::Hovered if !button.contains_point(x, y) => ButtonState::Normal,
+                state => state,
+            };
+            if state != button.state {
+                button.state = state;
+                changed.push(button.bounds());
+            }
+        }
+        changed
+    }
+
+    /// The button at `x`, `y`, if any.
+    pub fn button_at(&self, x: f32, y: f32) -> Option<&FormatButton> {
+        self.buttons
+            .values()
+            .flat_map(|buttons| buttons.iter())
+            .find(|button| button.contains_point(x, y))
+    }
+
+    /// The expanded dropdown, if any.
+    pub fn expanded_dropdown(&self) -> Option<&DropdownMenu> {
+        self.dropdowns.values().find(|menu| menu.expanded)
+    }
+
+    fn expanded_dropdown_mut(&mut self) -> Option<&mut DropdownMenu> {
+        self.dropdowns.values_mut().find(|menu| menu.expanded)
+    }
+
+    /// Handle a click at `x`, `y` on the dropdowns. Clicking a dropdown
+    /// expands or collapses it, clicking an option in an expanded one
+    /// chooses it, and clicking anywhere else collapses it.
+    pub fn click_dropdowns(&mut self, x: f32, y: f32) -> DropdownInput {
+        if let Some(menu) = self.expanded_dropdown_mut() {
+            let option = menu.option_at(x, y);
+            let id = menu.id.clone();
+            menu.close();
+            return match option.and_then(|index| self.choose(&id, index)) {
+                Some(choice) => DropdownInput::Chose(choice),
+                None => DropdownInput::Handled,
+            };
+        }
+        match self
+            .dropdowns
+            .values_mut()
+            .find(|menu| menu.contains_point(x, y))
+        {
+            Some(menu) => {
+                menu.open();
+                DropdownInput::Handled
+            }
+            None => DropdownInput::Ignored,
+        }
+    }
+
+    /// Handle a key while a dropdown is expanded. The arrow keys move
+    /// through the options, Enter chooses one and Escape collapses the
+    /// list. Numbers can be typed into an editable dropdown and chosen with
+    /// Enter. Keys are ignored while no dropdown is expanded.
+    pub fn dropdown_key(&mut self, event: &KeyboardEvent) -> DropdownInput {
+        let Some(menu) = self.expanded_dropdown_mut() else {
+            return DropdownInput::Ignored;
+        };
+        if !event.pressed {
+            return DropdownInput::Handled;
+        }
+        match event.key {
+            Key::ArrowDown => menu.move_highlight(1),
+            Key::ArrowUp => menu.move_highlight(-1),
+            Key::Home => menu.move_highlight(isize::MIN),
+            Key::End => menu.move_highlight(isize::MAX),
+            Key::Escape => menu.close(),
+            Key::Backspace if menu.editable => {
+                if let Some(typed) = &mut menu.typed {
+                    typed.pop();
+                }
+            }
+            Key::Enter => {
+                let id = menu.id.clone();
+                let choice = match menu.typed.take() {
+                    Some(typed) => {
+                        let size = typed.parse().ok().filter(|size| FONT_SIZES.contains(size));
+                        if size.is_none() {
+                            // Leave the list open to correct the value.
+                            return DropdownInput::Handled;
+                        }
+                        size.and_then(|size| self.choose_size(size))
+                    }
+                    None => menu.highlighted.and_then(|index| self.choose(&id, index)),
+                };
+                if let Some(menu) = self.dropdowns.get_mut(&id) {
+                    menu.close();
+                }
+                if let Some(choice) = choice {
+                    return DropdownInput::Chose(choice);
+                }
+            }
+            _ => {
+                let typed = event
+                    .char_code
+                    .filter(|c| menu.editable && (c.is_ascii_digit() || *c == '.'));
+                if let Some(c) = typed {
+                    let text = menu.typed.get_or_insert_with(String::new);
+                    if text.len() < MAX_TYPED {
+                        text.push(c);
+                    }
+                }
+            }
+        }
+        DropdownInput::Handled
+    }
+
+    /// Choose option `index` of the dropdown `id`.
+    fn choose(&mut self, id: &str, index: usize) -> Option<DropdownChoice> {
+        let option = self.dropdowns.get(id)?.options.get(index)?.clone();
+        match id {
+            "font" => {
+                self.select_font(&option);
+                Some(DropdownChoice::Font(option))
+            }
+            "size" => self.choose_size(option.parse().ok()?),
+            _ => None,
+        }
+    }
+
+    fn choose_size(&mut self, size: f32) -> Option<DropdownChoice> {
+        self.select_size(size);
+        Some(DropdownChoice::Size(size))
+    }
+
+    /// Show `family` as the selected font.
+    pub fn select_font(&mut self, family: &str) {
+        self.selected_font = family.to_string();
+        if let Some(menu) = self.dropdowns.get_mut("font") {
+            if let Some(index) = menu.options.iter().position(|option| option == family) {
+                menu.select(index);
+            }
+        }
+    }
+
+    /// Show `size` as the selected font size, which need not be one of the
+    /// listed sizes.
+    pub fn select_size(&mut self, size: f32) {
+        self.selected_size = size;
+        if let Some(menu) = self.dropdowns.get_mut("size") {
+            let listed = menu
+                .options
+                .iter()
+                .position(|option| option.parse() == Ok(size));
+            if let Some(index) = listed {
+                menu.select(index);
+            }
+        }
+    }
+
     /// Get button by ID.
     pub fn get_button(&self, id: &str) -> Option<&FormatButton> {
         for buttons in self.buttons.values() {
@@ -336,6 +541,41 @@ impl Toolbar {
         }
     }
 
+    /// Show the button `id` as active or not, leaving it alone while
+    /// disabled. Returns its rectangle if its state changed.
+    pub fn set_active(&mut self, id: &str, active: bool) -> Option<Rect> {
+        self.change_state(id, |state| match state {
+            ButtonState::Disabled => ButtonState::Disabled,
+            _ if active => ButtonState::Active,
+            ButtonState::Active => ButtonState::Normal,
+            state => state,
+        })
+    }
+
+    /// Enable or disable the button `id`. Returns its rectangle if its
+    /// state changed.
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> Option<Rect> {
+        self.change_state(id, |state| match state {
+            _ if !enabled => ButtonState::Disabled,
+            ButtonState::Disabled => ButtonState::Normal,
+            state => state,
+        })
+    }
+
+    fn change_state(
+        &mut self,
+        id: &str,
+        change: impl Fn(ButtonState) -> ButtonState,
+    ) -> Option<Rect> {
+        let button = self.get_button_mut(id)?;
+        let state = change(button.state);
+        if state == button.state {
+            return None;
+        }
+        button.state = state;
+        Some(button.bounds())
+    }
+
     /// Get active (pressed) buttons.
     pub fn active_buttons(&self) -> Vec<&FormatButton> {
         self.all_buttons()
@@ -372,6 +612,33 @@ pub struct DropdownMenu {
     pub selected_index: usize,
     /// Whether the menu is expanded.
     pub expanded: bool,
+    /// The option under the pointer or moved to with the keyboard, while
+    /// expanded.
+    pub highlighted: Option<usize>,
+    /// Whether values not in the list can be typed.
+    pub editable: bool,
+    /// The value typed so far, while expanded.
+    pub typed: Option<String>,
+}
+
+/// A value chosen in a toolbar dropdown.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropdownChoice {
+    /// A font family.
+    Font(String),
+    /// A font size in points.
+    Size(f32),
+}
+
+/// What the toolbar dropdowns did with some input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropdownInput {
+    /// The input was not for the dropdowns.
+    Ignored,
+    /// The input went to the dropdowns without choosing anything.
+    Handled,
+    /// The input chose a value.
+    Chose(DropdownChoice),
 }
 
 impl DropdownMenu {
@@ -389,7 +656,70 @@ impl DropdownMenu {
 
     /// Toggle menu expansion.
     pub fn toggle(&mut self) {
-        self.expanded = !self.expanded;
+        if self.expanded {
+            self.close();
+        } else {
+            self.open();
+        }
+    }
+
+    /// Expand the menu, highlighting the selected option.
+    pub fn open(&mut self) {
+        self.expanded = true;
+        self.highlighted = Some(self.selected_index);
+        self.typed = None;
+    }
+
+    /// Collapse the menu.
+    pub fn close(&mut self) {
+        self.expanded = false;
+        self.highlighted = None;
+        self.typed = None;
+    }
+
+    /// Move the highlight `by` options, stopping at either end.
+    pub fn move_highlight(&mut self, by: isize) {
+        let Some(last) = self.options.len().checked_sub(1) else {
+            return;
+        };
+        let from = self.highlighted.unwrap_or(self.selected_index);
+        self.highlighted = Some(from.saturating_add_signed(by).min(last));
+        self.typed = None;
+    }
+
+    /// The menu's rectangle, without its list.
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
+    /// Check if a point is inside the menu, without its list.
+    pub fn contains_point(&self, px: f32, py: f32) -> bool {
+        self.bounds().contains(Point::new(px, py))
+    }
+
+    /// The rectangle of option `index` in the expanded list.
+    pub fn option_bounds(&self, index: usize) -> Rect {
+        let y = self.y + self.height + index as f32 * OPTION_HEIGHT;
+        Rect::new(self.x, y, self.width, OPTION_HEIGHT)
+    }
+
+    /// The rectangle of the expanded list.
+    pub fn list_bounds(&self) -> Rect {
+        Rect::new(
+            self.x,
+            self.y + self.height,
+            self.width,
+            self.options.len() as f32 * OPTION_HEIGHT,
+        )
+    }
+
+    /// The option at `x`, `y` in the expanded list, if any.
+    pub fn option_at(&self, x: f32, y: f32) -> Option<usize> {
+        if !self.expanded || !self.list_bounds().contains(Point::new(x, y)) {
+            return None;
+        }
+        let index = ((y - self.y - self.height) / OPTION_HEIGHT) as usize;
+        (index < self.options.len()).then_some(index)
     }
 }
 
@@ -436,4 +766,269 @@ pub enum ToolbarAction {
     InsertTable,
     InsertLink,
     InsertPageBreak,
+
+    // View
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+}
+
+impl ToolbarAction {
+    /// The action of the toolbar button `id`.
+    pub fn for_button(id: &str) -> Option<Self> {
+        Some(match id {
+            "new" => Self::New,
+            "open" => Self::Open,
+            "save" => Self::Save,
+            "undo" => Self::Undo,
+            "redo" => Self::Redo,
+            "cut" => Self::Cut,
+            "copy" => Self::Copy,
+            "paste" => Self::Paste,
+            "bold" => Self::Bold,
+            "italic" => Self::Italic,
+            "underline" => Self::Underline,
+            "strikethrough" => Self::Strikethrough,
+            "align_left" => Self::AlignLeft,
+            "align_center" => Self::AlignCenter,
+            "align_right" => Self::AlignRight,
+            "align_justify" => Self::AlignJustify,
+            "bullet_list" => Self::BulletList,
+            "numbered_list" => Self::NumberedList,
+            "insert_image" => Self::InsertImage,
+            "insert_table" => Self::InsertTable,
+            "insert_link" => Self::InsertLink,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_math::Size;
+    use wolia_render::DamageTracker;
+
+    use super::*;
+
+    fn damage() -> DamageTracker {
+        let mut damage = DamageTracker::new(Size::new(1400.0, 900.0));
+        damage.take();
+        damage
+    }
+
+    #[test]
+    fn test_hover_damages_only_that_button() {
+        let mut toolbar = Toolbar::new();
+        let mut damage = damage();
+        let undo = toolbar.get_button("undo").unwrap().bounds();
+
+        for rect in toolbar.update_hover(undo.x + 4.0, undo.y + 4.0) {
+            damage.add(rect);
+        }
+        assert_eq!(
+            toolbar.get_button("undo").unwrap().state,
+            ButtonState::Hovered
+        );
+        assert_eq!(damage.take(), vec![undo]);
+
+        // Moving within the button is an idle frame.
+        for rect in toolbar.update_hover(undo.x + 8.0, undo.y + 8.0) {
+            damage.add(rect);
+        }
+        assert!(!damage.is_dirty());
+
+        // Leaving it damages it again, and nothing else.
+        for rect in toolbar.update_hover(-10.0, -10.0) {
+            damage.add(rect);
+        }
+        assert_eq!(damage.take(), vec![undo]);
+    }
+
+    #[test]
+    fn test_hover_keeps_active_buttons() {
+        let mut toolbar = Toolbar::new();
+        toolbar.set_button_state("bold", ButtonState::Active);
+        let bold = toolbar.get_button("bold").unwrap().bounds();
+        assert!(toolbar.update_hover(bold.x + 1.0, bold.y + 1.0).is_empty());
+        assert_eq!(
+            toolbar.get_button("bold").unwrap().state,
+            ButtonState::Active
+        );
+    }
+
+    #[test]
+    fn test_button_actions() {
+        let toolbar = Toolbar::new();
+        for button in toolbar.all_buttons() {
+            assert!(
+                ToolbarAction::for_button(&button.id).is_some(),
+                "{} has no action",
+                button.id
+            );
+        }
+        assert_eq!(ToolbarAction::for_button("save"), Some(ToolbarAction::Save));
+        assert_eq!(ToolbarAction::for_button("bold"), Some(ToolbarAction::Bold));
+        assert_eq!(
+            ToolbarAction::for_button("align_justify"),
+            Some(ToolbarAction::AlignJustify)
+        );
+        assert_eq!(ToolbarAction::for_button("missing"), None);
+
+        let save = toolbar.get_button("save").unwrap().bounds();
+        let clicked = toolbar.button_at(save.x + 1.0, save.y + 1.0).unwrap();
+        assert_eq!(clicked.id, "save");
+        assert!(
+            toolbar
+                .button_at(save.x + save.width + 1.0, save.y)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_active_and_disabled_states() {
+        let mut toolbar = Toolbar::new();
+        let undo = toolbar.get_button("undo").unwrap().bounds();
+        assert_eq!(toolbar.set_enabled("undo", false), Some(undo));
+        assert_eq!(toolbar.set_enabled("undo", false), None);
+        // Disabled buttons neither hover nor become active.
+        assert!(toolbar.update_hover(undo.x + 1.0, undo.y + 1.0).is_empty());
+        assert_eq!(toolbar.set_active("undo", true), None);
+        toolbar.set_enabled("undo", true);
+        assert_eq!(
+            toolbar.get_button("undo").unwrap().state,
+            ButtonState::Normal
+        );
+
+        let bold = toolbar.get_button("bold").unwrap().bounds();
+        toolbar.update_hover(bold.x + 1.0, bold.y + 1.0);
+        assert_eq!(toolbar.set_active("bold", false), None);
+        assert_eq!(toolbar.set_active("bold", true), Some(bold));
+        assert_eq!(toolbar.set_active("bold", false), Some(bold));
+        assert_eq!(toolbar.active_buttons().len(), 0);
+    }
+
+    fn key(key: Key, c: Option<char>) -> KeyboardEvent {
+        let event = KeyboardEvent::new(key, true, Default::default());
+        match c {
+            Some(c) => event.with_char(c),
+            None => event,
+        }
+    }
+
+    #[test]
+    fn test_click_selects_dropdown_option() {
+        let mut toolbar = Toolbar::new();
+        let font = toolbar.dropdowns["font"].bounds();
+        let last_button = toolbar.get_button("insert_link").unwrap().bounds();
+        assert!(font.x > last_button.x + last_button.width);
+
+        assert_eq!(
+            toolbar.click_dropdowns(font.x + 1.0, font.y + 1.0),
+            DropdownInput::Handled
+        );
+        let option = toolbar.dropdowns["font"].option_bounds(2);
+        assert_eq!(toolbar.expanded_dropdown().unwrap().id, "font");
+        assert_eq!(
+            toolbar.update_hover(option.x + 1.0, option.y + 1.0),
+            vec![toolbar.dropdowns["font"].list_bounds()]
+        );
+        assert_eq!(toolbar.dropdowns["font"].highlighted, Some(2));
+
+        assert_eq!(
+            toolbar.click_dropdowns(option.x + 1.0, option.y + 1.0),
+            DropdownInput::Chose(DropdownChoice::Font("Courier New".to_string()))
+        );
+        assert_eq!(toolbar.selected_font, "Courier New");
+        assert_eq!(
+            toolbar.dropdowns["font"].selected_option(),
+            Some("Courier New")
+        );
+        assert!(toolbar.expanded_dropdown().is_none());
+
+        // Clicking outside an expanded list collapses it, choosing nothing.
+        toolbar.click_dropdowns(font.x + 1.0, font.y + 1.0);
+        assert_eq!(
+            toolbar.click_dropdowns(700.0, 500.0),
+            DropdownInput::Handled
+        );
+        assert!(toolbar.expanded_dropdown().is_none());
+        assert_eq!(toolbar.selected_font, "Courier New");
+        assert_eq!(
+            toolbar.click_dropdowns(700.0, 500.0),
+            DropdownInput::Ignored
+        );
+    }
+
+    #[test]
+    fn test_dropdown_keyboard_navigation() {
+        let mut toolbar = Toolbar::new();
+        assert_eq!(
+            toolbar.dropdown_key(&key(Key::ArrowDown, None)),
+            DropdownInput::Ignored
+        );
+        toolbar.dropdowns.get_mut("size").unwrap().open();
+        toolbar.dropdown_key(&key(Key::ArrowDown, None));
+        toolbar.dropdown_key(&key(Key::ArrowDown, None));
+        toolbar.dropdown_key(&key(Key::ArrowUp, None));
+        assert_eq!(toolbar.dropdowns["size"].highlighted, Some(3));
+        toolbar.dropdown_key(&key(Key::End, None));
+        toolbar.dropdown_key(&key(Key::ArrowDown, None));
+        assert_eq!(toolbar.dropdowns["size"].highlighted, Some(9));
+        toolbar.dropdown_key(&key(Key::Home, None));
+        toolbar.dropdown_key(&key(Key::ArrowDown, None));
+        assert_eq!(
+            toolbar.dropdown_key(&key(Key::Enter, None)),
+            DropdownInput::Chose(DropdownChoice::Size(10.0))
+        );
+        assert_eq!(toolbar.selected_size, 10.0);
+        assert!(toolbar.expanded_dropdown().is_none());
+
+        // Escape collapses without choosing.
+        toolbar.dropdowns.get_mut("font").unwrap().open();
+        toolbar.dropdown_key(&key(Key::ArrowDown, None));
+        toolbar.dropdown_key(&key(Key::Escape, None));
+        assert!(toolbar.expanded_dropdown().is_none());
+        assert_eq!(toolbar.selected_font, "Arial");
+    }
+
+    #[test]
+    fn test_typed_font_size() {
+        let mut toolbar = Toolbar::new();
+        toolbar.dropdowns.get_mut("size").unwrap().open();
+        for (k, c) in [(Key::Digit1, '1'), (Key::Digit1, '1'), (Key::Period, '.')] {
+            toolbar.dropdown_key(&key(k, Some(c)));
+        }
+        toolbar.dropdown_key(&key(Key::A, Some('a')));
+        toolbar.dropdown_key(&key(Key::Digit5, Some('5')));
+        assert_eq!(toolbar.dropdowns["size"].typed.as_deref(), Some("11.5"));
+        assert_eq!(
+            toolbar.dropdown_key(&key(Key::Enter, None)),
+            DropdownInput::Chose(DropdownChoice::Size(11.5))
+        );
+        assert_eq!(toolbar.selected_size, 11.5);
+        // The list keeps its last listed choice.
+        assert_eq!(toolbar.dropdowns["size"].selected_option(), Some("12"));
+
+        // Sizes out of range keep the list open to be corrected.
+        toolbar.dropdowns.get_mut("size").unwrap().open();
+        toolbar.dropdown_key(&key(Key::Digit0, Some('0')));
+        assert_eq!(
+            toolbar.dropdown_key(&key(Key::Enter, None)),
+            DropdownInput::Handled
+        );
+        assert!(toolbar.expanded_dropdown().is_some());
+        toolbar.dropdown_key(&key(Key::Backspace, None));
+        toolbar.dropdown_key(&key(Key::Digit2, Some('2')));
+        toolbar.dropdown_key(&key(Key::Digit4, Some('4')));
+        assert_eq!(
+            toolbar.dropdown_key(&key(Key::Enter, None)),
+            DropdownInput::Chose(DropdownChoice::Size(24.0))
+        );
+        assert_eq!(toolbar.dropdowns["size"].selected_option(), Some("24"));
+
+        // The font list takes no typing.
+        toolbar.dropdowns.get_mut("font").unwrap().open();
+        toolbar.dropdown_key(&key(Key::Digit1, Some('1')));
+        assert_eq!(toolbar.dropdowns["font"].typed, None);
+    }
 }
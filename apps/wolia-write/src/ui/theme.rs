@@ -66,30 +66,30 @@ impl ThemeColors {
     /// Light theme colors.
     pub fn light() -> Self {
         Self {
-            background: Color::from_rgba8(255, 255, 255, 255),
-            foreground: Color::from_rgba8(33, 33, 33, 255),
-            primary: Color::from_rgba8(25, 118, 210, 255),
-            secondary: Color::from_rgba8(156, 39, 176, 255),
-            border: Color::from_rgba8(224, 224, 224, 255),
-            selection: Color::from_rgba8(25, 118, 210, 64),
-            error: Color::from_rgba8(211, 47, 47, 255),
-            warning: Color::from_rgba8(245, 124, 0, 255),
-            success: Color::from_rgba8(56, 142, 60, 255),
+            background: Color::from_srgb8(255, 255, 255, 255),
+            foreground: Color::from_srgb8(33, 33, 33, 255),
+            primary: Color::from_srgb8(25, 118, 210, 255),
+            secondary: Color::from_srgb8(156, 39, 176, 255),
+            border: Color::from_srgb8(224, 224, 224, 255),
+            selection: Color::from_srgb8(25, 118, 210, 64),
+            error: Color::from_srgb8(211, 47, 47, 255),
+            warning: Color::from_srgb8(245, 124, 0, 255),
+            success: Color::from_srgb8(56, 142, 60, 255),
         }
     }
 
     /// Dark theme colors.
     pub fn dark() -> Self {
         Self {
-            background: Color::from_rgba8(30, 30, 30, 255),
-            foreground: Color::from_rgba8(212, 212, 212, 255),
-            primary: Color::from_rgba8(100, 181, 246, 255),
-            secondary: Color::from_rgba8(206, 147, 216, 255),
-            border: Color::from_rgba8(66, 66, 66, 255),
-            selection: Color::from_rgba8(100, 181, 246, 64),
-            error: Color::from_rgba8(239, 83, 80, 255),
-            warning: Color::from_rgba8(255, 167, 38, 255),
-            success: Color::from_rgba8(102, 187, 106, 255),
+            background: Color::from_srgb8(30, 30, 30, 255),
+            foreground: Color::from_srgb8(212, 212, 212, 255),
+            primary: Color::from_srgb8(100, 181, 246, 255),
+            secondary: Color::from_srgb8(206, 147, 216, 255),
+            border: Color::from_srgb8(66, 66, 66, 255),
+            selection: Color::from_srgb8(100, 181, 246, 64),
+            error: Color::from_srgb8(239, 83, 80, 255),
+            warning: Color::from_srgb8(255, 167, 38, 255),
+            success: Color::from_srgb8(102, 187, 106, 255),
         }
     }
 }
@@ -1,50 +1,184 @@
 //! Document workspace with integrated UI components.
 
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+use winit::event::MouseScrollDelta;
 use wolia_core::Document;
-use wolia_edit::EditSession;
-use wolia_format::{DocumentReader, DocumentWriter};
+use wolia_core::node::{Node, NodeKind};
+use wolia_core::style::{Alignment, ParagraphStyle, RevisionKind, TextStyle};
+use wolia_edit::document;
+use wolia_edit::format::{self, FormatChange};
+use wolia_edit::input::ImeEvent;
+use wolia_edit::{
+    Autosave, Clipboard, EditSession, Editor, KeyboardEvent, RecoveryFile, Selection,
+};
 use wolia_layout::{LayoutEngine, LayoutTree};
+use wolia_math::{Point, Rect};
+use wolia_platform::dialog::{self, PendingFile, RecoveryChoice, UnsavedChanges};
 
+use crate::clipboard::SystemClipboard;
+use crate::editor::Editor as EditorView;
+use crate::images::{self, DocumentImages};
+use crate::ruler::{MarkerPositions, Ruler, RulerChange};
+use crate::shortcuts::Shortcuts;
 use crate::sidebar::Sidebar;
 use crate::statusbar::StatusBar;
-use crate::toolbar::Toolbar;
+use crate::toolbar::{DropdownChoice, Toolbar, ToolbarAction};
+
+/// Factor each zoom in or out step changes the zoom by.
+const ZOOM_STEP: f32 = 1.1;
+
+/// Extensions of the files documents are opened from and saved to. The
+/// workspace keeps a document's images with it, which only the native
+/// package format holds.
+const DOCUMENT_EXTENSIONS: &[&str] = &["wolia"];
+
+/// Rows and columns of a table inserted from the toolbar.
+const NEW_TABLE_SIZE: (usize, usize) = (2, 2);
+
+/// Whether a text style has some formatting.
+type StyleTest = fn(&TextStyle) -> bool;
+
+/// Toolbar buttons that show whether the selected text has a style.
+const STYLE_BUTTONS: &[(&str, StyleTest)] = &[
+    ("bold", format::is_bold),
+    ("italic", format::is_italic),
+    ("underline", format::is_underlined),
+    ("strikethrough", format::is_struck),
+];
+
+/// Toolbar buttons that align paragraphs, and show the alignment of the
+/// paragraph holding the cursor.
+const ALIGN_BUTTONS: &[(&str, Alignment)] = &[
+    ("align_left", Alignment::Left),
+    ("align_center", Alignment::Center),
+    ("align_right", Alignment::Right),
+    ("align_justify", Alignment::Justify),
+];
+
+/// Toolbar buttons for actions the editor cannot do yet, shown disabled.
+/// Lists are laid out as plain paragraphs, without markers.
+const UNAVAILABLE_BUTTONS: &[&str] = &["bullet_list", "numbered_list"];
+
+/// A ruler marker being dragged, with what it changes.
+struct RulerDrag {
+    /// The markers when the drag started.
+    markers: MarkerPositions,
+    /// The text whose paragraphs are indented.
+    selection: Selection,
+    /// Paragraph formatting of the selected blocks when the drag started.
+    styles: Vec<ParagraphStyle>,
+    /// The change the marker makes where it is.
+    change: Option<RulerChange>,
+}
+
+impl RulerDrag {
+    /// The range of the editor's text whose paragraphs are indented.
+    fn range(&self) -> Range<usize> {
+        let selection = &self.selection;
+        selection.start.min(selection.end)..selection.start.max(selection.end)
+    }
+}
+
+/// What the answer to an open file dialog is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileRequest {
+    /// A document to open in place of the current one.
+    Open,
+    /// Where to save the document, and what to do once it is saved.
+    Save(AfterSave),
+    /// An image to insert at the cursor.
+    InsertImage,
+}
+
+/// What was waiting on the document being saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AfterSave {
+    /// Nothing: the user asked to save.
+    Nothing,
+    /// Starting a new document.
+    New,
+    /// Opening another document.
+    Open,
+    /// Closing the window.
+    Close,
+}
 
 /// A document workspace containing the document and editing state with UI components.
 pub struct Workspace {
-    /// The document being edited.
-    pub document: Document,
+    /// The editor, which owns the document being edited.
+    pub editor: Editor,
+    /// Viewport, scroll and zoom of the document.
+    pub view: EditorView,
     /// Edit session (cursor, history, etc.).
     pub session: EditSession,
     /// Layout engine.
     pub layout_engine: LayoutEngine,
     /// Cached layout.
     pub layout: Option<LayoutTree>,
+    /// Images the document embeds.
+    pub images: DocumentImages,
     /// Whether the document has unsaved changes.
     pub dirty: bool,
     /// File path (if saved).
     pub file_path: Option<std::path::PathBuf>,
     /// Toolbar component.
     pub toolbar: Toolbar,
+    /// Keyboard shortcuts for toolbar actions.
+    pub shortcuts: Shortcuts,
     /// Sidebar component.
     pub sidebar: Sidebar,
     /// Status bar component.
     pub statusbar: StatusBar,
+    /// Ruler above the pages.
+    pub ruler: Ruler,
+    /// The marker being dragged along the ruler.
+    ruler_drag: Option<RulerDrag>,
+    /// The system clipboard.
+    clipboard: SystemClipboard,
+    /// Where a mouse selection started, while dragging.
+    drag_anchor: Option<usize>,
+    /// Writer of recovery copies, if autosave is on.
+    autosave: Option<Autosave>,
+    /// The file dialog waiting to be answered, with what it is for.
+    dialog: Option<(PendingFile, FileRequest)>,
+    /// Whether the document was saved on the way to closing the window.
+    close_ready: bool,
 }
 
 impl Workspace {
     /// Create a new workspace with a document.
     pub fn new(document: Document) -> Self {
-        Self {
-            document,
+        let toolbar = Toolbar::new();
+        let mut workspace = Self {
+            editor: Editor::with_document(document),
+            view: EditorView::new(),
             session: EditSession::new(),
             layout_engine: LayoutEngine::new(),
             layout: None,
+            images: DocumentImages::new(),
             dirty: false,
             file_path: None,
-            toolbar: Toolbar::new(),
+            shortcuts: Shortcuts::for_toolbar(&toolbar),
+            toolbar,
             sidebar: Sidebar::new(),
             statusbar: StatusBar::new(),
-        }
+            ruler: Ruler::default(),
+            ruler_drag: None,
+            clipboard: SystemClipboard::new(),
+            drag_anchor: None,
+            autosave: None,
+            dialog: None,
+            close_ready: false,
+        };
+        workspace.sync_toolbar();
+        workspace.update_ui_from_document();
+        workspace
     }
 
     /// Create a workspace from a file.
@@ -52,13 +186,12 @@ impl Workspace {
         let path = path.as_ref();
         let data = std::fs::read(path)?;
 
-        // Detect format and read document
-        let document = wolia_format::WoliaFormat
-            .read(&data)
+        let package = format_wolia::read_package(&data)
             .map_err(|e| anyhow::anyhow!("Failed to read document: {}", e))?;
 
-        let mut workspace = Self::new(document);
+        let mut workspace = Self::new(package.document);
         workspace.file_path = Some(path.to_path_buf());
+        workspace.set_images(DocumentImages::from_assets(package.assets));
 
         // Update UI with document info.
         workspace.update_ui_from_document();
@@ -73,27 +206,362 @@ impl Workspace {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("No file path set"))?;
 
-        let data = wolia_format::WoliaFormat
-            .write(&self.document)
+        let data = format_wolia::write_package(&self.images.package(&self.editor.document))
             .map_err(|e| anyhow::anyhow!("Failed to write document: {}", e))?;
 
-        std::fs::write(path, data)?;
+        // Written beside the file and renamed over it, so that a failed
+        // save leaves the file as it was.
+        document::write_atomic(path, |file| file.write_all(&data))?;
         self.dirty = false;
+        self.editor.mark_saved();
         self.statusbar.mark_saved();
 
+        // The saved file supersedes the recovery copy.
+        self.discard_recovery_copy();
+
         Ok(())
     }
 
+    /// Offer each recovery copy in `dir`, left by sessions that ended without
+    /// saving, newest first, and open the first one `ask` chooses to restore
+    /// as unsaved changes to the file it is a copy of.
+    ///
+    /// Copies `ask` discards are deleted. The rest stay: the restored one
+    /// until the document is saved or its changes discarded, and the others,
+    /// including any after the restored one, which are not offered, until
+    /// the next start.
+    pub fn recover(
+        dir: impl AsRef<Path>,
+        mut ask: impl FnMut(&RecoveryFile) -> RecoveryChoice,
+    ) -> Option<Self> {
+        let files = match RecoveryFile::list(dir) {
+            Ok(files) => files,
+            Err(e) => {
+                tracing::error!("Listing recovery copies failed: {}", e);
+                return None;
+            }
+        };
+        for file in files {
+            match ask(&file) {
+                RecoveryChoice::Restore => match Self::restore(&file) {
+                    Ok(workspace) => return Some(workspace),
+                    Err(e) => {
+                        tracing::error!("Recovering {} failed: {}", file.path.display(), e);
+                    }
+                },
+                RecoveryChoice::Discard => {
+                    let path = file.path.clone();
+                    if let Err(e) = file.discard() {
+                        tracing::error!("Discarding {} failed: {}", path.display(), e);
+                    }
+                }
+                RecoveryChoice::Later => {}
+            }
+        }
+        None
+    }
+
+    /// Open the recovery copy `file` as unsaved changes to the file it is a
+    /// copy of.
+    fn restore(file: &RecoveryFile) -> anyhow::Result<Self> {
+        let package = file.load_package()?;
+        let mut workspace = Self::new(package.document);
+        workspace.set_images(DocumentImages::from_assets(package.assets));
+        workspace.file_path = file.original.clone();
+        workspace.mark_modified();
+        workspace.update_ui_from_document();
+        Ok(workspace)
+    }
+
+    /// Write recovery copies into `dir` every `interval` while the document
+    /// has unsaved changes.
+    pub fn enable_autosave(&mut self, dir: impl Into<std::path::PathBuf>, interval: Duration) {
+        self.autosave = Some(Autosave::new(dir, interval));
+    }
+
+    /// Queue a recovery copy if the document has unsaved changes and the
+    /// autosave interval has passed at `now`. The copy is written in the
+    /// background. Returns whether a copy was queued.
+    pub fn autosave_if_due(&mut self, now: Instant) -> bool {
+        let due = self
+            .autosave
+            .as_ref()
+            .is_some_and(|autosave| autosave.is_due(now));
+        if !due || !self.dirty {
+            return false;
+        }
+        let (key, title) = (self.recovery_key(), self.suggested_file_name());
+        let package = self.images.package(&self.editor.document);
+        let Some(autosave) = &mut self.autosave else {
+            return false;
+        };
+        autosave.write(key, package, title, self.file_path.clone());
+        if let Some(e) = autosave.take_error() {
+            tracing::error!("Autosave failed: {}", e);
+        }
+        true
+    }
+
+    /// Remove the document's recovery copy, once its changes are saved or
+    /// discarded.
+    fn discard_recovery_copy(&mut self) {
+        let key = self.recovery_key();
+        if let Some(autosave) = &mut self.autosave {
+            autosave.remove(key);
+        }
+    }
+
+    /// Key of the document's recovery copy.
+    fn recovery_key(&self) -> String {
+        self.editor.document.id.to_string()
+    }
+
     /// Save document to a new path.
     pub fn save_to_path(&mut self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
         self.file_path = Some(path.as_ref().to_path_buf());
         self.save()
     }
 
+    /// Use `images` for the document's images, laying them out at their
+    /// natural sizes.
+    fn set_images(&mut self, images: DocumentImages) {
+        for (src, size) in images.sizes() {
+            self.layout_engine.set_image_size(src, size);
+        }
+        self.images = images;
+        self.layout = None;
+    }
+
+    /// Embed an encoded image and insert it at the cursor, laid out at its
+    /// natural size scaled to fit the width of the page.
+    pub fn insert_image(&mut self, data: Vec<u8>, alt: Option<String>) -> anyhow::Result<()> {
+        let src = self.images.add(data)?;
+        if let Some((_, size)) = self.images.sizes().find(|(other, _)| *other == src) {
+            self.layout_engine.set_image_size(src.as_str(), size);
+        }
+        let result = self
+            .editor
+            .insert_block(Node::new(NodeKind::Image { src, alt }));
+        self.sync_modified();
+        Ok(result?)
+    }
+
+    /// Ask for an image file to insert at the cursor.
+    fn insert_image_with_dialog(&mut self) {
+        let filters = [dialog::FileFilter::new("Images", images::EXTENSIONS)];
+        self.ask_for_file(FileRequest::InsertImage, || dialog::open_file(&filters));
+    }
+
+    /// Insert the image at `path` at the cursor.
+    fn insert_image_from(&mut self, path: &Path) {
+        let alt = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        let result = std::fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| self.insert_image(data, alt));
+        if let Err(e) = result {
+            tracing::error!("Inserting image failed: {}", e);
+        }
+    }
+
+    /// Link the selected text to `url`, or remove its link if `url` is
+    /// blank. With nothing selected, the link at the cursor is changed, or
+    /// `url` inserted as a new link.
+    pub fn set_link(&mut self, url: &str) -> wolia_edit::Result<()> {
+        let url = url.trim();
+        let result = self
+            .editor
+            .set_link((!url.is_empty()).then(|| url.to_string()));
+        self.sync_modified();
+        result
+    }
+
+    /// Ask for the address to link the selection to, offering the one it
+    /// links to now. Nothing changes if the user cancels.
+    fn edit_link_with_dialog(&mut self) {
+        let current = self.editor.link();
+        let Some(url) = dialog::prompt_text(
+            "Link",
+            "Address to link to, or nothing to remove the link:",
+            current.as_deref().unwrap_or("https://"),
+        ) else {
+            return;
+        };
+        if current.is_none() && url.trim() == "https://" {
+            return;
+        }
+        if let Err(e) = self.set_link(&url) {
+            tracing::error!("Linking failed: {}", e);
+        }
+    }
+
+    /// Open the link under `point`, if there is one. Returns whether there
+    /// was.
+    pub fn open_link_at(&mut self, point: Point) -> bool {
+        let Some((_, url)) = self
+            .offset_at(point)
+            .and_then(|offset| self.editor.link_at(offset))
+        else {
+            return false;
+        };
+        if let Err(e) = wolia_platform::open_url(&url) {
+            tracing::error!("Opening link failed: {}", e);
+        }
+        true
+    }
+
+    /// Switch to `workspace`, keeping what is on the clipboard, the
+    /// autosave and any open file dialog.
+    fn replace_with(&mut self, mut workspace: Workspace) {
+        std::mem::swap(&mut workspace.clipboard, &mut self.clipboard);
+        workspace.autosave = self.autosave.take();
+        workspace.dialog = self.dialog.take();
+        *self = workspace;
+    }
+
+    /// Offer to save unsaved changes before the window is closed. Returns
+    /// whether it can be closed: the document has no unsaved changes, or
+    /// the user saved or discarded them.
+    ///
+    /// A document never saved is saved through a file dialog, which is
+    /// answered later: the window can close once
+    /// [`take_close_ready`](Self::take_close_ready) says so.
+    pub fn confirm_close(&mut self) -> bool {
+        self.confirm_discard(AfterSave::Close)
+    }
+
+    /// Offer to save unsaved changes before the document is replaced by
+    /// what `then` is for. Returns whether it can be replaced now; if the
+    /// user saves through a file dialog, `then` is done once it is saved.
+    fn confirm_discard(&mut self, then: AfterSave) -> bool {
+        if !self.dirty {
+            return true;
+        }
+        match dialog::ask_unsaved_changes(&self.suggested_file_name()) {
+            UnsavedChanges::Save if self.file_path.is_some() => {
+                self.handle_action(ToolbarAction::Save);
+                !self.dirty
+            }
+            UnsavedChanges::Save => {
+                self.save_with_dialog(then);
+                false
+            }
+            UnsavedChanges::Discard => {
+                self.discard_recovery_copy();
+                true
+            }
+            UnsavedChanges::Cancel => false,
+        }
+    }
+
+    /// The filters of the open and save dialogs.
+    fn document_filters() -> Vec<dialog::FileFilter> {
+        vec![dialog::FileFilter::new(
+            "Wolia Document",
+            DOCUMENT_EXTENSIONS,
+        )]
+    }
+
+    /// Ask for a file to open in place of the current document.
+    fn open_with_dialog(&mut self) {
+        self.ask_for_file(FileRequest::Open, || {
+            dialog::open_file(&Self::document_filters())
+        });
+    }
+
+    /// Ask where to save the document, to save it there and then do what
+    /// `then` is for.
+    fn save_with_dialog(&mut self, then: AfterSave) {
+        let name = self.suggested_file_name();
+        self.ask_for_file(FileRequest::Save(then), || {
+            dialog::save_file(&name, &Self::document_filters())
+        });
+    }
+
+    /// Show a file dialog for `request`, unless one is already open.
+    fn ask_for_file(&mut self, request: FileRequest, show: impl FnOnce() -> PendingFile) {
+        if self.dialog.is_some() {
+            tracing::debug!("A file dialog is already open");
+            return;
+        }
+        self.dialog = Some((show(), request));
+    }
+
+    /// Whether a file dialog is waiting to be answered.
+    pub fn has_open_dialog(&self) -> bool {
+        self.dialog.is_some()
+    }
+
+    /// Act on the answer to the open file dialog, if it has come. Nothing
+    /// changes if the user cancelled. Returns whether it had come.
+    pub fn poll_dialog(&mut self) -> bool {
+        let Some((pending, request)) = &self.dialog else {
+            return false;
+        };
+        let (Poll::Ready(path), request) = (pending.poll(), *request) else {
+            return false;
+        };
+        self.dialog = None;
+        let Some(path) = path else {
+            return true;
+        };
+        match request {
+            FileRequest::Open => match Self::open(&path) {
+                Ok(workspace) => self.replace_with(workspace),
+                Err(e) => tracing::error!("Open failed: {}", e),
+            },
+            FileRequest::InsertImage => self.insert_image_from(&path),
+            FileRequest::Save(then) => {
+                if let Err(e) = self.save_to_path(path) {
+                    tracing::error!("Save failed: {}", e);
+                    return true;
+                }
+                match then {
+                    AfterSave::Nothing => {}
+                    AfterSave::New => self.replace_with(Self::new(Document::new())),
+                    AfterSave::Open => self.open_with_dialog(),
+                    AfterSave::Close => self.close_ready = true,
+                }
+            }
+        }
+        true
+    }
+
+    /// Whether the document was saved on the way to closing the window,
+    /// which can now close. Each time is reported once.
+    pub fn take_close_ready(&mut self) -> bool {
+        std::mem::take(&mut self.close_ready)
+    }
+
+    /// The file name offered when saving: the current one, or one made from
+    /// the document title.
+    fn suggested_file_name(&self) -> String {
+        if let Some(name) = self.file_path.as_ref().and_then(|path| path.file_name()) {
+            return name.to_string_lossy().into_owned();
+        }
+        let title = self
+            .editor
+            .document
+            .metadata
+            .title
+            .as_deref()
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .unwrap_or("Untitled");
+        format!("{}.wolia", title)
+    }
+
     /// Update the layout.
     pub fn update_layout(&mut self) {
-        match self.layout_engine.layout(&self.document) {
-            Ok(layout) => self.layout = Some(layout),
+        match self.layout_engine.layout(&self.editor.document) {
+            Ok(layout) => {
+                self.view
+                    .set_pages(self.layout_engine.page_size, layout.pages.len());
+                self.layout = Some(layout);
+                self.update_page_indicator();
+                self.update_caret_position();
+            }
             Err(e) => tracing::error!("Layout failed: {}", e),
         }
     }
@@ -101,9 +569,7 @@ impl Workspace {
     /// Update UI components from document state.
     pub fn update_ui_from_document(&mut self) {
         // Update status bar with document statistics.
-        if let Ok(content) = self.get_document_text() {
-            self.statusbar.update_statistics(&content);
-        }
+        self.statusbar.update_statistics(&self.editor.text());
 
         // Update status bar file path.
         if let Some(path) = &self.file_path {
@@ -112,7 +578,7 @@ impl Workspace {
         }
 
         // Update sidebar with document structure.
-        self.sidebar.update_outline(vec![]);
+        self.sidebar.update_outline(self.editor.document.outline());
     }
 
     /// Mark document as modified.
@@ -122,11 +588,515 @@ impl Workspace {
             .set_status(crate::statusbar::StatusIndicator::Modified);
     }
 
-    /// Get the document text content.
-    fn get_document_text(&self) -> anyhow::Result<String> {
-        // For now, return a placeholder. In a full implementation,
-        // this would extract text from the document structure.
-        Ok(String::new())
+    /// Pass a key event to the editor.
+    pub fn handle_key(&mut self, event: KeyboardEvent) {
+        if let Err(e) = self.editor.handle_keyboard_event(event) {
+            tracing::error!("Key handling failed: {}", e);
+        }
+        self.sync_modified();
+    }
+
+    /// Pass an IME event to the editor.
+    pub fn handle_ime(&mut self, event: ImeEvent) {
+        if let Err(e) = self.editor.handle_ime_event(event) {
+            tracing::error!("IME handling failed: {}", e);
+        }
+        self.sync_modified();
+    }
+
+    /// Perform a toolbar action, such as one triggered by a shortcut.
+    pub fn handle_action(&mut self, action: ToolbarAction) {
+        let result = match action {
+            ToolbarAction::Save if self.file_path.is_some() => {
+                if let Err(e) = self.save() {
+                    tracing::error!("Save failed: {}", e);
+                }
+                Ok(())
+            }
+            ToolbarAction::New => {
+                if self.confirm_discard(AfterSave::New) {
+                    self.replace_with(Self::new(Document::new()));
+                }
+                Ok(())
+            }
+            ToolbarAction::Open => {
+                if self.confirm_discard(AfterSave::Open) {
+                    self.open_with_dialog();
+                }
+                Ok(())
+            }
+            ToolbarAction::Save | ToolbarAction::SaveAs => {
+                self.save_with_dialog(AfterSave::Nothing);
+                Ok(())
+            }
+            ToolbarAction::InsertImage => {
+                self.insert_image_with_dialog();
+                Ok(())
+            }
+            ToolbarAction::InsertLink => {
+                self.edit_link_with_dialog();
+                Ok(())
+            }
+            ToolbarAction::InsertTable => {
+                let (rows, cols) = NEW_TABLE_SIZE;
+                self.editor.insert_table(rows, cols)
+            }
+            ToolbarAction::Undo => self.editor.undo(),
+            ToolbarAction::Redo => self.editor.redo(),
+            ToolbarAction::Copy => match self.editor.copy_selection() {
+                Some(fragment) => self.clipboard.copy(&fragment),
+                None => Ok(()),
+            },
+            ToolbarAction::Cut => match self.editor.copy_selection() {
+                Some(fragment) => self
+                    .clipboard
+                    .copy(&fragment)
+                    .and_then(|()| self.editor.delete_char()),
+                None => Ok(()),
+            },
+            ToolbarAction::Paste => match self.clipboard.paste_fragment() {
+                Some(fragment) => self.editor.paste(&fragment),
+                None => Ok(()),
+            },
+            ToolbarAction::Bold => self.format_selection(FormatChange::ToggleBold),
+            ToolbarAction::Italic => self.format_selection(FormatChange::ToggleItalic),
+            ToolbarAction::Underline => self.format_selection(FormatChange::ToggleUnderline),
+            ToolbarAction::Strikethrough => {
+                self.format_selection(FormatChange::ToggleStrikethrough)
+            }
+            ToolbarAction::AlignLeft => self.align_paragraphs(Alignment::Left),
+            ToolbarAction::AlignCenter => self.align_paragraphs(Alignment::Center),
+            ToolbarAction::AlignRight => self.align_paragraphs(Alignment::Right),
+            ToolbarAction::AlignJustify => self.align_paragraphs(Alignment::Justify),
+            ToolbarAction::ZoomIn => {
+                self.set_zoom(self.view.zoom * ZOOM_STEP);
+                Ok(())
+            }
+            ToolbarAction::ZoomOut => {
+                self.set_zoom(self.view.zoom / ZOOM_STEP);
+                Ok(())
+            }
+            ToolbarAction::ResetZoom => {
+                self.set_zoom(1.0);
+                Ok(())
+            }
+            action => {
+                tracing::debug!("Action {:?} is not available yet", action);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            tracing::error!("Action {:?} failed: {}", action, e);
+        }
+        self.sync_modified();
+    }
+
+    /// Apply a font or size chosen in a toolbar dropdown to the selected
+    /// text.
+    pub fn apply_choice(&mut self, choice: DropdownChoice) {
+        let change = match choice {
+            DropdownChoice::Font(family) => FormatChange::SetFontFamily(Some(family)),
+            DropdownChoice::Size(size) => FormatChange::SetFontSize(Some(size)),
+        };
+        if let Err(e) = self.format_selection(change) {
+            tracing::error!("Formatting failed: {}", e);
+        }
+        self.sync_modified();
+    }
+
+    /// Apply `change` to the selected text, if any.
+    fn format_selection(&mut self, change: FormatChange) -> wolia_edit::Result<()> {
+        match self.editor.selection.filter(|sel| !sel.is_empty()) {
+            Some(selection) => self.editor.apply_format(selection, change),
+            None => Ok(()),
+        }
+    }
+
+    /// Align the paragraphs the selection touches, or the one holding the
+    /// cursor.
+    fn align_paragraphs(&mut self, alignment: Alignment) -> wolia_edit::Result<()> {
+        let position = self.editor.cursor.position;
+        let selection = self
+            .editor
+            .selection
+            .unwrap_or(Selection::new(position, position));
+        self.editor
+            .format_paragraphs(selection, |style| style.alignment = Some(alignment))
+    }
+
+    /// Show the editor's state on the toolbar: style buttons are active
+    /// when all of the selection has their style, the alignment of the
+    /// paragraph holding the cursor is active, and undo and redo are
+    /// disabled with nothing to undo or redo. Returns the rectangles of
+    /// buttons whose state changed.
+    pub fn sync_toolbar(&mut self) -> Vec<Rect> {
+        let mut changed = Vec::new();
+        for &(id, test) in STYLE_BUTTONS {
+            changed.extend(self.toolbar.set_active(id, self.editor.selection_has(test)));
+        }
+        let alignment = self.editor.paragraph_style().alignment.unwrap_or_default();
+        for &(id, button) in ALIGN_BUTTONS {
+            changed.extend(self.toolbar.set_active(id, button == alignment));
+        }
+        for id in UNAVAILABLE_BUTTONS {
+            changed.extend(self.toolbar.set_enabled(id, false));
+        }
+        changed.extend(
+            self.toolbar
+                .set_enabled("undo", self.editor.history.can_undo()),
+        );
+        changed.extend(
+            self.toolbar
+                .set_enabled("redo", self.editor.history.can_redo()),
+        );
+        changed
+    }
+
+    /// Lay out the document if it changed since it was last laid out.
+    pub fn ensure_layout(&mut self) {
+        if self.layout.is_none() {
+            self.update_layout();
+        }
+    }
+
+    /// Scroll the view for a mouse wheel movement at `now`.
+    pub fn scroll_wheel(&mut self, delta: MouseScrollDelta, now: Instant) {
+        self.view.scroll_wheel(delta, now);
+        self.update_page_indicator();
+    }
+
+    /// Move smooth scrolling on to `now`, returning whether the view
+    /// scrolled.
+    pub fn animate_scroll(&mut self, now: Instant) -> bool {
+        let scrolled = self.view.animate(now);
+        if scrolled {
+            self.update_page_indicator();
+        }
+        scrolled
+    }
+
+    /// Count the document statistics again if an edit made them stale
+    /// long enough before `now`, returning whether they were counted.
+    pub fn refresh_statistics(&mut self, now: Instant) -> bool {
+        let due = self
+            .statusbar
+            .statistics_due()
+            .is_some_and(|due| due <= now);
+        if due {
+            self.statusbar.update_statistics(&self.editor.text());
+        }
+        due
+    }
+
+    /// Zoom the view, showing the new zoom in the status bar.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.view.set_zoom(zoom);
+        self.statusbar
+            .set_zoom_level((self.view.zoom * 100.0).round() as u32);
+        self.update_page_indicator();
+    }
+
+    /// Show the page, line and column of the caret in the status bar, if
+    /// the document is laid out.
+    fn update_caret_position(&mut self) {
+        let Some(layout) = &self.layout else {
+            return;
+        };
+        let position = self.editor.cursor.position;
+        let caret = self
+            .block_ranges()
+            .into_iter()
+            .find(|(_, range)| range.contains(&position) || range.end == position)
+            .and_then(|(id, range)| layout.caret_position(id, position - range.start));
+        if let Some(caret) = caret {
+            self.statusbar.set_cursor_page(caret.page);
+            self.statusbar.set_cursor_position(caret.line, caret.column);
+        }
+    }
+
+    /// Show the page in the middle of the view in the status bar.
+    pub fn update_page_indicator(&mut self) {
+        self.statusbar
+            .set_page(self.view.current_page(), self.view.page_count);
+    }
+
+    /// The document text offset shown at screen position `point`, if the
+    /// document has any text there.
+    pub fn offset_at(&mut self, point: Point) -> Option<usize> {
+        self.ensure_layout();
+        let hit = self
+            .layout
+            .as_ref()?
+            .hit_test(self.view.to_document(point))?;
+        let (_, start) = wolia_edit::buffer::block_starts(&self.editor.document)
+            .into_iter()
+            .find(|(id, _)| *id == hit.source_id)?;
+        Some(start + hit.offset)
+    }
+
+    /// The screen rectangle of the caret, zero pixels wide, if the
+    /// document is laid out and the caret is on screen.
+    pub fn caret_rect(&self) -> Option<Rect> {
+        let layout = self.layout.as_ref()?;
+        let position = self.editor.cursor.position;
+        let (id, range) = self
+            .block_ranges()
+            .into_iter()
+            .find(|(_, range)| range.contains(&position) || range.end == position)?;
+        let rect = layout.caret_rect(id, position - range.start)?;
+        Some(self.view.rect_to_screen(rect))
+    }
+
+    /// Screen rectangles underlining the linked text, if the document is
+    /// laid out.
+    pub fn link_underlines(&self) -> Vec<Rect> {
+        let Some(layout) = &self.layout else {
+            return Vec::new();
+        };
+        layout
+            .link_underlines()
+            .into_iter()
+            .map(|rect| self.view.rect_to_screen(rect))
+            .collect()
+    }
+
+    /// Screen rectangles underlining the text suggested for insertion and
+    /// striking through the text suggested for deletion, with the kind of
+    /// suggestion each marks, if the document is laid out.
+    pub fn revision_marks(&self) -> Vec<(Rect, RevisionKind)> {
+        let Some(layout) = &self.layout else {
+            return Vec::new();
+        };
+        layout
+            .revision_marks()
+            .into_iter()
+            .map(|(rect, kind)| (self.view.rect_to_screen(rect), kind))
+            .collect()
+    }
+
+    /// Screen rectangles covering the selected text on every line, or the
+    /// selected table cells, if the document is laid out.
+    pub fn selection_rects(&self) -> Vec<Rect> {
+        let (Some(layout), Some(selection)) = (&self.layout, self.editor.selection) else {
+            return Vec::new();
+        };
+        if let Some(cells) = self.editor.cell_selection() {
+            return wolia_edit::table::cells(&self.editor.document)
+                .into_iter()
+                .filter(|cell| cells.contains(cell))
+                .filter_map(|cell| layout.cell_rect(cell.block))
+                .map(|rect| self.view.rect_to_screen(rect))
+                .collect();
+        }
+        let (start, end) = (
+            selection.start.min(selection.end),
+            selection.start.max(selection.end),
+        );
+        self.block_ranges()
+            .into_iter()
+            .filter(|(_, range)| range.start < end && start < range.end)
+            .flat_map(|(id, range)| {
+                let local = start.max(range.start) - range.start..end.min(range.end) - range.start;
+                layout.selection_rects(id, local)
+            })
+            .map(|rect| self.view.rect_to_screen(rect))
+            .collect()
+    }
+
+    /// The ID of every text block with the range of the editor's text it
+    /// holds. A table cell is laid out as one paragraph, so the blocks of
+    /// a cell are one range under the ID of its first.
+    fn block_ranges(&self) -> Vec<(Uuid, Range<usize>)> {
+        let document = &self.editor.document;
+        let starts = wolia_edit::buffer::block_starts(document);
+        let text_len = self.editor.text().len();
+        let cells = wolia_edit::table::cells(document);
+        starts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &(id, start))| {
+                // Blocks are separated by a line feed.
+                let end = starts
+                    .get(index + 1)
+                    .map_or(text_len, |&(_, next)| next - 1);
+                // The outermost cell the block is in, if any.
+                match cells
+                    .iter()
+                    .find(|cell| cell.range.contains(&start) || cell.range.start == start)
+                {
+                    Some(cell) if cell.block == id => Some((id, cell.range.clone())),
+                    Some(_) => None,
+                    None => Some((id, start..end)),
+                }
+            })
+            .collect()
+    }
+
+    /// Handle a mouse press at `point` that is the `clicks`th in a row:
+    /// one click places the caret and starts a selection, two select a
+    /// word and three a paragraph.
+    pub fn mouse_down(&mut self, point: Point, clicks: u32) {
+        let Some(offset) = self.offset_at(point) else {
+            return;
+        };
+        self.drag_anchor = None;
+        match clicks {
+            1 => {
+                self.editor.set_cursor(offset);
+                self.drag_anchor = Some(offset);
+            }
+            2 => self.editor.select_word_at(offset),
+            _ => self.editor.select_paragraph_at(offset),
+        }
+        self.update_caret_position();
+    }
+
+    /// Extend the selection being dragged out to `point`.
+    pub fn mouse_drag(&mut self, point: Point) {
+        let Some(anchor) = self.drag_anchor else {
+            return;
+        };
+        if let Some(offset) = self.offset_at(point) {
+            if offset == anchor {
+                self.editor.set_cursor(offset);
+            } else {
+                self.editor.select_range(anchor, offset);
+            }
+            self.update_caret_position();
+        }
+    }
+
+    /// End any selection being dragged out.
+    pub fn mouse_up(&mut self) {
+        self.drag_anchor = None;
+    }
+
+    /// The ruler markers for the page size and margins, and the paragraph
+    /// at the caret.
+    pub fn ruler_markers(&self) -> MarkerPositions {
+        MarkerPositions::new(
+            self.layout_engine.page_size.width,
+            &self.layout_engine.margins,
+            &self.editor.paragraph_style(),
+        )
+    }
+
+    /// Start dragging the ruler marker within reach of a press at screen
+    /// `x`, `y` pixels below the top of the ruler. Returns whether the
+    /// press picked up a marker.
+    pub fn ruler_down(&mut self, x: f32, y: f32) -> bool {
+        let markers = self.ruler_markers();
+        let page_x = self.view.page_rect(0).x;
+        let Some(marker) = self.ruler.marker_at(x, y, &markers, page_x, self.view.zoom) else {
+            return false;
+        };
+        let position = self.editor.cursor.position;
+        let selection = self
+            .editor
+            .selection
+            .unwrap_or(Selection::new(position, position));
+        let range = selection.start.min(selection.end)..selection.start.max(selection.end);
+        let Ok(styles) = wolia_edit::buffer::paragraph_styles(&self.editor.document, range) else {
+            return false;
+        };
+        self.ruler.start_drag(marker);
+        self.ruler_drag = Some(RulerDrag {
+            markers,
+            selection,
+            styles,
+            change: None,
+        });
+        true
+    }
+
+    /// Move the marker being dragged to screen `x`, showing the change in
+    /// the layout as it goes. Returns whether a marker is being dragged.
+    pub fn ruler_move(&mut self, x: f32) -> bool {
+        let Some(drag) = &mut self.ruler_drag else {
+            return false;
+        };
+        let page_x = self.view.page_rect(0).x;
+        let change = self.ruler.drag_to(x, &drag.markers, page_x, self.view.zoom);
+        if change.is_none() || change == drag.change {
+            return true;
+        }
+        drag.change = change;
+        let Some(change) = change else {
+            return true;
+        };
+        change.apply_margins(&mut self.layout_engine.margins);
+        if self
+            .ruler
+            .dragging()
+            .is_some_and(|marker| marker.is_indent())
+        {
+            // The preview changes the document directly; the change is
+            // made as an undoable edit on release.
+            let styles = Self::changed_styles(&drag.styles, change);
+            if let Err(e) = wolia_edit::buffer::set_paragraph_styles(
+                &mut self.editor.document,
+                drag.range(),
+                &styles,
+            ) {
+                tracing::error!("Indenting failed: {}", e);
+            }
+        }
+        self.layout = None;
+        true
+    }
+
+    /// Drop the marker being dragged, making the indent it set one undo
+    /// step. Returns whether a marker was being dragged.
+    pub fn ruler_up(&mut self) -> bool {
+        let Some(drag) = self.ruler_drag.take() else {
+            return false;
+        };
+        let marker = self.ruler.end_drag();
+        let Some(change) = drag.change else {
+            return true;
+        };
+        if marker.is_some_and(|marker| marker.is_indent()) {
+            let result = wolia_edit::buffer::set_paragraph_styles(
+                &mut self.editor.document,
+                drag.range(),
+                &drag.styles,
+            )
+            .and_then(|()| {
+                self.editor
+                    .format_paragraphs(drag.selection, |style| change.apply(style))
+            });
+            if let Err(e) = result {
+                tracing::error!("Indenting failed: {}", e);
+            }
+            self.sync_modified();
+        }
+        self.layout = None;
+        true
+    }
+
+    /// `styles` with an indent change applied to each.
+    fn changed_styles(styles: &[ParagraphStyle], change: RulerChange) -> Vec<ParagraphStyle> {
+        styles
+            .iter()
+            .cloned()
+            .map(|mut style| {
+                change.apply(&mut style);
+                style
+            })
+            .collect()
+    }
+
+    /// Mark the workspace modified once the editor has changed the
+    /// document. The layout is dropped to be redone when next needed, as
+    /// the input may have changed the document, the toolbar shows the new
+    /// state and the statistics are counted again shortly.
+    fn sync_modified(&mut self) {
+        self.layout = None;
+        self.sync_toolbar();
+        self.statusbar.schedule_statistics(Instant::now());
+        if self.editor.has_unsaved_changes() && !self.dirty {
+            self.mark_modified();
+        }
     }
 
     /// Get the document title.
@@ -160,3 +1130,500 @@ impl Workspace {
         self.statusbar.toggle();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::{Node, Text};
+    use wolia_edit::{Key, KeyModifiers};
+    use wolia_layout::LayoutContent;
+    use wolia_math::Rect;
+
+    use super::*;
+
+    /// A workspace showing one paragraph at 200%, and the screen position
+    /// of the middle of its `index`th character.
+    fn workspace_at(index: usize) -> (Workspace, Point) {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("Hello wide world")));
+        let mut workspace = Workspace::new(document);
+        workspace
+            .view
+            .set_viewport(Rect::new(100.0, 50.0, 800.0, 600.0));
+        workspace.view.set_zoom(2.0);
+        workspace.update_layout();
+
+        let node = &workspace.layout.as_ref().unwrap().pages[0].nodes[0];
+        let LayoutContent::Paragraph(paragraph) = &node.content else {
+            panic!("expected a paragraph");
+        };
+        let fragment = &paragraph.lines[0].fragments[0];
+        // Characters are an estimated 6pt wide.
+        let document_point = Point::new(
+            node.bounds.x + fragment.bounds.x + index as f32 * 6.0 + 2.0,
+            node.bounds.y + fragment.bounds.y + 2.0,
+        );
+        let screen = workspace.view.to_screen(document_point);
+        (workspace, screen)
+    }
+
+    #[test]
+    fn test_unsaved_changes_recovered() {
+        let recovery = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let (mut workspace, _) = workspace_at(0);
+        workspace.enable_autosave(&recovery, Duration::ZERO);
+        // Clean documents are not copied.
+        assert!(!workspace.autosave_if_due(Instant::now()));
+
+        let mut event = KeyboardEvent::new(Key::A, true, KeyModifiers::new());
+        event.char_code = Some('a');
+        workspace.handle_key(event);
+        assert!(workspace.autosave_if_due(Instant::now()));
+        workspace.autosave.as_ref().unwrap().flush();
+        let recovered = Workspace::recover(&recovery, |_| RecoveryChoice::Restore).unwrap();
+        assert!(recovered.dirty);
+        assert_eq!(recovered.editor.text(), workspace.editor.text());
+        assert_eq!(recovered.editor.document.id, workspace.editor.document.id);
+
+        // Saving removes the copy.
+        let path = std::env::temp_dir().join(format!("{}.wolia", Uuid::new_v4()));
+        let saved = workspace.save_to_path(&path);
+        std::fs::remove_file(&path).unwrap();
+        saved.unwrap();
+        workspace.autosave.as_ref().unwrap().flush();
+        assert!(RecoveryFile::list(&recovery).unwrap().is_empty());
+        std::fs::remove_dir_all(&recovery).unwrap();
+    }
+
+    #[test]
+    fn test_recovery_copies_offered_one_by_one() {
+        let recovery = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        for _ in 0..2 {
+            let (mut workspace, _) = workspace_at(0);
+            workspace.enable_autosave(&recovery, Duration::ZERO);
+            workspace.mark_modified();
+            assert!(workspace.autosave_if_due(Instant::now()));
+            workspace.autosave.as_ref().unwrap().flush();
+        }
+
+        // Nothing is deleted until the user decides.
+        let mut offered = 0;
+        let recovered = Workspace::recover(&recovery, |_| {
+            offered += 1;
+            RecoveryChoice::Later
+        });
+        assert!(recovered.is_none());
+        assert_eq!(offered, 2);
+        assert_eq!(RecoveryFile::list(&recovery).unwrap().len(), 2);
+
+        let mut choices = vec![RecoveryChoice::Discard, RecoveryChoice::Restore];
+        let recovered = Workspace::recover(&recovery, |_| choices.remove(0)).unwrap();
+        assert!(recovered.dirty);
+        // The restored copy stays until the document is saved.
+        assert_eq!(RecoveryFile::list(&recovery).unwrap().len(), 1);
+        std::fs::remove_dir_all(&recovery).unwrap();
+    }
+
+    #[test]
+    fn test_file_dialog_answered_later() {
+        let (mut workspace, _) = workspace_at(0);
+        workspace.mark_modified();
+
+        // Cancelling changes nothing.
+        workspace.dialog = Some((
+            PendingFile::answered(None),
+            FileRequest::Save(AfterSave::Close),
+        ));
+        assert!(workspace.has_open_dialog());
+        assert!(workspace.poll_dialog());
+        assert!(!workspace.has_open_dialog());
+        assert!(workspace.dirty && !workspace.take_close_ready());
+        assert!(!workspace.poll_dialog());
+
+        // Saving on the way to closing lets the window close, once.
+        let path = std::env::temp_dir().join(format!("{}.wolia", Uuid::new_v4()));
+        workspace.dialog = Some((
+            PendingFile::answered(Some(path.clone())),
+            FileRequest::Save(AfterSave::Close),
+        ));
+        assert!(workspace.poll_dialog());
+        let saved = path.exists();
+        std::fs::remove_file(&path).unwrap();
+        assert!(saved && !workspace.dirty);
+        assert!(workspace.take_close_ready());
+        assert!(!workspace.take_close_ready());
+    }
+
+    #[test]
+    fn test_click_places_caret() {
+        let (mut workspace, point) = workspace_at(8);
+        assert_eq!(workspace.offset_at(point), Some(8));
+        workspace.mouse_down(point, 1);
+        assert_eq!(workspace.editor.cursor.position, 8);
+        assert!(workspace.editor.selection.is_none());
+
+        // Dragging two characters to the right selects them.
+        workspace.mouse_drag(point + Point::new(24.0, 0.0));
+        let selection = workspace.editor.selection.unwrap();
+        assert_eq!((selection.start, selection.end), (8, 10));
+        workspace.mouse_up();
+    }
+
+    #[test]
+    fn test_caret_on_screen() {
+        let (mut workspace, point) = workspace_at(8);
+        workspace.mouse_down(point, 1);
+        // The click landed 2pt into the character, 4px at 200%, and the
+        // caret is as tall as the 14.4pt line.
+        let caret = workspace.caret_rect().unwrap();
+        assert!((caret.x - (point.x - 4.0)).abs() < 1e-3);
+        assert!((caret.height - 28.8).abs() < 1e-3);
+        assert!(workspace.selection_rects().is_empty());
+
+        workspace.mouse_down(point, 2);
+        let rects = workspace.selection_rects();
+        assert_eq!(rects.len(), 1);
+        assert!((rects[0].width - 4.0 * 12.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_double_click_selects_word() {
+        let (mut workspace, point) = workspace_at(8);
+        workspace.mouse_down(point, 2);
+        assert_eq!(workspace.editor.selected_text().as_deref(), Some("wide"));
+
+        workspace.mouse_down(point, 3);
+        assert_eq!(
+            workspace.editor.selected_text().as_deref(),
+            Some("Hello wide world")
+        );
+    }
+
+    #[test]
+    fn test_suggested_file_name() {
+        let mut workspace = Workspace::new(Document::new());
+        assert_eq!(workspace.suggested_file_name(), "Untitled.wolia");
+        workspace.editor.document.metadata.title = Some("Report".to_string());
+        assert_eq!(workspace.suggested_file_name(), "Report.wolia");
+        workspace.file_path = Some("/tmp/notes.wolia".into());
+        assert_eq!(workspace.suggested_file_name(), "notes.wolia");
+    }
+
+    #[test]
+    fn test_format_action_reflects_selection() {
+        use crate::toolbar::ButtonState;
+
+        let (mut workspace, point) = workspace_at(8);
+        let state =
+            |workspace: &Workspace, id: &str| workspace.toolbar.get_button(id).unwrap().state;
+        assert_eq!(state(&workspace, "undo"), ButtonState::Disabled);
+        assert_eq!(state(&workspace, "redo"), ButtonState::Disabled);
+
+        workspace.mouse_down(point, 2);
+        workspace.handle_action(ToolbarAction::Bold);
+        assert_eq!(state(&workspace, "bold"), ButtonState::Active);
+        assert_eq!(state(&workspace, "italic"), ButtonState::Normal);
+        assert_eq!(state(&workspace, "undo"), ButtonState::Normal);
+
+        // Selecting text that is only partly bold shows it as not bold.
+        workspace.mouse_down(point, 3);
+        workspace.sync_toolbar();
+        assert_eq!(state(&workspace, "bold"), ButtonState::Normal);
+
+        workspace.mouse_down(point, 2);
+        workspace.sync_toolbar();
+        assert_eq!(state(&workspace, "bold"), ButtonState::Active);
+        workspace.handle_action(ToolbarAction::Bold);
+        assert_eq!(state(&workspace, "bold"), ButtonState::Normal);
+
+        workspace.handle_action(ToolbarAction::Undo);
+        assert_eq!(state(&workspace, "bold"), ButtonState::Active);
+        assert_eq!(state(&workspace, "redo"), ButtonState::Normal);
+    }
+
+    #[test]
+    fn test_align_action_aligns_paragraph() {
+        use crate::toolbar::ButtonState;
+
+        let (mut workspace, _) = workspace_at(8);
+        let state =
+            |workspace: &Workspace, id: &str| workspace.toolbar.get_button(id).unwrap().state;
+        assert_eq!(state(&workspace, "align_left"), ButtonState::Active);
+        assert_eq!(state(&workspace, "bullet_list"), ButtonState::Disabled);
+        assert_eq!(state(&workspace, "numbered_list"), ButtonState::Disabled);
+
+        workspace.handle_action(ToolbarAction::AlignCenter);
+        assert_eq!(
+            workspace.editor.paragraph_style().alignment,
+            Some(Alignment::Center)
+        );
+        assert_eq!(state(&workspace, "align_center"), ButtonState::Active);
+        assert_eq!(state(&workspace, "align_left"), ButtonState::Normal);
+        assert!(workspace.dirty);
+
+        workspace.handle_action(ToolbarAction::Undo);
+        assert_eq!(workspace.editor.paragraph_style().alignment, None);
+        assert_eq!(state(&workspace, "align_left"), ButtonState::Active);
+    }
+
+    #[test]
+    fn test_dropdown_choice_sets_selection_font() {
+        use wolia_core::node::NodeKind;
+
+        let (mut workspace, point) = workspace_at(8);
+        workspace.mouse_down(point, 2);
+        workspace.apply_choice(DropdownChoice::Font("Georgia".to_string()));
+        workspace.apply_choice(DropdownChoice::Size(18.0));
+
+        let NodeKind::Paragraph(text) = &workspace.editor.document.root.children[0].kind else {
+            panic!("expected a paragraph");
+        };
+        let spans = text.spans.to_vec();
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].start, spans[0].end), (6, 10));
+        assert_eq!(spans[0].style.font_family.as_deref(), Some("Georgia"));
+        assert_eq!(spans[0].style.font_size, Some(18.0));
+        assert!(workspace.dirty);
+    }
+
+    #[test]
+    fn test_editing_updates_word_count() {
+        use wolia_edit::{Key, KeyModifiers};
+
+        let (mut workspace, point) = workspace_at(16);
+        assert_eq!(workspace.statusbar.stats.word_count, 3);
+        workspace.mouse_down(point, 1);
+        for (key, c) in [(Key::Space, ' '), (Key::A, 'a')] {
+            workspace.handle_key(KeyboardEvent::new(key, true, KeyModifiers::new()).with_char(c));
+        }
+        assert_eq!(workspace.editor.text(), "Hello wide world a");
+
+        // The count waits for typing to pause.
+        let due = workspace.statusbar.statistics_due().unwrap();
+        assert!(!workspace.refresh_statistics(Instant::now()));
+        assert_eq!(workspace.statusbar.stats.word_count, 3);
+        assert!(workspace.refresh_statistics(due));
+        assert_eq!(workspace.statusbar.stats.word_count, 4);
+        assert_eq!(workspace.statusbar.statistics_due(), None);
+    }
+
+    #[test]
+    fn test_caret_page_and_zoom_in_status_bar() {
+        let mut document = Document::new();
+        for _ in 0..120 {
+            document
+                .root
+                .add_child(Node::paragraph(Text::new("A line of text")));
+        }
+        let mut workspace = Workspace::new(document);
+        workspace
+            .view
+            .set_viewport(Rect::new(0.0, 0.0, 800.0, 600.0));
+        workspace.update_layout();
+        assert_eq!(workspace.statusbar.cursor_page, Some(1));
+        assert_eq!(workspace.statusbar.cursor_position, (1, 1));
+
+        // The caret in the last paragraph is on the last line of the last
+        // page.
+        let end = workspace.editor.text().len();
+        workspace.editor.set_cursor(end - 4);
+        workspace.update_layout();
+        let pages = workspace.layout.as_ref().unwrap().page_count();
+        let last_page = workspace.layout.as_ref().unwrap().pages.last().unwrap();
+        let lines = last_page.nodes.len();
+        assert_eq!(workspace.statusbar.cursor_page, Some(pages));
+        assert_eq!(workspace.statusbar.cursor_position, (lines, 11));
+
+        workspace.handle_action(ToolbarAction::ZoomIn);
+        assert_eq!(workspace.statusbar.zoom_level, 110);
+        workspace.handle_action(ToolbarAction::ResetZoom);
+        assert_eq!(workspace.statusbar.zoom_level, 100);
+    }
+
+    #[test]
+    fn test_dragging_first_line_marker_indents_paragraph() {
+        use crate::ruler::to_pixels;
+
+        let (mut workspace, _) = workspace_at(0);
+        workspace.update_layout();
+        let (page_x, zoom) = (workspace.view.page_rect(0).x, workspace.view.zoom);
+        let first_line = workspace.ruler_markers().first_line_indent;
+        let x = to_pixels(first_line, page_x, zoom);
+        assert!(workspace.ruler_down(x, 0.0));
+
+        // Half an inch, give or take, snaps to half an inch and shows in
+        // the layout before the marker is dropped.
+        assert!(workspace.ruler_move(x + 38.0 * zoom));
+        assert_eq!(
+            workspace.editor.paragraph_style().first_line_indent,
+            Some(36.0)
+        );
+        assert!(workspace.layout.is_none());
+        assert!(workspace.ruler_up());
+        assert!(!workspace.ruler_up());
+        assert_eq!(
+            workspace.editor.paragraph_style().first_line_indent,
+            Some(36.0)
+        );
+        assert_eq!(
+            workspace.ruler_markers().first_line_indent,
+            first_line + 36.0
+        );
+        assert!(workspace.dirty);
+
+        workspace.handle_action(ToolbarAction::Undo);
+        assert_eq!(workspace.editor.paragraph_style().first_line_indent, None);
+
+        // The margins change the layout, but not the document.
+        let left = workspace.ruler_markers().left_margin;
+        assert!(workspace.ruler_down(to_pixels(left, page_x, zoom), 12.0));
+        workspace.ruler_move(to_pixels(left + 36.0, page_x, zoom));
+        workspace.ruler_up();
+        assert_eq!(workspace.layout_engine.margins.left, left + 36.0);
+        assert_eq!(
+            workspace.editor.paragraph_style(),
+            ParagraphStyle::default()
+        );
+    }
+
+    #[test]
+    fn test_inserted_image_is_laid_out_and_saved() {
+        const PNG: &[u8] = include_bytes!("../../../test-suite/images/gray.png");
+
+        let (mut workspace, _) = workspace_at(5);
+        workspace
+            .insert_image(PNG.to_vec(), Some("gray".to_string()))
+            .unwrap();
+        assert!(workspace.dirty);
+        workspace.ensure_layout();
+        let layout = workspace.layout.as_ref().unwrap();
+        let images = layout.images();
+        assert_eq!(images.len(), 1);
+        let (src, rect) = (images[0].0.to_string(), images[0].1);
+        assert!(workspace.images.get(&src).is_some());
+        // Three by one pixels, at 96 pixels to the inch.
+        assert_eq!((rect.width, rect.height), (2.25, 0.75));
+
+        let path = std::env::temp_dir().join(format!("{}.wolia", Uuid::new_v4()));
+        workspace.save_to_path(&path).unwrap();
+        let reopened = Workspace::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut reopened = reopened.unwrap();
+        reopened.ensure_layout();
+        let layout = reopened.layout.as_ref().unwrap();
+        assert_eq!(layout.images(), [(src.as_str(), rect)]);
+        assert_eq!(reopened.images.get(&src).unwrap().pixels.len(), 3 * 4);
+    }
+
+    #[test]
+    fn test_recovered_images_kept() {
+        const PNG: &[u8] = include_bytes!("../../../test-suite/images/gray.png");
+
+        let recovery = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let (mut workspace, _) = workspace_at(5);
+        workspace.enable_autosave(&recovery, Duration::ZERO);
+        workspace
+            .insert_image(PNG.to_vec(), Some("gray".to_string()))
+            .unwrap();
+        workspace.ensure_layout();
+        let expected = workspace.layout.as_ref().unwrap().images()[0];
+        let (src, rect) = (expected.0.to_string(), expected.1);
+
+        assert!(workspace.autosave_if_due(Instant::now()));
+        workspace.autosave.as_ref().unwrap().flush();
+        let recovered = Workspace::recover(&recovery, |_| RecoveryChoice::Restore);
+        std::fs::remove_dir_all(&recovery).unwrap();
+        let mut recovered = recovered.unwrap();
+        assert_eq!(recovered.images.get(&src).unwrap().pixels.len(), 3 * 4);
+        recovered.ensure_layout();
+        let layout = recovered.layout.as_ref().unwrap();
+        assert_eq!(layout.images(), [(src.as_str(), rect)]);
+    }
+
+    #[test]
+    fn test_typing_into_inserted_table() {
+        let (mut workspace, _) = workspace_at(0);
+        let end = workspace.editor.text().len();
+        workspace.editor.set_cursor(end);
+        workspace.handle_action(ToolbarAction::InsertTable);
+        workspace.ensure_layout();
+        let first_cell = workspace.caret_rect().unwrap();
+
+        for (key, c) in [(Key::A, 'a'), (Key::Tab, '\t'), (Key::B, 'b')] {
+            let mut event = KeyboardEvent::new(key, true, KeyModifiers::new());
+            event.char_code = Some(c);
+            workspace.handle_key(event);
+        }
+        assert_eq!(workspace.editor.text(), "Hello wide world\na\nb\n\n\n");
+        workspace.ensure_layout();
+
+        // The second cell is right of the first, and a click there finds it.
+        let second_cell = workspace.caret_rect().unwrap();
+        assert!(second_cell.x > first_cell.x);
+        assert_eq!(second_cell.y, first_cell.y);
+        let point = Point::new(second_cell.x - 1.0, second_cell.y + 1.0);
+        assert_eq!(workspace.offset_at(point), Some(20));
+
+        // Selecting across cells highlights the cells.
+        workspace.editor.select_range(17, 22);
+        assert_eq!(workspace.selection_rects().len(), 4);
+    }
+
+    #[test]
+    fn test_link_is_underlined_until_removed() {
+        let (mut workspace, _) = workspace_at(0);
+        workspace.editor.select_range(6, 10);
+        workspace.set_link(" https://example.com ").unwrap();
+        assert_eq!(
+            workspace.editor.link().as_deref(),
+            Some("https://example.com")
+        );
+        assert!(workspace.dirty);
+        workspace.ensure_layout();
+
+        // The underline runs along the bottom of the linked word.
+        let selected = workspace.selection_rects();
+        let underlines = workspace.link_underlines();
+        assert_eq!(underlines.len(), 1);
+        assert_eq!(underlines[0].x, selected[0].x);
+        assert_eq!(underlines[0].width, selected[0].width);
+        assert!(underlines[0].y > selected[0].y);
+        assert!(underlines[0].bottom() <= selected[0].bottom());
+
+        // Only text in a link opens one.
+        workspace.editor.set_cursor(1);
+        workspace.ensure_layout();
+        let plain = workspace.caret_rect().unwrap();
+        assert!(!workspace.open_link_at(Point::new(plain.x, plain.y + 1.0)));
+
+        workspace.editor.set_cursor(8);
+        workspace.set_link("").unwrap();
+        assert_eq!(workspace.editor.link(), None);
+        workspace.ensure_layout();
+        assert!(workspace.link_underlines().is_empty());
+    }
+
+    #[test]
+    fn test_suggestions_are_marked_until_resolved() {
+        let (mut workspace, _) = workspace_at(0);
+        workspace.editor.track_changes = Some("ana".to_string());
+        workspace.editor.select_range(6, 10);
+        workspace.editor.insert_text("narrow").unwrap();
+        workspace.update_layout();
+
+        // "wide" is struck through and "narrow" underlined after it.
+        let marks = workspace.revision_marks();
+        let kinds: Vec<RevisionKind> = marks.iter().map(|(_, kind)| *kind).collect();
+        assert_eq!(kinds, [RevisionKind::Deletion, RevisionKind::Insertion]);
+        assert!(marks[0].0.right() <= marks[1].0.x + 0.01);
+        assert!(marks[0].0.y < marks[1].0.y);
+
+        for suggestion in workspace.editor.suggestions() {
+            workspace.editor.accept(suggestion.revision.id).unwrap();
+        }
+        assert_eq!(workspace.editor.text(), "Hello narrow world");
+        workspace.update_layout();
+        assert!(workspace.revision_marks().is_empty());
+    }
+}
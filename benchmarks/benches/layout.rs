@@ -1,13 +1,20 @@
 //! Layout benchmarks.
 
 use criterion::{Criterion, criterion_group, criterion_main};
+use wolia_benchmarks::utils;
+use wolia_layout::LayoutEngine;
 
 fn layout_benchmark(c: &mut Criterion) {
-    c.bench_function("layout_small_doc", |b| {
-        b.iter(|| {
-            // TODO: Benchmark layout
-        })
-    });
+    let engine = LayoutEngine::new();
+    let documents = [
+        ("layout_small_doc", utils::create_test_document(10)),
+        ("layout_large_doc", utils::create_test_document(500)),
+        ("layout_headings", utils::create_heading_heavy_document(200)),
+        ("layout_tables", utils::create_table_heavy_document(50)),
+    ];
+    for (name, document) in &documents {
+        c.bench_function(name, |b| b.iter(|| engine.layout(document).unwrap()));
+    }
 }
 
 criterion_group!(benches, layout_benchmark);
@@ -1,12 +1,17 @@
 //! Parsing benchmarks.
 
 use criterion::{Criterion, criterion_group, criterion_main};
+use wolia_benchmarks::utils;
+use wolia_core::Document;
 
 fn parsing_benchmark(c: &mut Criterion) {
+    let small = utils::create_test_document(10).to_json().unwrap();
+    let large = utils::create_test_document(500).to_json().unwrap();
     c.bench_function("parse_small_doc", |b| {
-        b.iter(|| {
-            // TODO: Benchmark parsing
-        })
+        b.iter(|| Document::from_json(&small).unwrap())
+    });
+    c.bench_function("parse_large_doc", |b| {
+        b.iter(|| Document::from_json(&large).unwrap())
     });
 }
 
@@ -1,10 +1,73 @@
 //! Wolia benchmarks library.
 
 pub mod utils {
+    use test_generator::Generator;
     use wolia_core::Document;
 
-    /// Create a test document with N paragraphs.
-    pub fn create_test_document(_paragraphs: usize) -> Document {
-        Document::new()
+    /// Seed benchmark documents are generated from, so that every run
+    /// measures the same content.
+    const SEED: u64 = 0xBE4C;
+
+    /// Create a test document with N paragraphs of 20 to 120 words with
+    /// mixed formatting, under a heading every ten paragraphs.
+    pub fn create_test_document(paragraphs: usize) -> Document {
+        Generator::new(SEED).stress_document(paragraphs)
+    }
+
+    /// Create a document of N headings at every level, each followed by a
+    /// short paragraph.
+    pub fn create_heading_heavy_document(sections: usize) -> Document {
+        Generator::new(SEED).heading_heavy_document(sections)
+    }
+
+    /// Create a document of N tables, each after a short paragraph.
+    pub fn create_table_heavy_document(tables: usize) -> Document {
+        Generator::new(SEED).table_heavy_document(tables)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use wolia_core::NodeKind;
+
+        use super::*;
+
+        fn count(document: &Document, kind: fn(&NodeKind) -> bool) -> usize {
+            document
+                .root
+                .children
+                .iter()
+                .filter(|node| kind(&node.kind))
+                .count()
+        }
+
+        #[test]
+        fn test_paragraph_count_matches() {
+            for paragraphs in [0, 1, 10, 250] {
+                let document = create_test_document(paragraphs);
+                assert_eq!(
+                    count(&document, |kind| matches!(kind, NodeKind::Paragraph(_))),
+                    paragraphs
+                );
+            }
+        }
+
+        #[test]
+        fn test_documents_are_deterministic() {
+            let text = |document: Document| document.to_json().unwrap().len();
+            assert_eq!(
+                text(create_test_document(20)),
+                text(create_test_document(20))
+            );
+            let document = create_heading_heavy_document(12);
+            assert_eq!(
+                count(&document, |kind| matches!(kind, NodeKind::Heading { .. })),
+                12
+            );
+            let document = create_table_heavy_document(3);
+            assert_eq!(
+                count(&document, |kind| matches!(kind, NodeKind::Table { .. })),
+                3
+            );
+        }
     }
 }
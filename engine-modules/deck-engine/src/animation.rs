@@ -1,6 +1,16 @@
 //! Animation system.
+//!
+//! Each animation changes one shape over its duration, after a delay from
+//! when it is triggered. A slide's [`AnimationTimeline`] works out when
+//! each animation is triggered from the one before it, and samples every
+//! animated shape at a playback time.
+
+use std::f32::consts::PI;
 
 use uuid::Uuid;
+use wolia_math::Point;
+
+use crate::transition::Easing;
 
 /// An animation applied to a shape.
 #[derive(Debug, Clone)]
@@ -15,6 +25,8 @@ pub struct Animation {
     pub duration: f32,
     /// Delay in seconds.
     pub delay: f32,
+    /// How progress speeds up and slows down over the duration.
+    pub easing: Easing,
 }
 
 impl Animation {
@@ -26,6 +38,139 @@ impl Animation {
             trigger: AnimationTrigger::OnClick,
             duration: 0.5,
             delay: 0.0,
+            easing: Easing::default(),
+        }
+    }
+
+    /// Set the trigger.
+    pub fn with_trigger(mut self, trigger: AnimationTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// Set the duration in seconds.
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Set the delay in seconds.
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Set the easing.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Seconds from being triggered to finishing.
+    pub fn total_duration(&self) -> f32 {
+        self.delay.max(0.0) + self.duration.max(0.0)
+    }
+
+    /// Eased progress from 0 to 1, `t` seconds after being triggered.
+    /// Animations without a duration finish as soon as their delay ends.
+    pub fn progress(&self, t: f32) -> f32 {
+        let t = t - self.delay.max(0.0);
+        let linear = if self.duration <= 0.0 {
+            if t >= 0.0 { 1.0 } else { 0.0 }
+        } else {
+            (t / self.duration).clamp(0.0, 1.0)
+        };
+        self.easing.apply(linear)
+    }
+
+    /// The target shape `t` seconds after being triggered.
+    pub fn sample(&self, t: f32) -> AnimationFrame {
+        let p = self.progress(t);
+        let frame = AnimationFrame::default();
+        match &self.effect {
+            // Appearing and disappearing happen at once when the delay ends.
+            AnimationEffect::Appear => AnimationFrame {
+                visible: t >= self.delay,
+                ..frame
+            },
+            AnimationEffect::FadeIn => AnimationFrame {
+                visible: t >= self.delay,
+                opacity: p,
+                ..frame
+            },
+            AnimationEffect::FlyIn { direction } => AnimationFrame {
+                visible: t >= self.delay,
+                offset: direction.unit() * (1.0 - p),
+                ..frame
+            },
+            AnimationEffect::ZoomIn => AnimationFrame {
+                visible: t >= self.delay,
+                opacity: p,
+                scale: p,
+                ..frame
+            },
+            AnimationEffect::Pulse => AnimationFrame {
+                scale: 1.0 + 0.1 * (PI * p).sin(),
+                ..frame
+            },
+            AnimationEffect::Spin => AnimationFrame {
+                rotation: 360.0 * p,
+                ..frame
+            },
+            AnimationEffect::Grow => AnimationFrame {
+                scale: 1.0 + 0.5 * p,
+                ..frame
+            },
+            AnimationEffect::Disappear => AnimationFrame {
+                visible: t < self.delay,
+                ..frame
+            },
+            AnimationEffect::FadeOut => AnimationFrame {
+                visible: p < 1.0,
+                opacity: 1.0 - p,
+                ..frame
+            },
+            AnimationEffect::FlyOut { direction } => AnimationFrame {
+                visible: p < 1.0,
+                offset: direction.unit() * p,
+                ..frame
+            },
+            AnimationEffect::ZoomOut => AnimationFrame {
+                visible: p < 1.0,
+                opacity: 1.0 - p,
+                scale: 1.0 - p,
+                ..frame
+            },
+            // Paths are not followed yet.
+            AnimationEffect::MotionPath { .. } => frame,
+        }
+    }
+}
+
+/// How an animated shape is drawn at one moment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationFrame {
+    /// Whether the shape is drawn at all.
+    pub visible: bool,
+    /// Opacity from 0 to 1.
+    pub opacity: f32,
+    /// Offset from its place on the slide, in slide widths and heights.
+    pub offset: Point,
+    /// Scale about the center of the shape.
+    pub scale: f32,
+    /// Rotation in degrees, added to the shape's own.
+    pub rotation: f32,
+}
+
+impl Default for AnimationFrame {
+    /// A shape drawn as it is on the slide.
+    fn default() -> Self {
+        Self {
+            visible: true,
+            opacity: 1.0,
+            offset: Point::ZERO,
+            scale: 1.0,
+            rotation: 0.0,
         }
     }
 }
@@ -54,6 +199,16 @@ pub enum AnimationEffect {
     MotionPath { path: String },
 }
 
+impl AnimationEffect {
+    /// Whether the effect brings a hidden shape onto the slide.
+    pub fn is_entrance(&self) -> bool {
+        matches!(
+            self,
+            Self::Appear | Self::FadeIn | Self::FlyIn { .. } | Self::ZoomIn
+        )
+    }
+}
+
 /// Animation direction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -67,6 +222,23 @@ pub enum Direction {
     BottomRight,
 }
 
+impl Direction {
+    /// A movement of one slide size toward this side, diagonals moving a
+    /// slide size both ways.
+    pub fn unit(self) -> Point {
+        match self {
+            Self::Left => Point::new(-1.0, 0.0),
+            Self::Right => Point::new(1.0, 0.0),
+            Self::Up => Point::new(0.0, -1.0),
+            Self::Down => Point::new(0.0, 1.0),
+            Self::TopLeft => Point::new(-1.0, -1.0),
+            Self::TopRight => Point::new(1.0, -1.0),
+            Self::BottomLeft => Point::new(-1.0, 1.0),
+            Self::BottomRight => Point::new(1.0, 1.0),
+        }
+    }
+}
+
 /// Animation trigger.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AnimationTrigger {
@@ -113,4 +285,137 @@ impl AnimationTimeline {
     pub fn reset(&mut self) {
         self.position = 0;
     }
+
+    /// When each animation is triggered, in seconds from the start of
+    /// playback, with every click coming as soon as everything before it
+    /// has finished.
+    pub fn start_times(&self) -> Vec<f32> {
+        let mut starts = Vec::with_capacity(self.animations.len());
+        // When the previous animation started and finished, and when
+        // everything so far has finished.
+        let (mut previous_start, mut previous_end, mut all_end) = (0.0f32, 0.0f32, 0.0f32);
+        for animation in &self.animations {
+            let start = match animation.trigger {
+                AnimationTrigger::OnClick => all_end,
+                AnimationTrigger::WithPrevious => previous_start,
+                AnimationTrigger::AfterPrevious => previous_end,
+            };
+            previous_start = start;
+            previous_end = start + animation.total_duration();
+            all_end = all_end.max(previous_end);
+            starts.push(start);
+        }
+        starts
+    }
+
+    /// Seconds from the start of playback until every animation has
+    /// finished.
+    pub fn duration(&self) -> f32 {
+        self.start_times()
+            .iter()
+            .zip(&self.animations)
+            .map(|(start, animation)| start + animation.total_duration())
+            .fold(0.0, f32::max)
+    }
+
+    /// Every animated shape at `time` seconds into playback, in the order
+    /// the shapes are first animated. A shape follows the latest of its
+    /// animations to have been triggered, and is hidden until its first
+    /// animation is triggered if that one brings it onto the slide.
+    pub fn sample(&self, time: f32) -> Vec<(Uuid, AnimationFrame)> {
+        let starts = self.start_times();
+        let mut frames: Vec<(Uuid, AnimationFrame, f32)> = Vec::new();
+        for (animation, &start) in self.animations.iter().zip(&starts) {
+            let index = match frames.iter().position(|(id, ..)| *id == animation.target) {
+                Some(index) => index,
+                None => {
+                    let frame = AnimationFrame {
+                        visible: !animation.effect.is_entrance(),
+                        ..AnimationFrame::default()
+                    };
+                    frames.push((animation.target, frame, f32::NEG_INFINITY));
+                    frames.len() - 1
+                }
+            };
+            let (_, frame, latest) = &mut frames[index];
+            if start <= time && start >= *latest {
+                *frame = animation.sample(time - start);
+                *latest = start;
+            }
+        }
+        frames
+            .into_iter()
+            .map(|(id, frame, _)| (id, frame))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Point, b: Point) -> bool {
+        a.distance(b) < 1e-5
+    }
+
+    #[test]
+    fn test_fly_in_samples() {
+        let animation = Animation::new(
+            Uuid::new_v4(),
+            AnimationEffect::FlyIn {
+                direction: Direction::Left,
+            },
+        )
+        .with_duration(2.0)
+        .with_delay(1.0)
+        .with_easing(Easing::Linear);
+
+        // Waiting out the delay, the shape has not entered yet.
+        assert!(!animation.sample(0.5).visible);
+        let start = animation.sample(1.0);
+        assert!(start.visible);
+        assert!(close(start.offset, Point::new(-1.0, 0.0)));
+        assert!(close(animation.sample(2.0).offset, Point::new(-0.5, 0.0)));
+        assert_eq!(animation.sample(3.0), AnimationFrame::default());
+        assert_eq!(animation.sample(10.0), AnimationFrame::default());
+    }
+
+    #[test]
+    fn test_timeline_sequencing() {
+        let (title, body, image) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut timeline = AnimationTimeline::new();
+        timeline.add(Animation::new(title, AnimationEffect::FadeIn).with_duration(1.0));
+        timeline.add(
+            Animation::new(body, AnimationEffect::Appear)
+                .with_trigger(AnimationTrigger::AfterPrevious)
+                .with_delay(0.5),
+        );
+        timeline.add(
+            Animation::new(image, AnimationEffect::Grow)
+                .with_trigger(AnimationTrigger::WithPrevious)
+                .with_duration(2.0),
+        );
+        timeline.add(Animation::new(title, AnimationEffect::FadeOut).with_duration(1.0));
+
+        // The body waits for the title, the image starts with the body,
+        // and the click waits for the image to finish growing.
+        assert_eq!(timeline.start_times(), vec![0.0, 1.0, 1.0, 3.0]);
+        assert_eq!(timeline.duration(), 4.0);
+
+        let at = |time| timeline.sample(time);
+        let frames = at(0.5);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].0, title);
+        assert_eq!(frames[0].1.opacity, 0.5);
+        assert!(!frames[1].1.visible);
+
+        // The body appears once its delay after the title has passed.
+        assert!(!at(1.4)[1].1.visible);
+        assert!(at(1.5)[1].1.visible);
+        assert_eq!(at(2.0)[2].1.scale, 1.25);
+
+        // The title follows its later exit.
+        assert_eq!(at(2.5)[0].1.opacity, 1.0);
+        assert!(!at(4.0)[0].1.visible);
+    }
 }
@@ -0,0 +1,807 @@
+//! Slide export to SVG and PNG.
+//!
+//! SVG export writes each shape as the element closest to its geometry:
+//! rectangles as `<rect>`, ellipses as `<ellipse>`, triangles, lines,
+//! arrows and custom paths as `<path>`, and text boxes as `<text>` with a
+//! `<tspan>` per laid-out line. Coordinates are slide units, and the
+//! document's view box is the slide.
+//!
+//! PNG export draws the slide with the headless renderer at one pixel per
+//! slide unit, as the editor does, and scales the result to the size asked
+//! for. Shapes are tessellated into triangles, with ellipses and rounded
+//! corners as polygons fine enough not to show their sides; rotation and
+//! media are not drawn yet. The renderer is set up on the first export and
+//! kept for the rest.
+
+use std::fmt::Write;
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use image::{DynamicImage, ImageFormat, RgbaImage, imageops::FilterType};
+use wolia_core::text::Text;
+use wolia_layout::{Constraints, ParagraphLayout};
+use wolia_math::{Point, Rect, Size};
+use wolia_render::svg::escape;
+use wolia_render::{OffscreenTarget, QuadRenderer, RenderContext, TextRenderer, TextRun, Vertex};
+
+use crate::shape::{Shape, ShapeKind, ShapeStyle};
+use crate::slide::{Background, ImageFit, Slide};
+use crate::{Error, Result};
+
+/// Font size text boxes are laid out at, in slide units.
+const FONT_SIZE: f32 = 12.0;
+
+/// Format slides are drawn in before encoding. Colors are written as
+/// given, as in SVG.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Sides per quarter turn of ellipses and rounded corners.
+const ARC_SEGMENTS: usize = 16;
+
+/// Longest a stroke's corner may reach past its width, in widths, so that
+/// sharp corners do not spike.
+const MITER_LIMIT: f32 = 4.0;
+
+/// Write a slide of `slide_size` as an SVG document.
+pub fn slide_to_svg(slide: &Slide, slide_size: Size) -> String {
+    let (width, height) = (slide_size.width, slide_size.height);
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+
+    match &slide.background {
+        Background::Solid(rgba) => {
+            let _ = writeln!(
+                svg,
+                r#"<rect width="{width}" height="{height}"{}/>"#,
+                paint("fill", *rgba)
+            );
+        }
+        Background::Gradient { start, end, angle } => {
+            let _ = writeln!(
+                svg,
+                r#"<defs><linearGradient id="background" gradientTransform="rotate({angle} 0.5 0.5)"><stop offset="0"{}/><stop offset="1"{}/></linearGradient></defs>"#,
+                paint("stop-color", *start),
+                paint("stop-color", *end)
+            );
+            let _ = writeln!(
+                svg,
+                r#"<rect width="{width}" height="{height}" fill="url(#background)"/>"#
+            );
+        }
+        Background::Image { src, fit } => {
+            let aspect = match fit {
+                ImageFit::Fill | ImageFit::Tile => "none",
+                ImageFit::Contain => "xMidYMid meet",
+                ImageFit::Cover => "xMidYMid slice",
+            };
+            let _ = writeln!(
+                svg,
+                r#"<image xlink:href="{}" width="{width}" height="{height}" preserveAspectRatio="{aspect}"/>"#,
+                escape(src)
+            );
+        }
+    }
+
+    for shape in slide.shapes.iter().filter(|shape| !shape.hidden) {
+        shape_svg(shape, &mut svg);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Draw a slide of `slide_size` as a PNG image of `size` pixels. A slide
+/// point (x, y) lands at (x × width / slide width, y × height / slide
+/// height).
+pub fn slide_to_png(slide: &Slide, slide_size: Size, size: Size) -> Result<Vec<u8>> {
+    let (width, height) = (size.width.round() as u32, size.height.round() as u32);
+    if width == 0 || height == 0 {
+        return Err(Error::Export(format!(
+            "cannot export an image of {} by {} pixels",
+            width, height
+        )));
+    }
+    let pixels = Rasterizer::shared()?.rasterize(slide, slide_size)?;
+    let image = if (width, height) == (pixels.width(), pixels.height()) {
+        pixels
+    } else {
+        image::imageops::resize(&pixels, width, height, FilterType::Triangle)
+    };
+
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| Error::Export(e.to_string()))?;
+    Ok(png)
+}
+
+/// The renderer slides are exported with.
+struct Rasterizer {
+    context: RenderContext,
+    quad_renderer: QuadRenderer,
+    text_renderer: TextRenderer,
+}
+
+impl Rasterizer {
+    /// The renderer shared by every export, set up on first use. Setting
+    /// up a device takes far longer than drawing a slide, and a failure to
+    /// find one is kept rather than met again on every export.
+    fn shared() -> Result<MutexGuard<'static, Rasterizer>> {
+        static SHARED: OnceLock<std::result::Result<Mutex<Rasterizer>, String>> = OnceLock::new();
+        let shared = SHARED.get_or_init(|| {
+            let context =
+                pollster::block_on(RenderContext::headless()).map_err(|e| e.to_string())?;
+            let quad_renderer = QuadRenderer::new(&context.device, FORMAT);
+            let text_renderer = TextRenderer::new(&context.device, FORMAT);
+            Ok(Mutex::new(Rasterizer {
+                context,
+                quad_renderer,
+                text_renderer,
+            }))
+        });
+        match shared {
+            Ok(rasterizer) => Ok(rasterizer.lock().unwrap_or_else(|e| e.into_inner())),
+            Err(e) => Err(Error::Export(e.clone())),
+        }
+    }
+
+    /// Draw a slide at one pixel per slide unit.
+    fn rasterize(&mut self, slide: &Slide, slide_size: Size) -> Result<RgbaImage> {
+        let context = &self.context;
+        let (width, height) = (
+            slide_size.width.ceil() as u32,
+            slide_size.height.ceil() as u32,
+        );
+        let max = context.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max || height > max {
+            return Err(Error::Export(format!(
+                "cannot draw a slide of {} by {} units",
+                slide_size.width, slide_size.height
+            )));
+        }
+
+        let mut vertices = Vec::new();
+        let background = Rect::new(0.0, 0.0, slide_size.width, slide_size.height);
+        fill(
+            &outline(&ShapeKind::Rectangle, background),
+            background_color(&slide.background),
+            &mut vertices,
+        );
+        let shapes: Vec<&Shape> = slide.shapes.iter().filter(|shape| !shape.hidden).collect();
+        for shape in &shapes {
+            shape_triangles(shape, &mut vertices);
+        }
+        let paragraphs: Vec<(Rect, ParagraphLayout)> = shapes
+            .iter()
+            .filter_map(|shape| match &shape.kind {
+                ShapeKind::TextBox(text) => Some((shape.bounds, layout_text(text, shape.bounds))),
+                _ => None,
+            })
+            .collect();
+        let runs: Vec<TextRun<'_>> = paragraphs
+            .iter()
+            .map(|(bounds, paragraph)| {
+                TextRun::new(
+                    &paragraph.text,
+                    &paragraph.lines,
+                    Point::new(bounds.x, bounds.y),
+                )
+                .with_font_size(FONT_SIZE)
+            })
+            .collect();
+
+        let device = &context.device;
+        let target = OffscreenTarget::new(device, width, height, FORMAT);
+        let viewport = Rect::new(0.0, 0.0, width as f32, height as f32);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Slide Export Encoder"),
+        });
+        self.quad_renderer.render_triangles(
+            device,
+            &mut encoder,
+            &target.view,
+            &vertices,
+            viewport.width,
+            viewport.height,
+            Some(wgpu::Color::TRANSPARENT),
+            None,
+        );
+        self.text_renderer.render(
+            device,
+            &context.queue,
+            &mut encoder,
+            &target.view,
+            &runs,
+            viewport,
+        );
+        context.queue.submit([encoder.finish()]);
+
+        let pixels = target
+            .read(context)
+            .map_err(|e| Error::Export(e.to_string()))?;
+        RgbaImage::from_raw(target.width, target.height, pixels)
+            .ok_or_else(|| Error::Export("readback size mismatch".to_string()))
+    }
+}
+
+/// Lay out the text of a text box inside its bounds.
+fn layout_text(text: &Text, bounds: Rect) -> ParagraphLayout {
+    ParagraphLayout::layout(text, Constraints::loose(bounds.size()))
+}
+
+/// Write one shape.
+fn shape_svg(shape: &Shape, svg: &mut String) {
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = shape.bounds;
+    let (right, bottom) = (x + width, y + height);
+    let middle = y + height / 2.0;
+    let style = &shape.style;
+    let rotation = (shape.rotation != 0.0).then(|| {
+        let (cx, cy) = (x + width / 2.0, y + height / 2.0);
+        format!("rotate({} {cx} {cy})", shape.rotation)
+    });
+    let mut opacity = String::new();
+    if style.opacity > 0.0 && style.opacity < 1.0 {
+        let _ = write!(opacity, r#" opacity="{}""#, style.opacity);
+    }
+    let attributes = transform(rotation.iter()) + &opacity;
+
+    match &shape.kind {
+        ShapeKind::Rectangle | ShapeKind::Video { .. } | ShapeKind::Chart { .. } => {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{width}" height="{height}"{}{attributes}/>"#,
+                fill_and_stroke(style)
+            );
+        }
+        ShapeKind::RoundedRectangle { radius } => {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" rx="{radius}" ry="{radius}"{}{attributes}/>"#,
+                fill_and_stroke(style)
+            );
+        }
+        ShapeKind::Ellipse => {
+            let _ = writeln!(
+                svg,
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}"{}{attributes}/>"#,
+                x + width / 2.0,
+                middle,
+                width / 2.0,
+                height / 2.0,
+                fill_and_stroke(style)
+            );
+        }
+        ShapeKind::Triangle => {
+            let _ = writeln!(
+                svg,
+                r#"<path d="M{} {y} L{right} {bottom} L{x} {bottom} Z"{}{attributes}/>"#,
+                x + width / 2.0,
+                fill_and_stroke(style)
+            );
+        }
+        // Lines are drawn across the middle of their bounds, as in the
+        // editor, and arrows point right.
+        ShapeKind::Line | ShapeKind::Arrow => {
+            let (stroke, stroke_width) = line_stroke(style);
+            let mut d = format!("M{x} {middle} L{right} {middle}");
+            if matches!(shape.kind, ShapeKind::Arrow) {
+                let head = (stroke_width * 4.0).max(8.0).min(width);
+                let _ = write!(
+                    d,
+                    " M{} {} L{right} {middle} L{} {}",
+                    right - head,
+                    middle - head / 2.0,
+                    right - head,
+                    middle + head / 2.0
+                );
+            }
+            let _ = writeln!(
+                svg,
+                r#"<path d="{d}" fill="none"{} stroke-width="{stroke_width}"{attributes}/>"#,
+                paint("stroke", stroke)
+            );
+        }
+        // Path data is relative to the top left of the bounds.
+        ShapeKind::Path { data } => {
+            let translate = format!("translate({x} {y})");
+            let attributes = transform(rotation.iter().chain([&translate])) + &opacity;
+            let _ = writeln!(
+                svg,
+                r#"<path d="{}"{}{attributes}/>"#,
+                escape(data),
+                fill_and_stroke(style)
+            );
+        }
+        ShapeKind::Image { src } => {
+            let _ = writeln!(
+                svg,
+                r#"<image x="{x}" y="{y}" width="{width}" height="{height}" xlink:href="{}" preserveAspectRatio="none"{attributes}/>"#,
+                escape(src)
+            );
+        }
+        ShapeKind::Table { rows, cols } => {
+            let (stroke, stroke_width) = line_stroke(style);
+            let mut d = String::new();
+            for row in 1..*rows {
+                let row_y = y + height * row as f32 / *rows as f32;
+                let _ = write!(d, "M{x} {row_y} L{right} {row_y} ");
+            }
+            for col in 1..*cols {
+                let col_x = x + width * col as f32 / *cols as f32;
+                let _ = write!(d, "M{col_x} {y} L{col_x} {bottom} ");
+            }
+            let _ = writeln!(svg, "<g{attributes}>");
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{width}" height="{height}"{}{} stroke-width="{stroke_width}"/>"#,
+                style
+                    .fill
+                    .map_or(r#" fill="none""#.to_string(), |fill| paint("fill", fill)),
+                paint("stroke", stroke)
+            );
+            if !d.is_empty() {
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="{}" fill="none"{} stroke-width="{stroke_width}"/>"#,
+                    d.trim_end(),
+                    paint("stroke", stroke)
+                );
+            }
+            svg.push_str("</g>\n");
+        }
+        ShapeKind::TextBox(text) => {
+            let _ = writeln!(svg, "<g{attributes}>");
+            if style.fill.is_some() || style.stroke.is_some() {
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{x}" y="{y}" width="{width}" height="{height}"{}/>"#,
+                    fill_and_stroke(style)
+                );
+            }
+            let paragraph = layout_text(text, shape.bounds);
+            // Text without a color of its own is black.
+            let _ = write!(svg, r##"<text font-size="{FONT_SIZE}" fill="#000000">"##);
+            for line in &paragraph.lines {
+                let _ = write!(
+                    svg,
+                    r#"<tspan x="{}" y="{}">"#,
+                    x + line.bounds.x,
+                    y + line.bounds.y + line.baseline
+                );
+                for fragment in &line.fragments {
+                    let range = fragment.text_start..fragment.text_start + fragment.text_len;
+                    for (run, color) in color_runs(text, range) {
+                        let Some(content) = paragraph.text.get(run) else {
+                            continue;
+                        };
+                        let content = escape(content.trim_end_matches('\n'));
+                        match color {
+                            Some(color) => {
+                                let _ =
+                                    write!(svg, "<tspan{}>{content}</tspan>", paint("fill", color));
+                            }
+                            None => svg.push_str(&content),
+                        }
+                    }
+                }
+                svg.push_str("</tspan>");
+            }
+            svg.push_str("</text>\n</g>\n");
+        }
+    }
+}
+
+/// The runs of `range` in `text` that are one color, with the color, if
+/// the text has one.
+fn color_runs(text: &Text, range: Range<usize>) -> Vec<(Range<usize>, Option<[u8; 4]>)> {
+    let mut bounds: Vec<usize> = text
+        .spans
+        .iter()
+        .flat_map(|span| [span.start, span.end])
+        .filter(|&offset| range.start < offset && offset < range.end)
+        .chain([range.start, range.end])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+    let mut runs: Vec<(Range<usize>, Option<[u8; 4]>)> = Vec::new();
+    for pair in bounds.windows(2) {
+        // Later spans are applied over earlier ones.
+        let color = text
+            .spans
+            .iter()
+            .rev()
+            .filter(|span| span.start <= pair[0] && pair[0] < span.end)
+            .find_map(|span| span.style.color);
+        match runs.last_mut() {
+            Some((run, last)) if *last == color => run.end = pair[1],
+            _ => runs.push((pair[0]..pair[1], color)),
+        }
+    }
+    runs
+}
+
+/// A transform attribute applying `transforms` in order, if there are
+/// any.
+fn transform<'a>(transforms: impl Iterator<Item = &'a String>) -> String {
+    let transforms: Vec<&str> = transforms.map(String::as_str).collect();
+    if transforms.is_empty() {
+        String::new()
+    } else {
+        format!(r#" transform="{}""#, transforms.join(" "))
+    }
+}
+
+/// Fill and stroke attributes for a closed shape. Shapes without a fill
+/// are transparent inside.
+fn fill_and_stroke(style: &ShapeStyle) -> String {
+    let mut attributes = match style.fill {
+        Some(fill) => paint("fill", fill),
+        None => r#" fill="none""#.to_string(),
+    };
+    if let Some(stroke) = style.stroke.filter(|_| style.stroke_width > 0.0) {
+        attributes.push_str(&paint("stroke", stroke));
+        let _ = write!(attributes, r#" stroke-width="{}""#, style.stroke_width);
+    }
+    attributes
+}
+
+/// The color and width lines are drawn with: the stroke, or failing that
+/// the fill, or black, and at least one unit wide.
+fn line_stroke(style: &ShapeStyle) -> ([u8; 4], f32) {
+    let stroke = style.stroke.or(style.fill).unwrap_or([0, 0, 0, 255]);
+    (stroke, style.stroke_width.max(1.0))
+}
+
+/// A color attribute named `name`, with its opacity if not opaque.
+fn paint(name: &str, [r, g, b, a]: [u8; 4]) -> String {
+    let mut attribute = format!(r##" {name}="#{r:02x}{g:02x}{b:02x}""##);
+    if a < 255 {
+        let opacity = match name {
+            "stop-color" => "stop-opacity".to_string(),
+            _ => format!("{name}-opacity"),
+        };
+        let _ = write!(attribute, r#" {opacity}="{}""#, a as f32 / 255.0);
+    }
+    attribute
+}
+
+/// The color a background is drawn in. Gradients are drawn in their
+/// starting color and images as white until they can be rendered.
+fn background_color(background: &Background) -> [f32; 4] {
+    match background {
+        Background::Solid(rgba) => color(*rgba, 1.0),
+        Background::Gradient { start, .. } => color(*start, 1.0),
+        Background::Image { .. } => [1.0, 1.0, 1.0, 1.0],
+    }
+}
+
+/// Tessellate a shape: closed shapes as their fill and then their stroke,
+/// which lies inside the outline, and lines across the middle of their
+/// bounds.
+fn shape_triangles(shape: &Shape, vertices: &mut Vec<Vertex>) {
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = shape.bounds;
+    let style = &shape.style;
+
+    if matches!(shape.kind, ShapeKind::Line | ShapeKind::Arrow) {
+        let (stroke, stroke_width) = line_stroke(style);
+        let stroke = color(stroke, style.opacity);
+        let (right, middle) = (x + width, y + height / 2.0);
+        let tip = Point::new(right, middle);
+        segment(Point::new(x, middle), tip, stroke_width, stroke, vertices);
+        if matches!(shape.kind, ShapeKind::Arrow) {
+            let head = (stroke_width * 4.0).max(8.0).min(width);
+            for side in [-0.5, 0.5] {
+                let base = Point::new(right - head, middle + head * side);
+                segment(base, tip, stroke_width, stroke, vertices);
+            }
+        }
+        return;
+    }
+
+    let points = outline(&shape.kind, shape.bounds);
+    if let Some(fill_color) = style.fill {
+        fill(&points, color(fill_color, style.opacity), vertices);
+    }
+    if let Some(stroke) = style.stroke.filter(|_| style.stroke_width > 0.0) {
+        let inner = inset(&points, style.stroke_width);
+        let stroke = color(stroke, style.opacity);
+        for i in 0..points.len() {
+            let j = (i + 1) % points.len();
+            triangle([points[i], points[j], inner[j]], stroke, vertices);
+            triangle([points[i], inner[j], inner[i]], stroke, vertices);
+        }
+    }
+}
+
+/// The outline of a closed shape drawn in `bounds`, clockwise on screen.
+/// Shapes without a geometry of their own are outlined by their bounds.
+fn outline(kind: &ShapeKind, bounds: Rect) -> Vec<Point> {
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = bounds;
+    let (right, bottom) = (x + width, y + height);
+    let corners = vec![
+        Point::new(x, y),
+        Point::new(right, y),
+        Point::new(right, bottom),
+        Point::new(x, bottom),
+    ];
+    let arc = |center: Point, radii: Point, from: usize, points: &mut Vec<Point>| {
+        for i in 0..=ARC_SEGMENTS {
+            let angle = (from * ARC_SEGMENTS + i) as f32 * std::f32::consts::FRAC_PI_2
+                / ARC_SEGMENTS as f32;
+            points.push(center + radii * Point::new(angle.cos(), angle.sin()));
+        }
+    };
+    let mut points = match kind {
+        ShapeKind::Triangle => vec![
+            Point::new(x + width / 2.0, y),
+            Point::new(right, bottom),
+            Point::new(x, bottom),
+        ],
+        ShapeKind::Ellipse => {
+            let radii = Point::new(width / 2.0, height / 2.0);
+            let mut points = Vec::with_capacity(4 * (ARC_SEGMENTS + 1));
+            for quarter in 0..4 {
+                arc(Point::new(x, y) + radii, radii, quarter, &mut points);
+            }
+            points
+        }
+        ShapeKind::RoundedRectangle { radius } => {
+            let radius = radius.min(width / 2.0).min(height / 2.0);
+            if radius <= 0.0 {
+                return corners;
+            }
+            let radii = Point::splat(radius);
+            let mut points = Vec::with_capacity(4 * (ARC_SEGMENTS + 1));
+            // Corners clockwise from the bottom right, where angles start.
+            for (quarter, center) in [
+                Point::new(right - radius, bottom - radius),
+                Point::new(x + radius, bottom - radius),
+                Point::new(x + radius, y + radius),
+                Point::new(right - radius, y + radius),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                arc(center, radii, quarter, &mut points);
+            }
+            points
+        }
+        _ => corners,
+    };
+    // Arcs end where the next begins, and the last may end at the first
+    // point; repeated points would leave edges without a direction.
+    points.dedup_by(|a, b| a.distance_squared(*b) < 1e-6);
+    if points.len() > 1 && points[0].distance_squared(points[points.len() - 1]) < 1e-6 {
+        points.pop();
+    }
+    points
+}
+
+/// Fill a convex outline.
+fn fill(points: &[Point], color: [f32; 4], vertices: &mut Vec<Vertex>) {
+    for pair in points.windows(2).skip(1) {
+        triangle([points[0], pair[0], pair[1]], color, vertices);
+    }
+}
+
+/// A clockwise outline moved `distance` inward, with each corner moved
+/// along the line halving it so that edges stay parallel.
+fn inset(points: &[Point], distance: f32) -> Vec<Point> {
+    // On screen, where y points down, the inside of a clockwise outline
+    // is to the right of each edge.
+    let normal = |from: Point, to: Point| (to - from).perp().normalize_or_zero();
+    (0..points.len())
+        .map(|i| {
+            let before = points[(i + points.len() - 1) % points.len()];
+            let after = points[(i + 1) % points.len()];
+            let (a, b) = (normal(before, points[i]), normal(points[i], after));
+            let miter = (a + b).normalize_or_zero();
+            points[i] + miter * (distance / miter.dot(a).max(1.0 / MITER_LIMIT))
+        })
+        .collect()
+}
+
+/// A straight line `width` wide from `from` to `to`.
+fn segment(from: Point, to: Point, width: f32, color: [f32; 4], vertices: &mut Vec<Vertex>) {
+    let side = (to - from).perp().normalize_or_zero() * (width / 2.0);
+    let corners = [from - side, to - side, to + side, from + side];
+    fill(&corners, color, vertices);
+}
+
+fn triangle(points: [Point; 3], color: [f32; 4], vertices: &mut Vec<Vertex>) {
+    vertices.extend(points.map(|point| Vertex {
+        position: point.into(),
+        color,
+    }));
+}
+
+/// Convert a color to floats, folding in a shape opacity. An opacity of
+/// zero is the unset default and leaves the color untouched.
+fn color(rgba: [u8; 4], opacity: f32) -> [f32; 4] {
+    let alpha = rgba[3] as f32 / 255.0;
+    let alpha = if opacity > 0.0 && opacity < 1.0 {
+        alpha * opacity
+    } else {
+        alpha
+    };
+    [
+        rgba[0] as f32 / 255.0,
+        rgba[1] as f32 / 255.0,
+        rgba[2] as f32 / 255.0,
+        alpha,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Presentation;
+
+    /// A slide with one of each kind of element.
+    fn presentation() -> Presentation {
+        let mut presentation = Presentation::with_size(800.0, 600.0);
+        let slide = presentation.slide_mut(0).unwrap();
+        let mut rectangle = Shape::rectangle(Rect::new(100.0, 50.0, 200.0, 80.0));
+        rectangle.style.fill = Some([255, 0, 0, 255]);
+        slide.add_shape(rectangle);
+        slide.add_shape(Shape::new(
+            ShapeKind::RoundedRectangle { radius: 8.0 },
+            Rect::new(0.0, 0.0, 10.0, 10.0),
+        ));
+        slide.add_shape(Shape::new(
+            ShapeKind::Triangle,
+            Rect::new(400.0, 100.0, 100.0, 50.0),
+        ));
+        slide.add_shape(Shape::new(
+            ShapeKind::Arrow,
+            Rect::new(0.0, 300.0, 200.0, 20.0),
+        ));
+        slide.add_shape(Shape::text_box(
+            Rect::new(50.0, 400.0, 300.0, 100.0),
+            Text::new("Q&A <today>"),
+        ));
+        let mut hidden = Shape::rectangle(Rect::new(0.0, 0.0, 5.0, 5.0));
+        hidden.hidden = true;
+        slide.add_shape(hidden);
+        presentation
+    }
+
+    #[test]
+    fn test_svg_element_counts() {
+        let svg = presentation().export_slide_svg(0).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"viewBox="0 0 800 600""#));
+        // The background and two rectangles; the hidden one is left out.
+        assert_eq!(svg.matches("<rect ").count(), 3);
+        assert_eq!(svg.matches("<path ").count(), 2);
+        assert_eq!(svg.matches("<text ").count(), 1);
+        assert_eq!(svg.matches("<tspan ").count(), 1);
+    }
+
+    #[test]
+    fn test_svg_geometry() {
+        let svg = presentation().export_slide_svg(0).unwrap();
+        assert!(svg.contains(r##"<rect x="100" y="50" width="200" height="80" fill="#ff0000"/>"##));
+        assert!(svg.contains(r#"rx="8" ry="8""#));
+        assert!(svg.contains(r#"<path d="M450 100 L500 150 L400 150 Z""#));
+        assert!(svg.contains(r#"d="M0 310 L200 310 M192 306 L200 310 L192 314""#));
+        assert!(svg.contains("Q&amp;A &lt;today&gt;"));
+        assert!(svg.contains(r#"<tspan x="50" y="4"#));
+    }
+
+    #[test]
+    fn test_svg_text_colors() {
+        use wolia_core::style::TextStyle;
+        use wolia_core::text::Span;
+
+        let mut presentation = Presentation::with_size(400.0, 100.0);
+        let mut text = Text::new("red & plain");
+        let red = TextStyle {
+            color: Some([255, 0, 0, 255]),
+            ..TextStyle::default()
+        };
+        text.add_span(Span::new(0, 5, red));
+        let slide = presentation.slide_mut(0).unwrap();
+        slide.add_shape(Shape::text_box(Rect::new(0.0, 0.0, 400.0, 100.0), text));
+        let svg = presentation.export_slide_svg(0).unwrap();
+        assert!(svg.contains(r##"<tspan fill="#ff0000">red &amp;</tspan> plain</tspan>"##));
+    }
+
+    #[test]
+    fn test_svg_transforms() {
+        let mut presentation = Presentation::with_size(100.0, 100.0);
+        let slide = presentation.slide_mut(0).unwrap();
+        let mut path = Shape::new(
+            ShapeKind::Path {
+                data: "M0 0 L10 10".to_string(),
+            },
+            Rect::new(20.0, 30.0, 10.0, 10.0),
+        );
+        path.rotation = 90.0;
+        path.style.opacity = 0.5;
+        slide.add_shape(path);
+        let svg = presentation.export_slide_svg(0).unwrap();
+        assert!(svg.contains(
+            r#"<path d="M0 0 L10 10" fill="none" transform="rotate(90 25 35) translate(20 30)" opacity="0.5"/>"#
+        ));
+    }
+
+    #[test]
+    fn test_missing_slide() {
+        let presentation = presentation();
+        assert!(matches!(
+            presentation.export_slide_svg(1),
+            Err(Error::SlideNotFound(1))
+        ));
+        assert!(matches!(
+            presentation.export_slide_png(3, Size::new(80.0, 60.0)),
+            Err(Error::SlideNotFound(3))
+        ));
+    }
+
+    #[test]
+    fn test_png_maps_slide_coordinates() {
+        let png = match presentation().export_slide_png(0, Size::new(400.0, 300.0)) {
+            Ok(png) => png,
+            Err(Error::Export(e)) => {
+                eprintln!("skipping: {}", e);
+                return;
+            }
+            Err(e) => panic!("{}", e),
+        };
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (400, 300));
+        // The red rectangle covers (100, 50) to (300, 130) on the slide,
+        // so (50, 25) to (150, 65) at half size.
+        assert_eq!(image.get_pixel(100, 45).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(160, 45).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(100, 70).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_png_draws_shape_geometry() {
+        let mut presentation = Presentation::with_size(200.0, 100.0);
+        let slide = presentation.slide_mut(0).unwrap();
+        let mut ellipse = Shape::new(ShapeKind::Ellipse, Rect::new(0.0, 0.0, 100.0, 100.0));
+        ellipse.style.fill = Some([0, 0, 255, 255]);
+        slide.add_shape(ellipse);
+        let mut triangle = Shape::new(ShapeKind::Triangle, Rect::new(100.0, 0.0, 100.0, 100.0));
+        triangle.style.fill = Some([0, 255, 0, 255]);
+        slide.add_shape(triangle);
+        let png = match presentation.export_slide_png(0, Size::new(200.0, 100.0)) {
+            Ok(png) => png,
+            Err(Error::Export(e)) => {
+                eprintln!("skipping: {}", e);
+                return;
+            }
+            Err(e) => panic!("{}", e),
+        };
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        let white = [255, 255, 255, 255];
+        assert_eq!(image.get_pixel(50, 50).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(50, 2).0, [0, 0, 255, 255]);
+        // Inside the ellipse's bounds, outside the ellipse.
+        assert_eq!(image.get_pixel(8, 8).0, white);
+        assert_eq!(image.get_pixel(150, 90).0, [0, 255, 0, 255]);
+        assert_eq!(image.get_pixel(110, 10).0, white);
+        assert_eq!(image.get_pixel(190, 10).0, white);
+    }
+}
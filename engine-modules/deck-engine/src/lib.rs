@@ -8,16 +8,22 @@
 //! - Animations
 //! - Transitions
 //! - Speaker notes
+//! - Slide export to SVG and PNG
 
 pub mod animation;
+pub mod export;
 pub mod presentation;
+pub mod presenter;
 pub mod shape;
 pub mod slide;
+pub mod transition;
 
-pub use animation::{Animation, AnimationEffect};
+pub use animation::{Animation, AnimationEffect, AnimationFrame, AnimationTimeline};
 pub use presentation::Presentation;
+pub use presenter::PresenterView;
 pub use shape::{Shape, ShapeKind};
 pub use slide::Slide;
+pub use transition::{Easing, Transition, TransitionDirection, TransitionKind, TransitionState};
 
 /// Result type for deck operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -33,4 +39,7 @@ pub enum Error {
 
     #[error("Invalid animation: {0}")]
     InvalidAnimation(String),
+
+    #[error("Export failed: {0}")]
+    Export(String),
 }
@@ -2,7 +2,9 @@
 
 use wolia_math::Size;
 
+use crate::export;
 use crate::slide::Slide;
+use crate::{Error, Result};
 
 /// A presentation containing slides.
 #[derive(Debug, Clone)]
@@ -39,6 +41,11 @@ impl Presentation {
         self.slides.len()
     }
 
+    /// All slides in order.
+    pub fn slides(&self) -> &[Slide] {
+        &self.slides
+    }
+
     /// Get a slide by index.
     pub fn slide(&self, index: usize) -> Option<&Slide> {
         self.slides.get(index)
@@ -85,6 +92,19 @@ impl Presentation {
         self.slides.insert(new_index, slide);
         Some(new_index)
     }
+
+    /// Export a slide as an SVG document in slide units.
+    pub fn export_slide_svg(&self, index: usize) -> Result<String> {
+        let slide = self.slide(index).ok_or(Error::SlideNotFound(index))?;
+        Ok(export::slide_to_svg(slide, self.slide_size))
+    }
+
+    /// Export a slide as a PNG image of `size` pixels, drawn with the
+    /// headless renderer.
+    pub fn export_slide_png(&self, index: usize, size: Size) -> Result<Vec<u8>> {
+        let slide = self.slide(index).ok_or(Error::SlideNotFound(index))?;
+        export::slide_to_png(slide, self.slide_size, size)
+    }
 }
 
 impl Default for Presentation {
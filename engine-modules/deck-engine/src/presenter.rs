@@ -0,0 +1,170 @@
+//! Presenter view.
+//!
+//! What a presenter sees while presenting: the slide on screen, the one
+//! coming next, their notes, and how long the presentation has run. It is
+//! plain data, so it can drive a window of its own or be tested without a
+//! GPU.
+
+use std::time::Duration;
+
+use wolia_core::text::Text;
+
+use crate::presentation::Presentation;
+use crate::slide::Slide;
+
+/// The presenter's position in a presentation and its timer.
+#[derive(Debug, Clone, Default)]
+pub struct PresenterView {
+    /// Index of the slide on screen.
+    current: usize,
+    /// Time spent presenting, not counting pauses.
+    elapsed: Duration,
+    /// Whether the timer is paused.
+    paused: bool,
+}
+
+impl PresenterView {
+    /// Start presenting from the first slide.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start presenting from the slide at `index`.
+    pub fn starting_at(presentation: &Presentation, index: usize) -> Self {
+        let mut view = Self::new();
+        view.go_to(presentation, index);
+        view
+    }
+
+    /// Index of the slide on screen.
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// The slide on screen.
+    pub fn current_slide<'a>(&self, presentation: &'a Presentation) -> Option<&'a Slide> {
+        presentation.slide(self.current)
+    }
+
+    /// The slide after the one on screen, or `None` on the last slide.
+    pub fn next_slide<'a>(&self, presentation: &'a Presentation) -> Option<&'a Slide> {
+        presentation.slide(self.current + 1)
+    }
+
+    /// Notes for the slide on screen.
+    pub fn notes<'a>(&self, presentation: &'a Presentation) -> Option<&'a Text> {
+        self.current_slide(presentation).map(Slide::notes)
+    }
+
+    /// Move to the next slide, returning whether there was one.
+    pub fn advance(&mut self, presentation: &Presentation) -> bool {
+        if self.current + 1 >= presentation.slide_count() {
+            return false;
+        }
+        self.current += 1;
+        true
+    }
+
+    /// Move to the previous slide, returning whether there was one.
+    pub fn back(&mut self) -> bool {
+        if self.current == 0 {
+            return false;
+        }
+        self.current -= 1;
+        true
+    }
+
+    /// Move to the slide at `index`, or the last slide if there are fewer.
+    pub fn go_to(&mut self, presentation: &Presentation, index: usize) {
+        self.current = index.min(presentation.slide_count().saturating_sub(1));
+    }
+
+    /// Time spent presenting.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Run the timer on by `dt`, unless it is paused.
+    pub fn tick(&mut self, dt: Duration) {
+        if !self.paused {
+            self.elapsed += dt;
+        }
+    }
+
+    /// Whether the timer is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pause or resume the timer.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Restart the timer from zero.
+    pub fn reset_timer(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presentation() -> Presentation {
+        let mut presentation = Presentation::new();
+        presentation.add_slide();
+        presentation.add_slide();
+        for (index, notes) in ["Open", "Middle", "Close"].into_iter().enumerate() {
+            presentation.slide_mut(index).unwrap().set_notes(notes);
+        }
+        presentation
+    }
+
+    #[test]
+    fn test_notes_get_and_set() {
+        let mut presentation = presentation();
+        let slide = presentation.slide_mut(0).unwrap();
+        assert_eq!(slide.notes().content, "Open");
+        slide.set_notes(Text::new("Welcome everyone"));
+        slide.notes_mut().append(&Text::new("!"));
+        assert_eq!(slide.notes().content, "Welcome everyone!");
+    }
+
+    #[test]
+    fn test_advances_to_next_slide() {
+        let presentation = presentation();
+        let mut view = PresenterView::new();
+        assert_eq!(view.notes(&presentation).unwrap().content, "Open");
+        assert_eq!(
+            view.next_slide(&presentation).unwrap().notes().content,
+            "Middle"
+        );
+
+        assert!(view.advance(&presentation));
+        assert!(view.advance(&presentation));
+        // On the last slide there is nothing next and nowhere to go.
+        assert_eq!(view.current_index(), 2);
+        assert!(view.next_slide(&presentation).is_none());
+        assert!(!view.advance(&presentation));
+        assert_eq!(view.notes(&presentation).unwrap().content, "Close");
+
+        assert!(view.back());
+        let view = PresenterView::starting_at(&presentation, 10);
+        assert_eq!(view.current_index(), 2);
+    }
+
+    #[test]
+    fn test_timer_pauses() {
+        let mut view = PresenterView::new();
+        view.tick(Duration::from_secs(5));
+        view.set_paused(true);
+        view.tick(Duration::from_secs(5));
+        assert_eq!(view.elapsed(), Duration::from_secs(5));
+        view.set_paused(false);
+        view.tick(Duration::from_secs(1));
+        assert_eq!(view.elapsed(), Duration::from_secs(6));
+        view.reset_timer();
+        assert_eq!(view.elapsed(), Duration::ZERO);
+    }
+}
@@ -1,9 +1,11 @@
 //! Slide model.
 
 use uuid::Uuid;
+use wolia_core::text::Text;
 
 use crate::animation::Animation;
 use crate::shape::Shape;
+pub use crate::transition::{Transition, TransitionKind};
 
 /// A single slide.
 #[derive(Debug, Clone)]
@@ -19,7 +21,7 @@ pub struct Slide {
     /// Animations.
     pub animations: Vec<Animation>,
     /// Speaker notes.
-    pub notes: String,
+    notes: Text,
     /// Slide layout name.
     pub layout: Option<String>,
 }
@@ -33,11 +35,26 @@ impl Slide {
             background: Background::default(),
             transition: None,
             animations: Vec::new(),
-            notes: String::new(),
+            notes: Text::empty(),
             layout: None,
         }
     }
 
+    /// The speaker notes.
+    pub fn notes(&self) -> &Text {
+        &self.notes
+    }
+
+    /// The speaker notes, for editing in place.
+    pub fn notes_mut(&mut self) -> &mut Text {
+        &mut self.notes
+    }
+
+    /// Replace the speaker notes.
+    pub fn set_notes(&mut self, notes: impl Into<Text>) {
+        self.notes = notes.into();
+    }
+
     /// Add a shape to the slide.
     pub fn add_shape(&mut self, shape: Shape) {
         self.shapes.push(shape);
@@ -115,26 +132,3 @@ pub enum ImageFit {
     /// Tile the image.
     Tile,
 }
-
-/// Slide transition.
-#[derive(Debug, Clone)]
-pub struct Transition {
-    /// Transition type.
-    pub kind: TransitionKind,
-    /// Duration in seconds.
-    pub duration: f32,
-}
-
-/// Transition types.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TransitionKind {
-    None,
-    Fade,
-    Push,
-    Wipe,
-    Split,
-    Reveal,
-    Cover,
-    Dissolve,
-    Zoom,
-}
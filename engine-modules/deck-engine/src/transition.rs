@@ -0,0 +1,374 @@
+//! Slide transitions.
+//!
+//! A transition plays while the presentation moves from one slide to the
+//! next. [`TransitionState`] turns the time since it started into a
+//! progress from 0 to 1 and into how each of the two slides should be
+//! drawn at that moment. Moving back to the previous slide plays the
+//! transition into the current slide in reverse.
+
+use wolia_math::{Point, Rect};
+
+/// Slide transition.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    /// Transition type.
+    pub kind: TransitionKind,
+    /// Direction the slides move in.
+    pub direction: TransitionDirection,
+    /// Duration in seconds.
+    pub duration: f32,
+    /// How progress speeds up and slows down over the duration.
+    pub easing: Easing,
+}
+
+impl Transition {
+    /// Create a transition of a kind and duration, moving left with the
+    /// default easing.
+    pub fn new(kind: TransitionKind, duration: f32) -> Self {
+        Self {
+            kind,
+            direction: TransitionDirection::default(),
+            duration,
+            easing: Easing::default(),
+        }
+    }
+
+    /// Create a transition that switches slides at once.
+    pub fn none() -> Self {
+        Self::new(TransitionKind::None, 0.0)
+    }
+
+    /// Create a fade transition.
+    pub fn fade(duration: f32) -> Self {
+        Self::new(TransitionKind::Fade, duration)
+    }
+
+    /// Create a push transition.
+    pub fn push(direction: TransitionDirection, duration: f32) -> Self {
+        Self::new(TransitionKind::Push, duration).with_direction(direction)
+    }
+
+    /// Create a wipe transition.
+    pub fn wipe(direction: TransitionDirection, duration: f32) -> Self {
+        Self::new(TransitionKind::Wipe, duration).with_direction(direction)
+    }
+
+    /// Set the direction.
+    pub fn with_direction(mut self, direction: TransitionDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the easing.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Transition types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    None,
+    Fade,
+    Push,
+    Wipe,
+    Split,
+    Reveal,
+    Cover,
+    Dissolve,
+    Zoom,
+}
+
+/// Direction slides move in during a transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransitionDirection {
+    #[default]
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl TransitionDirection {
+    /// A movement of one slide size in this direction.
+    fn unit(self) -> Point {
+        match self {
+            Self::Left => Point::new(-1.0, 0.0),
+            Self::Right => Point::new(1.0, 0.0),
+            Self::Up => Point::new(0.0, -1.0),
+            Self::Down => Point::new(0.0, 1.0),
+        }
+    }
+}
+
+/// How progress through a transition speeds up and slows down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Start slowly and speed up.
+    EaseIn,
+    /// Start quickly and slow down.
+    EaseOut,
+    /// Start and end slowly.
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    /// The eased progress for a linear progress `t`, both from 0 to 1.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Self::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+        }
+    }
+}
+
+/// How to draw one of the slides in a transition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layer {
+    /// Opacity from 0 to 1.
+    pub opacity: f32,
+    /// Offset from its usual place, in slide widths and heights.
+    pub offset: Point,
+    /// The part of the slide shown, in slide widths and heights.
+    pub visible: Rect,
+}
+
+impl Layer {
+    /// A slide drawn in full, in its usual place.
+    pub const SHOWN: Self = Self {
+        opacity: 1.0,
+        offset: Point::ZERO,
+        visible: Rect::new(0.0, 0.0, 1.0, 1.0),
+    };
+
+    /// A slide not drawn at all.
+    pub const HIDDEN: Self = Self {
+        opacity: 0.0,
+        offset: Point::ZERO,
+        visible: Rect::new(0.0, 0.0, 1.0, 1.0),
+    };
+
+    fn offset(offset: Point) -> Self {
+        Self {
+            offset,
+            ..Self::SHOWN
+        }
+    }
+
+    fn visible(visible: Rect) -> Self {
+        Self {
+            visible,
+            ..Self::SHOWN
+        }
+    }
+}
+
+/// How to draw the slide being left and the slide being shown at one
+/// moment of a transition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compositing {
+    /// The slide being left.
+    pub from: Layer,
+    /// The slide being shown.
+    pub to: Layer,
+    /// Whether the slide being shown is drawn over the one being left.
+    pub to_on_top: bool,
+}
+
+/// A transition playing between two slides.
+#[derive(Debug, Clone)]
+pub struct TransitionState {
+    /// The transition played.
+    pub transition: Transition,
+    /// Whether it plays backward, moving to the previous slide.
+    pub reverse: bool,
+}
+
+impl TransitionState {
+    /// Play `transition` forward, into the slide it belongs to.
+    pub fn new(transition: Transition) -> Self {
+        Self {
+            transition,
+            reverse: false,
+        }
+    }
+
+    /// Play `transition` backward, out of the slide it belongs to and
+    /// into the one before it.
+    pub fn reversed(transition: Transition) -> Self {
+        Self {
+            transition,
+            reverse: true,
+        }
+    }
+
+    /// Progress from 0 to 1, `elapsed` seconds after starting. Transitions
+    /// without a duration are finished at once.
+    pub fn progress(&self, elapsed: f32) -> f32 {
+        let duration = self.transition.duration;
+        if duration <= 0.0 || self.transition.kind == TransitionKind::None {
+            return 1.0;
+        }
+        (elapsed / duration).clamp(0.0, 1.0)
+    }
+
+    /// Whether the transition has finished `elapsed` seconds after
+    /// starting.
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        self.progress(elapsed) >= 1.0
+    }
+
+    /// How to draw both slides `elapsed` seconds after starting.
+    pub fn compositing(&self, elapsed: f32) -> Compositing {
+        let eased = self.transition.easing.apply(self.progress(elapsed));
+        if !self.reverse {
+            return forward(&self.transition, eased);
+        }
+        // Backward, the slide being left is the one the transition leads
+        // into, seen at the matching moment going forward.
+        let forward = forward(&self.transition, 1.0 - eased);
+        Compositing {
+            from: forward.to,
+            to: forward.from,
+            to_on_top: !forward.to_on_top,
+        }
+    }
+}
+
+/// How to draw both slides at eased progress `t` playing forward.
+fn forward(transition: &Transition, t: f32) -> Compositing {
+    let direction = transition.direction.unit();
+    let (from, to, to_on_top) = match transition.kind {
+        TransitionKind::None => (Layer::HIDDEN, Layer::SHOWN, true),
+        // Zooming is drawn as a fade until layers can be scaled.
+        TransitionKind::Fade | TransitionKind::Dissolve | TransitionKind::Zoom => {
+            let to = Layer {
+                opacity: t,
+                ..Layer::SHOWN
+            };
+            (Layer::SHOWN, to, true)
+        }
+        TransitionKind::Push => (
+            Layer::offset(direction * t),
+            Layer::offset(direction * (t - 1.0)),
+            true,
+        ),
+        TransitionKind::Cover => (Layer::SHOWN, Layer::offset(direction * (t - 1.0)), true),
+        TransitionKind::Reveal => (Layer::offset(direction * t), Layer::SHOWN, false),
+        TransitionKind::Wipe => {
+            // The edge moves in the direction, uncovering the new slide
+            // from the side it started on.
+            let visible = match transition.direction {
+                TransitionDirection::Left => Rect::new(1.0 - t, 0.0, t, 1.0),
+                TransitionDirection::Right => Rect::new(0.0, 0.0, t, 1.0),
+                TransitionDirection::Up => Rect::new(0.0, 1.0 - t, 1.0, t),
+                TransitionDirection::Down => Rect::new(0.0, 0.0, 1.0, t),
+            };
+            (Layer::SHOWN, Layer::visible(visible), true)
+        }
+        TransitionKind::Split => {
+            let visible = match transition.direction {
+                TransitionDirection::Left | TransitionDirection::Right => {
+                    Rect::new(0.5 - t / 2.0, 0.0, t, 1.0)
+                }
+                TransitionDirection::Up | TransitionDirection::Down => {
+                    Rect::new(0.0, 0.5 - t / 2.0, 1.0, t)
+                }
+            };
+            (Layer::SHOWN, Layer::visible(visible), true)
+        }
+    };
+    Compositing {
+        from,
+        to,
+        to_on_top,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing_edges() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn test_progress_clamps() {
+        let state = TransitionState::new(Transition::fade(2.0));
+        assert_eq!(state.progress(-1.0), 0.0);
+        assert_eq!(state.progress(0.5), 0.25);
+        assert_eq!(state.progress(3.0), 1.0);
+        assert!(!state.is_finished(1.9));
+        assert!(state.is_finished(2.0));
+
+        // Without a duration the new slide shows at once.
+        let instant = TransitionState::new(Transition::fade(0.0));
+        assert_eq!(instant.progress(0.0), 1.0);
+        assert_eq!(instant.compositing(0.0).to, Layer::SHOWN);
+        let none = TransitionState::new(Transition::none());
+        assert_eq!(none.compositing(0.0).from.opacity, 0.0);
+    }
+
+    #[test]
+    fn test_push_moves_both_slides() {
+        let push = Transition::push(TransitionDirection::Left, 1.0).with_easing(Easing::Linear);
+        let state = TransitionState::new(push.clone());
+        let halfway = state.compositing(0.5);
+        assert_eq!(halfway.from.offset, Point::new(-0.5, 0.0));
+        assert_eq!(halfway.to.offset, Point::new(0.5, 0.0));
+        // The new slide starts just off the right edge.
+        assert_eq!(state.compositing(0.0).to.offset, Point::new(1.0, 0.0));
+
+        // Backward, the current slide leaves to the right and the previous
+        // one comes back from the left.
+        let back = TransitionState::reversed(push);
+        let start = back.compositing(0.0);
+        assert_eq!(start.from.offset, Point::ZERO);
+        assert_eq!(start.to.offset, Point::new(-1.0, 0.0));
+        let quarter = back.compositing(0.25);
+        assert_eq!(quarter.from.offset, Point::new(0.25, 0.0));
+        assert_eq!(back.compositing(1.0).to, Layer::SHOWN);
+    }
+
+    #[test]
+    fn test_wipe_uncovers_from_the_start_side() {
+        let wipe = Transition::wipe(TransitionDirection::Left, 1.0).with_easing(Easing::Linear);
+        let halfway = TransitionState::new(wipe.clone()).compositing(0.5);
+        assert_eq!(halfway.to.visible, Rect::new(0.5, 0.0, 0.5, 1.0));
+        assert!(halfway.to_on_top);
+
+        // Reversed, the current slide is wiped away over the previous one.
+        let back = TransitionState::reversed(wipe).compositing(0.25);
+        assert_eq!(back.from.visible, Rect::new(0.25, 0.0, 0.75, 1.0));
+        assert!(!back.to_on_top);
+    }
+}
@@ -0,0 +1,177 @@
+//! Series detection for filling cells from a source run.
+//!
+//! A run of one cell is copied. A longer run continues as a series when
+//! it has one: numbers or dates with a constant step, or text ending in
+//! numbers with a constant step, as in "Item 1", "Item 2". Otherwise the
+//! run is repeated.
+
+use crate::cell::{Cell, CellValue};
+use crate::formula::Formula;
+
+/// How a run of source cells continues.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Series {
+    /// Repeat the source cells.
+    Repeat,
+    /// Numbers from `start`, `step` apart.
+    Number { start: f64, step: f64 },
+    /// Dates from `start`, `step` days apart.
+    Date { start: i64, step: i64 },
+    /// Text with a number after `prefix`, padded to `width` digits.
+    Text {
+        prefix: String,
+        start: i64,
+        step: i64,
+        width: usize,
+    },
+}
+
+impl Series {
+    /// Detect the series in a run of source values.
+    pub(crate) fn detect(values: &[CellValue]) -> Self {
+        let [first, .., last] = values else {
+            return Self::Repeat;
+        };
+        let steps = (values.len() - 1) as i64;
+
+        if let (CellValue::Number(first), CellValue::Number(last)) = (first, last) {
+            let step = (last - first) / steps as f64;
+            let linear = values.iter().enumerate().all(|(i, value)| {
+                matches!(value, CellValue::Number(n)
+                    if (n - (first + step * i as f64)).abs() <= 1e-9 * step.abs().max(1.0))
+            });
+            if linear {
+                return Self::Number {
+                    start: *first,
+                    step,
+                };
+            }
+        }
+
+        if let (CellValue::Date(first), CellValue::Date(last)) = (first, last) {
+            let step = (last - first) / steps;
+            let linear = values
+                .iter()
+                .enumerate()
+                .all(|(i, value)| *value == CellValue::Date(first + step * i as i64));
+            if linear {
+                return Self::Date {
+                    start: *first,
+                    step,
+                };
+            }
+        }
+
+        let numbered: Option<Vec<_>> = values
+            .iter()
+            .map(|value| match value {
+                CellValue::Text(text) => split_trailing_number(text),
+                _ => None,
+            })
+            .collect();
+        if let Some(numbered) = numbered {
+            let (prefix, start, width) = &numbered[0];
+            let step = (numbered[numbered.len() - 1].1 - start) / steps;
+            let linear = numbered
+                .iter()
+                .enumerate()
+                .all(|(i, (p, n, _))| p == prefix && *n == start + step * i as i64);
+            if linear {
+                return Self::Text {
+                    prefix: prefix.clone(),
+                    start: *start,
+                    step,
+                    width: *width,
+                };
+            }
+        }
+
+        Self::Repeat
+    }
+
+    /// The value at `index` along the series, counting the first source
+    /// cell as 0, or `None` to repeat the source cells.
+    pub(crate) fn value_at(&self, index: isize) -> Option<CellValue> {
+        let index = index as i64;
+        match self {
+            Self::Repeat => None,
+            Self::Number { start, step } => Some(CellValue::Number(start + step * index as f64)),
+            Self::Date { start, step } => Some(CellValue::Date(start + step * index)),
+            Self::Text {
+                prefix,
+                start,
+                step,
+                width,
+            } => {
+                // Like other spreadsheets, count back up past zero.
+                let n = (start + step * index).unsigned_abs();
+                Some(CellValue::Text(format!("{}{:0width$}", prefix, n)))
+            }
+        }
+    }
+}
+
+/// The text before a trailing number, the number, and its digit count.
+fn split_trailing_number(text: &str) -> Option<(String, i64, usize)> {
+    let digits = text.len() - text.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let (prefix, number) = text.split_at(text.len() - digits);
+    Some((prefix.to_string(), number.parse().ok()?, digits))
+}
+
+/// A copy of `source` for a cell `rows` down and `cols` right of it, with
+/// its formula's references moved to match.
+pub(crate) fn copy_cell(source: &Cell, rows: isize, cols: isize) -> Cell {
+    let mut cell = source.clone();
+    if let Some(formula) = &source.formula {
+        cell.formula = Some(Formula::translate(formula, rows, cols));
+    }
+    cell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> CellValue {
+        CellValue::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Series::detect(&[CellValue::Number(5.0)]), Series::Repeat);
+        assert_eq!(
+            Series::detect(&[CellValue::Number(1.0), CellValue::Number(4.0)]),
+            Series::Number {
+                start: 1.0,
+                step: 3.0
+            }
+        );
+        assert_eq!(
+            Series::detect(&[
+                CellValue::Number(1.0),
+                CellValue::Number(2.0),
+                CellValue::Number(4.0)
+            ]),
+            Series::Repeat
+        );
+        assert_eq!(Series::detect(&[text("a"), text("b")]), Series::Repeat);
+        assert_eq!(
+            Series::detect(&[CellValue::Number(1.0), text("2")]),
+            Series::Repeat
+        );
+    }
+
+    #[test]
+    fn test_text_series() {
+        let series = Series::detect(&[text("Item 1"), text("Item 2")]);
+        assert_eq!(series.value_at(4), Some(text("Item 5")));
+        assert_eq!(series.value_at(-2), Some(text("Item 1")));
+
+        let series = Series::detect(&[text("Q08"), text("Q10")]);
+        assert_eq!(series.value_at(2), Some(text("Q12")));
+        assert_eq!(Series::detect(&[text("A 1"), text("B 2")]), Series::Repeat);
+    }
+}
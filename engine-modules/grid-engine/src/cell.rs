@@ -3,8 +3,23 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::number_format::{NumberFormat, NumberLocale};
+use crate::{Error, Result};
+
+/// Number of rows a sheet can have.
+pub const MAX_ROWS: usize = 1_048_576;
+
+/// Number of columns a sheet can have, up to column XFD.
+pub const MAX_COLS: usize = 16_384;
+
+/// Serial day of 1970-01-01, the epoch of [`CellValue::Date`].
+const UNIX_EPOCH_SERIAL: i64 = 25_569;
+
+/// Pattern dates are shown with when the cell has no number format.
+const DEFAULT_DATE_FORMAT: &str = "yyyy-mm-dd";
+
 /// A cell reference (row, column).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct CellRef {
     /// Row index (0-based).
     pub row: usize,
@@ -18,50 +33,60 @@ impl CellRef {
         Self { row, col }
     }
 
-    /// Parse from A1 notation (e.g., "B3").
+    /// Parse from A1 notation (e.g., "B3"). Absolute markers, as in
+    /// "$B$3", are accepted and ignored, as is the case of the letters.
     pub fn parse(s: &str) -> Option<Self> {
-        let s = s.trim().to_uppercase();
-        let mut chars = s.chars().peekable();
+        Self::from_label(&s.trim().to_uppercase().replace('$', "")).ok()
+    }
 
-        // Parse column letters
-        let mut col = 0usize;
-        while let Some(&c) = chars.peek() {
-            if c.is_ascii_alphabetic() {
-                col = col * 26 + (c as usize - 'A' as usize + 1);
-                chars.next();
-            } else {
-                break;
-            }
+    /// Parse a label in A1 notation, such as "AA10": uppercase column
+    /// letters, then a row number from 1.
+    pub fn from_label(label: &str) -> Result<Self> {
+        let invalid = || Error::InvalidCellRef(label.to_string());
+        let digits = label
+            .find(|c: char| !c.is_ascii_uppercase())
+            .ok_or_else(invalid)?;
+        let (letters, number) = label.split_at(digits);
+        if letters.is_empty() || number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
         }
 
-        if col == 0 {
-            return None;
+        // Columns count bijectively in base 26: A..Z, then AA..ZZ, and so on.
+        let mut col = 0usize;
+        for c in letters.bytes() {
+            col = col * 26 + (c - b'A' + 1) as usize;
+            if col > MAX_COLS {
+                return Err(invalid());
+            }
         }
-        col -= 1; // Convert to 0-based
-
-        // Parse row number
-        let row_str: String = chars.collect();
-        let row: usize = row_str.parse().ok()?;
-
-        if row == 0 {
-            return None;
+        let row: usize = number.parse().map_err(|_| invalid())?;
+        if row == 0 || row > MAX_ROWS {
+            return Err(invalid());
         }
 
-        Some(Self { row: row - 1, col })
+        Ok(Self {
+            row: row - 1,
+            col: col - 1,
+        })
     }
 
-    /// Convert to A1 notation.
-    pub fn to_a1(&self) -> String {
-        let mut col_str = String::new();
+    /// The column's letters, such as "A" for column 0 and "AA" for 26.
+    pub fn column_label(&self) -> String {
+        let mut label = Vec::new();
         let mut col = self.col + 1;
 
         while col > 0 {
             col -= 1;
-            col_str.insert(0, (b'A' + (col % 26) as u8) as char);
+            label.push(b'A' + (col % 26) as u8);
             col /= 26;
         }
 
-        format!("{}{}", col_str, self.row + 1)
+        label.iter().rev().map(|&c| c as char).collect()
+    }
+
+    /// Convert to A1 notation.
+    pub fn to_a1(&self) -> String {
+        format!("{}{}", self.column_label(), self.row + 1)
     }
 }
 
@@ -105,6 +130,48 @@ impl Cell {
             style: CellStyle::default(),
         }
     }
+
+    /// The cell's number format, or the general format if it has none or
+    /// its pattern is invalid.
+    pub fn number_format(&self) -> NumberFormat {
+        self.style
+            .number_format
+            .as_deref()
+            .map(NumberFormat::parse_or_general)
+            .unwrap_or_default()
+    }
+
+    /// The value as shown through the cell's number format. The value
+    /// itself is left as it is.
+    pub fn formatted_value(&self) -> String {
+        self.formatted_value_in(&NumberLocale::default())
+    }
+
+    /// The value as shown through the cell's number format, with the
+    /// separators of `locale`.
+    pub fn formatted_value_in(&self, locale: &NumberLocale) -> String {
+        let format = self.number_format();
+        match &self.value {
+            CellValue::Number(n) => format.format_number(*n, locale),
+            CellValue::Date(days) => {
+                let serial = (days + UNIX_EPOCH_SERIAL) as f64;
+                if format.is_general() {
+                    NumberFormat::parse_or_general(DEFAULT_DATE_FORMAT)
+                        .format_number(serial, locale)
+                } else {
+                    format.format_number(serial, locale)
+                }
+            }
+            CellValue::Text(text) => format.format_text(text),
+            value => value.to_display_string(),
+        }
+    }
+}
+
+impl From<CellValue> for Cell {
+    fn from(value: CellValue) -> Self {
+        Self::with_value(value)
+    }
 }
 
 /// Cell value types.
@@ -121,7 +188,7 @@ pub enum CellValue {
     Boolean(bool),
     /// Error value.
     Error(String),
-    /// Date value (days since epoch).
+    /// Date value (days since 1970-01-01).
     Date(i64),
 }
 
@@ -149,7 +216,8 @@ impl CellValue {
             Self::Number(n) => format!("{}", n),
             Self::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
             Self::Error(e) => format!("#{}!", e),
-            Self::Date(d) => format!("Date({})", d), // TODO: Format properly
+            Self::Date(d) => NumberFormat::parse_or_general(DEFAULT_DATE_FORMAT)
+                .format_number((d + UNIX_EPOCH_SERIAL) as f64, &NumberLocale::default()),
         }
     }
 }
@@ -203,6 +271,8 @@ mod tests {
         assert_eq!(CellRef::parse("B3"), Some(CellRef::new(2, 1)));
         assert_eq!(CellRef::parse("Z10"), Some(CellRef::new(9, 25)));
         assert_eq!(CellRef::parse("AA1"), Some(CellRef::new(0, 26)));
+        assert_eq!(CellRef::parse("$B$3"), Some(CellRef::new(2, 1)));
+        assert_eq!(CellRef::parse("B$3"), Some(CellRef::new(2, 1)));
     }
 
     #[test]
@@ -212,4 +282,83 @@ mod tests {
         assert_eq!(CellRef::new(9, 25).to_a1(), "Z10");
         assert_eq!(CellRef::new(0, 26).to_a1(), "AA1");
     }
+
+    #[test]
+    fn test_column_label_boundaries() {
+        for (col, label) in [
+            (0, "A"),
+            (25, "Z"),
+            (26, "AA"),
+            (51, "AZ"),
+            (52, "BA"),
+            (701, "ZZ"),
+            (702, "AAA"),
+            (1023, "AMJ"),
+            (MAX_COLS - 1, "XFD"),
+        ] {
+            let cell = CellRef::new(0, col);
+            assert_eq!(cell.column_label(), label);
+            assert_eq!(CellRef::from_label(&format!("{}1", label)).unwrap(), cell);
+        }
+    }
+
+    #[test]
+    fn test_label_round_trip() {
+        for col in (0..MAX_COLS).step_by(7).chain([MAX_COLS - 1]) {
+            let cell = CellRef::new(MAX_ROWS - 1 - col, col);
+            assert_eq!(CellRef::from_label(&cell.to_a1()).unwrap(), cell);
+        }
+    }
+
+    #[test]
+    fn test_malformed_labels() {
+        for label in [
+            "",
+            "aa10",
+            "Aa10",
+            "AA",
+            "10",
+            "A0",
+            "A-1",
+            "A1B",
+            "$A$1",
+            " A1",
+            "XFE1",
+            "A1048577",
+            "ZZZZZZZZZZZZZZZ1",
+        ] {
+            assert!(
+                matches!(CellRef::from_label(label), Err(Error::InvalidCellRef(_))),
+                "{:?} should be invalid",
+                label
+            );
+        }
+        assert_eq!(CellRef::parse("aa10"), Some(CellRef::new(9, 26)));
+        assert_eq!(CellRef::parse("XFE1"), None);
+    }
+
+    #[test]
+    fn test_formatted_value_keeps_value() {
+        let mut cell = Cell::with_value(CellValue::Number(1234.5));
+        cell.style.number_format = Some("$#,##0.00".to_string());
+        assert_eq!(cell.formatted_value(), "$1,234.50");
+        assert_eq!(cell.value, CellValue::Number(1234.5));
+
+        cell.style.number_format = Some("0.0z".to_string());
+        assert_eq!(cell.formatted_value(), "1234.5");
+        assert_eq!(
+            cell.formatted_value_in(&NumberLocale::new(',', '.')),
+            "1234,5"
+        );
+    }
+
+    #[test]
+    fn test_formatted_date_value() {
+        // 2023-03-15 is 19431 days after 1970-01-01.
+        let mut cell = Cell::with_value(CellValue::Date(19431));
+        assert_eq!(cell.formatted_value(), "2023-03-15");
+        assert_eq!(cell.value.to_display_string(), "2023-03-15");
+        cell.style.number_format = Some("d/m/yyyy".to_string());
+        assert_eq!(cell.formatted_value(), "15/3/2023");
+    }
 }
@@ -0,0 +1,251 @@
+//! Dependencies between cells.
+//!
+//! A formula cell depends on the cells its formula reads, its precedents;
+//! it is in turn a dependent of each of them. Edges are kept both ways so
+//! that an edit can find what to recalculate without scanning the sheet.
+//!
+//! Ranges are kept as ranges, however many cells they span, so a formula
+//! reading whole columns costs no more than one reading a cell. The
+//! dependents of a cell include the formulas with a range holding it,
+//! found by testing each such formula's ranges.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::cell::CellRef;
+use crate::selection::CellRange;
+
+/// The precedent and dependent edges of a sheet's formulas.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Cells and ranges each formula cell reads.
+    precedents: BTreeMap<CellRef, Vec<CellRange>>,
+    /// Formula cells reading each cell, of formulas reading single cells
+    /// only.
+    dependents: BTreeMap<CellRef, BTreeSet<CellRef>>,
+    /// Formula cells reading a range of more than one cell.
+    range_readers: BTreeSet<CellRef>,
+}
+
+impl DependencyGraph {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the cells and ranges `cell` reads. None removes its edges,
+    /// as when its formula is cleared.
+    pub fn set_precedents(
+        &mut self,
+        cell: CellRef,
+        precedents: impl IntoIterator<Item = CellRange>,
+    ) {
+        if let Some(old) = self.precedents.remove(&cell)
+            && !self.range_readers.remove(&cell)
+        {
+            for precedent in old {
+                if let Some(dependents) = self.dependents.get_mut(&precedent.start) {
+                    dependents.remove(&cell);
+                    if dependents.is_empty() {
+                        self.dependents.remove(&precedent.start);
+                    }
+                }
+            }
+        }
+
+        let mut unique: Vec<CellRange> = Vec::new();
+        for precedent in precedents {
+            if !unique.contains(&precedent) {
+                unique.push(precedent);
+            }
+        }
+        if unique.is_empty() {
+            return;
+        }
+        if unique.iter().any(|range| range.start != range.end) {
+            self.range_readers.insert(cell);
+        } else {
+            for precedent in &unique {
+                self.dependents
+                    .entry(precedent.start)
+                    .or_default()
+                    .insert(cell);
+            }
+        }
+        self.precedents.insert(cell, unique);
+    }
+
+    /// The cells and ranges `cell` reads.
+    pub fn precedents(&self, cell: CellRef) -> impl Iterator<Item = CellRange> + '_ {
+        self.precedents.get(&cell).into_iter().flatten().copied()
+    }
+
+    /// Whether the formula in `reader` reads `cell`.
+    pub fn reads(&self, reader: CellRef, cell: CellRef) -> bool {
+        self.precedents(reader).any(|range| range.contains(cell))
+    }
+
+    /// The cells that read `cell` directly, each once.
+    pub fn dependents(&self, cell: CellRef) -> impl Iterator<Item = CellRef> + '_ {
+        let direct = self.dependents.get(&cell).into_iter().flatten().copied();
+        let by_range = self
+            .range_readers
+            .iter()
+            .copied()
+            .filter(move |&reader| self.reads(reader, cell));
+        direct.chain(by_range)
+    }
+
+    /// How many of `cells` the formula in `cell` reads, counting each
+    /// once. Whichever of the cells and the ranges' cells are fewer are
+    /// gone through.
+    fn count_read(&self, cell: CellRef, cells: &BTreeSet<CellRef>) -> usize {
+        let ranges = self.precedents.get(&cell).map_or(&[][..], Vec::as_slice);
+        let area = ranges
+            .iter()
+            .map(|range| range.row_count().saturating_mul(range.col_count()))
+            .fold(0, usize::saturating_add);
+        if area <= cells.len() {
+            ranges
+                .iter()
+                .flat_map(CellRange::iter)
+                .filter(|read| cells.contains(read))
+                .collect::<BTreeSet<_>>()
+                .len()
+        } else {
+            cells
+                .iter()
+                .filter(|&&read| ranges.iter().any(|range| range.contains(read)))
+                .count()
+        }
+    }
+
+    /// Every cell that reads `cell`, directly or through other cells.
+    pub fn transitive_dependents(&self, cell: CellRef) -> BTreeSet<CellRef> {
+        let mut found = BTreeSet::new();
+        let mut pending = vec![cell];
+        while let Some(next) = pending.pop() {
+            for dependent in self.dependents(next) {
+                if found.insert(dependent) {
+                    pending.push(dependent);
+                }
+            }
+        }
+        found
+    }
+
+    /// Order `cells` so each comes after those of them it reads. Cells on
+    /// a cycle, or reading one, cannot be ordered, and are returned second.
+    pub fn topological_order(&self, cells: &BTreeSet<CellRef>) -> (Vec<CellRef>, Vec<CellRef>) {
+        // Kahn's algorithm, counting only edges within `cells`.
+        let mut waiting: BTreeMap<CellRef, usize> = cells
+            .iter()
+            .map(|&cell| (cell, self.count_read(cell, cells)))
+            .collect();
+        let mut ready: Vec<CellRef> = waiting
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(&cell, _)| cell)
+            .collect();
+        ready.reverse();
+
+        let mut order = Vec::with_capacity(cells.len());
+        while let Some(cell) = ready.pop() {
+            waiting.remove(&cell);
+            order.push(cell);
+            for dependent in self.dependents(cell) {
+                if let Some(count) = waiting.get_mut(&dependent) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(dependent);
+                    }
+                }
+            }
+        }
+        (order, waiting.into_keys().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(name: &str) -> CellRef {
+        CellRef::parse(name).unwrap()
+    }
+
+    fn range(name: &str) -> CellRange {
+        CellRange::parse(name).unwrap()
+    }
+
+    #[test]
+    fn test_set_precedents_replaces_edges() {
+        let mut graph = DependencyGraph::new();
+        graph.set_precedents(cell("C1"), [range("A1"), range("B1")]);
+        assert_eq!(
+            graph.dependents(cell("A1")).collect::<Vec<_>>(),
+            [cell("C1")]
+        );
+
+        graph.set_precedents(cell("C1"), [range("B1")]);
+        assert_eq!(graph.dependents(cell("A1")).count(), 0);
+        assert_eq!(
+            graph.precedents(cell("C1")).collect::<Vec<_>>(),
+            [range("B1")]
+        );
+
+        graph.set_precedents(cell("C1"), []);
+        assert_eq!(graph.dependents(cell("B1")).count(), 0);
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let mut graph = DependencyGraph::new();
+        // A1 <- B1 <- C1, and A1 <- C1 directly.
+        graph.set_precedents(cell("C1"), [range("B1"), range("A1")]);
+        graph.set_precedents(cell("B1"), [range("A1")]);
+        let dependents = graph.transitive_dependents(cell("A1"));
+        assert_eq!(dependents, BTreeSet::from([cell("B1"), cell("C1")]));
+        let (order, cyclic) = graph.topological_order(&dependents);
+        assert_eq!(order, [cell("B1"), cell("C1")]);
+        assert!(cyclic.is_empty());
+    }
+
+    #[test]
+    fn test_cycle_is_reported() {
+        let mut graph = DependencyGraph::new();
+        graph.set_precedents(cell("A1"), [range("B1")]);
+        graph.set_precedents(cell("B1"), [range("A1")]);
+        graph.set_precedents(cell("C1"), [range("D1")]);
+        let cells = BTreeSet::from([cell("A1"), cell("B1"), cell("C1")]);
+        let (order, cyclic) = graph.topological_order(&cells);
+        assert_eq!(order, [cell("C1")]);
+        assert_eq!(cyclic, [cell("A1"), cell("B1")]);
+    }
+
+    #[test]
+    fn test_ranges_kept_whole() {
+        let mut graph = DependencyGraph::new();
+        // B1 reads every cell of the sheet, and the sum in B2.
+        graph.set_precedents(cell("B1"), [range("A1:XFD1048576"), range("B2")]);
+        graph.set_precedents(cell("B2"), [range("A7")]);
+        assert_eq!(graph.precedents(cell("B1")).count(), 2);
+        assert_eq!(
+            graph.dependents(cell("ZZ900")).collect::<Vec<_>>(),
+            [cell("B1")]
+        );
+        let dependents = graph.transitive_dependents(cell("A7"));
+        assert_eq!(dependents, BTreeSet::from([cell("B1"), cell("B2")]));
+        let (order, cyclic) = graph.topological_order(&dependents);
+        // B1 is in its own range, so it reads itself.
+        assert_eq!(order, [cell("B2")]);
+        assert_eq!(cyclic, [cell("B1")]);
+
+        graph.set_precedents(cell("B1"), [range("A1:A10")]);
+        let dependents = graph.transitive_dependents(cell("A7"));
+        let (order, cyclic) = graph.topological_order(&dependents);
+        assert_eq!(order.len(), 2);
+        assert!(cyclic.is_empty());
+        graph.set_precedents(cell("B1"), []);
+        assert_eq!(graph.dependents(cell("A1")).count(), 0);
+    }
+}
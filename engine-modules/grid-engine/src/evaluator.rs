@@ -1,6 +1,7 @@
 //! Formula evaluation utilities and built-in functions.
 
 use crate::cell::CellValue;
+use crate::formula::FormulaError;
 
 /// Built-in formula functions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -129,6 +130,192 @@ impl Function {
 pub struct Evaluator;
 
 impl Evaluator {
+    /// Call `function` with its arguments, each given as the values of a
+    /// single value or of a range. An error value among the arguments is
+    /// the result.
+    pub fn call(function: Function, args: Vec<Vec<CellValue>>) -> Result<CellValue, FormulaError> {
+        if let Some(error) = args
+            .iter()
+            .flatten()
+            .find(|value| matches!(value, CellValue::Error(_)))
+        {
+            return Ok(error.clone());
+        }
+
+        let arity = |min: usize, max: usize| {
+            if (min..=max).contains(&args.len()) {
+                Ok(())
+            } else {
+                Err(FormulaError::InvalidArgument(format!(
+                    "{} takes {} to {} arguments, not {}",
+                    function.name(),
+                    min,
+                    max,
+                    args.len()
+                )))
+            }
+        };
+        let arg = |index: usize| {
+            args.get(index)
+                .and_then(|values| values.first().cloned())
+                .unwrap_or_default()
+        };
+        let number = |index: usize| -> Result<f64, FormulaError> {
+            match arg(index) {
+                CellValue::Empty => Ok(0.0),
+                value => value.as_number().ok_or_else(|| {
+                    FormulaError::TypeError(format!(
+                        "{} expects a number, not {}",
+                        function.name(),
+                        value.to_display_string()
+                    ))
+                }),
+            }
+        };
+        let text = |index: usize| arg(index).to_display_string();
+        let truthy = |value: &CellValue| value.as_number().is_some_and(|n| n != 0.0);
+        let values = || args.iter().flatten().cloned().collect::<Vec<_>>();
+
+        Ok(match function {
+            Function::Sum => Self::sum(values()),
+            Function::Average => Self::average(values()),
+            Function::Count => Self::count(values()),
+            Function::CountA => Self::counta(values()),
+            Function::Max => Self::max(values()),
+            Function::Min => Self::min(values()),
+            Function::Abs => {
+                arity(1, 1)?;
+                CellValue::Number(number(0)?.abs())
+            }
+            Function::Round => {
+                arity(1, 2)?;
+                Self::round(CellValue::Number(number(0)?), number(1)? as i32)
+            }
+            Function::Floor => {
+                arity(1, 1)?;
+                CellValue::Number(number(0)?.floor())
+            }
+            Function::Ceil => {
+                arity(1, 1)?;
+                CellValue::Number(number(0)?.ceil())
+            }
+            Function::Sqrt => {
+                arity(1, 1)?;
+                Self::sqrt(CellValue::Number(number(0)?))
+            }
+            Function::Power => {
+                arity(2, 2)?;
+                CellValue::Number(number(0)?.powf(number(1)?))
+            }
+            Function::If => {
+                arity(2, 3)?;
+                if truthy(&arg(0)) {
+                    arg(1)
+                } else if args.len() == 3 {
+                    arg(2)
+                } else {
+                    CellValue::Boolean(false)
+                }
+            }
+            Function::And => CellValue::Boolean(values().iter().all(truthy)),
+            Function::Or => CellValue::Boolean(values().iter().any(truthy)),
+            Function::Not => {
+                arity(1, 1)?;
+                CellValue::Boolean(!truthy(&arg(0)))
+            }
+            Function::True_ => CellValue::Boolean(true),
+            Function::False_ => CellValue::Boolean(false),
+            Function::Concatenate => Self::concatenate(values()),
+            Function::Len => {
+                arity(1, 1)?;
+                CellValue::Number(text(0).chars().count() as f64)
+            }
+            Function::Upper => {
+                arity(1, 1)?;
+                Self::upper(arg(0))
+            }
+            Function::Lower => {
+                arity(1, 1)?;
+                Self::lower(arg(0))
+            }
+            Function::Trim => {
+                arity(1, 1)?;
+                CellValue::Text(text(0).split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            Function::Left | Function::Right => {
+                arity(1, 2)?;
+                let count = if args.len() == 2 {
+                    number(1)? as usize
+                } else {
+                    1
+                };
+                let chars: Vec<char> = text(0).chars().collect();
+                let count = count.min(chars.len());
+                let part = if function == Function::Left {
+                    &chars[..count]
+                } else {
+                    &chars[chars.len() - count..]
+                };
+                CellValue::Text(part.iter().collect())
+            }
+            Function::Mid => {
+                arity(3, 3)?;
+                let start = number(1)?;
+                if start < 1.0 {
+                    return Err(FormulaError::InvalidArgument("MID starts at 1".to_string()));
+                }
+                let part = text(0)
+                    .chars()
+                    .skip(start as usize - 1)
+                    .take(number(2)?.max(0.0) as usize)
+                    .collect();
+                CellValue::Text(part)
+            }
+            Function::Find => {
+                arity(2, 3)?;
+                let within: Vec<char> = text(1).chars().collect();
+                let start = if args.len() == 3 {
+                    number(2)? as usize
+                } else {
+                    1
+                };
+                let needle: Vec<char> = text(0).chars().collect();
+                let found = (start.max(1) - 1..=within.len().saturating_sub(needle.len()))
+                    .find(|&i| within[i..].starts_with(&needle));
+                match found {
+                    Some(i) => CellValue::Number((i + 1) as f64),
+                    None => CellValue::Error("VALUE".to_string()),
+                }
+            }
+            Function::Substitute => {
+                arity(3, 3)?;
+                CellValue::Text(text(0).replace(&text(1), &text(2)))
+            }
+            Function::Char => {
+                arity(1, 1)?;
+                let ch = char::from_u32(number(0)? as u32).ok_or_else(|| {
+                    FormulaError::InvalidArgument("CHAR code out of range".to_string())
+                })?;
+                CellValue::Text(ch.to_string())
+            }
+            Function::Code => {
+                arity(1, 1)?;
+                match text(0).chars().next() {
+                    Some(ch) => CellValue::Number(ch as u32 as f64),
+                    None => CellValue::Error("VALUE".to_string()),
+                }
+            }
+            Function::Today | Function::Now => {
+                arity(0, 0)?;
+                let seconds = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_secs())
+                    .unwrap_or_default();
+                CellValue::Date((seconds / 86_400) as i64)
+            }
+        })
+    }
+
     /// Evaluate SUM function.
     pub fn sum(values: Vec<CellValue>) -> CellValue {
         let total: f64 = values.iter().filter_map(|v| v.as_number()).sum();
@@ -311,6 +498,41 @@ mod tests {
         assert_eq!(result, CellValue::Text("Hello World".to_string()));
     }
 
+    #[test]
+    fn test_call() {
+        let number = |n| vec![CellValue::Number(n)];
+        let text = |s: &str| vec![CellValue::Text(s.to_string())];
+        assert_eq!(
+            Evaluator::call(Function::Sum, vec![number(1.0), number(2.0)]).unwrap(),
+            CellValue::Number(3.0)
+        );
+        assert_eq!(
+            Evaluator::call(Function::If, vec![number(0.0), text("yes"), text("no")]).unwrap(),
+            CellValue::Text("no".to_string())
+        );
+        assert_eq!(
+            Evaluator::call(
+                Function::Mid,
+                vec![text("spreadsheet"), number(7.0), number(5.0)]
+            )
+            .unwrap(),
+            CellValue::Text("sheet".to_string())
+        );
+        assert_eq!(
+            Evaluator::call(Function::Find, vec![text("e"), text("sheet"), number(4.0)]).unwrap(),
+            CellValue::Number(4.0)
+        );
+        assert!(Evaluator::call(Function::Abs, vec![]).is_err());
+        assert_eq!(
+            Evaluator::call(
+                Function::Sum,
+                vec![number(1.0), vec![CellValue::Error("REF".to_string())]]
+            )
+            .unwrap(),
+            CellValue::Error("REF".to_string())
+        );
+    }
+
     #[test]
     fn test_sqrt_evaluation() {
         let value = CellValue::Number(16.0);
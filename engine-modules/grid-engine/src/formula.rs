@@ -1,6 +1,10 @@
 //! Formula parsing and evaluation.
 
-use crate::cell::CellValue;
+use std::cmp::Ordering;
+
+use crate::cell::{CellRef, CellValue, MAX_COLS, MAX_ROWS};
+use crate::evaluator::{Evaluator, Function};
+use crate::selection::CellRange;
 
 /// A parsed formula.
 #[derive(Debug, Clone)]
@@ -15,26 +19,92 @@ impl Formula {
     /// Parse a formula string.
     pub fn parse(text: &str) -> Result<Self, FormulaError> {
         let text = text.trim();
-        if !text.starts_with('=') {
+        let Some(body) = text.strip_prefix('=') else {
             return Err(FormulaError::InvalidSyntax(
                 "Formula must start with '='".into(),
             ));
-        }
+        };
 
-        // TODO: Implement full formula parser
+        let mut parser = Parser::new(body);
+        let expr = parser.expression()?;
+        parser.skip_whitespace();
+        if let Some(ch) = parser.peek() {
+            return Err(FormulaError::InvalidSyntax(format!("Unexpected '{}'", ch)));
+        }
         Ok(Self {
             text: text.to_string(),
-            expr: FormulaExpr::Value(CellValue::Number(0.0)),
+            expr,
         })
     }
 
     /// Evaluate the formula.
-    pub fn evaluate(&self, _context: &FormulaContext) -> Result<CellValue, FormulaError> {
-        // TODO: Implement formula evaluation
-        match &self.expr {
-            FormulaExpr::Value(v) => Ok(v.clone()),
-            _ => Ok(CellValue::Number(0.0)),
+    pub fn evaluate(&self, context: &FormulaContext) -> Result<CellValue, FormulaError> {
+        match self.expr.evaluate(context)? {
+            Operand::Value(value) => Ok(value),
+            // A range on its own stands for its top left cell.
+            Operand::Range(values) => Ok(values.into_iter().next().unwrap_or_default()),
+        }
+    }
+
+    /// Rewrite a formula's text for a copy `rows` rows down and `cols`
+    /// columns right, as when filling or pasting it. References without
+    /// `$` markers move with the copy; a reference moved off the sheet
+    /// becomes `#REF!`.
+    pub fn translate(text: &str, rows: isize, cols: isize) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(ch) = rest.chars().next() {
+            let token_len = if ch == '"' {
+                // Strings are copied as they are, doubled quotes included.
+                let mut end = 1;
+                let bytes = rest.as_bytes();
+                while end < bytes.len() {
+                    if bytes[end] == b'"' {
+                        if bytes.get(end + 1) == Some(&b'"') {
+                            end += 2;
+                            continue;
+                        }
+                        end += 1;
+                        break;
+                    }
+                    end += 1;
+                }
+                result.push_str(&rest[..end]);
+                end
+            } else if ch.is_ascii_alphanumeric() || ch == '$' || ch == '_' {
+                let end = rest
+                    .find(|c: char| {
+                        !(c.is_ascii_alphanumeric() || c == '$' || c == '_' || c == '.')
+                    })
+                    .unwrap_or(rest.len());
+                let token = &rest[..end];
+                let is_call = rest[end..].trim_start().starts_with('(');
+                match translate_reference(token, rows, cols) {
+                    Some(moved) if !is_call && !ch.is_ascii_digit() => result.push_str(&moved),
+                    _ => result.push_str(token),
+                }
+                end
+            } else {
+                result.push(ch);
+                ch.len_utf8()
+            };
+            rest = &rest[token_len..];
+        }
+        result
+    }
+
+    /// The cells and ranges the formula reads, each once, in the order
+    /// first read. A single cell is a range of one cell.
+    pub fn references(&self) -> Vec<CellRange> {
+        let mut ranges: Vec<CellRange> = Vec::new();
+        self.expr.collect_references(&mut ranges);
+        let mut unique = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if !unique.contains(&range) {
+                unique.push(range);
+            }
         }
+        unique
     }
 }
 
@@ -44,12 +114,9 @@ pub enum FormulaExpr {
     /// Literal value.
     Value(CellValue),
     /// Cell reference.
-    CellRef(crate::CellRef),
+    CellRef(CellRef),
     /// Range reference.
-    Range {
-        start: crate::CellRef,
-        end: crate::CellRef,
-    },
+    Range { start: CellRef, end: CellRef },
     /// Function call.
     Function {
         name: String,
@@ -92,6 +159,470 @@ pub enum UnaryOp {
     Percent,
 }
 
+impl FormulaExpr {
+    fn collect_references(&self, cells: &mut Vec<CellRange>) {
+        match self {
+            Self::Value(_) => {}
+            Self::CellRef(cell) => cells.push(CellRange::new(*cell, *cell)),
+            Self::Range { start, end } => cells.push(CellRange::new(*start, *end)),
+            Self::Function { args, .. } => {
+                for arg in args {
+                    arg.collect_references(cells);
+                }
+            }
+            Self::BinaryOp { left, right, .. } => {
+                left.collect_references(cells);
+                right.collect_references(cells);
+            }
+            Self::UnaryOp { operand, .. } => operand.collect_references(cells),
+        }
+    }
+
+    fn evaluate(&self, context: &FormulaContext) -> Result<Operand, FormulaError> {
+        let value = match self {
+            Self::Value(value) => value.clone(),
+            Self::CellRef(cell) => (context.get_cell)(*cell).unwrap_or_default(),
+            Self::Range { start, end } => {
+                // Functions skip empty cells, so only the top left cell,
+                // which a range stands for on its own, is read if empty.
+                let range = CellRange::new(*start, *end);
+                let mut values = vec![(context.get_cell)(range.start).unwrap_or_default()];
+                values.extend(
+                    (context.get_range)(range)
+                        .into_iter()
+                        .filter(|(cell, _)| *cell != range.start)
+                        .map(|(_, value)| value),
+                );
+                return Ok(Operand::Range(values));
+            }
+            Self::Function { name, args } => {
+                let function = Function::from_name(name)
+                    .ok_or_else(|| FormulaError::UnknownFunction(name.clone()))?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(context).map(Operand::into_values))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Evaluator::call(function, args)?
+            }
+            Self::BinaryOp { op, left, right } => {
+                let left = left.evaluate(context)?.into_value();
+                let right = right.evaluate(context)?.into_value();
+                binary(*op, left, right)?
+            }
+            Self::UnaryOp { op, operand } => {
+                let value = operand.evaluate(context)?.into_value();
+                if let CellValue::Error(_) = value {
+                    return Ok(Operand::Value(value));
+                }
+                let n = number(&value)?;
+                CellValue::Number(match op {
+                    UnaryOp::Neg => -n,
+                    UnaryOp::Percent => n / 100.0,
+                })
+            }
+        };
+        Ok(Operand::Value(value))
+    }
+}
+
+/// An evaluated expression: a value, or the values of a range, top left
+/// first.
+enum Operand {
+    Value(CellValue),
+    Range(Vec<CellValue>),
+}
+
+impl Operand {
+    fn into_value(self) -> CellValue {
+        match self {
+            Self::Value(value) => value,
+            Self::Range(values) => values.into_iter().next().unwrap_or_default(),
+        }
+    }
+
+    fn into_values(self) -> Vec<CellValue> {
+        match self {
+            Self::Value(value) => vec![value],
+            Self::Range(values) => values,
+        }
+    }
+}
+
+/// A value as a number, with empty cells as zero.
+fn number(value: &CellValue) -> Result<f64, FormulaError> {
+    match value {
+        CellValue::Empty => Ok(0.0),
+        value => value.as_number().ok_or_else(|| {
+            FormulaError::TypeError(format!("{} is not a number", value.to_display_string()))
+        }),
+    }
+}
+
+fn binary(op: BinaryOp, left: CellValue, right: CellValue) -> Result<CellValue, FormulaError> {
+    // Errors in operands pass through.
+    for value in [&left, &right] {
+        if let CellValue::Error(_) = value {
+            return Ok(value.clone());
+        }
+    }
+    let comparison =
+        |expected: fn(Ordering) -> bool| Ok(CellValue::Boolean(expected(compare(&left, &right))));
+    match op {
+        BinaryOp::Concat => Ok(CellValue::Text(
+            left.to_display_string() + &right.to_display_string(),
+        )),
+        BinaryOp::Eq => comparison(|o| o == Ordering::Equal),
+        BinaryOp::Ne => comparison(|o| o != Ordering::Equal),
+        BinaryOp::Lt => comparison(|o| o == Ordering::Less),
+        BinaryOp::Le => comparison(|o| o != Ordering::Greater),
+        BinaryOp::Gt => comparison(|o| o == Ordering::Greater),
+        BinaryOp::Ge => comparison(|o| o != Ordering::Less),
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Pow => {
+            let (a, b) = (number(&left)?, number(&right)?);
+            let result = match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                BinaryOp::Mul => a * b,
+                BinaryOp::Div if b == 0.0 => return Err(FormulaError::DivByZero),
+                BinaryOp::Div => a / b,
+                _ => a.powf(b),
+            };
+            Ok(CellValue::Number(result))
+        }
+    }
+}
+
+/// Order values as spreadsheets do: numbers before text before booleans,
+/// and text without regard to case. An empty cell compares as zero, empty
+/// text or FALSE, whichever the other side is.
+fn compare(left: &CellValue, right: &CellValue) -> Ordering {
+    fn like(value: &CellValue, other: &CellValue) -> CellValue {
+        match (value, other) {
+            (CellValue::Empty, CellValue::Text(_)) => CellValue::Text(String::new()),
+            (CellValue::Empty, CellValue::Boolean(_)) => CellValue::Boolean(false),
+            (CellValue::Empty, _) => CellValue::Number(0.0),
+            (CellValue::Date(days), _) => CellValue::Number(*days as f64),
+            _ => value.clone(),
+        }
+    }
+    fn rank(value: &CellValue) -> u8 {
+        match value {
+            CellValue::Number(_) => 0,
+            CellValue::Text(_) => 1,
+            _ => 2,
+        }
+    }
+    let (left, right) = (like(left, right), like(right, left));
+    match (&left, &right) {
+        (CellValue::Number(a), CellValue::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (CellValue::Text(a), CellValue::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (CellValue::Boolean(a), CellValue::Boolean(b)) => a.cmp(b),
+        _ => rank(&left).cmp(&rank(&right)),
+    }
+}
+
+/// Recursive descent parser for formula bodies.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Consume `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.text[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Result<FormulaExpr, FormulaError> {
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let mut left = self.concatenation()?;
+        loop {
+            // Two-character operators first, so "<=" is not read as "<".
+            let op = [
+                ("<>", BinaryOp::Ne),
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("=", BinaryOp::Eq),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ]
+            .into_iter()
+            .find(|(token, _)| self.eat(token));
+            let Some((_, op)) = op else {
+                return Ok(left);
+            };
+            let right = self.concatenation()?;
+            left = binary_expr(op, left, right);
+        }
+    }
+
+    fn concatenation(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let mut left = self.additive()?;
+        while self.eat("&") {
+            let right = self.additive()?;
+            left = binary_expr(BinaryOp::Concat, left, right);
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = if self.eat("+") {
+                BinaryOp::Add
+            } else if self.eat("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.multiplicative()?;
+            left = binary_expr(op, left, right);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let mut left = self.power()?;
+        loop {
+            let op = if self.eat("*") {
+                BinaryOp::Mul
+            } else if self.eat("/") {
+                BinaryOp::Div
+            } else {
+                return Ok(left);
+            };
+            let right = self.power()?;
+            left = binary_expr(op, left, right);
+        }
+    }
+
+    fn power(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let mut left = self.unary()?;
+        while self.eat("^") {
+            let right = self.unary()?;
+            left = binary_expr(BinaryOp::Pow, left, right);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<FormulaExpr, FormulaError> {
+        if self.eat("-") {
+            let operand = self.unary()?;
+            return Ok(FormulaExpr::UnaryOp {
+                op: UnaryOp::Neg,
+                operand: Box::new(operand),
+            });
+        }
+        if self.eat("+") {
+            return self.unary();
+        }
+        let mut expr = self.primary()?;
+        while self.eat("%") {
+            expr = FormulaExpr::UnaryOp {
+                op: UnaryOp::Percent,
+                operand: Box::new(expr),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<FormulaExpr, FormulaError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(FormulaError::InvalidSyntax(
+                "Unexpected end of formula".into(),
+            )),
+            Some('(') => {
+                self.bump();
+                let expr = self.expression()?;
+                if !self.eat(")") {
+                    return Err(FormulaError::InvalidSyntax("Expected ')'".into()));
+                }
+                Ok(expr)
+            }
+            Some('"') => self.string(),
+            Some(ch) if ch.is_ascii_digit() || ch == '.' => self.number(),
+            Some(ch) if ch.is_ascii_alphabetic() || ch == '$' || ch == '_' => self.name(),
+            Some(ch) => Err(FormulaError::InvalidSyntax(format!("Unexpected '{}'", ch))),
+        }
+    }
+
+    fn string(&mut self) -> Result<FormulaExpr, FormulaError> {
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                // A doubled quote stands for one.
+                Some('"') if self.peek() == Some('"') => {
+                    self.bump();
+                    text.push('"');
+                }
+                Some('"') => return Ok(FormulaExpr::Value(CellValue::Text(text))),
+                Some(ch) => text.push(ch),
+                None => return Err(FormulaError::InvalidSyntax("Unclosed string".into())),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|ch| ch.is_ascii_digit() || ch == '.')
+        {
+            self.bump();
+        }
+        // An exponent, as in 1.5E-3.
+        let rest = &self.text[self.pos..];
+        if rest.starts_with(['e', 'E']) {
+            let digits = rest[1..].trim_start_matches(['+', '-']);
+            if digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+                self.pos += rest.len() - digits.len();
+                while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    self.bump();
+                }
+            }
+        }
+        let literal = &self.text[start..self.pos];
+        literal
+            .parse()
+            .map(|n| FormulaExpr::Value(CellValue::Number(n)))
+            .map_err(|_| FormulaError::InvalidSyntax(format!("Invalid number '{}'", literal)))
+    }
+
+    /// A cell reference, range, function call or boolean.
+    fn name(&mut self) -> Result<FormulaExpr, FormulaError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '$' || ch == '_' || ch == '.')
+        {
+            self.bump();
+        }
+        let name = &self.text[start..self.pos];
+
+        if self.eat("(") {
+            let mut args = Vec::new();
+            if !self.eat(")") {
+                loop {
+                    args.push(self.expression()?);
+                    if self.eat(")") {
+                        break;
+                    }
+                    if !self.eat(",") {
+                        return Err(FormulaError::InvalidSyntax(format!(
+                            "Expected ',' or ')' in {}",
+                            name
+                        )));
+                    }
+                }
+            }
+            return Ok(FormulaExpr::Function {
+                name: name.to_uppercase(),
+                args,
+            });
+        }
+
+        if let Some(cell) = CellRef::parse(name) {
+            let checkpoint = self.pos;
+            if self.eat(":") {
+                self.skip_whitespace();
+                let end_start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '$')
+                {
+                    self.bump();
+                }
+                let end = CellRef::parse(&self.text[end_start..self.pos]).ok_or_else(|| {
+                    FormulaError::InvalidRef(self.text[start..self.pos].to_string())
+                })?;
+                return Ok(FormulaExpr::Range { start: cell, end });
+            }
+            self.pos = checkpoint;
+            return Ok(FormulaExpr::CellRef(cell));
+        }
+
+        match name.to_uppercase().as_str() {
+            "TRUE" => Ok(FormulaExpr::Value(CellValue::Boolean(true))),
+            "FALSE" => Ok(FormulaExpr::Value(CellValue::Boolean(false))),
+            _ => Err(FormulaError::InvalidRef(name.to_string())),
+        }
+    }
+}
+
+/// `token` moved by `rows` and `cols` if it is a cell reference, keeping
+/// its `$` markers.
+fn translate_reference(token: &str, rows: isize, cols: isize) -> Option<String> {
+    let (col_fixed, rest) = match token.strip_prefix('$') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let letters = rest.find(|c: char| !c.is_ascii_alphabetic())?;
+    let (col_label, rest) = rest.split_at(letters);
+    let (row_fixed, row_label) = match rest.strip_prefix('$') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let cell = CellRef::from_label(&format!("{}{}", col_label.to_uppercase(), row_label)).ok()?;
+
+    let shift = |index: usize, fixed: bool, by: isize, max: usize| {
+        if fixed {
+            return Some(index);
+        }
+        index.checked_add_signed(by).filter(|&index| index < max)
+    };
+    let moved =
+        shift(cell.row, row_fixed, rows, MAX_ROWS).zip(shift(cell.col, col_fixed, cols, MAX_COLS));
+    let Some((row, col)) = moved else {
+        return Some("#REF!".to_string());
+    };
+    let moved = CellRef::new(row, col);
+    Some(format!(
+        "{}{}{}{}",
+        if col_fixed { "$" } else { "" },
+        moved.column_label(),
+        if row_fixed { "$" } else { "" },
+        row + 1
+    ))
+}
+
+fn binary_expr(op: BinaryOp, left: FormulaExpr, right: FormulaExpr) -> FormulaExpr {
+    FormulaExpr::BinaryOp {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
 /// Formula errors.
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum FormulaError {
@@ -117,8 +648,125 @@ pub enum FormulaError {
     CircularRef,
 }
 
+impl FormulaError {
+    /// The error value a cell shows for this error, such as `#DIV/0!`.
+    pub fn to_cell_value(&self) -> CellValue {
+        let code = match self {
+            Self::InvalidSyntax(_) => "SYNTAX",
+            Self::DivByZero => "DIV/0",
+            Self::InvalidRef(_) => "REF",
+            Self::UnknownFunction(_) => "NAME",
+            Self::InvalidArgument(_) | Self::TypeError(_) => "VALUE",
+            Self::CircularRef => "CIRCULAR",
+        };
+        CellValue::Error(code.to_string())
+    }
+}
+
 /// Formula evaluation context.
 pub struct FormulaContext<'a> {
     /// Cell value lookup function.
-    pub get_cell: &'a dyn Fn(crate::CellRef) -> Option<CellValue>,
+    pub get_cell: &'a dyn Fn(CellRef) -> Option<CellValue>,
+    /// The cells of a range holding a value, with their values, row by
+    /// row.
+    pub get_range: &'a dyn Fn(CellRange) -> Vec<(CellRef, CellValue)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str) -> CellValue {
+        let cells = [
+            (CellRef::new(0, 0), CellValue::Number(2.0)),
+            (CellRef::new(0, 1), CellValue::Text("x".to_string())),
+            (CellRef::new(1, 0), CellValue::Number(3.0)),
+        ];
+        let get_cell = |cell: CellRef| {
+            cells
+                .iter()
+                .find(|(at, _)| *at == cell)
+                .map(|(_, value)| value.clone())
+        };
+        let get_range = |range: CellRange| {
+            cells
+                .iter()
+                .filter(|(at, _)| range.contains(*at))
+                .cloned()
+                .collect()
+        };
+        Formula::parse(text)
+            .and_then(|formula| {
+                formula.evaluate(&FormulaContext {
+                    get_cell: &get_cell,
+                    get_range: &get_range,
+                })
+            })
+            .unwrap_or_else(|error| error.to_cell_value())
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(evaluate("=1+2*3"), CellValue::Number(7.0));
+        assert_eq!(evaluate("=(1+2)*3"), CellValue::Number(9.0));
+        assert_eq!(evaluate("=2^3^2"), CellValue::Number(64.0));
+        assert_eq!(evaluate("=-2^2"), CellValue::Number(4.0));
+        assert_eq!(evaluate("=50%*A1"), CellValue::Number(1.0));
+        assert_eq!(evaluate("=1+1=2"), CellValue::Boolean(true));
+        assert_eq!(evaluate("=1.5E+1"), CellValue::Number(15.0));
+    }
+
+    #[test]
+    fn test_references_and_functions() {
+        assert_eq!(evaluate("=A1*$A$2"), CellValue::Number(6.0));
+        assert_eq!(evaluate("=SUM(A1:A3)"), CellValue::Number(5.0));
+        assert_eq!(evaluate("=SUM(A1:XFD1048576)"), CellValue::Number(5.0));
+        assert_eq!(evaluate("=COUNTA(B1:C2)"), CellValue::Number(1.0));
+        assert_eq!(
+            evaluate("=if(A1>A2, \"big\", B1&\"y\")"),
+            CellValue::Text("xy".to_string())
+        );
+        assert_eq!(
+            evaluate("=\"say \"\"hi\"\"\""),
+            CellValue::Text("say \"hi\"".to_string())
+        );
+        assert_eq!(evaluate("=C9+1"), CellValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(evaluate("=1/0"), CellValue::Error("DIV/0".to_string()));
+        assert_eq!(evaluate("=(1/0)+1"), CellValue::Error("DIV/0".to_string()));
+        assert_eq!(evaluate("=NOPE(1)"), CellValue::Error("NAME".to_string()));
+        assert_eq!(evaluate("=B1+1"), CellValue::Error("VALUE".to_string()));
+        assert!(Formula::parse("=1+").is_err());
+        assert!(Formula::parse("=(1").is_err());
+        assert!(Formula::parse("1+1").is_err());
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(Formula::translate("=A1+B2", 1, 0), "=A2+B3");
+        assert_eq!(
+            Formula::translate("=SUM($A1:A$2)*$C$3", 2, 1),
+            "=SUM($A3:B$2)*$C$3"
+        );
+        assert_eq!(
+            Formula::translate("=\"A1\"&LOG10(Z9)", 0, 1),
+            "=\"A1\"&LOG10(AA9)"
+        );
+        assert_eq!(Formula::translate("=1.5E3+A2", -1, 0), "=1.5E3+A1");
+        assert_eq!(Formula::translate("=A1", -1, 0), "=#REF!");
+    }
+
+    #[test]
+    fn test_references() {
+        let formula = Formula::parse("=A1+SUM(A1:B2)+A1+SUM(A1:XFD1048576)").unwrap();
+        let names: Vec<String> = formula
+            .references()
+            .iter()
+            .map(|range| range.to_range_string())
+            .collect();
+        assert_eq!(names, ["A1:A1", "A1:B2", "A1:XFD1048576"]);
+    }
 }
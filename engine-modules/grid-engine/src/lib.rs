@@ -6,24 +6,30 @@
 //! - Cell model and storage
 //! - Formula parsing and evaluation
 //! - Cell references and ranges
+//! - Number formats
 //! - Data validation
 //! - Sorting and filtering
 
+mod autofill;
 pub mod cell;
+pub mod dependency;
 pub mod evaluator;
 pub mod formula;
+pub mod number_format;
 pub mod selection;
 pub mod sheet;
 pub mod spreadsheet;
 pub mod view;
 
-pub use cell::{Cell, CellRef, CellValue};
+pub use cell::{Cell, CellRef, CellValue, MAX_COLS, MAX_ROWS};
+pub use dependency::DependencyGraph;
 pub use evaluator::{Evaluator, Function};
 pub use formula::{Formula, FormulaContext, FormulaError};
+pub use number_format::{NumberFormat, NumberLocale};
 pub use selection::{CellRange, Selection};
 pub use sheet::Sheet;
 pub use spreadsheet::Spreadsheet;
-pub use view::GridView;
+pub use view::{GridView, VisibleRange};
 
 /// Result type for grid operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -42,4 +48,7 @@ pub enum Error {
 
     #[error("Invalid range: {0}")]
     InvalidRange(String),
+
+    #[error("Invalid number format: {0}")]
+    InvalidFormat(String),
 }
@@ -0,0 +1,871 @@
+//! Number formats.
+//!
+//! A number format is a display pattern in the spreadsheet dialect, such as
+//! `#,##0.00`, `0%`, `$#,##0` or `yyyy-mm-dd hh:mm`. It changes how a
+//! value is shown, never the value stored in the cell.
+//!
+//! A pattern has up to four sections separated by `;`: for positive
+//! numbers, negative numbers, zero, and text. With one section, negative
+//! numbers are shown with a leading minus sign. Dates and times are
+//! serial numbers: whole days since 1899-12-30, with the time of day as
+//! the fraction.
+
+use std::fmt::Write;
+
+use crate::{Error, Result};
+
+/// Separators used when showing numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberLocale {
+    /// Separator between the whole and fractional parts.
+    pub decimal_separator: char,
+    /// Separator between groups of thousands.
+    pub thousands_separator: char,
+}
+
+impl NumberLocale {
+    /// Create a locale with the given separators.
+    pub fn new(decimal_separator: char, thousands_separator: char) -> Self {
+        Self {
+            decimal_separator,
+            thousands_separator,
+        }
+    }
+}
+
+impl Default for NumberLocale {
+    fn default() -> Self {
+        Self::new('.', ',')
+    }
+}
+
+/// A parsed number format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+    /// The sections, or none for the general format.
+    sections: Vec<Section>,
+}
+
+impl NumberFormat {
+    /// The general format, showing numbers as they are.
+    pub fn general() -> Self {
+        Self {
+            sections: Vec::new(),
+        }
+    }
+
+    /// Parse a pattern.
+    pub fn parse(pattern: &str) -> Result<Self> {
+        if pattern.trim().is_empty() || pattern.trim().eq_ignore_ascii_case("general") {
+            return Ok(Self::general());
+        }
+        let sections = split_sections(pattern)?
+            .into_iter()
+            .map(|section| Section::parse(section).map_err(|e| invalid(pattern, e)))
+            .collect::<Result<Vec<_>>>()?;
+        if sections.len() > 4 {
+            return Err(invalid(pattern, "more than four sections"));
+        }
+        Ok(Self { sections })
+    }
+
+    /// Parse a pattern, falling back to the general format if it is
+    /// invalid.
+    pub fn parse_or_general(pattern: &str) -> Self {
+        Self::parse(pattern).unwrap_or_else(|_| Self::general())
+    }
+
+    /// Whether this is the general format.
+    pub fn is_general(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Show a number.
+    pub fn format_number(&self, value: f64, locale: &NumberLocale) -> String {
+        if !value.is_finite() {
+            return general(value, locale);
+        }
+        let numeric: Vec<&Section> = self
+            .sections
+            .iter()
+            .filter(|section| !section.is_text())
+            .collect();
+        let (section, value, signed) = match numeric.as_slice() {
+            [] => return general(value, locale),
+            [only] => (*only, value, true),
+            [positive, negative] => {
+                if value < 0.0 {
+                    (*negative, -value, false)
+                } else {
+                    (*positive, value, false)
+                }
+            }
+            [positive, negative, zero, ..] => {
+                if value < 0.0 {
+                    (*negative, -value, false)
+                } else if value == 0.0 {
+                    (*zero, value, false)
+                } else {
+                    (*positive, value, false)
+                }
+            }
+        };
+        section.format_number(value, signed, locale)
+    }
+
+    /// Show text, through the text section if there is one.
+    pub fn format_text(&self, text: &str) -> String {
+        match self.sections.iter().find(|section| section.is_text()) {
+            Some(section) => section.format_text(text),
+            None => text.to_string(),
+        }
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::general()
+    }
+}
+
+fn invalid(pattern: &str, reason: &str) -> Error {
+    Error::InvalidFormat(format!("{:?}: {}", pattern, reason))
+}
+
+/// Split a pattern at the `;`s outside quotes, brackets and escapes.
+fn split_sections(pattern: &str) -> Result<Vec<&str>> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut chars = pattern.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                chars
+                    .find(|&(_, ch)| ch == '"')
+                    .ok_or_else(|| invalid(pattern, "unclosed quote"))?;
+            }
+            '[' => {
+                chars
+                    .find(|&(_, ch)| ch == ']')
+                    .ok_or_else(|| invalid(pattern, "unclosed bracket"))?;
+            }
+            '\\' | '_' | '*' => {
+                chars
+                    .next()
+                    .ok_or_else(|| invalid(pattern, "nothing after escape"))?;
+            }
+            ';' => {
+                sections.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    sections.push(&pattern[start..]);
+    Ok(sections)
+}
+
+/// A part of a section.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Text shown as is.
+    Literal(String),
+    /// A digit placeholder: `0` shows a zero, `#` nothing and `?` a space
+    /// where there is no digit.
+    Digit(char),
+    /// The decimal point.
+    Point,
+    /// A comma, grouping thousands or, after the digits, scaling.
+    Comma,
+    /// Multiply by 100.
+    Percent,
+    /// Scientific notation, showing the exponent's sign if `true` even
+    /// when it is positive.
+    Exponent(bool),
+    /// The value shown in the general format.
+    General,
+    /// The text value.
+    Text,
+    /// A date or time field.
+    Date(DateField),
+    /// AM or PM, as `AM/PM` or, if `true`, `A/P`.
+    AmPm(bool),
+}
+
+/// A date or time field, with how many letters it was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateField {
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Minute(usize),
+    Second(usize),
+}
+
+/// One section of a pattern.
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    tokens: Vec<Token>,
+}
+
+impl Section {
+    fn parse(section: &str) -> std::result::Result<Self, &'static str> {
+        let mut tokens = Vec::new();
+        let mut chars = section.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let token = match ch {
+                '"' => {
+                    let mut literal = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(ch) => literal.push(ch),
+                            None => return Err("unclosed quote"),
+                        }
+                    }
+                    Token::Literal(literal)
+                }
+                '\\' => Token::Literal(chars.next().ok_or("nothing after escape")?.to_string()),
+                // Padding to the width of a character is shown as a
+                // space, and repeating one to fill the cell as nothing.
+                '_' => {
+                    chars.next().ok_or("nothing after escape")?;
+                    Token::Literal(" ".to_string())
+                }
+                '*' => {
+                    chars.next().ok_or("nothing after escape")?;
+                    continue;
+                }
+                '[' => {
+                    let mut content = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(ch) => content.push(ch),
+                            None => return Err("unclosed bracket"),
+                        }
+                    }
+                    match bracket(&content)? {
+                        Some(literal) => Token::Literal(literal),
+                        None => continue,
+                    }
+                }
+                '0' | '#' | '?' => Token::Digit(ch),
+                '.' => Token::Point,
+                ',' => Token::Comma,
+                '%' => Token::Percent,
+                '@' => Token::Text,
+                'E' | 'e' if matches!(chars.peek(), Some('+' | '-')) => {
+                    Token::Exponent(chars.next() == Some('+'))
+                }
+                'G' | 'g' => {
+                    let rest: String = chars.clone().take(6).collect();
+                    if !rest.eq_ignore_ascii_case("eneral") {
+                        return Err("unknown letter");
+                    }
+                    chars.nth(5);
+                    Token::General
+                }
+                'A' | 'a' => {
+                    let rest: String = chars.clone().take(4).collect();
+                    if rest.eq_ignore_ascii_case("M/PM") {
+                        chars.nth(3);
+                        Token::AmPm(false)
+                    } else if rest.get(..2).is_some_and(|r| r.eq_ignore_ascii_case("/P")) {
+                        chars.nth(1);
+                        Token::AmPm(true)
+                    } else {
+                        return Err("unknown letter");
+                    }
+                }
+                'y' | 'Y' | 'm' | 'M' | 'd' | 'D' | 'h' | 'H' | 's' | 'S' => {
+                    let mut count = 1;
+                    while chars
+                        .next_if(|next| next.eq_ignore_ascii_case(&ch))
+                        .is_some()
+                    {
+                        count += 1;
+                    }
+                    Token::Date(match ch.to_ascii_lowercase() {
+                        'y' => DateField::Year(count),
+                        'm' => DateField::Month(count),
+                        'd' => DateField::Day(count),
+                        'h' => DateField::Hour(count),
+                        _ => DateField::Second(count),
+                    })
+                }
+                ch if ch.is_ascii_alphabetic() => return Err("unknown letter"),
+                ch => Token::Literal(ch.to_string()),
+            };
+            tokens.push(token);
+        }
+
+        resolve_minutes(&mut tokens);
+        let is_date = tokens.iter().any(|token| matches!(token, Token::Date(_)));
+        let is_number = tokens.iter().any(|token| {
+            matches!(
+                token,
+                Token::Digit(_) | Token::Percent | Token::Exponent(_) | Token::General
+            )
+        });
+        if is_date && is_number {
+            return Err("mixes dates with digits");
+        }
+        if tokens.contains(&Token::Text) && (is_date || is_number) {
+            return Err("mixes text with numbers");
+        }
+        if let Some(exponent) = tokens
+            .iter()
+            .position(|token| matches!(token, Token::Exponent(_)))
+        {
+            let digits_after = tokens[exponent..]
+                .iter()
+                .any(|token| matches!(token, Token::Digit(_)));
+            if !digits_after {
+                return Err("no digits in exponent");
+            }
+        }
+        Ok(Self { tokens })
+    }
+
+    fn is_text(&self) -> bool {
+        self.tokens.contains(&Token::Text)
+    }
+
+    fn is_date(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|token| matches!(token, Token::Date(_) | Token::AmPm(_)))
+    }
+
+    fn format_text(&self, text: &str) -> String {
+        let mut out = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(literal) => out.push_str(literal),
+                Token::Text => out.push_str(text),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    fn format_number(&self, value: f64, signed: bool, locale: &NumberLocale) -> String {
+        if self.is_date() {
+            return match format_date(&self.tokens, value) {
+                Some(date) => date,
+                None => general(value, locale),
+            };
+        }
+        // A section of literals alone shows just them, as for zero in
+        // `0;-0;"none"`.
+        if !self
+            .tokens
+            .iter()
+            .any(|token| matches!(token, Token::Digit(_) | Token::General))
+        {
+            return literals(&self.tokens);
+        }
+        if self.tokens.contains(&Token::General) {
+            let mut out = String::new();
+            for token in &self.tokens {
+                match token {
+                    Token::Literal(literal) => out.push_str(literal),
+                    Token::General => out.push_str(&general(value, locale)),
+                    _ => {}
+                }
+            }
+            return out;
+        }
+        format_digits(&self.tokens, value, signed, locale)
+    }
+}
+
+/// The literal shown for bracketed `content`, or `None` for a color.
+fn bracket(content: &str) -> std::result::Result<Option<String>, &'static str> {
+    const COLORS: [&str; 8] = [
+        "black", "blue", "cyan", "green", "magenta", "red", "white", "yellow",
+    ];
+    if let Some(currency) = content.strip_prefix('$') {
+        // A currency symbol and the locale it is from, as in `[$€-407]`.
+        return Ok(Some(currency.split('-').next().unwrap_or("").to_string()));
+    }
+    let lower = content.to_ascii_lowercase();
+    let numbered_color = lower
+        .strip_prefix("color")
+        .is_some_and(|n| n.parse::<u8>().is_ok());
+    if COLORS.contains(&lower.as_str()) || numbered_color {
+        Ok(None)
+    } else {
+        Err("unsupported bracket")
+    }
+}
+
+/// Make the `m`s meaning minutes into minutes: those after an hour or
+/// before a second, with only literals between.
+fn resolve_minutes(tokens: &mut [Token]) {
+    let is_time = |token: Option<&Token>, field: fn(&DateField) -> bool| match token {
+        Some(Token::Date(date)) => field(date),
+        _ => false,
+    };
+    for i in 0..tokens.len() {
+        let Token::Date(DateField::Month(count)) = tokens[i] else {
+            continue;
+        };
+        if count > 2 {
+            continue;
+        }
+        let previous = tokens[..i]
+            .iter()
+            .rev()
+            .find(|token| !matches!(token, Token::Literal(_)));
+        let next = tokens[i + 1..]
+            .iter()
+            .find(|token| !matches!(token, Token::Literal(_)));
+        if is_time(previous, |field| matches!(field, DateField::Hour(_)))
+            || is_time(next, |field| matches!(field, DateField::Second(_)))
+        {
+            tokens[i] = Token::Date(DateField::Minute(count));
+        }
+    }
+}
+
+/// A number in the general format: as it is, with the locale's decimal
+/// separator.
+fn general(value: f64, locale: &NumberLocale) -> String {
+    let text = format!("{}", value);
+    if locale.decimal_separator == '.' {
+        text
+    } else {
+        text.replace('.', &locale.decimal_separator.to_string())
+    }
+}
+
+/// Show a number through a section of digit placeholders.
+fn format_digits(tokens: &[Token], value: f64, signed: bool, locale: &NumberLocale) -> String {
+    let exponent_at = tokens
+        .iter()
+        .position(|token| matches!(token, Token::Exponent(_)));
+    let mantissa_tokens = &tokens[..exponent_at.unwrap_or(tokens.len())];
+    let point_at = mantissa_tokens
+        .iter()
+        .position(|token| *token == Token::Point);
+    let integer_tokens = &mantissa_tokens[..point_at.unwrap_or(mantissa_tokens.len())];
+    let fraction_tokens = point_at.map_or(&[][..], |at| &mantissa_tokens[at + 1..]);
+
+    // Commas between integer digits group thousands; those right after
+    // the last digit divide by a thousand each.
+    let first_digit = integer_tokens
+        .iter()
+        .position(|token| matches!(token, Token::Digit(_)));
+    let last_digit = integer_tokens
+        .iter()
+        .rposition(|token| matches!(token, Token::Digit(_)));
+    let mut grouping = false;
+    let mut scale = 1.0;
+    if let (Some(first), Some(last)) = (first_digit, last_digit) {
+        grouping = integer_tokens[first..last].contains(&Token::Comma);
+        for token in &integer_tokens[last + 1..] {
+            match token {
+                Token::Comma => scale /= 1000.0,
+                Token::Literal(_) => {}
+                _ => break,
+            }
+        }
+    }
+    let percents = tokens
+        .iter()
+        .filter(|token| **token == Token::Percent)
+        .count();
+    let mut magnitude = value.abs() * scale * 100f64.powi(percents as i32);
+
+    let fraction_digits = fraction_tokens
+        .iter()
+        .filter(|token| matches!(token, Token::Digit(_)))
+        .count();
+    let mut exponent = 0i32;
+    if exponent_at.is_some() && magnitude != 0.0 {
+        exponent = magnitude.log10().floor() as i32;
+        magnitude /= 10f64.powi(exponent);
+        // Rounding may carry the mantissa up to 10.
+        if round(magnitude, fraction_digits).0.len() > 1 {
+            magnitude /= 10.0;
+            exponent += 1;
+        }
+    }
+    let (whole, fraction) = round(magnitude, fraction_digits);
+    let whole = whole.trim_start_matches('0');
+
+    let mut out = String::new();
+    let is_zero = whole.is_empty() && fraction.bytes().all(|digit| digit == b'0');
+    if signed && value < 0.0 && !is_zero {
+        out.push('-');
+    }
+    out.push_str(&format_integer(integer_tokens, whole, grouping, locale));
+    if point_at.is_some() {
+        out.push(locale.decimal_separator);
+        out.push_str(&format_fraction(fraction_tokens, &fraction));
+    }
+    if let Some(at) = exponent_at {
+        out.push_str(&format_exponent(&tokens[at..], exponent));
+    }
+    out
+}
+
+/// The whole and fractional digits of `magnitude` rounded to `places`,
+/// halves away from zero. It is first taken to 15 significant digits, so
+/// that 2.675 rounds up as written rather than down as stored.
+fn round(magnitude: f64, places: usize) -> (String, String) {
+    let significant: f64 = format!("{:.14e}", magnitude).parse().unwrap_or(magnitude);
+    let scaled = significant * 10f64.powi(places as i32);
+    let digits = if scaled < 1e18 {
+        format!("{:0width$}", scaled.round() as u64, width = places + 1)
+    } else {
+        format!("{:.*}", places, significant).replace('.', "")
+    };
+    let (whole, fraction) = digits.split_at(digits.len() - places);
+    (whole.to_string(), fraction.to_string())
+}
+
+/// Show the whole part's `digits` through the placeholders before the
+/// point. Each placeholder takes a digit from the right, and the first
+/// takes all those left over.
+fn format_integer(tokens: &[Token], digits: &str, grouping: bool, locale: &NumberLocale) -> String {
+    let placeholders = tokens
+        .iter()
+        .filter(|token| matches!(token, Token::Digit(_)))
+        .count();
+    // Without placeholders, the digits go just before the point.
+    if placeholders == 0 {
+        return literals(tokens) + digits;
+    }
+
+    let mut remaining = digits;
+    let mut placeholder = placeholders;
+    let mut reversed = String::new();
+    let mut grouped = 0;
+    for token in tokens.iter().rev() {
+        match token {
+            Token::Digit(kind) => {
+                placeholder -= 1;
+                let split = if placeholder == 0 {
+                    0
+                } else {
+                    remaining.len().saturating_sub(1)
+                };
+                let (rest, taken) = remaining.split_at(split);
+                remaining = rest;
+                let shown = match (taken, kind) {
+                    ("", '0') => "0",
+                    ("", '?') => " ",
+                    ("", _) => "",
+                    (taken, _) => taken,
+                };
+                for ch in shown.chars().rev() {
+                    if ch.is_ascii_digit() {
+                        if grouping && grouped > 0 && grouped % 3 == 0 {
+                            reversed.push(locale.thousands_separator);
+                        }
+                        grouped += 1;
+                    }
+                    reversed.push(ch);
+                }
+            }
+            Token::Literal(literal) => reversed.extend(literal.chars().rev()),
+            Token::Percent => reversed.push('%'),
+            _ => {}
+        }
+    }
+    reversed.chars().rev().collect()
+}
+
+/// Show the fractional part's `digits` through the placeholders after the
+/// point, one each. Trailing zeros are dropped where `#` stands and shown
+/// as spaces where `?` does.
+fn format_fraction(tokens: &[Token], digits: &str) -> String {
+    let digits = digits.as_bytes();
+    let mut kept = digits.len();
+    let kinds: Vec<char> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Digit(kind) => Some(*kind),
+            _ => None,
+        })
+        .collect();
+    while kept > 0 && kinds[kept - 1] != '0' && digits[kept - 1] == b'0' {
+        kept -= 1;
+    }
+
+    let mut out = String::new();
+    let mut placeholder = 0;
+    for token in tokens {
+        match token {
+            Token::Digit(kind) => {
+                if placeholder < kept {
+                    out.push(digits[placeholder] as char);
+                } else if *kind == '?' {
+                    out.push(' ');
+                }
+                placeholder += 1;
+            }
+            Token::Literal(literal) => out.push_str(literal),
+            Token::Percent => out.push('%'),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Show an exponent through the tokens from `E+` or `E-` on. The
+/// placeholders set its least number of digits.
+fn format_exponent(tokens: &[Token], exponent: i32) -> String {
+    let mut out = String::from("E");
+    match tokens.first() {
+        _ if exponent < 0 => out.push('-'),
+        Some(Token::Exponent(true)) => out.push('+'),
+        _ => {}
+    }
+    let width = tokens
+        .iter()
+        .filter(|token| **token == Token::Digit('0'))
+        .count();
+    let mut shown = false;
+    for token in &tokens[1..] {
+        match token {
+            Token::Digit(_) if !shown => {
+                let _ = write!(out, "{:0width$}", exponent.unsigned_abs());
+                shown = true;
+            }
+            Token::Literal(literal) => out.push_str(literal),
+            Token::Percent => out.push('%'),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The literal text among `tokens`.
+fn literals(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        match token {
+            Token::Literal(literal) => out.push_str(literal),
+            Token::Percent => out.push('%'),
+            _ => {}
+        }
+    }
+    out
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Show a serial date and time through date fields, or `None` if it is
+/// out of the range dates can be shown in.
+fn format_date(tokens: &[Token], serial: f64) -> Option<String> {
+    // Up to 9999-12-31.
+    if !(0.0..2_958_466.0).contains(&serial) {
+        return None;
+    }
+    let mut days = serial.floor() as i64;
+    let mut seconds = ((serial - serial.floor()) * 86_400.0).round() as i64;
+    if seconds == 86_400 {
+        days += 1;
+        seconds = 0;
+    }
+    let (year, month, day, weekday) = serial_date(days);
+    let (hour, minute, second) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let twelve_hour = tokens.iter().any(|token| matches!(token, Token::AmPm(_)));
+
+    let mut out = String::new();
+    for token in tokens {
+        let _ = match *token {
+            Token::Literal(ref literal) => write!(out, "{}", literal),
+            Token::Comma => write!(out, ","),
+            Token::Point => write!(out, "."),
+            Token::Date(DateField::Year(1 | 2)) => write!(out, "{:02}", year % 100),
+            Token::Date(DateField::Year(_)) => write!(out, "{:04}", year),
+            Token::Date(DateField::Month(1)) => write!(out, "{}", month),
+            Token::Date(DateField::Month(2)) => write!(out, "{:02}", month),
+            Token::Date(DateField::Month(count)) => {
+                let name = MONTHS[month as usize - 1];
+                match count {
+                    3 => write!(out, "{}", &name[..3]),
+                    4 => write!(out, "{}", name),
+                    _ => write!(out, "{}", &name[..1]),
+                }
+            }
+            Token::Date(DateField::Day(1)) => write!(out, "{}", day),
+            Token::Date(DateField::Day(2)) => write!(out, "{:02}", day),
+            Token::Date(DateField::Day(3)) => write!(out, "{}", &WEEKDAYS[weekday][..3]),
+            Token::Date(DateField::Day(_)) => write!(out, "{}", WEEKDAYS[weekday]),
+            Token::Date(DateField::Hour(count)) => {
+                let hour = if twelve_hour {
+                    (hour + 11) % 12 + 1
+                } else {
+                    hour
+                };
+                if count == 1 {
+                    write!(out, "{}", hour)
+                } else {
+                    write!(out, "{:02}", hour)
+                }
+            }
+            Token::Date(DateField::Minute(1)) => write!(out, "{}", minute),
+            Token::Date(DateField::Minute(_)) => write!(out, "{:02}", minute),
+            Token::Date(DateField::Second(1)) => write!(out, "{}", second),
+            Token::Date(DateField::Second(_)) => write!(out, "{:02}", second),
+            Token::AmPm(short) => {
+                let marker = if hour < 12 { "AM" } else { "PM" };
+                write!(out, "{}", if short { &marker[..1] } else { marker })
+            }
+            _ => Ok(()),
+        };
+    }
+    Some(out)
+}
+
+/// The year, month, day and weekday (0 for Sunday) of a serial day.
+///
+/// Serial days count 1900 as a leap year, as spreadsheets have always
+/// done: day 60 is 1900-02-29, and days before it are one day later than
+/// they would otherwise be.
+fn serial_date(days: i64) -> (i64, u32, u32, usize) {
+    if days == 60 {
+        return (1900, 2, 29, 3);
+    }
+    if days == 0 {
+        return (1900, 1, 0, 6);
+    }
+    // Day 0 from 1970-01-01.
+    let unix_day = days - 25_569 + i64::from(days < 60);
+    let weekday = (unix_day + 4).rem_euclid(7) as usize;
+
+    // Days to civil date, after Howard Hinnant's algorithm.
+    let z = unix_day + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day, weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(pattern: &str, value: f64) -> String {
+        NumberFormat::parse(pattern)
+            .unwrap()
+            .format_number(value, &NumberLocale::default())
+    }
+
+    #[test]
+    fn test_currency() {
+        assert_eq!(format("$#,##0.00", 1234.5), "$1,234.50");
+        assert_eq!(format("$#,##0", 1234.5), "$1,235");
+        assert_eq!(format("$#,##0.00", -1234.5), "-$1,234.50");
+        assert_eq!(format("[$€-407] #,##0.00", 1234567.891), "€ 1,234,567.89");
+        assert_eq!(format("#,##0.00;(#,##0.00)", -1234.5), "(1,234.50)");
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(format("0%", 0.25), "25%");
+        assert_eq!(format("0.0%", 0.1234), "12.3%");
+        assert_eq!(format("0%", -0.004), "0%");
+    }
+
+    #[test]
+    fn test_digit_placeholders() {
+        assert_eq!(format("0.00", 2.675), "2.68");
+        assert_eq!(format("000", 7.0), "007");
+        assert_eq!(format("#.##", 2.5), "2.5");
+        assert_eq!(format("0.0#", 2.0), "2.0");
+        assert_eq!(format("#,##0", 0.0), "0");
+        assert_eq!(format("#,##0,", 1_234_567.0), "1,235");
+        assert_eq!(format("0.00E+00", 12345.0), "1.23E+04");
+        assert_eq!(format("0.00E+00", 0.000123), "1.23E-04");
+        assert_eq!(format("0;-0;\"zero\"", 0.0), "zero");
+    }
+
+    #[test]
+    fn test_serial_dates() {
+        assert_eq!(format("yyyy-mm-dd", 45000.0), "2023-03-15");
+        assert_eq!(format("d mmm yy", 45000.0), "15 Mar 23");
+        assert_eq!(
+            format("dddd, mmmm d, yyyy", 45000.0),
+            "Wednesday, March 15, 2023"
+        );
+        assert_eq!(
+            format("yyyy-mm-dd hh:mm:ss", 45000.75),
+            "2023-03-15 18:00:00"
+        );
+        assert_eq!(format("h:mm AM/PM", 0.5625), "1:30 PM");
+        assert_eq!(format("m/d/yyyy", 1.0), "1/1/1900");
+        assert_eq!(format("m/d/yyyy", 60.0), "2/29/1900");
+        assert_eq!(format("m/d/yyyy", 61.0), "3/1/1900");
+        // Dates before 1900 are shown as numbers.
+        assert_eq!(format("yyyy-mm-dd", -1.0), "-1");
+    }
+
+    #[test]
+    fn test_locale_separators() {
+        let format = NumberFormat::parse("#,##0.00").unwrap();
+        let german = NumberLocale::new(',', '.');
+        assert_eq!(format.format_number(1234567.5, &german), "1.234.567,50");
+        assert_eq!(NumberFormat::general().format_number(2.5, &german), "2,5");
+    }
+
+    #[test]
+    fn test_text_section() {
+        let format = NumberFormat::parse("0.00;-0.00;0;\"Name: \"@").unwrap();
+        assert_eq!(format.format_text("Ada"), "Name: Ada");
+        assert_eq!(NumberFormat::general().format_text("Ada"), "Ada");
+    }
+
+    #[test]
+    fn test_invalid_patterns_fall_back_to_general() {
+        for pattern in ["0.0x", "\"unclosed", "[>100]0", "yyyy 0.00", "0;0;0;0;0"] {
+            assert!(NumberFormat::parse(pattern).is_err(), "{pattern}");
+            let format = NumberFormat::parse_or_general(pattern);
+            assert!(format.is_general());
+            assert_eq!(
+                format.format_number(1234.5, &NumberLocale::default()),
+                "1234.5"
+            );
+        }
+    }
+}
@@ -41,6 +41,11 @@ impl CellRange {
             && cell.col <= self.end.col
     }
 
+    /// The number of (rows, columns) in this range.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.row_count(), self.col_count())
+    }
+
     /// Get the number of rows in this range.
     pub fn row_count(&self) -> usize {
         self.end.row - self.start.row + 1
@@ -51,8 +56,8 @@ impl CellRange {
         self.end.col - self.start.col + 1
     }
 
-    /// Get all cells in this range.
-    pub fn cells(&self) -> impl Iterator<Item = CellRef> {
+    /// The cells in this range, row by row from the top left.
+    pub fn iter(&self) -> impl Iterator<Item = CellRef> + use<> {
         let start_row = self.start.row;
         let start_col = self.start.col;
         let end_row = self.end.row;
@@ -62,10 +67,20 @@ impl CellRange {
             .flat_map(move |row| (start_col..=end_col).map(move |col| CellRef::new(row, col)))
     }
 
-    /// Parse a range from a string (e.g., "A1:B10").
+    /// Get all cells in this range.
+    pub fn cells(&self) -> impl Iterator<Item = CellRef> + use<> {
+        self.iter()
+    }
+
+    /// Parse a range from a string (e.g., "A1:B10", "$A$1:$B$10" or
+    /// "B3"). The corners may be given in any order.
     pub fn parse(s: &str) -> Option<Self> {
         let parts: Vec<&str> = s.split(':').collect();
         match parts.as_slice() {
+            [cell] => {
+                let cell = CellRef::parse(cell)?;
+                Some(Self::new(cell, cell))
+            }
             [start_str, end_str] => {
                 let start = CellRef::parse(start_str)?;
                 let end = CellRef::parse(end_str)?;
@@ -184,6 +199,38 @@ mod tests {
         assert_eq!(range.to_range_string(), "A1:C5");
     }
 
+    #[test]
+    fn test_cell_range_normalization() {
+        let range = CellRange::parse("B10:A1").unwrap();
+        assert_eq!(range, CellRange::parse("A1:B10").unwrap());
+        assert_eq!(range.to_range_string(), "A1:B10");
+        // Corners given bottom left and top right.
+        let range = CellRange::new(CellRef::new(9, 0), CellRef::new(0, 1));
+        assert_eq!(range.start, CellRef::new(0, 0));
+        assert_eq!(range.end, CellRef::new(9, 1));
+        assert_eq!(CellRange::parse("$A$1:B$10"), CellRange::parse("A1:B10"));
+    }
+
+    #[test]
+    fn test_cell_range_iteration_order() {
+        let range = CellRange::parse("C2:B3").unwrap();
+        let cells: Vec<String> = range.iter().map(|cell| cell.to_a1()).collect();
+        assert_eq!(cells, ["B2", "C2", "B3", "C3"]);
+        assert_eq!(range.dimensions(), (2, 2));
+        assert!(range.contains(CellRef::parse("C3").unwrap()));
+        assert!(!range.contains(CellRef::parse("A2").unwrap()));
+    }
+
+    #[test]
+    fn test_single_cell_range() {
+        let range = CellRange::parse("D4").unwrap();
+        assert_eq!(range, CellRange::parse("D4:D4").unwrap());
+        assert_eq!(range.dimensions(), (1, 1));
+        assert_eq!(range.iter().collect::<Vec<_>>(), [CellRef::new(3, 3)]);
+        assert!(range.contains(CellRef::new(3, 3)));
+        assert!(!range.contains(CellRef::new(3, 4)));
+    }
+
     #[test]
     fn test_selection_extend() {
         let mut sel = Selection::new(CellRef::new(0, 0));
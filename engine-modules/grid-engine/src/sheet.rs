@@ -1,8 +1,15 @@
 //! Sheet model.
 
+use std::collections::BTreeSet;
+
 use indexmap::IndexMap;
 
-use crate::cell::{Cell, CellRef};
+use crate::autofill::{self, Series};
+use crate::cell::{Cell, CellRef, CellValue};
+use crate::dependency::DependencyGraph;
+use crate::formula::{Formula, FormulaContext, FormulaError};
+use crate::selection::CellRange;
+use crate::{Error, Result};
 
 /// A single sheet in a spreadsheet.
 #[derive(Debug, Clone)]
@@ -23,6 +30,8 @@ pub struct Sheet {
     pub frozen_rows: usize,
     /// Frozen columns.
     pub frozen_cols: usize,
+    /// Which formula cells read which cells.
+    dependencies: DependencyGraph,
 }
 
 impl Sheet {
@@ -37,6 +46,7 @@ impl Sheet {
             default_row_height: 24.0,
             frozen_rows: 0,
             frozen_cols: 0,
+            dependencies: DependencyGraph::new(),
         }
     }
 
@@ -50,8 +60,16 @@ impl Sheet {
         self.cells.get_mut(&cell_ref)
     }
 
-    /// Set a cell.
+    /// Set a cell without recalculating anything.
     pub fn set(&mut self, cell_ref: CellRef, cell: Cell) {
+        let precedents = cell
+            .formula
+            .as_deref()
+            .and_then(|text| Formula::parse(text).ok())
+            .map(|formula| formula.references())
+            .unwrap_or_default();
+        self.dependencies.set_precedents(cell_ref, precedents);
+
         if cell.value.is_empty() && cell.formula.is_none() {
             self.cells.shift_remove(&cell_ref);
         } else {
@@ -59,9 +77,211 @@ impl Sheet {
         }
     }
 
-    /// Clear a cell.
-    pub fn clear(&mut self, cell_ref: CellRef) {
-        self.cells.shift_remove(&cell_ref);
+    /// Set a cell and recalculate it, if it has a formula, and the cells
+    /// that depend on it. Returns the recalculated cells in the order they
+    /// were evaluated.
+    ///
+    /// Cells on a circular reference get a `#CIRCULAR!` error.
+    pub fn set_cell(&mut self, cell_ref: CellRef, cell: impl Into<Cell>) -> Vec<CellRef> {
+        let cell = cell.into();
+        let has_formula = cell.formula.is_some();
+        self.set(cell_ref, cell);
+
+        let mut dirty = self.dependencies.transitive_dependents(cell_ref);
+        if has_formula {
+            dirty.insert(cell_ref);
+        }
+        self.recalculate_cells(&dirty)
+    }
+
+    /// Clear a cell and recalculate the cells that depend on it. Returns
+    /// the recalculated cells in the order they were evaluated.
+    pub fn clear(&mut self, cell_ref: CellRef) -> Vec<CellRef> {
+        self.set_cell(cell_ref, Cell::default())
+    }
+
+    /// Fill `target` from the cells of `source`, as when dragging the fill
+    /// handle. `target` must extend `source` down, up, right or left, and
+    /// may include it. Each row or column of the source continues as a
+    /// series if it has one, and is repeated otherwise, with formula
+    /// references moved to match. Returns the filled cells.
+    pub fn autofill(&mut self, source: CellRange, target: CellRange) -> Result<Vec<CellRef>> {
+        let vertical = target.start.col == source.start.col && target.end.col == source.end.col;
+        let horizontal = target.start.row == source.start.row && target.end.row == source.end.row;
+        if !vertical && !horizontal {
+            return Err(Error::InvalidRange(format!(
+                "{} does not extend {}",
+                target.to_range_string(),
+                source.to_range_string()
+            )));
+        }
+
+        // Each line runs along the fill direction.
+        let (lines, along) = if vertical {
+            (
+                source.start.col..=source.end.col,
+                source.start.row..=source.end.row,
+            )
+        } else {
+            (
+                source.start.row..=source.end.row,
+                source.start.col..=source.end.col,
+            )
+        };
+        let fill = if vertical {
+            target.start.row..=target.end.row
+        } else {
+            target.start.col..=target.end.col
+        };
+        let cell_ref = |line: usize, position: usize| {
+            if vertical {
+                CellRef::new(position, line)
+            } else {
+                CellRef::new(line, position)
+            }
+        };
+
+        let len = along.clone().count() as isize;
+        let mut filled = Vec::new();
+        for line in lines {
+            let cells: Vec<Cell> = along
+                .clone()
+                .map(|position| {
+                    self.get(cell_ref(line, position))
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .collect();
+            let series = if cells.iter().any(|cell| cell.formula.is_some()) {
+                Series::Repeat
+            } else {
+                let values: Vec<_> = cells.iter().map(|cell| cell.value.clone()).collect();
+                Series::detect(&values)
+            };
+
+            for position in fill.clone() {
+                let index = position as isize - *along.start() as isize;
+                if (0..len).contains(&index) {
+                    continue;
+                }
+                let source_index = index.rem_euclid(len);
+                let offset = index - source_index;
+                let (rows, cols) = if vertical { (offset, 0) } else { (0, offset) };
+                let mut cell = autofill::copy_cell(&cells[source_index as usize], rows, cols);
+                if let Some(value) = series.value_at(index) {
+                    cell.value = value;
+                }
+                let target = cell_ref(line, position);
+                self.set(target, cell);
+                filled.push(target);
+            }
+        }
+
+        let mut dirty = BTreeSet::new();
+        for &cell_ref in &filled {
+            if self
+                .get(cell_ref)
+                .is_some_and(|cell| cell.formula.is_some())
+            {
+                dirty.insert(cell_ref);
+            }
+            dirty.extend(self.dependencies.transitive_dependents(cell_ref));
+        }
+        self.recalculate_cells(&dirty);
+        Ok(filled)
+    }
+
+    /// Recalculate every formula cell, as after loading a sheet. Returns
+    /// the recalculated cells in the order they were evaluated.
+    pub fn recalculate(&mut self) -> Vec<CellRef> {
+        let formulas = self
+            .cells
+            .iter()
+            .filter(|(_, cell)| cell.formula.is_some())
+            .map(|(&cell_ref, _)| cell_ref)
+            .collect();
+        self.recalculate_cells(&formulas)
+    }
+
+    /// The dependency graph of the sheet's formulas.
+    pub fn dependencies(&self) -> &DependencyGraph {
+        &self.dependencies
+    }
+
+    fn recalculate_cells(&mut self, cells: &BTreeSet<CellRef>) -> Vec<CellRef> {
+        let (mut order, cyclic) = self.dependencies.topological_order(cells);
+        for &cell_ref in &order {
+            let value = self.evaluate(cell_ref);
+            if let Some(cell) = self.cells.get_mut(&cell_ref) {
+                cell.value = value;
+            }
+        }
+        for &cell_ref in &cyclic {
+            if let Some(cell) = self.cells.get_mut(&cell_ref) {
+                cell.value = FormulaError::CircularRef.to_cell_value();
+            }
+        }
+        order.extend(cyclic);
+        order
+    }
+
+    /// The value of the formula in `cell_ref`, from the current values of
+    /// the cells it reads.
+    fn evaluate(&self, cell_ref: CellRef) -> CellValue {
+        let Some(text) = self.get(cell_ref).and_then(|cell| cell.formula.as_deref()) else {
+            return CellValue::Empty;
+        };
+        let get_cell = |cell_ref| self.get(cell_ref).map(|cell| cell.value.clone());
+        let get_range = |range| self.cells_in(range);
+        Formula::parse(text)
+            .and_then(|formula| {
+                formula.evaluate(&FormulaContext {
+                    get_cell: &get_cell,
+                    get_range: &get_range,
+                })
+            })
+            .unwrap_or_else(|error| error.to_cell_value())
+    }
+
+    /// The cells in `range` holding a value, with their values, row by
+    /// row. Whichever of the range's cells and the sheet's are fewer are
+    /// gone through, so a range of whole columns costs no more than the
+    /// sheet's cells.
+    fn cells_in(&self, range: CellRange) -> Vec<(CellRef, CellValue)> {
+        let area = range.row_count().saturating_mul(range.col_count());
+        let mut cells: Vec<(CellRef, CellValue)> = if area <= self.cells.len() {
+            range
+                .iter()
+                .filter_map(|cell_ref| Some((cell_ref, self.get(cell_ref)?.value.clone())))
+                .collect()
+        } else {
+            let mut cells: Vec<_> = self
+                .cells
+                .iter()
+                .filter(|(cell_ref, _)| range.contains(**cell_ref))
+                .map(|(&cell_ref, cell)| (cell_ref, cell.value.clone()))
+                .collect();
+            cells.sort_by_key(|(cell_ref, _)| (cell_ref.row, cell_ref.col));
+            cells
+        };
+        cells.retain(|(_, value)| !value.is_empty());
+        cells
+    }
+
+    /// The values in `range`, row by row, with empty cells as
+    /// [`CellValue::Empty`].
+    pub fn values_in(&self, range: CellRange) -> Vec<Vec<CellValue>> {
+        (range.start.row..=range.end.row)
+            .map(|row| {
+                (range.start.col..=range.end.col)
+                    .map(|col| {
+                        self.get(CellRef::new(row, col))
+                            .map(|cell| cell.value.clone())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
     /// Get column width.
@@ -125,3 +345,205 @@ impl Default for Sheet {
         Self::new("Sheet1")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_in() {
+        let mut sheet = Sheet::default();
+        let number = |n| Cell::with_value(CellValue::Number(n));
+        sheet.set(CellRef::parse("A1").unwrap(), number(1.0));
+        sheet.set(CellRef::parse("B1").unwrap(), number(2.0));
+        sheet.set(CellRef::parse("B2").unwrap(), number(4.0));
+
+        let values = sheet.values_in(CellRange::parse("B2:A1").unwrap());
+        assert_eq!(
+            values,
+            vec![
+                vec![CellValue::Number(1.0), CellValue::Number(2.0)],
+                vec![CellValue::Empty, CellValue::Number(4.0)],
+            ]
+        );
+        assert_eq!(
+            sheet.values_in(CellRange::parse("B1").unwrap()),
+            vec![vec![CellValue::Number(2.0)]]
+        );
+    }
+
+    fn cell(name: &str) -> CellRef {
+        CellRef::parse(name).unwrap()
+    }
+
+    fn value(sheet: &Sheet, name: &str) -> CellValue {
+        sheet.get(cell(name)).unwrap().value.clone()
+    }
+
+    #[test]
+    fn test_set_cell_recalculates_transitive_dependents() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("A1"), CellValue::Number(1.0));
+        sheet.set_cell(cell("A2"), CellValue::Number(10.0));
+        sheet.set_cell(cell("B1"), Cell::with_formula("=A1*2"));
+        sheet.set_cell(cell("C1"), Cell::with_formula("=B1+1"));
+        sheet.set_cell(cell("D1"), Cell::with_formula("=SUM(B1:C1)"));
+        sheet.set_cell(cell("B2"), Cell::with_formula("=A2+1"));
+        assert_eq!(value(&sheet, "D1"), CellValue::Number(5.0));
+
+        let recalculated = sheet.set_cell(cell("A1"), CellValue::Number(3.0));
+        assert_eq!(recalculated, [cell("B1"), cell("C1"), cell("D1")]);
+        assert_eq!(value(&sheet, "B1"), CellValue::Number(6.0));
+        assert_eq!(value(&sheet, "C1"), CellValue::Number(7.0));
+        assert_eq!(value(&sheet, "D1"), CellValue::Number(13.0));
+
+        // A new formula is evaluated along with what reads it.
+        assert_eq!(
+            sheet.set_cell(cell("C1"), Cell::with_formula("=B1")),
+            [cell("C1"), cell("D1")]
+        );
+        assert_eq!(sheet.set_cell(cell("B2"), CellValue::Number(0.0)), []);
+    }
+
+    #[test]
+    fn test_changed_formula_rederives_edges() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("B1"), Cell::with_formula("=A1"));
+        sheet.set_cell(cell("B1"), Cell::with_formula("=A2"));
+
+        assert!(
+            sheet
+                .set_cell(cell("A1"), CellValue::Number(1.0))
+                .is_empty()
+        );
+        assert_eq!(
+            sheet.set_cell(cell("A2"), CellValue::Number(2.0)),
+            [cell("B1")]
+        );
+        assert_eq!(value(&sheet, "B1"), CellValue::Number(2.0));
+
+        // Replacing the formula with a value drops its edges.
+        sheet.set_cell(cell("B1"), CellValue::Number(0.0));
+        assert!(
+            sheet
+                .set_cell(cell("A2"), CellValue::Number(3.0))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_circular_reference() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("A1"), Cell::with_formula("=B1+1"));
+        sheet.set_cell(cell("B1"), Cell::with_formula("=A1+1"));
+        assert_eq!(
+            value(&sheet, "A1"),
+            CellValue::Error("CIRCULAR".to_string())
+        );
+        assert_eq!(
+            value(&sheet, "B1"),
+            CellValue::Error("CIRCULAR".to_string())
+        );
+
+        sheet.set_cell(cell("B1"), CellValue::Number(1.0));
+        assert_eq!(value(&sheet, "A1"), CellValue::Number(2.0));
+    }
+
+    #[test]
+    fn test_whole_sheet_range() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("A1"), CellValue::Number(1.0));
+        sheet.set_cell(cell("C5"), CellValue::Number(2.0));
+        sheet.set_cell(cell("B1"), Cell::with_formula("=SUM(A2:XFD1048576)"));
+        assert_eq!(value(&sheet, "B1"), CellValue::Number(2.0));
+
+        assert_eq!(
+            sheet.set_cell(cell("ZZ900"), CellValue::Number(4.0)),
+            [cell("B1")]
+        );
+        assert_eq!(value(&sheet, "B1"), CellValue::Number(6.0));
+
+        // Clearing a cell recalculates what reads it.
+        assert_eq!(sheet.clear(cell("C5")), [cell("B1")]);
+        assert_eq!(value(&sheet, "B1"), CellValue::Number(4.0));
+        assert!(sheet.get(cell("C5")).is_none());
+    }
+
+    fn range(name: &str) -> CellRange {
+        CellRange::parse(name).unwrap()
+    }
+
+    #[test]
+    fn test_autofill_single_cell_copies() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("A1"), CellValue::Number(7.0));
+        let filled = sheet.autofill(range("A1"), range("A1:A3")).unwrap();
+        assert_eq!(filled, [cell("A2"), cell("A3")]);
+        assert_eq!(value(&sheet, "A3"), CellValue::Number(7.0));
+    }
+
+    #[test]
+    fn test_autofill_linear_numbers() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("A1"), CellValue::Number(2.0));
+        sheet.set_cell(cell("A2"), CellValue::Number(5.0));
+        sheet.set_cell(cell("B1"), CellValue::Text("Item 1".to_string()));
+        sheet.set_cell(cell("B2"), CellValue::Text("Item 2".to_string()));
+
+        sheet.autofill(range("A1:B2"), range("A1:B5")).unwrap();
+        assert_eq!(value(&sheet, "A5"), CellValue::Number(14.0));
+        assert_eq!(value(&sheet, "B5"), CellValue::Text("Item 5".to_string()));
+
+        // Filling up continues the series backwards.
+        sheet.autofill(range("A3:A4"), range("A1:A4")).unwrap();
+        assert_eq!(value(&sheet, "A1"), CellValue::Number(2.0));
+    }
+
+    #[test]
+    fn test_autofill_dates() {
+        let mut sheet = Sheet::default();
+        sheet.set_cell(cell("A1"), CellValue::Date(19_431));
+        sheet.set_cell(cell("B1"), CellValue::Date(19_438));
+        sheet.autofill(range("A1:B1"), range("A1:D1")).unwrap();
+        assert_eq!(value(&sheet, "C1"), CellValue::Date(19_445));
+        assert_eq!(value(&sheet, "D1"), CellValue::Date(19_452));
+    }
+
+    #[test]
+    fn test_autofill_formulas() {
+        let mut sheet = Sheet::default();
+        for (row, n) in [1.0, 2.0, 3.0].into_iter().enumerate() {
+            sheet.set_cell(CellRef::new(row, 0), CellValue::Number(n));
+        }
+        sheet.set_cell(cell("B1"), Cell::with_formula("=A1*$A$1*10"));
+
+        sheet.autofill(range("B1"), range("B1:B3")).unwrap();
+        let b3 = sheet.get(cell("B3")).unwrap();
+        assert_eq!(b3.formula.as_deref(), Some("=A3*$A$1*10"));
+        assert_eq!(b3.value, CellValue::Number(30.0));
+
+        // The filled formulas are live.
+        sheet.set_cell(cell("A3"), CellValue::Number(4.0));
+        assert_eq!(value(&sheet, "B3"), CellValue::Number(40.0));
+    }
+
+    #[test]
+    fn test_autofill_rejects_unaligned_target() {
+        let mut sheet = Sheet::default();
+        assert!(matches!(
+            sheet.autofill(range("A1:A2"), range("B1:C5")),
+            Err(Error::InvalidRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_recalculate() {
+        let mut sheet = Sheet::default();
+        sheet.set(cell("A1"), Cell::with_value(CellValue::Number(4.0)));
+        sheet.set(cell("B1"), Cell::with_formula("=SQRT(A1)"));
+        sheet.set(cell("C1"), Cell::with_formula("=B1/0"));
+        assert_eq!(sheet.recalculate(), [cell("B1"), cell("C1")]);
+        assert_eq!(value(&sheet, "B1"), CellValue::Number(2.0));
+        assert_eq!(value(&sheet, "C1"), CellValue::Error("DIV/0".to_string()));
+    }
+}
@@ -1,5 +1,6 @@
 //! Spreadsheet model.
 
+use crate::cell::{Cell, CellRef};
 use crate::sheet::Sheet;
 
 /// A spreadsheet workbook containing multiple sheets.
@@ -45,6 +46,12 @@ impl Spreadsheet {
         &mut self.sheets[self.active_sheet]
     }
 
+    /// Set a cell on the active sheet and recalculate the cells that
+    /// depend on it. Returns the recalculated cells.
+    pub fn set_cell(&mut self, cell_ref: CellRef, cell: impl Into<Cell>) -> Vec<CellRef> {
+        self.active_mut().set_cell(cell_ref, cell)
+    }
+
     /// Add a new sheet.
     pub fn add_sheet(&mut self, name: impl Into<String>) -> usize {
         let index = self.sheets.len();
@@ -1,5 +1,7 @@
 //! Grid view management and rendering state.
 
+use std::ops::Range;
+
 use crate::cell::CellRef;
 use crate::selection::Selection;
 
@@ -14,8 +16,13 @@ pub struct GridView {
     pub cell_width: f32,
     /// Default cell height (in pixels).
     pub cell_height: f32,
-    /// Top-left visible cell.
+    /// Top-left visible cell of the scrolling pane, at or past the frozen
+    /// rows and columns.
     pub scroll_position: CellRef,
+    /// Rows kept in view at the top while the rest scroll.
+    pub frozen_rows: usize,
+    /// Columns kept in view at the left while the rest scroll.
+    pub frozen_cols: usize,
     /// Current selection.
     pub selection: Selection,
     /// Currently editing cell (if any).
@@ -39,6 +46,8 @@ impl GridView {
             cell_width: 100.0,
             cell_height: 24.0,
             scroll_position: CellRef::new(0, 0),
+            frozen_rows: 0,
+            frozen_cols: 0,
             selection: Selection::default(),
             editing_cell: None,
             edit_buffer: String::new(),
@@ -48,44 +57,159 @@ impl GridView {
         }
     }
 
-    /// Get the cell at the given pixel coordinates (relative to grid area).
+    /// Keep the top `rows` rows and left `cols` columns in view while the
+    /// rest scroll. Freezing none of either unfreezes them.
+    pub fn freeze_panes(&mut self, rows: usize, cols: usize) {
+        self.frozen_rows = rows;
+        self.frozen_cols = cols;
+        self.scroll_position.row = self.scroll_position.row.max(rows);
+        self.scroll_position.col = self.scroll_position.col.max(cols);
+    }
+
+    /// Width of the frozen columns (in pixels).
+    fn frozen_width(&self) -> f32 {
+        self.frozen_cols as f32 * self.cell_width
+    }
+
+    /// Height of the frozen rows (in pixels).
+    fn frozen_height(&self) -> f32 {
+        self.frozen_rows as f32 * self.cell_height
+    }
+
+    /// How far the scrolling pane is scrolled (in pixels), for the current
+    /// scroll position.
+    pub fn scroll_offset(&self) -> (f32, f32) {
+        let cols = self.scroll_position.col.saturating_sub(self.frozen_cols);
+        let rows = self.scroll_position.row.saturating_sub(self.frozen_rows);
+        (
+            cols as f32 * self.cell_width,
+            rows as f32 * self.cell_height,
+        )
+    }
+
+    /// The cells visible in a `width` by `height` viewport, headers
+    /// included, at the current scroll position.
+    pub fn visible_range(&self, width: f32, height: f32) -> VisibleRange {
+        let (scroll_x, scroll_y) = self.scroll_offset();
+        self.visible_range_at(scroll_x, scroll_y, width, height)
+    }
+
+    /// The cells visible in a `width` by `height` viewport, headers
+    /// included, with the scrolling pane scrolled by `scroll_x` and
+    /// `scroll_y` pixels. Partly visible cells count as visible.
+    pub fn visible_range_at(
+        &self,
+        scroll_x: f32,
+        scroll_y: f32,
+        width: f32,
+        height: f32,
+    ) -> VisibleRange {
+        // The scrolling pane starts where the frozen panes end.
+        let span = |frozen: usize, scroll: f32, extent: f32, size: f32| {
+            let extent = extent - frozen as f32 * size;
+            let scroll = scroll.max(0.0);
+            let first = frozen + (scroll / size).floor() as usize;
+            if extent <= 0.0 {
+                return first..first;
+            }
+            let last = frozen + ((scroll + extent) / size).ceil() as usize;
+            first..last.max(first + 1)
+        };
+        let frozen_rows_shown = ((height - self.column_header_height) / self.cell_height)
+            .ceil()
+            .max(0.0) as usize;
+        let frozen_cols_shown = ((width - self.row_header_width) / self.cell_width)
+            .ceil()
+            .max(0.0) as usize;
+
+        VisibleRange {
+            frozen_rows: self.frozen_rows.min(frozen_rows_shown),
+            frozen_cols: self.frozen_cols.min(frozen_cols_shown),
+            rows: span(
+                self.frozen_rows,
+                scroll_y,
+                height - self.column_header_height,
+                self.cell_height,
+            ),
+            cols: span(
+                self.frozen_cols,
+                scroll_x,
+                width - self.row_header_width,
+                self.cell_width,
+            ),
+        }
+    }
+
+    /// Get the cell at the given pixel coordinates (relative to grid
+    /// area), or `None` over the headers.
     pub fn cell_at(&self, x: f32, y: f32) -> Option<CellRef> {
-        let col_index = ((x - self.row_header_width) / self.cell_width).floor() as usize;
-        let row_index = ((y - self.column_header_height) / self.cell_height).floor() as usize;
+        let x = x - self.row_header_width;
+        let y = y - self.column_header_height;
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
 
-        let col = self.scroll_position.col + col_index;
-        let row = self.scroll_position.row + row_index;
+        let col = if x < self.frozen_width() {
+            (x / self.cell_width).floor() as usize
+        } else {
+            self.scroll_position.col
+                + ((x - self.frozen_width()) / self.cell_width).floor() as usize
+        };
+        let row = if y < self.frozen_height() {
+            (y / self.cell_height).floor() as usize
+        } else {
+            self.scroll_position.row
+                + ((y - self.frozen_height()) / self.cell_height).floor() as usize
+        };
 
         Some(CellRef::new(row, col))
     }
 
-    /// Get the pixel bounds of a cell (in grid coordinates).
+    /// Get the pixel bounds of a cell (in grid coordinates). Cells scrolled
+    /// out of view are placed at the start of the scrolling pane.
     pub fn cell_bounds(&self, cell: CellRef) -> (f32, f32, f32, f32) {
-        let col_offset = cell.col.saturating_sub(self.scroll_position.col) as f32;
-        let row_offset = cell.row.saturating_sub(self.scroll_position.row) as f32;
-
-        let x = self.row_header_width + col_offset * self.cell_width;
-        let y = self.column_header_height + row_offset * self.cell_height;
-        let width = self.cell_width;
-        let height = self.cell_height;
+        let x = if cell.col < self.frozen_cols {
+            cell.col as f32 * self.cell_width
+        } else {
+            self.frozen_width()
+                + cell.col.saturating_sub(self.scroll_position.col) as f32 * self.cell_width
+        };
+        let y = if cell.row < self.frozen_rows {
+            cell.row as f32 * self.cell_height
+        } else {
+            self.frozen_height()
+                + cell.row.saturating_sub(self.scroll_position.row) as f32 * self.cell_height
+        };
 
-        (x, y, width, height)
+        (
+            self.row_header_width + x,
+            self.column_header_height + y,
+            self.cell_width,
+            self.cell_height,
+        )
     }
 
-    /// Scroll to make a cell visible.
+    /// Scroll to make a cell visible. Frozen cells are always visible.
     pub fn scroll_to_cell(&mut self, cell: CellRef) {
+        let scrolling_rows = self.visible_rows.saturating_sub(self.frozen_rows).max(1);
+        let scrolling_cols = self.visible_cols.saturating_sub(self.frozen_cols).max(1);
+
         // Handle vertical scrolling
-        if cell.row < self.scroll_position.row {
-            self.scroll_position.row = cell.row;
-        } else if cell.row >= self.scroll_position.row + self.visible_rows {
-            self.scroll_position.row = cell.row.saturating_sub(self.visible_rows - 1);
+        if cell.row >= self.frozen_rows {
+            if cell.row < self.scroll_position.row {
+                self.scroll_position.row = cell.row;
+            } else if cell.row >= self.scroll_position.row + scrolling_rows {
+                self.scroll_position.row = cell.row + 1 - scrolling_rows;
+            }
         }
 
         // Handle horizontal scrolling
-        if cell.col < self.scroll_position.col {
-            self.scroll_position.col = cell.col;
-        } else if cell.col >= self.scroll_position.col + self.visible_cols {
-            self.scroll_position.col = cell.col.saturating_sub(self.visible_cols - 1);
+        if cell.col >= self.frozen_cols {
+            if cell.col < self.scroll_position.col {
+                self.scroll_position.col = cell.col;
+            } else if cell.col >= self.scroll_position.col + scrolling_cols {
+                self.scroll_position.col = cell.col + 1 - scrolling_cols;
+            }
         }
     }
 
@@ -151,6 +275,38 @@ impl Default for GridView {
     }
 }
 
+/// The rows and columns a viewport shows: the frozen ones, then a run of
+/// scrolling ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisibleRange {
+    /// Frozen rows shown, from row 0.
+    pub frozen_rows: usize,
+    /// Frozen columns shown, from column 0.
+    pub frozen_cols: usize,
+    /// Scrolling rows shown.
+    pub rows: Range<usize>,
+    /// Scrolling columns shown.
+    pub cols: Range<usize>,
+}
+
+impl VisibleRange {
+    /// Every row shown, top to bottom.
+    pub fn row_indices(&self) -> impl Iterator<Item = usize> + use<> {
+        (0..self.frozen_rows).chain(self.rows.clone())
+    }
+
+    /// Every column shown, left to right.
+    pub fn col_indices(&self) -> impl Iterator<Item = usize> + use<> {
+        (0..self.frozen_cols).chain(self.cols.clone())
+    }
+
+    /// Check if a cell is shown.
+    pub fn contains(&self, cell: CellRef) -> bool {
+        (cell.row < self.frozen_rows || self.rows.contains(&cell.row))
+            && (cell.col < self.frozen_cols || self.cols.contains(&cell.col))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,6 +341,91 @@ mod tests {
         assert!(grid.scroll_position.col <= 10);
     }
 
+    #[test]
+    fn test_visible_range() {
+        let grid = GridView::new();
+        // 350 x 124 leaves 300 x 100 for cells: 3 columns and 4 1/6 rows.
+        let range = grid.visible_range_at(0.0, 0.0, 350.0, 124.0);
+        assert_eq!(range.rows, 0..5);
+        assert_eq!(range.cols, 0..3);
+        assert_eq!(range.frozen_rows, 0);
+
+        // Scrolled half a cell, a partly visible cell shows at each end.
+        let range = grid.visible_range_at(150.0, 240.0, 350.0, 124.0);
+        assert_eq!(range.rows, 10..15);
+        assert_eq!(range.cols, 1..5);
+    }
+
+    #[test]
+    fn test_visible_range_with_frozen_panes() {
+        let mut grid = GridView::new();
+        grid.freeze_panes(2, 1);
+        assert_eq!(grid.scroll_position, CellRef::new(2, 1));
+
+        // Frozen panes take 48 of 100 pixels of rows and 100 of 300 of
+        // columns, leaving 52 and 200 to scroll.
+        let range = grid.visible_range_at(0.0, 0.0, 350.0, 124.0);
+        assert_eq!(range.frozen_rows, 2);
+        assert_eq!(range.frozen_cols, 1);
+        assert_eq!(range.rows, 2..5);
+        assert_eq!(range.cols, 1..3);
+
+        let range = grid.visible_range_at(1000.0, 240.0, 350.0, 124.0);
+        assert_eq!(range.rows, 12..15);
+        assert_eq!(range.cols, 11..13);
+        assert_eq!(range.row_indices().collect::<Vec<_>>(), [0, 1, 12, 13, 14]);
+        assert!(range.contains(CellRef::new(1, 12)));
+        assert!(!range.contains(CellRef::new(5, 0)));
+
+        // Frozen panes filling the viewport leave nothing to scroll.
+        let range = grid.visible_range_at(0.0, 0.0, 150.0, 60.0);
+        assert_eq!(range.frozen_rows, 2);
+        assert!(range.rows.is_empty());
+        assert!(range.cols.is_empty());
+    }
+
+    #[test]
+    fn test_hit_testing_with_frozen_panes() {
+        let mut grid = GridView::new();
+        grid.freeze_panes(2, 1);
+        grid.scroll_position = CellRef::new(20, 5);
+
+        // Just inside the frozen panes.
+        assert_eq!(grid.cell_at(149.0, 71.0), Some(CellRef::new(1, 0)));
+        // Just past them, the first scrolling cell.
+        assert_eq!(grid.cell_at(150.0, 72.0), Some(CellRef::new(20, 5)));
+        assert_eq!(grid.cell_at(251.0, 97.0), Some(CellRef::new(21, 6)));
+        assert_eq!(grid.cell_at(10.0, 50.0), None);
+
+        assert_eq!(
+            grid.cell_bounds(CellRef::new(1, 0)),
+            (50.0, 48.0, 100.0, 24.0)
+        );
+        assert_eq!(
+            grid.cell_bounds(CellRef::new(21, 6)),
+            (250.0, 96.0, 100.0, 24.0)
+        );
+        for cell in [CellRef::new(0, 0), CellRef::new(1, 0), CellRef::new(22, 7)] {
+            let (x, y, ..) = grid.cell_bounds(cell);
+            assert_eq!(grid.cell_at(x, y), Some(cell));
+        }
+    }
+
+    #[test]
+    fn test_scroll_to_cell_with_frozen_panes() {
+        let mut grid = GridView::new();
+        grid.visible_rows = 5;
+        grid.freeze_panes(2, 0);
+
+        grid.scroll_to_cell(CellRef::new(10, 0));
+        assert_eq!(grid.scroll_position.row, 8);
+        // Frozen rows are in view without scrolling back.
+        grid.scroll_to_cell(CellRef::new(0, 0));
+        assert_eq!(grid.scroll_position.row, 8);
+        grid.scroll_to_cell(CellRef::new(3, 0));
+        assert_eq!(grid.scroll_position.row, 3);
+    }
+
     #[test]
     fn test_edit_operations() {
         let mut grid = GridView::new();
@@ -3,6 +3,8 @@
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 use crate::Result;
@@ -67,9 +69,37 @@ struct CacheEntry<T: Clone> {
     ref_count: usize,
     /// Last access time (in arbitrary units).
     last_accessed: u64,
+    /// Number of times the entry was retrieved.
+    access_count: u64,
+    /// When the entry expires, if it has a time to live.
+    expires_at: Option<Instant>,
 }
 
-/// Generic asset cache with reference counting and LRU eviction.
+impl<T: Clone> CacheEntry<T> {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// How the cache chooses entries to evict when it is full. Entries that are
+/// still referenced are only evicted when nothing else can be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Evict the least recently used entry.
+    #[default]
+    Lru,
+    /// Evict the least frequently used entry, and the least recently used
+    /// of those used equally often.
+    Lfu,
+    /// Give entries inserted without a time to live this one, and evict
+    /// the entry closest to expiring.
+    Ttl(Duration),
+}
+
+/// Source of the current time, replaceable to simulate time passing.
+type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+/// Generic asset cache with reference counting and policy-based eviction.
 pub struct AssetCache<T: Clone> {
     /// Cached entries.
     entries: RwLock<HashMap<AssetId, CacheEntry<T>>>,
@@ -81,10 +111,20 @@ pub struct AssetCache<T: Clone> {
     current_size: RwLock<u64>,
     /// Access counter for LRU tracking.
     access_counter: RwLock<u64>,
+    /// Eviction policy.
+    policy: CachePolicy,
+    /// Source of the current time for expiry.
+    clock: Clock,
+    /// Number of entries evicted or expired.
+    evictions: AtomicU64,
+    /// Number of lookups that found an asset.
+    hits: AtomicU64,
+    /// Number of lookups that found no asset.
+    misses: AtomicU64,
 }
 
 impl<T: Clone> AssetCache<T> {
-    /// Create a new asset cache with maximum size.
+    /// Create a new LRU asset cache with maximum size.
     pub fn new(max_size: u64) -> Self {
         Self {
             entries: RwLock::new(HashMap::new()),
@@ -92,10 +132,33 @@ impl<T: Clone> AssetCache<T> {
             max_size,
             current_size: RwLock::new(0),
             access_counter: RwLock::new(0),
+            policy: CachePolicy::default(),
+            clock: Arc::new(Instant::now),
+            evictions: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
-    /// Insert an asset into the cache.
+    /// Set the eviction policy.
+    pub fn with_policy(mut self, policy: CachePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Read the time for expiry from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Fn() -> Instant + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The eviction policy.
+    pub fn policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    /// Insert an asset into the cache. Under the TTL policy it expires
+    /// after the policy's time to live.
     pub fn insert(
         &self,
         path: String,
@@ -103,6 +166,25 @@ impl<T: Clone> AssetCache<T> {
         data: T,
         size: u64,
     ) -> Result<AssetId> {
+        self.insert_with_ttl(path, asset_type, data, size, None)
+    }
+
+    /// Insert an asset into the cache that expires after `ttl`, or after
+    /// the policy's time to live if `None`. Expired assets are dropped once
+    /// nothing references them.
+    pub fn insert_with_ttl(
+        &self,
+        path: String,
+        asset_type: AssetType,
+        data: T,
+        size: u64,
+        ttl: Option<Duration>,
+    ) -> Result<AssetId> {
+        let now = (self.clock)();
+        let ttl = ttl.or(match self.policy {
+            CachePolicy::Ttl(ttl) => Some(ttl),
+            _ => None,
+        });
         let id = AssetId::new();
         let metadata = AssetMetadata {
             id,
@@ -115,9 +197,10 @@ impl<T: Clone> AssetCache<T> {
         let mut path_map = self.path_map.write();
         let mut current_size = self.current_size.write();
 
-        // Evict entries if necessary
+        // Drop expired entries, then evict more if necessary
+        self.purge_expired_unlocked(&mut entries, &mut path_map, &mut current_size, now, false);
         while *current_size + size > self.max_size && !entries.is_empty() {
-            self.evict_lru_unlocked(&mut entries, &mut path_map, &mut current_size);
+            self.evict_unlocked(&mut entries, &mut path_map, &mut current_size);
         }
 
         // Insert new entry
@@ -126,6 +209,8 @@ impl<T: Clone> AssetCache<T> {
             data,
             ref_count: 1,
             last_accessed: *self.access_counter.read(),
+            access_count: 0,
+            expires_at: ttl.map(|ttl| now + ttl),
         };
 
         *current_size += size;
@@ -135,17 +220,34 @@ impl<T: Clone> AssetCache<T> {
         Ok(id)
     }
 
-    /// Retrieve an asset from the cache.
+    /// Retrieve an asset from the cache. Expired assets are only returned
+    /// while still referenced.
     pub fn get(&self, id: AssetId) -> Option<T> {
+        let now = (self.clock)();
         let mut entries = self.entries.write();
+        let expired = entries
+            .get(&id)
+            .is_some_and(|entry| entry.ref_count == 0 && entry.is_expired(now));
+        if expired {
+            let mut path_map = self.path_map.write();
+            let mut current_size = self.current_size.write();
+            Self::remove_unlocked(&mut entries, &mut path_map, &mut current_size, id);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
         let mut counter = self.access_counter.write();
         *counter += 1;
 
         if let Some(entry) = entries.get_mut(&id) {
             entry.last_accessed = *counter;
+            entry.access_count += 1;
             entry.ref_count += 1;
+            self.hits.fetch_add(1, Ordering::Relaxed);
             Some(entry.data.clone())
         } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
             None
         }
     }
@@ -158,10 +260,16 @@ impl<T: Clone> AssetCache<T> {
             drop(path_map_guard);
             self.get(id_copy)
         } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
             None
         }
     }
 
+    /// The ID of the asset cached for a path, without accessing it.
+    pub fn id_by_path(&self, path: &str) -> Option<AssetId> {
+        self.path_map.read().get(path).copied()
+    }
+
     /// Release a reference to an asset.
     pub fn release(&self, id: AssetId) {
         let mut entries = self.entries.write();
@@ -187,6 +295,16 @@ impl<T: Clone> AssetCache<T> {
         }
     }
 
+    /// Drop expired assets, returning how many were dropped. Assets still
+    /// referenced are kept unless `force` is set.
+    pub fn purge_expired(&self, force: bool) -> usize {
+        let now = (self.clock)();
+        let mut entries = self.entries.write();
+        let mut path_map = self.path_map.write();
+        let mut current_size = self.current_size.write();
+        self.purge_expired_unlocked(&mut entries, &mut path_map, &mut current_size, now, force)
+    }
+
     /// Clear all entries from the cache.
     pub fn clear(&self) {
         self.entries.write().clear();
@@ -208,28 +326,100 @@ impl<T: Clone> AssetCache<T> {
             } else {
                 0.0
             },
+            evictions: self.evictions.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         }
     }
 
-    /// Evict the least recently used entry.
-    fn evict_lru_unlocked(
+    /// Fraction of lookups that found an asset, or 0 before any lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let lookups = hits + self.misses.load(Ordering::Relaxed);
+        if lookups > 0 {
+            hits as f64 / lookups as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Reset the hit, miss, and eviction counts.
+    pub fn reset_metrics(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        self.evictions.store(0, Ordering::Relaxed);
+    }
+
+    /// Evict the entry the policy ranks lowest.
+    fn evict_unlocked(
         &self,
         entries: &mut HashMap<AssetId, CacheEntry<T>>,
         path_map: &mut HashMap<String, AssetId>,
         current_size: &mut u64,
     ) {
-        if let Some((id, entry)) = entries
+        let policy = self.policy;
+        let victim = entries
             .iter()
-            .min_by_key(|(_, e)| (e.ref_count, e.last_accessed))
-            .map(|(k, v)| (*k, v.clone()))
-        {
-            entries.remove(&id);
-            path_map.remove(&entry.metadata.path);
+            .min_by_key(|(_, e)| {
+                // Entries that never expire rank above all that do.
+                let (frequency, expiry) = match policy {
+                    CachePolicy::Lru => (0, (false, None)),
+                    CachePolicy::Lfu => (e.access_count, (false, None)),
+                    CachePolicy::Ttl(_) => (0, (e.expires_at.is_none(), e.expires_at)),
+                };
+                (e.ref_count, frequency, expiry, e.last_accessed)
+            })
+            .map(|(id, _)| *id);
+        if let Some(id) = victim {
+            Self::remove_unlocked(entries, path_map, current_size, id);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn purge_expired_unlocked(
+        &self,
+        entries: &mut HashMap<AssetId, CacheEntry<T>>,
+        path_map: &mut HashMap<String, AssetId>,
+        current_size: &mut u64,
+        now: Instant,
+        force: bool,
+    ) -> usize {
+        let expired: Vec<AssetId> = entries
+            .iter()
+            .filter(|(_, e)| e.is_expired(now) && (force || e.ref_count == 0))
+            .map(|(id, _)| *id)
+            .collect();
+        for &id in &expired {
+            Self::remove_unlocked(entries, path_map, current_size, id);
+        }
+        self.evictions
+            .fetch_add(expired.len() as u64, Ordering::Relaxed);
+        expired.len()
+    }
+
+    fn remove_unlocked(
+        entries: &mut HashMap<AssetId, CacheEntry<T>>,
+        path_map: &mut HashMap<String, AssetId>,
+        current_size: &mut u64,
+        id: AssetId,
+    ) {
+        if let Some(entry) = entries.remove(&id) {
+            if path_map.get(&entry.metadata.path) == Some(&id) {
+                path_map.remove(&entry.metadata.path);
+            }
             *current_size = current_size.saturating_sub(entry.metadata.size);
         }
     }
 }
 
+/// A hash of an asset's content, for caching assets that have no path by
+/// what they hold (64-bit FNV-1a).
+pub fn content_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Cache statistics.
 #[derive(Debug, Clone)]
 pub struct CacheStats {
@@ -241,6 +431,12 @@ pub struct CacheStats {
     pub max_size: u64,
     /// Cache usage as percentage.
     pub usage_percent: f32,
+    /// Number of entries evicted for space or dropped on expiry.
+    pub evictions: u64,
+    /// Number of lookups that found an asset.
+    pub hits: u64,
+    /// Number of lookups that found no asset.
+    pub misses: u64,
 }
 
 #[cfg(test)]
@@ -318,4 +514,151 @@ mod tests {
         assert_eq!(stats.total_entries, 0);
         assert_eq!(stats.total_size, 0);
     }
+
+    /// A clock that starts now and advances when told to.
+    fn manual_clock() -> (
+        Arc<RwLock<Instant>>,
+        impl Fn() -> Instant + Send + Sync + 'static,
+    ) {
+        let now = Arc::new(RwLock::new(Instant::now()));
+        let clock = Arc::clone(&now);
+        (now, move || *clock.read())
+    }
+
+    #[test]
+    fn test_lfu_keeps_frequently_used_entries() {
+        let cache: AssetCache<Vec<u8>> = AssetCache::new(100).with_policy(CachePolicy::Lfu);
+        let frequent = cache
+            .insert(
+                "frequent.png".to_string(),
+                AssetType::Image,
+                vec![0; 40],
+                40,
+            )
+            .unwrap();
+        let rare = cache
+            .insert("rare.png".to_string(), AssetType::Image, vec![0; 40], 40)
+            .unwrap();
+        cache.release(frequent);
+        cache.release(rare);
+
+        for _ in 0..3 {
+            cache.get(frequent);
+            cache.release(frequent);
+        }
+        // The rare entry is the most recently used, which would save it under LRU.
+        cache.get(rare);
+        cache.release(rare);
+
+        cache
+            .insert("new.png".to_string(), AssetType::Image, vec![0; 40], 40)
+            .unwrap();
+        assert!(cache.get(frequent).is_some());
+        assert!(cache.get(rare).is_none());
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let (now, clock) = manual_clock();
+        let cache: AssetCache<Vec<u8>> = AssetCache::new(1000)
+            .with_policy(CachePolicy::Ttl(Duration::from_secs(60)))
+            .with_clock(clock);
+        let short = cache
+            .insert_with_ttl(
+                "short.png".to_string(),
+                AssetType::Image,
+                vec![1],
+                1,
+                Some(Duration::from_secs(10)),
+            )
+            .unwrap();
+        let default = cache
+            .insert("default.png".to_string(), AssetType::Image, vec![2], 1)
+            .unwrap();
+        cache.release(short);
+        cache.release(default);
+
+        *now.write() += Duration::from_secs(30);
+        assert_eq!(cache.get(short), None);
+        assert_eq!(cache.get_by_path("short.png"), None);
+        assert_eq!(cache.get(default), Some(vec![2]));
+        cache.release(default);
+
+        *now.write() += Duration::from_secs(60);
+        assert_eq!(cache.purge_expired(false), 1);
+        let stats = cache.stats();
+        assert_eq!(stats.total_entries, 0);
+        assert_eq!(stats.evictions, 2);
+    }
+
+    #[test]
+    fn test_ttl_spares_referenced_entries_unless_forced() {
+        let (now, clock) = manual_clock();
+        let cache: AssetCache<Vec<u8>> = AssetCache::new(1000).with_clock(clock);
+        let id = cache
+            .insert_with_ttl(
+                "pinned.png".to_string(),
+                AssetType::Image,
+                vec![1],
+                1,
+                Some(Duration::from_secs(1)),
+            )
+            .unwrap();
+
+        *now.write() += Duration::from_secs(2);
+        assert_eq!(cache.purge_expired(false), 0);
+        assert_eq!(cache.get(id), Some(vec![1]));
+        assert_eq!(cache.purge_expired(true), 1);
+        assert_eq!(cache.get(id), None);
+    }
+
+    #[test]
+    fn test_hit_and_miss_counts() {
+        let cache: AssetCache<Vec<u8>> = AssetCache::new(1000);
+        let id = cache
+            .insert("present.png".to_string(), AssetType::Image, vec![1], 1)
+            .unwrap();
+
+        cache.get(id);
+        cache.get_by_path("present.png");
+        cache.get(AssetId::new());
+        cache.get_by_path("absent.png");
+        cache.get_by_path("present.png");
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (3, 2));
+        assert!((cache.hit_rate() - 0.6).abs() < 1e-9);
+
+        cache.reset_metrics();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (0, 0, 0));
+        assert_eq!(cache.hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_concurrent_lookups_are_all_counted() {
+        let cache: Arc<AssetCache<Vec<u8>>> = Arc::new(AssetCache::new(1000));
+        let id = cache
+            .insert("present.png".to_string(), AssetType::Image, vec![1], 1)
+            .unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    for _ in 0..250 {
+                        cache.get(id);
+                        cache.get_by_path("absent.png");
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1000, 1000));
+    }
 }
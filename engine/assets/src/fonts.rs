@@ -1,9 +1,17 @@
 //! Font loading and management.
+//!
+//! Text is drawn with a primary font, but few fonts have glyphs for every
+//! script. Runs of text the primary font cannot show are handed to the
+//! fallback fonts in the order they were registered, with emoji and CJK
+//! characters trying fonts registered for them first. Which characters a
+//! font covers is read from its character map once and cached.
 
-use fontdb::{Database, ID};
+use fontdb::{Database, ID, Source};
 use parking_lot::RwLock;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::{AssetCache, AssetType, Error, Result};
 
@@ -18,6 +26,88 @@ pub struct CachedFont {
     pub data: Option<Vec<u8>>,
 }
 
+/// Characters that are best drawn with fonts made for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharClass {
+    /// Emoji and pictographs.
+    Emoji,
+    /// Chinese, Japanese, and Korean characters.
+    Cjk,
+}
+
+impl CharClass {
+    /// The class of a character, if it has one.
+    pub fn of(c: char) -> Option<Self> {
+        match c as u32 {
+            0x1F000..=0x1FAFF
+            | 0x2600..=0x27BF
+            | 0x231A..=0x231B
+            | 0x23E9..=0x23FA
+            | 0x2B50
+            | 0x2B55 => Some(Self::Emoji),
+            0x1100..=0x11FF
+            | 0x2E80..=0x9FFF
+            | 0xAC00..=0xD7AF
+            | 0xF900..=0xFAFF
+            | 0xFF00..=0xFFEF
+            | 0x20000..=0x3FFFF => Some(Self::Cjk),
+            _ => None,
+        }
+    }
+}
+
+/// The characters a font has glyphs for, as a bitmap for each block of 256
+/// code points it has any glyphs in.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    blocks: HashMap<u32, [u64; 4]>,
+}
+
+impl Coverage {
+    /// Read the coverage of a face from its character map.
+    pub fn from_face(face: &ttf_parser::Face) -> Self {
+        let mut coverage = Self::default();
+        let Some(cmap) = face.tables().cmap else {
+            return coverage;
+        };
+        for subtable in cmap.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|code_point| {
+                if subtable
+                    .glyph_index(code_point)
+                    .is_some_and(|glyph| glyph.0 != 0)
+                {
+                    coverage.insert(code_point);
+                }
+            });
+        }
+        coverage
+    }
+
+    /// Mark a code point as covered.
+    pub fn insert(&mut self, code_point: u32) {
+        let bits = self.blocks.entry(code_point >> 8).or_default();
+        bits[(code_point as usize >> 6) & 3] |= 1 << (code_point & 63);
+    }
+
+    /// Whether the font has a glyph for a character.
+    pub fn contains(&self, c: char) -> bool {
+        let code_point = c as u32;
+        self.blocks.get(&(code_point >> 8)).is_some_and(|bits| {
+            bits[(code_point as usize >> 6) & 3] & (1 << (code_point & 63)) != 0
+        })
+    }
+}
+
+impl FromIterator<char> for Coverage {
+    fn from_iter<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        let mut coverage = Self::default();
+        for c in chars {
+            coverage.insert(c as u32);
+        }
+        coverage
+    }
+}
+
 /// Font manager with caching.
 pub struct FontManager {
     /// Font database.
@@ -26,30 +116,39 @@ pub struct FontManager {
     cache: AssetCache<CachedFont>,
     /// Family name to font ID mapping.
     family_map: RwLock<HashMap<String, ID>>,
+    /// Fonts tried, in order, for characters the primary font lacks.
+    fallbacks: RwLock<Vec<ID>>,
+    /// Fonts tried first for characters of a class.
+    class_fallbacks: RwLock<HashMap<CharClass, Vec<ID>>>,
+    /// Font used when no font covers a character, to draw its missing
+    /// glyph box.
+    last_resort: RwLock<Option<ID>>,
+    /// Coverage of each font resolved so far.
+    coverage: RwLock<HashMap<ID, Arc<Coverage>>>,
 }
 
 impl FontManager {
     /// Create a new font manager.
     pub fn new() -> Self {
-        let mut db = Database::new();
-        db.load_system_fonts();
-
-        Self {
-            db: RwLock::new(db),
-            cache: AssetCache::new(50 * 1024 * 1024), // 50 MB cache
-            family_map: RwLock::new(HashMap::new()),
-        }
+        Self::with_cache_size(50 * 1024 * 1024) // 50 MB cache
     }
 
     /// Create a new font manager with custom cache size.
     pub fn with_cache_size(cache_size: u64) -> Self {
         let mut db = Database::new();
         db.load_system_fonts();
+        Self::with_database(db, cache_size)
+    }
 
+    fn with_database(db: Database, cache_size: u64) -> Self {
         Self {
             db: RwLock::new(db),
             cache: AssetCache::new(cache_size),
             family_map: RwLock::new(HashMap::new()),
+            fallbacks: RwLock::new(Vec::new()),
+            class_fallbacks: RwLock::new(HashMap::new()),
+            last_resort: RwLock::new(None),
+            coverage: RwLock::new(HashMap::new()),
         }
     }
 
@@ -70,14 +169,20 @@ impl FontManager {
         Ok(())
     }
 
-    /// Load a font from bytes with caching.
-    pub fn load_font_data(&self, family: String, data: Vec<u8>) -> Result<()> {
+    /// Load a font from bytes with caching, returning the IDs of its faces.
+    pub fn load_font_data(&self, family: String, data: Vec<u8>) -> Result<Vec<ID>> {
         let data_len = data.len() as u64;
-        self.db.write().load_font_data(data.clone());
+        let ids = self
+            .db
+            .write()
+            .load_font_source(Source::Binary(Arc::new(data.clone())));
+        let Some(&id) = ids.first() else {
+            return Err(Error::Font(format!("No faces in font data for {}", family)));
+        };
 
         // Cache the font data
         let cached = CachedFont {
-            id: fontdb::ID::dummy(),
+            id,
             family: family.clone(),
             data: Some(data),
         };
@@ -86,7 +191,7 @@ impl FontManager {
             .cache
             .insert(family.clone(), AssetType::Font, cached, data_len);
 
-        Ok(())
+        Ok(ids.to_vec())
     }
 
     /// Query for a font.
@@ -128,6 +233,114 @@ impl FontManager {
         None
     }
 
+    /// Add a font to try, after those added before it, for characters the
+    /// primary font lacks.
+    pub fn add_fallback(&self, id: ID) {
+        self.fallbacks.write().push(id);
+    }
+
+    /// Add a font to try first for characters of `class`.
+    pub fn add_fallback_for(&self, class: CharClass, id: ID) {
+        self.class_fallbacks
+            .write()
+            .entry(class)
+            .or_default()
+            .push(id);
+    }
+
+    /// Set the font for characters no font covers. Without one, they are
+    /// left to the primary font.
+    pub fn set_last_resort(&self, id: ID) {
+        *self.last_resort.write() = Some(id);
+    }
+
+    /// The fallback fonts, in the order they are tried.
+    pub fn fallbacks(&self) -> Vec<ID> {
+        self.fallbacks.read().clone()
+    }
+
+    /// The characters a font covers, read once and then cached.
+    pub fn coverage(&self, id: ID) -> Option<Arc<Coverage>> {
+        if let Some(coverage) = self.coverage.read().get(&id) {
+            return Some(coverage.clone());
+        }
+        let coverage = self
+            .db
+            .read()
+            .with_face_data(id, |data, index| {
+                ttf_parser::Face::parse(data, index)
+                    .ok()
+                    .map(|face| Arc::new(Coverage::from_face(&face)))
+            })
+            .flatten()?;
+        self.coverage.write().insert(id, coverage.clone());
+        Some(coverage)
+    }
+
+    /// Split text into runs, each with the font to draw it with: the
+    /// primary font where it covers the text, and otherwise the first
+    /// fallback that does. Ranges are byte ranges into `text`.
+    pub fn resolve_runs(&self, text: &str, primary: ID) -> Vec<(ID, Range<usize>)> {
+        let with_coverage = |ids: &[ID]| -> Vec<(ID, Arc<Coverage>)> {
+            ids.iter()
+                .filter_map(|&id| Some((id, self.coverage(id)?)))
+                .collect()
+        };
+        let primary_font = with_coverage(&[primary]);
+        let general = with_coverage(&self.fallbacks.read());
+        let classes: HashMap<CharClass, Vec<(ID, Arc<Coverage>)>> = self
+            .class_fallbacks
+            .read()
+            .iter()
+            .map(|(&class, ids)| (class, with_coverage(ids)))
+            .collect();
+        let last_resort = self.last_resort.read().unwrap_or(primary);
+
+        let mut runs: Vec<(ID, Range<usize>)> = Vec::new();
+        for (start, c) in text.char_indices() {
+            let end = start + c.len_utf8();
+            // Joiners, selectors, and marks belong with the character
+            // before them.
+            if let Some((_, range)) = runs.last_mut().filter(|_| continues_cluster(c)) {
+                range.end = end;
+                continue;
+            }
+
+            let class = CharClass::of(c);
+            let class_fonts = class.and_then(|class| classes.get(&class));
+            // Emoji are drawn by emoji fonts even where text fonts have a
+            // plain glyph for them.
+            let candidates: Vec<&(ID, Arc<Coverage>)> = match class {
+                Some(CharClass::Emoji) => class_fonts
+                    .into_iter()
+                    .flatten()
+                    .chain(&primary_font)
+                    .chain(&general)
+                    .collect(),
+                _ => primary_font
+                    .iter()
+                    .chain(class_fonts.into_iter().flatten())
+                    .chain(&general)
+                    .collect(),
+            };
+            // Spaces stay in the run they are in when its font has them.
+            let current = runs
+                .last()
+                .filter(|_| c.is_whitespace())
+                .and_then(|(id, _)| candidates.iter().find(|(other, _)| other == id))
+                .filter(|(_, coverage)| coverage.contains(c));
+            let font = current
+                .or_else(|| candidates.iter().find(|(_, coverage)| coverage.contains(c)))
+                .map_or(last_resort, |(id, _)| *id);
+
+            match runs.last_mut() {
+                Some((id, range)) if *id == font => range.end = end,
+                _ => runs.push((font, start..end)),
+            }
+        }
+        runs
+    }
+
     /// Get the database.
     pub fn database(&self) -> parking_lot::RwLockReadGuard<'_, Database> {
         self.db.read()
@@ -142,6 +355,7 @@ impl FontManager {
     pub fn clear_cache(&self) {
         self.cache.clear();
         self.family_map.write().clear();
+        self.coverage.write().clear();
     }
 
     /// Get the number of cached fonts.
@@ -156,6 +370,19 @@ impl Default for FontManager {
     }
 }
 
+/// Whether a character joins the one before it into a single cluster.
+fn continues_cluster(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x200C..=0x200D           // zero-width (non-)joiner
+            | 0xFE00..=0xFE0F     // variation selectors
+            | 0x1F3FB..=0x1F3FF   // skin tone modifiers
+            | 0xE0020..=0xE007F   // tag characters
+            | 0x20E3              // combining keycap
+            | 0x0300..=0x036F     // combining diacritics
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,6 +399,60 @@ mod tests {
         assert_eq!(manager.cache_stats().max_size, 100 * 1024 * 1024);
     }
 
+    const TUFFY: &[u8] = include_bytes!("../../../test-suite/fonts/Tuffy.ttf");
+
+    /// A manager without system fonts, with Tuffy as the primary font and
+    /// fallbacks standing in for an emoji and a CJK font.
+    fn manager() -> (FontManager, [ID; 3]) {
+        let manager = FontManager::with_database(Database::new(), 1024 * 1024);
+        let load = |family: &str| manager.load_font_data(family.to_string(), TUFFY.to_vec());
+        let ids = [load("Tuffy"), load("Emoji"), load("Cjk")].map(|ids| ids.unwrap()[0]);
+        // Tuffy has no emoji or CJK glyphs; pretend the stand-ins do.
+        let mut coverage = manager.coverage.write();
+        coverage.insert(ids[1], Arc::new("👍☕".chars().collect()));
+        coverage.insert(ids[2], Arc::new("中文!".chars().collect()));
+        drop(coverage);
+        manager.add_fallback_for(CharClass::Emoji, ids[1]);
+        manager.add_fallback(ids[2]);
+        (manager, ids)
+    }
+
+    #[test]
+    fn test_coverage_from_font() {
+        let (manager, [tuffy, ..]) = manager();
+        let coverage = manager.coverage(tuffy).unwrap();
+        assert!(coverage.contains('A') && coverage.contains(' '));
+        assert!(!coverage.contains('中'));
+        // Coverage is read once.
+        assert!(Arc::ptr_eq(&coverage, &manager.coverage(tuffy).unwrap()));
+    }
+
+    #[test]
+    fn test_resolves_runs_by_coverage() {
+        let (manager, [tuffy, emoji, cjk]) = manager();
+        let text = "Hi 👍🏽中文!";
+        let runs = manager.resolve_runs(text, tuffy);
+        assert_eq!(
+            runs,
+            vec![
+                (tuffy, 0..3),
+                (emoji, 3..11),
+                (cjk, 11..17),
+                (tuffy, 17..18)
+            ]
+        );
+        assert_eq!(&text[3..11], "👍🏽");
+
+        // Characters no font has go to the last resort, or the primary font.
+        let runic = "ᚠ";
+        assert_eq!(manager.resolve_runs(runic, emoji), vec![(emoji, 0..3)]);
+        manager.set_last_resort(cjk);
+        assert_eq!(
+            manager.resolve_runs("aᚠ", tuffy),
+            vec![(tuffy, 0..1), (cjk, 1..4)]
+        );
+    }
+
     #[test]
     fn test_font_cache_stats() {
         let manager = FontManager::new();
@@ -1,10 +1,25 @@
 //! Image loading with caching.
 
-use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader, Limits, Rgba, RgbaImage};
+use std::io::Cursor;
 use std::path::Path;
 
+use crate::cache::content_hash;
 use crate::{AssetCache, AssetId, AssetType, Error, Result};
 
+/// An image decoded to 8-bit RGBA.
+#[derive(Debug, Clone)]
+pub struct DecodedImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Format the image was decoded from.
+    pub format: SupportedFormat,
+    /// Pixels row by row from the top, four bytes each.
+    pub pixels: Vec<u8>,
+}
+
 /// Cached image data.
 #[derive(Debug, Clone)]
 pub struct CachedImage {
@@ -28,6 +43,15 @@ impl CachedImage {
         }
     }
 
+    /// Create from a decoded image.
+    pub fn from_decoded(image: DecodedImage) -> Self {
+        Self {
+            dimensions: (image.width, image.height),
+            color_type: "rgba8",
+            buffer: image.pixels,
+        }
+    }
+
     /// Get as DynamicImage.
     pub fn to_dynamic(&self) -> DynamicImage {
         let img = RgbaImage::from_raw(self.dimensions.0, self.dimensions.1, self.buffer.clone())
@@ -40,21 +64,68 @@ impl CachedImage {
 pub struct ImageLoader {
     /// Image cache.
     cache: AssetCache<CachedImage>,
+    /// Largest width or height decoded, if limited.
+    max_dimension: Option<u32>,
 }
 
 impl ImageLoader {
     /// Create a new image loader.
     pub fn new() -> Self {
-        Self {
-            cache: AssetCache::new(100 * 1024 * 1024), // 100 MB cache
-        }
+        Self::with_cache_size(100 * 1024 * 1024) // 100 MB cache
     }
 
     /// Create a new image loader with custom cache size.
     pub fn with_cache_size(cache_size: u64) -> Self {
         Self {
             cache: AssetCache::new(cache_size),
+            max_dimension: None,
+        }
+    }
+
+    /// Refuse to decode images wider or taller than `max_dimension`
+    /// pixels, checked against the header before any pixels are allocated.
+    pub fn with_max_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_dimension = Some(max_dimension);
+        self
+    }
+
+    /// Decode PNG, JPEG, GIF, or WebP data, recognized by its signature, to
+    /// RGBA. Grayscale and palette images are expanded.
+    pub fn decode(&self, data: &[u8]) -> Result<DecodedImage> {
+        let format = SupportedFormat::sniff(data)
+            .ok_or_else(|| Error::Image("Unrecognized image format".to_string()))?;
+        let mut reader = ImageReader::with_format(Cursor::new(data), format.image_format());
+        if let Some(max) = self.max_dimension {
+            let mut limits = Limits::default();
+            limits.max_image_width = Some(max);
+            limits.max_image_height = Some(max);
+            reader.limits(limits);
         }
+        let image = reader.decode().map_err(|e| Error::Image(e.to_string()))?;
+        let (width, height) = image.dimensions();
+        Ok(DecodedImage {
+            width,
+            height,
+            format,
+            pixels: image.into_rgba8().into_raw(),
+        })
+    }
+
+    /// Decode an image with caching, keyed by a hash of its content so the
+    /// same image is decoded once wherever it comes from.
+    pub fn decode_cached(&self, data: &[u8]) -> Result<AssetId> {
+        let key = format!("content:{:016x}", content_hash(data));
+        if let Some(id) = self.cache.id_by_path(&key) {
+            return Ok(id);
+        }
+        let decoded = self.decode(data)?;
+        let size = decoded.pixels.len() as u64;
+        self.cache.insert(
+            key,
+            AssetType::Image,
+            CachedImage::from_decoded(decoded),
+            size,
+        )
     }
 
     /// Load an image from a file with caching.
@@ -172,6 +243,33 @@ pub enum SupportedFormat {
 }
 
 impl SupportedFormat {
+    /// Recognize PNG, JPEG, GIF, or WebP data by its signature.
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+            Some(Self::WebP)
+        } else {
+            None
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Self::Png => ImageFormat::Png,
+            Self::Jpeg => ImageFormat::Jpeg,
+            Self::Gif => ImageFormat::Gif,
+            Self::WebP => ImageFormat::WebP,
+            Self::Bmp => ImageFormat::Bmp,
+            Self::Ico => ImageFormat::Ico,
+            Self::Tiff => ImageFormat::Tiff,
+        }
+    }
+
     /// Get the file extension.
     pub fn extension(&self) -> &'static str {
         match self {
@@ -215,6 +313,75 @@ mod tests {
         assert_eq!(loader.cache_stats().max_size, 200 * 1024 * 1024);
     }
 
+    const PALETTE_PNG: &[u8] = include_bytes!("../../../test-suite/images/palette.png");
+    const GRAY_PNG: &[u8] = include_bytes!("../../../test-suite/images/gray.png");
+
+    fn jpeg(width: u32, height: u32, color: [u8; 3]) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb(color));
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Jpeg)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_decodes_png_to_rgba() {
+        let loader = ImageLoader::new();
+        let palette = loader.decode(PALETTE_PNG).unwrap();
+        assert_eq!((palette.width, palette.height), (2, 2));
+        assert_eq!(palette.format, SupportedFormat::Png);
+        assert_eq!(&palette.pixels[..8], &[255, 0, 0, 255, 0, 0, 255, 128]);
+
+        let gray = loader.decode(GRAY_PNG).unwrap();
+        assert_eq!(
+            gray.pixels,
+            [0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn test_decodes_jpeg() {
+        let image = ImageLoader::new()
+            .decode(&jpeg(16, 8, [0, 128, 255]))
+            .unwrap();
+        assert_eq!((image.width, image.height), (16, 8));
+        assert_eq!(image.format, SupportedFormat::Jpeg);
+        assert_eq!(image.pixels.len(), 16 * 8 * 4);
+        let [r, g, b, a] = image.pixels[..4] else {
+            unreachable!()
+        };
+        assert!(r < 8 && g.abs_diff(128) < 8 && b > 247 && a == 255);
+    }
+
+    #[test]
+    fn test_rejects_bad_data() {
+        let loader = ImageLoader::new();
+        assert!(matches!(
+            loader.decode(b"not an image"),
+            Err(Error::Image(_))
+        ));
+        let truncated = &PALETTE_PNG[..PALETTE_PNG.len() - 30];
+        assert!(matches!(loader.decode(truncated), Err(Error::Image(_))));
+
+        let limited = ImageLoader::new().with_max_dimension(10);
+        assert!(limited.decode(&jpeg(8, 8, [0, 0, 0])).is_ok());
+        assert!(matches!(
+            limited.decode(&jpeg(16, 8, [0, 0, 0])),
+            Err(Error::Image(_))
+        ));
+    }
+
+    #[test]
+    fn test_caches_by_content() {
+        let loader = ImageLoader::new();
+        let id = loader.decode_cached(GRAY_PNG).unwrap();
+        assert_eq!(loader.decode_cached(GRAY_PNG).unwrap(), id);
+        assert_ne!(loader.decode_cached(PALETTE_PNG).unwrap(), id);
+        assert_eq!(loader.cached_images(), 2);
+        assert_eq!(loader.get_cached(id).unwrap().dimensions, (3, 1));
+    }
+
     #[test]
     fn test_cached_image_conversion() {
         let img = image::RgbaImage::new(100, 100);
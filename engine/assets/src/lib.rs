@@ -15,10 +15,12 @@ pub mod icons;
 pub mod images;
 pub mod pipeline;
 
-pub use cache::{AssetCache, AssetId, AssetMetadata, AssetType, CacheStats};
-pub use fonts::FontManager;
+pub use cache::{
+    AssetCache, AssetId, AssetMetadata, AssetType, CachePolicy, CacheStats, content_hash,
+};
+pub use fonts::{CharClass, Coverage, FontManager};
 pub use icons::IconManager;
-pub use images::ImageLoader;
+pub use images::{DecodedImage, ImageLoader, SupportedFormat};
 pub use pipeline::{AssetPipeline, PipelineConfig, PipelineStats};
 
 /// Result type for asset operations.
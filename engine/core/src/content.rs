@@ -3,6 +3,7 @@
 pub use crate::document::{Document, Metadata};
 pub use crate::node::{Node, NodeKind};
 pub use crate::style::{
-    Alignment, ParagraphStyle, Style, StyleSheet, TabAlignment, TabStop, TextStyle,
+    Alignment, ComputedStyle, Formatting, ParagraphStyle, Style, StyleResolver, StyleSheet,
+    TabAlignment, TabStop, TextStyle,
 };
 pub use crate::text::{Span, Text};
@@ -0,0 +1,811 @@
+//! Differences between two versions of a document.
+//!
+//! Nodes of the two versions are matched by ID first, so a document that
+//! was edited keeps its nodes paired however they moved. Nodes left over,
+//! as in documents imported twice from a format without IDs, are paired
+//! with the most similar unpaired node of the same kind whose text is
+//! similar enough. Only a few nearby nodes are compared with each, so
+//! documents sharing no IDs take time in proportion to their size. Text is
+//! compared a word at a time with Myers' diff algorithm.
+//!
+//! A matched node is moved if its parent is not the match of its old
+//! parent, or if it changed places with its siblings. Of siblings that
+//! kept their parent, the most that are still in their old order stay, and
+//! the rest are taken to have moved, so inserting or deleting a sibling
+//! moves nothing.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use crate::document::Document;
+use crate::node::{Node, NodeKind};
+
+/// How much of their text two nodes without matching IDs must share to be
+/// taken for versions of the same node.
+const MIN_SIMILARITY: f32 = 0.5;
+
+/// Most unpaired nodes a node without a matching ID is compared with from
+/// each of two places: the children of its parent's match, and the nodes
+/// of its kind and depth around the same position.
+const MAX_CANDIDATES: usize = 16;
+
+/// The differences between an old and a new version of a document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentDiff {
+    /// Nodes only in the new version, outermost first in document order.
+    /// The nodes under an inserted node are not listed.
+    pub inserted: Vec<NodeChange>,
+    /// Nodes only in the old version, likewise.
+    pub deleted: Vec<NodeChange>,
+    /// Nodes in both versions that changed, in the new version's order.
+    pub modified: Vec<NodeModification>,
+    /// Nodes in both versions under a different parent or in a different
+    /// place among their siblings, in the new version's order. A moved node
+    /// may be modified as well.
+    #[serde(default)]
+    pub moved: Vec<NodeMove>,
+}
+
+impl DocumentDiff {
+    /// Whether the versions have the same content.
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty()
+            && self.deleted.is_empty()
+            && self.modified.is_empty()
+            && self.moved.is_empty()
+    }
+}
+
+/// A node inserted into or deleted from a document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeChange {
+    /// ID of the node's parent, in the version holding the node.
+    pub parent: Uuid,
+    /// Index of the node among its parent's children.
+    pub index: usize,
+    /// The node, with its children.
+    pub node: Node,
+}
+
+impl PartialEq for NodeChange {
+    fn eq(&self, other: &Self) -> bool {
+        self.parent == other.parent && self.index == other.index && self.node.id == other.node.id
+    }
+}
+
+/// A node in both versions of a document whose content changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeModification {
+    /// ID of the node in the old version.
+    pub old_id: Uuid,
+    /// ID of the node in the new version, which differs from the old one
+    /// if the nodes were matched by content.
+    pub new_id: Uuid,
+    /// Changes to the node's text, in order.
+    pub text: Vec<TextEdit>,
+    /// Whether anything besides the text changed: the node's kind or its
+    /// properties, its style or formatting, or the formatting of its text.
+    pub attributes_changed: bool,
+}
+
+/// A node in both versions of a document that moved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeMove {
+    /// ID of the node in the old version.
+    pub old_id: Uuid,
+    /// ID of the node in the new version.
+    pub new_id: Uuid,
+    /// ID of the node's parent in the old version.
+    pub old_parent: Uuid,
+    /// Index of the node among its old parent's children.
+    pub old_index: usize,
+    /// ID of the node's parent in the new version.
+    pub new_parent: Uuid,
+    /// Index of the node among its new parent's children.
+    pub new_index: usize,
+}
+
+/// A change to the text of a node. Ranges are byte ranges.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TextEdit {
+    /// Text at `new` in the new version, inserted at `at` in the old.
+    Insert {
+        at: usize,
+        new: Range<usize>,
+        text: String,
+    },
+    /// Text at `old` in the old version, deleted at `at` in the new.
+    Delete {
+        old: Range<usize>,
+        at: usize,
+        text: String,
+    },
+}
+
+impl Document {
+    /// The differences from this version of the document to `other`.
+    pub fn diff(&self, other: &Document) -> DocumentDiff {
+        let old = Entry::collect(&self.root);
+        let new = Entry::collect(&other.root);
+        let pairs = match_nodes(&old, &new);
+
+        let matched_old: HashSet<usize> = pairs.values().copied().collect();
+        let inserted = unmatched(&new, |index| pairs.contains_key(&index));
+        let deleted = unmatched(&old, |index| matched_old.contains(&index));
+
+        let mut modified = Vec::new();
+        for (new_index, entry) in new.iter().enumerate() {
+            let Some(&old_index) = pairs.get(&new_index) else {
+                continue;
+            };
+            let (before, after) = (old[old_index].node, entry.node);
+            let (old_text, new_text) = (own_text(before), own_text(after));
+            let text = if old_text == new_text {
+                Vec::new()
+            } else {
+                text_edits(old_text, new_text)
+            };
+            let attributes_changed = !same_attributes(before, after, old_text == new_text);
+            if !text.is_empty() || attributes_changed {
+                modified.push(NodeModification {
+                    old_id: before.id,
+                    new_id: after.id,
+                    text,
+                    attributes_changed,
+                });
+            }
+        }
+
+        let moved = moved(&old, &new, &pairs)
+            .into_iter()
+            .map(|new_index| {
+                let (before, after) = (&old[pairs[&new_index]], &new[new_index]);
+                NodeMove {
+                    old_id: before.node.id,
+                    new_id: after.node.id,
+                    old_parent: before.parent_id,
+                    old_index: before.index,
+                    new_parent: after.parent_id,
+                    new_index: after.index,
+                }
+            })
+            .collect();
+
+        DocumentDiff {
+            inserted,
+            deleted,
+            modified,
+            moved,
+        }
+    }
+}
+
+/// A node below the root, with where it is.
+struct Entry<'a> {
+    node: &'a Node,
+    /// Index of the parent's entry, or `None` under the root.
+    parent: Option<usize>,
+    parent_id: Uuid,
+    index: usize,
+    /// Number of nodes above this one, below the root.
+    depth: usize,
+}
+
+impl<'a> Entry<'a> {
+    /// Every node below `root`, in document order.
+    fn collect(root: &'a Node) -> Vec<Self> {
+        fn walk<'a>(
+            node: &'a Node,
+            parent: Option<usize>,
+            depth: usize,
+            entries: &mut Vec<Entry<'a>>,
+        ) {
+            for (index, child) in node.children.iter().enumerate() {
+                entries.push(Entry {
+                    node: child,
+                    parent,
+                    parent_id: node.id,
+                    index,
+                    depth,
+                });
+                let own = entries.len() - 1;
+                walk(child, Some(own), depth + 1, entries);
+            }
+        }
+        let mut entries = Vec::new();
+        walk(root, None, 0, &mut entries);
+        entries
+    }
+}
+
+/// Pair the new entries with old ones, as a map from new index to old.
+fn match_nodes(old: &[Entry<'_>], new: &[Entry<'_>]) -> HashMap<usize, usize> {
+    let old_ids: HashMap<Uuid, usize> = old
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.node.id, index))
+        .collect();
+    let mut pairs: HashMap<usize, usize> = new
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| Some((index, *old_ids.get(&entry.node.id)?)))
+        .collect();
+
+    let mut taken: HashSet<usize> = pairs.values().copied().collect();
+    // The old entries left, by parent and by kind and depth, in order.
+    let mut children: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+    let mut groups: HashMap<(&str, usize), Vec<usize>> = HashMap::new();
+    for (index, entry) in old.iter().enumerate() {
+        if !taken.contains(&index) {
+            children.entry(entry.parent).or_default().push(index);
+            groups
+                .entry((kind_name(&entry.node.kind), entry.depth))
+                .or_default()
+                .push(index);
+        }
+    }
+    let old_texts: Vec<String> = old.iter().map(|entry| content(entry.node)).collect();
+    let old_words: Vec<Vec<&str>> = old_texts.iter().map(|text| tokens(text)).collect();
+    // How many new entries left of each kind and depth came before.
+    let mut ranks: HashMap<(&str, usize), usize> = HashMap::new();
+    for (new_index, entry) in new.iter().enumerate() {
+        if pairs.contains_key(&new_index) {
+            continue;
+        }
+        let kind = kind_name(&entry.node.kind);
+        let rank = ranks.entry((kind, entry.depth)).or_default();
+        let nearby = around(groups.get(&(kind, entry.depth)), *rank);
+        *rank += 1;
+        let parent = entry.parent.and_then(|parent| pairs.get(&parent).copied());
+        let siblings = around(children.get(&parent), entry.index);
+
+        let mut candidates: Vec<usize> = siblings
+            .iter()
+            .chain(nearby)
+            .copied()
+            .filter(|&index| !taken.contains(&index) && kind_name(&old[index].node.kind) == kind)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let text = content(entry.node);
+        let words = tokens(&text);
+        let best = candidates
+            .into_iter()
+            .filter(|&index| could_be_similar(&old_words[index], &words))
+            .map(|index| {
+                let same_parent = old[index].parent == parent;
+                (index, similarity(&old_words[index], &words), same_parent)
+            })
+            .filter(|&(_, score, _)| score >= MIN_SIMILARITY)
+            .fold(
+                None,
+                |best: Option<(usize, f32, bool)>, candidate| match best {
+                    Some(best) if (best.1, best.2) >= (candidate.1, candidate.2) => Some(best),
+                    _ => Some(candidate),
+                },
+            );
+        if let Some((old_index, _, _)) = best {
+            pairs.insert(new_index, old_index);
+            taken.insert(old_index);
+        }
+    }
+    pairs
+}
+
+/// The new entries of `pairs` that moved, in order.
+fn moved(old: &[Entry<'_>], new: &[Entry<'_>], pairs: &HashMap<usize, usize>) -> Vec<usize> {
+    let mut moved = Vec::new();
+    // Entries that kept their parent, by old parent, with their old index
+    // among its children, in the new order.
+    let mut kept: HashMap<Option<usize>, Vec<(usize, usize)>> = HashMap::new();
+    for (new_index, entry) in new.iter().enumerate() {
+        let Some(&old_index) = pairs.get(&new_index) else {
+            continue;
+        };
+        let parent = match entry.parent {
+            None => Some(None),
+            Some(parent) => pairs.get(&parent).map(|&parent| Some(parent)),
+        };
+        if parent == Some(old[old_index].parent) {
+            kept.entry(old[old_index].parent)
+                .or_default()
+                .push((new_index, old[old_index].index));
+        } else {
+            moved.push(new_index);
+        }
+    }
+    for siblings in kept.values() {
+        let order: Vec<usize> = siblings.iter().map(|&(_, index)| index).collect();
+        let in_order = longest_increasing(&order);
+        moved.extend(
+            siblings
+                .iter()
+                .zip(in_order)
+                .filter(|&(_, in_order)| !in_order)
+                .map(|(&(new_index, _), _)| new_index),
+        );
+    }
+    moved.sort_unstable();
+    moved
+}
+
+/// Which of `values` make up a longest increasing subsequence of them.
+fn longest_increasing(values: &[usize]) -> Vec<bool> {
+    // The index of the last value of the best subsequence of each length
+    // found so far, and the value before each value in its subsequence.
+    let mut ends: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; values.len()];
+    for (index, &value) in values.iter().enumerate() {
+        let length = ends.partition_point(|&end| values[end] < value);
+        previous[index] = length.checked_sub(1).map(|before| ends[before]);
+        if length == ends.len() {
+            ends.push(index);
+        } else {
+            ends[length] = index;
+        }
+    }
+    let mut kept = vec![false; values.len()];
+    let mut next = ends.last().copied();
+    while let Some(index) = next {
+        kept[index] = true;
+        next = previous[index];
+    }
+    kept
+}
+
+/// At most [`MAX_CANDIDATES`] of `indices`, around `position`.
+fn around(indices: Option<&Vec<usize>>, position: usize) -> &[usize] {
+    let indices = indices.map_or(&[][..], Vec::as_slice);
+    let start = position
+        .saturating_sub(MAX_CANDIDATES / 2)
+        .min(indices.len().saturating_sub(MAX_CANDIDATES));
+    &indices[start..(start + MAX_CANDIDATES).min(indices.len())]
+}
+
+/// The outermost entries that `matched` does not hold for.
+fn unmatched(entries: &[Entry<'_>], matched: impl Fn(usize) -> bool) -> Vec<NodeChange> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|&(index, entry)| !matched(index) && entry.parent.is_none_or(&matched))
+        .map(|(_, entry)| NodeChange {
+            parent: entry.parent_id,
+            index: entry.index,
+            node: entry.node.clone(),
+        })
+        .collect()
+}
+
+/// The text a node holds itself.
+fn own_text(node: &Node) -> &str {
+    match &node.kind {
+        NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => &text.content,
+        NodeKind::CodeBlock { code, .. } => code,
+        _ => "",
+    }
+}
+
+/// The text of a node and the nodes under it, one line per node holding
+/// text.
+fn content(node: &Node) -> String {
+    fn collect<'a>(node: &'a Node, lines: &mut Vec<&'a str>) {
+        match &node.kind {
+            NodeKind::Paragraph(_) | NodeKind::Heading { .. } | NodeKind::CodeBlock { .. } => {
+                lines.push(own_text(node))
+            }
+            _ => node.children.iter().for_each(|child| collect(child, lines)),
+        }
+    }
+    let mut lines = Vec::new();
+    collect(node, &mut lines);
+    lines.join("\n")
+}
+
+/// Name of a node's kind, as it is serialized.
+fn kind_name(kind: &NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Root => "root",
+        NodeKind::Section => "section",
+        NodeKind::Paragraph(_) => "paragraph",
+        NodeKind::Heading { .. } => "heading",
+        NodeKind::BlockQuote => "block_quote",
+        NodeKind::List { .. } => "list",
+        NodeKind::ListItem => "list_item",
+        NodeKind::Table { .. } => "table",
+        NodeKind::TableRow => "table_row",
+        NodeKind::TableCell { .. } => "table_cell",
+        NodeKind::Image { .. } => "image",
+        NodeKind::CodeBlock { .. } => "code_block",
+        NodeKind::HorizontalRule => "horizontal_rule",
+        NodeKind::PageBreak => "page_break",
+        NodeKind::Custom { .. } => "custom",
+    }
+}
+
+/// Whether two nodes have the same kind, properties, style and formatting.
+/// The formatting of their text is compared when `same_text` says their
+/// text is the same, as it moves with any change to the text.
+fn same_attributes(old: &Node, new: &Node, same_text: bool) -> bool {
+    let kind = match (&old.kind, &new.kind) {
+        (NodeKind::Paragraph(a), NodeKind::Paragraph(b)) => !same_text || a.spans == b.spans,
+        (
+            NodeKind::Heading {
+                level: a,
+                text: a_text,
+            },
+            NodeKind::Heading {
+                level: b,
+                text: b_text,
+            },
+        ) => a == b && (!same_text || a_text.spans == b_text.spans),
+        (NodeKind::List { ordered: a }, NodeKind::List { ordered: b }) => a == b,
+        (
+            NodeKind::TableCell {
+                col_span: a_cols,
+                row_span: a_rows,
+            },
+            NodeKind::TableCell {
+                col_span: b_cols,
+                row_span: b_rows,
+            },
+        ) => (a_cols, a_rows) == (b_cols, b_rows),
+        (NodeKind::Image { src: a, alt: a_alt }, NodeKind::Image { src: b, alt: b_alt }) => {
+            (a, a_alt) == (b, b_alt)
+        }
+        (NodeKind::CodeBlock { language: a, .. }, NodeKind::CodeBlock { language: b, .. }) => {
+            a == b
+        }
+        (
+            NodeKind::Custom {
+                kind: a,
+                data: a_data,
+            },
+            NodeKind::Custom {
+                kind: b,
+                data: b_data,
+            },
+        ) => (a, a_data) == (b, b_data),
+        // A table's size follows its rows, which are compared themselves.
+        (a, b) => kind_name(a) == kind_name(b),
+    };
+    kind && old.style == new.style && old.formatting == new.formatting
+}
+
+/// Whether two texts, split into words, differ little enough in length to
+/// be [similar](similarity) enough, which is cheap to tell.
+fn could_be_similar(a: &[&str], b: &[&str]) -> bool {
+    let (shorter, longer) = (a.len().min(b.len()), a.len().max(b.len()));
+    longer == 0 || 2.0 * shorter as f32 / (shorter + longer) as f32 >= MIN_SIMILARITY
+}
+
+/// How much text two texts, split into words, share, from 0 for nothing
+/// to 1 for all of it.
+fn similarity(a: &[&str], b: &[&str]) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let kept = myers(a, b)
+        .iter()
+        .filter(|step| matches!(step, Step::Keep))
+        .count();
+    2.0 * kept as f32 / (a.len() + b.len()) as f32
+}
+
+/// The edits turning `old` into `new`, a word at a time, with touching
+/// edits of the same kind joined.
+fn text_edits(old: &str, new: &str) -> Vec<TextEdit> {
+    let (a, b) = (tokens(old), tokens(new));
+    let (mut i, mut j) = (0, 0);
+    let (mut old_at, mut new_at) = (0, 0);
+    let mut edits: Vec<TextEdit> = Vec::new();
+    for step in myers(&a, &b) {
+        match step {
+            Step::Keep => {
+                old_at += a[i].len();
+                new_at += b[j].len();
+                i += 1;
+                j += 1;
+            }
+            Step::Delete => {
+                let end = old_at + a[i].len();
+                match edits.last_mut() {
+                    Some(TextEdit::Delete { old, text, .. }) if old.end == old_at => {
+                        old.end = end;
+                        text.push_str(a[i]);
+                    }
+                    _ => edits.push(TextEdit::Delete {
+                        old: old_at..end,
+                        at: new_at,
+                        text: a[i].to_string(),
+                    }),
+                }
+                old_at = end;
+                i += 1;
+            }
+            Step::Insert => {
+                let end = new_at + b[j].len();
+                match edits.last_mut() {
+                    Some(TextEdit::Insert { new, text, .. }) if new.end == new_at => {
+                        new.end = end;
+                        text.push_str(b[j]);
+                    }
+                    _ => edits.push(TextEdit::Insert {
+                        at: old_at,
+                        new: new_at..end,
+                        text: b[j].to_string(),
+                    }),
+                }
+                new_at = end;
+                j += 1;
+            }
+        }
+    }
+    edits
+}
+
+/// Words, spaces and punctuation, which together make up `text`.
+fn tokens(text: &str) -> Vec<&str> {
+    text.split_word_bounds().collect()
+}
+
+/// One step of an edit script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// The next item is in both sequences.
+    Keep,
+    /// The next item of the first sequence is not in the second.
+    Delete,
+    /// The next item of the second sequence is not in the first.
+    Insert,
+}
+
+/// A shortest edit script turning `a` into `b`, by Myers' O(ND) algorithm,
+/// with deletions before insertions where both are possible.
+///
+/// Round `d` reaches only the diagonals from `-d` to `d`, so only those of
+/// each round are kept for walking back, in O(D²) memory for D edits.
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Step> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    // The furthest x reached on each diagonal k = x - y, after each round.
+    let mut v = vec![0isize; 2 * max + 3];
+    // Before each round d, the diagonals from -d - 1 to d + 1 of `v`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=max as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end through the rounds.
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = at(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            steps.push(Step::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            steps.push(if x == previous_x {
+                Step::Insert
+            } else {
+                Step::Delete
+            });
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    steps.reverse();
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Text;
+
+    fn document(paragraphs: &[&str]) -> Document {
+        let mut document = Document::new();
+        for text in paragraphs {
+            document.root.add_child(Node::paragraph(Text::new(*text)));
+        }
+        document
+    }
+
+    #[test]
+    fn test_myers_finds_shortest_script() {
+        let (a, b) = (
+            "ABCABBA".chars().collect::<Vec<_>>(),
+            "CBABAC".chars().collect::<Vec<_>>(),
+        );
+        let steps = myers(&a, &b);
+        let edits = steps.iter().filter(|step| **step != Step::Keep).count();
+        assert_eq!(edits, 5);
+        assert_eq!(myers::<char>(&[], &[]), []);
+        assert_eq!(myers(&['a'], &[]), [Step::Delete]);
+    }
+
+    #[test]
+    fn test_inserted_paragraph() {
+        let old = document(&["First", "Third"]);
+        let mut new = old.clone();
+        let inserted = Node::paragraph(Text::new("Second"));
+        let id = inserted.id;
+        new.root.children.insert(1, inserted);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.inserted.len(), 1);
+        assert_eq!(diff.inserted[0].node.id, id);
+        assert_eq!(
+            (diff.inserted[0].parent, diff.inserted[0].index),
+            (new.root.id, 1)
+        );
+        assert!(diff.deleted.is_empty());
+        assert!(diff.modified.is_empty());
+
+        // The other way round, it is deleted.
+        let diff = new.diff(&old);
+        assert_eq!(diff.deleted.len(), 1);
+        assert_eq!(diff.deleted[0].node.id, id);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_changed_word_in_paragraph() {
+        let old = document(&["Intro", "The quick brown fox jumps"]);
+        let mut new = old.clone();
+        if let NodeKind::Paragraph(text) = &mut new.root.children[1].kind {
+            *text = Text::new("The quick red fox jumps");
+        }
+
+        let diff = old.diff(&new);
+        assert!(diff.inserted.is_empty() && diff.deleted.is_empty());
+        assert_eq!(
+            diff.modified,
+            vec![NodeModification {
+                old_id: old.root.children[1].id,
+                new_id: new.root.children[1].id,
+                text: vec![
+                    TextEdit::Delete {
+                        old: 10..15,
+                        at: 10,
+                        text: "brown".to_string(),
+                    },
+                    TextEdit::Insert {
+                        at: 15,
+                        new: 10..13,
+                        text: "red".to_string(),
+                    },
+                ],
+                attributes_changed: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_nodes_without_shared_ids_match_by_content() {
+        // Documents imported twice have fresh IDs.
+        let old = document(&["Alpha beta gamma delta", "Unrelated text"]);
+        let new = document(&["Alpha beta gamma epsilon", "Something else entirely"]);
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].old_id, old.root.children[0].id);
+        assert_eq!(diff.modified[0].new_id, new.root.children[0].id);
+        assert_eq!(diff.inserted[0].node.id, new.root.children[1].id);
+        assert_eq!(diff.deleted[0].node.id, old.root.children[1].id);
+    }
+
+    #[test]
+    fn test_large_documents_without_shared_ids() {
+        let texts: Vec<String> = (0..5000)
+            .map(|i| format!("Paragraph {} of a long report", i))
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let old = document(&texts);
+        let mut new = document(&texts);
+        let NodeKind::Paragraph(text) = &mut new.root.children[2500].kind else {
+            unreachable!();
+        };
+        text.content.push_str(" draft");
+
+        let diff = old.diff(&new);
+        assert!(diff.inserted.is_empty() && diff.deleted.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].old_id, old.root.children[2500].id);
+    }
+
+    #[test]
+    fn test_moved_nodes() {
+        let old = document(&["One", "Two", "Three", "Four"]);
+        let mut new = old.clone();
+        // "Four" moves to the front and a new paragraph goes after it,
+        // which moves nothing else.
+        let four = new.root.children.remove(3);
+        new.root.children.insert(0, four);
+        new.root
+            .children
+            .insert(1, Node::paragraph(Text::new("Inserted")));
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.moved,
+            vec![NodeMove {
+                old_id: old.root.children[3].id,
+                new_id: old.root.children[3].id,
+                old_parent: old.root.id,
+                old_index: 3,
+                new_parent: new.root.id,
+                new_index: 0,
+            }]
+        );
+        assert_eq!(diff.inserted.len(), 1);
+        assert!(diff.deleted.is_empty() && diff.modified.is_empty());
+
+        // Moving into a quote changes the parent.
+        let mut quoted = old.clone();
+        let two = quoted.root.children.remove(1);
+        let mut quote = Node::new(NodeKind::BlockQuote);
+        quote.add_child(two);
+        let quote_id = quote.id;
+        quoted.root.add_child(quote);
+        let diff = old.diff(&quoted);
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old_id, old.root.children[1].id);
+        assert_eq!(
+            (diff.moved[0].new_parent, diff.moved[0].new_index),
+            (quote_id, 0)
+        );
+        assert!(old.diff(&old).moved.is_empty());
+    }
+
+    #[test]
+    fn test_diff_serializes() {
+        let old = document(&["One two"]);
+        let mut new = old.clone();
+        new.root.children[0].kind = NodeKind::Heading {
+            level: 1,
+            text: Text::new("One two three"),
+        };
+        new.root.add_child(Node::paragraph(Text::new("Four")));
+        let diff = old.diff(&new);
+        assert!(diff.modified[0].attributes_changed);
+
+        let json = serde_json::to_string(&diff).unwrap();
+        assert!(json.contains(r#""op":"insert""#));
+        let back: DocumentDiff = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, diff);
+    }
+}
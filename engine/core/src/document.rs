@@ -1,15 +1,18 @@
 //! Document model.
 
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::index::{self, NodeIndex};
 use crate::node::Node;
 use crate::style::StyleSheet;
+use crate::{Error, Result};
 
 /// A Wolia document.
 ///
 /// This is the root container for all document content, shared across
 /// all Wolia applications (Write, Grid, Deck).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
     /// Unique identifier for this document.
     pub id: Uuid,
@@ -19,6 +22,9 @@ pub struct Document {
     pub root: Node,
     /// Document-level styles.
     pub styles: StyleSheet,
+    /// Where each node is in the tree, by ID.
+    #[serde(skip)]
+    index: NodeIndex,
 }
 
 impl Document {
@@ -29,6 +35,7 @@ impl Document {
             metadata: Metadata::default(),
             root: Node::root(),
             styles: StyleSheet::default(),
+            index: NodeIndex::default(),
         }
     }
 
@@ -36,6 +43,61 @@ impl Document {
     pub fn with_id(id: Uuid) -> Self {
         Self { id, ..Self::new() }
     }
+
+    /// Find a node by ID.
+    ///
+    /// Lookups go through an index of where nodes are, which
+    /// [`insert_child`](Self::insert_child) and [`remove`](Self::remove)
+    /// keep current. After other changes to the tree, as to
+    /// [`root`](Self::root) directly, a lookup that finds the index out of
+    /// date or meets an ID it does not know rebuilds it.
+    pub fn node(&self, id: Uuid) -> Option<&Node> {
+        let path = self.index.path(&self.root, id)?;
+        index::node_at(&self.root, &path)
+    }
+
+    /// Find a node by ID, mutably.
+    pub fn node_mut(&mut self, id: Uuid) -> Option<&mut Node> {
+        let path = self.index.path(&self.root, id)?;
+        index::node_at_mut(&mut self.root, &path)
+    }
+
+    /// Insert a node as the `index`th child of the node `parent_id`.
+    pub fn insert_child(&mut self, parent_id: Uuid, index: usize, node: Node) -> Result<()> {
+        let path = self
+            .index
+            .path(&self.root, parent_id)
+            .ok_or(Error::NodeNotFound(parent_id))?;
+        let parent =
+            index::node_at_mut(&mut self.root, &path).ok_or(Error::NodeNotFound(parent_id))?;
+        if index > parent.children.len() {
+            return Err(Error::InvalidOperation(format!(
+                "child index {index} is past the {} children of {parent_id}",
+                parent.children.len()
+            )));
+        }
+        self.index.insert(parent_id, index, &node);
+        parent.children.insert(index, node);
+        Ok(())
+    }
+
+    /// Remove a node and its children from the tree.
+    pub fn remove(&mut self, id: Uuid) -> Result<Node> {
+        let path = self
+            .index
+            .path(&self.root, id)
+            .ok_or(Error::NodeNotFound(id))?;
+        let Some((&position, parent_path)) = path.split_last() else {
+            return Err(Error::InvalidOperation(
+                "the root node cannot be removed".to_string(),
+            ));
+        };
+        let parent =
+            index::node_at_mut(&mut self.root, parent_path).ok_or(Error::NodeNotFound(id))?;
+        let removed = parent.children.remove(position);
+        self.index.forget(&removed);
+        Ok(removed)
+    }
 }
 
 impl Default for Document {
@@ -45,18 +107,157 @@ impl Default for Document {
 }
 
 /// Document metadata.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Metadata {
     /// Document title.
     pub title: Option<String>,
     /// Document author.
     pub author: Option<String>,
-    /// Creation timestamp.
+    /// Creation timestamp in milliseconds since the Unix epoch.
     pub created: Option<i64>,
-    /// Last modified timestamp.
+    /// Last modified timestamp in milliseconds since the Unix epoch.
     pub modified: Option<i64>,
     /// Document description.
     pub description: Option<String>,
     /// Custom properties.
     pub properties: indexmap::IndexMap<String, String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Text;
+
+    fn paragraph(text: &str) -> Node {
+        Node::paragraph(Text::new(text))
+    }
+
+    #[test]
+    fn test_insert_and_look_up() {
+        let mut document = Document::new();
+        let root = document.root.id;
+        let section = Node::section();
+        let section_id = section.id;
+        document.insert_child(root, 0, section).unwrap();
+
+        let (first, second, third) = (paragraph("1"), paragraph("2"), paragraph("3"));
+        let ids = [first.id, second.id, third.id];
+        document.insert_child(section_id, 0, second).unwrap();
+        document.insert_child(section_id, 0, first).unwrap();
+        document.insert_child(section_id, 2, third).unwrap();
+
+        for (id, text) in ids.into_iter().zip(["1", "2", "3"]) {
+            match &document.node(id).unwrap().kind {
+                crate::node::NodeKind::Paragraph(t) => assert_eq!(t.content, text),
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+        assert_eq!(document.node(root).unwrap().id, root);
+
+        document.node_mut(ids[1]).unwrap().style = Some("Quote".to_string());
+        assert_eq!(
+            document.root.children[0].children[1].style.as_deref(),
+            Some("Quote")
+        );
+
+        assert!(matches!(
+            document.insert_child(section_id, 4, paragraph("x")),
+            Err(Error::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            document.insert_child(Uuid::new_v4(), 0, paragraph("x")),
+            Err(Error::NodeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_removed_ids_no_longer_resolve() {
+        let mut document = Document::new();
+        let mut section = Node::section();
+        let nested = paragraph("nested");
+        let nested_id = nested.id;
+        section.add_child(nested);
+        let section_id = section.id;
+        let before = paragraph("before");
+        let before_id = before.id;
+        let after = paragraph("after");
+        let after_id = after.id;
+        document.root.add_child(before);
+        document.root.add_child(section);
+        document.root.add_child(after);
+        assert!(document.node(nested_id).is_some());
+
+        let removed = document.remove(section_id).unwrap();
+        assert_eq!(removed.children[0].id, nested_id);
+        assert!(document.node(section_id).is_none());
+        assert!(document.node(nested_id).is_none());
+        assert_eq!(document.node(before_id).unwrap().id, before_id);
+        assert_eq!(document.node(after_id).unwrap().id, after_id);
+
+        assert!(
+            matches!(document.remove(section_id), Err(Error::NodeNotFound(id)) if id == section_id)
+        );
+        assert!(matches!(
+            document.remove(document.root.id),
+            Err(Error::InvalidOperation(_))
+        ));
+    }
+
+    #[test]
+    fn test_siblings_found_after_earlier_inserts() {
+        let mut document = Document::new();
+        let root = document.root.id;
+        let mut section = Node::section();
+        let nested = paragraph("nested");
+        let nested_id = nested.id;
+        section.add_child(nested);
+        let section_id = section.id;
+        document.insert_child(root, 0, section).unwrap();
+        for i in 0..10 {
+            document
+                .insert_child(root, 0, paragraph(&i.to_string()))
+                .unwrap();
+        }
+        assert_eq!(document.node(nested_id).unwrap().id, nested_id);
+        assert!(std::ptr::eq(
+            document.node(section_id).unwrap(),
+            &document.root.children[10]
+        ));
+
+        let first = document.root.children[0].id;
+        document.remove(first).unwrap();
+        assert!(std::ptr::eq(
+            document.node(nested_id).unwrap(),
+            &document.root.children[9].children[0]
+        ));
+    }
+
+    #[test]
+    fn test_nodes_added_directly_found() {
+        let mut document = Document::new();
+        document.root.add_child(paragraph("indexed"));
+        assert!(document.node(document.root.children[0].id).is_some());
+
+        // Added directly, after the index was built.
+        let direct = paragraph("direct");
+        let direct_id = direct.id;
+        document.root.add_child(direct);
+        assert!(document.node(Uuid::new_v4()).is_none());
+        assert_eq!(document.node(direct_id).unwrap().id, direct_id);
+    }
+
+    #[test]
+    fn test_index_follows_direct_changes() {
+        let mut document = Document::new();
+        let (first, second) = (paragraph("1"), paragraph("2"));
+        let (first_id, second_id) = (first.id, second.id);
+        document.root.add_child(first);
+        document.root.add_child(second);
+        assert!(document.node(second_id).is_some());
+
+        // Changed without going through the document's methods.
+        document.root.children.remove(0);
+        assert!(document.node(first_id).is_none());
+        assert_eq!(document.node(second_id).unwrap().id, second_id);
+    }
+}
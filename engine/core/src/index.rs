@@ -0,0 +1,163 @@
+//! Index from node IDs to their place in the tree.
+
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+use uuid::Uuid;
+
+use crate::node::Node;
+
+/// Child indices leading from the root to a node.
+pub(crate) type Path = Vec<usize>;
+
+/// Where a node is, relative to its parent.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    /// ID of the parent, or `None` for the root.
+    parent: Option<Uuid>,
+    /// Where the node was among its parent's children when it was last
+    /// seen. Inserting or removing an earlier sibling moves it, so it is
+    /// only where the search for the node starts.
+    position: usize,
+}
+
+/// Parents of the nodes in a tree, by ID.
+///
+/// The index is built on first use. Inserting a subtree records only the
+/// subtree and removing one forgets only it: siblings that moved are found
+/// by searching outward from where they were, and their positions are
+/// corrected as they are found. An ID the index does not know, or whose
+/// path no longer leads to it, as after the tree is changed without going
+/// through the index, has the index rebuilt once before it is given up on.
+#[derive(Default)]
+pub(crate) struct NodeIndex {
+    entries: RwLock<HashMap<Uuid, Entry>>,
+}
+
+impl NodeIndex {
+    /// The path of the node with `id`, or `None` if it is not in the tree.
+    pub(crate) fn path(&self, root: &Node, id: Uuid) -> Option<Path> {
+        if self.entries.read().is_empty() {
+            self.rebuild(root);
+        }
+        self.find(root, id).or_else(|| {
+            self.rebuild(root);
+            self.find(root, id)
+        })
+    }
+
+    /// Index every node in the tree anew.
+    pub(crate) fn rebuild(&self, root: &Node) {
+        let mut entries = self.entries.write();
+        entries.clear();
+        record(root, None, 0, &mut entries);
+    }
+
+    /// Index a subtree inserted as the `position`th child of `parent`.
+    pub(crate) fn insert(&self, parent: Uuid, position: usize, node: &Node) {
+        record(node, Some(parent), position, &mut self.entries.write());
+    }
+
+    /// Forget the nodes of a subtree that left the tree.
+    pub(crate) fn forget(&self, removed: &Node) {
+        let mut entries = self.entries.write();
+        let mut stack = vec![removed];
+        while let Some(node) = stack.pop() {
+            entries.remove(&node.id);
+            stack.extend(&node.children);
+        }
+    }
+
+    /// The path of the node with `id` as the index has it, or `None` if the
+    /// index does not know the ID or no longer matches the tree.
+    fn find(&self, root: &Node, id: Uuid) -> Option<Path> {
+        // The node and its ancestors, from the node up.
+        let mut chain = Vec::new();
+        {
+            let entries = self.entries.read();
+            let &entry = entries.get(&id)?;
+            let mut current = (id, entry);
+            loop {
+                chain.push(current);
+                let Some(parent) = current.1.parent else {
+                    break;
+                };
+                // A longer chain than there are nodes goes round in a cycle.
+                match entries.get(&parent) {
+                    Some(&entry) if chain.len() <= entries.len() => current = (parent, entry),
+                    _ => return None,
+                }
+            }
+        }
+        let ((top, _), steps) = chain.split_last()?;
+        if *top != root.id {
+            return None;
+        }
+
+        let mut node = root;
+        let mut path = Vec::with_capacity(steps.len());
+        let mut moved = Vec::new();
+        for &(id, entry) in steps.iter().rev() {
+            let position = locate(&node.children, id, entry.position)?;
+            if position != entry.position {
+                moved.push((id, position));
+            }
+            path.push(position);
+            node = &node.children[position];
+        }
+        if !moved.is_empty() {
+            let mut entries = self.entries.write();
+            for (id, position) in moved {
+                if let Some(entry) = entries.get_mut(&id) {
+                    entry.position = position;
+                }
+            }
+        }
+        Some(path)
+    }
+}
+
+impl Clone for NodeIndex {
+    fn clone(&self) -> Self {
+        Self {
+            entries: RwLock::new(self.entries.read().clone()),
+        }
+    }
+}
+
+impl std::fmt::Debug for NodeIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeIndex")
+            .field("len", &self.entries.read().len())
+            .finish()
+    }
+}
+
+fn record(node: &Node, parent: Option<Uuid>, position: usize, entries: &mut HashMap<Uuid, Entry>) {
+    entries.insert(node.id, Entry { parent, position });
+    for (i, child) in node.children.iter().enumerate() {
+        record(child, Some(node.id), i, entries);
+    }
+}
+
+/// Where the child with `id` is, searching outward from `near`.
+fn locate(children: &[Node], id: Uuid, near: usize) -> Option<usize> {
+    let near = near.min(children.len().saturating_sub(1));
+    (0..children.len()).find_map(|distance| {
+        [near.checked_add(distance), near.checked_sub(distance)]
+            .into_iter()
+            .flatten()
+            .find(|&i| children.get(i).is_some_and(|child| child.id == id))
+    })
+}
+
+/// The node at a path, if the path leads anywhere.
+pub(crate) fn node_at<'a>(root: &'a Node, path: &[usize]) -> Option<&'a Node> {
+    path.iter().try_fold(root, |node, &i| node.children.get(i))
+}
+
+/// The node at a path, mutably.
+pub(crate) fn node_at_mut<'a>(root: &'a mut Node, path: &[usize]) -> Option<&'a mut Node> {
+    path.iter()
+        .try_fold(root, |node, &i| node.children.get_mut(i))
+}
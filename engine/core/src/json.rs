@@ -0,0 +1,177 @@
+//! JSON serialization of documents.
+
+use serde::Serialize;
+
+use crate::document::Document;
+use crate::{Error, Result};
+
+/// Version of the JSON shape written by [`Document::to_json`].
+///
+/// Documents of a newer version are rejected rather than read wrongly.
+/// Fields this version does not know are ignored, so additions that older
+/// readers can skip do not need a new version.
+pub const JSON_VERSION: u32 = 1;
+
+/// The document's fields with the version beside them.
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u32,
+    #[serde(flatten)]
+    document: &'a Document,
+}
+
+impl Document {
+    /// Serialize the document to JSON.
+    ///
+    /// Node kinds are tagged with a `"type"` field and IDs are written as
+    /// UUID strings.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&Versioned {
+            version: JSON_VERSION,
+            document: self,
+        })?)
+    }
+
+    /// Deserialize a document from JSON written by [`to_json`](Self::to_json).
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = match value.get("version") {
+            None => JSON_VERSION,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| {
+                    Error::InvalidStructure(format!("invalid JSON version {version}"))
+                })?,
+        };
+        if version > JSON_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{Node, NodeKind};
+    use crate::style::{Style, TextStyle};
+    use crate::text::{Span, Text};
+    use serde_json::Value;
+
+    fn document() -> Document {
+        let mut document = Document::new();
+        document.metadata.title = Some("Report".to_string());
+        document.styles.insert(Style {
+            name: "Title".to_string(),
+            parent: Some("Normal".to_string()),
+            ..Style::default()
+        });
+
+        let mut title = Text::new("Quarterly report");
+        title.add_span(Span::new(
+            0,
+            9,
+            TextStyle {
+                italic: Some(true),
+                ..TextStyle::default()
+            },
+        ));
+        document
+            .root
+            .add_child(Node::heading(1, title).with_style("Title"));
+        document
+            .root
+            .add_child(Node::heading(2, Text::new("Summary")));
+
+        let mut list = Node::new(NodeKind::List { ordered: true });
+        for item in ["Revenue", "Costs"] {
+            let mut list_item = Node::new(NodeKind::ListItem);
+            list_item.add_child(Node::paragraph(Text::new(item)));
+            list.add_child(list_item);
+        }
+        document.root.add_child(list);
+
+        let mut table = Node::new(NodeKind::Table { rows: 1, cols: 2 });
+        let mut row = Node::new(NodeKind::TableRow);
+        for (text, col_span) in [("Q1", 1), ("Q2", 1)] {
+            let mut cell = Node::new(NodeKind::TableCell {
+                col_span,
+                row_span: 1,
+            });
+            cell.add_child(Node::paragraph(Text::new(text)));
+            row.add_child(cell);
+        }
+        table.add_child(row);
+        document.root.add_child(table);
+
+        document.root.add_child(Node::new(NodeKind::Image {
+            src: "asset:chart".to_string(),
+            alt: Some("Chart".to_string()),
+        }));
+        document
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let document = document();
+        let json = document.to_json().unwrap();
+        let read = Document::from_json(&json).unwrap();
+        assert_eq!(read.to_json().unwrap(), json);
+
+        assert_eq!(read.id, document.id);
+        assert_eq!(read.metadata.title.as_deref(), Some("Report"));
+        assert_eq!(read.root.children[0].style.as_deref(), Some("Title"));
+        assert!(matches!(
+            &read.root.children[0].kind,
+            NodeKind::Heading { level: 1, text } if text.spans[0].style.italic == Some(true)
+        ));
+        assert!(matches!(
+            read.root.children[2].kind,
+            NodeKind::List { ordered: true }
+        ));
+        assert!(matches!(
+            read.root.children[3].children[0].children[1].kind,
+            NodeKind::TableCell {
+                col_span: 1,
+                row_span: 1
+            }
+        ));
+        assert!(matches!(
+            &read.root.children[4].kind,
+            NodeKind::Image { src, alt: Some(alt) } if src == "asset:chart" && alt == "Chart"
+        ));
+    }
+
+    #[test]
+    fn test_shape() {
+        let document = document();
+        let json: Value = serde_json::from_str(&document.to_json().unwrap()).unwrap();
+        assert_eq!(json["version"], JSON_VERSION);
+        assert_eq!(json["id"], document.id.to_string());
+        assert_eq!(json["root"]["id"], document.root.id.to_string());
+        assert_eq!(json["root"]["children"][0]["kind"]["type"], "heading");
+        assert_eq!(json["root"]["children"][3]["kind"]["type"], "table");
+    }
+
+    #[test]
+    fn test_ignores_unknown_fields() {
+        let mut json: Value = serde_json::from_str(&document().to_json().unwrap()).unwrap();
+        json["added_later"] = Value::from(true);
+        json["root"]["children"][0]["added_later"] = Value::from("x");
+        json["root"]["children"][0]["kind"]["added_later"] = Value::from(1);
+        let read = Document::from_json(&json.to_string()).unwrap();
+        assert_eq!(read.root.children.len(), 5);
+    }
+
+    #[test]
+    fn test_rejects_newer_versions() {
+        let mut json: Value = serde_json::from_str(&document().to_json().unwrap()).unwrap();
+        json["version"] = Value::from(JSON_VERSION + 1);
+        assert!(matches!(
+            Document::from_json(&json.to_string()),
+            Err(Error::UnsupportedVersion(version)) if version == JSON_VERSION + 1
+        ));
+        assert!(matches!(Document::from_json("{"), Err(Error::Json(_))));
+    }
+}
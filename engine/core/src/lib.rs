@@ -9,16 +9,27 @@
 //! - Text representation and attributes
 //! - Style system
 //! - Content nodes (paragraphs, tables, images, etc.)
+//! - Document outline and statistics
+//! - Differences between document versions
+//! - JSON serialization
 
 pub mod content;
+pub mod diff;
 pub mod document;
+mod index;
+pub mod json;
 pub mod node;
+pub mod outline;
+pub mod stats;
 pub mod style;
 pub mod text;
 
 pub use content::*;
+pub use diff::DocumentDiff;
 pub use document::Document;
 pub use node::Node;
+pub use outline::OutlineEntry;
+pub use stats::DocumentStats;
 pub use style::Style;
 pub use text::Text;
 
@@ -39,4 +50,10 @@ pub enum Error {
 
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported document version: {0}")]
+    UnsupportedVersion(u32),
 }
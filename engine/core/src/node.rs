@@ -1,11 +1,13 @@
 //! Content nodes.
 
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::style::Formatting;
 use crate::text::Text;
 
 /// A node in the document tree.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     /// Unique identifier.
     pub id: Uuid,
@@ -13,15 +15,34 @@ pub struct Node {
     pub kind: NodeKind,
     /// Child nodes.
     pub children: Vec<Node>,
+    /// Name of the node's style in the document's style sheet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// Formatting set on the node itself, over its style.
+    #[serde(default, skip_serializing_if = "Formatting::is_empty")]
+    pub formatting: Formatting,
 }
 
 impl Node {
+    /// Create a node of the given kind.
+    pub fn new(kind: NodeKind) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            children: Vec::new(),
+            style: None,
+            formatting: Formatting::default(),
+        }
+    }
+
     /// Create a root node.
     pub fn root() -> Self {
         Self {
             id: Uuid::new_v4(),
             kind: NodeKind::Root,
             children: Vec::new(),
+            style: None,
+            formatting: Formatting::default(),
         }
     }
 
@@ -31,26 +52,47 @@ impl Node {
             id: Uuid::new_v4(),
             kind: NodeKind::Paragraph(text),
             children: Vec::new(),
+            style: None,
+            formatting: Formatting::default(),
         }
     }
 
+    /// Create a heading node.
+    pub fn heading(level: u8, text: Text) -> Self {
+        Self::new(NodeKind::Heading { level, text })
+    }
+
     /// Create a section node.
     pub fn section() -> Self {
         Self {
             id: Uuid::new_v4(),
             kind: NodeKind::Section,
             children: Vec::new(),
+            style: None,
+            formatting: Formatting::default(),
         }
     }
 
+    /// Set the node's named style.
+    pub fn with_style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
     /// Add a child node.
     pub fn add_child(&mut self, child: Node) {
         self.children.push(child);
     }
 }
 
+/// Serde default for spans.
+fn one() -> usize {
+    1
+}
+
 /// The type and content of a node.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum NodeKind {
     /// Document root.
     Root,
@@ -60,6 +102,8 @@ pub enum NodeKind {
     Paragraph(Text),
     /// A heading with level (1-6) and text.
     Heading { level: u8, text: Text },
+    /// A block quotation.
+    BlockQuote,
     /// A list (ordered or unordered).
     List { ordered: bool },
     /// A list item.
@@ -68,8 +112,13 @@ pub enum NodeKind {
     Table { rows: usize, cols: usize },
     /// A table row.
     TableRow,
-    /// A table cell.
-    TableCell,
+    /// A table cell, spanning one or more columns and rows.
+    TableCell {
+        #[serde(default = "one")]
+        col_span: usize,
+        #[serde(default = "one")]
+        row_span: usize,
+    },
     /// An image.
     Image { src: String, alt: Option<String> },
     /// A code block.
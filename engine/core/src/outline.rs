@@ -0,0 +1,191 @@
+//! Document outline.
+
+use uuid::Uuid;
+
+use crate::document::Document;
+use crate::node::{Node, NodeKind};
+
+/// A heading in a document's outline, with the headings under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// ID of the heading node.
+    pub id: Uuid,
+    /// Heading level (1-6).
+    pub level: u8,
+    /// Heading text.
+    pub text: String,
+    /// Headings nested under this one.
+    pub children: Vec<OutlineEntry>,
+}
+
+impl OutlineEntry {
+    /// Create an entry without children.
+    pub fn new(id: Uuid, level: u8, text: impl Into<String>) -> Self {
+        Self {
+            id,
+            level,
+            text: text.into(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// An entry of a flattened outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlatEntry<'a> {
+    /// The entry.
+    pub entry: &'a OutlineEntry,
+    /// How deep the entry is nested; top-level entries are at depth 0.
+    pub depth: usize,
+}
+
+/// List an outline's entries in document order with their depths.
+pub fn flatten(entries: &[OutlineEntry]) -> Vec<FlatEntry<'_>> {
+    fn walk<'a>(entries: &'a [OutlineEntry], depth: usize, out: &mut Vec<FlatEntry<'a>>) {
+        for entry in entries {
+            out.push(FlatEntry { entry, depth });
+            walk(&entry.children, depth + 1, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(entries, 0, &mut out);
+    out
+}
+
+impl Document {
+    /// The document's headings as a tree.
+    ///
+    /// Each heading is nested under the closest heading before it with a
+    /// lower level, so an H3 directly after an H1 is the H1's child, one
+    /// level deeper rather than two. The outline is built from the current
+    /// tree on every call.
+    pub fn outline(&self) -> Vec<OutlineEntry> {
+        let mut headings = Vec::new();
+        collect_headings(&self.root, &mut headings);
+
+        // Entries still open for children, outermost first.
+        let mut open: Vec<OutlineEntry> = Vec::new();
+        let mut roots = Vec::new();
+        for entry in headings {
+            while open.last().is_some_and(|last| last.level >= entry.level) {
+                close(&mut open, &mut roots);
+            }
+            open.push(entry);
+        }
+        while !open.is_empty() {
+            close(&mut open, &mut roots);
+        }
+        roots
+    }
+}
+
+/// Move the innermost open entry into its parent, or into the roots.
+fn close(open: &mut Vec<OutlineEntry>, roots: &mut Vec<OutlineEntry>) {
+    if let Some(entry) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.children.push(entry),
+            None => roots.push(entry),
+        }
+    }
+}
+
+fn collect_headings(node: &Node, out: &mut Vec<OutlineEntry>) {
+    if let NodeKind::Heading { level, text } = &node.kind {
+        out.push(OutlineEntry::new(node.id, *level, text.content.clone()));
+    }
+    for child in &node.children {
+        collect_headings(child, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Text;
+
+    fn document(headings: &[(u8, &str)]) -> Document {
+        let mut document = Document::new();
+        let mut section = Node::section();
+        for &(level, text) in headings {
+            section.add_child(Node::heading(level, Text::new(text)));
+            section.add_child(Node::paragraph(Text::new("body")));
+        }
+        document.root.add_child(section);
+        document
+    }
+
+    fn shape(entries: &[OutlineEntry]) -> Vec<(usize, String)> {
+        flatten(entries)
+            .into_iter()
+            .map(|flat| (flat.depth, flat.entry.text.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_nests_mixed_levels() {
+        let document = document(&[
+            (1, "Intro"),
+            (3, "Skipped"),
+            (2, "Background"),
+            (3, "History"),
+            (1, "Method"),
+            (2, "Setup"),
+        ]);
+        let outline = document.outline();
+
+        assert_eq!(outline.len(), 2);
+        let intro = &outline[0];
+        assert_eq!(intro.level, 1);
+        assert_eq!(intro.children.len(), 2);
+        assert_eq!(intro.children[0].text, "Skipped");
+        assert_eq!(intro.children[1].children[0].text, "History");
+        assert_eq!(outline[1].children[0].text, "Setup");
+
+        let depths: Vec<_> = shape(&outline)
+            .into_iter()
+            .map(|(depth, text)| format!("{depth} {text}"))
+            .collect();
+        assert_eq!(
+            depths,
+            [
+                "0 Intro",
+                "1 Skipped",
+                "1 Background",
+                "2 History",
+                "0 Method",
+                "1 Setup"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_starts_below_top_level() {
+        let outline = document(&[(2, "A"), (3, "B"), (1, "C")]).outline();
+        assert_eq!(
+            shape(&outline),
+            [(0, "A".into()), (1, "B".into()), (0, "C".into())]
+        );
+    }
+
+    #[test]
+    fn test_follows_heading_changes() {
+        let mut document = document(&[(1, "Old"), (2, "Child")]);
+        let heading = &mut document.root.children[0].children[0];
+        let id = heading.id;
+        heading.kind = NodeKind::Heading {
+            level: 1,
+            text: Text::new("New"),
+        };
+        document.root.children[0].children[2].kind = NodeKind::Heading {
+            level: 1,
+            text: Text::new("Promoted"),
+        };
+
+        let outline = document.outline();
+        assert_eq!(outline[0].id, id);
+        assert_eq!(outline[0].text, "New");
+        assert!(outline[0].children.is_empty());
+        assert_eq!(outline[1].text, "Promoted");
+    }
+}
@@ -0,0 +1,196 @@
+//! Document statistics.
+
+use std::time::Duration;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::document::Document;
+use crate::node::{Node, NodeKind};
+
+/// Reading speed the reading time is estimated with.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Counts of the words and characters in some text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStats {
+    /// Words, where each CJK character counts as a word.
+    pub words: usize,
+    /// Characters (grapheme clusters), including whitespace.
+    pub characters: usize,
+    /// Characters (grapheme clusters), excluding whitespace.
+    pub characters_no_spaces: usize,
+}
+
+impl TextStats {
+    /// Count the words and characters in a string.
+    pub fn of(text: &str) -> Self {
+        let mut stats = Self::default();
+        for grapheme in text.graphemes(true) {
+            stats.characters += 1;
+            if !grapheme.chars().all(char::is_whitespace) {
+                stats.characters_no_spaces += 1;
+            }
+        }
+        stats.words = text.unicode_words().map(count_words).sum();
+        stats
+    }
+}
+
+impl std::ops::AddAssign for TextStats {
+    fn add_assign(&mut self, other: Self) {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.characters_no_spaces += other.characters_no_spaces;
+    }
+}
+
+/// Statistics of a whole document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DocumentStats {
+    /// Words and characters in paragraphs and headings.
+    pub text: TextStats,
+    /// Paragraphs and headings that hold text.
+    pub paragraphs: usize,
+}
+
+impl DocumentStats {
+    /// Word count.
+    pub fn words(&self) -> usize {
+        self.text.words
+    }
+
+    /// Character count, including whitespace.
+    pub fn characters(&self) -> usize {
+        self.text.characters
+    }
+
+    /// Character count, excluding whitespace.
+    pub fn characters_no_spaces(&self) -> usize {
+        self.text.characters_no_spaces
+    }
+
+    /// Estimated time to read the document at [`WORDS_PER_MINUTE`].
+    pub fn reading_time(&self) -> Duration {
+        let seconds = self.text.words as f64 * 60.0 / WORDS_PER_MINUTE as f64;
+        Duration::from_secs_f64(seconds)
+    }
+}
+
+impl Document {
+    /// Count the document's words, characters, and paragraphs.
+    ///
+    /// Only paragraph and heading text is counted; code blocks, images,
+    /// and other content are not.
+    pub fn statistics(&self) -> DocumentStats {
+        let mut stats = DocumentStats::default();
+        count_node(&self.root, &mut stats);
+        stats
+    }
+}
+
+fn count_node(node: &Node, stats: &mut DocumentStats) {
+    if let NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } = &node.kind {
+        let text = TextStats::of(&text.content);
+        if text.characters_no_spaces > 0 {
+            stats.paragraphs += 1;
+        }
+        stats.text += text;
+    }
+    for child in &node.children {
+        count_node(child, stats);
+    }
+}
+
+/// Count a segmented word, as one word per CJK character plus one for
+/// each run of other characters.
+fn count_words(word: &str) -> usize {
+    let mut count = 0;
+    let mut in_run = false;
+    for c in word.chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_run = false;
+        } else if !in_run {
+            count += 1;
+            in_run = true;
+        }
+    }
+    count
+}
+
+/// Whether a character is written without spaces between words: Han
+/// ideographs and Japanese kana.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana and Katakana
+            | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+            | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+            | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+            | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+            | '\u{20000}'..='\u{2FA1F}' // Supplementary ideographs
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Text;
+
+    #[test]
+    fn test_counts_text() {
+        let stats = TextStats::of("Hello, wide world! It's 3.5 km.");
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.characters, 31);
+        assert_eq!(stats.characters_no_spaces, 26);
+
+        // Combining marks belong to the character before them.
+        assert_eq!(TextStats::of("cafe\u{301}").characters, 4);
+    }
+
+    #[test]
+    fn test_counts_cjk_per_character() {
+        assert_eq!(TextStats::of("我喜欢读书").words, 5);
+        assert_eq!(TextStats::of("カタカナ").words, 4);
+        assert_eq!(TextStats::of("Rust 语言 is fun").words, 5);
+        assert_eq!(TextStats::of("한국어 문장").words, 2);
+    }
+
+    #[test]
+    fn test_document_statistics() {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::heading(1, Text::new("Getting started")));
+        let mut section = Node::section();
+        section.add_child(Node::paragraph(Text::new("The quick brown fox.")));
+        section.add_child(Node::paragraph(Text::new("   ")));
+        section.add_child(Node::new(NodeKind::CodeBlock {
+            language: None,
+            code: "let not_counted = 1;".to_string(),
+        }));
+        section.add_child(Node::new(NodeKind::Image {
+            src: "fox.png".to_string(),
+            alt: Some("A fox".to_string()),
+        }));
+        document.root.add_child(section);
+
+        let stats = document.statistics();
+        assert_eq!(stats.words(), 6);
+        assert_eq!(stats.characters(), 15 + 20 + 3);
+        assert_eq!(stats.characters_no_spaces(), 14 + 17);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.reading_time(), Duration::from_millis(1800));
+    }
+
+    #[test]
+    fn test_reading_time() {
+        let words = "word ".repeat(500);
+        let mut document = Document::new();
+        document.root.add_child(Node::paragraph(Text::new(words)));
+        let stats = document.statistics();
+        assert_eq!(stats.words(), 500);
+        assert_eq!(stats.reading_time(), Duration::from_secs(150));
+        assert_eq!(Document::new().statistics().reading_time(), Duration::ZERO);
+    }
+}
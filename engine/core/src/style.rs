@@ -1,6 +1,12 @@
 //! Style system.
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::node::Node;
+use crate::{Error, Result};
 
 /// A named style in the document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,7 +33,7 @@ impl Default for Style {
 }
 
 /// Text-level formatting.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TextStyle {
     /// Font family name.
     pub font_family: Option<String>,
@@ -53,10 +59,78 @@ pub struct TextStyle {
     pub small_caps: Option<bool>,
     /// Letter spacing in ems.
     pub letter_spacing: Option<f32>,
+    /// Hyperlink target URL.
+    pub link: Option<String>,
+    /// Suggested change the text is part of, if it was inserted or deleted
+    /// while changes were tracked.
+    pub revision: Option<Revision>,
+}
+
+impl TextStyle {
+    /// Apply the properties `other` sets over this style.
+    pub fn merge(&mut self, other: &TextStyle) {
+        fn over<T: Clone>(value: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                value.clone_from(other);
+            }
+        }
+        over(&mut self.font_family, &other.font_family);
+        over(&mut self.font_size, &other.font_size);
+        over(&mut self.font_weight, &other.font_weight);
+        over(&mut self.italic, &other.italic);
+        over(&mut self.underline, &other.underline);
+        over(&mut self.strikethrough, &other.strikethrough);
+        over(&mut self.color, &other.color);
+        over(&mut self.background, &other.background);
+        over(&mut self.superscript, &other.superscript);
+        over(&mut self.subscript, &other.subscript);
+        over(&mut self.small_caps, &other.small_caps);
+        over(&mut self.letter_spacing, &other.letter_spacing);
+        over(&mut self.link, &other.link);
+        over(&mut self.revision, &other.revision);
+    }
+}
+
+/// A suggested change to text, made while changes were tracked, that is
+/// kept apart until it is accepted or rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    /// ID shared by all the text of one suggestion.
+    pub id: Uuid,
+    /// Whether the text is suggested for insertion or deletion.
+    pub kind: RevisionKind,
+    /// Who suggested the change.
+    pub author: String,
+    /// When the change was suggested, in milliseconds since the Unix epoch.
+    pub timestamp: i64,
+}
+
+impl Revision {
+    /// A new suggestion by `author`, made at `timestamp`.
+    pub fn new(kind: RevisionKind, author: impl Into<String>, timestamp: i64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            author: author.into(),
+            timestamp,
+        }
+    }
+}
+
+/// Kind of a suggested change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionKind {
+    /// The text is suggested for insertion: it is shown, and removed if
+    /// the suggestion is rejected.
+    Insertion,
+    /// The text is suggested for deletion: it is shown struck through, and
+    /// removed if the suggestion is accepted.
+    Deletion,
 }
 
 /// Paragraph-level formatting.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ParagraphStyle {
     /// Text alignment.
     pub alignment: Option<Alignment>,
@@ -74,6 +148,55 @@ pub struct ParagraphStyle {
     pub margin_right: Option<f32>,
     /// Tab stops.
     pub tab_stops: Option<Vec<TabStop>>,
+    /// Distance between the default tab stops that follow the last tab
+    /// stop, in points.
+    #[serde(default)]
+    pub default_tab_interval: Option<f32>,
+}
+
+impl ParagraphStyle {
+    /// Apply the properties `other` sets over this style.
+    pub fn merge(&mut self, other: &ParagraphStyle) {
+        fn over<T: Clone>(value: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                value.clone_from(other);
+            }
+        }
+        over(&mut self.alignment, &other.alignment);
+        over(&mut self.line_height, &other.line_height);
+        over(&mut self.space_before, &other.space_before);
+        over(&mut self.space_after, &other.space_after);
+        over(&mut self.first_line_indent, &other.first_line_indent);
+        over(&mut self.margin_left, &other.margin_left);
+        over(&mut self.margin_right, &other.margin_right);
+        over(&mut self.tab_stops, &other.tab_stops);
+        over(&mut self.default_tab_interval, &other.default_tab_interval);
+    }
+}
+
+/// Text and paragraph formatting without a name, such as a node's inline
+/// overrides or a document's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Formatting {
+    /// Text formatting.
+    #[serde(default)]
+    pub text: TextStyle,
+    /// Paragraph formatting.
+    #[serde(default)]
+    pub paragraph: ParagraphStyle,
+}
+
+impl Formatting {
+    /// Whether no property is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply the properties `other` sets over this formatting.
+    pub fn merge(&mut self, other: &Formatting) {
+        self.text.merge(&other.text);
+        self.paragraph.merge(&other.paragraph);
+    }
 }
 
 /// Text alignment.
@@ -86,8 +209,11 @@ pub enum Alignment {
     Justify,
 }
 
+/// Default distance between tab stops, in points: half an inch.
+pub const DEFAULT_TAB_INTERVAL: f32 = 36.0;
+
 /// A tab stop definition.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TabStop {
     /// Position in points from left margin.
     pub position: f32,
@@ -97,20 +223,45 @@ pub struct TabStop {
     pub leader: Option<char>,
 }
 
+impl TabStop {
+    /// Create a tab stop without a leader.
+    pub fn new(position: f32, alignment: TabAlignment) -> Self {
+        Self {
+            position,
+            alignment,
+            leader: None,
+        }
+    }
+
+    /// Fill the space before text at this stop with `leader`, as with the
+    /// dots of a table of contents.
+    pub fn with_leader(mut self, leader: char) -> Self {
+        self.leader = Some(leader);
+        self
+    }
+}
+
 /// Tab stop alignment.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TabAlignment {
+    /// Text starts at the stop.
     Left,
+    /// Text is centered on the stop.
     Center,
+    /// Text ends at the stop.
     Right,
+    /// The decimal separator sits at the stop.
     Decimal,
 }
 
 /// A collection of styles.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StyleSheet {
     /// Named styles.
     pub styles: indexmap::IndexMap<String, Style>,
+    /// Formatting of text that no style or override sets.
+    #[serde(default)]
+    pub defaults: Formatting,
 }
 
 impl StyleSheet {
@@ -131,3 +282,218 @@ impl StyleSheet {
         self.styles.insert(style.name.clone(), style);
     }
 }
+
+/// Formatting resolved for a node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComputedStyle {
+    /// Text formatting.
+    pub text: TextStyle,
+    /// Paragraph formatting.
+    pub paragraph: ParagraphStyle,
+}
+
+/// Resolves the formatting of nodes against a style sheet.
+///
+/// From lowest to highest precedence, a node's formatting is made of:
+///
+/// 1. the style sheet's defaults,
+/// 2. the ancestors of the node's named style, furthest first,
+/// 3. the node's named style,
+/// 4. the node's inline overrides.
+///
+/// Each layer only replaces the properties it sets. Styles are looked up on
+/// every call, so a change to a base style shows in every style derived
+/// from it.
+pub struct StyleResolver<'a> {
+    sheet: &'a StyleSheet,
+}
+
+impl<'a> StyleResolver<'a> {
+    /// Create a resolver for a style sheet.
+    pub fn new(sheet: &'a StyleSheet) -> Self {
+        Self { sheet }
+    }
+
+    /// Resolve the formatting of a node.
+    pub fn resolve(&self, node: &Node) -> Result<ComputedStyle> {
+        let mut formatting = self.named(node.style.as_deref())?;
+        formatting.merge(&node.formatting);
+        Ok(ComputedStyle {
+            text: formatting.text,
+            paragraph: formatting.paragraph,
+        })
+    }
+
+    /// Resolve a named style, as it applies to a node without overrides.
+    pub fn resolve_style(&self, name: &str) -> Result<ComputedStyle> {
+        let formatting = self.named(Some(name))?;
+        Ok(ComputedStyle {
+            text: formatting.text,
+            paragraph: formatting.paragraph,
+        })
+    }
+
+    /// The defaults with a named style and its ancestors over them.
+    fn named(&self, name: Option<&str>) -> Result<Formatting> {
+        let mut formatting = self.sheet.defaults.clone();
+        if let Some(name) = name {
+            for style in self.chain(name)?.into_iter().rev() {
+                formatting.text.merge(&style.text);
+                formatting.paragraph.merge(&style.paragraph);
+            }
+        }
+        Ok(formatting)
+    }
+
+    /// Check that every style's parents exist and do not form a cycle.
+    pub fn validate(&self) -> Result<()> {
+        for name in self.sheet.styles.keys() {
+            self.chain(name)?;
+        }
+        Ok(())
+    }
+
+    /// A style followed by its ancestors, nearest first.
+    fn chain(&self, name: &str) -> Result<Vec<&'a Style>> {
+        let mut chain = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if !seen.insert(name) {
+                return Err(Error::InvalidStructure(format!(
+                    "style {name:?} inherits from itself"
+                )));
+            }
+            let style = self
+                .sheet
+                .get(name)
+                .ok_or_else(|| Error::StyleNotFound(name.to_string()))?;
+            chain.push(style);
+            next = style.parent.as_deref();
+        }
+        Ok(chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Text;
+
+    fn style(name: &str, parent: Option<&str>, text: TextStyle) -> Style {
+        Style {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            text,
+            paragraph: ParagraphStyle::default(),
+        }
+    }
+
+    /// Normal <- Heading <- Heading 1, over defaults.
+    fn sheet() -> StyleSheet {
+        let mut sheet = StyleSheet::new();
+        sheet.defaults.text = TextStyle {
+            font_family: Some("Serif".to_string()),
+            font_size: Some(12.0),
+            color: Some([0, 0, 0, 255]),
+            ..TextStyle::default()
+        };
+        sheet.insert(style(
+            "Normal",
+            None,
+            TextStyle {
+                font_size: Some(11.0),
+                ..TextStyle::default()
+            },
+        ));
+        sheet.insert(style(
+            "Heading",
+            Some("Normal"),
+            TextStyle {
+                font_family: Some("Sans".to_string()),
+                font_weight: Some(700),
+                ..TextStyle::default()
+            },
+        ));
+        let mut heading1 = style(
+            "Heading 1",
+            Some("Heading"),
+            TextStyle {
+                font_size: Some(24.0),
+                ..TextStyle::default()
+            },
+        );
+        heading1.paragraph.space_after = Some(6.0);
+        sheet.insert(heading1);
+        sheet
+    }
+
+    fn heading(style: &str) -> Node {
+        Node::heading(1, Text::new("Title")).with_style(style)
+    }
+
+    #[test]
+    fn test_inherits_through_two_levels() {
+        let sheet = sheet();
+        let computed = StyleResolver::new(&sheet)
+            .resolve(&heading("Heading 1"))
+            .unwrap();
+        assert_eq!(computed.text.font_size, Some(24.0));
+        assert_eq!(computed.text.font_weight, Some(700));
+        assert_eq!(computed.text.font_family.as_deref(), Some("Sans"));
+        assert_eq!(computed.text.color, Some([0, 0, 0, 255]));
+        assert_eq!(computed.paragraph.space_after, Some(6.0));
+
+        let plain = StyleResolver::new(&sheet)
+            .resolve(&Node::section())
+            .unwrap();
+        assert_eq!(plain.text, sheet.defaults.text);
+    }
+
+    #[test]
+    fn test_inline_overrides_win() {
+        let sheet = sheet();
+        let mut node = heading("Heading 1");
+        node.formatting.text.font_size = Some(30.0);
+        node.formatting.text.italic = Some(true);
+        let computed = StyleResolver::new(&sheet).resolve(&node).unwrap();
+        assert_eq!(computed.text.font_size, Some(30.0));
+        assert_eq!(computed.text.italic, Some(true));
+        assert_eq!(computed.text.font_weight, Some(700));
+    }
+
+    #[test]
+    fn test_base_changes_propagate() {
+        let mut sheet = sheet();
+        sheet.styles["Normal"].text.color = Some([200, 0, 0, 255]);
+        sheet.styles["Heading"].text.font_weight = Some(600);
+        let computed = StyleResolver::new(&sheet)
+            .resolve_style("Heading 1")
+            .unwrap();
+        assert_eq!(computed.text.color, Some([200, 0, 0, 255]));
+        assert_eq!(computed.text.font_weight, Some(600));
+    }
+
+    #[test]
+    fn test_rejects_cycles_and_missing_styles() {
+        let mut sheet = sheet();
+        sheet.styles["Normal"].parent = Some("Heading 1".to_string());
+        let resolver = StyleResolver::new(&sheet);
+        assert!(matches!(
+            resolver.resolve(&heading("Heading")),
+            Err(Error::InvalidStructure(_))
+        ));
+        assert!(matches!(
+            resolver.validate(),
+            Err(Error::InvalidStructure(_))
+        ));
+
+        let sheet = self::sheet();
+        let resolver = StyleResolver::new(&sheet);
+        assert!(resolver.validate().is_ok());
+        assert!(matches!(
+            resolver.resolve(&heading("Title")),
+            Err(Error::StyleNotFound(name)) if name == "Title"
+        ));
+    }
+}
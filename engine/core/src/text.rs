@@ -1,11 +1,12 @@
 //! Text representation and spans.
 
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::style::TextStyle;
 
 /// Rich text content with formatting spans.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Text {
     /// The raw text content.
     pub content: String,
@@ -41,6 +42,71 @@ impl Text {
     pub fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
+
+    /// Replace a byte range of the content, moving spans to match.
+    ///
+    /// Spans after the range shift with the text. Text inserted at the end
+    /// of a span joins it, and spans left empty are removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or does not lie on character
+    /// boundaries, like [`String::replace_range`].
+    pub fn replace_range(&mut self, range: std::ops::Range<usize>, with: &str) {
+        let (start, end) = (range.start, range.end);
+        self.content.replace_range(range, with);
+        let inserted_end = start + with.len();
+        let shift = |offset: usize| offset - end + inserted_end;
+        for span in &mut self.spans {
+            span.start = match span.start {
+                offset if offset < start => offset,
+                offset if offset >= end => shift(offset),
+                _ => start,
+            };
+            span.end = match span.end {
+                offset if offset < start => offset,
+                offset if offset >= end && offset > start => shift(offset),
+                _ => inserted_end,
+            };
+        }
+        self.spans.retain(|span| span.start < span.end);
+    }
+
+    /// Copy a byte range of the text with the parts of its spans that fall
+    /// inside it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or does not lie on character
+    /// boundaries.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Text {
+        let mut text = Text::new(&self.content[range.clone()]);
+        for span in &self.spans {
+            let start = span.start.clamp(range.start, range.end);
+            let end = span.end.clamp(range.start, range.end);
+            if start < end {
+                text.add_span(Span::new(
+                    start - range.start,
+                    end - range.start,
+                    span.style.clone(),
+                ));
+            }
+        }
+        text
+    }
+
+    /// Append another text, keeping its spans.
+    pub fn append(&mut self, other: &Text) {
+        let offset = self.content.len();
+        self.content.push_str(&other.content);
+        for span in &other.spans {
+            self.add_span(Span::new(
+                span.start + offset,
+                span.end + offset,
+                span.style.clone(),
+            ));
+        }
+    }
 }
 
 impl From<&str> for Text {
@@ -56,7 +122,7 @@ impl From<String> for Text {
 }
 
 /// A formatting span within text.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Span {
     /// Start offset (byte index).
     pub start: usize,
@@ -0,0 +1,313 @@
+//! Autosave and crash recovery.
+//!
+//! While a document has unsaved changes, a copy of it is written every so
+//! often to a recovery directory in the native format, together with the
+//! assets it embeds. Copies are encoded
+//! and written by a background thread, so editing never waits on the disk,
+//! and each is written to a temporary file and renamed into place, so a
+//! crash mid-write leaves the previous copy whole.
+//!
+//! Saving a document removes its copy. Copies still present at startup
+//! belong to sessions that ended without saving, and are offered for
+//! recovery.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use format_wolia::Package;
+use serde::{Deserialize, Serialize};
+use wolia_core::Document;
+
+use crate::document::{DocumentError, DocumentManager, Result, write_atomic};
+
+/// Time between autosaves unless set otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Extension of recovery copies.
+const DATA_EXTENSION: &str = "wolia";
+
+/// Extension of the files describing recovery copies.
+const INFO_EXTENSION: &str = "json";
+
+/// Writes recovery copies of a document in the background.
+pub struct Autosave {
+    /// Directory copies are written to.
+    dir: PathBuf,
+    /// Time between autosaves.
+    interval: Duration,
+    /// When the last copy was queued.
+    last: Option<Instant>,
+    /// Jobs for the writer thread.
+    sender: Option<Sender<Job>>,
+    /// The writer thread.
+    writer: Option<JoinHandle<()>>,
+    /// The last error the writer met, if not yet taken.
+    error: Arc<Mutex<Option<String>>>,
+}
+
+enum Job {
+    Write(Box<Package>, RecoveryInfo),
+    Remove(String),
+    Flush(Sender<()>),
+}
+
+/// What a recovery copy is a copy of.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RecoveryInfo {
+    key: String,
+    title: String,
+    path: Option<PathBuf>,
+    saved: SystemTime,
+}
+
+impl Autosave {
+    /// Start autosaving into `dir` every `interval`.
+    pub fn new(dir: impl Into<PathBuf>, interval: Duration) -> Self {
+        let dir = dir.into();
+        let error = Arc::new(Mutex::new(None));
+        let (sender, receiver) = mpsc::channel();
+        let writer = {
+            let (dir, error) = (dir.clone(), error.clone());
+            thread::spawn(move || write_jobs(&dir, receiver, &error))
+        };
+        Self {
+            dir,
+            interval,
+            last: None,
+            sender: Some(sender),
+            writer: Some(writer),
+            error,
+        }
+    }
+
+    /// Directory copies are written to.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Time between autosaves.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether an interval has passed since the last copy was queued.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.last
+            .is_none_or(|last| now.duration_since(last) >= self.interval)
+    }
+
+    /// Wait until every queued copy has been written or removed.
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.send(Job::Flush(done)) {
+            let _ = wait.recv();
+        }
+    }
+
+    /// The last error met writing copies, if any since the last call.
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Queue a copy of `package`, replacing any earlier copy with the same
+    /// key.
+    pub fn write(&mut self, key: String, package: Package, title: String, path: Option<PathBuf>) {
+        self.last = Some(Instant::now());
+        let info = RecoveryInfo {
+            key,
+            title,
+            path,
+            saved: SystemTime::now(),
+        };
+        self.send(Job::Write(Box::new(package), info));
+    }
+
+    /// Queue removing the copy with `key`, as once the document is saved.
+    pub fn remove(&mut self, key: String) {
+        self.last = None;
+        self.send(Job::Remove(key));
+    }
+
+    fn send(&self, job: Job) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(job).is_ok())
+    }
+}
+
+impl Drop for Autosave {
+    fn drop(&mut self) {
+        // Closing the channel ends the writer once its queue is empty.
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Carry out jobs until the channel closes.
+fn write_jobs(dir: &Path, jobs: Receiver<Job>, error: &Mutex<Option<String>>) {
+    for job in jobs {
+        let result = match job {
+            Job::Write(package, info) => write_copy(dir, &package, &info),
+            Job::Remove(key) => remove_copy(dir, &key),
+            Job::Flush(done) => {
+                let _ = done.send(());
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            *error.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+        }
+    }
+}
+
+fn write_copy(dir: &Path, package: &Package, info: &RecoveryInfo) -> Result<()> {
+    let (data_path, info_path) = (
+        copy_path(dir, &info.key, DATA_EXTENSION)?,
+        copy_path(dir, &info.key, INFO_EXTENSION)?,
+    );
+    fs::create_dir_all(dir)?;
+    let data = format_wolia::write_package(package).map_err(|_| DocumentError::InvalidFormat)?;
+    let info_data = serde_json::to_vec(info).map_err(|_| DocumentError::InvalidFormat)?;
+    // The data goes first, so that a described copy is always complete.
+    write_atomic(&data_path, |file| file.write_all(&data))?;
+    write_atomic(&info_path, |file| file.write_all(&info_data))
+}
+
+fn remove_copy(dir: &Path, key: &str) -> Result<()> {
+    for extension in [INFO_EXTENSION, DATA_EXTENSION] {
+        match fs::remove_file(copy_path(dir, key, extension)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Path of the copy with `key` in `dir`. Keys are read back from the files
+/// describing copies, so anything but a plain file name is refused, lest a
+/// doctored key reach outside `dir`.
+fn copy_path(dir: &Path, key: &str, extension: &str) -> Result<PathBuf> {
+    let plain = !key.is_empty() && key != "." && key != ".." && !key.contains(['/', '\\', '\0']);
+    if !plain {
+        return Err(DocumentError::InvalidRecoveryKey(key.to_string()));
+    }
+    Ok(dir.join(format!("{key}.{extension}")))
+}
+
+/// A recovery copy left by a session that ended without saving.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryFile {
+    /// Title of the document.
+    pub title: String,
+    /// Where the document was saved, if it ever was.
+    pub original: Option<PathBuf>,
+    /// When the copy was written.
+    pub saved: SystemTime,
+    /// The copy.
+    pub path: PathBuf,
+    /// Key of the copy.
+    key: String,
+}
+
+impl RecoveryFile {
+    /// Recovery copies in `dir`, newest first. Copies that cannot be read
+    /// are skipped.
+    pub fn list(dir: impl AsRef<Path>) -> Result<Vec<RecoveryFile>> {
+        let dir = dir.as_ref();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut files: Vec<RecoveryFile> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != INFO_EXTENSION {
+                    return None;
+                }
+                let info: RecoveryInfo = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+                let data = copy_path(dir, &info.key, DATA_EXTENSION).ok()?;
+                data.exists().then_some(RecoveryFile {
+                    title: info.title,
+                    original: info.path,
+                    saved: info.saved,
+                    path: data,
+                    key: info.key,
+                })
+            })
+            .collect();
+        files.sort_by_key(|file| std::cmp::Reverse(file.saved));
+        Ok(files)
+    }
+
+    /// Read the copied document.
+    pub fn load(&self) -> Result<Document> {
+        Ok(self.load_package()?.document)
+    }
+
+    /// Read the copied document together with the assets it embeds.
+    pub fn load_package(&self) -> Result<Package> {
+        format_wolia::read_package(&fs::read(&self.path)?).map_err(|_| DocumentError::InvalidFormat)
+    }
+
+    /// Open the copy as an unsaved document, at the path of the original.
+    /// The copy stays until the document is saved or it is discarded.
+    pub fn restore(&self) -> Result<DocumentManager> {
+        DocumentManager::restore(self.load()?, &self.title, self.original.clone())
+    }
+
+    /// Delete the copy.
+    pub fn discard(self) -> Result<()> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        remove_copy(dir, &self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_keys_outside_dir_refused() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join("recovery");
+        fs::create_dir(&dir).unwrap();
+        let victim = root.path().join("victim.json");
+        fs::write(&victim, "keep").unwrap();
+        let info = RecoveryInfo {
+            key: "../victim".to_string(),
+            title: "Victim".to_string(),
+            path: None,
+            saved: SystemTime::now(),
+        };
+        fs::write(dir.join("evil.json"), serde_json::to_vec(&info).unwrap()).unwrap();
+        fs::write(dir.join("evil.wolia"), "").unwrap();
+
+        assert!(RecoveryFile::list(&dir).unwrap().is_empty());
+        assert!(remove_copy(&dir, "../victim").is_err());
+        assert!(write_copy(&dir, &Package::default(), &info).is_err());
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep");
+    }
+
+    #[test]
+    fn test_unreadable_copies_skipped() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+        fs::write(dir.path().join("stray.wolia"), "").unwrap();
+        assert!(RecoveryFile::list(dir.path()).unwrap().is_empty());
+        assert!(
+            RecoveryFile::list(dir.path().join("missing"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
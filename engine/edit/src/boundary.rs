@@ -0,0 +1,180 @@
+//! Text boundaries for cursor movement.
+//!
+//! Cursor positions are byte offsets into UTF-8 text. Character movement
+//! steps over whole extended grapheme clusters (UAX #29), so an emoji with a
+//! skin-tone modifier, a flag or a letter with combining accents is passed
+//! in one step, and word movement stops at the word boundaries of the same
+//! annex. Every function returns a grapheme boundary of the text, so the
+//! result is always a valid place to split it.
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The grapheme boundary at or before `position`, clamped to the text.
+pub fn snap(text: &str, position: usize) -> usize {
+    if position >= text.len() {
+        return text.len();
+    }
+    text.grapheme_indices(true)
+        .map(|(offset, _)| offset)
+        .take_while(|&offset| offset <= position)
+        .last()
+        .unwrap_or(0)
+}
+
+/// The boundary after the grapheme cluster at `position`, or the end of the
+/// text if there is none.
+pub fn next_grapheme(text: &str, position: usize) -> usize {
+    let position = snap(text, position);
+    text[position..]
+        .graphemes(true)
+        .next()
+        .map_or(position, |grapheme| position + grapheme.len())
+}
+
+/// The boundary before the grapheme cluster ending at `position`, or the
+/// start of the text if there is none.
+pub fn prev_grapheme(text: &str, position: usize) -> usize {
+    let position = snap(text, position);
+    text[..position]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(offset, _)| offset)
+}
+
+/// The end of the word at or after `position`, skipping any whitespace and
+/// punctuation before it.
+pub fn next_word(text: &str, position: usize) -> usize {
+    let position = snap(text, position);
+    text[position..]
+        .split_word_bound_indices()
+        .find(|(_, segment)| is_word(segment))
+        .map_or(text.len(), |(offset, segment)| {
+            position + offset + segment.len()
+        })
+}
+
+/// The start of the word at or before `position`, skipping any whitespace
+/// and punctuation after it.
+pub fn prev_word(text: &str, position: usize) -> usize {
+    let position = snap(text, position);
+    text[..position]
+        .split_word_bound_indices()
+        .rev()
+        .find(|(_, segment)| is_word(segment))
+        .map_or(0, |(offset, _)| offset)
+}
+
+/// The word-bounded segment at `position`: a word, or a run of whitespace
+/// or punctuation. At the boundary between a word and something else, the
+/// word is preferred.
+pub fn word_at(text: &str, position: usize) -> Range<usize> {
+    let position = snap(text, position);
+    let mut before = None;
+    for (offset, segment) in text.split_word_bound_indices() {
+        let range = offset..offset + segment.len();
+        if range.contains(&position) {
+            return match before {
+                Some(before) if !is_word(segment) => before,
+                _ => range,
+            };
+        }
+        before = (range.end == position && is_word(segment)).then_some(range);
+    }
+    before.unwrap_or(position..position)
+}
+
+/// The line at `position`, not counting the line feeds around it.
+pub fn line_at(text: &str, position: usize) -> Range<usize> {
+    let position = snap(text, position);
+    let start = text[..position].rfind('\n').map_or(0, |index| index + 1);
+    let end = text[position..]
+        .find('\n')
+        .map_or(text.len(), |index| position + index);
+    start..end
+}
+
+/// Whether a word-bounded segment is a word rather than whitespace or
+/// punctuation.
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every boundary reached by stepping right from the start.
+    fn stops(text: &str, step: impl Fn(&str, usize) -> usize) -> Vec<usize> {
+        let mut positions = vec![0];
+        loop {
+            let next = step(text, *positions.last().unwrap());
+            if next == *positions.last().unwrap() {
+                return positions;
+            }
+            positions.push(next);
+        }
+    }
+
+    #[test]
+    fn test_emoji_clusters() {
+        // Waving hand with a skin-tone modifier, then a flag made of two
+        // regional indicators.
+        let text = "a\u{1F44B}\u{1F3FD}\u{1F1EF}\u{1F1F5}b";
+        assert_eq!(stops(text, next_grapheme), vec![0, 1, 9, 17, 18]);
+        assert_eq!(prev_grapheme(text, 17), 9);
+        assert_eq!(prev_grapheme(text, 9), 1);
+    }
+
+    #[test]
+    fn test_combining_accents() {
+        // "e" followed by a combining acute and a combining grave.
+        let text = "e\u{301}\u{300}x";
+        assert_eq!(next_grapheme(text, 0), 5);
+        assert_eq!(prev_grapheme(text, 5), 0);
+        // A position inside the cluster snaps back to its start.
+        assert_eq!(snap(text, 2), 0);
+        assert_eq!(next_grapheme(text, 2), 5);
+    }
+
+    #[test]
+    fn test_cjk() {
+        let text = "日本語";
+        assert_eq!(stops(text, next_grapheme), vec![0, 3, 6, 9]);
+        assert_eq!(prev_grapheme(text, 9), 6);
+    }
+
+    #[test]
+    fn test_ends_of_text() {
+        let text = "ab";
+        assert_eq!(next_grapheme(text, 2), 2);
+        assert_eq!(next_grapheme(text, 10), 2);
+        assert_eq!(prev_grapheme(text, 0), 0);
+        assert_eq!(next_grapheme("", 0), 0);
+        assert_eq!(next_word("", 0), 0);
+    }
+
+    #[test]
+    fn test_words() {
+        let text = "Hello, wide world!";
+        assert_eq!(stops(text, next_word), vec![0, 5, 11, 17, 18]);
+        assert_eq!(prev_word(text, 18), 12);
+        assert_eq!(prev_word(text, 12), 7);
+        assert_eq!(prev_word(text, 9), 7);
+        assert_eq!(prev_word(text, 3), 0);
+    }
+
+    #[test]
+    fn test_word_and_line_at() {
+        let text = "Hello, wide world!\nnext";
+        assert_eq!(word_at(text, 8), 7..11);
+        // The end of a word selects the word, not the space after it.
+        assert_eq!(word_at(text, 11), 7..11);
+        assert_eq!(word_at(text, 6), 6..7);
+        assert_eq!(word_at(text, text.len()), 19..23);
+        assert_eq!(word_at("", 0), 0..0);
+        assert_eq!(line_at(text, 8), 0..18);
+        assert_eq!(line_at(text, 20), 19..23);
+    }
+}
@@ -0,0 +1,554 @@
+//! Plain-text view of a document.
+//!
+//! The editor addresses a document as one string: the text of its
+//! paragraphs, headings and code blocks in document order, separated by line
+//! feeds. Edits to that string are mapped back onto the blocks. Inserting a
+//! line feed outside a code block splits its block into a new paragraph,
+//! and deleting one joins the blocks on either side of it.
+
+use std::ops::Range;
+
+use uuid::Uuid;
+use wolia_core::node::NodeKind;
+use wolia_core::style::ParagraphStyle;
+use wolia_core::text::Span;
+use wolia_core::{Document, Node, Text};
+
+use crate::{Error, Result};
+
+/// The plain text of `document`.
+pub fn text(document: &Document) -> String {
+    let mut blocks = Vec::new();
+    collect_text(&document.root, &mut blocks);
+    blocks.join("\n")
+}
+
+/// The ID of every text block with the plain-text offset it starts at, in
+/// document order.
+pub fn block_starts(document: &Document) -> Vec<(Uuid, usize)> {
+    let mut blocks = Vec::new();
+    collect_blocks_with_ids(&document.root, &mut blocks);
+    let mut start = 0;
+    blocks
+        .into_iter()
+        .map(|(id, len)| {
+            let block = (id, start);
+            start += len + 1;
+            block
+        })
+        .collect()
+}
+
+/// Replace `range` of the document's plain text with `with`, returning the
+/// text that was removed.
+///
+/// Fails without changing the document if the range is out of bounds or
+/// does not lie on character boundaries.
+pub fn replace(document: &mut Document, range: Range<usize>, with: &str) -> Result<String> {
+    let full = text(document);
+    check(&full, &range)?;
+    let removed = full[range.clone()].to_string();
+
+    let mut paths = Vec::new();
+    collect_paths(&document.root, &mut Vec::new(), &mut paths);
+    if paths.is_empty() {
+        document.root.add_child(Node::paragraph(Text::empty()));
+        paths.push(vec![document.root.children.len() - 1]);
+    }
+
+    let lengths: Vec<usize> = paths
+        .iter()
+        .map(|path| content(node_at(&document.root, path)).len())
+        .collect();
+    let (first, start) = locate(&lengths, range.start);
+    let (last, end) = locate(&lengths, range.end);
+
+    let first_node = node_at_mut(&mut document.root, &paths[first]);
+    if first == last && (!with.contains('\n') || is_code(first_node)) {
+        replace_in(first_node, start..end, with);
+        return Ok(removed);
+    }
+
+    let tail = content(node_at(&document.root, &paths[last]))[end..].to_string();
+    for path in paths[first + 1..=last].iter().rev() {
+        let (index, parent) = path.split_last().unwrap();
+        node_at_mut(&mut document.root, parent)
+            .children
+            .remove(*index);
+    }
+
+    let mut pieces = with.split('\n');
+    let head = pieces.next().unwrap_or_default();
+    let first_node = node_at_mut(&mut document.root, &paths[first]);
+    let first_len = content(first_node).len();
+    replace_in(first_node, start..first_len, head);
+
+    let new_blocks: Vec<&str> = pieces.collect();
+    if new_blocks.is_empty() {
+        let len = content(first_node).len();
+        replace_in(first_node, len..len, &tail);
+        return Ok(removed);
+    }
+    let (index, parent) = paths[first].split_last().unwrap();
+    let parent = node_at_mut(&mut document.root, parent);
+    let count = new_blocks.len();
+    for (offset, piece) in new_blocks.into_iter().enumerate() {
+        let mut block = piece.to_string();
+        if offset + 1 == count {
+            block.push_str(&tail);
+        }
+        parent
+            .children
+            .insert(index + 1 + offset, Node::paragraph(Text::new(block)));
+    }
+    Ok(removed)
+}
+
+/// Copies of the text blocks covering `range` of the plain text, cut down
+/// to the part inside it with their formatting.
+///
+/// Joining the contents of the copies with line feeds gives exactly the
+/// text in `range`. Fails if the range is out of bounds or does not lie on
+/// character boundaries.
+pub fn slice(document: &Document, range: Range<usize>) -> Result<Vec<Node>> {
+    if range.is_empty() {
+        check(&text(document), &range)?;
+        return Ok(Vec::new());
+    }
+    Ok(covering(document, range)?
+        .into_iter()
+        .map(|(path, local)| {
+            let node = node_at(&document.root, &path);
+            let mut block = Node::new(node.kind.clone());
+            set_block_text(&mut block, block_text(node).slice(local));
+            block
+        })
+        .collect())
+}
+
+/// The formatting of a block covering part of the plain text.
+#[derive(Debug, Clone)]
+pub struct BlockSpans {
+    /// The part of the block inside the range, relative to its start.
+    pub range: Range<usize>,
+    /// Length of the block's text.
+    pub len: usize,
+    /// The block's spans.
+    pub spans: Vec<Span>,
+    /// Whether the block holds formatting; code blocks do not.
+    pub formatted: bool,
+}
+
+/// The formatting of each block covering `range`.
+pub fn spans(document: &Document, range: Range<usize>) -> Result<Vec<BlockSpans>> {
+    Ok(covering(document, range)?
+        .into_iter()
+        .map(|(path, local)| {
+            let node = node_at(&document.root, &path);
+            let (spans, formatted) = match &node.kind {
+                NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => {
+                    (text.spans.to_vec(), true)
+                }
+                _ => (Vec::new(), false),
+            };
+            BlockSpans {
+                range: local,
+                len: content(node).len(),
+                spans,
+                formatted,
+            }
+        })
+        .collect())
+}
+
+/// Replace the spans of each block covering `range`, in order. Blocks
+/// without formatting, such as code blocks, are left alone.
+pub fn set_spans(document: &mut Document, range: Range<usize>, spans: &[Vec<Span>]) -> Result<()> {
+    for ((path, _), spans) in covering(document, range)?.into_iter().zip(spans) {
+        if let NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } =
+            &mut node_at_mut(&mut document.root, &path).kind
+        {
+            text.spans = spans.iter().cloned().collect();
+        }
+    }
+    Ok(())
+}
+
+/// The paragraph formatting set directly on each block covering `range`.
+pub fn paragraph_styles(document: &Document, range: Range<usize>) -> Result<Vec<ParagraphStyle>> {
+    Ok(covering(document, range)?
+        .into_iter()
+        .map(|(path, _)| node_at(&document.root, &path).formatting.paragraph.clone())
+        .collect())
+}
+
+/// Replace the paragraph formatting set directly on each block covering
+/// `range`, in order.
+pub fn set_paragraph_styles(
+    document: &mut Document,
+    range: Range<usize>,
+    styles: &[ParagraphStyle],
+) -> Result<()> {
+    for ((path, _), style) in covering(document, range)?.into_iter().zip(styles) {
+        node_at_mut(&mut document.root, &path).formatting.paragraph = style.clone();
+    }
+    Ok(())
+}
+
+/// The paths of the blocks covering `range`, each with the part of the
+/// block inside the range.
+fn covering(document: &Document, range: Range<usize>) -> Result<Vec<(Vec<usize>, Range<usize>)>> {
+    check(&text(document), &range)?;
+    let mut paths = Vec::new();
+    collect_paths(&document.root, &mut Vec::new(), &mut paths);
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    for path in paths {
+        let end = start + content(node_at(&document.root, &path)).len();
+        if range.start <= end && range.end >= start {
+            let local = range.start.max(start) - start..range.end.min(end) - start;
+            blocks.push((path, local));
+        }
+        start = end + 1;
+    }
+    Ok(blocks)
+}
+
+/// Insert text blocks at `position` of the plain text, keeping their kinds
+/// and formatting, and return the length of plain text inserted.
+///
+/// The first block's text joins the block at `position`, and the text
+/// after `position` moves to the end of the last inserted block, as if the
+/// blocks' contents had been typed there separated by line feeds.
+pub fn insert_blocks(document: &mut Document, position: usize, blocks: &[Node]) -> Result<usize> {
+    let full = text(document);
+    check(&full, &(position..position))?;
+    let Some((first, rest)) = blocks.split_first() else {
+        return Ok(0);
+    };
+    let inserted = blocks
+        .iter()
+        .map(|block| content(block).len())
+        .sum::<usize>()
+        + blocks.len()
+        - 1;
+
+    let mut paths = Vec::new();
+    collect_paths(&document.root, &mut Vec::new(), &mut paths);
+    if paths.is_empty() {
+        document.root.add_child(Node::paragraph(Text::empty()));
+        paths.push(vec![document.root.children.len() - 1]);
+    }
+    let lengths: Vec<usize> = paths
+        .iter()
+        .map(|path| content(node_at(&document.root, path)).len())
+        .collect();
+    let (index, offset) = locate(&lengths, position);
+
+    let target = node_at_mut(&mut document.root, &paths[index]);
+    let current = block_text(target);
+    let mut head = current.slice(0..offset);
+    let tail = current.slice(offset..current.content.len());
+    head.append(&block_text(first));
+    let Some((last, middle)) = rest.split_last() else {
+        head.append(&tail);
+        set_block_text(target, head);
+        return Ok(inserted);
+    };
+    set_block_text(target, head);
+
+    let mut last_text = block_text(last);
+    last_text.append(&tail);
+    let mut last = Node::new(last.kind.clone());
+    set_block_text(&mut last, last_text);
+
+    let (child, parent) = paths[index].split_last().unwrap();
+    let parent = node_at_mut(&mut document.root, parent);
+    let new_blocks = middle
+        .iter()
+        .map(|block| {
+            let mut node = Node::new(block.kind.clone());
+            set_block_text(&mut node, block_text(block));
+            node
+        })
+        .chain(std::iter::once(last));
+    for (offset, block) in new_blocks.enumerate() {
+        parent.children.insert(child + 1 + offset, block);
+    }
+    Ok(inserted)
+}
+
+/// Insert `block`, which holds no text, such as an image, after the text
+/// block containing `position` of the plain text.
+pub fn insert_block(document: &mut Document, position: usize, block: Node) -> Result<()> {
+    let full = text(document);
+    check(&full, &(position..position))?;
+    let mut paths = Vec::new();
+    collect_paths(&document.root, &mut Vec::new(), &mut paths);
+    if paths.is_empty() {
+        document.root.add_child(block);
+        return Ok(());
+    }
+    let lengths: Vec<usize> = paths
+        .iter()
+        .map(|path| content(node_at(&document.root, path)).len())
+        .collect();
+    let (index, _) = locate(&lengths, position);
+    let (child, parent) = paths[index].split_last().unwrap();
+    node_at_mut(&mut document.root, parent)
+        .children
+        .insert(child + 1, block);
+    Ok(())
+}
+
+/// Remove the node with `id` from wherever it is in the document,
+/// returning it.
+pub fn remove_block(document: &mut Document, id: Uuid) -> Result<Node> {
+    fn remove(node: &mut Node, id: Uuid) -> Option<Node> {
+        if let Some(index) = node.children.iter().position(|child| child.id == id) {
+            return Some(node.children.remove(index));
+        }
+        node.children.iter_mut().find_map(|child| remove(child, id))
+    }
+    remove(&mut document.root, id).ok_or(Error::NodeNotFound(id))
+}
+
+/// Replace the node with the ID of `block` by `block`, returning the node
+/// it replaced.
+pub fn replace_block(document: &mut Document, block: Node) -> Result<Node> {
+    fn find(node: &mut Node, id: Uuid) -> Option<&mut Node> {
+        if node.id == id {
+            return Some(node);
+        }
+        node.children.iter_mut().find_map(|child| find(child, id))
+    }
+    let id = block.id;
+    let node = find(&mut document.root, id).ok_or(Error::NodeNotFound(id))?;
+    Ok(std::mem::replace(node, block))
+}
+
+/// A copy of the text block holding `range` of the plain text with the
+/// text in it removed, keeping the formatting of the rest, to put back
+/// with [`replace_block`].
+///
+/// Fails if the range is out of bounds or not inside one block.
+pub fn cut_block(document: &Document, range: Range<usize>) -> Result<Node> {
+    let blocks = covering(document, range)?;
+    let [(path, local)] = blocks.as_slice() else {
+        return Err(Error::InvalidSelection);
+    };
+    let mut block = node_at(&document.root, path).clone();
+    replace_in(&mut block, local.clone(), "");
+    Ok(block)
+}
+
+/// The text of `blocks`, separated by line feeds.
+pub fn blocks_text(blocks: &[Node]) -> String {
+    blocks.iter().map(content).collect::<Vec<_>>().join("\n")
+}
+
+/// Collect copies of every text block under `node`, in document order.
+pub fn collect_blocks(node: &Node, blocks: &mut Vec<Node>) {
+    if is_text_block(node) {
+        blocks.push(Node::new(node.kind.clone()));
+    }
+    for child in &node.children {
+        collect_blocks(child, blocks);
+    }
+}
+
+/// Check that `range` is a valid range of `full`.
+fn check(full: &str, range: &Range<usize>) -> Result<()> {
+    if range.start > range.end {
+        return Err(Error::InvalidSelection);
+    }
+    for position in [range.start, range.end] {
+        if !full.is_char_boundary(position) {
+            return Err(Error::InvalidPosition(position));
+        }
+    }
+    Ok(())
+}
+
+/// Collect the text of every text block under `node`, in document order.
+fn collect_text<'a>(node: &'a Node, blocks: &mut Vec<&'a str>) {
+    if is_text_block(node) {
+        blocks.push(content(node));
+    }
+    for child in &node.children {
+        collect_text(child, blocks);
+    }
+}
+
+/// Collect the ID and text length of every text block under `node`.
+fn collect_blocks_with_ids(node: &Node, blocks: &mut Vec<(Uuid, usize)>) {
+    if is_text_block(node) {
+        blocks.push((node.id, content(node).len()));
+    }
+    for child in &node.children {
+        collect_blocks_with_ids(child, blocks);
+    }
+}
+
+/// Collect the child-index paths of every text block under `node`.
+fn collect_paths(node: &Node, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+    if is_text_block(node) {
+        paths.push(path.clone());
+    }
+    for (index, child) in node.children.iter().enumerate() {
+        path.push(index);
+        collect_paths(child, path, paths);
+        path.pop();
+    }
+}
+
+/// The block containing plain-text `position`, and the offset within it.
+///
+/// A position on a block boundary belongs to the end of the earlier block.
+fn locate(lengths: &[usize], position: usize) -> (usize, usize) {
+    let mut start = 0;
+    for (index, &len) in lengths.iter().enumerate() {
+        if position <= start + len {
+            return (index, position - start);
+        }
+        start += len + 1;
+    }
+    let last = lengths.len() - 1;
+    (last, lengths[last])
+}
+
+fn is_text_block(node: &Node) -> bool {
+    matches!(
+        node.kind,
+        NodeKind::Paragraph(_) | NodeKind::Heading { .. } | NodeKind::CodeBlock { .. }
+    )
+}
+
+fn is_code(node: &Node) -> bool {
+    matches!(node.kind, NodeKind::CodeBlock { .. })
+}
+
+fn content(node: &Node) -> &str {
+    match &node.kind {
+        NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => &text.content,
+        NodeKind::CodeBlock { code, .. } => code,
+        _ => "",
+    }
+}
+
+/// The text of a block with its formatting.
+fn block_text(node: &Node) -> Text {
+    match &node.kind {
+        NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => text.clone(),
+        NodeKind::CodeBlock { code, .. } => Text::new(code.as_str()),
+        _ => Text::empty(),
+    }
+}
+
+fn set_block_text(node: &mut Node, new_text: Text) {
+    match &mut node.kind {
+        NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => *text = new_text,
+        NodeKind::CodeBlock { code, .. } => *code = new_text.content,
+        _ => {}
+    }
+}
+
+fn replace_in(node: &mut Node, range: Range<usize>, with: &str) {
+    match &mut node.kind {
+        NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => {
+            text.replace_range(range, with)
+        }
+        NodeKind::CodeBlock { code, .. } => code.replace_range(range, with),
+        _ => {}
+    }
+}
+
+fn node_at<'a>(root: &'a Node, path: &[usize]) -> &'a Node {
+    path.iter().fold(root, |node, &index| &node.children[index])
+}
+
+fn node_at_mut<'a>(root: &'a mut Node, path: &[usize]) -> &'a mut Node {
+    path.iter()
+        .fold(root, |node, &index| &mut node.children[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(blocks: &[&str]) -> Document {
+        let mut document = Document::new();
+        for block in blocks {
+            document.root.add_child(Node::paragraph(Text::new(*block)));
+        }
+        document
+    }
+
+    #[test]
+    fn test_edit_within_block() {
+        let mut doc = document(&["hello", "world"]);
+        assert_eq!(replace(&mut doc, 6..6, "big ").unwrap(), "");
+        assert_eq!(text(&doc), "hello\nbig world");
+        assert_eq!(replace(&mut doc, 0..5, "hi").unwrap(), "hello");
+        assert_eq!(text(&doc), "hi\nbig world");
+        assert_eq!(doc.root.children.len(), 2);
+    }
+
+    #[test]
+    fn test_line_feed_splits_and_joins_blocks() {
+        let mut doc = document(&["onetwo"]);
+        replace(&mut doc, 3..3, "\n").unwrap();
+        assert_eq!(text(&doc), "one\ntwo");
+        assert_eq!(doc.root.children.len(), 2);
+
+        assert_eq!(replace(&mut doc, 3..4, "").unwrap(), "\n");
+        assert_eq!(text(&doc), "onetwo");
+        assert_eq!(doc.root.children.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_across_blocks() {
+        let mut doc = document(&["abc", "def", "ghi"]);
+        assert_eq!(replace(&mut doc, 2..9, "X").unwrap(), "c\ndef\ng");
+        assert_eq!(text(&doc), "abXhi");
+        assert_eq!(doc.root.children.len(), 1);
+    }
+
+    #[test]
+    fn test_empty_document_gets_a_paragraph() {
+        let mut doc = Document::new();
+        replace(&mut doc, 0..0, "hi").unwrap();
+        assert_eq!(text(&doc), "hi");
+        assert!(matches!(doc.root.children[0].kind, NodeKind::Paragraph(_)));
+    }
+
+    #[test]
+    fn test_slice_and_insert_blocks() {
+        let mut doc = document(&["abc", "def", "ghi"]);
+        let blocks = slice(&doc, 1..6).unwrap();
+        let contents: Vec<&str> = blocks.iter().map(content).collect();
+        assert_eq!(contents, vec!["bc", "de"]);
+
+        assert_eq!(insert_blocks(&mut doc, 9, &blocks).unwrap(), 5);
+        assert_eq!(text(&doc), "abc\ndef\ngbc\ndehi");
+        assert_eq!(doc.root.children.len(), 4);
+
+        let single = slice(&doc, 0..2).unwrap();
+        assert_eq!(insert_blocks(&mut doc, 0, &single).unwrap(), 2);
+        assert_eq!(text(&doc), "ababc\ndef\ngbc\ndehi");
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        let mut doc = document(&["é"]);
+        assert!(matches!(
+            replace(&mut doc, 1..1, "x"),
+            Err(Error::InvalidPosition(1))
+        ));
+        assert!(matches!(
+            replace(&mut doc, 0..9, ""),
+            Err(Error::InvalidPosition(9))
+        ));
+        assert_eq!(text(&doc), "é");
+    }
+}
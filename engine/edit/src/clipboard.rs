@@ -1,4 +1,27 @@
 //! Clipboard operations.
+//!
+//! Copying puts a [`Fragment`] on the clipboard in several representations
+//! at once: the native fragment, which keeps every block and span exactly,
+//! Markdown, which keeps headings, bold and italic for other applications,
+//! and plain text. Pasting takes the richest representation on offer,
+//! falling back through [`PASTE_PREFERENCE`].
+
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+use wolia_core::{Document, Node, Text};
+
+use crate::{Error, Result, buffer};
+
+/// MIME type of plain text.
+pub const MIME_TEXT: &str = "text/plain";
+/// MIME type of Markdown.
+pub const MIME_MARKDOWN: &str = "text/markdown";
+/// MIME type of a serialized [`Fragment`].
+pub const MIME_NATIVE: &str = "application/x-wolia-fragment";
+
+/// Representations to paste from, most preferred first.
+pub const PASTE_PREFERENCE: [&str; 3] = [MIME_NATIVE, MIME_MARKDOWN, MIME_TEXT];
 
 /// Clipboard content types.
 #[derive(Debug, Clone)]
@@ -11,6 +34,105 @@ pub enum ClipboardContent {
     Native(Vec<u8>),
 }
 
+/// A copied piece of a document: text blocks with their kinds and
+/// formatting.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fragment {
+    /// The blocks, in order. The first and last may be parts of blocks.
+    pub blocks: Vec<Node>,
+}
+
+impl Fragment {
+    /// A fragment of plain text, one paragraph per line.
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            blocks: text
+                .split('\n')
+                .map(|line| Node::paragraph(Text::new(line)))
+                .collect(),
+        }
+    }
+
+    /// A fragment of the text blocks in some Markdown.
+    pub fn from_markdown(markdown: &str) -> Result<Self> {
+        let document =
+            format_markdown::read(markdown).map_err(|error| Error::Clipboard(error.to_string()))?;
+        let mut blocks = Vec::new();
+        buffer::collect_blocks(&document.root, &mut blocks);
+        Ok(Self { blocks })
+    }
+
+    /// Decode a fragment serialized with [`to_native`](Self::to_native).
+    pub fn from_native(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).map_err(|error| Error::Clipboard(error.to_string()))
+    }
+
+    /// Serialize the fragment in the native clipboard format.
+    pub fn to_native(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    /// The fragment as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut document = Document::new();
+        document.root.children = self.blocks.clone();
+        format_markdown::write(&document).unwrap_or_else(|_| self.plain_text())
+    }
+
+    /// The fragment's text, with blocks separated by line feeds.
+    pub fn plain_text(&self) -> String {
+        buffer::blocks_text(&self.blocks)
+    }
+
+    /// Whether the fragment has no text.
+    pub fn is_empty(&self) -> bool {
+        self.plain_text().is_empty()
+    }
+}
+
+/// Clipboard contents in one or more representations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClipboardData {
+    representations: Vec<(String, Vec<u8>)>,
+}
+
+impl ClipboardData {
+    /// Create empty clipboard data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every representation of a fragment.
+    pub fn from_fragment(fragment: &Fragment) -> Self {
+        let mut data = Self::new();
+        data.insert(MIME_NATIVE, fragment.to_native());
+        data.insert(MIME_MARKDOWN, fragment.to_markdown().into_bytes());
+        data.insert(MIME_TEXT, fragment.plain_text().into_bytes());
+        data
+    }
+
+    /// Add or replace a representation.
+    pub fn insert(&mut self, mime: &str, data: Vec<u8>) {
+        match self.representations.iter_mut().find(|(m, _)| m == mime) {
+            Some((_, existing)) => *existing = data,
+            None => self.representations.push((mime.to_string(), data)),
+        }
+    }
+
+    /// Get a representation.
+    pub fn get(&self, mime: &str) -> Option<&[u8]> {
+        self.representations
+            .iter()
+            .find(|(m, _)| m == mime)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// MIME types of the representations held.
+    pub fn mimes(&self) -> impl Iterator<Item = &str> {
+        self.representations.iter().map(|(mime, _)| mime.as_str())
+    }
+}
+
 /// Clipboard interface.
 pub trait Clipboard {
     /// Get text from clipboard.
@@ -24,4 +146,175 @@ pub trait Clipboard {
 
     /// Set rich content to clipboard.
     fn set_content(&self, content: ClipboardContent) -> crate::Result<()>;
+
+    /// Replace the clipboard contents with every representation in `data`.
+    fn set_data(&self, data: ClipboardData) -> crate::Result<()>;
+
+    /// Get one representation from the clipboard.
+    fn get_data(&self, mime: &str) -> Option<Vec<u8>>;
+
+    /// Copy a fragment in every representation.
+    fn copy(&self, fragment: &Fragment) -> crate::Result<()> {
+        self.set_data(ClipboardData::from_fragment(fragment))
+    }
+
+    /// Paste from the most preferred of the `available` representations,
+    /// falling back to the next if one cannot be read.
+    fn paste(&self, available: &[&str]) -> Option<Fragment> {
+        PASTE_PREFERENCE
+            .iter()
+            .filter(|mime| available.contains(mime))
+            .find_map(|&mime| {
+                let data = self.get_data(mime)?;
+                match mime {
+                    MIME_NATIVE => Fragment::from_native(&data).ok(),
+                    MIME_MARKDOWN => Fragment::from_markdown(std::str::from_utf8(&data).ok()?).ok(),
+                    _ => Some(Fragment::from_text(std::str::from_utf8(&data).ok()?)),
+                }
+            })
+    }
+}
+
+/// A clipboard held in memory, for headless use and tests.
+#[derive(Debug, Default)]
+pub struct MemoryClipboard {
+    data: RefCell<ClipboardData>,
+}
+
+impl MemoryClipboard {
+    /// Create an empty clipboard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// MIME types of the representations held.
+    pub fn mimes(&self) -> Vec<String> {
+        self.data.borrow().mimes().map(str::to_string).collect()
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get_text(&self) -> Option<String> {
+        self.get_data(MIME_TEXT)
+            .and_then(|data| String::from_utf8(data).ok())
+    }
+
+    fn set_text(&self, text: &str) -> crate::Result<()> {
+        let mut data = ClipboardData::new();
+        data.insert(MIME_TEXT, text.as_bytes().to_vec());
+        self.set_data(data)
+    }
+
+    fn get_content(&self) -> Option<ClipboardContent> {
+        self.get_data(MIME_NATIVE)
+            .map(ClipboardContent::Native)
+            .or_else(|| self.get_text().map(ClipboardContent::Text))
+    }
+
+    fn set_content(&self, content: ClipboardContent) -> crate::Result<()> {
+        let mut data = ClipboardData::new();
+        match content {
+            ClipboardContent::Text(text) => data.insert(MIME_TEXT, text.into_bytes()),
+            ClipboardContent::RichText(html) => data.insert("text/html", html.into_bytes()),
+            ClipboardContent::Native(native) => data.insert(MIME_NATIVE, native),
+        }
+        self.set_data(data)
+    }
+
+    fn set_data(&self, data: ClipboardData) -> crate::Result<()> {
+        *self.data.borrow_mut() = data;
+        Ok(())
+    }
+
+    fn get_data(&self, mime: &str) -> Option<Vec<u8>> {
+        self.data.borrow().get(mime).map(<[u8]>::to_vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::node::NodeKind;
+    use wolia_core::style::TextStyle;
+    use wolia_core::text::Span;
+
+    use super::*;
+
+    fn bold() -> TextStyle {
+        TextStyle {
+            font_weight: Some(700),
+            ..TextStyle::default()
+        }
+    }
+
+    fn italic() -> TextStyle {
+        TextStyle {
+            italic: Some(true),
+            ..TextStyle::default()
+        }
+    }
+
+    fn styled_fragment() -> Fragment {
+        let mut heading = Text::new("Title");
+        heading.add_span(Span::new(0, 5, italic()));
+        let mut paragraph = Text::new("some bold text");
+        paragraph.add_span(Span::new(5, 9, bold()));
+        Fragment {
+            blocks: vec![Node::heading(2, heading), Node::paragraph(paragraph)],
+        }
+    }
+
+    #[test]
+    fn test_copy_offers_every_representation() {
+        let clipboard = MemoryClipboard::new();
+        clipboard.copy(&styled_fragment()).unwrap();
+        assert_eq!(
+            clipboard.mimes(),
+            vec![MIME_NATIVE, MIME_MARKDOWN, MIME_TEXT]
+        );
+        assert_eq!(clipboard.get_text().unwrap(), "Title\nsome bold text");
+        let markdown = String::from_utf8(clipboard.get_data(MIME_MARKDOWN).unwrap()).unwrap();
+        assert!(markdown.contains("**bold**"), "{markdown}");
+    }
+
+    #[test]
+    fn test_paste_prefers_native() {
+        let clipboard = MemoryClipboard::new();
+        clipboard.copy(&styled_fragment()).unwrap();
+        let fragment = clipboard
+            .paste(&[MIME_TEXT, MIME_MARKDOWN, MIME_NATIVE])
+            .unwrap();
+        let NodeKind::Heading { level, text } = &fragment.blocks[0].kind else {
+            panic!("expected a heading");
+        };
+        assert_eq!(*level, 2);
+        assert_eq!(text.spans[0].style.italic, Some(true));
+        let NodeKind::Paragraph(text) = &fragment.blocks[1].kind else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!((text.spans[0].start, text.spans[0].end), (5, 9));
+        assert_eq!(text.spans[0].style.font_weight, Some(700));
+    }
+
+    #[test]
+    fn test_paste_falls_back_to_markdown() {
+        let clipboard = MemoryClipboard::new();
+        clipboard.copy(&styled_fragment()).unwrap();
+        let fragment = clipboard.paste(&[MIME_TEXT, MIME_MARKDOWN]).unwrap();
+        assert_eq!(fragment.plain_text(), "Title\nsome bold text");
+        let NodeKind::Paragraph(text) = &fragment.blocks[1].kind else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(text.spans[0].style.font_weight, Some(700));
+    }
+
+    #[test]
+    fn test_paste_plain_text_only() {
+        let clipboard = MemoryClipboard::new();
+        clipboard.set_text("one\ntwo").unwrap();
+        // A stale native entry in the list is skipped when it is missing.
+        let fragment = clipboard.paste(&[MIME_NATIVE, MIME_TEXT]).unwrap();
+        assert_eq!(fragment.blocks.len(), 2);
+        assert_eq!(fragment.plain_text(), "one\ntwo");
+        assert!(clipboard.paste(&["image/png"]).is_none());
+    }
 }
@@ -0,0 +1,495 @@
+//! Conflict-free replicated text for collaborative editing.
+//!
+//! [`CrdtText`] keeps the text as a replicated growable array (RGA). Every
+//! character gets an [`ItemId`], a Lamport clock paired with the site that
+//! inserted it, and remembers the character to its left at the time it was
+//! inserted, its origin. Characters inserted concurrently after the same
+//! origin are ordered by descending id, so every replica puts them in the
+//! same place. Deleted characters stay in the sequence as tombstones, so an
+//! insertion made after a character that another site has since deleted
+//! still finds its place.
+//!
+//! Operations commute: replicas that have seen the same operations hold the
+//! same text, whatever order they arrived in. An operation that refers to
+//! characters a replica has not seen yet is held back until they arrive.
+//!
+//! Offsets are byte offsets into the visible text, as with [`Operation`].
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Operation, Result};
+
+/// Identifies a replica taking part in an editing session.
+pub type SiteId = u64;
+
+/// The identity of one character: the Lamport clock when it was inserted and
+/// the site that inserted it. Ids compare by clock, then by site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ItemId {
+    pub clock: u64,
+    pub site: SiteId,
+}
+
+/// A replicated change to the text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrdtOp {
+    /// Insert `text` after the character `origin`, or at the start if there
+    /// is none. The inserted characters take consecutive clocks starting at
+    /// `id`, each following the one before it.
+    Insert {
+        id: ItemId,
+        origin: Option<ItemId>,
+        text: String,
+    },
+    /// Delete the characters with these ids.
+    Delete { ids: Vec<ItemId> },
+}
+
+/// One character of the sequence, visible or not.
+#[derive(Debug, Clone)]
+struct Item {
+    id: ItemId,
+    origin: Option<ItemId>,
+    ch: char,
+    deleted: bool,
+}
+
+/// Text replicated across sites.
+#[derive(Debug, Clone, Default)]
+pub struct CrdtText {
+    /// Every character ever inserted, in document order.
+    items: Vec<Item>,
+    /// The highest clock seen.
+    clock: u64,
+    /// Remote operations waiting for characters they refer to.
+    pending: Vec<CrdtOp>,
+}
+
+impl CrdtText {
+    /// Create empty text.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The visible text.
+    pub fn text(&self) -> String {
+        self.visible().map(|item| item.ch).collect()
+    }
+
+    /// Whether remote operations are waiting for characters that have not
+    /// arrived yet.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Insert `text` at byte offset `position` on behalf of `site`,
+    /// returning the operation to send to the other replicas.
+    pub fn insert(&mut self, position: usize, text: &str, site: SiteId) -> Result<CrdtOp> {
+        let origin = self.origin_at(position)?;
+        let id = ItemId {
+            clock: self.clock + 1,
+            site,
+        };
+        let op = CrdtOp::Insert {
+            id,
+            origin,
+            text: text.to_string(),
+        };
+        self.integrate_run(id, origin, text);
+        Ok(op)
+    }
+
+    /// Delete the bytes in `range`, returning the operation to send to the
+    /// other replicas.
+    pub fn delete(&mut self, range: Range<usize>) -> Result<CrdtOp> {
+        if range.start > range.end {
+            return Err(Error::InvalidSelection);
+        }
+        let start = self.index_at(range.start)?;
+        let end = self.index_at(range.end)?;
+        let mut ids = Vec::new();
+        for item in &mut self.items[start..end] {
+            if !item.deleted {
+                item.deleted = true;
+                ids.push(item.id);
+            }
+        }
+        Ok(CrdtOp::Delete { ids })
+    }
+
+    /// Apply a local [`Operation`] on behalf of `site`, returning the
+    /// operations to send to the other replicas. Operations that do not
+    /// change the text, such as formatting, produce none.
+    pub fn apply_operation(&mut self, op: &Operation, site: SiteId) -> Result<Vec<CrdtOp>> {
+        match op {
+            Operation::InsertText { position, text } => {
+                Ok(vec![self.insert(*position, text, site)?])
+            }
+            Operation::DeleteText { start, end, .. } => Ok(vec![self.delete(*start..*end)?]),
+            Operation::ReplaceText {
+                start,
+                end,
+                new_text,
+                ..
+            } => {
+                // Check both offsets before changing anything.
+                self.index_at(*end)?;
+                let delete = self.delete(*start..*end)?;
+                let insert = self.insert(*start, new_text, site)?;
+                Ok(vec![delete, insert])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Apply an operation from another replica, returning the equivalent
+    /// [`Operation`]s on the local text, ordered so that applying them in
+    /// order never shifts the offsets of the ones still to come.
+    ///
+    /// An operation that refers to characters not seen yet is held back
+    /// and applied once they arrive; operations already applied are
+    /// ignored.
+    pub fn apply_remote(&mut self, op: CrdtOp) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        if !self.is_ready(&op) {
+            self.pending.push(op);
+            return operations;
+        }
+        self.apply_ready(op, &mut operations);
+        self.apply_pending(&mut operations);
+        operations
+    }
+
+    /// Merge the whole state of another replica into this one. Afterwards
+    /// this replica holds everything either did.
+    pub fn merge(&mut self, other: &CrdtText) {
+        // An origin always has a lower clock than the characters inserted
+        // after it, so integrating in id order never misses one.
+        let mut items: Vec<&Item> = other.items.iter().collect();
+        items.sort_by_key(|item| item.id);
+        for item in items {
+            self.integrate(item.id, item.origin, item.ch);
+        }
+        for item in other.items.iter().filter(|item| item.deleted) {
+            if let Some(index) = self.index_of(item.id) {
+                self.items[index].deleted = true;
+            }
+        }
+        for op in &other.pending {
+            if !self.pending.contains(op) {
+                self.pending.push(op.clone());
+            }
+        }
+        self.apply_pending(&mut Vec::new());
+    }
+
+    /// Visible characters in order.
+    fn visible(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|item| !item.deleted)
+    }
+
+    /// The index into `items` of the first visible character at or after
+    /// byte offset `position`, or the end of the sequence.
+    fn index_at(&self, position: usize) -> Result<usize> {
+        let mut offset = 0;
+        for (index, item) in self.items.iter().enumerate() {
+            if item.deleted {
+                continue;
+            }
+            if offset == position {
+                return Ok(index);
+            }
+            offset += item.ch.len_utf8();
+            if offset > position {
+                return Err(Error::InvalidPosition(position));
+            }
+        }
+        if offset == position {
+            Ok(self.items.len())
+        } else {
+            Err(Error::InvalidPosition(position))
+        }
+    }
+
+    /// The visible character ending at byte offset `position`, which text
+    /// inserted there follows.
+    fn origin_at(&self, position: usize) -> Result<Option<ItemId>> {
+        let index = self.index_at(position)?;
+        Ok(self.items[..index]
+            .iter()
+            .rev()
+            .find(|item| !item.deleted)
+            .map(|item| item.id))
+    }
+
+    /// The byte offset in the visible text where the item at `index` is, or
+    /// would be if it were visible.
+    fn offset_of(&self, index: usize) -> usize {
+        self.items[..index]
+            .iter()
+            .filter(|item| !item.deleted)
+            .map(|item| item.ch.len_utf8())
+            .sum()
+    }
+
+    fn index_of(&self, id: ItemId) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    /// Whether every character `op` refers to has arrived.
+    fn is_ready(&self, op: &CrdtOp) -> bool {
+        match op {
+            CrdtOp::Insert { origin, .. } => origin.is_none_or(|id| self.index_of(id).is_some()),
+            CrdtOp::Delete { ids } => ids.iter().all(|id| self.index_of(*id).is_some()),
+        }
+    }
+
+    fn apply_ready(&mut self, op: CrdtOp, operations: &mut Vec<Operation>) {
+        match op {
+            CrdtOp::Insert { id, origin, text } => {
+                if text.is_empty() || self.index_of(id).is_some() {
+                    return;
+                }
+                self.integrate_run(id, origin, &text);
+                let index = self.index_of(id).unwrap_or_default();
+                operations.push(Operation::InsertText {
+                    position: self.offset_of(index),
+                    text,
+                });
+            }
+            CrdtOp::Delete { ids } => {
+                let mut indices: Vec<usize> = ids
+                    .iter()
+                    .filter_map(|id| self.index_of(*id))
+                    .filter(|index| !self.items[*index].deleted)
+                    .collect();
+                indices.sort_unstable();
+                indices.dedup();
+
+                // Group the characters into runs that are adjacent in the
+                // visible text, and delete the last run first.
+                let mut runs: Vec<(usize, String)> = Vec::new();
+                let mut run_end = None;
+                for index in indices {
+                    let start = self.offset_of(index);
+                    let ch = self.items[index].ch;
+                    match runs.last_mut() {
+                        Some((_, deleted)) if run_end == Some(start) => deleted.push(ch),
+                        _ => runs.push((start, ch.to_string())),
+                    }
+                    run_end = Some(start + ch.len_utf8());
+                }
+                for id in ids {
+                    if let Some(index) = self.index_of(id) {
+                        self.items[index].deleted = true;
+                    }
+                }
+                operations.extend(runs.into_iter().rev().map(|(start, deleted)| {
+                    Operation::DeleteText {
+                        start,
+                        end: start + deleted.len(),
+                        deleted,
+                    }
+                }));
+            }
+        }
+    }
+
+    /// Apply held-back operations until none of those left can be.
+    fn apply_pending(&mut self, operations: &mut Vec<Operation>) {
+        while let Some(index) = self.pending.iter().position(|op| self.is_ready(op)) {
+            let op = self.pending.remove(index);
+            self.apply_ready(op, operations);
+        }
+    }
+
+    /// Integrate the characters of `text`, the first after `origin` and
+    /// each of the rest after the one before it.
+    fn integrate_run(&mut self, id: ItemId, origin: Option<ItemId>, text: &str) {
+        let mut origin = origin;
+        for (clock, ch) in (id.clock..).zip(text.chars()) {
+            let id = ItemId {
+                clock,
+                site: id.site,
+            };
+            self.integrate(id, origin, ch);
+            origin = Some(id);
+        }
+    }
+
+    /// Place one character after its origin, skipping over characters
+    /// inserted after the same origin with higher ids, along with
+    /// everything inserted after those.
+    fn integrate(&mut self, id: ItemId, origin: Option<ItemId>, ch: char) {
+        if self.index_of(id).is_some() {
+            return;
+        }
+        let mut index = match origin {
+            Some(origin) => match self.index_of(origin) {
+                Some(index) => index + 1,
+                None => return,
+            },
+            None => 0,
+        };
+        // Characters inserted after a higher id all have higher ids
+        // themselves, so the first lower id ends the skipped region.
+        while index < self.items.len() && self.items[index].id > id {
+            index += 1;
+        }
+        self.items.insert(
+            index,
+            Item {
+                id,
+                origin,
+                ch,
+                deleted: false,
+            },
+        );
+        self.clock = self.clock.max(id.clock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `op` to a plain string, as the editor would to its document.
+    fn apply(text: &mut String, op: &Operation) {
+        match op {
+            Operation::InsertText { position, text: t } => text.insert_str(*position, t),
+            Operation::DeleteText { start, end, .. } => text.replace_range(*start..*end, ""),
+            _ => unreachable!(),
+        }
+    }
+
+    fn replica(text: &str) -> (CrdtText, CrdtOp) {
+        let mut crdt = CrdtText::new();
+        let op = crdt.insert(0, text, 0).unwrap();
+        (crdt, op)
+    }
+
+    #[test]
+    fn test_concurrent_inserts_converge() {
+        let (mut a, base) = replica("ac");
+        let mut b = CrdtText::new();
+        b.apply_remote(base);
+
+        let from_a = a.insert(1, "x", 1).unwrap();
+        let from_b = b.insert(1, "y", 2).unwrap();
+        a.apply_remote(from_b);
+        b.apply_remote(from_a);
+
+        assert_eq!(a.text(), b.text());
+        assert_eq!(a.text(), "ayxc");
+    }
+
+    #[test]
+    fn test_insert_after_concurrently_deleted_character() {
+        let (mut a, base) = replica("abc");
+        let mut b = CrdtText::new();
+        b.apply_remote(base);
+
+        let delete = a.delete(1..2).unwrap();
+        let insert = b.insert(2, "X", 2).unwrap();
+        a.apply_remote(insert);
+        b.apply_remote(delete);
+
+        assert_eq!(a.text(), "aXc");
+        assert_eq!(b.text(), "aXc");
+    }
+
+    #[test]
+    fn test_out_of_order_delivery() {
+        let mut a = CrdtText::new();
+        let first = a.insert(0, "hello", 1).unwrap();
+        let second = a.insert(5, " world", 1).unwrap();
+        let third = a.delete(0..1).unwrap();
+
+        let mut b = CrdtText::new();
+        assert!(b.apply_remote(third.clone()).is_empty());
+        assert!(b.apply_remote(second).is_empty());
+        assert!(b.has_pending());
+        b.apply_remote(first);
+        assert!(!b.has_pending());
+        assert_eq!(b.text(), "ello world");
+
+        // Applying an operation twice changes nothing.
+        assert!(b.apply_remote(third).is_empty());
+        assert_eq!(b.text(), "ello world");
+    }
+
+    #[test]
+    fn test_merge_converges() {
+        let (mut a, base) = replica("shared");
+        let mut b = CrdtText::new();
+        b.apply_remote(base);
+
+        a.insert(0, "one ", 1).unwrap();
+        a.delete(4..5).unwrap();
+        b.insert(6, " two", 2).unwrap();
+        b.insert(0, "zero ", 2).unwrap();
+
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b.clone();
+        ba.merge(&a);
+        assert_eq!(ab.text(), ba.text());
+        assert_eq!(ab.text(), "zero one hared two");
+
+        ab.merge(&b);
+        assert_eq!(ab.text(), ba.text());
+    }
+
+    #[test]
+    fn test_remote_operations_follow_the_text() {
+        let (mut a, base) = replica("héllo wörld");
+        let mut b = CrdtText::new();
+        let mut mirror = String::new();
+        for op in b.apply_remote(base) {
+            apply(&mut mirror, &op);
+        }
+
+        let local = [
+            Operation::DeleteText {
+                start: 1,
+                end: 3,
+                deleted: "é".to_string(),
+            },
+            Operation::ReplaceText {
+                start: 5,
+                end: 11,
+                old_text: "wörld".to_string(),
+                new_text: "there".to_string(),
+            },
+            Operation::InsertText {
+                position: 0,
+                text: "¡".to_string(),
+            },
+        ];
+        for op in &local {
+            for crdt_op in a.apply_operation(op, 1).unwrap() {
+                for op in b.apply_remote(crdt_op) {
+                    apply(&mut mirror, &op);
+                }
+            }
+        }
+        assert_eq!(a.text(), "¡hllo there");
+        assert_eq!(mirror, a.text());
+        assert_eq!(b.text(), a.text());
+    }
+
+    #[test]
+    fn test_invalid_offsets() {
+        let (mut a, _) = replica("é");
+        assert!(matches!(
+            a.insert(1, "x", 1),
+            Err(Error::InvalidPosition(1))
+        ));
+        assert!(matches!(a.delete(0..3), Err(Error::InvalidPosition(3))));
+        let (start, end) = (2, 0);
+        assert!(matches!(a.delete(start..end), Err(Error::InvalidSelection)));
+        assert_eq!(a.text(), "é");
+    }
+}
@@ -1,9 +1,14 @@
 //! Document management system for Wolia Write.
 
-use std::fs;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
+use wolia_core::Document;
+
+use crate::autosave::{Autosave, RecoveryFile};
 use crate::editor::Editor;
 
 /// Result type for document operations.
@@ -32,6 +37,12 @@ pub enum DocumentError {
 
     #[error("Edit operation error")]
     EditError,
+
+    #[error("Could not save: {0}")]
+    SaveFailed(String),
+
+    #[error("Invalid recovery copy key: {0}")]
+    InvalidRecoveryKey(String),
 }
 
 /// Document metadata.
@@ -99,6 +110,8 @@ pub struct DocumentManager {
     metadata: DocumentMetadata,
     /// Recent files list.
     recent_files: Vec<PathBuf>,
+    /// Writer of recovery copies, if autosave is on.
+    autosave: Option<Autosave>,
 }
 
 impl DocumentManager {
@@ -111,6 +124,7 @@ impl DocumentManager {
             editor,
             metadata,
             recent_files: Vec::new(),
+            autosave: None,
         }
     }
 
@@ -132,11 +146,9 @@ impl DocumentManager {
         let metadata_fs = fs::metadata(path)?;
         let read_only = metadata_fs.permissions().readonly();
 
-        // Read file content
-        let _content = fs::read_to_string(path)?;
-
-        // Create editor with content (simplified - just track that it's open)
-        let editor = Editor::new();
+        let document =
+            format_wolia::read(&fs::read(path)?).map_err(|_| DocumentError::InvalidFormat)?;
+        let editor = Editor::with_document(document);
 
         let title = path
             .file_stem()
@@ -153,9 +165,26 @@ impl DocumentManager {
             editor,
             metadata: doc_metadata,
             recent_files: Vec::new(),
+            autosave: None,
         })
     }
 
+    /// Recovery copies in `dir` left by sessions that ended without saving,
+    /// newest first, to offer for [restoring](RecoveryFile::restore) on
+    /// startup.
+    pub fn recover(dir: impl AsRef<Path>) -> Result<Vec<RecoveryFile>> {
+        RecoveryFile::list(dir)
+    }
+
+    /// Open a recovered document as unsaved changes to `path`.
+    pub(crate) fn restore(document: Document, title: &str, path: Option<PathBuf>) -> Result<Self> {
+        let mut manager = Self::new(title.to_string());
+        manager.editor = Editor::with_document(document);
+        manager.metadata.path = path;
+        manager.metadata.dirty = true;
+        Ok(manager)
+    }
+
     /// Save document to file.
     pub fn save(&mut self) -> Result<()> {
         if let Some(path) = &self.metadata.path {
@@ -180,8 +209,10 @@ impl DocumentManager {
             fs::create_dir_all(parent)?;
         }
 
-        // Write placeholder content
-        fs::write(path, "document content")?;
+        let data =
+            format_wolia::write(&self.editor.document).map_err(|_| DocumentError::InvalidFormat)?;
+        write_atomic(path, |file| file.write_all(&data))?;
+        self.editor.mark_saved();
 
         // Update metadata
         self.metadata.path = Some(path.to_path_buf());
@@ -191,9 +222,60 @@ impl DocumentManager {
         // Add to recent files
         self.add_to_recent(path);
 
+        // The saved file supersedes the recovery copy, which is kept until
+        // the document is safely written.
+        let key = self.recovery_key();
+        if let Some(autosave) = &mut self.autosave {
+            autosave.remove(key);
+        }
+
         Ok(())
     }
 
+    /// Write recovery copies into `dir` every `interval` while the document
+    /// has unsaved changes.
+    pub fn enable_autosave(&mut self, dir: impl Into<PathBuf>, interval: Duration) {
+        self.autosave = Some(Autosave::new(dir, interval));
+    }
+
+    /// Stop autosaving, keeping any recovery copy.
+    pub fn disable_autosave(&mut self) {
+        self.autosave = None;
+    }
+
+    /// The autosave, if on.
+    pub fn autosave(&self) -> Option<&Autosave> {
+        self.autosave.as_ref()
+    }
+
+    /// Queue a recovery copy if the document has unsaved changes and the
+    /// autosave interval has passed. Call this regularly, as from the event
+    /// loop; the copy is written in the background.
+    ///
+    /// Returns whether a copy was queued.
+    pub fn autosave_if_due(&mut self) -> bool {
+        let due = self
+            .autosave
+            .as_ref()
+            .is_some_and(|autosave| autosave.is_due(Instant::now()));
+        if !due || !self.is_dirty() {
+            return false;
+        }
+        let key = self.recovery_key();
+        let document = self.editor.document.clone();
+        let title = self.metadata.title.clone();
+        let path = self.metadata.path.clone();
+        if let Some(autosave) = &mut self.autosave {
+            autosave.write(key, format_wolia::Package::new(document), title, path);
+        }
+        true
+    }
+
+    /// Key of the document's recovery copy.
+    fn recovery_key(&self) -> String {
+        self.editor.document.id.to_string()
+    }
+
     /// Get the editor.
     pub fn editor(&self) -> &Editor {
         &self.editor
@@ -226,9 +308,9 @@ impl DocumentManager {
 
     /// Update statistics from current content.
     pub fn update_statistics(&mut self) {
-        // Simplified - in real implementation would count words from document content
-        self.metadata.char_count = 100;
-        self.metadata.word_count = 20;
+        let stats = self.editor.document.statistics();
+        self.metadata.char_count = stats.characters();
+        self.metadata.word_count = stats.words();
         self.metadata.page_count = self.metadata.word_count.div_ceil(250);
     }
 
@@ -261,11 +343,77 @@ impl DocumentManager {
     }
 }
 
+/// Write a file safely: `write` fills a temporary file beside `path`,
+/// which is flushed to disk and renamed over `path`. Whatever happens to
+/// the process, `path` holds either its old or its new contents, and if
+/// anything fails the temporary file is removed and `path` left as it was.
+pub fn write_atomic(path: &Path, write: impl FnOnce(&mut File) -> io::Result<()>) -> Result<()> {
+    // Renaming would replace a read-only file as readily as any other.
+    if fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly()) {
+        return Err(DocumentError::PermissionDenied(path.display().to_string()));
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| DocumentError::FileNotFound(path.display().to_string()))?;
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    // A name of its own, so that concurrent saves do not share a file.
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let temp = dir.join(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(match e.kind() {
+            io::ErrorKind::PermissionDenied => {
+                DocumentError::PermissionDenied(path.display().to_string())
+            }
+            io::ErrorKind::CrossesDevices => DocumentError::SaveFailed(format!(
+                "{} cannot be replaced from its own folder, which is on another file system",
+                path.display()
+            )),
+            _ => e.into(),
+        });
+    }
+    sync_dir(dir);
+    Ok(())
+}
+
+/// Flush a directory's entries to disk, so that a rename in it survives a
+/// crash. Best effort: not every platform or file system supports it.
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::tempdir;
+    use wolia_core::{Node, NodeKind, Text};
 
     #[test]
     fn test_new_document() {
@@ -307,9 +455,17 @@ mod tests {
     fn test_document_statistics() {
         let mut doc = DocumentManager::new("Test".to_string());
         doc.update_statistics();
+        assert_eq!(doc.metadata().word_count, 0);
+        assert_eq!(doc.metadata().page_count, 0);
+
+        doc.editor_mut()
+            .document
+            .root
+            .add_child(Node::paragraph(Text::new("Twenty one words ".repeat(7))));
+        doc.update_statistics();
 
-        assert!(doc.metadata().word_count > 0);
-        assert!(doc.metadata().char_count > 0);
+        assert_eq!(doc.metadata().word_count, 21);
+        assert_eq!(doc.metadata().char_count, 17 * 7);
         assert_eq!(doc.metadata().page_count, 1);
     }
 
@@ -350,4 +506,139 @@ mod tests {
         doc.mark_clean();
         assert!(doc.close().is_ok());
     }
+
+    #[test]
+    fn test_autosave_and_recover() {
+        let recovery = tempdir().unwrap();
+        let mut doc = DocumentManager::new("Draft".to_string());
+        doc.enable_autosave(recovery.path(), Duration::ZERO);
+
+        // Clean documents are not copied.
+        assert!(!doc.autosave_if_due());
+        doc.editor_mut()
+            .document
+            .root
+            .add_child(Node::paragraph(Text::new("Unsaved work")));
+        doc.mark_dirty();
+        assert!(doc.autosave_if_due());
+        doc.autosave().unwrap().flush();
+
+        let found = DocumentManager::recover(recovery.path()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "Draft");
+        assert_eq!(found[0].original, None);
+        let restored = found[0].restore().unwrap();
+        assert!(restored.is_dirty());
+        assert_eq!(restored.editor().document.id, doc.editor().document.id);
+        let NodeKind::Paragraph(text) = &restored.editor().document.root.children[0].kind else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(text.content, "Unsaved work");
+    }
+
+    #[test]
+    fn test_save_clears_recovery_copy() {
+        let recovery = tempdir().unwrap();
+        let files = tempdir().unwrap();
+        let mut doc = DocumentManager::new("Notes".to_string());
+        doc.enable_autosave(recovery.path(), Duration::ZERO);
+        doc.mark_dirty();
+        doc.autosave_if_due();
+        doc.autosave().unwrap().flush();
+        assert_eq!(DocumentManager::recover(recovery.path()).unwrap().len(), 1);
+
+        doc.save_to_path(files.path().join("notes.wolia")).unwrap();
+        doc.autosave().unwrap().flush();
+        assert!(
+            DocumentManager::recover(recovery.path())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(doc.autosave().unwrap().take_error().is_none());
+
+        // A copy can also be thrown away unrestored.
+        doc.mark_dirty();
+        doc.autosave_if_due();
+        doc.autosave().unwrap().flush();
+        let found = DocumentManager::recover(recovery.path()).unwrap();
+        assert_eq!(found[0].original, Some(files.path().join("notes.wolia")));
+        found.into_iter().next().unwrap().discard().unwrap();
+        assert_eq!(fs::read_dir(recovery.path()).unwrap().count(), 0);
+    }
+
+    /// Names of the files in `dir`.
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_save_replaces_atomically() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.wolia");
+        fs::write(&path, "original").unwrap();
+
+        let mut doc = DocumentManager::new("Report".to_string());
+        doc.editor_mut()
+            .document
+            .root
+            .add_child(Node::paragraph(Text::new("Quarterly figures")));
+        doc.mark_dirty();
+        doc.save_to_path(&path).unwrap();
+        assert!(!doc.is_dirty());
+        // The file holds the document itself.
+        let saved = format_wolia::read(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&saved).unwrap(),
+            serde_json::to_value(&doc.editor().document).unwrap()
+        );
+        let reopened = DocumentManager::open(&path).unwrap();
+        assert_eq!(reopened.editor().text(), "Quarterly figures");
+        // No temporary file is left behind.
+        assert_eq!(file_names(dir.path()), ["report.wolia"]);
+    }
+
+    #[test]
+    fn test_failed_write_keeps_original() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("report.wolia");
+        fs::write(&path, "original").unwrap();
+
+        // The process gives up halfway through the new contents.
+        let result = write_atomic(&path, |file| {
+            file.write_all(b"half of the new")?;
+            Err(io::Error::other("disk full"))
+        });
+        assert!(matches!(result, Err(DocumentError::Io(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(file_names(dir.path()), ["report.wolia"]);
+    }
+
+    #[test]
+    fn test_read_only_destination() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("locked.wolia");
+        fs::write(&path, "original").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        let recovery = tempdir().unwrap();
+        let mut doc = DocumentManager::new("Locked".to_string());
+        doc.enable_autosave(recovery.path(), Duration::ZERO);
+        doc.mark_dirty();
+        doc.autosave_if_due();
+        let result = doc.save_to_path(&path);
+        assert!(matches!(result, Err(DocumentError::PermissionDenied(_))));
+        assert!(doc.is_dirty());
+        // Nothing was saved, so the recovery copy stays.
+        doc.autosave().unwrap().flush();
+        assert_eq!(DocumentManager::recover(recovery.path()).unwrap().len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(file_names(dir.path()), ["locked.wolia"]);
+    }
 }
@@ -1,11 +1,22 @@
 //! Document editor with cursor, selection, and undo/redo support.
 
-use wolia_core::{Document, Text};
+use std::ops::Range;
 
+use uuid::Uuid;
+use wolia_core::style::{ParagraphStyle, Revision, RevisionKind, TextStyle as CoreTextStyle};
+use wolia_core::{Document, Node, Text};
+
+use crate::clipboard::Fragment;
 use crate::cursor::{Cursor, Selection};
+use crate::format::{self, FormatChange};
 use crate::history::History;
-use crate::input::{InputHandler, Key, KeyModifiers, KeyboardEvent};
+use crate::ime::ImeState;
+use crate::input::{ImeEvent, InputHandler, Key, KeyModifiers, KeyboardEvent};
 use crate::operation::Operation;
+use crate::revision::{self, Suggestion};
+use crate::search::{self, FindOptions};
+use crate::table::{self, Cell, CellSelection};
+use crate::{boundary, buffer};
 
 /// A document editor that manages editing state and operations.
 #[derive(Debug)]
@@ -16,12 +27,20 @@ pub struct Editor {
     pub cursor: Cursor,
     /// Selection state.
     pub selection: Option<Selection>,
+    /// Additional cursors for multi-cursor editing, each a selection that
+    /// is empty when it is only a caret. Kept sorted and non-overlapping.
+    pub secondary: Vec<Selection>,
     /// Operation history for undo/redo.
     pub history: History,
     /// Input handler for keyboard/mouse events.
     pub input: InputHandler,
+    /// IME composition at the cursor.
+    pub ime: ImeState,
     /// Whether the document has unsaved changes.
     pub dirty: bool,
+    /// The author that typing, deleting and pasting are suggested by, while
+    /// changes are tracked. Edits are made directly when `None`.
+    pub track_changes: Option<String>,
 }
 
 impl Editor {
@@ -31,9 +50,12 @@ impl Editor {
             document: Document::new(),
             cursor: Cursor::new(),
             selection: None,
+            secondary: Vec::new(),
             history: History::new(),
             input: InputHandler::new(),
+            ime: ImeState::new(),
             dirty: false,
+            track_changes: None,
         }
     }
 
@@ -43,75 +65,412 @@ impl Editor {
             document,
             cursor: Cursor::new(),
             selection: None,
+            secondary: Vec::new(),
             history: History::new(),
             input: InputHandler::new(),
+            ime: ImeState::new(),
             dirty: false,
+            track_changes: None,
         }
     }
 
-    /// Insert text at the current cursor position.
+    /// Insert text at every cursor, replacing any selected text.
     pub fn insert_text(&mut self, text: &str) -> crate::Result<()> {
-        let position = self.cursor.position;
+        self.edit_at_cursors(|_, range| range, text)
+    }
 
-        let operation = Operation::InsertText {
-            position,
-            text: text.to_string(),
+    /// Delete the grapheme cluster before every cursor, or the selected
+    /// text.
+    pub fn delete_char(&mut self) -> crate::Result<()> {
+        self.edit_at_cursors(
+            |text, range| {
+                if range.is_empty() {
+                    boundary::prev_grapheme(text, range.start)..range.end
+                } else {
+                    range
+                }
+            },
+            "",
+        )
+    }
+
+    /// Delete the grapheme cluster after every cursor, or the selected
+    /// text.
+    pub fn delete_char_forward(&mut self) -> crate::Result<()> {
+        self.edit_at_cursors(
+            |text, range| {
+                if range.is_empty() {
+                    range.start..boundary::next_grapheme(text, range.end)
+                } else {
+                    range
+                }
+            },
+            "",
+        )
+    }
+
+    /// Replace the range `target` picks for each cursor with `text`.
+    ///
+    /// `target` is given the document text and the cursor's selection, or
+    /// an empty range at a caret. Ranges that overlap are merged, and the
+    /// edits are applied from the start of the document with each later
+    /// range shifted by the edits before it. Edits at several cursors are
+    /// undone in one step. Every cursor is left as a caret after its
+    /// replacement. A range that would join the text of a table cell with
+    /// the text outside it is left alone, and a selection of cells has
+    /// their text replaced instead. While changes are tracked, each
+    /// replacement is suggested instead, and a caret that deleted backwards
+    /// is left before the text it marked.
+    fn edit_at_cursors(
+        &mut self,
+        target: impl Fn(&str, Range<usize>) -> Range<usize>,
+        text: &str,
+    ) -> crate::Result<()> {
+        if let Some(selection) = self.cell_selection() {
+            self.history.begin_group();
+            let result = self.replace_cells(&selection, text);
+            self.history.end_group();
+            return result;
+        }
+        let full = self.text();
+        let primary = match self.selection.filter(|sel| !sel.is_empty()) {
+            Some(sel) => sel.start.min(sel.end)..sel.start.max(sel.end),
+            None => self.cursor.position..self.cursor.position,
         };
+        let mut ranges: Vec<(Range<usize>, bool, bool)> = std::iter::once((primary, true))
+            .chain(self.secondary.iter().map(|sel| (sel.start..sel.end, false)))
+            .map(|(range, is_primary)| {
+                let edited = target(&full, range.clone());
+                if table::crosses_cells(&self.document, &edited) {
+                    (range.start..range.start, is_primary, false)
+                } else {
+                    let backward = edited.start < range.start;
+                    (edited, is_primary, backward)
+                }
+            })
+            .collect();
+        ranges.sort_by_key(|(range, _, _)| (range.start, range.end));
+        let mut merged: Vec<(Range<usize>, bool, bool)> = Vec::with_capacity(ranges.len());
+        for (range, is_primary, backward) in ranges {
+            match merged.last_mut() {
+                Some((last, last_primary, last_backward))
+                    if range.start < last.end || range.start == last.start =>
+                {
+                    last.end = last.end.max(range.end);
+                    *last_primary |= is_primary;
+                    *last_backward &= backward;
+                }
+                _ => merged.push((range, is_primary, backward)),
+            }
+        }
 
-        self.apply_operation(operation)?;
-        self.cursor.position += text.len();
-        self.dirty = true;
+        let grouped = merged.len() > 1;
+        if grouped {
+            self.history.begin_group();
+        }
+        let mut delta = 0isize;
+        let mut carets = Vec::with_capacity(merged.len());
+        let mut result = Ok(());
+        for (range, is_primary, backward) in &merged {
+            let start = range.start.saturating_add_signed(delta);
+            let end = range.end.saturating_add_signed(delta);
+            if let Some(author) = self.track_changes.clone() {
+                match self.suggest(start..end, text, &author) {
+                    Ok((changed_end, growth)) => {
+                        let caret = if text.is_empty() && *backward {
+                            start
+                        } else {
+                            changed_end
+                        };
+                        carets.push((caret, *is_primary));
+                        delta += growth;
+                        continue;
+                    }
+                    Err(error) => {
+                        result = Err(error);
+                        break;
+                    }
+                }
+            }
+            let operation = match (range.is_empty(), text.is_empty()) {
+                (true, true) => None,
+                (true, false) => Some(Operation::InsertText {
+                    position: start,
+                    text: text.to_string(),
+                }),
+                (false, true) => Some(Operation::DeleteText {
+                    start,
+                    end,
+                    deleted: String::new(),
+                }),
+                (false, false) => Some(Operation::ReplaceText {
+                    start,
+                    end,
+                    old_text: String::new(),
+                    new_text: text.to_string(),
+                }),
+            };
+            if let Some(operation) = operation {
+                result = self
+                    .apply_operation(operation)
+                    .and_then(|()| self.settle_inserted(start..start + text.len()));
+                if result.is_err() {
+                    break;
+                }
+            }
+            carets.push((start + text.len(), *is_primary));
+            delta += text.len() as isize - range.len() as isize;
+        }
+        if grouped {
+            self.history.end_group();
+        }
+        result?;
 
+        self.selection = None;
+        self.secondary.clear();
+        for (caret, is_primary) in carets {
+            if is_primary {
+                self.cursor.position = caret;
+            } else {
+                self.secondary.push(Selection::new(caret, caret));
+            }
+        }
         Ok(())
     }
 
-    /// Delete the character at the current cursor position.
-    pub fn delete_char(&mut self) -> crate::Result<()> {
-        let position = self.cursor.position;
-
-        if position > 0 {
-            let operation = Operation::DeleteText {
-                start: position - 1,
-                end: position,
-                deleted: String::new(), // In a real implementation, get from document
+    /// Clear the text of the selected cells and put `text` in the first of
+    /// them, leaving the cursor after it. While changes are tracked, the
+    /// text is suggested for deletion instead, and `text` goes after it.
+    fn replace_cells(&mut self, selection: &CellSelection, text: &str) -> crate::Result<()> {
+        let cells: Vec<Cell> = table::cells(&self.document)
+            .into_iter()
+            .filter(|cell| selection.contains(cell))
+            .collect();
+        let mut at = cells.first().map(|cell| cell.range.start);
+        for (index, cell) in cells.iter().enumerate().rev() {
+            if cell.range.is_empty() {
+                continue;
+            }
+            let end = match self.track_changes.clone() {
+                Some(author) => self.suggest(cell.range.clone(), "", &author)?.0,
+                None => {
+                    self.apply_operation(Operation::DeleteText {
+                        start: cell.range.start,
+                        end: cell.range.end,
+                        deleted: String::new(),
+                    })?;
+                    cell.range.start
+                }
             };
+            if index == 0 {
+                at = Some(end);
+            }
+        }
+        self.selection = None;
+        self.secondary.clear();
+        if let Some(at) = at {
+            self.cursor.position = at;
+            if !text.is_empty() {
+                self.insert_at(at, text)?;
+            }
+        }
+        Ok(())
+    }
 
-            self.apply_operation(operation)?;
-            self.cursor.position = self.cursor.position.saturating_sub(1);
-            self.dirty = true;
+    /// Insert `text` at `position`, leaving the cursor after it.
+    fn insert_at(&mut self, position: usize, text: &str) -> crate::Result<()> {
+        self.apply_operation(Operation::InsertText {
+            position,
+            text: text.to_string(),
+        })?;
+        self.settle_inserted(position..position + text.len())
+    }
+
+    /// Mark the text just inserted at `range` as a suggested insertion
+    /// while changes are tracked, joining a suggestion by the same author
+    /// next to it. Otherwise clear any suggestion it was copied with or
+    /// took from the text it was typed next to, so that text inserted
+    /// directly is never suggested.
+    fn settle_inserted(&mut self, range: Range<usize>) -> crate::Result<()> {
+        let revision = self.track_changes.clone().map(|author| {
+            revision::adjoining(
+                &self.document,
+                range.clone(),
+                RevisionKind::Insertion,
+                &author,
+            )
+            .unwrap_or_else(|| Revision::new(RevisionKind::Insertion, author, revision::now()))
+        });
+        self.set_revision(range, revision)
+    }
+
+    /// Suggest replacing `range` with `text` on behalf of `author`, as one
+    /// undo step, or as part of the typing before it if `range` is empty.
+    ///
+    /// The text in `range` is marked for deletion, joining a deletion by
+    /// the same author next to it, except that text suggested for
+    /// insertion is removed, as is text that cannot be marked. `text` is
+    /// inserted after it as a suggested insertion. Returns the end of the
+    /// changed text and how much longer the document became.
+    fn suggest(
+        &mut self,
+        range: Range<usize>,
+        text: &str,
+        author: &str,
+    ) -> crate::Result<(usize, isize)> {
+        if range.is_empty() {
+            return self.suggest_ungrouped(range, text, author);
+        }
+        self.history.begin_group();
+        let result = self.suggest_ungrouped(range, text, author);
+        self.history.end_group();
+        result
+    }
+
+    fn suggest_ungrouped(
+        &mut self,
+        range: Range<usize>,
+        text: &str,
+        author: &str,
+    ) -> crate::Result<(usize, isize)> {
+        let mut end = range.end;
+        if !range.is_empty() {
+            let deletion = revision::adjoining(
+                &self.document,
+                range.clone(),
+                RevisionKind::Deletion,
+                author,
+            )
+            .unwrap_or_else(|| Revision::new(RevisionKind::Deletion, author, revision::now()));
+            for run in revision::runs(&self.document, range.clone())?
+                .into_iter()
+                .rev()
+            {
+                match (&run.revision, run.markable) {
+                    (Some(revision), _) if revision.kind == RevisionKind::Deletion => {}
+                    (None, true) => self.set_revision(run.range, Some(deletion.clone()))?,
+                    _ => {
+                        end -= run.range.len();
+                        self.remove_text(run.range)?;
+                    }
+                }
+            }
+        }
+        let removed = range.end - end;
+        if !text.is_empty() {
+            self.insert_at(end, text)?;
+        }
+        Ok((end + text.len(), text.len() as isize - removed as isize))
+    }
+
+    /// Mark the text in `range` as part of `revision`, or clear its marks
+    /// if `None`, unless it already is.
+    fn set_revision(
+        &mut self,
+        range: Range<usize>,
+        revision: Option<Revision>,
+    ) -> crate::Result<()> {
+        let runs = revision::runs(&self.document, range.clone())?;
+        if runs
+            .iter()
+            .all(|run| !run.markable || run.revision == revision)
+        {
+            return Ok(());
         }
+        let blocks = buffer::spans(&self.document, range.clone())?;
+        let operation = Self::restyle(blocks, range, &FormatChange::SetRevision(revision));
+        self.apply_operation(operation)
+    }
 
+    /// Remove the text in `range`, which is inside one block, by replacing
+    /// the block, so that undoing the removal puts the text back with its
+    /// formatting and suggestions.
+    fn remove_text(&mut self, range: Range<usize>) -> crate::Result<()> {
+        let block = buffer::cut_block(&self.document, range.clone())?;
+        self.apply_operation(Operation::ReplaceBlock {
+            old: Box::new(block.clone()),
+            new: Box::new(block),
+        })?;
+        self.cursor.position = range.start;
         Ok(())
     }
 
-    /// Delete the character after the current cursor position.
-    pub fn delete_char_forward(&mut self) -> crate::Result<()> {
-        let position = self.cursor.position;
+    /// Add a caret at `position`, alongside the existing cursors.
+    pub fn add_cursor_at(&mut self, position: usize) {
+        let position = boundary::snap(&self.text(), position);
+        self.secondary.push(Selection::new(position, position));
+        self.normalize_cursors();
+    }
 
-        let operation = Operation::DeleteText {
-            start: position,
-            end: position + 1,
-            deleted: String::new(), // In a real implementation, get from document
-        };
+    /// Add a cursor selecting every match of `query`, as found with the
+    /// default [`FindOptions`]. Returns the number of cursors afterwards,
+    /// counting the primary one.
+    pub fn add_cursors_for_matches(&mut self, query: &str) -> usize {
+        let matches =
+            search::find_all(&self.text(), query, FindOptions::default()).unwrap_or_default();
+        self.secondary.extend(
+            matches
+                .into_iter()
+                .map(|found| Selection::new(found.start, found.end)),
+        );
+        self.normalize_cursors();
+        self.secondary.len() + 1
+    }
 
-        self.apply_operation(operation)?;
-        self.dirty = true;
+    /// Remove every cursor but the primary one.
+    pub fn clear_secondary_cursors(&mut self) {
+        self.secondary.clear();
+    }
 
-        Ok(())
+    /// Sort the secondary cursors and merge those that overlap each other.
+    /// Secondary cursors overlapping the primary one are dropped.
+    fn normalize_cursors(&mut self) {
+        let primary = match self.selection {
+            Some(sel) => Selection::new(sel.start, sel.end),
+            None => Selection::new(self.cursor.position, self.cursor.position),
+        };
+        self.secondary.sort_by_key(|sel| (sel.start, sel.end));
+        let mut merged: Vec<Selection> = Vec::with_capacity(self.secondary.len());
+        for sel in self.secondary.drain(..) {
+            if overlaps(&sel, &primary) {
+                continue;
+            }
+            match merged.last_mut() {
+                Some(last) if overlaps(last, &sel) => last.end = last.end.max(sel.end),
+                _ => merged.push(sel),
+            }
+        }
+        self.secondary = merged;
+    }
+
+    /// Move the primary cursor and every caret with `step`.
+    fn move_cursors(&mut self, step: fn(&str, usize) -> usize) {
+        self.history.break_group();
+        let text = self.text();
+        self.cursor.position = step(&text, self.cursor.position);
+        for sel in &mut self.secondary {
+            let position = step(&text, sel.end);
+            *sel = Selection::new(position, position);
+        }
+        self.normalize_cursors();
     }
 
     /// Move cursor to the beginning of the line.
     pub fn cursor_line_start(&mut self) {
-        // This would need document context to find actual line start
-        // For now, just record the movement
-        self.cursor.position = 0;
+        self.history.break_group();
+        let text = self.text();
+        let position = boundary::snap(&text, self.cursor.position);
+        self.cursor.position = text[..position].rfind('\n').map_or(0, |index| index + 1);
     }
 
     /// Move cursor to the end of the line.
     pub fn cursor_line_end(&mut self) {
-        // This would need document context to find actual line end
-        // For now, record the movement
-        self.cursor.position = 1000;
+        self.history.break_group();
+        let text = self.text();
+        let position = boundary::snap(&text, self.cursor.position);
+        self.cursor.position = text[position..]
+            .find('\n')
+            .map_or(text.len(), |index| position + index);
     }
 
     /// Move cursor up by one line.
@@ -124,17 +483,66 @@ impl Editor {
         // This requires layout information
     }
 
-    /// Move cursor left by one character.
+    /// Move cursor left by one grapheme cluster.
     pub fn cursor_left(&mut self) {
-        let pos = self.cursor.position;
-        if pos > 0 {
-            self.cursor.position = pos - 1;
-        }
+        self.move_cursors(boundary::prev_grapheme);
     }
 
-    /// Move cursor right by one character.
+    /// Move cursor right by one grapheme cluster.
     pub fn cursor_right(&mut self) {
-        self.cursor.position += 1;
+        self.move_cursors(boundary::next_grapheme);
+    }
+
+    /// Move cursor left to the start of the previous word.
+    pub fn cursor_left_word(&mut self) {
+        self.move_cursors(boundary::prev_word);
+    }
+
+    /// Move cursor right to the end of the next word.
+    pub fn cursor_right_word(&mut self) {
+        self.move_cursors(boundary::next_word);
+    }
+
+    /// The plain text of the document that cursor positions index into.
+    ///
+    /// Text blocks are taken in document order and separated by line feeds.
+    pub fn text(&self) -> String {
+        buffer::text(&self.document)
+    }
+
+    /// Place the cursor at `position`, dropping any selection and other
+    /// cursors.
+    pub fn set_cursor(&mut self, position: usize) {
+        self.history.break_group();
+        self.cursor.position = boundary::snap(&self.text(), position);
+        self.selection = None;
+        self.secondary.clear();
+    }
+
+    /// Select from `anchor` to `position`, leaving the cursor at
+    /// `position`.
+    pub fn select_range(&mut self, anchor: usize, position: usize) {
+        self.history.break_group();
+        let text = self.text();
+        let (anchor, position) = (
+            boundary::snap(&text, anchor),
+            boundary::snap(&text, position),
+        );
+        self.cursor.position = position;
+        self.selection = Some(Selection::new(anchor, position));
+        self.secondary.clear();
+    }
+
+    /// Select the word at `position`.
+    pub fn select_word_at(&mut self, position: usize) {
+        let word = boundary::word_at(&self.text(), position);
+        self.select_range(word.start, word.end);
+    }
+
+    /// Select the paragraph at `position`.
+    pub fn select_paragraph_at(&mut self, position: usize) {
+        let line = boundary::line_at(&self.text(), position);
+        self.select_range(line.start, line.end);
     }
 
     /// Start a selection from the current cursor position.
@@ -160,20 +568,623 @@ impl Editor {
 
     /// Get the selected text.
     pub fn selected_text(&self) -> Option<String> {
-        self.selection.as_ref().map(|sel| {
-            let start = sel.start.min(sel.end);
-            let end = sel.start.max(sel.end);
-            // In a real implementation, extract from document
-            format!("[selected {} to {}]", start, end)
+        let sel = self.selection.as_ref()?;
+        let start = sel.start.min(sel.end);
+        let end = sel.start.max(sel.end);
+        self.text().get(start..end).map(str::to_string)
+    }
+
+    /// Apply a formatting change to the text in `selection` as one undo
+    /// step.
+    ///
+    /// Toggles such as [`FormatChange::ToggleBold`] apply the style to the
+    /// whole selection unless all of it already has it, in which case they
+    /// remove it.
+    pub fn apply_format(
+        &mut self,
+        selection: Selection,
+        change: FormatChange,
+    ) -> crate::Result<()> {
+        let range = selection.start.min(selection.end)..selection.start.max(selection.end);
+        let blocks = buffer::spans(&self.document, range.clone())?;
+        let all = |test: fn(&CoreTextStyle) -> bool| all_styled(&blocks, test);
+        let change = match change {
+            FormatChange::ToggleBold => FormatChange::SetBold(!all(format::is_bold)),
+            FormatChange::ToggleItalic => FormatChange::SetItalic(!all(format::is_italic)),
+            FormatChange::ToggleUnderline => {
+                FormatChange::SetUnderline(!all(format::is_underlined))
+            }
+            FormatChange::ToggleStrikethrough => {
+                FormatChange::SetStrikethrough(!all(format::is_struck))
+            }
+            change => change,
+        };
+        let operation = Self::restyle(blocks, range, &change);
+        self.history.break_group();
+        let result = self.apply_operation(operation);
+        self.history.break_group();
+        result
+    }
+
+    /// The operation making `change` to the formatting `blocks` have over
+    /// `range`.
+    fn restyle(
+        blocks: Vec<buffer::BlockSpans>,
+        range: Range<usize>,
+        change: &FormatChange,
+    ) -> Operation {
+        let (old_spans, new_spans) = blocks
+            .into_iter()
+            .map(|block| {
+                let restyled = format::restyle(&block.spans, block.len, block.range, change);
+                (block.spans, restyled)
+            })
+            .unzip();
+        Operation::SetSpans {
+            start: range.start,
+            end: range.end,
+            old_spans,
+            new_spans,
+        }
+    }
+
+    /// The link at `position`, with the range of text it covers. The text
+    /// after `position` is looked at first, then the text before it.
+    pub fn link_at(&self, position: usize) -> Option<(Range<usize>, String)> {
+        let block = buffer::spans(&self.document, position..position)
+            .ok()?
+            .into_iter()
+            .next()?;
+        let local = block.range.start;
+        let block_start = position - local;
+        let runs = format::runs(&block.spans, block.len);
+        let linked = |index: usize| runs.get(index).and_then(|(_, style)| style.link.clone());
+        let index = runs
+            .iter()
+            .position(|(run, _)| run.contains(&local))
+            .filter(|&index| linked(index).is_some())
+            .or_else(|| {
+                runs.iter()
+                    .position(|(run, _)| !run.is_empty() && run.end == local)
+                    .filter(|&index| linked(index).is_some())
+            })?;
+        let url = linked(index)?;
+        let same = |style: &CoreTextStyle| style.link.as_ref() == Some(&url);
+        let first = runs[..index]
+            .iter()
+            .rposition(|(_, style)| !same(style))
+            .map_or(0, |before| before + 1);
+        let last = runs[index..]
+            .iter()
+            .position(|(_, style)| !same(style))
+            .map_or(runs.len(), |after| index + after);
+        let range = block_start + runs[first].0.start..block_start + runs[last - 1].0.end;
+        Some((range, url))
+    }
+
+    /// The URL the selection links to, or the link at the cursor when
+    /// nothing is selected.
+    pub fn link(&self) -> Option<String> {
+        match self.selection.filter(|sel| !sel.is_empty()) {
+            Some(sel) => {
+                let range = sel.start.min(sel.end)..sel.start.max(sel.end);
+                self.link_at(range.start)
+                    .filter(|(link, _)| link.start <= range.start && range.end <= link.end)
+                    .map(|(_, url)| url)
+            }
+            None => self.link_at(self.cursor.position).map(|(_, url)| url),
+        }
+    }
+
+    /// Link the selected text to `url`, or remove its link if `url` is
+    /// `None`, as one undo step. With nothing selected, the whole link at
+    /// the cursor is changed; if there is none, `url` is inserted as its
+    /// own link text.
+    pub fn set_link(&mut self, url: Option<String>) -> crate::Result<()> {
+        let change = FormatChange::SetLink(url.clone());
+        let target = match self.selection.filter(|sel| !sel.is_empty()) {
+            Some(sel) => Some(sel),
+            None => self
+                .link_at(self.cursor.position)
+                .map(|(range, _)| Selection::new(range.start, range.end)),
+        };
+        if let Some(target) = target {
+            return self.apply_format(target, change);
+        }
+        let Some(url) = url else {
+            return Ok(());
+        };
+        let position = self.cursor.position;
+        let range = position..position + url.len();
+        self.history.break_group();
+        self.history.begin_group();
+        let result = self
+            .apply_operation(Operation::InsertText {
+                position,
+                text: url,
+            })
+            .and_then(|()| self.settle_inserted(range.clone()))
+            .and_then(|()| {
+                let blocks = buffer::spans(&self.document, range.clone())?;
+                self.apply_operation(Self::restyle(blocks, range, &change))
+            });
+        self.history.end_group();
+        self.history.break_group();
+        result
+    }
+
+    /// The suggested changes in the document, in the order they start.
+    pub fn suggestions(&self) -> Vec<Suggestion> {
+        revision::suggestions(&self.document)
+    }
+
+    /// The suggested change at `position`, looking at the text after it
+    /// first, then the text before it.
+    pub fn suggestion_at(&self, position: usize) -> Option<Suggestion> {
+        revision::suggestion_at(&self.document, position)
+    }
+
+    /// Accept the suggested change `id`, as one undo step: text suggested
+    /// for insertion becomes ordinary text, and text suggested for deletion
+    /// is removed.
+    pub fn accept(&mut self, id: Uuid) -> crate::Result<()> {
+        self.resolve(id, true)
+    }
+
+    /// Reject the suggested change `id`, as one undo step: text suggested
+    /// for insertion is removed, and text suggested for deletion becomes
+    /// ordinary text again.
+    pub fn reject(&mut self, id: Uuid) -> crate::Result<()> {
+        self.resolve(id, false)
+    }
+
+    /// Keep or remove the text of the suggested change `id`, clearing its
+    /// marks. The cursor keeps its place in the text around it.
+    fn resolve(&mut self, id: Uuid, accept: bool) -> crate::Result<()> {
+        let suggestion = self
+            .suggestions()
+            .into_iter()
+            .find(|suggestion| suggestion.revision.id == id)
+            .ok_or(crate::Error::SuggestionNotFound(id))?;
+        let remove = (suggestion.revision.kind == RevisionKind::Insertion) != accept;
+        let mut position = self.cursor.position;
+        self.history.break_group();
+        self.history.begin_group();
+        let mut result = Ok(());
+        for range in suggestion.ranges.iter().rev() {
+            result = if remove {
+                if position >= range.end {
+                    position -= range.len();
+                } else if position > range.start {
+                    position = range.start;
+                }
+                self.remove_text(range.clone())
+            } else {
+                self.set_revision(range.clone(), None)
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        self.history.end_group();
+        self.history.break_group();
+        self.cursor.position = position;
+        if remove {
+            self.selection = None;
+            self.secondary.clear();
+        }
+        result
+    }
+
+    /// The paragraph formatting set directly on the block holding the
+    /// cursor.
+    pub fn paragraph_style(&self) -> ParagraphStyle {
+        let position = self.cursor.position;
+        buffer::paragraph_styles(&self.document, position..position)
+            .ok()
+            .and_then(|styles| styles.into_iter().next())
+            .unwrap_or_default()
+    }
+
+    /// Change the paragraph formatting of every block `selection` touches
+    /// with `edit`, as one undo step.
+    pub fn format_paragraphs(
+        &mut self,
+        selection: Selection,
+        edit: impl Fn(&mut ParagraphStyle),
+    ) -> crate::Result<()> {
+        let range = selection.start.min(selection.end)..selection.start.max(selection.end);
+        let old_styles = buffer::paragraph_styles(&self.document, range.clone())?;
+        let new_styles: Vec<ParagraphStyle> = old_styles
+            .iter()
+            .cloned()
+            .map(|mut style| {
+                edit(&mut style);
+                style
+            })
+            .collect();
+        if new_styles == old_styles {
+            return Ok(());
+        }
+
+        self.history.break_group();
+        let result = self.apply_operation(Operation::SetParagraphStyles {
+            start: range.start,
+            end: range.end,
+            old_styles,
+            new_styles,
+        });
+        self.history.break_group();
+        result
+    }
+
+    /// Whether all of the selected text has a style `test` holds for. With
+    /// no selection, the character before the cursor is tested, and an
+    /// empty document has no style.
+    pub fn selection_has(&self, test: fn(&CoreTextStyle) -> bool) -> bool {
+        let range = match self.selection.filter(|sel| !sel.is_empty()) {
+            Some(sel) => sel.start.min(sel.end)..sel.start.max(sel.end),
+            None => {
+                let position = self.cursor.position;
+                let Some(before) = self.text().get(..position).and_then(|t| t.chars().last())
+                else {
+                    return false;
+                };
+                position - before.len_utf8()..position
+            }
+        };
+        buffer::spans(&self.document, range).is_ok_and(|blocks| all_styled(&blocks, test))
+    }
+
+    /// Copy the primary selection with its formatting.
+    pub fn copy_selection(&self) -> Option<Fragment> {
+        let sel = self.selection.filter(|sel| !sel.is_empty())?;
+        let range = sel.start.min(sel.end)..sel.start.max(sel.end);
+        let blocks = buffer::slice(&self.document, range).ok()?;
+        Some(Fragment { blocks })
+    }
+
+    /// Paste a fragment at the cursor, replacing the primary selection, as
+    /// one undo step.
+    pub fn paste(&mut self, fragment: &Fragment) -> crate::Result<()> {
+        self.history.break_group();
+        self.history.begin_group();
+        let result = self.paste_blocks(fragment);
+        self.history.end_group();
+        self.history.break_group();
+        result
+    }
+
+    fn paste_blocks(&mut self, fragment: &Fragment) -> crate::Result<()> {
+        self.delete_primary_selection()?;
+        if fragment.blocks.is_empty() {
+            return Ok(());
+        }
+        let position = self.cursor.position;
+        self.apply_operation(Operation::InsertBlocks {
+            position,
+            blocks: fragment.blocks.clone(),
+        })?;
+        self.settle_inserted(position..self.cursor.position)
+    }
+
+    /// Insert `block`, which holds no text, such as an image, at the
+    /// cursor, replacing the primary selection, as one undo step. The text
+    /// block the cursor is inside is split in two around it.
+    pub fn insert_block(&mut self, block: Node) -> crate::Result<()> {
+        self.history.break_group();
+        self.history.begin_group();
+        let result = self.insert_block_at_cursor(block);
+        self.history.end_group();
+        self.history.break_group();
+        result
+    }
+
+    fn insert_block_at_cursor(&mut self, block: Node) -> crate::Result<()> {
+        self.delete_primary_selection()?;
+        let text = self.text();
+        let position = self.cursor.position;
+        let at_start = position > 0 && text[..position].ends_with('\n');
+        let at_end = position == text.len() || text[position..].starts_with('\n');
+        let position = if at_start {
+            // After the block before, so that no empty block is left.
+            position - 1
+        } else {
+            if !at_end {
+                self.apply_operation(Operation::InsertText {
+                    position,
+                    text: "\n".to_string(),
+                })?;
+            }
+            position
+        };
+        self.apply_operation(Operation::InsertBlock { position, block })
+    }
+
+    /// Insert a table of `rows` rows and `cols` columns of empty cells at
+    /// the cursor, as one undo step, and put the cursor in its first cell.
+    /// A table inserted at the end of the document is followed by an empty
+    /// paragraph to go on typing in.
+    pub fn insert_table(&mut self, rows: usize, cols: usize) -> crate::Result<()> {
+        let table = table::new_table(rows.max(1), cols.max(1));
+        let id = table.id;
+        self.history.break_group();
+        self.history.begin_group();
+        let result = self.delete_primary_selection().and_then(|()| {
+            let position = self.cursor.position;
+            if position == self.text().len() {
+                self.apply_operation(Operation::InsertText {
+                    position,
+                    text: "\n".to_string(),
+                })?;
+            }
+            self.insert_block_at_cursor(table)
+        });
+        self.history.end_group();
+        self.history.break_group();
+        result?;
+        if let Some(first) = table::cells(&self.document)
+            .into_iter()
+            .find(|cell| cell.table == id)
+        {
+            self.cursor.position = first.range.start;
+        }
+        Ok(())
+    }
+
+    /// The cells the primary selection covers, if it runs from one cell of
+    /// a table into another.
+    pub fn cell_selection(&self) -> Option<CellSelection> {
+        let sel = self.selection.filter(|sel| !sel.is_empty())?;
+        table::cell_selection(&self.document, sel.start, sel.end)
+    }
+
+    /// Select the text of the table cell after the cursor's. In the last
+    /// cell, a row is added and the cursor put in its first cell.
+    pub fn next_cell(&mut self) -> crate::Result<()> {
+        let Some(cell) = table::cell_at(&self.document, self.cursor.position) else {
+            return Ok(());
+        };
+        let next = table::cells(&self.document)
+            .into_iter()
+            .find(|next| next.table == cell.table && (next.row, next.col) > (cell.row, cell.col));
+        match next {
+            Some(next) => {
+                self.select_cell(&next);
+                Ok(())
+            }
+            None => self.edit_table(|table, rows, _| {
+                table::insert_row(table, rows.end);
+                Some((rows.end, 0))
+            }),
+        }
+    }
+
+    /// Select the text of the table cell before the cursor's.
+    pub fn previous_cell(&mut self) {
+        let Some(cell) = table::cell_at(&self.document, self.cursor.position) else {
+            return;
+        };
+        let previous = table::cells(&self.document).into_iter().rfind(|previous| {
+            previous.table == cell.table && (previous.row, previous.col) < (cell.row, cell.col)
+        });
+        if let Some(previous) = previous {
+            self.select_cell(&previous);
+        }
+    }
+
+    /// Move the cursor to the cell above or below in its table, keeping its
+    /// offset in the cell where it fits. From the first or last row it
+    /// moves out of the table, to the end of the block before or the start
+    /// of the block after, if there is one. Returns whether the cursor was
+    /// in a table.
+    pub fn cursor_cell_vertical(&mut self, down: bool) -> bool {
+        let position = self.cursor.position;
+        let Some(cell) = table::cell_at(&self.document, position) else {
+            return false;
+        };
+        let cells: Vec<Cell> = table::cells(&self.document)
+            .into_iter()
+            .filter(|other| other.table == cell.table)
+            .collect();
+        let row = if down {
+            Some(cell.row + 1)
+        } else {
+            cell.row.checked_sub(1)
+        };
+        let target = row.and_then(|row| {
+            cells
+                .iter()
+                .rfind(|other| other.row == row && other.col <= cell.col)
+        });
+        let text = self.text();
+        let moved = match target {
+            Some(target) => {
+                Some(target.range.start + (position - cell.range.start).min(target.range.len()))
+            }
+            None if down => cells
+                .last()
+                .map(|last| last.range.end + 1)
+                .filter(|&after| after <= text.len()),
+            None => cells
+                .first()
+                .and_then(|first| first.range.start.checked_sub(1)),
+        };
+        if let Some(moved) = moved {
+            self.history.break_group();
+            self.cursor.position = boundary::snap(&text, moved);
+        }
+        true
+    }
+
+    /// Insert a row of empty cells above or below the cursor's row of a
+    /// table, or the selected rows, as one undo step.
+    pub fn insert_row(&mut self, below: bool) -> crate::Result<()> {
+        self.edit_table(|table, rows, cols| {
+            let row = if below { rows.end } else { rows.start };
+            table::insert_row(table, row);
+            Some((row, cols.start))
         })
     }
 
-    /// Apply an operation to the document.
-    pub fn apply_operation(&mut self, operation: Operation) -> crate::Result<()> {
-        // Apply the operation to the document
-        // In a real implementation, this would modify the document tree
+    /// Delete the cursor's row of a table, or the selected rows, as one
+    /// undo step. The last rows of a table are not deleted.
+    pub fn delete_rows(&mut self) -> crate::Result<()> {
+        self.edit_table(|table, rows, cols| {
+            if rows.len() >= table::row_count(table) {
+                return None;
+            }
+            for row in rows.clone().rev() {
+                table::remove_row(table, row);
+            }
+            Some((rows.start, cols.start))
+        })
+    }
+
+    /// Insert a column of empty cells left or right of the cursor's column
+    /// of a table, or the selected columns, as one undo step.
+    pub fn insert_column(&mut self, right: bool) -> crate::Result<()> {
+        self.edit_table(|table, rows, cols| {
+            let col = if right { cols.end } else { cols.start };
+            table::insert_column(table, col);
+            Some((rows.start, col))
+        })
+    }
+
+    /// Delete the cursor's column of a table, or the selected columns, as
+    /// one undo step. The last columns of a table are not deleted.
+    pub fn delete_columns(&mut self) -> crate::Result<()> {
+        self.edit_table(|table, rows, cols| {
+            if cols.len() >= table::column_count(table) {
+                return None;
+            }
+            for col in cols.clone().rev() {
+                table::remove_column(table, col);
+            }
+            Some((rows.start, cols.start))
+        })
+    }
+
+    /// Change the table the cursor is in with `edit`, as one undo step.
+    ///
+    /// `edit` is given a copy of the table with the rows and cells of the
+    /// selected cells, or of the cursor's cell, and returns the row and
+    /// cell to put the cursor in, or `None` to leave the table as it was.
+    fn edit_table(
+        &mut self,
+        edit: impl FnOnce(&mut Node, Range<usize>, Range<usize>) -> Option<(usize, usize)>,
+    ) -> crate::Result<()> {
+        let Some(cell) = table::cell_at(&self.document, self.cursor.position) else {
+            return Ok(());
+        };
+        let Some(old) = table::find_table(&self.document, cell.table).cloned() else {
+            return Ok(());
+        };
+        let (rows, cols) = match self.cell_selection() {
+            Some(selection) if selection.table == cell.table => (selection.rows, selection.cols),
+            _ => (cell.row..cell.row + 1, cell.col..cell.col + 1),
+        };
+        let mut new = old.clone();
+        let Some((row, col)) = edit(&mut new, rows, cols) else {
+            return Ok(());
+        };
+        self.history.break_group();
+        self.apply_operation(Operation::ReplaceBlock {
+            old: Box::new(old),
+            new: Box::new(new),
+        })?;
+        self.history.break_group();
+        self.selection = None;
+        self.secondary.clear();
+        // The cell asked for, or the nearest before it.
+        if let Some(target) = table::cells(&self.document)
+            .into_iter()
+            .rfind(|target| target.table == cell.table && (target.row, target.col) <= (row, col))
+        {
+            self.cursor.position = target.range.start;
+        }
+        Ok(())
+    }
+
+    /// Select the text of `cell`, or put a caret in it if it is empty.
+    fn select_cell(&mut self, cell: &Cell) {
+        self.secondary.clear();
+        if cell.range.is_empty() {
+            self.set_cursor(cell.range.start);
+        } else {
+            self.select_range(cell.range.start, cell.range.end);
+        }
+    }
+
+    /// Delete the text the primary selection covers, if any, dropping the
+    /// selection. While changes are tracked, the text is suggested for
+    /// deletion instead, and the cursor left after it.
+    fn delete_primary_selection(&mut self) -> crate::Result<()> {
+        if let Some(sel) = self.selection.take().filter(|sel| !sel.is_empty()) {
+            let range = sel.start.min(sel.end)..sel.start.max(sel.end);
+            match self.track_changes.clone() {
+                Some(author) => self.cursor.position = self.suggest(range, "", &author)?.0,
+                None => self.apply_operation(Operation::DeleteText {
+                    start: range.start,
+                    end: range.end,
+                    deleted: String::new(),
+                })?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace every match of `query`, as one undo step. Returns the number
+    /// of replacements made.
+    pub fn replace_all(
+        &mut self,
+        query: &str,
+        replacement: &str,
+        options: FindOptions,
+    ) -> crate::Result<usize> {
+        let operations = search::replace_all(&self.text(), query, replacement, options)?;
+        let count = operations.len();
+        self.history.begin_group();
+        let result = operations
+            .into_iter()
+            .try_for_each(|operation| self.apply_operation(operation));
+        self.history.end_group();
+        result.map(|()| count)
+    }
+
+    /// Replace the next match of `query` at or after the cursor, wrapping
+    /// around to the start of the document. Returns whether a match was
+    /// replaced.
+    pub fn replace_next(
+        &mut self,
+        query: &str,
+        replacement: &str,
+        options: FindOptions,
+    ) -> crate::Result<bool> {
+        let operation = search::replace_next(
+            &self.text(),
+            query,
+            replacement,
+            options,
+            self.cursor.position,
+        )?;
+        match operation {
+            Some(operation) => {
+                self.history.break_group();
+                self.apply_operation(operation)?;
+                self.history.break_group();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 
-        // Add to history
+    /// Apply an operation to the document and record it for undo.
+    ///
+    /// The cursor is left after any inserted text, and a deletion records
+    /// the text it removed.
+    pub fn apply_operation(&mut self, operation: Operation) -> crate::Result<()> {
+        let operation = self.perform(operation)?;
         self.history.push(operation);
         self.dirty = true;
 
@@ -182,9 +1193,11 @@ impl Editor {
 
     /// Undo the last operation.
     pub fn undo(&mut self) -> crate::Result<()> {
-        if let Some(_operation) = self.history.undo() {
-            // Apply the inverse operation
-            // In a real implementation, reconstruct document state
+        if let Some(operations) = self.history.undo().map(|group| group.operations.clone()) {
+            for operation in operations.iter().rev() {
+                self.perform(operation.inverse())?;
+            }
+            self.selection = None;
             self.dirty = true;
         }
         Ok(())
@@ -192,18 +1205,179 @@ impl Editor {
 
     /// Redo the last undone operation.
     pub fn redo(&mut self) -> crate::Result<()> {
-        if let Some(_operation) = self.history.redo() {
-            // Apply the operation
-            // In a real implementation, reconstruct document state
+        if let Some(operations) = self.history.redo().map(|group| group.operations.clone()) {
+            for operation in operations {
+                self.perform(operation)?;
+            }
+            self.selection = None;
             self.dirty = true;
         }
         Ok(())
     }
 
+    /// Apply an operation to the document without recording it.
+    ///
+    /// Returns the operation with the text it replaced filled in. Formatting
+    /// and node operations are returned unchanged.
+    fn perform(&mut self, operation: Operation) -> crate::Result<Operation> {
+        let operation = match operation {
+            Operation::InsertText { position, text } => {
+                buffer::replace(&mut self.document, position..position, &text)?;
+                self.cursor.position = position + text.len();
+                Operation::InsertText { position, text }
+            }
+            Operation::DeleteText { start, end, .. } => {
+                let deleted = buffer::replace(&mut self.document, start..end, "")?;
+                self.cursor.position = start;
+                Operation::DeleteText {
+                    start,
+                    end,
+                    deleted,
+                }
+            }
+            Operation::InsertBlocks { position, blocks } => {
+                let inserted = buffer::insert_blocks(&mut self.document, position, &blocks)?;
+                self.cursor.position = position + inserted;
+                Operation::InsertBlocks { position, blocks }
+            }
+            Operation::InsertBlock { position, block } => {
+                buffer::insert_block(&mut self.document, position, block.clone())?;
+                Operation::InsertBlock { position, block }
+            }
+            Operation::RemoveBlock { position, block } => {
+                buffer::remove_block(&mut self.document, block.id)?;
+                Operation::RemoveBlock { position, block }
+            }
+            Operation::ReplaceBlock { new, .. } => {
+                let old = buffer::replace_block(&mut self.document, (*new).clone())?;
+                self.cursor.position = boundary::snap(&self.text(), self.cursor.position);
+                Operation::ReplaceBlock {
+                    old: Box::new(old),
+                    new,
+                }
+            }
+            Operation::SetSpans {
+                start,
+                end,
+                old_spans,
+                new_spans,
+            } => {
+                buffer::set_spans(&mut self.document, start..end, &new_spans)?;
+                Operation::SetSpans {
+                    start,
+                    end,
+                    old_spans,
+                    new_spans,
+                }
+            }
+            Operation::SetParagraphStyles {
+                start,
+                end,
+                old_styles,
+                new_styles,
+            } => {
+                buffer::set_paragraph_styles(&mut self.document, start..end, &new_styles)?;
+                Operation::SetParagraphStyles {
+                    start,
+                    end,
+                    old_styles,
+                    new_styles,
+                }
+            }
+            Operation::ReplaceText {
+                start,
+                end,
+                new_text,
+                ..
+            } => {
+                let old_text = buffer::replace(&mut self.document, start..end, &new_text)?;
+                self.cursor.position = start + new_text.len();
+                Operation::ReplaceText {
+                    start,
+                    end,
+                    old_text,
+                    new_text,
+                }
+            }
+            operation => operation,
+        };
+        Ok(operation)
+    }
+
+    /// Show uncommitted IME text at the cursor, with the IME's cursor at
+    /// byte offset `cursor` within it. The document is not changed.
+    pub fn set_preedit(&mut self, text: &str, cursor: usize) {
+        self.ime.set_preedit(text, cursor);
+    }
+
+    /// End IME composition by inserting `text` at the cursor as one undo
+    /// step.
+    pub fn commit_composition(&mut self, text: &str) -> crate::Result<()> {
+        let text = self.ime.commit(text);
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.history.break_group();
+        let result = self.insert_text(&text);
+        self.history.break_group();
+        result
+    }
+
+    /// End IME composition without inserting anything.
+    pub fn cancel_composition(&mut self) {
+        self.ime.cancel();
+    }
+
+    /// Position of the IME cursor in [`display_text`](Self::display_text),
+    /// if composing.
+    pub fn preedit_cursor(&self) -> Option<usize> {
+        self.ime
+            .preedit()
+            .map(|_| self.cursor.position + self.ime.cursor)
+    }
+
+    /// The document text with any IME preedit shown at the cursor.
+    pub fn display_text(&self) -> String {
+        let mut text = self.text();
+        if let Some(preedit) = self.ime.preedit() {
+            let position = boundary::snap(&text, self.cursor.position);
+            text.insert_str(position, preedit);
+        }
+        text
+    }
+
+    /// Handle an IME event.
+    pub fn handle_ime_event(&mut self, event: ImeEvent) -> crate::Result<()> {
+        self.input.handle_ime(&event);
+
+        match event {
+            ImeEvent::Start => self.ime.start(),
+            ImeEvent::Preedit(text, cursor) => {
+                let cursor = cursor.unwrap_or(text.len());
+                self.set_preedit(&text, cursor);
+            }
+            ImeEvent::Commit(text) => self.commit_composition(&text)?,
+            ImeEvent::End => self.cancel_composition(),
+        }
+
+        Ok(())
+    }
+
     /// Handle a keyboard event.
     pub fn handle_keyboard_event(&mut self, event: KeyboardEvent) -> crate::Result<()> {
         self.input.handle_keyboard(&event);
 
+        // Keys edit the composition, never the committed text, until it
+        // ends.
+        if self.ime.composing {
+            match event.key {
+                Key::Backspace if event.pressed => self.ime.delete_backward(),
+                Key::Delete if event.pressed => self.ime.delete_forward(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
         match event.key {
             Key::ArrowLeft => {
                 if event.modifiers.shift {
@@ -213,7 +1387,11 @@ impl Editor {
                 } else {
                     self.clear_selection();
                 }
-                self.cursor_left();
+                if event.modifiers.control || event.modifiers.alt {
+                    self.cursor_left_word();
+                } else {
+                    self.cursor_left();
+                }
                 if event.modifiers.shift {
                     self.extend_selection();
                 }
@@ -226,11 +1404,46 @@ impl Editor {
                 } else {
                     self.clear_selection();
                 }
-                self.cursor_right();
+                if event.modifiers.control || event.modifiers.alt {
+                    self.cursor_right_word();
+                } else {
+                    self.cursor_right();
+                }
+                if event.modifiers.shift {
+                    self.extend_selection();
+                }
+            }
+            Key::ArrowUp | Key::ArrowDown if event.pressed => {
+                if event.modifiers.shift {
+                    if self.selection.is_none() {
+                        self.start_selection();
+                    }
+                } else {
+                    self.clear_selection();
+                }
+                if !self.cursor_cell_vertical(event.key == Key::ArrowDown) {
+                    if event.key == Key::ArrowDown {
+                        self.cursor_down();
+                    } else {
+                        self.cursor_up();
+                    }
+                }
                 if event.modifiers.shift {
                     self.extend_selection();
                 }
             }
+            // Tab moves between the cells of a table rather than typing.
+            Key::Tab
+                if event.pressed
+                    && table::cell_at(&self.document, self.cursor.position).is_some() =>
+            {
+                if event.modifiers.shift {
+                    self.previous_cell();
+                } else {
+                    self.next_cell()?;
+                }
+                return Ok(());
+            }
             Key::Home => self.cursor_line_start(),
             Key::End => self.cursor_line_end(),
             Key::Backspace if event.pressed => {
@@ -265,6 +1478,27 @@ impl Editor {
     }
 }
 
+/// Whether two cursors overlap: their selections share text, or they start
+/// at the same place.
+fn overlaps(a: &Selection, b: &Selection) -> bool {
+    a.start == b.start || (a.start < b.end && b.start < a.end)
+}
+
+/// Whether any text of `blocks` is inside their ranges, and all of it has
+/// a style `test` holds for.
+fn all_styled(blocks: &[buffer::BlockSpans], test: fn(&CoreTextStyle) -> bool) -> bool {
+    let mut styles = blocks
+        .iter()
+        .flat_map(|block| {
+            format::runs(&block.spans, block.len)
+                .into_iter()
+                .filter(|(run, _)| run.start < block.range.end && run.end > block.range.start)
+                .map(|(_, style)| style)
+        })
+        .peekable();
+    styles.peek().is_some() && styles.all(|style| test(&style))
+}
+
 impl Default for Editor {
     fn default() -> Self {
         Self::new()
@@ -283,9 +1517,19 @@ mod tests {
         assert!(editor.selection.is_none());
     }
 
+    fn editor_with(paragraphs: &[&str]) -> Editor {
+        let mut document = Document::new();
+        for paragraph in paragraphs {
+            document
+                .root
+                .add_child(Node::paragraph(Text::new(*paragraph)));
+        }
+        Editor::with_document(document)
+    }
+
     #[test]
     fn test_cursor_movement() {
-        let mut editor = Editor::new();
+        let mut editor = editor_with(&["ab"]);
         editor.cursor_right();
         assert_eq!(editor.cursor.position, 1);
 
@@ -294,16 +1538,806 @@ mod tests {
     }
 
     #[test]
-    fn test_selection() {
+    fn test_cursor_at_ends_of_text() {
+        let mut editor = editor_with(&["ab"]);
+        editor.cursor_left();
+        assert_eq!(editor.cursor.position, 0);
+
+        editor.cursor.position = 2;
+        editor.cursor_right();
+        assert_eq!(editor.cursor.position, 2);
+
+        let mut empty = Editor::new();
+        empty.cursor_right();
+        assert_eq!(empty.cursor.position, 0);
+    }
+
+    #[test]
+    fn test_cursor_moves_over_clusters() {
+        // Thumbs up with a skin tone, a flag, "é" as "e" plus a combining
+        // acute, and two ideographs.
+        let text = "\u{1F44D}\u{1F3FB}\u{1F1FA}\u{1F1F8}e\u{301}日本";
+        let mut editor = editor_with(&[text]);
+        let mut positions = Vec::new();
+        for _ in 0..6 {
+            editor.cursor_right();
+            positions.push(editor.cursor.position);
+        }
+        assert_eq!(positions, vec![8, 16, 19, 22, 25, 25]);
+        for &position in &positions {
+            assert!(text.is_char_boundary(position));
+        }
+
+        editor.cursor_left();
+        editor.cursor_left();
+        assert_eq!(editor.cursor.position, 19);
+        editor.cursor_left();
+        assert_eq!(editor.cursor.position, 16);
+    }
+
+    #[test]
+    fn test_cursor_word_movement() {
+        let mut editor = editor_with(&["Hello there,", "world"]);
+        editor.cursor_right_word();
+        assert_eq!(editor.cursor.position, 5);
+        editor.cursor_right_word();
+        assert_eq!(editor.cursor.position, 11);
+        // Crosses the paragraph break to the end of "world".
+        editor.cursor_right_word();
+        assert_eq!(editor.cursor.position, 18);
+        editor.cursor_right_word();
+        assert_eq!(editor.cursor.position, 18);
+
+        editor.cursor_left_word();
+        assert_eq!(editor.cursor.position, 13);
+        editor.cursor_left_word();
+        assert_eq!(editor.cursor.position, 6);
+    }
+
+    #[test]
+    fn test_control_arrow_moves_by_word() {
+        let mut editor = editor_with(&["one two"]);
+        let modifiers = KeyModifiers {
+            control: true,
+            ..KeyModifiers::new()
+        };
+        editor
+            .handle_keyboard_event(KeyboardEvent::new(Key::ArrowRight, true, modifiers))
+            .unwrap();
+        assert_eq!(editor.cursor.position, 3);
+    }
+
+    #[test]
+    fn test_insert_and_delete_edit_the_document() {
+        let mut editor = editor_with(&["world"]);
+        editor.insert_text("hello ").unwrap();
+        assert_eq!(editor.text(), "hello world");
+        assert_eq!(editor.cursor.position, 6);
+
+        editor.delete_char().unwrap();
+        assert_eq!(editor.text(), "helloworld");
+        assert_eq!(editor.cursor.position, 5);
+
+        editor.delete_char_forward().unwrap();
+        assert_eq!(editor.text(), "helloorld");
+        assert_eq!(editor.cursor.position, 5);
+        assert!(editor.has_unsaved_changes());
+    }
+
+    #[test]
+    fn test_deletion_records_removed_text() {
+        let mut editor = editor_with(&["cafe\u{301}"]);
+        editor.cursor.position = 6;
+        editor.delete_char().unwrap();
+        assert_eq!(editor.text(), "caf");
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "cafe\u{301}");
+        assert_eq!(editor.cursor.position, 6);
+    }
+
+    #[test]
+    fn test_undo_redo_restore_text_and_cursor() {
+        let mut editor = editor_with(&["abc"]);
+        editor.cursor.position = 3;
+        editor.insert_text("def").unwrap();
+        editor.cursor.position = 1;
+        editor.delete_char().unwrap();
+        assert_eq!(editor.text(), "bcdef");
+        assert_eq!(editor.cursor.position, 0);
+
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "abcdef");
+        assert_eq!(editor.cursor.position, 1);
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "abc");
+        assert_eq!(editor.cursor.position, 3);
+
+        editor.redo().unwrap();
+        assert_eq!(editor.text(), "abcdef");
+        assert_eq!(editor.cursor.position, 6);
+        editor.redo().unwrap();
+        assert_eq!(editor.text(), "bcdef");
+        assert_eq!(editor.cursor.position, 0);
+    }
+
+    #[test]
+    fn test_undo_reverts_typed_word() {
         let mut editor = Editor::new();
+        for c in "one two".chars() {
+            editor.insert_text(&c.to_string()).unwrap();
+        }
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "one ");
+        assert_eq!(editor.cursor.position, 4);
+
+        // Moving the cursor starts a new undo step.
+        editor.insert_text("x").unwrap();
+        editor.cursor_left();
+        editor.cursor_right();
+        editor.insert_text("y").unwrap();
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "one x");
+    }
+
+    #[test]
+    fn test_replace_all_is_one_undo_step() {
+        let mut editor = editor_with(&["red green", "red"]);
+        let count = editor
+            .replace_all("red", "blue", FindOptions::default())
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(editor.text(), "blue green\nblue");
+
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "red green\nred");
+    }
+
+    #[test]
+    fn test_replace_next_from_cursor() {
+        let mut editor = editor_with(&["ab ab"]);
+        editor.cursor.position = 1;
+        assert!(
+            editor
+                .replace_next("ab", "X", FindOptions::default())
+                .unwrap()
+        );
+        assert_eq!(editor.text(), "ab X");
+        assert_eq!(editor.cursor.position, 4);
+        assert!(
+            !editor
+                .replace_next("zz", "X", FindOptions::default())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_insert_at_three_cursors() {
+        let mut editor = editor_with(&["a b c"]);
+        editor.cursor.position = 1;
+        editor.add_cursor_at(5);
+        editor.add_cursor_at(3);
+        editor.insert_text("XY").unwrap();
+        assert_eq!(editor.text(), "aXY bXY cXY");
+        assert_eq!(editor.cursor.position, 3);
+        assert_eq!(
+            editor.secondary,
+            vec![Selection::new(7, 7), Selection::new(11, 11)]
+        );
+
+        // One undo step for the whole gesture.
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "a b c");
+    }
+
+    #[test]
+    fn test_overlapping_cursors_merge() {
+        let mut editor = editor_with(&["abcdef"]);
+        editor.add_cursor_at(0);
+        assert!(editor.secondary.is_empty());
+        editor.add_cursor_at(3);
+        editor.add_cursor_at(3);
+        assert_eq!(editor.secondary.len(), 1);
+
+        // Backspacing at carets one apart deletes both characters.
+        editor.add_cursor_at(4);
+        editor.delete_char().unwrap();
+        assert_eq!(editor.text(), "abef");
+        // The two carets now coincide and merge.
+        editor.cursor_right();
+        assert_eq!(editor.cursor.position, 1);
+        assert_eq!(editor.secondary, vec![Selection::new(3, 3)]);
+    }
+
+    #[test]
+    fn test_cursors_for_matches_replace_selections() {
+        let mut editor = editor_with(&["let x = x + x;"]);
+        // The first match is already selected by the primary cursor.
+        editor.cursor.position = 5;
+        editor.selection = Some(Selection::new(4, 5));
+        assert_eq!(editor.add_cursors_for_matches("x"), 3);
+        editor.insert_text("total").unwrap();
+        assert_eq!(editor.text(), "let total = total + total;");
+        assert_eq!(editor.cursor.position, 9);
+        let carets: Vec<usize> = editor.secondary.iter().map(|sel| sel.start).collect();
+        assert_eq!(carets, vec![17, 25]);
+    }
+
+    #[test]
+    fn test_copy_and_paste_keep_formatting() {
+        use wolia_core::node::NodeKind;
+        use wolia_core::style::TextStyle;
+        use wolia_core::text::Span;
+
+        let mut heading = Text::new("Intro");
+        heading.add_span(Span::new(
+            0,
+            5,
+            TextStyle {
+                italic: Some(true),
+                ..TextStyle::default()
+            },
+        ));
+        let mut body = Text::new("a bold move");
+        body.add_span(Span::new(
+            2,
+            6,
+            TextStyle {
+                font_weight: Some(700),
+                ..TextStyle::default()
+            },
+        ));
+        let mut document = Document::new();
+        document.root.add_child(Node::heading(1, heading));
+        document.root.add_child(Node::paragraph(body));
+        document.root.add_child(Node::paragraph(Text::new("end")));
+        let mut editor = Editor::with_document(document);
+
+        // Copy from the middle of the heading to after "bold".
+        editor.selection = Some(Selection::new(2, 12));
+        let fragment = editor.copy_selection().unwrap();
+        assert_eq!(fragment.plain_text(), "tro\na bold");
+
+        editor.selection = None;
+        editor.cursor.position = editor.text().len();
+        editor.paste(&fragment).unwrap();
+        assert_eq!(editor.text(), "Intro\na bold move\nendtro\na bold");
+        assert_eq!(editor.cursor.position, editor.text().len());
+
+        let children = &editor.document.root.children;
+        let NodeKind::Paragraph(end) = &children[2].kind else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(end.spans[0].style.italic, Some(true));
+        assert_eq!((end.spans[0].start, end.spans[0].end), (3, 6));
+        let NodeKind::Paragraph(pasted) = &children[3].kind else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(pasted.spans[0].style.font_weight, Some(700));
+        assert_eq!((pasted.spans[0].start, pasted.spans[0].end), (2, 6));
+
+        // Undo removes the paste; redo brings the formatting back.
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "Intro\na bold move\nend");
+        editor.redo().unwrap();
+        let NodeKind::Paragraph(pasted) = &editor.document.root.children[3].kind else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(pasted.spans[0].style.font_weight, Some(700));
+    }
+
+    #[test]
+    fn test_compose_then_commit() {
+        let mut editor = editor_with(&["私は"]);
+        editor.cursor.position = 6;
+        editor
+            .handle_ime_event(ImeEvent::Preedit("にほん".into(), Some(3)))
+            .unwrap();
+        assert_eq!(editor.text(), "私は");
+        assert_eq!(editor.display_text(), "私はにほん");
+        assert_eq!(editor.preedit_cursor(), Some(9));
+        assert!(!editor.history.can_undo());
+
+        editor
+            .handle_ime_event(ImeEvent::Commit("日本".into()))
+            .unwrap();
+        editor.handle_ime_event(ImeEvent::End).unwrap();
+        assert_eq!(editor.text(), "私は日本");
+        assert_eq!(editor.cursor.position, 12);
+        assert_eq!(editor.preedit_cursor(), None);
+
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "私は");
+    }
+
+    #[test]
+    fn test_compose_then_cancel() {
+        let mut editor = editor_with(&["abc"]);
+        editor.cursor.position = 3;
+        editor.set_preedit("かな", 6);
+        let backspace = KeyboardEvent::new(Key::Backspace, true, KeyModifiers::new());
+        editor.handle_keyboard_event(backspace.clone()).unwrap();
+        assert_eq!(editor.display_text(), "abcか");
+        editor.handle_keyboard_event(backspace.clone()).unwrap();
+        editor.handle_keyboard_event(backspace).unwrap();
+        // Deleting past the start of the preedit leaves committed text.
+        assert_eq!(editor.text(), "abc");
+
+        editor.cancel_composition();
+        assert_eq!(editor.display_text(), "abc");
+        assert!(!editor.history.can_undo());
+    }
+
+    #[test]
+    fn test_toggle_bold_on_mixed_selection() {
+        use wolia_core::node::NodeKind;
+        use wolia_core::style::TextStyle;
+        use wolia_core::text::Span;
+
+        let bold = TextStyle {
+            font_weight: Some(700),
+            ..TextStyle::default()
+        };
+        let mut text = Text::new("plain bold");
+        text.add_span(Span::new(6, 10, bold.clone()));
+        let mut document = Document::new();
+        document.root.add_child(Node::paragraph(text));
+        document.root.add_child(Node::paragraph(Text::new("next")));
+        let mut editor = Editor::with_document(document);
+        let spans = |editor: &Editor, index: usize| match &editor.document.root.children[index].kind
+        {
+            NodeKind::Paragraph(text) => text.spans.to_vec(),
+            _ => Vec::new(),
+        };
+
+        // Mixed: everything becomes bold, across both paragraphs.
+        editor
+            .apply_format(Selection::new(3, 13), FormatChange::ToggleBold)
+            .unwrap();
+        assert_eq!(spans(&editor, 0), vec![Span::new(3, 10, bold.clone())]);
+        assert_eq!(spans(&editor, 1), vec![Span::new(0, 2, bold.clone())]);
+
+        // All bold: toggling removes it.
+        editor
+            .apply_format(Selection::new(6, 10), FormatChange::ToggleBold)
+            .unwrap();
+        assert_eq!(spans(&editor, 0), vec![Span::new(3, 6, bold.clone())]);
+
+        editor.undo().unwrap();
+        assert_eq!(spans(&editor, 0), vec![Span::new(3, 10, bold.clone())]);
+        editor.undo().unwrap();
+        assert_eq!(spans(&editor, 0), vec![Span::new(6, 10, bold)]);
+        assert!(spans(&editor, 1).is_empty());
+        editor.redo().unwrap();
+        assert_eq!(spans(&editor, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_format_paragraphs() {
+        let mut editor = editor_with(&["one", "two", "three"]);
+        editor.set_cursor(5);
+        editor
+            .format_paragraphs(Selection::new(1, 5), |style| {
+                style.first_line_indent = Some(18.0)
+            })
+            .unwrap();
+        assert_eq!(editor.paragraph_style().first_line_indent, Some(18.0));
+        let indents: Vec<_> = editor
+            .document
+            .root
+            .children
+            .iter()
+            .map(|node| node.formatting.paragraph.first_line_indent)
+            .collect();
+        assert_eq!(indents, vec![Some(18.0), Some(18.0), None]);
+
+        // An edit that changes nothing is not an undo step.
+        editor
+            .format_paragraphs(Selection::new(1, 5), |style| {
+                style.first_line_indent = Some(18.0)
+            })
+            .unwrap();
+        editor.undo().unwrap();
+        assert_eq!(editor.paragraph_style().first_line_indent, None);
+        assert!(!editor.history.can_undo());
+        editor.redo().unwrap();
+        assert_eq!(editor.paragraph_style().first_line_indent, Some(18.0));
+    }
+
+    #[test]
+    fn test_selection_has_style() {
+        let mut editor = editor_with(&["one two"]);
+        assert!(!editor.selection_has(format::is_italic));
+        editor
+            .apply_format(Selection::new(4, 7), FormatChange::ToggleItalic)
+            .unwrap();
+        editor.select_range(4, 7);
+        assert!(editor.selection_has(format::is_italic));
+        assert!(!editor.selection_has(format::is_underlined));
+        editor.select_range(2, 7);
+        assert!(!editor.selection_has(format::is_italic));
+
+        // With only a caret, the character before it counts.
+        editor.set_cursor(5);
+        assert!(editor.selection_has(format::is_italic));
+        editor.set_cursor(4);
+        assert!(!editor.selection_has(format::is_italic));
+        editor.set_cursor(0);
+        assert!(!editor.selection_has(format::is_italic));
+
+        editor
+            .apply_format(Selection::new(4, 7), FormatChange::ToggleItalic)
+            .unwrap();
+        editor.select_range(4, 7);
+        assert!(!editor.selection_has(format::is_italic));
+    }
+
+    #[test]
+    fn test_link_selection_and_remove_it() {
+        use wolia_core::node::NodeKind;
+        use wolia_core::text::Span;
+
+        let mut editor = editor_with(&["see the docs here"]);
+        let spans = |editor: &Editor| match &editor.document.root.children[0].kind {
+            NodeKind::Paragraph(text) => text.spans.to_vec(),
+            _ => Vec::new(),
+        };
+        let url = "https://example.com/docs";
+        editor.select_range(8, 12);
+        editor.set_link(Some(url.to_string())).unwrap();
+        let linked = CoreTextStyle {
+            link: Some(url.to_string()),
+            ..CoreTextStyle::default()
+        };
+        assert_eq!(spans(&editor), vec![Span::new(8, 12, linked)]);
+        assert_eq!(editor.link().as_deref(), Some(url));
+        assert_eq!(editor.link_at(12), Some((8..12, url.to_string())));
+        assert_eq!(editor.link_at(13), None);
+        let markdown = format_markdown::write(&editor.document).unwrap();
+        assert!(markdown.contains("the [docs](https://example.com/docs) here"));
+
+        // With only a caret in it, the whole link is changed.
+        editor.set_cursor(10);
+        editor
+            .set_link(Some("https://example.org".to_string()))
+            .unwrap();
+        assert_eq!(
+            editor.link_at(8),
+            Some((8..12, "https://example.org".to_string()))
+        );
+        editor.set_link(None).unwrap();
+        assert!(spans(&editor).is_empty());
+        assert_eq!(editor.text(), "see the docs here");
+        assert_eq!(editor.link(), None);
+
+        editor.undo().unwrap();
+        assert_eq!(
+            editor.link_at(8).map(|(_, url)| url).as_deref(),
+            Some("https://example.org")
+        );
+    }
+
+    #[test]
+    fn test_link_without_selection_inserts_url() {
+        let mut editor = editor_with(&["go "]);
+        editor.set_cursor(3);
+        editor
+            .set_link(Some("https://wolia.app".to_string()))
+            .unwrap();
+        assert_eq!(editor.text(), "go https://wolia.app");
+        assert_eq!(editor.link_at(5).map(|(range, _)| range), Some(3..20));
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "go ");
+    }
+
+    fn tracking(paragraphs: &[&str]) -> Editor {
+        let mut editor = editor_with(paragraphs);
+        editor.track_changes = Some("ana".to_string());
+        editor
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.insert_text(&c.to_string()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_tracked_insertion_is_a_suggestion() {
+        let mut editor = tracking(&["The fox"]);
+        editor.set_cursor(4);
+        type_text(&mut editor, "quick ");
+        assert_eq!(editor.text(), "The quick fox");
+        let suggestions = editor.suggestions();
+        assert_eq!(suggestions.len(), 1);
+        let revision = &suggestions[0].revision;
+        assert_eq!(revision.kind, RevisionKind::Insertion);
+        assert_eq!(revision.author, "ana");
+        assert_eq!(suggestions[0].ranges, vec![4..10]);
+
+        // Accepting keeps the text as ordinary text, in one undo step.
+        editor.accept(revision.id).unwrap();
+        assert_eq!(editor.text(), "The quick fox");
+        assert!(editor.suggestions().is_empty());
+        editor.undo().unwrap();
+        assert_eq!(editor.suggestions(), suggestions);
+        // The typing is undone a word at a time, as without tracking.
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "The fox");
+        editor.redo().unwrap();
+        editor.redo().unwrap();
+        assert_eq!(editor.text(), "The quick fox");
+        assert!(editor.suggestions().is_empty());
+
+        // Text typed directly does not join a suggestion.
+        let mut editor = tracking(&["ab"]);
+        editor.set_cursor(1);
+        editor.insert_text("X").unwrap();
+        editor.track_changes = None;
+        editor.insert_text("Y").unwrap();
+        assert_eq!(editor.text(), "aXYb");
+        assert_eq!(editor.suggestions()[0].ranges, vec![1..2]);
+    }
+
+    #[test]
+    fn test_tracked_deletion_and_replacement() {
+        let mut editor = tracking(&["The quick fox"]);
+        editor.set_cursor(10);
+        for _ in 0..6 {
+            editor.delete_char().unwrap();
+        }
+        assert_eq!(editor.text(), "The quick fox");
+        assert_eq!(editor.cursor.position, 4);
+        let suggestions = editor.suggestions();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].revision.kind, RevisionKind::Deletion);
+        assert_eq!(suggestions[0].ranges, vec![4..10]);
+        editor.reject(suggestions[0].revision.id).unwrap();
+        assert!(editor.suggestions().is_empty());
+
+        // Replacing a word strikes it and inserts after it.
+        editor.select_range(4, 9);
+        editor.insert_text("slow").unwrap();
+        assert_eq!(editor.text(), "The quickslow fox");
+        assert_eq!(editor.cursor.position, 13);
+        let [deletion, insertion] = editor.suggestions().try_into().unwrap();
+        assert_eq!(deletion.ranges, vec![4..9]);
+        assert_eq!(insertion.ranges, vec![9..13]);
+        editor.accept(deletion.revision.id).unwrap();
+        assert_eq!(editor.text(), "The slow fox");
+        assert_eq!(editor.cursor.position, 8);
+        editor.reject(insertion.revision.id).unwrap();
+        assert_eq!(editor.text(), "The  fox");
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "The slow fox");
+        assert_eq!(editor.suggestions()[0].ranges, vec![4..8]);
+    }
+
+    #[test]
+    fn test_deleting_suggested_insertion_removes_it() {
+        let mut editor = tracking(&["ab"]);
+        editor.set_cursor(1);
+        editor.insert_text("X").unwrap();
+        let inserted = editor.suggestions();
+
+        // The deletion covers the insertion and the text either side of it.
+        editor.select_range(0, 3);
+        editor.delete_char().unwrap();
+        assert_eq!(editor.text(), "ab");
+        let [deletion] = editor.suggestions().try_into().unwrap();
+        assert_eq!(deletion.revision.kind, RevisionKind::Deletion);
+        assert_eq!(deletion.ranges, vec![0..2]);
+
+        // Deleting it again leaves the suggestion as it is.
+        editor.select_range(0, 2);
+        editor.delete_char().unwrap();
+        assert_eq!(editor.suggestions(), vec![deletion.clone()]);
+
+        editor.accept(deletion.revision.id).unwrap();
+        assert_eq!(editor.text(), "");
+        editor.undo().unwrap();
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "aXb");
+        assert_eq!(editor.suggestions(), inserted);
+        assert!(editor.accept(deletion.revision.id).is_err());
+    }
+
+    #[test]
+    fn test_invalid_position_leaves_document_unchanged() {
+        let mut editor = editor_with(&["ab"]);
+        editor.cursor.position = 10;
+        assert!(editor.insert_text("x").is_err());
+        assert_eq!(editor.text(), "ab");
+        assert!(!editor.history.can_undo());
+    }
+
+    #[test]
+    fn test_line_start_and_end() {
+        let mut editor = editor_with(&["one", "two"]);
+        editor.cursor.position = 5;
+        editor.cursor_line_start();
+        assert_eq!(editor.cursor.position, 4);
+        editor.cursor_line_end();
+        assert_eq!(editor.cursor.position, 7);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut editor = editor_with(&["ab"]);
         editor.start_selection();
         assert!(editor.selection.is_some());
 
         editor.cursor_right();
         editor.extend_selection();
-        assert!(editor.selected_text().is_some());
+        assert_eq!(editor.selected_text().as_deref(), Some("a"));
 
         editor.clear_selection();
         assert!(editor.selection.is_none());
     }
+
+    #[test]
+    fn test_insert_block_splits_paragraph() {
+        use wolia_core::node::NodeKind;
+
+        let kinds = |editor: &Editor| -> Vec<String> {
+            editor
+                .document
+                .root
+                .children
+                .iter()
+                .map(|node| match &node.kind {
+                    NodeKind::Paragraph(text) => text.content.clone(),
+                    NodeKind::Image { src, .. } => format!("[{src}]"),
+                    _ => String::new(),
+                })
+                .collect()
+        };
+        let image = |src: &str| {
+            Node::new(NodeKind::Image {
+                src: src.to_string(),
+                alt: None,
+            })
+        };
+        let mut editor = editor_with(&["one two", "three"]);
+        editor.set_cursor(3);
+        editor.insert_block(image("a")).unwrap();
+        assert_eq!(kinds(&editor), ["one", "[a]", " two", "three"]);
+        assert_eq!(editor.cursor.position, 4);
+
+        // At the start or end of a block nothing is split.
+        editor.set_cursor(9);
+        editor.insert_block(image("b")).unwrap();
+        editor.set_cursor(editor.text().len());
+        editor.insert_block(image("c")).unwrap();
+        assert_eq!(
+            kinds(&editor),
+            ["one", "[a]", " two", "[b]", "three", "[c]"]
+        );
+
+        // Each insertion is one undo step.
+        editor.undo().unwrap();
+        editor.undo().unwrap();
+        editor.undo().unwrap();
+        assert_eq!(kinds(&editor), ["one two", "three"]);
+        editor.redo().unwrap();
+        assert_eq!(kinds(&editor), ["one", "[a]", " two", "three"]);
+    }
+
+    fn press(editor: &mut Editor, key: Key, shift: bool) {
+        let modifiers = KeyModifiers {
+            shift,
+            ..KeyModifiers::new()
+        };
+        let mut event = KeyboardEvent::new(key, true, modifiers);
+        event.char_code = (key == Key::Tab).then_some('\t');
+        editor.handle_keyboard_event(event).unwrap();
+    }
+
+    fn table_size(editor: &Editor) -> (usize, usize) {
+        let cell = table::cells(&editor.document).remove(0);
+        let table = table::find_table(&editor.document, cell.table).unwrap();
+        (table::row_count(table), table::column_count(table))
+    }
+
+    #[test]
+    fn test_insert_table_and_move_between_cells() {
+        let mut editor = editor_with(&["Hello"]);
+        editor.set_cursor(5);
+        editor.insert_table(2, 2).unwrap();
+        // Four empty cells, then a paragraph to go on typing in.
+        assert_eq!(editor.text(), "Hello\n\n\n\n\n");
+        assert_eq!(table_size(&editor), (2, 2));
+        assert_eq!(editor.cursor.position, 6);
+
+        editor.insert_text("a").unwrap();
+        press(&mut editor, Key::Tab, false);
+        editor.insert_text("b").unwrap();
+        assert_eq!(editor.text(), "Hello\na\nb\n\n\n");
+        press(&mut editor, Key::Tab, false);
+        press(&mut editor, Key::Tab, false);
+        assert_eq!(editor.cursor.position, 11);
+
+        press(&mut editor, Key::ArrowUp, false);
+        assert_eq!(editor.cursor.position, 8);
+        press(&mut editor, Key::ArrowDown, false);
+        assert_eq!(editor.cursor.position, 11);
+        press(&mut editor, Key::ArrowDown, false);
+        assert_eq!(editor.cursor.position, 12);
+
+        // Shift+Tab selects the text of the cell before.
+        editor.set_cursor(11);
+        press(&mut editor, Key::Tab, true);
+        press(&mut editor, Key::Tab, true);
+        assert_eq!(editor.selected_text().as_deref(), Some("b"));
+
+        // Backspace at the start of a cell leaves the table alone.
+        editor.set_cursor(10);
+        press(&mut editor, Key::Backspace, false);
+        assert_eq!(editor.text(), "Hello\na\nb\n\n\n");
+    }
+
+    #[test]
+    fn test_tab_in_last_cell_adds_row() {
+        let mut editor = editor_with(&[""]);
+        editor.insert_table(2, 2).unwrap();
+        let last = table::cells(&editor.document).last().unwrap().range.start;
+        editor.set_cursor(last);
+        press(&mut editor, Key::Tab, false);
+        assert_eq!(table_size(&editor), (3, 2));
+        let cell = table::cell_at(&editor.document, editor.cursor.position).unwrap();
+        assert_eq!((cell.row, cell.col), (2, 0));
+
+        editor.undo().unwrap();
+        assert_eq!(table_size(&editor), (2, 2));
+        editor.redo().unwrap();
+        assert_eq!(table_size(&editor), (3, 2));
+    }
+
+    #[test]
+    fn test_rows_and_columns_are_undoable() {
+        let mut editor = editor_with(&[""]);
+        editor.insert_table(2, 2).unwrap();
+        editor.insert_column(true).unwrap();
+        assert_eq!(table_size(&editor), (2, 3));
+        let cell = table::cell_at(&editor.document, editor.cursor.position).unwrap();
+        assert_eq!((cell.row, cell.col), (0, 1));
+        editor.insert_row(false).unwrap();
+        assert_eq!(table_size(&editor), (3, 3));
+
+        editor.delete_rows().unwrap();
+        editor.delete_columns().unwrap();
+        assert_eq!(table_size(&editor), (2, 2));
+        editor.delete_rows().unwrap();
+        editor.delete_rows().unwrap();
+        assert_eq!(table_size(&editor), (1, 2));
+
+        for _ in 0..3 {
+            editor.undo().unwrap();
+        }
+        assert_eq!(table_size(&editor), (3, 3));
+    }
+
+    #[test]
+    fn test_deleting_cell_selection_clears_cells() {
+        let mut editor = editor_with(&[""]);
+        editor.insert_table(2, 2).unwrap();
+        for text in ["a", "b", "c", "d"] {
+            editor.insert_text(text).unwrap();
+            press(&mut editor, Key::Tab, false);
+        }
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "\na\nb\nc\nd\n");
+
+        // Text selected within a cell is text, across cells it is cells.
+        editor.select_range(1, 2);
+        assert_eq!(editor.cell_selection(), None);
+        editor.select_range(2, 5);
+        let selection = editor.cell_selection().unwrap();
+        assert_eq!((selection.rows, selection.cols), (0..2, 0..1));
+
+        editor.delete_char().unwrap();
+        assert_eq!(editor.text(), "\n\nb\n\nd\n");
+        assert_eq!(table_size(&editor), (2, 2));
+        editor.undo().unwrap();
+        assert_eq!(editor.text(), "\na\nb\nc\nd\n");
+    }
 }
@@ -1,6 +1,10 @@
 //! Text formatting system for rich text support.
 
 use std::collections::HashMap;
+use std::ops::Range;
+
+use wolia_core::style::{Revision, TextStyle as CoreTextStyle};
+use wolia_core::text::Span;
 
 /// Text style attributes.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
@@ -317,6 +321,175 @@ impl Default for FormattedLine {
     }
 }
 
+/// A formatting change to apply over a range of document text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatChange {
+    /// Make the text bold or not.
+    SetBold(bool),
+    /// Make the text bold, unless all of it already is, in which case make
+    /// none of it bold.
+    ToggleBold,
+    /// Make the text italic or not.
+    SetItalic(bool),
+    /// Make the text italic, unless all of it already is.
+    ToggleItalic,
+    /// Underline the text or not.
+    SetUnderline(bool),
+    /// Underline the text, unless all of it already is.
+    ToggleUnderline,
+    /// Strike the text through or not.
+    SetStrikethrough(bool),
+    /// Strike the text through, unless all of it already is.
+    ToggleStrikethrough,
+    /// Set the font family, or clear it to inherit.
+    SetFontFamily(Option<String>),
+    /// Set the font size in points, or clear it to inherit.
+    SetFontSize(Option<f32>),
+    /// Set the text color, or clear it to inherit.
+    SetColor(Option<Color>),
+    /// Link the text to a URL, or remove its link.
+    SetLink(Option<String>),
+    /// Mark the text as part of a suggested change, or clear its mark.
+    SetRevision(Option<Revision>),
+}
+
+/// Font weight of bold text.
+const BOLD_WEIGHT: u16 = 700;
+
+impl FormatChange {
+    /// Apply the change to a style. Toggles must be resolved to the
+    /// matching `Set` change first and are ignored.
+    pub fn apply(&self, style: &mut CoreTextStyle) {
+        match self {
+            Self::SetBold(true) => style.font_weight = Some(BOLD_WEIGHT),
+            Self::SetBold(false) => style.font_weight = None,
+            Self::SetItalic(italic) => style.italic = italic.then_some(true),
+            Self::SetUnderline(underline) => style.underline = underline.then_some(true),
+            Self::SetStrikethrough(struck) => style.strikethrough = struck.then_some(true),
+            Self::ToggleBold
+            | Self::ToggleItalic
+            | Self::ToggleUnderline
+            | Self::ToggleStrikethrough => {}
+            Self::SetFontFamily(family) => style.font_family = family.clone(),
+            Self::SetFontSize(size) => style.font_size = *size,
+            Self::SetColor(color) => style.color = color.map(|c| [c.red, c.green, c.blue, c.alpha]),
+            Self::SetLink(url) => style.link = url.clone(),
+            Self::SetRevision(revision) => style.revision = revision.clone(),
+        }
+    }
+}
+
+/// Whether a document style is bold.
+pub fn is_bold(style: &CoreTextStyle) -> bool {
+    style.font_weight.is_some_and(|weight| weight >= 600)
+}
+
+/// Whether a document style is italic.
+pub fn is_italic(style: &CoreTextStyle) -> bool {
+    style.italic == Some(true)
+}
+
+/// Whether a document style is underlined.
+pub fn is_underlined(style: &CoreTextStyle) -> bool {
+    style.underline == Some(true)
+}
+
+/// Whether a document style is struck through.
+pub fn is_struck(style: &CoreTextStyle) -> bool {
+    style.strikethrough == Some(true)
+}
+
+/// Split text of length `len` into runs of uniform style.
+///
+/// Where spans overlap, fields set by later spans win. Every byte of the
+/// text is covered, with unstyled runs having the default style.
+pub fn runs(spans: &[Span], len: usize) -> Vec<(Range<usize>, CoreTextStyle)> {
+    let mut edges: Vec<usize> = spans
+        .iter()
+        .flat_map(|span| [span.start.min(len), span.end.min(len)])
+        .chain([0, len])
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+        .windows(2)
+        .map(|edge| {
+            let range = edge[0]..edge[1];
+            let style = spans
+                .iter()
+                .filter(|span| span.start <= range.start && span.end >= range.end)
+                .fold(CoreTextStyle::default(), |style, span| {
+                    merge(style, &span.style)
+                });
+            (range, style)
+        })
+        .collect()
+}
+
+/// Apply `change` to the text in `range`, returning the new span list.
+///
+/// Spans are split at the edges of the range, and afterwards neighbouring
+/// runs with identical styles are merged, so the result never has
+/// overlapping, empty or redundant spans.
+pub fn restyle(
+    spans: &[Span],
+    len: usize,
+    range: Range<usize>,
+    change: &FormatChange,
+) -> Vec<Span> {
+    let mut edges = spans.to_vec();
+    // Zero-width spans that only mark the edges of the range.
+    edges.push(Span::new(
+        range.start,
+        range.start,
+        CoreTextStyle::default(),
+    ));
+    edges.push(Span::new(range.end, range.end, CoreTextStyle::default()));
+
+    let mut result: Vec<Span> = Vec::new();
+    for (run, mut style) in runs(&edges, len) {
+        if run.start >= range.start && run.end <= range.end {
+            change.apply(&mut style);
+        }
+        if style == CoreTextStyle::default() {
+            continue;
+        }
+        match result.last_mut() {
+            Some(last) if last.end == run.start && last.style == style => last.end = run.end,
+            _ => result.push(Span::new(run.start, run.end, style)),
+        }
+    }
+    result
+}
+
+/// Overlay the fields set in `top` onto `base`.
+fn merge(mut base: CoreTextStyle, top: &CoreTextStyle) -> CoreTextStyle {
+    macro_rules! overlay {
+        ($($field:ident),*) => {
+            $(if top.$field.is_some() {
+                base.$field = top.$field.clone();
+            })*
+        };
+    }
+    overlay!(
+        font_family,
+        font_size,
+        font_weight,
+        italic,
+        underline,
+        strikethrough,
+        color,
+        background,
+        superscript,
+        subscript,
+        small_caps,
+        letter_spacing,
+        link,
+        revision
+    );
+    base
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -414,4 +587,65 @@ mod tests {
         assert!(spans[0].format().is_italic());
         assert!(spans[0].format().is_underlined());
     }
+
+    fn italic() -> CoreTextStyle {
+        CoreTextStyle {
+            italic: Some(true),
+            ..CoreTextStyle::default()
+        }
+    }
+
+    #[test]
+    fn test_bold_across_span_boundary() {
+        // "hello world" with "hello" italic; embolden "llo wo".
+        let spans = vec![Span::new(0, 5, italic())];
+        let result = restyle(&spans, 11, 2..8, &FormatChange::SetBold(true));
+        let bold_italic = CoreTextStyle {
+            font_weight: Some(700),
+            italic: Some(true),
+            ..CoreTextStyle::default()
+        };
+        let bold = CoreTextStyle {
+            font_weight: Some(700),
+            ..CoreTextStyle::default()
+        };
+        assert_eq!(
+            result,
+            vec![
+                Span::new(0, 2, italic()),
+                Span::new(2, 5, bold_italic),
+                Span::new(5, 8, bold),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restyle_merges_identical_runs() {
+        let bold = CoreTextStyle {
+            font_weight: Some(700),
+            ..CoreTextStyle::default()
+        };
+        let spans = vec![Span::new(0, 3, bold.clone()), Span::new(6, 9, bold.clone())];
+        let result = restyle(&spans, 9, 3..6, &FormatChange::SetBold(true));
+        assert_eq!(result, vec![Span::new(0, 9, bold)]);
+
+        // Clearing the formatting again leaves no spans behind.
+        let result = restyle(&result, 9, 0..9, &FormatChange::SetBold(false));
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_runs_of_overlapping_spans() {
+        let red = CoreTextStyle {
+            color: Some([255, 0, 0, 255]),
+            ..CoreTextStyle::default()
+        };
+        let spans = vec![Span::new(0, 6, italic()), Span::new(3, 9, red)];
+        let runs = runs(&spans, 10);
+        let ranges: Vec<Range<usize>> = runs.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..3, 3..6, 6..9, 9..10]);
+        assert_eq!(runs[1].1.italic, Some(true));
+        assert_eq!(runs[1].1.color, Some([255, 0, 0, 255]));
+        assert_eq!(runs[3].1, CoreTextStyle::default());
+    }
 }
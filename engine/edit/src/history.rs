@@ -1,78 +1,223 @@
 //! Undo/redo history.
+//!
+//! Typing is undone a word at a time: an insertion that continues the
+//! previous one, made within [`DEFAULT_COALESCE_WINDOW`], joins its undo
+//! group, and so does a deletion that continues a run of backspaces or
+//! forward deletes. Typing suggested while changes are tracked coalesces
+//! the same way, along with the marking of each insertion. Any other
+//! operation, a pause, or a call to
+//! [`History::break_group`] starts a new group.
+//!
+//! The history keeps at most a number of undo steps and a budget of bytes,
+//! measured by [`Operation::size`]; past either, the oldest steps are
+//! dropped. The step just made is always kept, however large, so that it
+//! can be undone.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 use crate::Operation;
 
+/// How long after one edit the next may still join its undo group.
+pub const DEFAULT_COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Undo steps kept unless set otherwise.
+pub const DEFAULT_MAX_STEPS: usize = 1000;
+
+/// Bytes of operations kept unless set otherwise.
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
 /// Edit history for undo/redo.
 #[derive(Debug)]
 pub struct History {
-    /// Undo stack.
-    undo_stack: Vec<UndoGroup>,
+    /// Undo stack, oldest first.
+    undo_stack: VecDeque<UndoGroup>,
     /// Redo stack.
     redo_stack: Vec<UndoGroup>,
-    /// Maximum history size.
-    max_size: usize,
+    /// Most undo steps kept.
+    max_steps: usize,
+    /// Most bytes of operations kept, on both stacks.
+    max_bytes: usize,
+    /// Bytes of operations on both stacks.
+    bytes: usize,
     /// Current group being built.
     current_group: Option<UndoGroup>,
+    /// How many explicit groups are open.
+    group_depth: usize,
+    /// How long after one edit the next may join its group.
+    coalesce_window: Duration,
+    /// When the last group on the undo stack can be extended until, if it
+    /// can.
+    coalesce_until: Option<Instant>,
 }
 
 impl History {
     /// Create a new history.
     pub fn new() -> Self {
         Self {
-            undo_stack: Vec::new(),
+            undo_stack: VecDeque::new(),
             redo_stack: Vec::new(),
-            max_size: 1000,
+            max_steps: DEFAULT_MAX_STEPS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            bytes: 0,
             current_group: None,
+            group_depth: 0,
+            coalesce_window: DEFAULT_COALESCE_WINDOW,
+            coalesce_until: None,
         }
     }
 
+    /// Set how long after one edit the next may join its undo group. A zero
+    /// window disables coalescing.
+    pub fn with_coalesce_window(mut self, window: Duration) -> Self {
+        self.coalesce_window = window;
+        self
+    }
+
+    /// Set the most undo steps kept, at least one.
+    pub fn with_max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = steps.max(1);
+        self.trim();
+        self
+    }
+
+    /// Set the most bytes of operations kept.
+    pub fn with_max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self.trim();
+        self
+    }
+
+    /// Most undo steps kept.
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    /// Most bytes of operations kept.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Bytes of operations held for undo and redo.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Number of steps that can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Number of steps that can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.redo_stack.len()
+    }
+
     /// Push an operation to history.
     pub fn push(&mut self, op: Operation) {
-        // Clear redo stack when new operations are added
-        self.redo_stack.clear();
+        self.push_at(op, Instant::now());
+    }
+
+    /// Push an operation made at `now`.
+    fn push_at(&mut self, op: Operation, now: Instant) {
+        // A new edit forks history: what was undone can no longer be redone.
+        self.clear_redo();
 
         if let Some(group) = &mut self.current_group {
-            group.operations.push(op);
-        } else {
-            self.undo_stack.push(UndoGroup {
-                operations: vec![op],
-            });
+            group.push(op);
+            return;
         }
 
-        // Trim history if too large
-        while self.undo_stack.len() > self.max_size {
-            self.undo_stack.remove(0);
+        let coalesce = self.coalesce_until.is_some_and(|until| now < until)
+            && self
+                .undo_stack
+                .back()
+                .is_some_and(|group| continues(&group.operations, &op));
+        self.coalesce_until = Some(now + self.coalesce_window);
+        if coalesce {
+            if let Some(group) = self.undo_stack.back_mut() {
+                self.bytes += op.size();
+                group.push(op);
+                self.trim();
+                return;
+            }
         }
+        self.push_group(UndoGroup::new(vec![op]));
     }
 
-    /// Start a new undo group.
+    /// Push a finished group onto the undo stack.
+    fn push_group(&mut self, group: UndoGroup) {
+        self.bytes += group.bytes;
+        self.undo_stack.push_back(group);
+        self.trim();
+    }
+
+    /// Drop the oldest undo steps until the history is within its limits,
+    /// keeping the newest step.
+    fn trim(&mut self) {
+        while self.undo_stack.len() > self.max_steps
+            || (self.bytes > self.max_bytes && self.undo_stack.len() > 1)
+        {
+            match self.undo_stack.pop_front() {
+                Some(group) => self.bytes -= group.bytes,
+                None => break,
+            }
+        }
+    }
+
+    /// Discard the steps that can be redone.
+    pub fn clear_redo(&mut self) {
+        for group in self.redo_stack.drain(..) {
+            self.bytes -= group.bytes;
+        }
+    }
+
+    /// Start an undo group: every operation pushed until the matching
+    /// [`end_group`](Self::end_group) is undone in one step.
+    ///
+    /// Groups may be nested; operations are grouped until the outermost one
+    /// ends.
     pub fn begin_group(&mut self) {
-        self.current_group = Some(UndoGroup {
-            operations: Vec::new(),
-        });
+        self.group_depth += 1;
+        if self.current_group.is_none() {
+            self.current_group = Some(UndoGroup::new(Vec::new()));
+        }
     }
 
     /// End the current undo group.
     pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth > 0 {
+            return;
+        }
         if let Some(group) = self.current_group.take() {
             if !group.operations.is_empty() {
-                self.undo_stack.push(group);
+                self.push_group(group);
+                self.coalesce_until = None;
             }
         }
     }
 
+    /// Stop the next operation from joining the last undo group, as when
+    /// the cursor moves.
+    pub fn break_group(&mut self) {
+        self.coalesce_until = None;
+    }
+
     /// Undo the last operation group.
     pub fn undo(&mut self) -> Option<&UndoGroup> {
-        let group = self.undo_stack.pop()?;
+        self.coalesce_until = None;
+        let group = self.undo_stack.pop_back()?;
         self.redo_stack.push(group);
         self.redo_stack.last()
     }
 
     /// Redo the last undone operation group.
     pub fn redo(&mut self) -> Option<&UndoGroup> {
+        self.coalesce_until = None;
         let group = self.redo_stack.pop()?;
-        self.undo_stack.push(group);
-        self.undo_stack.last()
+        self.undo_stack.push_back(group);
+        self.undo_stack.back()
     }
 
     /// Check if undo is available.
@@ -89,7 +234,50 @@ impl History {
     pub fn clear(&mut self) {
         self.undo_stack.clear();
         self.redo_stack.clear();
+        self.bytes = 0;
         self.current_group = None;
+        self.group_depth = 0;
+        self.coalesce_until = None;
+    }
+}
+
+/// Whether `next` continues the typing or deleting done by the last of
+/// `operations`.
+fn continues(operations: &[Operation], next: &Operation) -> bool {
+    let Some((last, before)) = operations.split_last() else {
+        return false;
+    };
+    match (last, next) {
+        // Marking typed text as a suggested insertion, after which typing
+        // goes on from the insertion.
+        (Operation::InsertText { position, text }, Operation::SetSpans { start, end, .. }) => {
+            *start == *position && *end == position + text.len()
+        }
+        (Operation::SetSpans { .. }, _) if continues(before, last) => continues(before, next),
+        (
+            Operation::InsertText { position, text },
+            Operation::InsertText {
+                position: next_position,
+                text: next_text,
+            },
+        ) => {
+            // A new word starts a new group.
+            let word_start =
+                text.ends_with(char::is_whitespace) && !next_text.starts_with(char::is_whitespace);
+            *next_position == position + text.len() && !word_start
+        }
+        (
+            Operation::DeleteText { start, .. },
+            Operation::DeleteText {
+                start: next_start,
+                end: next_end,
+                ..
+            },
+        ) => {
+            // Backspacing leftwards, or deleting forwards in place.
+            next_end == start || next_start == start
+        }
+        _ => false,
     }
 }
 
@@ -104,4 +292,198 @@ impl Default for History {
 pub struct UndoGroup {
     /// Operations in this group.
     pub operations: Vec<Operation>,
+    /// Total size of the operations.
+    bytes: usize,
+}
+
+impl UndoGroup {
+    fn new(operations: Vec<Operation>) -> Self {
+        Self {
+            bytes: operations.iter().map(Operation::size).sum(),
+            operations,
+        }
+    }
+
+    fn push(&mut self, op: Operation) {
+        self.bytes += op.size();
+        self.operations.push(op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(position: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            position,
+            text: text.to_string(),
+        }
+    }
+
+    fn delete(start: usize, end: usize) -> Operation {
+        Operation::DeleteText {
+            start,
+            end,
+            deleted: String::new(),
+        }
+    }
+
+    fn undo_len(history: &mut History) -> Option<usize> {
+        history.undo().map(|group| group.operations.len())
+    }
+
+    #[test]
+    fn test_typing_coalesces_by_word() {
+        let mut history = History::new();
+        for (position, c) in "hi there".char_indices() {
+            history.push(insert(position, &c.to_string()));
+        }
+        assert_eq!(undo_len(&mut history), Some(5));
+        assert_eq!(undo_len(&mut history), Some(3));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_suggested_typing_coalesces_by_word() {
+        let mark = |start: usize, end: usize| Operation::SetSpans {
+            start,
+            end,
+            old_spans: Vec::new(),
+            new_spans: Vec::new(),
+        };
+        let mut history = History::new();
+        for (position, c) in "hi there".char_indices() {
+            history.push(insert(position, &c.to_string()));
+            history.push(mark(position, position + 1));
+        }
+        assert_eq!(undo_len(&mut history), Some(10));
+        assert_eq!(undo_len(&mut history), Some(6));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_deletion_runs_coalesce() {
+        let mut history = History::new();
+        history.push(delete(4, 5));
+        history.push(delete(3, 4));
+        history.push(delete(2, 3));
+        // Switching to inserting breaks the run.
+        history.push(insert(2, "x"));
+        history.push(delete(3, 4));
+        history.push(delete(3, 4));
+        assert_eq!(undo_len(&mut history), Some(2));
+        assert_eq!(undo_len(&mut history), Some(1));
+        assert_eq!(undo_len(&mut history), Some(3));
+    }
+
+    #[test]
+    fn test_break_group() {
+        let mut history = History::new();
+        history.push(insert(0, "a"));
+        history.break_group();
+        history.push(insert(1, "b"));
+        // Not adjacent: typing somewhere else.
+        history.push(insert(5, "c"));
+        assert_eq!(undo_len(&mut history), Some(1));
+        assert_eq!(undo_len(&mut history), Some(1));
+        assert_eq!(undo_len(&mut history), Some(1));
+    }
+
+    #[test]
+    fn test_pause_breaks_group() {
+        let mut history = History::new();
+        let start = Instant::now();
+        history.push_at(insert(0, "a"), start);
+        history.push_at(insert(1, "b"), start + Duration::from_millis(500));
+        history.push_at(insert(2, "c"), start + Duration::from_secs(2));
+        assert_eq!(undo_len(&mut history), Some(1));
+        assert_eq!(undo_len(&mut history), Some(2));
+
+        let mut history = History::new().with_coalesce_window(Duration::ZERO);
+        history.push(insert(0, "a"));
+        history.push(insert(1, "b"));
+        assert_eq!(undo_len(&mut history), Some(1));
+    }
+
+    #[test]
+    fn test_explicit_groups() {
+        let mut history = History::new();
+        history.push(insert(0, "a"));
+        history.begin_group();
+        history.push(insert(1, "b"));
+        history.begin_group();
+        history.push(delete(0, 1));
+        history.end_group();
+        history.push(insert(5, "c"));
+        history.end_group();
+        // Typing after the group does not join it.
+        history.push(insert(6, "d"));
+        assert_eq!(undo_len(&mut history), Some(1));
+        assert_eq!(undo_len(&mut history), Some(3));
+        assert_eq!(undo_len(&mut history), Some(1));
+    }
+
+    #[test]
+    fn test_step_cap_evicts_oldest() {
+        let mut history = History::new().with_max_steps(2);
+        history.push(insert(0, "a"));
+        history.break_group();
+        history.push(insert(1, "b"));
+        history.break_group();
+        history.push(insert(2, "c"));
+        assert_eq!(history.undo_len(), 2);
+        assert_eq!(history.bytes(), 2 * insert(0, "x").size());
+
+        // The newest steps remain, and redo still follows undo.
+        let text = |group: Option<&UndoGroup>| match group.map(|group| &group.operations[0]) {
+            Some(Operation::InsertText { text, .. }) => text.clone(),
+            _ => String::new(),
+        };
+        assert_eq!(text(history.undo()), "c");
+        assert_eq!(text(history.undo()), "b");
+        assert!(history.undo().is_none());
+        assert_eq!(text(history.redo()), "b");
+        assert_eq!(text(history.redo()), "c");
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn test_byte_cap_counts_pastes() {
+        let paste = insert(0, &"x".repeat(1000));
+        let mut history = History::new().with_max_bytes(paste.size() + 10);
+        history.push(insert(0, "a"));
+        history.break_group();
+        history.push(insert(1, "b"));
+        history.break_group();
+        history.push(paste.clone());
+        // The paste alone fills the budget, so both typed steps go.
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(history.bytes(), paste.size());
+
+        // A step over budget on its own is still kept.
+        history.break_group();
+        history.push(insert(0, &"y".repeat(5000)));
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(undo_len(&mut history), Some(1));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_new_edit_discards_redo() {
+        let mut history = History::new();
+        history.push(insert(0, "a"));
+        history.break_group();
+        history.push(insert(1, "b"));
+        history.undo();
+        assert!(history.can_redo());
+        let bytes = history.bytes();
+
+        history.push(insert(1, "c"));
+        assert!(!history.can_redo());
+        assert_eq!(history.redo_len(), 0);
+        assert!(history.redo().is_none());
+        assert_eq!(history.bytes(), bytes);
+        assert_eq!(history.undo_len(), 2);
+    }
 }
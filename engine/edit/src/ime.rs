@@ -1,4 +1,11 @@
 //! IME (Input Method Editor) support.
+//!
+//! While composing, the input method's uncommitted text, the preedit, is
+//! shown inline at the cursor but is not part of the document: it never
+//! enters the undo history, and deleting within it leaves the document
+//! alone. Only the committed string is inserted, as a single operation.
+
+use crate::boundary;
 
 /// IME composition state.
 #[derive(Debug, Default)]
@@ -7,7 +14,7 @@ pub struct ImeState {
     pub composing: bool,
     /// The current composition string.
     pub composition: String,
-    /// Cursor position within the composition.
+    /// Cursor position within the composition, as a byte offset.
     pub cursor: usize,
 }
 
@@ -26,8 +33,41 @@ impl ImeState {
 
     /// Update composition.
     pub fn update(&mut self, text: &str, cursor: usize) {
+        self.set_preedit(text, cursor);
+    }
+
+    /// Replace the preedit text, placing the cursor at byte offset `cursor`
+    /// within it. The offset is moved back to a grapheme boundary if it
+    /// falls inside one, and clamped to the end of the text.
+    pub fn set_preedit(&mut self, text: &str, cursor: usize) {
+        self.composing = true;
         self.composition = text.to_string();
-        self.cursor = cursor;
+        self.cursor = boundary::snap(text, cursor);
+    }
+
+    /// The preedit text, if composing.
+    pub fn preedit(&self) -> Option<&str> {
+        self.composing.then_some(self.composition.as_str())
+    }
+
+    /// Delete the grapheme cluster before the preedit cursor.
+    pub fn delete_backward(&mut self) {
+        let start = boundary::prev_grapheme(&self.composition, self.cursor);
+        self.composition.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Delete the grapheme cluster after the preedit cursor.
+    pub fn delete_forward(&mut self) {
+        let end = boundary::next_grapheme(&self.composition, self.cursor);
+        self.composition.replace_range(self.cursor..end, "");
+    }
+
+    /// Finish composition with the final `text`, which may differ from the
+    /// preedit, and return it for insertion.
+    pub fn commit(&mut self, text: &str) -> String {
+        self.cancel();
+        text.to_string()
     }
 
     /// End composition.
@@ -49,3 +89,49 @@ impl ImeState {
         self.cursor = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preedit_cursor() {
+        let mut ime = ImeState::new();
+        // "にほん" with the cursor after "に".
+        ime.set_preedit("にほん", 3);
+        assert_eq!(ime.preedit(), Some("にほん"));
+        assert_eq!(ime.cursor, 3);
+
+        // Offsets inside a character or past the end are corrected.
+        ime.set_preedit("にほん", 4);
+        assert_eq!(ime.cursor, 3);
+        ime.set_preedit("にほん", 20);
+        assert_eq!(ime.cursor, 9);
+    }
+
+    #[test]
+    fn test_delete_within_preedit() {
+        let mut ime = ImeState::new();
+        ime.set_preedit("かな", 3);
+        ime.delete_backward();
+        assert_eq!(ime.composition, "な");
+        assert_eq!(ime.cursor, 0);
+        ime.delete_backward();
+        assert_eq!(ime.composition, "な");
+        ime.delete_forward();
+        assert_eq!(ime.composition, "");
+    }
+
+    #[test]
+    fn test_commit_and_cancel() {
+        let mut ime = ImeState::new();
+        ime.set_preedit("にほん", 9);
+        assert_eq!(ime.commit("日本"), "日本");
+        assert!(ime.preedit().is_none());
+
+        ime.set_preedit("か", 3);
+        ime.cancel();
+        assert!(!ime.composing);
+        assert!(ime.preedit().is_none());
+    }
+}
@@ -8,10 +8,18 @@
 //! - Undo/redo history
 //! - IME (Input Method Editor) support
 //! - Clipboard integration
+//! - Autosave and crash recovery
+//! - Conflict-free replicated text for collaboration
+//! - Table editing
+//! - Track changes, with suggestions to accept or reject
 
 #![allow(dead_code, unused_imports, unused_variables)]
 
+pub mod autosave;
+pub mod boundary;
+pub mod buffer;
 pub mod clipboard;
+pub mod crdt;
 pub mod cursor;
 pub mod document;
 pub mod editor;
@@ -21,12 +29,19 @@ pub mod ime;
 pub mod input;
 pub mod operation;
 pub mod paragraph;
+pub mod revision;
+pub mod search;
+pub mod table;
 
+pub use autosave::{Autosave, RecoveryFile};
+pub use clipboard::{Clipboard, ClipboardData, Fragment};
 pub use cursor::{Cursor, Selection};
 pub use editor::Editor;
 pub use history::{History, UndoGroup};
 pub use input::{InputHandler, Key, KeyModifiers, KeyboardEvent, MouseEvent};
 pub use operation::Operation;
+pub use revision::Suggestion;
+pub use search::FindOptions;
 
 /// Result type for edit operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -40,14 +55,23 @@ pub enum Error {
     #[error("Invalid selection")]
     InvalidSelection,
 
+    #[error("Node not found: {0}")]
+    NodeNotFound(uuid::Uuid),
+
     #[error("Nothing to undo")]
     NothingToUndo,
 
     #[error("Nothing to redo")]
     NothingToRedo,
 
+    #[error("Invalid search pattern: {0}")]
+    InvalidPattern(String),
+
     #[error("Clipboard error: {0}")]
     Clipboard(String),
+
+    #[error("Suggestion not found: {0}")]
+    SuggestionNotFound(uuid::Uuid),
 }
 
 /// Edit session managing document state and editing.
@@ -1,5 +1,11 @@
 //! Edit operations.
 
+use wolia_core::Node;
+use wolia_core::style::ParagraphStyle;
+use wolia_core::text::Span;
+
+use crate::buffer;
+
 /// An atomic editing operation.
 #[derive(Debug, Clone)]
 pub enum Operation {
@@ -19,6 +25,33 @@ pub enum Operation {
         old_text: String,
         new_text: String,
     },
+    /// Insert text blocks with their formatting, as when pasting.
+    InsertBlocks { position: usize, blocks: Vec<Node> },
+    /// Insert a block holding no text, such as an image, after the text
+    /// block containing a position.
+    InsertBlock { position: usize, block: Node },
+    /// Remove a block holding no text, inserted after the text block
+    /// containing a position.
+    RemoveBlock { position: usize, block: Node },
+    /// Replace a block, such as a table, with a changed copy that has the
+    /// same ID.
+    ReplaceBlock { old: Box<Node>, new: Box<Node> },
+    /// Replace the formatting of the blocks covering a range.
+    SetSpans {
+        start: usize,
+        end: usize,
+        /// Spans of each block before the change (for undo).
+        old_spans: Vec<Vec<Span>>,
+        new_spans: Vec<Vec<Span>>,
+    },
+    /// Replace the paragraph formatting of the blocks covering a range.
+    SetParagraphStyles {
+        start: usize,
+        end: usize,
+        /// Formatting of each block before the change (for undo).
+        old_styles: Vec<ParagraphStyle>,
+        new_styles: Vec<ParagraphStyle>,
+    },
     /// Apply formatting to a range.
     Format {
         start: usize,
@@ -70,6 +103,48 @@ impl Operation {
                 old_text: new_text.clone(),
                 new_text: old_text.clone(),
             },
+            Operation::InsertBlocks { position, blocks } => {
+                let text = buffer::blocks_text(blocks);
+                Operation::DeleteText {
+                    start: *position,
+                    end: position + text.len(),
+                    deleted: text,
+                }
+            }
+            Operation::InsertBlock { position, block } => Operation::RemoveBlock {
+                position: *position,
+                block: block.clone(),
+            },
+            Operation::RemoveBlock { position, block } => Operation::InsertBlock {
+                position: *position,
+                block: block.clone(),
+            },
+            Operation::ReplaceBlock { old, new } => Operation::ReplaceBlock {
+                old: new.clone(),
+                new: old.clone(),
+            },
+            Operation::SetSpans {
+                start,
+                end,
+                old_spans,
+                new_spans,
+            } => Operation::SetSpans {
+                start: *start,
+                end: *end,
+                old_spans: new_spans.clone(),
+                new_spans: old_spans.clone(),
+            },
+            Operation::SetParagraphStyles {
+                start,
+                end,
+                old_styles,
+                new_styles,
+            } => Operation::SetParagraphStyles {
+                start: *start,
+                end: *end,
+                old_styles: new_styles.clone(),
+                new_styles: old_styles.clone(),
+            },
             Operation::Format { .. } => {
                 // TODO: Store original formatting for proper undo
                 self.clone()
@@ -102,6 +177,165 @@ impl Operation {
             },
         }
     }
+
+    /// Approximate memory the operation holds, in bytes. Text and block
+    /// content count in full, so a large paste weighs as much as it holds.
+    pub fn size(&self) -> usize {
+        let spans = |blocks: &[Vec<Span>]| {
+            blocks
+                .iter()
+                .map(|spans| size_of::<Vec<Span>>() + spans.len() * size_of::<Span>())
+                .sum::<usize>()
+        };
+        let held = match self {
+            Operation::InsertText { text, .. } => text.len(),
+            Operation::DeleteText { deleted, .. } => deleted.len(),
+            Operation::ReplaceText {
+                old_text, new_text, ..
+            } => old_text.len() + new_text.len(),
+            Operation::InsertBlocks { blocks, .. } => {
+                blocks.len() * size_of::<Node>() + buffer::blocks_text(blocks).len()
+            }
+            Operation::InsertBlock { .. } | Operation::RemoveBlock { .. } => size_of::<Node>(),
+            Operation::ReplaceBlock { .. } => 2 * size_of::<Node>(),
+            Operation::SetSpans {
+                old_spans,
+                new_spans,
+                ..
+            } => spans(old_spans) + spans(new_spans),
+            Operation::SetParagraphStyles {
+                old_styles,
+                new_styles,
+                ..
+            } => (old_styles.len() + new_styles.len()) * size_of::<ParagraphStyle>(),
+            Operation::Format { style_changes, .. } => {
+                style_changes.len() * size_of::<StyleChange>()
+            }
+            Operation::InsertNode { node_data, .. } | Operation::DeleteNode { node_data, .. } => {
+                node_data.len()
+            }
+            Operation::MoveNode { .. } => 0,
+        };
+        size_of::<Operation>() + held
+    }
+
+    /// Rebase this operation onto a document that `against` has already
+    /// been applied to, where both were made against the same earlier
+    /// state.
+    ///
+    /// Applying `against` and then `self.transform(against)` gives the same
+    /// text as applying `self` and then `against.transform(self)`. Two
+    /// insertions at the same offset are ordered by their text, so both
+    /// sides agree on which comes first. A deletion that an insertion lands
+    /// inside cannot be split into one operation, so it becomes a
+    /// replacement of the widened range with the inserted text. Only
+    /// insertions and deletions are transformed; other operations are
+    /// returned unchanged.
+    pub fn transform(&self, against: &Operation) -> Operation {
+        match (self, against) {
+            (
+                Operation::InsertText { position, text },
+                Operation::InsertText {
+                    position: other,
+                    text: other_text,
+                },
+            ) => {
+                let after = other < position || (other == position && other_text <= text);
+                Operation::InsertText {
+                    position: if after {
+                        position + other_text.len()
+                    } else {
+                        *position
+                    },
+                    text: text.clone(),
+                }
+            }
+            (
+                Operation::InsertText { position, text },
+                Operation::DeleteText { start, end, .. },
+            ) => Operation::InsertText {
+                position: shift_past_delete(*position, *start, *end),
+                text: text.clone(),
+            },
+            (
+                Operation::DeleteText {
+                    start,
+                    end,
+                    deleted,
+                },
+                Operation::InsertText { position, text },
+            ) => {
+                if position <= start {
+                    Operation::DeleteText {
+                        start: start + text.len(),
+                        end: end + text.len(),
+                        deleted: deleted.clone(),
+                    }
+                } else if position >= end {
+                    self.clone()
+                } else {
+                    let split = position - start;
+                    let old_text =
+                        if deleted.len() == end - start && deleted.is_char_boundary(split) {
+                            format!("{}{}{}", &deleted[..split], text, &deleted[split..])
+                        } else {
+                            String::new()
+                        };
+                    Operation::ReplaceText {
+                        start: *start,
+                        end: end + text.len(),
+                        old_text,
+                        new_text: text.clone(),
+                    }
+                }
+            }
+            (
+                Operation::DeleteText {
+                    start,
+                    end,
+                    deleted,
+                },
+                Operation::DeleteText {
+                    start: other_start,
+                    end: other_end,
+                    ..
+                },
+            ) => {
+                // Whatever `against` deleted is already gone.
+                let overlap = (*start).max(*other_start)..(*end).min(*other_end);
+                let deleted = if overlap.start < overlap.end
+                    && deleted.len() == end - start
+                    && deleted.is_char_boundary(overlap.start - start)
+                    && deleted.is_char_boundary(overlap.end - start)
+                {
+                    format!(
+                        "{}{}",
+                        &deleted[..overlap.start - start],
+                        &deleted[overlap.end - start..]
+                    )
+                } else {
+                    deleted.clone()
+                };
+                Operation::DeleteText {
+                    start: shift_past_delete(*start, *other_start, *other_end),
+                    end: shift_past_delete(*end, *other_start, *other_end),
+                    deleted,
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Where `offset` ends up once `start..end` has been deleted.
+fn shift_past_delete(offset: usize, start: usize, end: usize) -> usize {
+    if offset <= start {
+        offset
+    } else if offset >= end {
+        offset - (end - start)
+    } else {
+        start
+    }
 }
 
 /// A style change.
@@ -110,3 +344,107 @@ pub struct StyleChange {
     pub property: String,
     pub value: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(position: usize, text: &str) -> Operation {
+        Operation::InsertText {
+            position,
+            text: text.to_string(),
+        }
+    }
+
+    fn delete(start: usize, end: usize, deleted: &str) -> Operation {
+        Operation::DeleteText {
+            start,
+            end,
+            deleted: deleted.to_string(),
+        }
+    }
+
+    fn apply(text: &str, op: &Operation) -> String {
+        let mut text = text.to_string();
+        match op {
+            Operation::InsertText { position, text: t } => text.insert_str(*position, t),
+            Operation::DeleteText { start, end, .. } => text.replace_range(*start..*end, ""),
+            Operation::ReplaceText {
+                start,
+                end,
+                new_text,
+                ..
+            } => text.replace_range(*start..*end, new_text),
+            _ => unreachable!(),
+        }
+        text
+    }
+
+    /// Apply `a` and `b` to `text` in both orders, transforming the second
+    /// against the first, and check both give `expected`.
+    fn assert_converges(text: &str, a: &Operation, b: &Operation, expected: &str) {
+        assert_eq!(apply(&apply(text, a), &b.transform(a)), expected);
+        assert_eq!(apply(&apply(text, b), &a.transform(b)), expected);
+    }
+
+    #[test]
+    fn test_insert_before_insert() {
+        assert_converges("abcdef", &insert(1, "X"), &insert(4, "Y"), "aXbcdYef");
+        assert_converges("abc", &insert(1, "X"), &insert(1, "Y"), "aXYbc");
+        assert_converges("abc", &insert(1, "X"), &insert(1, "X"), "aXXbc");
+    }
+
+    #[test]
+    fn test_delete_before_insert() {
+        assert_converges("abcdef", &delete(0, 2, "ab"), &insert(4, "X"), "cdXef");
+        assert_converges("abcdef", &delete(4, 6, "ef"), &insert(1, "X"), "aXbcd");
+        assert_converges("abcdef", &delete(2, 4, "cd"), &insert(2, "X"), "abXef");
+        assert_converges("abcdef", &delete(2, 4, "cd"), &insert(4, "X"), "abXef");
+    }
+
+    #[test]
+    fn test_insert_inside_delete() {
+        let del = delete(1, 5, "bcde");
+        let ins = insert(3, "XY");
+        assert_converges("abcdef", &del, &ins, "aXYf");
+        match del.transform(&ins) {
+            Operation::ReplaceText {
+                start,
+                end,
+                old_text,
+                new_text,
+            } => {
+                assert_eq!((start, end), (1, 7));
+                assert_eq!(old_text, "bcXYde");
+                assert_eq!(new_text, "XY");
+            }
+            op => panic!("expected a replacement, got {op:?}"),
+        }
+    }
+
+    #[test]
+    fn test_overlapping_deletes() {
+        assert_converges(
+            "abcdefgh",
+            &delete(1, 5, "bcde"),
+            &delete(3, 7, "defg"),
+            "ah",
+        );
+        assert_converges(
+            "abcdefgh",
+            &delete(1, 7, "bcdefg"),
+            &delete(3, 5, "de"),
+            "ah",
+        );
+        assert_converges(
+            "abcdefgh",
+            &delete(2, 4, "cd"),
+            &delete(2, 4, "cd"),
+            "abefgh",
+        );
+        assert!(matches!(
+            delete(1, 5, "bcde").transform(&delete(3, 7, "defg")),
+            Operation::DeleteText { start: 1, end: 3, deleted } if deleted == "bc"
+        ));
+    }
+}
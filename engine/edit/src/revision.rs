@@ -0,0 +1,131 @@
+//! Suggested changes, recorded while changes are tracked.
+//!
+//! A suggestion marks text with a [`Revision`] in its formatting instead of
+//! changing it: text suggested for insertion is in the document until the
+//! suggestion is rejected, and text suggested for deletion stays, struck
+//! through, until it is accepted. The text of one suggestion may be split
+//! into several ranges, across blocks or around other text, which share
+//! its ID.
+//!
+//! Text is part of at most one suggestion, so suggestions never nest or
+//! overlap. Deleting text suggested for insertion removes it outright,
+//! whoever suggested it, and deleting text already suggested for deletion
+//! leaves its suggestion as it is.
+
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use wolia_core::Document;
+use wolia_core::style::{Revision, RevisionKind};
+
+use crate::{Result, boundary, buffer, format};
+
+/// A suggested change and the text it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The change, as the text is marked with it.
+    pub revision: Revision,
+    /// Ranges of the plain text the change covers, in order.
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// A run of text that is part of one suggestion, or of none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    /// Range of the plain text.
+    pub range: Range<usize>,
+    /// The suggestion the text is part of.
+    pub revision: Option<Revision>,
+    /// Whether the text can be marked as part of a suggestion. Code blocks
+    /// hold no formatting, so edits to them are made directly.
+    pub markable: bool,
+}
+
+/// Every suggestion in `document`, in the order they start.
+pub fn suggestions(document: &Document) -> Vec<Suggestion> {
+    let len = buffer::text(document).len();
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for run in runs(document, 0..len).unwrap_or_default() {
+        let Some(revision) = run.revision else {
+            continue;
+        };
+        let range = run.range;
+        match suggestions
+            .iter_mut()
+            .find(|suggestion| suggestion.revision.id == revision.id)
+        {
+            Some(suggestion) => match suggestion.ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => suggestion.ranges.push(range),
+            },
+            None => suggestions.push(Suggestion {
+                revision,
+                ranges: vec![range],
+            }),
+        }
+    }
+    suggestions
+}
+
+/// The suggestion the text at `position` is part of. The text after
+/// `position` is looked at first, then the text before it.
+pub fn suggestion_at(document: &Document, position: usize) -> Option<Suggestion> {
+    let text = buffer::text(document);
+    let after = position..boundary::next_grapheme(&text, position);
+    let before = boundary::prev_grapheme(&text, position)..position;
+    let id = [after, before]
+        .into_iter()
+        .filter_map(|range| runs(document, range).ok()?.into_iter().next()?.revision)
+        .next()?
+        .id;
+    suggestions(document)
+        .into_iter()
+        .find(|suggestion| suggestion.revision.id == id)
+}
+
+/// The runs of the text in `range`, in order. Runs do not cross blocks.
+pub fn runs(document: &Document, range: Range<usize>) -> Result<Vec<Run>> {
+    let blocks = buffer::spans(document, range.clone())?;
+    let mut start = range.start - blocks.first().map_or(0, |block| block.range.start);
+    let mut runs = Vec::new();
+    for block in blocks {
+        for (run, style) in format::runs(&block.spans, block.len) {
+            let run = run.start.max(block.range.start)..run.end.min(block.range.end);
+            if run.start < run.end {
+                runs.push(Run {
+                    range: start + run.start..start + run.end,
+                    revision: style.revision,
+                    markable: block.formatted,
+                });
+            }
+        }
+        start += block.len + 1;
+    }
+    Ok(runs)
+}
+
+/// The suggestion of `kind` by `author` that the text just before or just
+/// after `range` is part of, which text marked in `range` joins, so that a
+/// word typed or deleted a character at a time is one suggestion.
+pub fn adjoining(
+    document: &Document,
+    range: Range<usize>,
+    kind: RevisionKind,
+    author: &str,
+) -> Option<Revision> {
+    let text = buffer::text(document);
+    let before = boundary::prev_grapheme(&text, range.start)..range.start;
+    let after = range.end..boundary::next_grapheme(&text, range.end);
+    [before, after]
+        .into_iter()
+        .filter_map(|range| runs(document, range).ok()?.into_iter().next()?.revision)
+        .find(|revision| revision.kind == kind && revision.author == author)
+}
+
+/// The time now, in milliseconds since the Unix epoch, to date suggestions
+/// with.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
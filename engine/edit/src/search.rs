@@ -0,0 +1,245 @@
+//! Find and replace.
+//!
+//! Every query is compiled to a regular expression: plain queries are
+//! escaped first, and whole-word queries are wrapped in word boundaries.
+//! Matches never overlap and empty matches are ignored. Replacements are
+//! returned as [`Operation::ReplaceText`] operations ordered from the end of
+//! the text to the start, so applying them in order never shifts the offsets
+//! of the ones still to come.
+
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{Error, Operation, Result};
+
+/// How a query is matched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    /// Match letters regardless of case.
+    pub case_insensitive: bool,
+    /// Only match whole words.
+    pub whole_word: bool,
+    /// Treat the query as a regular expression, whose capture groups the
+    /// replacement can refer to as `$1`, `$name` and so on.
+    pub regex: bool,
+}
+
+/// Byte ranges of every match of `query` in `text`, in order.
+pub fn find_all(text: &str, query: &str, options: FindOptions) -> Result<Vec<Range<usize>>> {
+    let Some(pattern) = compile(query, options)? else {
+        return Ok(Vec::new());
+    };
+    Ok(pattern
+        .find_iter(text)
+        .filter(|found| !found.is_empty())
+        .map(|found| found.range())
+        .collect())
+}
+
+/// Operations replacing every match of `query` in `text`, from last to
+/// first.
+pub fn replace_all(
+    text: &str,
+    query: &str,
+    replacement: &str,
+    options: FindOptions,
+) -> Result<Vec<Operation>> {
+    let Some(pattern) = compile(query, options)? else {
+        return Ok(Vec::new());
+    };
+    let mut operations: Vec<Operation> = pattern
+        .captures_iter(text)
+        .filter_map(|captures| replace(&captures, replacement, options))
+        .collect();
+    operations.reverse();
+    Ok(operations)
+}
+
+/// The operation replacing the first match of `query` that starts at or
+/// after `from`, wrapping around to the start of the text if there is none.
+pub fn replace_next(
+    text: &str,
+    query: &str,
+    replacement: &str,
+    options: FindOptions,
+    from: usize,
+) -> Result<Option<Operation>> {
+    let Some(pattern) = compile(query, options)? else {
+        return Ok(None);
+    };
+    let mut operations = pattern
+        .captures_iter(text)
+        .filter_map(|captures| replace(&captures, replacement, options));
+    let first = operations.next();
+    Ok(first
+        .iter()
+        .cloned()
+        .chain(operations)
+        .find(|operation| start(operation) >= from)
+        .or(first))
+}
+
+/// Compile a query, or `None` if it is empty.
+fn compile(query: &str, options: FindOptions) -> Result<Option<Regex>> {
+    if query.is_empty() {
+        return Ok(None);
+    }
+    let mut pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{pattern})\b");
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .map(Some)
+        .map_err(|error| Error::InvalidPattern(error.to_string()))
+}
+
+fn replace(
+    captures: &regex::Captures<'_>,
+    replacement: &str,
+    options: FindOptions,
+) -> Option<Operation> {
+    let found = captures.get(0).filter(|found| !found.is_empty())?;
+    let new_text = if options.regex {
+        let mut expanded = String::new();
+        captures.expand(replacement, &mut expanded);
+        expanded
+    } else {
+        replacement.to_string()
+    };
+    Some(Operation::ReplaceText {
+        start: found.start(),
+        end: found.end(),
+        old_text: found.as_str().to_string(),
+        new_text,
+    })
+}
+
+fn start(operation: &Operation) -> usize {
+    match operation {
+        Operation::ReplaceText { start, .. } => *start,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply replacement operations to `text` in order.
+    fn apply(text: &str, operations: &[Operation]) -> String {
+        let mut text = text.to_string();
+        for operation in operations {
+            if let Operation::ReplaceText {
+                start,
+                end,
+                new_text,
+                ..
+            } = operation
+            {
+                text.replace_range(*start..*end, new_text);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn test_matches_do_not_overlap() {
+        let ranges = find_all("aaaaa", "aa", FindOptions::default()).unwrap();
+        assert_eq!(ranges, vec![0..2, 2..4]);
+        assert!(
+            find_all("abc", "", FindOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_case_and_literal_queries() {
+        let text = "Cat cat CAT c.t";
+        assert_eq!(
+            find_all(text, "cat", FindOptions::default()).unwrap(),
+            vec![4..7]
+        );
+        let options = FindOptions {
+            case_insensitive: true,
+            ..FindOptions::default()
+        };
+        assert_eq!(find_all(text, "cat", options).unwrap().len(), 3);
+        // Without the regex option, "." is literal.
+        assert_eq!(
+            find_all(text, "c.t", FindOptions::default()).unwrap(),
+            vec![12..15]
+        );
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let text = "cat concat cats cat.";
+        let options = FindOptions {
+            whole_word: true,
+            ..FindOptions::default()
+        };
+        assert_eq!(find_all(text, "cat", options).unwrap(), vec![0..3, 16..19]);
+    }
+
+    #[test]
+    fn test_replace_all_back_to_front() {
+        let text = "one two one";
+        let operations = replace_all(text, "one", "three", FindOptions::default()).unwrap();
+        assert_eq!(start(&operations[0]), 8);
+        assert_eq!(apply(text, &operations), "three two three");
+    }
+
+    #[test]
+    fn test_regex_capture_groups() {
+        let options = FindOptions {
+            regex: true,
+            ..FindOptions::default()
+        };
+        let text = "2024-03-15 and 1999-12-31";
+        let operations =
+            replace_all(text, r"(\d{4})-(\d{2})-(\d{2})", "$3/$2/$1", options).unwrap();
+        assert_eq!(apply(text, &operations), "15/03/2024 and 31/12/1999");
+
+        // Outside regex mode "$1" is literal.
+        let operations = replace_all("a", "a", "$1", FindOptions::default()).unwrap();
+        assert_eq!(apply("a", &operations), "$1");
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let options = FindOptions {
+            regex: true,
+            ..FindOptions::default()
+        };
+        assert!(matches!(
+            find_all("text", "(unclosed", options),
+            Err(Error::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_replace_next_wraps() {
+        let text = "ab ab ab";
+        let next = |from| {
+            replace_next(text, "ab", "X", FindOptions::default(), from)
+                .unwrap()
+                .map(|operation| start(&operation))
+        };
+        assert_eq!(next(0), Some(0));
+        assert_eq!(next(1), Some(3));
+        assert_eq!(next(7), Some(0));
+        assert!(
+            replace_next(text, "zz", "X", FindOptions::default(), 0)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Tables in the plain-text view of a document.
+//!
+//! Every cell of a table made here holds at least one text block, so each
+//! cell is a range of the document's plain text. A cell is addressed by the
+//! index of its row in the table and its index within the row, which does
+//! not count the columns spanned by cells to its left.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use uuid::Uuid;
+use wolia_core::node::NodeKind;
+use wolia_core::{Document, Node, Text};
+
+use crate::buffer;
+
+/// A table cell holding text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// ID of the table.
+    pub table: Uuid,
+    /// Index of the cell's row.
+    pub row: usize,
+    /// Index of the cell within its row.
+    pub col: usize,
+    /// ID of the cell's first text block.
+    pub block: Uuid,
+    /// The plain text the cell holds.
+    pub range: Range<usize>,
+}
+
+/// A rectangle of selected cells in one table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellSelection {
+    /// ID of the table.
+    pub table: Uuid,
+    /// Selected rows.
+    pub rows: Range<usize>,
+    /// Selected cells of each row.
+    pub cols: Range<usize>,
+}
+
+impl CellSelection {
+    /// Whether `cell` is selected.
+    pub fn contains(&self, cell: &Cell) -> bool {
+        cell.table == self.table && self.rows.contains(&cell.row) && self.cols.contains(&cell.col)
+    }
+}
+
+/// A table of `rows` rows and `cols` columns of empty cells.
+pub fn new_table(rows: usize, cols: usize) -> Node {
+    let mut table = Node::new(NodeKind::Table { rows: 0, cols });
+    for _ in 0..rows {
+        insert_row(&mut table, 0);
+    }
+    table
+}
+
+/// Every cell of every table in `document` that holds text, in document
+/// order. A cell comes before the cells of tables inside it.
+pub fn cells(document: &Document) -> Vec<Cell> {
+    let text_len = buffer::text(document).len();
+    let starts = buffer::block_starts(document);
+    let ranges: HashMap<Uuid, Range<usize>> = starts
+        .iter()
+        .enumerate()
+        .map(|(index, &(id, start))| {
+            // Blocks are separated by a line feed.
+            let end = starts
+                .get(index + 1)
+                .map_or(text_len, |&(_, next)| next - 1);
+            (id, start..end)
+        })
+        .collect();
+    let mut cells = Vec::new();
+    collect_cells(&document.root, &ranges, &mut cells);
+    cells
+}
+
+/// The innermost cell whose text contains `position`, if any.
+pub fn cell_at(document: &Document, position: usize) -> Option<Cell> {
+    cells(document)
+        .into_iter()
+        .rfind(|cell| cell.range.start <= position && position <= cell.range.end)
+}
+
+/// The cells selected between `anchor` and `focus`, if they are in
+/// different cells of the same table.
+pub fn cell_selection(document: &Document, anchor: usize, focus: usize) -> Option<CellSelection> {
+    let (from, to) = (cell_at(document, anchor)?, cell_at(document, focus)?);
+    if from.table != to.table || from == to {
+        return None;
+    }
+    Some(CellSelection {
+        table: from.table,
+        rows: from.row.min(to.row)..from.row.max(to.row) + 1,
+        cols: from.col.min(to.col)..from.col.max(to.col) + 1,
+    })
+}
+
+/// Whether editing `range` of the plain text would take in the line feed
+/// before or after a cell, joining it with the text outside it.
+pub fn crosses_cells(document: &Document, range: &Range<usize>) -> bool {
+    !range.is_empty()
+        && cells(document).iter().any(|cell| {
+            (range.start < cell.range.start && cell.range.start <= range.end)
+                || (range.start <= cell.range.end && cell.range.end < range.end)
+        })
+}
+
+/// The table with `id` in `document`, if any.
+pub fn find_table(document: &Document, id: Uuid) -> Option<&Node> {
+    fn find(node: &Node, id: Uuid) -> Option<&Node> {
+        if node.id == id {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| find(child, id))
+    }
+    find(&document.root, id).filter(|node| matches!(node.kind, NodeKind::Table { .. }))
+}
+
+/// Number of rows of `table`.
+pub fn row_count(table: &Node) -> usize {
+    rows(table).count()
+}
+
+/// Number of columns of `table`: the most cells in any row, or the
+/// declared columns if there are more.
+pub fn column_count(table: &Node) -> usize {
+    let declared = match table.kind {
+        NodeKind::Table { cols, .. } => cols,
+        _ => 0,
+    };
+    rows(table)
+        .map(|row| row_cells(row).count())
+        .fold(declared, usize::max)
+}
+
+/// Insert a row of empty cells before row `index` of `table`, or after the
+/// last row if there is no such row.
+pub fn insert_row(table: &mut Node, index: usize) {
+    let cols = column_count(table).max(1);
+    let mut row = Node::new(NodeKind::TableRow);
+    for _ in 0..cols {
+        row.add_child(empty_cell());
+    }
+    let child = child_index(table, index, |node| matches!(node.kind, NodeKind::TableRow));
+    table.children.insert(child, row);
+    set_size(table);
+}
+
+/// Remove row `index` of `table`, returning whether there was one.
+pub fn remove_row(table: &mut Node, index: usize) -> bool {
+    let Some(child) = nth_child(table, index, |node| matches!(node.kind, NodeKind::TableRow))
+    else {
+        return false;
+    };
+    table.children.remove(child);
+    set_size(table);
+    true
+}
+
+/// Insert an empty cell before cell `index` of every row of `table`, or
+/// after the last cell of rows without one.
+pub fn insert_column(table: &mut Node, index: usize) {
+    for row in rows_mut(table) {
+        let child = child_index(row, index, is_cell);
+        row.children.insert(child, empty_cell());
+    }
+    set_size(table);
+}
+
+/// Remove cell `index` from every row of `table` that has one, returning
+/// whether any had.
+pub fn remove_column(table: &mut Node, index: usize) -> bool {
+    let mut removed = false;
+    for row in rows_mut(table) {
+        if let Some(child) = nth_child(row, index, is_cell) {
+            row.children.remove(child);
+            removed = true;
+        }
+    }
+    set_size(table);
+    removed
+}
+
+fn collect_cells(node: &Node, ranges: &HashMap<Uuid, Range<usize>>, cells: &mut Vec<Cell>) {
+    if !matches!(node.kind, NodeKind::Table { .. }) {
+        for child in &node.children {
+            collect_cells(child, ranges, cells);
+        }
+        return;
+    }
+    for (row_index, row) in rows(node).enumerate() {
+        for (col, cell) in row_cells(row).enumerate() {
+            let mut blocks = Vec::new();
+            collect_block_ids(cell, &mut blocks);
+            let first = blocks.first().and_then(|id| ranges.get(id));
+            let last = blocks.last().and_then(|id| ranges.get(id));
+            if let (Some(first), Some(last)) = (first, last) {
+                cells.push(Cell {
+                    table: node.id,
+                    row: row_index,
+                    col,
+                    block: blocks[0],
+                    range: first.start..last.end,
+                });
+            }
+            collect_cells(cell, ranges, cells);
+        }
+    }
+}
+
+fn collect_block_ids(node: &Node, ids: &mut Vec<Uuid>) {
+    if matches!(
+        node.kind,
+        NodeKind::Paragraph(_) | NodeKind::Heading { .. } | NodeKind::CodeBlock { .. }
+    ) {
+        ids.push(node.id);
+    }
+    for child in &node.children {
+        collect_block_ids(child, ids);
+    }
+}
+
+fn rows(table: &Node) -> impl Iterator<Item = &Node> {
+    table
+        .children
+        .iter()
+        .filter(|node| matches!(node.kind, NodeKind::TableRow))
+}
+
+fn rows_mut(table: &mut Node) -> impl Iterator<Item = &mut Node> {
+    table
+        .children
+        .iter_mut()
+        .filter(|node| matches!(node.kind, NodeKind::TableRow))
+}
+
+fn row_cells(row: &Node) -> impl Iterator<Item = &Node> {
+    row.children.iter().filter(|node| is_cell(node))
+}
+
+fn is_cell(node: &Node) -> bool {
+    matches!(node.kind, NodeKind::TableCell { .. })
+}
+
+fn empty_cell() -> Node {
+    let mut cell = Node::new(NodeKind::TableCell {
+        col_span: 1,
+        row_span: 1,
+    });
+    cell.add_child(Node::paragraph(Text::empty()));
+    cell
+}
+
+/// Index among the children of `node` of the `index`th child that `test`
+/// holds for, if there is one.
+fn nth_child(node: &Node, index: usize, test: fn(&Node) -> bool) -> Option<usize> {
+    node.children
+        .iter()
+        .enumerate()
+        .filter(|(_, child)| test(child))
+        .nth(index)
+        .map(|(child, _)| child)
+}
+
+/// Index among the children of `node` to insert a child at to make it the
+/// `index`th one `test` holds for, or the last if there are fewer.
+fn child_index(node: &Node, index: usize, test: fn(&Node) -> bool) -> usize {
+    nth_child(node, index, test).unwrap_or_else(|| {
+        node.children
+            .iter()
+            .rposition(test)
+            .map_or(node.children.len(), |last| last + 1)
+    })
+}
+
+/// Update the declared size of `table` to its rows and cells.
+fn set_size(table: &mut Node) {
+    let rows = row_count(table);
+    let cols = rows_mut(table)
+        .map(|row| row_cells(row).count())
+        .max()
+        .unwrap_or(0);
+    table.kind = NodeKind::Table { rows, cols };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document_with_table() -> Document {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("Before")));
+        document.root.add_child(new_table(2, 3));
+        document.root.add_child(Node::paragraph(Text::new("After")));
+        document
+    }
+
+    #[test]
+    fn test_cells_are_ranges_of_the_text() {
+        let document = document_with_table();
+        assert_eq!(buffer::text(&document), "Before\n\n\n\n\n\n\nAfter");
+        let cells = cells(&document);
+        assert_eq!(cells.len(), 6);
+        assert_eq!(
+            (cells[4].row, cells[4].col, cells[4].range.clone()),
+            (1, 1, 11..11)
+        );
+
+        assert_eq!(cell_at(&document, 3), None);
+        assert_eq!(cell_at(&document, 8).map(|cell| cell.col), Some(1));
+        assert!(crosses_cells(&document, &(6..7)));
+        assert!(!crosses_cells(&document, &(0..6)));
+
+        let selection = cell_selection(&document, 7, 11).unwrap();
+        assert_eq!((selection.rows, selection.cols), (0..2, 0..2));
+        assert_eq!(cell_selection(&document, 2, 7), None);
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let mut table = new_table(2, 2);
+        insert_row(&mut table, 1);
+        insert_column(&mut table, 5);
+        assert!(matches!(table.kind, NodeKind::Table { rows: 3, cols: 3 }));
+        assert!(remove_column(&mut table, 0));
+        assert!(remove_row(&mut table, 2));
+        assert!(!remove_row(&mut table, 2));
+        assert!(matches!(table.kind, NodeKind::Table { rows: 2, cols: 2 }));
+        assert_eq!((row_count(&table), column_count(&table)), (2, 2));
+    }
+}
@@ -1,8 +1,44 @@
 //! Format detection.
+//!
+//! Formats are recognized by their content: the native container and PDF
+//! by their signatures, Word, Excel and PowerPoint files by the parts in
+//! their ZIP archive, and Markdown and plain text by being UTF-8. Only the
+//! start of a file is needed, plus the ZIP central directory at its end
+//! for archives, so large files are never read in full.
 
-/// Detected file format.
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// How many bytes from the start of a file detection looks at.
+pub const SNIFF_LEN: usize = 64 * 1024;
+
+/// Signature of the native container.
+const WOLIA_MAGIC: &[u8] = b"WOLIA";
+
+/// Signature of a ZIP local file header.
+const ZIP_LOCAL_HEADER: &[u8; 4] = b"PK\x03\x04";
+
+/// Signature of a ZIP central directory header.
+const ZIP_CENTRAL_HEADER: &[u8; 4] = b"PK\x01\x02";
+
+/// Signature of the ZIP end of central directory record.
+const ZIP_END: &[u8; 4] = b"PK\x05\x06";
+
+/// Size of the end of central directory record without its comment.
+const ZIP_END_LEN: usize = 22;
+
+/// The end record sits before a comment of at most this many bytes.
+const ZIP_MAX_COMMENT: usize = u16::MAX as usize;
+
+/// Largest central directory read when detecting from a file. Office files
+/// have a few dozen parts, far below this.
+const MAX_CENTRAL_DIRECTORY: usize = 1024 * 1024;
+
+/// Part every Office Open XML package has.
+const CONTENT_TYPES: &[u8] = b"[Content_Types].xml";
+
+/// A detected file format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DetectedFormat {
+pub enum FormatKind {
     /// Native Wolia format.
     Wolia,
     /// Microsoft Word (.docx).
@@ -17,62 +53,318 @@ pub enum DetectedFormat {
     Markdown,
     /// Plain text.
     PlainText,
-    /// Unknown format.
-    Unknown,
 }
 
-/// Detect the format of a file by examining its contents.
-pub fn detect(data: &[u8]) -> DetectedFormat {
-    // Check for Wolia format
-    if data.starts_with(b"WOLIA") {
-        return DetectedFormat::Wolia;
+impl FormatKind {
+    /// Whether the format is text, which has no signature and so can only
+    /// be told from other text formats by extension.
+    pub fn is_text(self) -> bool {
+        matches!(self, Self::Markdown | Self::PlainText)
+    }
+}
+
+/// Detect the format of a file from its contents, or from as much of its
+/// start as is available.
+pub fn detect(data: &[u8]) -> Option<FormatKind> {
+    let head = &data[..data.len().min(SNIFF_LEN)];
+    if !head.starts_with(ZIP_LOCAL_HEADER) {
+        return detect_head(head);
+    }
+    // Given the whole archive, its central directory lists every part.
+    // Given only its start, the local headers there are all there is.
+    let tail_start = data.len().saturating_sub(ZIP_END_LEN + ZIP_MAX_COMMENT);
+    let central = find_end(&data[tail_start..]).and_then(|(offset, size)| {
+        let start = usize::try_from(offset).ok()?;
+        data.get(start..start.checked_add(usize::try_from(size).ok()?)?)
+    });
+    match central {
+        Some(directory) => classify_ooxml(central_names(directory)),
+        None => classify_ooxml(local_names(head)),
+    }
+}
+
+/// Detect the format of a file, reading only its start and, for ZIP
+/// archives, its central directory.
+pub fn detect_reader<R: Read + Seek>(reader: &mut R) -> io::Result<Option<FormatKind>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    reader
+        .by_ref()
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    if !head.starts_with(ZIP_LOCAL_HEADER) {
+        return Ok(detect_head(&head));
     }
 
-    // Check for ZIP-based formats (OOXML)
-    if data.starts_with(&[0x50, 0x4B, 0x03, 0x04]) {
-        // This is a ZIP file, could be docx, xlsx, pptx
-        // Would need to inspect contents to determine exact type
-        return DetectedFormat::Docx; // Placeholder
+    let len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = len.min((ZIP_END_LEN + ZIP_MAX_COMMENT) as u64);
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = Vec::with_capacity(tail_len as usize);
+    reader.by_ref().take(tail_len).read_to_end(&mut tail)?;
+
+    let Some((offset, size)) = find_end(&tail) else {
+        return Ok(classify_ooxml(local_names(&head)));
+    };
+    if size as usize > MAX_CENTRAL_DIRECTORY || u64::from(offset) + u64::from(size) > len {
+        return Ok(None);
     }
+    reader.seek(SeekFrom::Start(offset.into()))?;
+    let mut directory = Vec::with_capacity(size as usize);
+    reader
+        .by_ref()
+        .take(size.into())
+        .read_to_end(&mut directory)?;
+    Ok(classify_ooxml(central_names(&directory)))
+}
 
-    // Check for PDF
-    if data.starts_with(b"%PDF") {
-        return DetectedFormat::Pdf;
+/// Detect a format other than a ZIP archive from the start of a file.
+fn detect_head(head: &[u8]) -> Option<FormatKind> {
+    if head.starts_with(WOLIA_MAGIC) {
+        return Some(FormatKind::Wolia);
+    }
+    if head.starts_with(b"%PDF") {
+        return Some(FormatKind::Pdf);
     }
+    let text = as_text(head)?;
+    Some(if looks_like_markdown(text) {
+        FormatKind::Markdown
+    } else {
+        FormatKind::PlainText
+    })
+}
 
-    // Check for UTF-8 BOM or plain text
-    if data.starts_with(&[0xEF, 0xBB, 0xBF]) || data.iter().all(|&b| !(128..0xC0).contains(&b)) {
-        // Could be markdown or plain text
-        if looks_like_markdown(data) {
-            return DetectedFormat::Markdown;
+/// Tell Word, Excel and PowerPoint packages apart by their main part, or
+/// failing that by the folder their parts are in. Archives without the
+/// content types part are not Office files.
+fn classify_ooxml<'a>(names: impl Iterator<Item = &'a [u8]>) -> Option<FormatKind> {
+    let (mut office, mut main, mut folder) = (false, None, None);
+    for name in names {
+        match name {
+            CONTENT_TYPES => office = true,
+            b"word/document.xml" => main = Some(FormatKind::Docx),
+            b"xl/workbook.xml" => main = Some(FormatKind::Xlsx),
+            b"ppt/presentation.xml" => main = Some(FormatKind::Pptx),
+            _ if name.starts_with(b"word/") => folder = folder.or(Some(FormatKind::Docx)),
+            _ if name.starts_with(b"xl/") => folder = folder.or(Some(FormatKind::Xlsx)),
+            _ if name.starts_with(b"ppt/") => folder = folder.or(Some(FormatKind::Pptx)),
+            _ => {}
         }
-        return DetectedFormat::PlainText;
     }
+    if !office {
+        return None;
+    }
+    main.or(folder)
+}
 
-    DetectedFormat::Unknown
+/// Find the end of central directory record in the last bytes of an
+/// archive, returning the offset and size of the central directory.
+fn find_end(tail: &[u8]) -> Option<(u32, u32)> {
+    // Search backward, as the comment after the record could contain the
+    // signature.
+    let start = (0..=tail.len().checked_sub(ZIP_END_LEN)?)
+        .rev()
+        .find(|&at| tail[at..].starts_with(ZIP_END))?;
+    let record = &tail[start..];
+    Some((u32_at(record, 16), u32_at(record, 12)))
 }
 
-/// Check if data looks like Markdown.
-fn looks_like_markdown(data: &[u8]) -> bool {
+/// The part names listed in a central directory.
+fn central_names(directory: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let header = directory.get(offset..offset + 46)?;
+        if !header.starts_with(ZIP_CENTRAL_HEADER) {
+            return None;
+        }
+        let (name_len, extra_len, comment_len) =
+            (u16_at(header, 28), u16_at(header, 30), u16_at(header, 32));
+        let name = directory.get(offset + 46..offset + 46 + name_len)?;
+        offset += 46 + name_len + extra_len + comment_len;
+        Some(name)
+    })
+}
+
+/// The part names in the local file headers at the start of an archive,
+/// up to the first that cannot be skipped over.
+fn local_names(head: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut offset = Some(0);
+    std::iter::from_fn(move || {
+        let at = offset?;
+        let header = head.get(at..at + 30)?;
+        if !header.starts_with(ZIP_LOCAL_HEADER) {
+            return None;
+        }
+        let (flags, name_len, extra_len) =
+            (u16_at(header, 6), u16_at(header, 26), u16_at(header, 28));
+        let name = head.get(at + 30..at + 30 + name_len)?;
+        // With a data descriptor the size follows the data instead, so the
+        // next header cannot be found.
+        offset = (flags & 0x08 == 0)
+            .then(|| at + 30 + name_len + extra_len + u32_at(header, 18) as usize);
+        Some(name)
+    })
+}
+
+fn u16_at(data: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([data[at], data[at + 1]]) as usize
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+/// The data as text, if it is UTF-8 without control characters other than
+/// whitespace. A character cut off at the end of the data is allowed, as
+/// the data may be the start of a longer file.
+fn as_text(data: &[u8]) -> Option<&str> {
+    let data = data.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(data);
     let text = match std::str::from_utf8(data) {
-        Ok(s) => s,
-        Err(_) => return false,
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&data[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
     };
+    let binary = text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0C'));
+    (!binary).then_some(text)
+}
 
-    // Look for common Markdown patterns
-    text.contains("# ") || text.contains("## ") || text.contains("```") || text.contains("[](")
+/// Check if text looks like Markdown.
+fn looks_like_markdown(text: &str) -> bool {
+    // Look for common Markdown patterns at the start of lines
+    text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("# ")
+            || line.starts_with("## ")
+            || line.starts_with("```")
+            || line.starts_with("- ")
+            || line.starts_with("> ")
+    }) || text.contains("](")
 }
 
 /// Detect format from file extension.
-pub fn detect_from_extension(extension: &str) -> DetectedFormat {
+pub fn detect_from_extension(extension: &str) -> Option<FormatKind> {
     match extension.to_lowercase().as_str() {
-        "wolia" => DetectedFormat::Wolia,
-        "docx" => DetectedFormat::Docx,
-        "xlsx" => DetectedFormat::Xlsx,
-        "pptx" => DetectedFormat::Pptx,
-        "pdf" => DetectedFormat::Pdf,
-        "md" | "markdown" => DetectedFormat::Markdown,
-        "txt" => DetectedFormat::PlainText,
-        _ => DetectedFormat::Unknown,
+        "wolia" => Some(FormatKind::Wolia),
+        "docx" => Some(FormatKind::Docx),
+        "xlsx" => Some(FormatKind::Xlsx),
+        "pptx" => Some(FormatKind::Pptx),
+        "pdf" => Some(FormatKind::Pdf),
+        "md" | "markdown" => Some(FormatKind::Markdown),
+        "txt" => Some(FormatKind::PlainText),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A ZIP archive of empty stored files with `names`, in order.
+    fn zip(names: &[&str]) -> Vec<u8> {
+        let (mut data, mut directory) = (Vec::new(), Vec::new());
+        for name in names {
+            let offset = data.len() as u32;
+            data.extend_from_slice(ZIP_LOCAL_HEADER);
+            data.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            data.extend_from_slice(&[0; 12]);
+            data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(name.as_bytes());
+
+            directory.extend_from_slice(ZIP_CENTRAL_HEADER);
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let offset = data.len() as u32;
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(ZIP_END);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&(names.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(names.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    fn ooxml(main: &str) -> Vec<u8> {
+        // Shared parts come first, so the order of parts says nothing.
+        zip(&[
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "docProps/core.xml",
+            "customXml/item1.xml",
+            main,
+        ])
+    }
+
+    #[test]
+    fn test_signatures() {
+        assert_eq!(detect(b"WOLIA\x00\x01"), Some(FormatKind::Wolia));
+        assert_eq!(
+            detect(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3"),
+            Some(FormatKind::Pdf)
+        );
+        assert_eq!(detect(b"\x7FELF\x02\x01\x01"), None);
+    }
+
+    #[test]
+    fn test_office_packages() {
+        for (main, kind) in [
+            ("word/document.xml", FormatKind::Docx),
+            ("xl/workbook.xml", FormatKind::Xlsx),
+            ("ppt/presentation.xml", FormatKind::Pptx),
+        ] {
+            let archive = ooxml(main);
+            assert_eq!(detect(&archive), Some(kind), "{main}");
+            let found = detect_reader(&mut Cursor::new(&archive)).unwrap();
+            assert_eq!(found, Some(kind), "{main}");
+        }
+
+        // The start of an archive is enough when its parts are listed
+        // there.
+        let archive = ooxml("ppt/presentation.xml");
+        let head = &archive[..archive.len() - 60];
+        assert_eq!(detect(head), Some(FormatKind::Pptx));
+
+        // An ordinary archive is not an Office file.
+        assert_eq!(detect(&zip(&["word/readme.txt"])), None);
+    }
+
+    #[test]
+    fn test_text_detection() {
+        assert_eq!(detect(b"Just a line."), Some(FormatKind::PlainText));
+        assert_eq!(
+            detect("Café ☕ menu".as_bytes()),
+            Some(FormatKind::PlainText)
+        );
+        assert_eq!(detect(b"# Title\n\nBody"), Some(FormatKind::Markdown));
+        assert_eq!(
+            detect(b"Not a heading: C# and F# code"),
+            Some(FormatKind::PlainText)
+        );
+        // A character cut off by the end of the data is still text.
+        assert_eq!(detect(&"é".as_bytes()[..1]), Some(FormatKind::PlainText));
+        assert_eq!(detect(&[0, 1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_large_text_reads_only_the_start() {
+        // Past the sniffed start the data is not text at all.
+        let mut data = b"# Notes\n".repeat(SNIFF_LEN / 8);
+        data.extend_from_slice(&[0; 1024]);
+        assert_eq!(detect(&data), Some(FormatKind::Markdown));
+        let mut reader = Cursor::new(&data);
+        assert_eq!(
+            detect_reader(&mut reader).unwrap(),
+            Some(FormatKind::Markdown)
+        );
+        assert_eq!(reader.position(), SNIFF_LEN as u64);
     }
 }
@@ -5,12 +5,16 @@
 //! This crate provides:
 //! - Native .wolia format save/load
 //! - Format detection
+//! - A registry of readers and writers by extension and content
 //! - Export interfaces
 
 use wolia_core::Document;
 
 pub mod detect;
 pub mod native;
+pub mod registry;
+
+pub use registry::Registry;
 
 /// Result type for format operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,45 +1,38 @@
 //! Native .wolia format.
+//!
+//! Thin adapter over the `format-wolia` container so that [`crate::WoliaFormat`]
+//! reads and writes real files.
 
 use wolia_core::Document;
 
 use crate::{Error, Result};
 
-/// Magic bytes for the Wolia format.
-const MAGIC: &[u8; 5] = b"WOLIA";
-
-/// Current format version.
-const VERSION: u8 = 1;
-
 /// Read a document from the native format.
 pub fn read(data: &[u8]) -> Result<Document> {
-    // Check magic bytes
-    if data.len() < 6 || &data[0..5] != MAGIC {
-        return Err(Error::Parse("Invalid magic bytes".to_string()));
-    }
-
-    let version = data[5];
-    if version > VERSION {
-        return Err(Error::Parse(format!(
-            "Unsupported format version: {}",
-            version
-        )));
-    }
-
-    // TODO: Implement full deserialization
-    Ok(Document::new())
+    format_wolia::read(data).map_err(Error::from)
 }
 
 /// Write a document to the native format.
-pub fn write(_document: &Document) -> Result<Vec<u8>> {
-    let mut data = Vec::new();
-
-    // Write header
-    data.extend_from_slice(MAGIC);
-    data.push(VERSION);
-
-    // TODO: Implement full serialization
+pub fn write(document: &Document) -> Result<Vec<u8>> {
+    format_wolia::write(document).map_err(Error::from)
+}
 
-    Ok(data)
+impl From<format_wolia::Error> for Error {
+    fn from(error: format_wolia::Error) -> Self {
+        match error {
+            format_wolia::Error::Io(e) => Error::Io(e),
+            format_wolia::Error::Parse(message) => Error::Parse(message),
+            format_wolia::Error::InvalidFormat => {
+                Error::Parse("Not a valid .wolia file".to_string())
+            }
+            e @ format_wolia::Error::UnsupportedVersion { .. } => {
+                Error::UnsupportedFormat(e.to_string())
+            }
+            e @ (format_wolia::Error::PasswordRequired | format_wolia::Error::WrongPassword) => {
+                Error::Parse(e.to_string())
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -50,6 +43,7 @@ mod tests {
     fn test_roundtrip() {
         let doc = Document::new();
         let data = write(&doc).unwrap();
-        let _ = read(&data).unwrap();
+        let loaded = read(&data).unwrap();
+        assert_eq!(loaded.id, doc.id);
     }
 }
@@ -0,0 +1,268 @@
+//! Format registry.
+//!
+//! Maps file extensions and MIME types to the readers and writers that
+//! handle them, so applications can open and save files without knowing
+//! which format crate is behind each one.
+
+use std::path::Path;
+
+use wolia_core::Document;
+
+use crate::detect::{self, FormatKind};
+use crate::{DocumentReader, DocumentWriter, Error, Result, WoliaFormat};
+
+/// Readers and writers by format.
+#[derive(Default)]
+pub struct Registry {
+    readers: Vec<Box<dyn DocumentReader + Send + Sync>>,
+    writers: Vec<Box<dyn DocumentWriter + Send + Sync>>,
+}
+
+impl Registry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the formats this crate provides.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register_reader(WoliaFormat);
+        registry.register_writer(WoliaFormat);
+        registry
+    }
+
+    /// Add a reader. Readers registered later take precedence for the same
+    /// extension.
+    pub fn register_reader(&mut self, reader: impl DocumentReader + Send + Sync + 'static) {
+        self.readers.push(Box::new(reader));
+    }
+
+    /// Add a writer. Writers registered later take precedence for the same
+    /// extension.
+    pub fn register_writer(&mut self, writer: impl DocumentWriter + Send + Sync + 'static) {
+        self.writers.push(Box::new(writer));
+    }
+
+    /// The (name, extension) of every format that can be read, in the
+    /// order they were registered, for building file dialog filters.
+    pub fn reader_formats(&self) -> Vec<(&str, &str)> {
+        self.readers
+            .iter()
+            .map(|reader| (reader.name(), reader.extension()))
+            .collect()
+    }
+
+    /// The (name, extension) of every format that can be written, in the
+    /// order they were registered.
+    pub fn writer_formats(&self) -> Vec<(&str, &str)> {
+        self.writers
+            .iter()
+            .map(|writer| (writer.name(), writer.extension()))
+            .collect()
+    }
+
+    /// The reader for files with `extension`, given with or without the
+    /// leading dot.
+    pub fn reader_for_extension(&self, extension: &str) -> Result<&dyn DocumentReader> {
+        let extension = extension.trim_start_matches('.');
+        self.readers
+            .iter()
+            .rev()
+            .find(|reader| reader.extension().eq_ignore_ascii_case(extension))
+            .map(|reader| reader.as_ref() as &dyn DocumentReader)
+            .ok_or_else(|| Error::UnsupportedFormat(format!(".{}", extension)))
+    }
+
+    /// The writer for files with `extension`, given with or without the
+    /// leading dot.
+    pub fn writer_for_extension(&self, extension: &str) -> Result<&dyn DocumentWriter> {
+        let extension = extension.trim_start_matches('.');
+        self.writers
+            .iter()
+            .rev()
+            .find(|writer| writer.extension().eq_ignore_ascii_case(extension))
+            .map(|writer| writer.as_ref() as &dyn DocumentWriter)
+            .ok_or_else(|| Error::UnsupportedFormat(format!(".{}", extension)))
+    }
+
+    /// The reader for content of `mime_type`.
+    pub fn reader_for_mime(&self, mime_type: &str) -> Result<&dyn DocumentReader> {
+        self.readers
+            .iter()
+            .rev()
+            .find(|reader| reader.mime_type().eq_ignore_ascii_case(mime_type))
+            .map(|reader| reader.as_ref() as &dyn DocumentReader)
+            .ok_or_else(|| Error::UnsupportedFormat(mime_type.to_string()))
+    }
+
+    /// The writer for content of `mime_type`.
+    pub fn writer_for_mime(&self, mime_type: &str) -> Result<&dyn DocumentWriter> {
+        self.writers
+            .iter()
+            .rev()
+            .find(|writer| writer.mime_type().eq_ignore_ascii_case(mime_type))
+            .map(|writer| writer.as_ref() as &dyn DocumentWriter)
+            .ok_or_else(|| Error::UnsupportedFormat(mime_type.to_string()))
+    }
+
+    /// The reader for a file at `path` holding `data`.
+    ///
+    /// Binary formats recognized from their content take precedence over
+    /// the extension, so a misnamed file still opens. Text has no reliable
+    /// signature, so text files go by their extension, and by their
+    /// content only when the extension is unknown.
+    pub fn reader_for(&self, path: &Path, data: &[u8]) -> Result<&dyn DocumentReader> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("");
+        let detected = detect::detect(data);
+        let by_content = || detected.and_then(|kind| self.reader_for_kind(kind));
+        match detected {
+            Some(kind) if !kind.is_text() => {
+                by_content().map_or_else(|| self.reader_for_extension(extension), Ok)
+            }
+            _ => self
+                .reader_for_extension(extension)
+                .or_else(|error| by_content().ok_or(error)),
+        }
+    }
+
+    /// Read the file at `path` holding `data` with the reader for its
+    /// format.
+    pub fn detect_and_read(&self, path: impl AsRef<Path>, data: &[u8]) -> Result<Document> {
+        self.reader_for(path.as_ref(), data)?.read(data)
+    }
+
+    /// The reader whose extension is for `kind`.
+    fn reader_for_kind(&self, kind: FormatKind) -> Option<&dyn DocumentReader> {
+        self.readers
+            .iter()
+            .rev()
+            .find(|reader| detect::detect_from_extension(reader.extension()) == Some(kind))
+            .map(|reader| reader.as_ref() as &dyn DocumentReader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Format;
+
+    /// A reader that makes a document titled with its extension.
+    struct Mock {
+        extension: &'static str,
+        mime_type: &'static str,
+    }
+
+    impl Format for Mock {
+        fn extension(&self) -> &str {
+            self.extension
+        }
+
+        fn mime_type(&self) -> &str {
+            self.mime_type
+        }
+
+        fn name(&self) -> &str {
+            self.extension
+        }
+    }
+
+    impl DocumentReader for Mock {
+        fn read(&self, _data: &[u8]) -> Result<Document> {
+            let mut document = Document::new();
+            document.metadata.title = Some(self.extension.to_string());
+            Ok(document)
+        }
+    }
+
+    fn registry() -> Registry {
+        let mut registry = Registry::with_defaults();
+        for (extension, mime_type) in [
+            ("md", "text/markdown"),
+            ("txt", "text/plain"),
+            (
+                "docx",
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            ),
+        ] {
+            registry.register_reader(Mock {
+                extension,
+                mime_type,
+            });
+        }
+        registry
+    }
+
+    /// Which mock read the document.
+    fn reader_name(document: &Document) -> &str {
+        document.metadata.title.as_deref().unwrap_or("")
+    }
+
+    #[test]
+    fn test_resolves_by_extension() {
+        let registry = registry();
+        assert_eq!(
+            registry.reader_for_extension("MD").unwrap().extension(),
+            "md"
+        );
+        assert_eq!(
+            registry.reader_for_extension(".wolia").unwrap().name(),
+            "Wolia Document"
+        );
+        assert_eq!(
+            registry.reader_for_mime("text/plain").unwrap().extension(),
+            "txt"
+        );
+        assert!(registry.writer_for_extension("wolia").is_ok());
+        assert!(matches!(
+            registry.reader_for_extension("xyz"),
+            Err(Error::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            registry.writer_for_extension("md"),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolves_by_content() {
+        let registry = registry();
+
+        // A Word document saved with the wrong extension.
+        let mut docx = Vec::new();
+        for name in ["[Content_Types].xml", "word/document.xml"] {
+            docx.extend_from_slice(b"PK\x03\x04");
+            docx.extend_from_slice(&[0; 22]);
+            docx.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            docx.extend_from_slice(&[0, 0]);
+            docx.extend_from_slice(name.as_bytes());
+        }
+        let document = registry.detect_and_read("report.txt", &docx).unwrap();
+        assert_eq!(reader_name(&document), "docx");
+
+        // Plain text without any Markdown stays Markdown by its extension,
+        // and text with a heading stays plain text by its extension.
+        let document = registry
+            .detect_and_read("notes.md", b"Just a line.")
+            .unwrap();
+        assert_eq!(reader_name(&document), "md");
+        let document = registry
+            .detect_and_read("a.txt", b"# Not a heading")
+            .unwrap();
+        assert_eq!(reader_name(&document), "txt");
+        let document = registry.detect_and_read("README", b"# Title\n").unwrap();
+        assert_eq!(reader_name(&document), "md");
+
+        // The native format is read whatever the file is called.
+        let native = WoliaFormat.write(&Document::new()).unwrap();
+        assert!(registry.detect_and_read("copy.bin", &native).is_ok());
+
+        assert!(matches!(
+            registry.detect_and_read("data.bin", &[0, 159, 146, 150]),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+//! Bidirectional text.
+//!
+//! Embedding levels are resolved with the Unicode Bidirectional Algorithm
+//! (UAX #9) over the whole paragraph in logical order, which is also the
+//! order lines are broken in. Each line is then split into level runs and
+//! the runs are reordered for display, so a line mixing Arabic or Hebrew
+//! with Latin text and numbers is shown as a sequence of directional runs
+//! from left to right.
+
+use std::ops::Range;
+
+use unicode_bidi::{BidiInfo, Level};
+
+/// Base direction of a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDirection {
+    /// Taken from the first strong directional character, or left-to-right
+    /// if there is none.
+    #[default]
+    Auto,
+    /// Left-to-right.
+    Ltr,
+    /// Right-to-left.
+    Rtl,
+}
+
+/// A directional run of a line, in visual order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisualRun {
+    /// Byte range of the run in the source text.
+    pub range: Range<usize>,
+    /// Whether the run's characters are shown right to left.
+    pub rtl: bool,
+}
+
+/// Resolved embedding levels of some text.
+pub struct BidiText<'a> {
+    info: BidiInfo<'a>,
+}
+
+impl<'a> BidiText<'a> {
+    /// Resolve the embedding levels of `text`, each paragraph of which has
+    /// the given base direction.
+    pub fn new(text: &'a str, direction: TextDirection) -> Self {
+        let level = match direction {
+            TextDirection::Auto => None,
+            TextDirection::Ltr => Some(Level::ltr()),
+            TextDirection::Rtl => Some(Level::rtl()),
+        };
+        Self {
+            info: BidiInfo::new(text, level),
+        }
+    }
+
+    /// Whether the paragraph containing byte `offset` is right-to-left.
+    pub fn is_rtl(&self, offset: usize) -> bool {
+        self.paragraph(offset)
+            .is_some_and(|index| self.info.paragraphs[index].level.is_rtl())
+    }
+
+    /// Whether the text in `line` needs reordering or right-to-left
+    /// placement, rather than being shown as one left-to-right run.
+    pub fn is_mixed(&self, line: Range<usize>) -> bool {
+        self.is_rtl(line.start) || self.info.levels[line].iter().any(Level::is_rtl)
+    }
+
+    /// The directional runs of `line`, from left to right.
+    ///
+    /// `line` should exclude trailing whitespace, which takes the paragraph
+    /// direction rather than forming a run of its own.
+    pub fn visual_runs(&self, line: Range<usize>) -> Vec<VisualRun> {
+        if line.is_empty() {
+            return Vec::new();
+        }
+        let Some(index) = self.paragraph(line.start) else {
+            return Vec::new();
+        };
+        let (levels, runs) = self.info.visual_runs(&self.info.paragraphs[index], line);
+        runs.into_iter()
+            .map(|range| VisualRun {
+                rtl: levels[range.start].is_rtl(),
+                range,
+            })
+            .collect()
+    }
+
+    fn paragraph(&self, offset: usize) -> Option<usize> {
+        self.info
+            .paragraphs
+            .iter()
+            .position(|paragraph| paragraph.range.contains(&offset))
+            .or_else(|| self.info.paragraphs.len().checked_sub(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(text: &str, direction: TextDirection) -> Vec<(&str, bool)> {
+        BidiText::new(text, direction)
+            .visual_runs(0..text.len())
+            .into_iter()
+            .map(|run| (&text[run.range], run.rtl))
+            .collect()
+    }
+
+    #[test]
+    fn test_ltr_paragraph_with_arabic() {
+        let text = "Hello سلام world";
+        assert_eq!(
+            runs(text, TextDirection::Auto),
+            vec![("Hello ", false), ("سلام", true), (" world", false)]
+        );
+        assert!(!BidiText::new(text, TextDirection::Auto).is_rtl(0));
+    }
+
+    #[test]
+    fn test_rtl_paragraph_with_english() {
+        let text = "سلام Hello";
+        let bidi = BidiText::new(text, TextDirection::Auto);
+        assert!(bidi.is_rtl(0));
+        // The English run comes first from the left; the space between the
+        // runs takes the paragraph's direction.
+        assert_eq!(
+            runs(text, TextDirection::Auto),
+            vec![("Hello", false), ("سلام ", true)]
+        );
+    }
+
+    #[test]
+    fn test_numbers_in_arabic() {
+        let text = "العدد 42 هنا";
+        assert_eq!(
+            runs(text, TextDirection::Auto),
+            vec![(" هنا", true), ("42", false), ("العدد ", true)]
+        );
+    }
+
+    #[test]
+    fn test_explicit_direction() {
+        let text = "abc";
+        let bidi = BidiText::new(text, TextDirection::Rtl);
+        assert!(bidi.is_rtl(0));
+        assert!(bidi.is_mixed(0..3));
+        assert!(!BidiText::new(text, TextDirection::Auto).is_mixed(0..3));
+        assert_eq!(runs(text, TextDirection::Rtl), vec![("abc", false)]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Layout cache.
+//!
+//! Laying out a paragraph does not depend on where it ends up, so a layout
+//! can be reused for as long as the paragraph's text, formatting, styles and
+//! constraints stay the same. Entries are kept per node and keyed by a hash
+//! of all of those: an edited paragraph misses and replaces its own entry,
+//! while the blocks after it reuse theirs and are only placed again.
+//!
+//! Entries of nodes no longer in the document are dropped after each pass,
+//! and the cache holds at most its capacity of entries. Changes the key
+//! cannot see, such as to the fonts text is measured with, need an explicit
+//! [`clear`](LayoutCache::clear).
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+use uuid::Uuid;
+use wolia_core::node::Node;
+use wolia_core::style::StyleSheet;
+use wolia_core::text::Text;
+
+use crate::Constraints;
+use crate::paragraph::ParagraphLayout;
+
+/// Entries a cache holds unless told otherwise.
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// Hit and miss counts of a cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Layouts reused.
+    pub hits: usize,
+    /// Layouts computed.
+    pub misses: usize,
+}
+
+/// Paragraph layouts kept between layout passes.
+#[derive(Debug)]
+pub struct LayoutCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<Uuid, Entry>,
+    capacity: usize,
+    /// Number of the current pass.
+    pass: u64,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: u64,
+    layout: ParagraphLayout,
+    /// Last pass the entry was used in.
+    pass: u64,
+}
+
+impl LayoutCache {
+    /// Create a cache holding up to `capacity` paragraph layouts.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                capacity,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Number of layouts held.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Check if no layouts are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Most layouts held at once.
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    /// Drop every layout, as after the fonts change.
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// Hits and misses since the cache was created or the counts reset.
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    /// Reset the hit and miss counts.
+    pub fn reset_stats(&self) {
+        self.lock().stats = CacheStats::default();
+    }
+
+    /// Start a layout pass.
+    pub(crate) fn begin_pass(&self) {
+        self.lock().pass += 1;
+    }
+
+    /// End a layout pass, dropping the layouts of nodes it did not reach.
+    pub(crate) fn end_pass(&self) {
+        let mut inner = self.lock();
+        let pass = inner.pass;
+        inner.entries.retain(|_, entry| entry.pass == pass);
+    }
+
+    /// The layout of node `id` for `key`, computed with `layout` if the
+    /// cache has none.
+    pub(crate) fn paragraph(
+        &self,
+        id: Uuid,
+        key: u64,
+        layout: impl FnOnce() -> ParagraphLayout,
+    ) -> ParagraphLayout {
+        let mut inner = self.lock();
+        let pass = inner.pass;
+        if let Some(entry) = inner.entries.get_mut(&id).filter(|entry| entry.key == key) {
+            entry.pass = pass;
+            let layout = entry.layout.clone();
+            inner.stats.hits += 1;
+            return layout;
+        }
+        inner.stats.misses += 1;
+        drop(inner);
+
+        let computed = layout();
+        let mut inner = self.lock();
+        if inner.entries.len() >= inner.capacity && !inner.entries.contains_key(&id) {
+            // Make room from nodes not reached yet in this pass, which may
+            // be gone; if every entry is in use, leave this node uncached.
+            inner.entries.retain(|_, entry| entry.pass == pass);
+            if inner.entries.len() >= inner.capacity {
+                return computed;
+            }
+        }
+        inner.entries.insert(
+            id,
+            Entry {
+                key,
+                layout: computed.clone(),
+                pass,
+            },
+        );
+        computed
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // A panic mid-update leaves at worst a stale entry, which keys guard.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for LayoutCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// A fingerprint of a style sheet, part of every key laid out under it.
+pub(crate) fn styles_key(styles: &StyleSheet) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_debug(&mut hasher, styles);
+    hasher.finish()
+}
+
+/// The key of a paragraph's layout: its text, its formatting, the style
+/// sheet and the constraints it is laid out under.
+pub(crate) fn paragraph_key(
+    node: &Node,
+    text: &Text,
+    styles: u64,
+    constraints: &Constraints,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    styles.hash(&mut hasher);
+    text.content.hash(&mut hasher);
+    hash_debug(&mut hasher, &text.spans);
+    node.style.hash(&mut hasher);
+    hash_debug(&mut hasher, &node.formatting);
+    hash_debug(&mut hasher, constraints);
+    hasher.finish()
+}
+
+/// Hash a value by its debug output. Styles hold floats, which have no
+/// `Hash`, but print distinctly.
+fn hash_debug(hasher: &mut impl Hasher, value: &impl fmt::Debug) {
+    struct HashWriter<'a, H>(&'a mut H);
+
+    impl<H: Hasher> Write for HashWriter<'_, H> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    let _ = write!(HashWriter(hasher), "{:?}", value);
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::Document;
+    use wolia_core::node::NodeKind;
+    use wolia_core::style::Style;
+    use wolia_math::Size;
+
+    use super::*;
+    use crate::LayoutEngine;
+
+    fn document(paragraphs: &[&str]) -> Document {
+        let mut document = Document::new();
+        for text in paragraphs {
+            document.root.add_child(Node::paragraph(Text::new(*text)));
+        }
+        document
+    }
+
+    fn set_text(document: &mut Document, index: usize, text: &str) {
+        document.root.children[index].kind = NodeKind::Paragraph(Text::new(text));
+    }
+
+    #[test]
+    fn test_unchanged_paragraphs_hit() {
+        let engine = LayoutEngine::new();
+        let mut document = document(&["one", "two", "three"]);
+        let first = engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().stats(), CacheStats { hits: 0, misses: 3 });
+
+        engine.cache().reset_stats();
+        set_text(
+            &mut document,
+            1,
+            "two, edited to be a good deal longer than before",
+        );
+        let second = engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().stats(), CacheStats { hits: 2, misses: 1 });
+        assert_eq!(engine.cache().len(), 3);
+
+        // The third paragraph is reused, but placed below the longer second.
+        let y = |tree: &crate::LayoutTree, index: usize| tree.pages[0].nodes[index].bounds.y;
+        assert_eq!(y(&first, 0), y(&second, 0));
+        assert!(y(&second, 2) >= y(&first, 2));
+    }
+
+    #[test]
+    fn test_formatting_and_constraints_miss() {
+        let mut engine = LayoutEngine::new();
+        let mut document = document(&["one", "two"]);
+        engine.layout(&document).unwrap();
+
+        engine.cache().reset_stats();
+        document.root.children[0].formatting.text.italic = Some(true);
+        engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().stats(), CacheStats { hits: 1, misses: 1 });
+
+        engine.cache().reset_stats();
+        document.styles.insert(Style {
+            name: "Quote".to_string(),
+            ..Default::default()
+        });
+        engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().stats(), CacheStats { hits: 0, misses: 2 });
+
+        engine.cache().reset_stats();
+        engine.page_size = Size::new(300.0, 400.0);
+        engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().stats(), CacheStats { hits: 0, misses: 2 });
+
+        engine.cache().reset_stats();
+        engine.cache().clear();
+        engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[test]
+    fn test_bounded_and_swept() {
+        let engine = LayoutEngine::new().with_cache_capacity(2);
+        let mut document = document(&["one", "two", "three"]);
+        engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().len(), 2);
+
+        // Removed nodes leave the cache with the next pass.
+        document.root.children.truncate(1);
+        engine.layout(&document).unwrap();
+        assert_eq!(engine.cache().len(), 1);
+    }
+}
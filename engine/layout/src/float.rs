@@ -0,0 +1,281 @@
+//! Float positioning.
+//!
+//! A float is a box, such as an image, pushed to the left or right edge of
+//! the region while text flows around it. Floats on the same side stack
+//! beside each other while they fit and below each other once they do not,
+//! and no float is placed higher than one placed before it. Coordinates are
+//! relative to the top-left corner of the region, which is usually what
+//! remains of the current page.
+
+use wolia_math::{Rect, Size};
+
+/// The edge a float is pushed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatSide {
+    Left,
+    Right,
+}
+
+/// Which floats content must be placed below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Clear {
+    /// Flow beside any float.
+    #[default]
+    None,
+    /// Start below every left float.
+    Left,
+    /// Start below every right float.
+    Right,
+    /// Start below every float.
+    Both,
+}
+
+impl Clear {
+    fn applies_to(self, side: FloatSide) -> bool {
+        matches!(
+            (self, side),
+            (Clear::Both, _) | (Clear::Left, FloatSide::Left) | (Clear::Right, FloatSide::Right)
+        )
+    }
+}
+
+/// A box to float.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Float {
+    pub side: FloatSide,
+    /// Size of the box, including any margin around it.
+    pub size: Size,
+}
+
+impl Float {
+    /// A box pushed to the left edge.
+    pub fn left(size: Size) -> Self {
+        Self {
+            side: FloatSide::Left,
+            size,
+        }
+    }
+
+    /// A box pushed to the right edge.
+    pub fn right(size: Size) -> Self {
+        Self {
+            side: FloatSide::Right,
+            size,
+        }
+    }
+}
+
+/// A float that has been given a position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedFloat {
+    pub side: FloatSide,
+    /// Position and size within the region.
+    pub bounds: Rect,
+    /// Whether the float is taller than a whole page and was cut off at the
+    /// bottom of the region.
+    pub clipped: bool,
+}
+
+/// Floats placed in a region, and the space they leave for text.
+#[derive(Debug, Clone)]
+pub struct FloatContext {
+    /// Width of the region.
+    width: f32,
+    /// Height left in the region.
+    height: f32,
+    /// Height of a whole page, which decides whether a float that does not
+    /// fit the region could fit on the next page.
+    page_height: f32,
+    floats: Vec<PlacedFloat>,
+    deferred: Vec<Float>,
+}
+
+impl FloatContext {
+    /// Create a context for a region of the given size, taken to be a
+    /// whole page.
+    pub fn new(size: Size) -> Self {
+        Self {
+            width: size.width,
+            height: size.height,
+            page_height: size.height,
+            floats: Vec::new(),
+            deferred: Vec::new(),
+        }
+    }
+
+    /// Set the height of a whole page, for regions that start partway down
+    /// a page.
+    pub fn with_page_height(mut self, page_height: f32) -> Self {
+        self.page_height = page_height;
+        self
+    }
+
+    /// Floats placed so far, in placement order.
+    pub fn floats(&self) -> &[PlacedFloat] {
+        &self.floats
+    }
+
+    /// Take the floats that did not fit in the region, to be placed at the
+    /// top of the next page.
+    pub fn take_deferred(&mut self) -> Vec<Float> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    /// Place a float as high as possible at or below `y`.
+    ///
+    /// A float is moved down past other floats until it fits beside them.
+    /// If it would then extend below the region it is deferred to the next
+    /// page and `None` is returned, unless it is taller than a whole page,
+    /// in which case waiting would not help: it is placed anyway and cut off
+    /// at the bottom of the region.
+    pub fn place(&mut self, float: Float, y: f32) -> Option<PlacedFloat> {
+        let size = Size::new(float.size.width.min(self.width), float.size.height);
+        let mut top = self.floats.last().map_or(y, |last| y.max(last.bounds.y));
+
+        loop {
+            let (left, right) = self.edges(top, size.height.max(f32::EPSILON));
+            if right - left >= size.width {
+                let x = match float.side {
+                    FloatSide::Left => left,
+                    FloatSide::Right => right - size.width,
+                };
+                return self.commit(float.side, Rect::new(x, top, size.width, size.height));
+            }
+            // Try again below the first float in the way.
+            match self
+                .floats
+                .iter()
+                .filter(|placed| overlaps(placed.bounds, top, size.height))
+                .map(|placed| placed.bounds.bottom())
+                .min_by(f32::total_cmp)
+            {
+                Some(bottom) if bottom > top => top = bottom,
+                _ => return self.commit(float.side, Rect::new(0.0, top, size.width, size.height)),
+            }
+        }
+    }
+
+    fn commit(&mut self, side: FloatSide, bounds: Rect) -> Option<PlacedFloat> {
+        let mut placed = PlacedFloat {
+            side,
+            bounds,
+            clipped: false,
+        };
+        if bounds.bottom() > self.height {
+            if bounds.height <= self.page_height {
+                self.deferred.push(Float {
+                    side,
+                    size: bounds.size(),
+                });
+                return None;
+            }
+            placed.bounds.height = (self.height - bounds.y).max(0.0);
+            placed.clipped = true;
+        }
+        self.floats.push(placed);
+        Some(placed)
+    }
+
+    /// The x offset and width left for content in the band from `y` down
+    /// `height`.
+    pub fn band(&self, y: f32, height: f32) -> (f32, f32) {
+        let (left, right) = self.edges(y, height);
+        (left, (right - left).max(0.0))
+    }
+
+    /// The highest position at or below `y` that is clear of the given
+    /// floats.
+    pub fn clear(&self, clear: Clear, y: f32) -> f32 {
+        self.floats
+            .iter()
+            .filter(|placed| clear.applies_to(placed.side))
+            .map(|placed| placed.bounds.bottom())
+            .fold(y, f32::max)
+    }
+
+    /// Innermost edges of the left and right floats beside the band.
+    fn edges(&self, y: f32, height: f32) -> (f32, f32) {
+        let mut left = 0.0f32;
+        let mut right = self.width;
+        for placed in self
+            .floats
+            .iter()
+            .filter(|placed| overlaps(placed.bounds, y, height))
+        {
+            match placed.side {
+                FloatSide::Left => left = left.max(placed.bounds.right()),
+                FloatSide::Right => right = right.min(placed.bounds.x),
+            }
+        }
+        (left, right.max(left))
+    }
+}
+
+/// Whether `bounds` overlaps the band from `y` down `height`.
+fn overlaps(bounds: Rect, y: f32, height: f32) -> bool {
+    bounds.y < y + height && bounds.bottom() > y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stacks_floats_on_one_side() {
+        let mut floats = FloatContext::new(Size::new(200.0, 500.0));
+        let first = floats
+            .place(Float::left(Size::new(80.0, 40.0)), 0.0)
+            .unwrap();
+        let second = floats
+            .place(Float::left(Size::new(80.0, 20.0)), 0.0)
+            .unwrap();
+        // The third does not fit beside the others and goes below the
+        // shorter one.
+        let third = floats
+            .place(Float::left(Size::new(80.0, 20.0)), 0.0)
+            .unwrap();
+        assert_eq!(first.bounds, Rect::new(0.0, 0.0, 80.0, 40.0));
+        assert_eq!(second.bounds, Rect::new(80.0, 0.0, 80.0, 20.0));
+        assert_eq!(third.bounds, Rect::new(80.0, 20.0, 80.0, 20.0));
+        assert_eq!(floats.band(0.0, 10.0), (160.0, 40.0));
+        assert_eq!(floats.band(40.0, 10.0), (0.0, 200.0));
+    }
+
+    #[test]
+    fn test_left_and_right_floats() {
+        let mut floats = FloatContext::new(Size::new(200.0, 500.0));
+        floats.place(Float::left(Size::new(50.0, 30.0)), 0.0);
+        let right = floats
+            .place(Float::right(Size::new(60.0, 60.0)), 0.0)
+            .unwrap();
+        assert_eq!(right.bounds.x, 140.0);
+        assert_eq!(floats.band(10.0, 10.0), (50.0, 90.0));
+        assert_eq!(floats.band(40.0, 10.0), (0.0, 140.0));
+        assert_eq!(floats.clear(Clear::Left, 0.0), 30.0);
+        assert_eq!(floats.clear(Clear::Both, 0.0), 60.0);
+        assert_eq!(floats.clear(Clear::Right, 100.0), 100.0);
+    }
+
+    #[test]
+    fn test_float_taller_than_remaining_page() {
+        // 100pt left on a 300pt page.
+        let mut floats = FloatContext::new(Size::new(200.0, 100.0)).with_page_height(300.0);
+        assert!(
+            floats
+                .place(Float::left(Size::new(50.0, 150.0)), 0.0)
+                .is_none()
+        );
+        assert_eq!(
+            floats.take_deferred(),
+            vec![Float::left(Size::new(50.0, 150.0))]
+        );
+        assert!(floats.floats().is_empty());
+
+        // Too tall for any page: placed and cut off.
+        let placed = floats
+            .place(Float::right(Size::new(50.0, 400.0)), 20.0)
+            .unwrap();
+        assert!(placed.clipped);
+        assert_eq!(placed.bounds, Rect::new(150.0, 20.0, 50.0, 80.0));
+    }
+}
@@ -0,0 +1,228 @@
+//! Hyphenation.
+//!
+//! Words are hyphenated with Liang's algorithm, as used by TeX: every
+//! pattern found in a word contributes priorities to the gaps between its
+//! letters, the highest priority at each gap wins, and odd priorities mark
+//! allowed breaks. The line breaker only consults these points when a line
+//! would otherwise be left badly underfull.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Hyphenation settings for a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hyphenation {
+    /// Whether words may be hyphenated at all.
+    pub enabled: bool,
+    /// Shortest word, in characters, that may be hyphenated.
+    pub min_word_length: usize,
+    /// Minimum number of characters left before a hyphen.
+    pub min_before: usize,
+    /// Minimum number of characters carried over after a hyphen.
+    pub min_after: usize,
+    /// Fraction of the line width below which a line counts as badly
+    /// underfull. Words are only hyphenated to fill such lines.
+    pub min_fill: f32,
+}
+
+impl Hyphenation {
+    /// Hyphenation turned off.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::english()
+        }
+    }
+
+    /// English hyphenation with the conventional TeX limits.
+    pub fn english() -> Self {
+        Self {
+            enabled: true,
+            min_word_length: 5,
+            min_before: 2,
+            min_after: 3,
+            min_fill: 0.75,
+        }
+    }
+
+    /// Set the shortest word that may be hyphenated.
+    pub fn with_min_word_length(mut self, length: usize) -> Self {
+        self.min_word_length = length;
+        self
+    }
+
+    /// Set the minimum number of characters on either side of a hyphen.
+    pub fn with_min_chars(mut self, before: usize, after: usize) -> Self {
+        self.min_before = before;
+        self.min_after = after;
+        self
+    }
+
+    /// Byte offsets within `token` at which it may be hyphenated.
+    ///
+    /// `token` is a run of text between two line break opportunities, such
+    /// as a word with trailing punctuation and spaces. Only the first run of
+    /// letters in it is hyphenated.
+    pub fn break_points(&self, token: &str) -> Vec<usize> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let Some(word_start) = token.find(char::is_alphabetic) else {
+            return Vec::new();
+        };
+        let word_end = token[word_start..]
+            .find(|c: char| !c.is_alphabetic())
+            .map_or(token.len(), |end| word_start + end);
+        let word = &token[word_start..word_end];
+
+        let length = word.chars().count();
+        if length < self.min_word_length.max(self.min_before + self.min_after) {
+            return Vec::new();
+        }
+
+        let offsets: Vec<usize> = word.char_indices().map(|(offset, _)| offset).collect();
+        Patterns::english()
+            .hyphenate(word)
+            .into_iter()
+            .filter(|&index| index >= self.min_before.max(1) && length - index >= self.min_after)
+            .map(|index| word_start + offsets[index])
+            .collect()
+    }
+}
+
+impl Default for Hyphenation {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+/// A compiled set of Liang patterns.
+struct Patterns {
+    /// Letters of each pattern, mapped to the priorities of the gaps before,
+    /// between and after them.
+    priorities: HashMap<String, Vec<u8>>,
+    /// Length of the longest pattern, in characters.
+    max_length: usize,
+}
+
+impl Patterns {
+    fn english() -> &'static Self {
+        static ENGLISH: OnceLock<Patterns> = OnceLock::new();
+        ENGLISH.get_or_init(|| Self::parse(include_str!("../patterns/hyph-en-us.txt")))
+    }
+
+    fn parse(source: &str) -> Self {
+        let mut priorities = HashMap::new();
+        let mut max_length = 0;
+        for pattern in source
+            .lines()
+            .filter(|line| !line.starts_with('%'))
+            .flat_map(str::split_whitespace)
+        {
+            let mut letters = String::new();
+            let mut values = vec![0];
+            for c in pattern.chars() {
+                match c.to_digit(10) {
+                    Some(digit) => *values.last_mut().unwrap() = digit as u8,
+                    None => {
+                        letters.push(c);
+                        values.push(0);
+                    }
+                }
+            }
+            max_length = max_length.max(letters.chars().count());
+            priorities.insert(letters, values);
+        }
+        Self {
+            priorities,
+            max_length,
+        }
+    }
+
+    /// Character indices in `word` before which a hyphen may go.
+    fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let mut chars = vec!['.'];
+        chars.extend(word.chars().flat_map(char::to_lowercase));
+        chars.push('.');
+        // Lowercasing may change the number of characters; such words are
+        // left alone rather than hyphenated at the wrong places.
+        if chars.len() != word.chars().count() + 2 {
+            return Vec::new();
+        }
+
+        // `gaps[i]` is the priority of the gap before `chars[i]`.
+        let mut gaps = vec![0u8; chars.len() + 1];
+        let mut key = String::new();
+        for start in 0..chars.len() {
+            key.clear();
+            for &c in chars[start..].iter().take(self.max_length) {
+                key.push(c);
+                if let Some(values) = self.priorities.get(&key) {
+                    for (offset, &value) in values.iter().enumerate() {
+                        let gap = &mut gaps[start + offset];
+                        *gap = (*gap).max(value);
+                    }
+                }
+            }
+        }
+
+        // Gap `i + 1` sits before character `i` of the word itself.
+        (1..word.chars().count())
+            .filter(|&index| gaps[index + 1] % 2 == 1)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syllables(word: &str, hyphenation: Hyphenation) -> Vec<&str> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for point in hyphenation.break_points(word) {
+            pieces.push(&word[start..point]);
+            start = point;
+        }
+        pieces.push(&word[start..]);
+        pieces
+    }
+
+    #[test]
+    fn test_liang_example() {
+        assert_eq!(
+            syllables("hyphenation", Hyphenation::english()),
+            vec!["hy", "phen", "ation"]
+        );
+        assert_eq!(
+            syllables("Hyphenation,", Hyphenation::english()),
+            vec!["Hy", "phen", "ation,"]
+        );
+    }
+
+    #[test]
+    fn test_respects_limits() {
+        assert_eq!(
+            syllables("hyphenation", Hyphenation::english().with_min_chars(3, 3)),
+            vec!["hyphen", "ation"]
+        );
+        assert!(
+            Hyphenation::english()
+                .with_min_word_length(12)
+                .break_points("hyphenation")
+                .is_empty()
+        );
+        assert!(
+            Hyphenation::disabled()
+                .break_points("hyphenation")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_non_words() {
+        let hyphenation = Hyphenation::english();
+        assert!(hyphenation.break_points("").is_empty());
+        assert!(hyphenation.break_points("1234567890").is_empty());
+    }
+}
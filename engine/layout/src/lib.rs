@@ -3,26 +3,52 @@
 //! Layout and pagination engine for the Wolia platform.
 //!
 //! This crate handles:
-//! - Text wrapping and line breaking
+//! - Text measurement with font metrics
+//! - Text wrapping, line breaking and hyphenation
+//! - Tab stops
 //! - Paragraph layout
 //! - Page layout and pagination
 //! - Table layout
 //! - Float positioning
 
+pub mod bidi;
+pub mod cache;
+pub mod float;
+pub mod hyphenate;
 pub mod line;
+pub mod linebreak;
+pub mod measure;
 pub mod page;
+pub mod paginate;
 pub mod paragraph;
+pub mod table;
+pub mod tabs;
 pub mod text;
 pub mod tree;
 
+use std::collections::HashMap;
+
 use wolia_core::Document;
+use wolia_core::node::{Node, NodeKind};
+use wolia_core::text::Text;
 use wolia_math::{Rect, Size};
+use wolia_plugin::{BlockRegistry, BlockSource, RenderedBlock};
+
+use paginate::{Block, BlockKind};
 
-pub use line::{Line, LineFragment};
-pub use page::{Page, PageLayout};
+pub use bidi::{BidiText, TextDirection, VisualRun};
+pub use cache::{CacheStats, LayoutCache};
+pub use float::{Clear, Float, FloatContext, FloatSide, PlacedFloat};
+pub use hyphenate::Hyphenation;
+pub use line::{Line, LineFragment, TabLeader};
+pub use linebreak::{BrokenLine, break_lines, break_lines_hyphenated, break_lines_varying};
+pub use measure::{EstimatedMeasurer, FontMeasurer, LineMetrics, TextMeasurer};
+pub use page::{HeaderFooter, Page, PageLayout};
+pub use paginate::Pagination;
 pub use paragraph::ParagraphLayout;
+pub use table::{CellLayout, ColumnWidth, TableLayout, TableOverflow, TableStyle};
 pub use text::TextLayout;
-pub use tree::{LayoutNode, LayoutTree};
+pub use tree::{CaretPosition, LayoutContent, LayoutNode, LayoutTree, TextHit};
 
 /// Result type for layout operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -47,36 +73,39 @@ pub struct Constraints {
     pub min: Size,
     /// Maximum size.
     pub max: Size,
+    /// Hyphenation of text laid out in the region.
+    pub hyphenation: Hyphenation,
 }
 
 impl Constraints {
     /// Create new constraints.
     pub fn new(min: Size, max: Size) -> Self {
-        Self { min, max }
+        Self {
+            min,
+            max,
+            hyphenation: Hyphenation::disabled(),
+        }
     }
 
     /// Tight constraints (exact size).
     pub fn tight(size: Size) -> Self {
-        Self {
-            min: size,
-            max: size,
-        }
+        Self::new(size, size)
     }
 
     /// Loose constraints (any size up to max).
     pub fn loose(max: Size) -> Self {
-        Self {
-            min: Size::ZERO,
-            max,
-        }
+        Self::new(Size::ZERO, max)
     }
 
     /// Unbounded constraints.
     pub fn unbounded() -> Self {
-        Self {
-            min: Size::ZERO,
-            max: Size::new(f32::INFINITY, f32::INFINITY),
-        }
+        Self::new(Size::ZERO, Size::new(f32::INFINITY, f32::INFINITY))
+    }
+
+    /// Set how text in the region is hyphenated.
+    pub fn with_hyphenation(mut self, hyphenation: Hyphenation) -> Self {
+        self.hyphenation = hyphenation;
+        self
     }
 }
 
@@ -86,21 +115,195 @@ pub struct LayoutEngine {
     pub page_size: Size,
     /// Page margins.
     pub margins: Margins,
+    /// How content is split across pages.
+    pub pagination: Pagination,
+    /// Running headers and footers.
+    pub header_footer: HeaderFooter,
+    /// Natural size of the images with each `src`, in points.
+    image_sizes: HashMap<String, Size>,
+    /// Paragraph layouts kept from earlier passes.
+    cache: LayoutCache,
 }
 
+/// Height given to a horizontal rule.
+const RULE_HEIGHT: f32 = 12.0;
+
 impl LayoutEngine {
     /// Create a new layout engine with A4 page size.
     pub fn new() -> Self {
         Self {
             page_size: Size::new(595.0, 842.0), // A4 in points
             margins: Margins::default(),
+            pagination: Pagination::default(),
+            header_footer: HeaderFooter::new(),
+            image_sizes: HashMap::new(),
+            cache: LayoutCache::default(),
         }
     }
 
-    /// Layout a document.
-    pub fn layout(&self, _document: &Document) -> Result<LayoutTree> {
-        // TODO: Implement full document layout
-        Ok(LayoutTree::new(self.page_size))
+    /// Keep up to `capacity` paragraph layouts between passes.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = LayoutCache::new(capacity);
+        self
+    }
+
+    /// Give images with `src` their natural `size`, in points. Images the
+    /// engine knows no size for are laid out the full width of the content
+    /// at a 4:3 aspect ratio.
+    pub fn set_image_size(&mut self, src: impl Into<String>, size: Size) {
+        self.image_sizes.insert(src.into(), size);
+    }
+
+    /// The size an image with `src` is laid out at: its natural size,
+    /// scaled down to fit `width` if it is wider.
+    fn image_size(&self, src: &str, width: f32) -> Size {
+        match self.image_sizes.get(src) {
+            Some(size) if size.width > width => Size::new(width, size.height * width / size.width),
+            Some(size) => *size,
+            None => Size::new(width, width * 0.75),
+        }
+    }
+
+    /// The cache of paragraph layouts.
+    pub fn cache(&self) -> &LayoutCache {
+        &self.cache
+    }
+
+    /// Layout a document, flowing its blocks across as many pages as they
+    /// need.
+    pub fn layout(&self, document: &Document) -> Result<LayoutTree> {
+        self.layout_with_blocks(document, &BlockRegistry::new())
+    }
+
+    /// Layout a document, rendering code and custom blocks with the plugins
+    /// that claimed their kinds. Blocks no plugin renders are laid out as if
+    /// there were no plugins.
+    pub fn layout_with_blocks(
+        &self,
+        document: &Document,
+        plugins: &BlockRegistry,
+    ) -> Result<LayoutTree> {
+        let area = self.margins.content_rect(self.page_size);
+        let content_rect = self.header_footer.content_rect(area);
+        if content_rect.width <= 0.0 || content_rect.height <= 0.0 {
+            return Err(Error::InvalidConstraint(
+                "margins, header and footer leave no room for content".to_string(),
+            ));
+        }
+        let constraints = Constraints::loose(content_rect.size());
+
+        let styles = cache::styles_key(&document.styles);
+        let mut blocks = Vec::new();
+        self.cache.begin_pass();
+        let collected =
+            self.collect_blocks(&document.root, constraints, plugins, styles, &mut blocks);
+        self.cache.end_pass();
+        collected?;
+        let mut pages = paginate::paginate(blocks, self.page_size, content_rect, &self.pagination);
+        // Fields need the page count, so bands are laid out last.
+        self.header_footer.place(&mut pages, area);
+
+        Ok(LayoutTree {
+            total_height: pages.len() as f32 * self.page_size.height,
+            pages,
+        })
+    }
+
+    /// Lay out the blocks of `node` and its descendants, in document order.
+    fn collect_blocks(
+        &self,
+        node: &Node,
+        constraints: Constraints,
+        plugins: &BlockRegistry,
+        styles: u64,
+        blocks: &mut Vec<Block>,
+    ) -> Result<()> {
+        let width = constraints.max.width;
+        let paragraph = |text: &Text| {
+            let key = cache::paragraph_key(node, text, styles, &constraints);
+            let layout = self.cache.paragraph(node.id, key, || {
+                ParagraphLayout::layout_indented(text, &node.formatting.paragraph, constraints)
+            });
+            BlockKind::Paragraph(layout)
+        };
+        let block = |kind, keep_with_next| Block {
+            source_id: node.id,
+            kind,
+            keep_with_next,
+        };
+        match &node.kind {
+            NodeKind::Paragraph(text) => blocks.push(block(paragraph(text), false)),
+            // Headings stay with the paragraph that follows them.
+            NodeKind::Heading { text, .. } => blocks.push(block(paragraph(text), true)),
+            NodeKind::CodeBlock { language, code } => {
+                let source = BlockSource {
+                    kind: "code",
+                    language: language.as_deref(),
+                    source: code,
+                };
+                let rendered = plugins
+                    .render(&source)
+                    .unwrap_or_else(|| RenderedBlock::Text(Box::new(Text::new(code.clone()))));
+                blocks.push(block(rendered_block(rendered, constraints), false));
+            }
+            NodeKind::Table { .. } => blocks.push(block(
+                BlockKind::Table(TableLayout::layout(node, &TableStyle::new(), constraints)?),
+                false,
+            )),
+            NodeKind::Image { src, .. } => blocks.push(block(
+                BlockKind::Atomic(
+                    LayoutContent::Image { src: src.clone() },
+                    self.image_size(src, width),
+                ),
+                false,
+            )),
+            NodeKind::HorizontalRule => blocks.push(block(
+                BlockKind::Atomic(
+                    LayoutContent::Container {
+                        children: Vec::new(),
+                    },
+                    Size::new(width, RULE_HEIGHT),
+                ),
+                false,
+            )),
+            NodeKind::PageBreak => blocks.push(block(BlockKind::PageBreak, false)),
+            NodeKind::Custom { kind, data } => {
+                let source = String::from_utf8_lossy(data);
+                let source = BlockSource {
+                    kind,
+                    language: None,
+                    source: &source,
+                };
+                if let Some(rendered) = plugins.render(&source) {
+                    blocks.push(block(rendered_block(rendered, constraints), false));
+                }
+            }
+            NodeKind::Root
+            | NodeKind::Section
+            | NodeKind::BlockQuote
+            | NodeKind::List { .. }
+            | NodeKind::ListItem
+            | NodeKind::TableRow
+            | NodeKind::TableCell { .. } => {
+                for child in &node.children {
+                    self.collect_blocks(child, constraints, plugins, styles, blocks)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lay out a block a plugin rendered.
+fn rendered_block(rendered: RenderedBlock, constraints: Constraints) -> BlockKind {
+    match rendered {
+        RenderedBlock::Text(text) => {
+            BlockKind::Paragraph(ParagraphLayout::layout(&text, constraints))
+        }
+        RenderedBlock::Drawing(drawing) => {
+            let size = Size::new(drawing.width, drawing.height());
+            BlockKind::Atomic(LayoutContent::Drawing(drawing), size)
+        }
     }
 }
 
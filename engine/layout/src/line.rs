@@ -11,6 +11,10 @@ pub struct Line {
     pub baseline: f32,
     /// Fragments in this line.
     pub fragments: Vec<LineFragment>,
+    /// Whether the line ends with an inserted hyphen.
+    pub hyphenated: bool,
+    /// Leaders filling the space before text at tab stops.
+    pub leaders: Vec<TabLeader>,
 }
 
 impl Line {
@@ -20,6 +24,8 @@ impl Line {
             bounds,
             baseline,
             fragments: Vec::new(),
+            hyphenated: false,
+            leaders: Vec::new(),
         }
     }
 }
@@ -33,10 +39,22 @@ pub struct LineFragment {
     pub text_start: usize,
     /// Length in bytes.
     pub text_len: usize,
+    /// Whether the fragment's text runs right to left, so its first
+    /// character is drawn at the right edge.
+    pub rtl: bool,
     /// Glyph positions.
     pub glyphs: Vec<GlyphPosition>,
 }
 
+/// Space before a tab stop filled with a repeated character.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TabLeader {
+    /// Space to fill.
+    pub bounds: Rect,
+    /// Character to repeat, such as `.` for a dotted leader.
+    pub character: char,
+}
+
 /// A positioned glyph.
 #[derive(Debug, Clone, Copy)]
 pub struct GlyphPosition {
@@ -0,0 +1,335 @@
+//! Line breaking.
+//!
+//! Break opportunities follow the Unicode line breaking algorithm
+//! (UAX #14): lines may end after spaces, between ideographs, after hyphens
+//! and so on, and must end after hard line breaks. Lines are filled greedily:
+//! each line takes as many opportunities as fit, and a single token that is
+//! wider than a whole line is split between grapheme clusters. With
+//! hyphenation enabled, a word that would leave its line badly underfull, or
+//! that is wider than a whole line, is hyphenated instead.
+
+use std::ops::Range;
+
+use unicode_linebreak::{BreakOpportunity, linebreaks};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::hyphenate::Hyphenation;
+
+/// The hyphen shown at the end of a hyphenated line.
+pub const HYPHEN: &str = "-";
+
+/// A line produced by [`break_lines`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenLine {
+    /// Byte range of the line in the source text, including trailing
+    /// whitespace and any line feed that ends it.
+    pub range: Range<usize>,
+    /// Byte offset at which the line's trailing whitespace starts.
+    pub content_end: usize,
+    /// Measured width, excluding trailing whitespace but including the
+    /// hyphen of a hyphenated line.
+    pub width: f32,
+    /// Whether the line ends at a hard break or the end of the text, rather
+    /// than being wrapped.
+    pub mandatory: bool,
+    /// Whether the line ends inside a word and must be shown with a
+    /// trailing [`HYPHEN`].
+    pub hyphenated: bool,
+}
+
+impl BrokenLine {
+    /// The visible part of the line: its text without trailing whitespace.
+    pub fn content<'a>(&self, text: &'a str) -> &'a str {
+        &text[self.range.start..self.content_end]
+    }
+}
+
+/// Break `text` into lines no wider than `max_width`.
+///
+/// `measure` returns the advance width of a run of text. Trailing
+/// whitespace never counts toward a line's width, so a line may end in
+/// spaces that would not otherwise fit. Empty text yields no lines.
+pub fn break_lines(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<BrokenLine> {
+    break_lines_hyphenated(text, max_width, &Hyphenation::disabled(), measure)
+}
+
+/// Break `text` into lines no wider than `max_width`, hyphenating words
+/// according to `hyphenation`.
+///
+/// A word is only hyphenated when wrapping before it would fill less than
+/// [`Hyphenation::min_fill`] of the line, or when it is too wide to fit on a
+/// line of its own.
+pub fn break_lines_hyphenated(
+    text: &str,
+    max_width: f32,
+    hyphenation: &Hyphenation,
+    measure: impl Fn(&str) -> f32,
+) -> Vec<BrokenLine> {
+    break_lines_varying(text, |_| max_width, hyphenation, measure)
+}
+
+/// Break `text` into lines whose widths may differ, as when text flows
+/// around a float.
+///
+/// `line_width` returns the width available to the line with the given
+/// index, counting from zero.
+pub fn break_lines_varying(
+    text: &str,
+    line_width: impl Fn(usize) -> f32,
+    hyphenation: &Hyphenation,
+    measure: impl Fn(&str) -> f32,
+) -> Vec<BrokenLine> {
+    let mut lines = Vec::new();
+    if text.is_empty() {
+        return lines;
+    }
+
+    let mut start = 0;
+    // The furthest opportunity on the current line known to fit.
+    let mut last_fit: Option<usize> = None;
+
+    for (position, opportunity) in linebreaks(text) {
+        loop {
+            let max_width = line_width(lines.len());
+            let width = measure(trim_trailing(&text[start..position]));
+            if width <= max_width {
+                break;
+            }
+            // Hyphenate the overflowing word if the line would otherwise be
+            // badly underfull.
+            let word_start = last_fit.unwrap_or(start);
+            let underfull = last_fit.is_none_or(|fit| {
+                measure(trim_trailing(&text[start..fit])) < max_width * hyphenation.min_fill
+            });
+            if underfull {
+                if let Some(end) = hyphen_break(
+                    text,
+                    start,
+                    word_start..position,
+                    max_width,
+                    hyphenation,
+                    &measure,
+                ) {
+                    lines.push(hyphenated_line(text, start..end, &measure));
+                    start = end;
+                    last_fit = None;
+                    continue;
+                }
+            }
+            match last_fit.take() {
+                // Wrap at the last opportunity that fit and re-measure the
+                // rest against a fresh line.
+                Some(fit) => {
+                    lines.push(line(text, start..fit, false, &measure));
+                    start = fit;
+                }
+                // Nothing fits: the first token alone is too wide.
+                None => {
+                    let end = force_break(text, start, position, max_width, &measure);
+                    if end >= position {
+                        break;
+                    }
+                    lines.push(line(text, start..end, false, &measure));
+                    start = end;
+                }
+            }
+        }
+
+        match opportunity {
+            BreakOpportunity::Mandatory => {
+                lines.push(line(text, start..position, true, &measure));
+                start = position;
+                last_fit = None;
+            }
+            BreakOpportunity::Allowed => last_fit = Some(position),
+        }
+    }
+
+    lines
+}
+
+fn line(
+    text: &str,
+    range: Range<usize>,
+    mandatory: bool,
+    measure: &impl Fn(&str) -> f32,
+) -> BrokenLine {
+    let content = trim_trailing(&text[range.clone()]);
+    BrokenLine {
+        content_end: range.start + content.len(),
+        width: measure(content),
+        range,
+        mandatory,
+        hyphenated: false,
+    }
+}
+
+fn hyphenated_line(text: &str, range: Range<usize>, measure: &impl Fn(&str) -> f32) -> BrokenLine {
+    BrokenLine {
+        content_end: range.end,
+        width: measure(&format!("{}{}", &text[range.clone()], HYPHEN)),
+        range,
+        mandatory: false,
+        hyphenated: true,
+    }
+}
+
+/// Find the last hyphenation point in `word` at which the line starting at
+/// `start` still fits with its hyphen.
+fn hyphen_break(
+    text: &str,
+    start: usize,
+    word: Range<usize>,
+    max_width: f32,
+    hyphenation: &Hyphenation,
+    measure: &impl Fn(&str) -> f32,
+) -> Option<usize> {
+    hyphenation
+        .break_points(&text[word.clone()])
+        .into_iter()
+        .rev()
+        .map(|point| word.start + point)
+        .find(|&end| measure(&format!("{}{}", &text[start..end], HYPHEN)) <= max_width)
+}
+
+/// Find where to split an over-long token that starts at `start`: after as
+/// many grapheme clusters as fit, but always at least one.
+fn force_break(
+    text: &str,
+    start: usize,
+    end: usize,
+    max_width: f32,
+    measure: &impl Fn(&str) -> f32,
+) -> usize {
+    let mut split = None;
+    for (offset, grapheme) in text[start..end].grapheme_indices(true) {
+        let candidate = start + offset + grapheme.len();
+        match split {
+            None => split = Some(candidate),
+            Some(_) if measure(trim_trailing(&text[start..candidate])) <= max_width => {
+                split = Some(candidate)
+            }
+            Some(_) => break,
+        }
+    }
+    split.unwrap_or(end)
+}
+
+/// Strip trailing whitespace, including line feeds.
+fn trim_trailing(text: &str) -> &str {
+    text.trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character is 10 units wide.
+    fn measure(text: &str) -> f32 {
+        text.chars().count() as f32 * 10.0
+    }
+
+    fn contents<'a>(text: &'a str, lines: &[BrokenLine]) -> Vec<&'a str> {
+        lines.iter().map(|line| line.content(text)).collect()
+    }
+
+    #[test]
+    fn test_wraps_at_spaces() {
+        let text = "the quick brown fox";
+        let lines = break_lines(text, 100.0, measure);
+        assert_eq!(contents(text, &lines), vec!["the quick", "brown fox"]);
+        // The space after "quick" does not count toward the width.
+        assert_eq!(lines[0].width, 90.0);
+        assert_eq!(lines[0].range, 0..10);
+        assert!(!lines[0].mandatory);
+        assert!(lines[1].mandatory);
+    }
+
+    #[test]
+    fn test_trailing_spaces_do_not_overflow() {
+        let text = "abcdefghij     next";
+        let lines = break_lines(text, 100.0, measure);
+        assert_eq!(contents(text, &lines), vec!["abcdefghij", "next"]);
+    }
+
+    #[test]
+    fn test_hard_breaks() {
+        let text = "one\n\ntwo";
+        let lines = break_lines(text, 1000.0, measure);
+        assert_eq!(contents(text, &lines), vec!["one", "", "two"]);
+        assert!(lines.iter().all(|line| line.mandatory));
+    }
+
+    #[test]
+    fn test_breaks_between_ideographs() {
+        let text = "日本語のテキストを折り返す";
+        let lines = break_lines(text, 40.0, measure);
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert!(line.width <= 40.0, "{:?}", line);
+        }
+        assert_eq!(contents(text, &lines).concat(), text);
+    }
+
+    #[test]
+    fn test_prefers_earlier_opportunity_over_splitting_a_word() {
+        let text = "a extraordinarily";
+        let lines = break_lines(text, 150.0, measure);
+        assert_eq!(contents(text, &lines), vec!["a", "extraordinarily"]);
+    }
+
+    #[test]
+    fn test_long_url_wraps_at_opportunities() {
+        let text = "see https://example.com/a/very/long/path/to/a/resource";
+        let lines = break_lines(text, 120.0, measure);
+        let opportunities: Vec<usize> = linebreaks(text).map(|(position, _)| position).collect();
+        for line in &lines {
+            assert!(line.width <= 120.0, "{:?}", line);
+            // No line had to be force-split mid-token.
+            assert!(opportunities.contains(&line.range.end), "{:?}", line);
+        }
+        assert!(lines.len() > 2);
+        let rebuilt: String = lines.iter().map(|line| &text[line.range.clone()]).collect();
+        assert_eq!(rebuilt, text);
+    }
+
+    #[test]
+    fn test_force_breaks_long_token() {
+        let text = "abcdefghijklmnopqrstuvwxyz";
+        let lines = break_lines(text, 100.0, measure);
+        assert_eq!(
+            contents(text, &lines),
+            vec!["abcdefghij", "klmnopqrst", "uvwxyz"]
+        );
+    }
+
+    #[test]
+    fn test_narrower_than_one_character() {
+        let text = "ab";
+        let lines = break_lines(text, 5.0, measure);
+        assert_eq!(contents(text, &lines), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_varying_widths() {
+        let text = "aaa bbb ccc ddd eee";
+        let widths = |index: usize| if index < 2 { 30.0 } else { 120.0 };
+        let lines = break_lines_varying(text, widths, &Hyphenation::disabled(), measure);
+        assert_eq!(contents(text, &lines), vec!["aaa", "bbb", "ccc ddd eee"]);
+    }
+
+    #[test]
+    fn test_hyphenates_word_that_does_not_fit_alone() {
+        let text = "hyphenation";
+        let lines = break_lines_hyphenated(text, 80.0, &Hyphenation::english(), measure);
+        assert_eq!(contents(text, &lines), vec!["hyphen", "ation"]);
+        assert!(lines[0].hyphenated);
+        assert_eq!(lines[0].width, 70.0);
+        assert!(!lines[1].hyphenated);
+
+        // Without hyphenation the word is split wherever it overflows.
+        let lines = break_lines(text, 80.0, measure);
+        assert_eq!(contents(text, &lines), vec!["hyphenat", "ion"]);
+        assert!(lines.iter().all(|line| !line.hyphenated));
+    }
+}
@@ -0,0 +1,270 @@
+//! Text measurement.
+//!
+//! Layout needs the width of runs of text and the height of lines, but not
+//! a renderer: a [`TextMeasurer`] answers both from font data alone, so
+//! text lays out the same headless, in tests and on screen.
+//!
+//! [`FontMeasurer`] shapes text on the CPU with cosmic-text, which applies
+//! the font's kerning and falls back to other fonts for characters the
+//! first lacks. Characters no font has are given an estimated advance
+//! rather than the width of the font's missing-glyph box.
+//! [`EstimatedMeasurer`] needs no fonts at all.
+
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+use cosmic_text::fontdb::{self, Family, Query};
+use cosmic_text::{Attrs, AttrsList, FontSystem, ShapeLine, Shaping};
+
+/// Tab width in spaces used when shaping.
+const TAB_WIDTH: u16 = 8;
+
+/// Advance of a character without a glyph, in ems.
+const FALLBACK_ADVANCE: f32 = 0.5;
+
+/// Vertical metrics of a line of text at a font size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    /// Distance from the baseline up to the top of the line.
+    pub ascent: f32,
+    /// Distance from the baseline down to the bottom of the line.
+    pub descent: f32,
+    /// Extra space the font asks for between lines.
+    pub line_gap: f32,
+}
+
+impl LineMetrics {
+    /// Estimated metrics for fonts without data.
+    pub fn estimated(font_size: f32) -> Self {
+        Self {
+            ascent: font_size * 0.8,
+            descent: font_size * 0.2,
+            line_gap: font_size * 0.2,
+        }
+    }
+
+    /// Distance from one baseline to the next.
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent + self.line_gap
+    }
+}
+
+/// Measures text for layout.
+pub trait TextMeasurer: Send + Sync {
+    /// Advance width of `text` at `font_size`, from the start of its first
+    /// glyph to the end of its last.
+    fn advance(&self, text: &str, font_size: f32) -> f32;
+
+    /// Vertical metrics of a line at `font_size`.
+    fn line_metrics(&self, font_size: f32) -> LineMetrics;
+}
+
+/// Measures text by its length, for callers without fonts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EstimatedMeasurer;
+
+impl TextMeasurer for EstimatedMeasurer {
+    fn advance(&self, text: &str, font_size: f32) -> f32 {
+        crate::text::estimate_width(text, font_size)
+    }
+
+    fn line_metrics(&self, font_size: f32) -> LineMetrics {
+        LineMetrics::estimated(font_size)
+    }
+}
+
+/// Measures text by shaping it with real fonts.
+pub struct FontMeasurer {
+    font_system: Mutex<FontSystem>,
+    /// Family text is set in; the default sans-serif if `None`.
+    family: Option<String>,
+    /// Face vertical metrics are read from.
+    face: Option<fontdb::ID>,
+}
+
+impl FontMeasurer {
+    /// Create a measurer for the system's fonts, setting text in the
+    /// default sans-serif family.
+    pub fn new() -> Self {
+        Self::with_font_system(FontSystem::new(), None)
+    }
+
+    /// Create a measurer for a single font, given as TrueType or OpenType
+    /// data. Text is set in the font's family.
+    pub fn from_font_data(data: Vec<u8>) -> Self {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(data);
+        let family = db
+            .faces()
+            .next()
+            .and_then(|face| face.families.first())
+            .map(|(name, _)| name.clone());
+        Self::with_font_system(
+            FontSystem::new_with_locale_and_db("en-US".to_string(), db),
+            family,
+        )
+    }
+
+    /// Create a measurer for the fonts of `font_system`, setting text in
+    /// `family`.
+    pub fn with_font_system(font_system: FontSystem, family: Option<String>) -> Self {
+        let face = {
+            let families = [family.as_deref().map_or(Family::SansSerif, Family::Name)];
+            let db = font_system.db();
+            db.query(&Query {
+                families: &families,
+                ..Default::default()
+            })
+            .or_else(|| db.faces().next().map(|face| face.id))
+        };
+        Self {
+            font_system: Mutex::new(font_system),
+            family,
+            face,
+        }
+    }
+
+    /// Family text is set in, if not the default sans-serif.
+    pub fn family(&self) -> Option<&str> {
+        self.family.as_deref()
+    }
+
+    fn attrs(&self) -> Attrs<'_> {
+        Attrs::new().family(
+            self.family
+                .as_deref()
+                .map_or(Family::SansSerif, Family::Name),
+        )
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FontSystem> {
+        // Font systems only cache, so one a panic interrupted is still sound.
+        self.font_system.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for FontMeasurer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FontMeasurer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FontMeasurer")
+            .field("family", &self.family)
+            .field("face", &self.face)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TextMeasurer for FontMeasurer {
+    fn advance(&self, text: &str, font_size: f32) -> f32 {
+        // Shaping works a paragraph at a time; break lines are measured on
+        // their own and the widest kept.
+        let attrs = AttrsList::new(self.attrs());
+        let mut font_system = self.lock();
+        text.split('\n')
+            .map(|line| {
+                let shaped =
+                    ShapeLine::new(&mut font_system, line, &attrs, Shaping::Advanced, TAB_WIDTH);
+                shaped
+                    .spans
+                    .iter()
+                    .flat_map(|span| &span.words)
+                    .flat_map(|word| &word.glyphs)
+                    .map(|glyph| {
+                        if glyph.glyph_id == 0 {
+                            let missing = line[glyph.start..glyph.end].chars().count();
+                            missing as f32 * font_size * FALLBACK_ADVANCE
+                        } else {
+                            glyph.width(font_size)
+                        }
+                    })
+                    .sum::<f32>()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    fn line_metrics(&self, font_size: f32) -> LineMetrics {
+        let Some(font) = self.face.and_then(|id| self.lock().get_font(id)) else {
+            return LineMetrics::estimated(font_size);
+        };
+        let face = font.rustybuzz();
+        let scale = font_size / face.units_per_em() as f32;
+        LineMetrics {
+            ascent: face.ascender() as f32 * scale,
+            descent: -(face.descender() as f32) * scale,
+            line_gap: face.line_gap() as f32 * scale,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, LazyLock};
+
+    use super::*;
+
+    static TUFFY: LazyLock<Arc<FontMeasurer>> = LazyLock::new(|| {
+        let data = include_bytes!("../../../test-suite/fonts/Tuffy.ttf");
+        Arc::new(FontMeasurer::from_font_data(data.to_vec()))
+    });
+
+    /// A measurer for the test suite's font.
+    pub(crate) fn tuffy() -> Arc<FontMeasurer> {
+        TUFFY.clone()
+    }
+
+    /// Width of "Hello, world" in Tuffy at 12 points: the glyphs advance
+    /// 9728 units at 2048 to the em, and no pair in it is kerned.
+    const EXPECTED_HELLO_WORLD: f32 = 9728.0 * 12.0 / 2048.0;
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_known_string_width() {
+        let measurer = tuffy();
+        assert_eq!(measurer.family(), Some("Tuffy"));
+        assert_near(measurer.advance("", 12.0), 0.0);
+        let width = measurer.advance("Hello, world", 12.0);
+        assert_near(width, EXPECTED_HELLO_WORLD);
+        // Advances scale with the font size.
+        assert_near(measurer.advance("Hello, world", 24.0), width * 2.0);
+        // Break lines are measured apart.
+        assert_near(measurer.advance("Hello, world\nHi", 12.0), width);
+    }
+
+    #[test]
+    fn test_kerning() {
+        let measurer = tuffy();
+        let pair = measurer.advance("AV", 100.0);
+        let apart = measurer.advance("A", 100.0) + measurer.advance("V", 100.0);
+        assert!(pair < apart, "{pair} should be kerned below {apart}");
+    }
+
+    #[test]
+    fn test_missing_glyph_fallback() {
+        let measurer = tuffy();
+        // Tuffy has no CJK glyphs and no other font is loaded.
+        let missing = measurer.advance("日本", 12.0);
+        assert_near(missing, 2.0 * 12.0 * FALLBACK_ADVANCE);
+        assert_near(
+            measurer.advance("a日", 12.0),
+            measurer.advance("a", 12.0) + 12.0 * FALLBACK_ADVANCE,
+        );
+    }
+
+    #[test]
+    fn test_line_metrics() {
+        let metrics = tuffy().line_metrics(10.0);
+        assert!(metrics.ascent > 0.0 && metrics.descent > 0.0);
+        assert!(metrics.height() > 10.0 && metrics.height() < 15.0);
+        assert_eq!(EstimatedMeasurer.line_metrics(10.0).height(), 12.0);
+    }
+}
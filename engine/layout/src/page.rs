@@ -1,8 +1,27 @@
 //! Page layout.
+//!
+//! Pages may carry a running header and footer, laid out from template
+//! text in bands that the content area leaves free below the top margin
+//! and above the bottom one. Templates may hold `{page}` and `{pages}`
+//! fields. Band heights do not depend on the fields, so the body is
+//! paginated first and the fields resolved once the page count is known.
 
+use uuid::Uuid;
+use wolia_core::text::Text;
 use wolia_math::{Rect, Size};
 
-use crate::LayoutNode;
+use crate::paragraph::ParagraphLayout;
+use crate::tree::LayoutContent;
+use crate::{Constraints, LayoutNode};
+
+/// Field replaced by the number of the page a header or footer is on.
+pub const PAGE_FIELD: &str = "{page}";
+
+/// Field replaced by the number of pages in the document.
+pub const PAGES_FIELD: &str = "{pages}";
+
+/// Height of header and footer bands unless set otherwise.
+pub const DEFAULT_BAND_HEIGHT: f32 = 24.0;
 
 /// A laid-out page.
 #[derive(Debug, Clone)]
@@ -15,6 +34,10 @@ pub struct Page {
     pub content_rect: Rect,
     /// Layout nodes on this page.
     pub nodes: Vec<LayoutNode>,
+    /// Running header, above the content area.
+    pub header: Option<LayoutNode>,
+    /// Running footer, below the content area.
+    pub footer: Option<LayoutNode>,
 }
 
 impl Page {
@@ -25,10 +48,168 @@ impl Page {
             size,
             content_rect,
             nodes: Vec::new(),
+            header: None,
+            footer: None,
+        }
+    }
+
+    /// The page's header, body nodes and footer, in that order.
+    pub fn all_nodes(&self) -> impl Iterator<Item = &LayoutNode> {
+        self.header
+            .iter()
+            .chain(&self.nodes)
+            .chain(self.footer.iter())
+    }
+}
+
+/// Running headers and footers.
+#[derive(Debug, Clone)]
+pub struct HeaderFooter {
+    /// Header template.
+    pub header: Option<Text>,
+    /// Footer template.
+    pub footer: Option<Text>,
+    /// Whether the first page has its own header and footer.
+    pub different_first_page: bool,
+    /// Header template of the first page, if it is different.
+    pub first_header: Option<Text>,
+    /// Footer template of the first page, if it is different.
+    pub first_footer: Option<Text>,
+    /// Height of the header band, in points.
+    pub header_height: f32,
+    /// Height of the footer band, in points.
+    pub footer_height: f32,
+}
+
+impl HeaderFooter {
+    /// Create an empty header and footer.
+    pub fn new() -> Self {
+        Self {
+            header: None,
+            footer: None,
+            different_first_page: false,
+            first_header: None,
+            first_footer: None,
+            header_height: DEFAULT_BAND_HEIGHT,
+            footer_height: DEFAULT_BAND_HEIGHT,
+        }
+    }
+
+    /// Set the header template.
+    pub fn with_header(mut self, header: Text) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Set the footer template.
+    pub fn with_footer(mut self, footer: Text) -> Self {
+        self.footer = Some(footer);
+        self
+    }
+
+    /// Give the first page its own header and footer, or none.
+    pub fn with_first_page(mut self, header: Option<Text>, footer: Option<Text>) -> Self {
+        self.different_first_page = true;
+        self.first_header = header;
+        self.first_footer = footer;
+        self
+    }
+
+    /// Header and footer templates of page `number`.
+    pub fn templates(&self, number: usize) -> (Option<&Text>, Option<&Text>) {
+        if self.different_first_page && number == 1 {
+            (self.first_header.as_ref(), self.first_footer.as_ref())
+        } else {
+            (self.header.as_ref(), self.footer.as_ref())
+        }
+    }
+
+    /// Whether any page has a header, whose band is then reserved on all.
+    pub fn has_header(&self) -> bool {
+        self.header.is_some() || (self.different_first_page && self.first_header.is_some())
+    }
+
+    /// Whether any page has a footer, whose band is then reserved on all.
+    pub fn has_footer(&self) -> bool {
+        self.footer.is_some() || (self.different_first_page && self.first_footer.is_some())
+    }
+
+    /// The part of the area within the margins left for content.
+    pub fn content_rect(&self, area: Rect) -> Rect {
+        let header = if self.has_header() {
+            self.header_height
+        } else {
+            0.0
+        };
+        let footer = if self.has_footer() {
+            self.footer_height
+        } else {
+            0.0
+        };
+        Rect::new(
+            area.x,
+            area.y + header,
+            area.width,
+            area.height - header - footer,
+        )
+    }
+
+    /// Lay out the header and footer of each of `pages`, whose area within
+    /// the margins is `area`, resolving their fields.
+    pub(crate) fn place(&self, pages: &mut [Page], area: Rect) {
+        let total = pages.len();
+        let band = |template: &Text, number: usize, bounds: Rect| {
+            let text = resolve_fields(template, number, total);
+            let mut paragraph = ParagraphLayout::layout(&text, Constraints::loose(bounds.size()));
+            paragraph.bounds.width = bounds.width;
+            LayoutNode {
+                source_id: Uuid::nil(),
+                bounds,
+                content: LayoutContent::Paragraph(paragraph),
+            }
+        };
+        let header_bounds = Rect::new(area.x, area.y, area.width, self.header_height);
+        let footer_bounds = Rect::new(
+            area.x,
+            area.bottom() - self.footer_height,
+            area.width,
+            self.footer_height,
+        );
+        for page in pages {
+            let (header, footer) = self.templates(page.number);
+            page.header = header.map(|header| band(header, page.number, header_bounds));
+            page.footer = footer.map(|footer| band(footer, page.number, footer_bounds));
         }
     }
 }
 
+impl Default for HeaderFooter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replace the `{page}` and `{pages}` fields of a template with the page's
+/// number and the page count, keeping the template's formatting.
+pub fn resolve_fields(template: &Text, page: usize, pages: usize) -> Text {
+    let mut text = template.clone();
+    let mut from = 0;
+    while let Some((start, field)) = next_field(&text.content, from) {
+        let value = if field == PAGE_FIELD { page } else { pages }.to_string();
+        text.replace_range(start..start + field.len(), &value);
+        from = start + value.len();
+    }
+    text
+}
+
+/// The first field at or after byte `from`, with its offset.
+fn next_field(content: &str, from: usize) -> Option<(usize, &'static str)> {
+    [PAGE_FIELD, PAGES_FIELD]
+        .into_iter()
+        .filter_map(|field| content[from..].find(field).map(|at| (from + at, field)))
+        .min_by_key(|&(at, _)| at)
+}
+
 /// Page layout configuration.
 #[derive(Debug, Clone)]
 pub struct PageLayout {
@@ -84,3 +265,109 @@ impl Default for PageLayout {
         Self::a4()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::Document;
+    use wolia_core::node::Node;
+    use wolia_core::style::TextStyle;
+    use wolia_core::text::Span;
+
+    use super::*;
+    use crate::{LayoutEngine, LayoutTree, Margins};
+
+    fn band_text(node: &Option<LayoutNode>) -> Option<&str> {
+        match node.as_ref().map(|node| &node.content) {
+            Some(LayoutContent::Paragraph(paragraph)) => Some(&paragraph.text),
+            _ => None,
+        }
+    }
+
+    fn layout(header_footer: HeaderFooter, paragraphs: usize) -> LayoutTree {
+        let mut document = Document::new();
+        for _ in 0..paragraphs {
+            document
+                .root
+                .add_child(Node::paragraph(Text::new("A paragraph of the body.")));
+        }
+        let engine = LayoutEngine {
+            page_size: Size::new(200.0, 200.0),
+            margins: Margins::uniform(20.0),
+            header_footer,
+            ..LayoutEngine::new()
+        };
+        engine.layout(&document).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_fields() {
+        let mut template = Text::new("Page {page} of {pages}");
+        let bold = TextStyle {
+            font_weight: Some(700),
+            ..Default::default()
+        };
+        template.add_span(Span::new(15, 22, bold));
+        let text = resolve_fields(&template, 9, 120);
+        assert_eq!(text.content, "Page 9 of 120");
+        assert_eq!((text.spans[0].start, text.spans[0].end), (10, 13));
+        assert_eq!(
+            resolve_fields(&Text::new("{pages}{page}{x}"), 1, 2).content,
+            "21{x}"
+        );
+    }
+
+    #[test]
+    fn test_page_numbers() {
+        let header_footer = HeaderFooter::new()
+            .with_header(Text::new("Report"))
+            .with_footer(Text::new("Page {page} of {pages}"));
+        let tree = layout(header_footer, 40);
+        let pages = tree.pages.len();
+        assert!(pages > 2);
+        for (index, page) in tree.pages.iter().enumerate() {
+            assert_eq!(band_text(&page.header), Some("Report"));
+            let footer = format!("Page {} of {}", index + 1, pages);
+            assert_eq!(band_text(&page.footer), Some(footer.as_str()));
+            assert_eq!(page.all_nodes().count(), page.nodes.len() + 2);
+        }
+
+        // The bands sit inside the margins, with the body between them.
+        let page = &tree.pages[0];
+        let (header, footer) = (page.header.as_ref().unwrap(), page.footer.as_ref().unwrap());
+        assert_eq!(header.bounds.y, 20.0);
+        assert_eq!(footer.bounds.bottom(), 180.0);
+        assert_eq!(page.content_rect.y, header.bounds.bottom());
+        assert_eq!(page.content_rect.bottom(), footer.bounds.y);
+        assert!(page.nodes.iter().all(|node| {
+            node.bounds.y >= page.content_rect.y
+                && node.bounds.bottom() <= page.content_rect.bottom() + 1e-3
+        }));
+    }
+
+    #[test]
+    fn test_different_first_page() {
+        let header_footer = HeaderFooter::new()
+            .with_header(Text::new("{page}"))
+            .with_first_page(None, Some(Text::new("{pages} pages")));
+        let tree = layout(header_footer, 40);
+        let pages = tree.pages.len();
+        let first = &tree.pages[0];
+        assert_eq!(band_text(&first.header), None);
+        assert_eq!(
+            band_text(&first.footer),
+            Some(format!("{pages} pages").as_str())
+        );
+        assert_eq!(band_text(&tree.pages[1].header), Some("2"));
+        assert_eq!(band_text(&tree.pages[1].footer), None);
+
+        // Both bands are kept clear on every page, so the body flows the
+        // same way whichever has content.
+        let plain = layout(HeaderFooter::new(), 40);
+        assert!(plain.pages.len() < pages);
+        assert!(
+            tree.pages
+                .iter()
+                .all(|page| page.content_rect.height == 112.0)
+        );
+    }
+}
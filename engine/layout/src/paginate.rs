@@ -0,0 +1,546 @@
+//! Pagination.
+//!
+//! Laid-out blocks are flowed down the content area of successive pages. A
+//! block that does not fit in what is left of a page is split if it can be:
+//! paragraphs between lines, tables between rows. Orphan and widow control
+//! keep a paragraph from leaving too few lines at the bottom of one page or
+//! the top of the next, moving the split, or the whole paragraph, as needed.
+//! A block flagged keep-with-next moves to a new page along with the start
+//! of the block after it.
+//!
+//! A block is always placed once it reaches the top of an empty page, so a
+//! block taller than a whole page is split wherever it must be, and one that
+//! cannot be split at all overflows its page rather than being retried
+//! forever.
+
+use uuid::Uuid;
+use wolia_math::{Rect, Size};
+
+use crate::page::Page;
+use crate::paragraph::ParagraphLayout;
+use crate::table::TableLayout;
+use crate::tree::{LayoutContent, LayoutNode};
+
+/// Allowance for rounding when checking whether content fits.
+const EPSILON: f32 = 1e-3;
+
+/// Pagination settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pagination {
+    /// Fewest lines of a paragraph left at the bottom of a page.
+    pub orphans: usize,
+    /// Fewest lines of a paragraph carried to the top of a page.
+    pub widows: usize,
+    /// Vertical space between blocks, in points.
+    pub block_spacing: f32,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            orphans: 2,
+            widows: 2,
+            block_spacing: 6.0,
+        }
+    }
+}
+
+/// A laid-out block waiting to be placed on a page.
+#[derive(Debug, Clone)]
+pub(crate) struct Block {
+    pub(crate) source_id: Uuid,
+    pub(crate) kind: BlockKind,
+    /// Whether the block must share a page with the start of the next one.
+    pub(crate) keep_with_next: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum BlockKind {
+    /// A paragraph, split between lines.
+    Paragraph(ParagraphLayout),
+    /// A table, split between rows.
+    Table(TableLayout),
+    /// Content that cannot be split, with its size.
+    Atomic(LayoutContent, Size),
+    /// A forced page break.
+    PageBreak,
+}
+
+/// What fits of a block in the space left on a page.
+enum Take {
+    /// The whole block, with its height.
+    All(LayoutContent, f32),
+    /// The start of the block and its height, and the rest of the block.
+    Part(LayoutContent, f32, BlockKind),
+    /// Nothing: the block must start on a new page.
+    Nothing(BlockKind),
+}
+
+impl BlockKind {
+    /// Height of the smallest piece the block may start a page with.
+    fn min_height(&self, pagination: &Pagination) -> f32 {
+        match self {
+            BlockKind::Paragraph(paragraph) => {
+                let lines = paragraph.lines.len().min(pagination.orphans.max(1));
+                lines_height(paragraph, lines)
+            }
+            BlockKind::Table(table) => row_breaks(table)
+                .first()
+                .map_or(0.0, |&rows| table.row_heights[..rows].iter().sum()),
+            BlockKind::Atomic(_, size) => size.height,
+            BlockKind::PageBreak => 0.0,
+        }
+    }
+
+    /// Width of the block.
+    fn width(&self) -> f32 {
+        match self {
+            BlockKind::Paragraph(paragraph) => paragraph.bounds.width,
+            BlockKind::Table(table) => table.bounds.width,
+            BlockKind::Atomic(_, size) => size.width,
+            BlockKind::PageBreak => 0.0,
+        }
+    }
+
+    /// Take as much of the block as fits in `available` points.
+    ///
+    /// On an empty page something is always taken.
+    fn take(self, available: f32, empty_page: bool, pagination: &Pagination) -> Take {
+        match self {
+            BlockKind::Paragraph(mut paragraph) => {
+                let total = paragraph.lines.len();
+                let fit = paragraph
+                    .lines
+                    .iter()
+                    .take_while(|line| line.bounds.bottom() <= available + EPSILON)
+                    .count();
+                if fit == total {
+                    let height = paragraph.height();
+                    return Take::All(LayoutContent::Paragraph(paragraph), height);
+                }
+
+                let mut split = fit;
+                if total - split < pagination.widows {
+                    split = total.saturating_sub(pagination.widows);
+                }
+                if split < pagination.orphans {
+                    split = 0;
+                }
+                if split == 0 && empty_page {
+                    // Orphans and widows cannot both be honoured here.
+                    split = fit.max(1);
+                }
+                if split == 0 {
+                    return Take::Nothing(BlockKind::Paragraph(paragraph));
+                }
+                let rest = paragraph.split_off(split);
+                let height = paragraph.height();
+                Take::Part(
+                    LayoutContent::Paragraph(paragraph),
+                    height,
+                    BlockKind::Paragraph(rest),
+                )
+            }
+            BlockKind::Table(table) => {
+                let total: f32 = table.row_heights.iter().sum();
+                if total <= available + EPSILON {
+                    return Take::All(table_content(&table, 0..table.row_count()), total);
+                }
+                let breaks = row_breaks(&table);
+                let height_to = |rows: usize| -> f32 { table.row_heights[..rows].iter().sum() };
+                let split = breaks
+                    .iter()
+                    .copied()
+                    .take_while(|&rows| height_to(rows) <= available + EPSILON)
+                    .last()
+                    .or_else(|| empty_page.then(|| breaks.first().copied()).flatten());
+                match split {
+                    Some(rows) if rows < table.row_count() => {
+                        let rest = table_rows(&table, rows..table.row_count());
+                        Take::Part(
+                            table_content(&table, 0..rows),
+                            height_to(rows),
+                            BlockKind::Table(rest),
+                        )
+                    }
+                    Some(_) => Take::All(table_content(&table, 0..table.row_count()), total),
+                    None => Take::Nothing(BlockKind::Table(table)),
+                }
+            }
+            BlockKind::Atomic(content, size) => {
+                if size.height <= available + EPSILON || empty_page {
+                    Take::All(content, size.height)
+                } else {
+                    Take::Nothing(BlockKind::Atomic(content, size))
+                }
+            }
+            BlockKind::PageBreak => Take::All(
+                LayoutContent::Container {
+                    children: Vec::new(),
+                },
+                0.0,
+            ),
+        }
+    }
+}
+
+/// Height of the first `lines` lines of a paragraph.
+fn lines_height(paragraph: &ParagraphLayout, lines: usize) -> f32 {
+    match lines {
+        0 => 0.0,
+        _ => paragraph.lines[lines - 1].bounds.bottom() - paragraph.lines[0].bounds.y,
+    }
+}
+
+/// Row counts after which a table may be split: the ends of rows that no
+/// cell spans past.
+fn row_breaks(table: &TableLayout) -> Vec<usize> {
+    (1..=table.row_count())
+        .filter(|&rows| {
+            table
+                .cells
+                .iter()
+                .all(|cell| cell.row >= rows || cell.row + cell.row_span <= rows)
+        })
+        .collect()
+}
+
+/// The rows in `rows` of a table, moved up to start at the top.
+fn table_rows(table: &TableLayout, rows: std::ops::Range<usize>) -> TableLayout {
+    let offset: f32 = table.row_heights[..rows.start].iter().sum();
+    let row_heights = table.row_heights[rows.clone()].to_vec();
+    let cells = table
+        .cells
+        .iter()
+        .filter(|cell| rows.contains(&cell.row))
+        .cloned()
+        .map(|mut cell| {
+            cell.row -= rows.start;
+            cell.bounds.y -= offset;
+            cell.content.y -= offset;
+            cell
+        })
+        .collect();
+    TableLayout {
+        bounds: Rect::new(
+            table.bounds.x,
+            0.0,
+            table.bounds.width,
+            row_heights.iter().sum(),
+        ),
+        column_widths: table.column_widths.clone(),
+        row_heights,
+        cells,
+        clipped: table.clipped,
+    }
+}
+
+/// Layout content for the rows in `rows` of a table: one paragraph node per
+/// cell, positioned relative to the table.
+fn table_content(table: &TableLayout, rows: std::ops::Range<usize>) -> LayoutContent {
+    let piece = table_rows(table, rows);
+    let cells = piece
+        .cells
+        .into_iter()
+        .map(|cell| {
+            let mut paragraph = ParagraphLayout::new(cell.content);
+            paragraph.lines = cell.lines;
+            paragraph.text = cell.text;
+            paragraph.links = cell.links;
+            paragraph.revisions = cell.revisions;
+            LayoutNode {
+                source_id: cell.source_id,
+                bounds: cell.bounds,
+                content: LayoutContent::Paragraph(paragraph),
+            }
+        })
+        .collect();
+    LayoutContent::Table { cells }
+}
+
+/// Flow blocks onto pages of `page_size`, within `content_rect`.
+pub(crate) fn paginate(
+    blocks: Vec<Block>,
+    page_size: Size,
+    content_rect: Rect,
+    pagination: &Pagination,
+) -> Vec<Page> {
+    let mut pages = vec![Page::new(1, page_size, content_rect)];
+    // Height used on the current page, from the top of the content area.
+    let mut cursor = 0.0;
+    let new_page = |pages: &mut Vec<Page>| {
+        pages.push(Page::new(pages.len() + 1, page_size, content_rect));
+    };
+
+    let mut blocks = blocks.into_iter().peekable();
+    while let Some(block) = blocks.next() {
+        if matches!(block.kind, BlockKind::PageBreak) {
+            new_page(&mut pages);
+            cursor = 0.0;
+            continue;
+        }
+
+        let spacing = |pages: &[Page]| {
+            if pages.last().is_some_and(|page| page.nodes.is_empty()) {
+                0.0
+            } else {
+                pagination.block_spacing
+            }
+        };
+
+        if block.keep_with_next && !pages.last().unwrap().nodes.is_empty() {
+            let next = blocks.peek().map_or(0.0, |next| {
+                pagination.block_spacing + next.kind.min_height(pagination)
+            });
+            let needed = block_height(&block.kind) + next;
+            let left = content_rect.height - cursor - spacing(&pages);
+            if needed > left + EPSILON && needed <= content_rect.height + EPSILON {
+                new_page(&mut pages);
+                cursor = 0.0;
+            }
+        }
+
+        let mut kind = block.kind;
+        loop {
+            let empty = pages.last().unwrap().nodes.is_empty();
+            let gap = spacing(&pages);
+            let available = content_rect.height - cursor - gap;
+            let width = kind.width();
+            let (content, height, rest) = match kind.take(available, empty, pagination) {
+                Take::All(content, height) => (content, height, None),
+                Take::Part(content, height, rest) => (content, height, Some(rest)),
+                Take::Nothing(rest) => {
+                    new_page(&mut pages);
+                    cursor = 0.0;
+                    kind = rest;
+                    continue;
+                }
+            };
+
+            let bounds = Rect::new(content_rect.x, content_rect.y + cursor + gap, width, height);
+            pages.last_mut().unwrap().nodes.push(LayoutNode {
+                source_id: block.source_id,
+                bounds,
+                content,
+            });
+            cursor += gap + height;
+
+            match rest {
+                Some(rest) => {
+                    new_page(&mut pages);
+                    cursor = 0.0;
+                    kind = rest;
+                }
+                None => break,
+            }
+        }
+    }
+
+    pages
+}
+
+fn block_height(kind: &BlockKind) -> f32 {
+    match kind {
+        BlockKind::Paragraph(paragraph) => paragraph.height(),
+        BlockKind::Table(table) => table.row_heights.iter().sum(),
+        BlockKind::Atomic(_, size) => size.height,
+        BlockKind::PageBreak => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::Document;
+    use wolia_core::node::{Node, NodeKind};
+    use wolia_core::text::Text;
+
+    use super::*;
+    use crate::{LayoutEngine, LayoutTree, Margins};
+
+    /// A paragraph that wraps to `lines` lines 100pt wide: three four-letter
+    /// words fit on each.
+    fn paragraph(lines: usize) -> Node {
+        Node::paragraph(Text::new(vec!["word"; lines * 3].join(" ")))
+    }
+
+    /// Lay out on 100x100pt pages with no margins.
+    fn layout(nodes: Vec<Node>, pagination: Pagination) -> LayoutTree {
+        let mut document = Document::new();
+        nodes
+            .into_iter()
+            .for_each(|node| document.root.add_child(node));
+        let engine = LayoutEngine {
+            page_size: Size::new(100.0, 100.0),
+            margins: Margins::uniform(0.0),
+            pagination,
+            ..LayoutEngine::new()
+        };
+        engine.layout(&document).unwrap()
+    }
+
+    fn line_counts(tree: &LayoutTree) -> Vec<Vec<usize>> {
+        tree.pages
+            .iter()
+            .map(|page| {
+                page.nodes
+                    .iter()
+                    .map(|node| match &node.content {
+                        LayoutContent::Paragraph(paragraph) => paragraph.line_count(),
+                        _ => 0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_page_count() {
+        let mut document = Document::new();
+        for _ in 0..100 {
+            document
+                .root
+                .add_child(Node::paragraph(Text::new("A short paragraph.")));
+        }
+        // A4 leaves 698pt between margins, and each one-line paragraph takes
+        // 14.4pt plus 6pt of spacing: 34 fit on a page.
+        let tree = LayoutEngine::new().layout(&document).unwrap();
+        assert_eq!(tree.page_count(), 3);
+        assert_eq!(tree.pages[0].nodes.len(), 34);
+        assert_eq!(tree.pages[2].nodes.len(), 32);
+        assert_eq!(tree.page(3).unwrap().number, 3);
+        assert_eq!(tree.pages[0].nodes[1].bounds.y, 72.0 + 14.4 + 6.0);
+        assert_eq!(tree.total_height, 3.0 * 842.0);
+    }
+
+    #[test]
+    fn test_orphan_control_moves_lone_line() {
+        // Five lines and spacing leave room for one line of the next
+        // paragraph.
+        let nodes = vec![paragraph(5), paragraph(4)];
+        let tree = layout(
+            nodes.clone(),
+            Pagination {
+                orphans: 1,
+                widows: 1,
+                ..Pagination::default()
+            },
+        );
+        assert_eq!(line_counts(&tree), vec![vec![5, 1], vec![3]]);
+
+        let tree = layout(nodes, Pagination::default());
+        assert_eq!(line_counts(&tree), vec![vec![5], vec![4]]);
+        assert_eq!(tree.pages[1].nodes[0].bounds.y, 0.0);
+    }
+
+    #[test]
+    fn test_widow_control_carries_lines() {
+        // Three lines of the second paragraph fit, which would strand its
+        // last line.
+        let tree = layout(vec![paragraph(3), paragraph(4)], Pagination::default());
+        assert_eq!(line_counts(&tree), vec![vec![3, 2], vec![2]]);
+    }
+
+    #[test]
+    fn test_block_taller_than_page_splits() {
+        // Six 14.4pt lines fit on a 100pt page.
+        let tree = layout(vec![paragraph(20)], Pagination::default());
+        assert_eq!(line_counts(&tree), vec![vec![6], vec![6], vec![6], vec![2]]);
+    }
+
+    #[test]
+    fn test_heading_keeps_with_next() {
+        let heading = Node::heading(1, Text::new("Title"));
+        let tree = layout(
+            vec![paragraph(4), heading, paragraph(3)],
+            Pagination::default(),
+        );
+        // The heading would fit below the first paragraph, but the two lines
+        // that must follow it would not.
+        assert_eq!(line_counts(&tree), vec![vec![4], vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_page_break() {
+        let tree = layout(
+            vec![
+                paragraph(1),
+                Node::new(wolia_core::node::NodeKind::PageBreak),
+                paragraph(1),
+            ],
+            Pagination::default(),
+        );
+        assert_eq!(line_counts(&tree), vec![vec![1], vec![1]]);
+    }
+
+    #[test]
+    fn test_plugins_render_claimed_blocks() {
+        use std::sync::Arc;
+        use wolia_core::node::NodeKind;
+        use wolia_plugin::{BlockHandler, BlockRegistry, BlockSource, RenderedBlock};
+
+        struct Labeled;
+
+        impl BlockHandler for Labeled {
+            fn handle_block(&self, block: &BlockSource<'_>) -> Option<RenderedBlock> {
+                let label = format!("{}: {}", block.language?, block.source);
+                Some(RenderedBlock::Text(Box::new(Text::new(label))))
+            }
+        }
+
+        let mut document = Document::new();
+        let code = |language: Option<&str>| {
+            Node::new(NodeKind::CodeBlock {
+                language: language.map(str::to_string),
+                code: "x".to_string(),
+            })
+        };
+        document.root.add_child(code(Some("rust")));
+        document.root.add_child(code(None));
+        let mut plugins = BlockRegistry::new();
+        plugins
+            .claim("labels", &["code"], Arc::new(Labeled))
+            .unwrap();
+
+        let tree = LayoutEngine::new()
+            .layout_with_blocks(&document, &plugins)
+            .unwrap();
+        let texts: Vec<_> = tree.pages[0]
+            .nodes
+            .iter()
+            .map(|node| match &node.content {
+                LayoutContent::Paragraph(paragraph) => paragraph.text.as_str(),
+                _ => "",
+            })
+            .collect();
+        // The handler declines blocks without a language.
+        assert_eq!(texts, ["rust: x", "x"]);
+    }
+
+    #[test]
+    fn test_image_reserves_its_size() {
+        let image = |src: &str| {
+            Node::new(NodeKind::Image {
+                src: src.to_string(),
+                alt: None,
+            })
+        };
+        let mut document = Document::new();
+        document.root.add_child(image("asset:small"));
+        document.root.add_child(image("asset:wide"));
+        document.root.add_child(image("asset:unknown"));
+        let mut engine = LayoutEngine::new();
+        engine.set_image_size("asset:small", Size::new(120.0, 90.0));
+        engine.set_image_size("asset:wide", Size::new(902.0, 451.0));
+
+        // A4 leaves 451pt between the margins: the wide image is scaled
+        // down to fit, keeping its aspect ratio. Blocks are 6pt apart.
+        let tree = engine.layout(&document).unwrap();
+        let bounds: Vec<Rect> = tree.pages[0].nodes.iter().map(|node| node.bounds).collect();
+        assert_eq!(bounds[0], Rect::new(72.0, 72.0, 120.0, 90.0));
+        assert_eq!(bounds[1].size(), Size::new(451.0, 225.5));
+        assert_eq!(bounds[1].y, 72.0 + 90.0 + 6.0);
+        assert_eq!(bounds[2].size(), Size::new(451.0, 451.0 * 0.75));
+        assert_eq!(tree.images()[0], ("asset:small", bounds[0]));
+        assert_eq!(tree.images().len(), 3);
+    }
+}
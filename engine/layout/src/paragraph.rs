@@ -1,10 +1,15 @@
 //! Paragraph layout.
 
+use std::ops::Range;
+
+use wolia_core::style::{ParagraphStyle, RevisionKind, TextStyle};
 use wolia_core::text::Text;
-use wolia_math::Rect;
+use wolia_math::{Point, Rect};
 
 use crate::Constraints;
-use crate::line::Line;
+use crate::float::{Clear, FloatContext};
+use crate::line::{Line, LineFragment};
+use crate::text::{TextLayout, estimate_width};
 
 /// A laid-out paragraph.
 #[derive(Debug, Clone)]
@@ -13,21 +18,161 @@ pub struct ParagraphLayout {
     pub bounds: Rect,
     /// Lines in this paragraph.
     pub lines: Vec<Line>,
+    /// The laid-out text, which line fragments index into.
+    pub text: String,
+    /// Linked parts of the text, with the URL each links to.
+    pub links: Vec<(Range<usize>, String)>,
+    /// Parts of the text suggested for insertion or deletion.
+    pub revisions: Vec<(Range<usize>, RevisionKind)>,
 }
 
+/// Distance of an underline below the baseline.
+const UNDERLINE_OFFSET: f32 = 1.5;
+
+/// Height of a line striking text through above the baseline.
+const STRIKE_RAISE: f32 = 3.5;
+
+/// Thickness of an underline or a line striking text through.
+const UNDERLINE_THICKNESS: f32 = 1.0;
+
 impl ParagraphLayout {
     /// Create a new paragraph layout.
     pub fn new(bounds: Rect) -> Self {
         Self {
             bounds,
             lines: Vec::new(),
+            text: String::new(),
+            links: Vec::new(),
+            revisions: Vec::new(),
+        }
+    }
+
+    /// Layout text into a paragraph, wrapping it to the maximum width of
+    /// the constraints with estimated glyph widths and hyphenating words as
+    /// the constraints allow.
+    pub fn layout(text: &Text, constraints: Constraints) -> Self {
+        Self::layout_indented(text, &ParagraphStyle::default(), constraints)
+    }
+
+    /// Layout text as by [`layout`](Self::layout), indented as `style`
+    /// sets: lines start `margin_left` in and end `margin_right` short of
+    /// the maximum width, and the first line starts `first_line_indent`
+    /// further in, or out for a hanging indent. Other paragraph properties
+    /// are not applied.
+    pub fn layout_indented(text: &Text, style: &ParagraphStyle, constraints: Constraints) -> Self {
+        let text_style = TextStyle::default();
+        let paragraph_style = ParagraphStyle::default();
+        let font_size = text_style.font_size.unwrap_or(12.0);
+        let width = constraints.max.width;
+        let left = style.margin_left.unwrap_or(0.0).max(0.0);
+        let right = style.margin_right.unwrap_or(0.0).max(0.0);
+        let first = (left + style.first_line_indent.unwrap_or(0.0)).max(0.0);
+        let indent = |y: f32| if y == 0.0 { first } else { left };
+
+        let lines = TextLayout::new(width)
+            .with_hyphenation(constraints.hyphenation)
+            .layout_lines_in(
+                &text.content,
+                |y, _| (indent(y), (width - indent(y) - right).max(0.0)),
+                &text_style,
+                &paragraph_style,
+                |run| estimate_width(run, font_size),
+            );
+        let content_width = lines
+            .iter()
+            .map(|line| indent(line.bounds.y) + line.bounds.width)
+            .fold(0.0, f32::max);
+        let content_height = lines.last().map_or(0.0, |line| line.bounds.bottom());
+
+        Self {
+            bounds: Rect::new(
+                0.0,
+                0.0,
+                content_width.clamp(constraints.min.width, width),
+                content_height.max(constraints.min.height),
+            ),
+            lines,
+            text: text.content.clone(),
+            links: links(text),
+            revisions: revisions(text),
         }
     }
 
-    /// Layout text into a paragraph.
-    pub fn layout(_text: &Text, _constraints: Constraints) -> Self {
-        // TODO: Implement text shaping and line breaking
-        Self::new(Rect::ZERO)
+    /// Layout text into a paragraph that starts at `y` in a region holding
+    /// floats, wrapping each line to the space the floats leave beside it.
+    ///
+    /// The paragraph first moves down below any floats it must `clear`.
+    /// Unlike [`layout`](Self::layout), the bounds and lines are positioned
+    /// in the region's coordinates, with lines beside a left float starting
+    /// at its right edge.
+    pub fn layout_with_floats(
+        text: &Text,
+        constraints: Constraints,
+        floats: &FloatContext,
+        y: f32,
+        clear: Clear,
+    ) -> Self {
+        let text_style = TextStyle::default();
+        let paragraph_style = ParagraphStyle::default();
+        let font_size = text_style.font_size.unwrap_or(12.0);
+        let width = constraints.max.width;
+        let top = floats.clear(clear, y);
+
+        let mut lines = TextLayout::new(width)
+            .with_hyphenation(constraints.hyphenation)
+            .layout_lines_in(
+                &text.content,
+                |line_y, height| {
+                    let (left, available) = floats.band(top + line_y, height);
+                    (left, available.min(width - left).max(0.0))
+                },
+                &text_style,
+                &paragraph_style,
+                |run| estimate_width(run, font_size),
+            );
+        for line in &mut lines {
+            line.bounds.y += top;
+            for fragment in &mut line.fragments {
+                fragment.bounds.y += top;
+            }
+        }
+        let content_height = lines.last().map_or(0.0, |line| line.bounds.bottom() - top);
+
+        Self {
+            bounds: Rect::new(0.0, top, width, content_height.max(constraints.min.height)),
+            lines,
+            text: text.content.clone(),
+            links: links(text),
+            revisions: revisions(text),
+        }
+    }
+
+    /// Split the paragraph after its first `at` lines, returning the rest as
+    /// a paragraph whose lines start again from the top.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let rest: Vec<Line> = self.lines.split_off(at.min(self.lines.len()));
+        let offset = rest.first().map_or(0.0, |line| line.bounds.y);
+        let rest: Vec<Line> = rest
+            .into_iter()
+            .map(|mut line| {
+                line.bounds.y -= offset;
+                for fragment in &mut line.fragments {
+                    fragment.bounds.y -= offset;
+                }
+                line
+            })
+            .collect();
+
+        let height = |lines: &[Line]| lines.last().map_or(0.0, |line| line.bounds.bottom());
+        self.bounds.height =
+            height(&self.lines) - self.lines.first().map_or(0.0, |line| line.bounds.y);
+        Self {
+            bounds: Rect::new(self.bounds.x, 0.0, self.bounds.width, height(&rest)),
+            lines: rest,
+            text: self.text.clone(),
+            links: self.links.clone(),
+            revisions: self.revisions.clone(),
+        }
     }
 
     /// Get the total height.
@@ -39,4 +184,488 @@ impl ParagraphLayout {
     pub fn line_count(&self) -> usize {
         self.lines.len()
     }
+
+    /// The offset into [`text`](Self::text) nearest to `point`, which is in
+    /// the coordinates the lines are positioned in.
+    ///
+    /// Points above or below the paragraph hit its first or last line, and
+    /// points beside a line hit its nearest end.
+    pub fn offset_at(&self, point: Point) -> usize {
+        let Some(index) = self
+            .lines
+            .iter()
+            .position(|line| point.y < line.bounds.bottom())
+            .or(self.lines.len().checked_sub(1))
+        else {
+            return 0;
+        };
+        let distance = |fragment: &&LineFragment| {
+            let bounds = fragment.bounds;
+            (bounds.x - point.x).max(point.x - bounds.right()).max(0.0)
+        };
+        let nearest = self.lines[index]
+            .fragments
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        match nearest {
+            Some(fragment) => self.offset_in(fragment, point.x),
+            // An empty line sits after the text of the lines before it.
+            None => self.lines[..index]
+                .iter()
+                .flat_map(|line| &line.fragments)
+                .map(|fragment| fragment.text_start + fragment.text_len)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// The index of the line holding the caret before `offset`, with the
+    /// offset its text starts at, or `None` if no line holds the offset.
+    /// As with [`caret_rect`](Self::caret_rect), an offset where a line
+    /// wraps is on the next line.
+    pub fn line_of(&self, offset: usize) -> Option<(usize, usize)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, line)| {
+                let holds = line.fragments.iter().any(|fragment| {
+                    (fragment.text_start..=fragment.text_start + fragment.text_len)
+                        .contains(&offset)
+                });
+                let start = line
+                    .fragments
+                    .iter()
+                    .map(|fragment| fragment.text_start)
+                    .min();
+                start.filter(|_| holds).map(|start| (index, start))
+            })
+    }
+
+    /// A zero-width rectangle at the caret before `offset`, as tall as its
+    /// line, or `None` if no line of this paragraph holds the offset.
+    ///
+    /// An offset where a line wraps is shown at the start of the next line.
+    pub fn caret_rect(&self, offset: usize) -> Option<Rect> {
+        let mut found = None;
+        for line in &self.lines {
+            for fragment in &line.fragments {
+                let end = fragment.text_start + fragment.text_len;
+                if (fragment.text_start..=end).contains(&offset) {
+                    found = Some((line, fragment));
+                    if offset < end {
+                        break;
+                    }
+                }
+            }
+        }
+        let Some((line, fragment)) = found else {
+            // An empty paragraph has no lines, but still shows a caret as
+            // tall as a line of its text would be.
+            if !self.text.is_empty() || offset != 0 {
+                return None;
+            }
+            let font_size = TextStyle::default().font_size.unwrap_or(12.0);
+            let line_height = font_size * ParagraphStyle::default().line_height.unwrap_or(1.2);
+            return Some(Rect::new(self.bounds.x, self.bounds.y, 0.0, line_height));
+        };
+        let x = self.boundary_x(fragment, offset - fragment.text_start);
+        Some(Rect::new(x, line.bounds.y, 0.0, line.bounds.height))
+    }
+
+    /// Rectangles covering the text in `range`, one for each run of it on
+    /// a line, each as tall as its line.
+    pub fn selection_rects(&self, range: Range<usize>) -> Vec<Rect> {
+        self.runs_in(range)
+            .into_iter()
+            .map(|(line, left, right)| {
+                Rect::new(left, line.bounds.y, right - left, line.bounds.height)
+            })
+            .collect()
+    }
+
+    /// Rectangles underlining the linked text, one for each run of a link
+    /// on a line.
+    pub fn link_underlines(&self) -> Vec<Rect> {
+        self.links
+            .iter()
+            .flat_map(|(range, _)| self.runs_in(range.clone()))
+            .map(|(line, left, right)| {
+                let y = line.bounds.y + line.baseline + UNDERLINE_OFFSET;
+                Rect::new(left, y, right - left, UNDERLINE_THICKNESS)
+            })
+            .collect()
+    }
+
+    /// Rectangles underlining the text suggested for insertion and striking
+    /// through the text suggested for deletion, one for each run of a
+    /// suggestion on a line, with the kind of suggestion each marks.
+    pub fn revision_marks(&self) -> Vec<(Rect, RevisionKind)> {
+        self.revisions
+            .iter()
+            .flat_map(|(range, kind)| {
+                self.runs_in(range.clone())
+                    .into_iter()
+                    .map(move |(line, left, right)| {
+                        let baseline = line.bounds.y + line.baseline;
+                        let y = match kind {
+                            RevisionKind::Insertion => baseline + UNDERLINE_OFFSET,
+                            RevisionKind::Deletion => baseline - STRIKE_RAISE,
+                        };
+                        (Rect::new(left, y, right - left, UNDERLINE_THICKNESS), *kind)
+                    })
+            })
+            .collect()
+    }
+
+    /// Each run of the text in `range` on a line, with the line and the
+    /// left and right edges of the run.
+    fn runs_in(&self, range: Range<usize>) -> Vec<(&Line, f32, f32)> {
+        let mut runs = Vec::new();
+        for line in &self.lines {
+            for fragment in &line.fragments {
+                let start = range.start.max(fragment.text_start);
+                let end = range.end.min(fragment.text_start + fragment.text_len);
+                if start >= end {
+                    continue;
+                }
+                let a = self.boundary_x(fragment, start - fragment.text_start);
+                let b = self.boundary_x(fragment, end - fragment.text_start);
+                runs.push((line, a.min(b), a.max(b)));
+            }
+        }
+        runs
+    }
+
+    /// The character boundary of a fragment nearest to `x`.
+    fn offset_in(&self, fragment: &LineFragment, x: f32) -> usize {
+        let Some(content) = self.fragment_text(fragment) else {
+            return fragment.text_start;
+        };
+        content
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([content.len()])
+            .map(|offset| (offset, (self.boundary_x(fragment, offset) - x).abs()))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(fragment.text_start, |(offset, _)| {
+                fragment.text_start + offset
+            })
+    }
+
+    /// Where the boundary `offset` bytes into a fragment is drawn.
+    /// Characters are spread over the fragment in proportion to their
+    /// estimated widths, which also accounts for any justification
+    /// stretching it.
+    fn boundary_x(&self, fragment: &LineFragment, offset: usize) -> f32 {
+        let bounds = fragment.bounds;
+        let content = self.fragment_text(fragment).unwrap_or_default();
+        let total = estimate_width(content, 1.0);
+        let share = match content.get(..offset) {
+            Some(before) if total > 0.0 => estimate_width(before, 1.0) / total,
+            _ => 0.0,
+        };
+        if fragment.rtl {
+            bounds.right() - share * bounds.width
+        } else {
+            bounds.x + share * bounds.width
+        }
+    }
+
+    fn fragment_text(&self, fragment: &LineFragment) -> Option<&str> {
+        self.text
+            .get(fragment.text_start..fragment.text_start + fragment.text_len)
+    }
+}
+
+/// The linked parts of `text` with their URLs, in order, with touching
+/// spans linking to the same URL joined.
+fn links(text: &Text) -> Vec<(Range<usize>, String)> {
+    let mut spans: Vec<_> = text
+        .spans
+        .iter()
+        .filter(|span| span.start < span.end)
+        .filter_map(|span| Some((span.start..span.end, span.style.link.clone()?)))
+        .collect();
+    spans.sort_by_key(|(range, _)| range.start);
+    let mut links: Vec<(Range<usize>, String)> = Vec::new();
+    for (range, url) in spans {
+        match links.last_mut() {
+            Some((last, last_url)) if *last_url == url && range.start <= last.end => {
+                last.end = last.end.max(range.end);
+            }
+            _ => links.push((range, url)),
+        }
+    }
+    links
+}
+
+/// The parts of `text` suggested for insertion or deletion, in order, with
+/// touching parts of the same kind joined.
+fn revisions(text: &Text) -> Vec<(Range<usize>, RevisionKind)> {
+    let mut spans: Vec<_> = text
+        .spans
+        .iter()
+        .filter(|span| span.start < span.end)
+        .filter_map(|span| Some((span.start..span.end, span.style.revision.as_ref()?.kind)))
+        .collect();
+    spans.sort_by_key(|(range, _)| range.start);
+    let mut revisions: Vec<(Range<usize>, RevisionKind)> = Vec::new();
+    for (range, kind) in spans {
+        match revisions.last_mut() {
+            Some((last, last_kind)) if *last_kind == kind && range.start <= last.end => {
+                last.end = last.end.max(range.end);
+            }
+            _ => revisions.push((range, kind)),
+        }
+    }
+    revisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hyphenation;
+    use crate::float::Float;
+    use wolia_math::Size;
+
+    #[test]
+    fn test_offset_at_points() {
+        // Six points per character: "one two" on the first line, "three"
+        // on the second, 14.4pt apart.
+        let text = Text::new("one two three");
+        let layout = ParagraphLayout::layout(&text, Constraints::loose(Size::new(48.0, 1000.0)));
+        assert_eq!(layout.line_count(), 2);
+        // Nearest boundary: 8pt is closer to "o|ne" than "on|e".
+        assert_eq!(layout.offset_at(Point::new(8.0, 5.0)), 1);
+        assert_eq!(layout.offset_at(Point::new(10.0, 5.0)), 2);
+        // Past the end of the first line, and on the second line.
+        assert_eq!(layout.offset_at(Point::new(200.0, 5.0)), 7);
+        assert_eq!(layout.offset_at(Point::new(13.0, 20.0)), 10);
+        // Above and below the paragraph.
+        assert_eq!(layout.offset_at(Point::new(-5.0, -50.0)), 0);
+        assert_eq!(layout.offset_at(Point::new(400.0, 500.0)), 13);
+    }
+
+    #[test]
+    fn test_links_are_underlined_on_every_line() {
+        use wolia_core::text::Span;
+
+        let link = |url: &str| TextStyle {
+            link: Some(url.to_string()),
+            ..TextStyle::default()
+        };
+        let mut text = Text::new("one two three");
+        text.add_span(Span::new(4, 7, link("https://example.com")));
+        text.add_span(Span::new(7, 13, link("https://example.com")));
+        let layout = ParagraphLayout::layout(&text, Constraints::loose(Size::new(48.0, 1000.0)));
+        assert_eq!(
+            layout.links,
+            vec![(4..13, "https://example.com".to_string())]
+        );
+
+        // "two" on the first line and "three" on the second, just below
+        // their baselines.
+        let underlines = layout.link_underlines();
+        assert_eq!(underlines.len(), 2);
+        for (underline, line) in underlines.iter().zip(&layout.lines) {
+            assert!(underline.y > line.bounds.y + line.baseline);
+            assert!(underline.bottom() <= line.bounds.bottom());
+        }
+        assert_eq!(underlines[1].width, 5.0 * 6.0);
+        let mut first = layout.clone();
+        assert_eq!(first.split_off(1).links, layout.links);
+    }
+
+    #[test]
+    fn test_suggestions_are_underlined_or_struck() {
+        use wolia_core::style::Revision;
+        use wolia_core::text::Span;
+
+        let marked = |kind| TextStyle {
+            revision: Some(Revision::new(kind, "ana", 0)),
+            ..TextStyle::default()
+        };
+        let mut text = Text::new("one two three");
+        text.add_span(Span::new(0, 3, marked(RevisionKind::Deletion)));
+        text.add_span(Span::new(4, 7, marked(RevisionKind::Insertion)));
+        let layout = ParagraphLayout::layout(&text, Constraints::loose(Size::new(48.0, 1000.0)));
+        assert_eq!(
+            layout.revisions,
+            vec![
+                (0..3, RevisionKind::Deletion),
+                (4..7, RevisionKind::Insertion)
+            ]
+        );
+
+        let line = &layout.lines[0];
+        let baseline = line.bounds.y + line.baseline;
+        let [(struck, _), (underlined, _)] = layout.revision_marks().try_into().unwrap();
+        assert!(struck.y < baseline && struck.y > line.bounds.y);
+        assert!(underlined.y > baseline);
+        assert_eq!(struck.width, 3.0 * 6.0);
+        assert!((underlined.x - 4.0 * 6.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_caret_matches_glyph_positions() {
+        let text = Text::new("one two three");
+        let layout = ParagraphLayout::layout(&text, Constraints::loose(Size::new(48.0, 1000.0)));
+        let second = &layout.lines[1];
+        let glyph_x = second.fragments[0].bounds.x + 2.0 * 6.0;
+        // Between "th" and "ree", as tall as the second line.
+        assert_eq!(
+            layout.caret_rect(10),
+            Some(Rect::new(
+                glyph_x,
+                second.bounds.y,
+                0.0,
+                second.bounds.height
+            ))
+        );
+        // The wrap point shows at the start of the second line.
+        let wrap = layout.caret_rect(8).unwrap();
+        assert_eq!((wrap.x, wrap.y), (second.bounds.x, second.bounds.y));
+        assert_eq!(layout.caret_rect(99), None);
+        assert_eq!(layout.line_of(10), Some((1, 8)));
+        assert_eq!(layout.line_of(8), Some((1, 8)));
+        assert_eq!(layout.line_of(3), Some((0, 0)));
+        assert_eq!(layout.line_of(99), None);
+    }
+
+    #[test]
+    fn test_selection_spans_lines() {
+        let text = Text::new("one two three");
+        let layout = ParagraphLayout::layout(&text, Constraints::loose(Size::new(48.0, 1000.0)));
+        let rects = layout.selection_rects(4..10);
+        // "two" on the first line and "th" on the second.
+        assert_eq!(rects.len(), 2);
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        assert!(close(rects[0].x, 24.0) && close(rects[0].width, 18.0));
+        assert!(close(rects[1].x, 0.0) && close(rects[1].width, 12.0));
+        assert_eq!(rects[1].y, layout.lines[1].bounds.y);
+        assert_eq!(rects[1].height, layout.lines[1].bounds.height);
+    }
+
+    fn line_texts<'a>(text: &'a Text, layout: &ParagraphLayout) -> Vec<(&'a str, bool)> {
+        layout
+            .lines
+            .iter()
+            .map(|line| {
+                let fragment = &line.fragments[0];
+                let start = fragment.text_start;
+                (
+                    &text.content[start..start + fragment.text_len],
+                    line.hyphenated,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hyphenates_long_word_in_narrow_column() {
+        let text = Text::new("a hyphenation example");
+        // Twelve characters at the estimated 6pt per character.
+        let constraints =
+            Constraints::loose(Size::new(72.0, 1000.0)).with_hyphenation(Hyphenation::english());
+        let layout = ParagraphLayout::layout(&text, constraints);
+        let lines = line_texts(&text, &layout);
+        assert_eq!(lines[0], ("a hyphen", true));
+        assert!(lines[1].0.starts_with("ation"));
+        // The hyphen counts toward the line's width.
+        assert_eq!(layout.lines[0].bounds.width, 54.0);
+    }
+
+    #[test]
+    fn test_disabled_hyphenation_breaks_whole_words() {
+        let text = Text::new("a hyphenation example");
+        let layout = ParagraphLayout::layout(&text, Constraints::loose(Size::new(72.0, 1000.0)));
+        assert_eq!(
+            line_texts(&text, &layout),
+            vec![("a", false), ("hyphenation", false), ("example", false)]
+        );
+    }
+
+    #[test]
+    fn test_well_filled_lines_are_not_hyphenated() {
+        let text = Text::new("abcdefghij hyphenation");
+        let constraints =
+            Constraints::loose(Size::new(72.0, 1000.0)).with_hyphenation(Hyphenation::english());
+        let layout = ParagraphLayout::layout(&text, constraints);
+        assert_eq!(
+            line_texts(&text, &layout),
+            vec![("abcdefghij", false), ("hyphenation", false)]
+        );
+    }
+
+    #[test]
+    fn test_indents() {
+        let text = Text::new("aaaa bbbb cccc dddd eeee");
+        let style = ParagraphStyle {
+            margin_left: Some(12.0),
+            margin_right: Some(6.0),
+            first_line_indent: Some(24.0),
+            ..ParagraphStyle::default()
+        };
+        let constraints = Constraints::loose(Size::new(90.0, 1000.0));
+        let layout = ParagraphLayout::layout_indented(&text, &style, constraints);
+        let starts: Vec<f32> = layout.lines.iter().map(|line| line.bounds.x).collect();
+        // The first line has 48pt, room for one word, and the rest 72pt,
+        // room for two.
+        assert_eq!(starts, vec![36.0, 12.0, 12.0]);
+        assert_eq!(layout.line_of(10), Some((1, 5)));
+
+        // A hanging indent starts the first line left of the others.
+        let hanging = ParagraphStyle {
+            first_line_indent: Some(-12.0),
+            ..style
+        };
+        let layout = ParagraphLayout::layout_indented(&text, &hanging, constraints);
+        assert_eq!(layout.lines[0].bounds.x, 0.0);
+        assert_eq!(layout.lines[1].bounds.x, 12.0);
+    }
+
+    #[test]
+    fn test_flows_around_left_float() {
+        let mut floats = FloatContext::new(Size::new(120.0, 1000.0));
+        floats.place(Float::left(Size::new(60.0, 30.0)), 0.0);
+        let text = Text::new("aaaa bbbb cccc dddd eeee ffff gggg hhhh iiii jjjj");
+        let layout = ParagraphLayout::layout_with_floats(
+            &text,
+            Constraints::loose(Size::new(120.0, 1000.0)),
+            &floats,
+            0.0,
+            Clear::None,
+        );
+        // The first three lines (14.4pt each) overlap the float and fit two
+        // words in the 60pt it leaves; the last resumes the full width.
+        let starts: Vec<f32> = layout.lines.iter().map(|line| line.bounds.x).collect();
+        assert_eq!(starts, vec![60.0, 60.0, 60.0, 0.0]);
+        assert_eq!(layout.lines[0].bounds.width, 54.0);
+        assert_eq!(layout.lines[3].bounds.width, 114.0);
+        assert!((layout.lines[3].bounds.y - 3.0 * 14.4).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_clear_moves_below_floats() {
+        let mut floats = FloatContext::new(Size::new(120.0, 1000.0));
+        floats.place(Float::left(Size::new(60.0, 30.0)), 0.0);
+        floats.place(Float::right(Size::new(30.0, 50.0)), 0.0);
+        let text = Text::new("aaaa");
+        let constraints = Constraints::loose(Size::new(120.0, 1000.0));
+
+        let left =
+            ParagraphLayout::layout_with_floats(&text, constraints, &floats, 0.0, Clear::Left);
+        assert_eq!(left.bounds.y, 30.0);
+        // Still beside the right float.
+        assert_eq!(left.lines[0].bounds.x, 0.0);
+        assert_eq!(left.lines[0].bounds.y, 30.0);
+
+        let both =
+            ParagraphLayout::layout_with_floats(&text, constraints, &floats, 0.0, Clear::Both);
+        assert_eq!(both.bounds.y, 50.0);
+        let none =
+            ParagraphLayout::layout_with_floats(&text, constraints, &floats, 0.0, Clear::None);
+        assert_eq!(none.lines[0].bounds.x, 60.0);
+    }
 }
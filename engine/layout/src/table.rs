@@ -0,0 +1,635 @@
+//! Table layout.
+//!
+//! Tables are laid out in three passes. Cells are first placed on a grid,
+//! skipping slots covered by row spans from earlier rows. Column widths are
+//! then resolved: fixed and percentage columns take their specified width,
+//! auto columns size to their content, and cells spanning several columns
+//! widen the columns they cover if those are too narrow. Finally each cell's
+//! text is wrapped to its width, and every row grows to fit its tallest cell.
+//!
+//! A table that does not fit the available width first has its auto columns
+//! narrowed toward their narrowest content; if that is not enough the table
+//! is scaled down or clipped as [`TableOverflow`] says.
+
+use std::ops::Range;
+
+use uuid::Uuid;
+use wolia_core::node::{Node, NodeKind};
+use wolia_core::style::RevisionKind;
+use wolia_core::text::{Span, Text};
+use wolia_math::{Rect, Size};
+
+use crate::line::Line;
+use crate::paragraph::ParagraphLayout;
+use crate::text::estimate_width;
+use crate::{Constraints, Error, Result};
+
+/// Font size used to estimate cell content widths.
+const CELL_FONT_SIZE: f32 = 12.0;
+
+/// How wide a column should be.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColumnWidth {
+    /// Size to the column's content.
+    #[default]
+    Auto,
+    /// A fixed width in points.
+    Fixed(f32),
+    /// A percentage of the available width.
+    Percent(f32),
+}
+
+/// What to do with a table wider than the available width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableOverflow {
+    /// Scale every column down by the same factor.
+    #[default]
+    Scale,
+    /// Keep the column widths and cut the table off at the available width.
+    Clip,
+}
+
+/// Table layout options.
+#[derive(Debug, Clone, Default)]
+pub struct TableStyle {
+    /// Width of each column. Columns without an entry are auto-sized.
+    pub columns: Vec<ColumnWidth>,
+    /// Space between a cell's edges and its content, in points.
+    pub padding: f32,
+    /// How tables wider than the available width are fitted.
+    pub overflow: TableOverflow,
+}
+
+impl TableStyle {
+    /// Create options with auto-sized columns and no padding.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the column widths.
+    pub fn with_columns(mut self, columns: Vec<ColumnWidth>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Set the cell padding.
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set how tables wider than the available width are fitted.
+    pub fn with_overflow(mut self, overflow: TableOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+/// A laid-out table cell.
+#[derive(Debug, Clone)]
+pub struct CellLayout {
+    /// First row the cell occupies.
+    pub row: usize,
+    /// First column the cell occupies.
+    pub col: usize,
+    /// Number of rows the cell spans.
+    pub row_span: usize,
+    /// Number of columns the cell spans.
+    pub col_span: usize,
+    /// Cell rectangle, relative to the table's top-left corner.
+    pub bounds: Rect,
+    /// Content rectangle: the cell bounds inset by the padding.
+    pub content: Rect,
+    /// Wrapped lines of the cell's text, relative to the content rectangle.
+    pub lines: Vec<Line>,
+    /// The cell's text, which line fragments index into.
+    pub text: String,
+    /// Linked parts of the cell's text, with the URL each links to.
+    pub links: Vec<(Range<usize>, String)>,
+    /// Parts of the cell's text suggested for insertion or deletion.
+    pub revisions: Vec<(Range<usize>, RevisionKind)>,
+    /// ID of the first text block of the cell, whose text the cell's text
+    /// starts with, or nil if the cell holds no text block.
+    pub source_id: Uuid,
+}
+
+/// A laid-out table.
+#[derive(Debug, Clone)]
+pub struct TableLayout {
+    /// Bounding box of the table, starting at the origin.
+    pub bounds: Rect,
+    /// Resolved width of each column.
+    pub column_widths: Vec<f32>,
+    /// Resolved height of each row.
+    pub row_heights: Vec<f32>,
+    /// Cells in document order.
+    pub cells: Vec<CellLayout>,
+    /// Whether columns extend past `bounds` and must be clipped to it.
+    pub clipped: bool,
+}
+
+/// A cell placed on the grid, before sizing.
+struct GridCell {
+    row: usize,
+    col: usize,
+    row_span: usize,
+    col_span: usize,
+    text: Text,
+    source_id: Uuid,
+}
+
+impl TableLayout {
+    /// Lay out a [`NodeKind::Table`] node within the maximum width of the
+    /// constraints.
+    pub fn layout(table: &Node, style: &TableStyle, constraints: Constraints) -> Result<Self> {
+        if !matches!(table.kind, NodeKind::Table { .. }) {
+            return Err(Error::InvalidConstraint(
+                "table layout requires a table node".to_string(),
+            ));
+        }
+        let available = constraints.max.width;
+        let padding = style.padding.max(0.0);
+
+        let (cells, row_count, col_count) = place_cells(table);
+        let mut column_widths = resolve_columns(&cells, col_count, style, available);
+
+        let total: f32 = column_widths.iter().sum();
+        let mut clipped = false;
+        if total > available && total > 0.0 {
+            match style.overflow {
+                TableOverflow::Scale => {
+                    let factor = available / total;
+                    column_widths.iter_mut().for_each(|width| *width *= factor);
+                }
+                TableOverflow::Clip => clipped = true,
+            }
+        }
+
+        let mut column_x = vec![0.0];
+        for width in &column_widths {
+            column_x.push(column_x.last().unwrap() + width);
+        }
+
+        // Wrap each cell's text to its final width.
+        let mut laid_out: Vec<(ParagraphLayout, f32)> = Vec::with_capacity(cells.len());
+        for cell in &cells {
+            let width = column_x[cell.col + cell.col_span] - column_x[cell.col];
+            let content_width = (width - 2.0 * padding).max(0.0);
+            let cell_constraints = Constraints::loose(Size::new(content_width, f32::INFINITY))
+                .with_hyphenation(constraints.hyphenation);
+            let paragraph = ParagraphLayout::layout(&cell.text, cell_constraints);
+            let height = paragraph.height() + 2.0 * padding;
+            laid_out.push((paragraph, height));
+        }
+
+        // Rows fit their tallest single-row cell, then grow evenly to fit
+        // cells spanning several rows.
+        let mut row_heights = vec![0.0f32; row_count];
+        for (cell, (_, height)) in cells.iter().zip(&laid_out) {
+            if cell.row_span == 1 {
+                row_heights[cell.row] = row_heights[cell.row].max(*height);
+            }
+        }
+        for (cell, (_, height)) in cells.iter().zip(&laid_out) {
+            if cell.row_span > 1 {
+                let rows = &mut row_heights[cell.row..cell.row + cell.row_span];
+                let spanned: f32 = rows.iter().sum();
+                if *height > spanned {
+                    let extra = (height - spanned) / cell.row_span as f32;
+                    rows.iter_mut().for_each(|row| *row += extra);
+                }
+            }
+        }
+
+        let mut row_y = vec![0.0];
+        for height in &row_heights {
+            row_y.push(row_y.last().unwrap() + height);
+        }
+
+        let cells = cells
+            .into_iter()
+            .zip(laid_out)
+            .map(|(cell, (paragraph, _))| {
+                let x = column_x[cell.col];
+                let y = row_y[cell.row];
+                let bounds = Rect::new(
+                    x,
+                    y,
+                    column_x[cell.col + cell.col_span] - x,
+                    row_y[cell.row + cell.row_span] - y,
+                );
+                let content = Rect::new(
+                    x + padding,
+                    y + padding,
+                    (bounds.width - 2.0 * padding).max(0.0),
+                    (bounds.height - 2.0 * padding).max(0.0),
+                );
+                CellLayout {
+                    row: cell.row,
+                    col: cell.col,
+                    row_span: cell.row_span,
+                    col_span: cell.col_span,
+                    bounds,
+                    content,
+                    lines: paragraph.lines,
+                    text: paragraph.text,
+                    links: paragraph.links,
+                    revisions: paragraph.revisions,
+                    source_id: cell.source_id,
+                }
+            })
+            .collect();
+
+        let width = column_x.last().copied().unwrap_or(0.0);
+        let height = row_y.last().copied().unwrap_or(0.0);
+        Ok(Self {
+            bounds: Rect::new(
+                0.0,
+                0.0,
+                width.min(available).max(constraints.min.width),
+                height.max(constraints.min.height),
+            ),
+            column_widths,
+            row_heights,
+            cells,
+            clipped,
+        })
+    }
+
+    /// Number of rows.
+    pub fn row_count(&self) -> usize {
+        self.row_heights.len()
+    }
+
+    /// Number of columns.
+    pub fn column_count(&self) -> usize {
+        self.column_widths.len()
+    }
+}
+
+/// Place the table's cells on a grid.
+///
+/// Returns the cells with the number of rows and columns the grid needs.
+fn place_cells(table: &Node) -> (Vec<GridCell>, usize, usize) {
+    let declared_cols = match table.kind {
+        NodeKind::Table { cols, .. } => cols,
+        _ => 0,
+    };
+    let rows: Vec<&Node> = table
+        .children
+        .iter()
+        .filter(|row| matches!(row.kind, NodeKind::TableRow))
+        .collect();
+
+    let mut cells = Vec::new();
+    // Rows still covered by a row span from above, per column.
+    let mut covered: Vec<usize> = Vec::new();
+    let mut col_count = declared_cols;
+    let mut row_count = rows.len();
+
+    for (row_index, row) in rows.iter().enumerate() {
+        let mut col = 0;
+        for node in &row.children {
+            let NodeKind::TableCell { col_span, row_span } = node.kind else {
+                continue;
+            };
+            let (col_span, row_span) = (col_span.max(1), row_span.max(1));
+            while covered.get(col).is_some_and(|&rows| rows > 0) {
+                col += 1;
+            }
+            if covered.len() < col + col_span {
+                covered.resize(col + col_span, 0);
+            }
+            covered[col..col + col_span]
+                .iter_mut()
+                .for_each(|rows| *rows = row_span);
+
+            cells.push(GridCell {
+                row: row_index,
+                col,
+                row_span,
+                col_span,
+                text: cell_text(node),
+                source_id: first_block(node).unwrap_or(Uuid::nil()),
+            });
+            col += col_span;
+            col_count = col_count.max(col);
+            row_count = row_count.max(row_index + row_span);
+        }
+        covered
+            .iter_mut()
+            .for_each(|rows| *rows = rows.saturating_sub(1));
+    }
+
+    (cells, row_count, col_count)
+}
+
+/// The text of a cell, one line per block, with the formatting of its
+/// blocks.
+fn cell_text(cell: &Node) -> Text {
+    fn collect(node: &Node, text: &mut Text, first: &mut bool) {
+        let block = match &node.kind {
+            NodeKind::Paragraph(block) | NodeKind::Heading { text: block, .. } => block.clone(),
+            NodeKind::CodeBlock { code, .. } => Text::new(code.clone()),
+            _ => {
+                node.children
+                    .iter()
+                    .for_each(|child| collect(child, text, first));
+                return;
+            }
+        };
+        if !std::mem::take(first) {
+            text.content.push('\n');
+        }
+        let start = text.content.len();
+        text.content.push_str(&block.content);
+        for span in block.spans {
+            text.add_span(Span::new(start + span.start, start + span.end, span.style));
+        }
+    }
+    let mut text = Text::empty();
+    collect(cell, &mut text, &mut true);
+    text
+}
+
+/// ID of the first text block under `node`.
+fn first_block(node: &Node) -> Option<Uuid> {
+    match node.kind {
+        NodeKind::Paragraph(_) | NodeKind::Heading { .. } | NodeKind::CodeBlock { .. } => {
+            Some(node.id)
+        }
+        _ => node.children.iter().find_map(first_block),
+    }
+}
+
+/// Narrowest width the text fits in without splitting words, and the width
+/// it takes without wrapping.
+fn content_widths(text: &Text) -> (f32, f32) {
+    let measure = |run: &str| estimate_width(run, CELL_FONT_SIZE);
+    let min = text
+        .content
+        .split_whitespace()
+        .map(measure)
+        .fold(0.0, f32::max);
+    let max = text.content.lines().map(measure).fold(0.0, f32::max);
+    (min, max)
+}
+
+/// Resolve the width of every column.
+fn resolve_columns(
+    cells: &[GridCell],
+    col_count: usize,
+    style: &TableStyle,
+    available: f32,
+) -> Vec<f32> {
+    let padding = 2.0 * style.padding.max(0.0);
+    let specs: Vec<ColumnWidth> = (0..col_count)
+        .map(|col| style.columns.get(col).copied().unwrap_or_default())
+        .collect();
+    let is_auto = |col: usize| specs[col] == ColumnWidth::Auto;
+
+    // Content widths of auto columns, from the cells that fit in one column.
+    let mut min = vec![0.0f32; col_count];
+    let mut max = vec![0.0f32; col_count];
+    let widths: Vec<(f32, f32)> = cells
+        .iter()
+        .map(|cell| {
+            let (lo, hi) = content_widths(&cell.text);
+            (lo + padding, hi + padding)
+        })
+        .collect();
+    for (cell, &(lo, hi)) in cells.iter().zip(&widths) {
+        if cell.col_span == 1 {
+            min[cell.col] = min[cell.col].max(lo);
+            max[cell.col] = max[cell.col].max(hi);
+        }
+    }
+
+    let specified = |col: usize| match specs[col] {
+        ColumnWidth::Fixed(width) => Some(width.max(0.0)),
+        ColumnWidth::Percent(percent) => Some((percent / 100.0 * available).max(0.0)),
+        ColumnWidth::Auto => None,
+    };
+    for col in 0..col_count {
+        if let Some(width) = specified(col) {
+            min[col] = width;
+            max[col] = width;
+        }
+    }
+
+    // Spanning cells widen the auto columns they cover, or all of them if
+    // none is auto.
+    for (cell, &(lo, hi)) in cells.iter().zip(&widths) {
+        if cell.col_span < 2 {
+            continue;
+        }
+        let span = cell.col..cell.col + cell.col_span;
+        let targets: Vec<usize> = match span.clone().filter(|&col| is_auto(col)).collect::<Vec<_>>()
+        {
+            auto if !auto.is_empty() => auto,
+            _ => span.clone().collect(),
+        };
+        for (widths, needed) in [(&mut min, lo), (&mut max, hi)] {
+            let current: f32 = widths[span.clone()].iter().sum();
+            if needed > current {
+                let extra = (needed - current) / targets.len() as f32;
+                targets.iter().for_each(|&col| widths[col] += extra);
+            }
+        }
+    }
+
+    // Auto columns take their unwrapped width if it fits, and otherwise
+    // shrink toward their narrowest content in proportion to their slack.
+    let fixed: f32 = (0..col_count)
+        .filter(|&col| !is_auto(col))
+        .map(|col| max[col])
+        .sum();
+    let room = available - fixed;
+    let auto_min: f32 = (0..col_count)
+        .filter(|&col| is_auto(col))
+        .map(|col| min[col])
+        .sum();
+    let auto_max: f32 = (0..col_count)
+        .filter(|&col| is_auto(col))
+        .map(|col| max[col])
+        .sum();
+    let fill = if auto_max <= room {
+        1.0
+    } else if auto_min >= room || auto_max <= auto_min {
+        0.0
+    } else {
+        (room - auto_min) / (auto_max - auto_min)
+    };
+
+    (0..col_count)
+        .map(|col| {
+            if is_auto(col) {
+                min[col] + (max[col] - min[col]) * fill
+            } else {
+                max[col]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(content: &str, col_span: usize, row_span: usize) -> Node {
+        let mut cell = Node::new(NodeKind::TableCell { col_span, row_span });
+        cell.add_child(Node::paragraph(Text::new(content)));
+        cell
+    }
+
+    fn table(rows: Vec<Vec<Node>>) -> Node {
+        let mut table = Node::new(NodeKind::Table {
+            rows: rows.len(),
+            cols: 0,
+        });
+        for cells in rows {
+            let mut row = Node::new(NodeKind::TableRow);
+            cells.into_iter().for_each(|cell| row.add_child(cell));
+            table.add_child(row);
+        }
+        table
+    }
+
+    fn constraints(width: f32) -> Constraints {
+        Constraints::loose(Size::new(width, 1000.0))
+    }
+
+    #[test]
+    fn test_auto_width_columns() {
+        let table = table(vec![
+            vec![cell("a", 1, 1), cell("bbbb", 1, 1), cell("cc", 1, 1)],
+            vec![cell("aaa", 1, 1), cell("b", 1, 1), cell("c", 1, 1)],
+        ]);
+        let style = TableStyle::new().with_padding(2.0);
+        let layout = TableLayout::layout(&table, &style, constraints(500.0)).unwrap();
+
+        // Widest content at 6pt per character, plus padding on both sides.
+        assert_eq!(layout.column_widths, vec![22.0, 28.0, 16.0]);
+        assert_eq!(layout.row_count(), 2);
+        assert_eq!(layout.bounds.width, 66.0);
+        // One line of 14.4pt plus padding.
+        assert!((layout.row_heights[0] - 18.4).abs() < 1e-3);
+
+        let last = &layout.cells[5];
+        assert_eq!((last.row, last.col), (1, 2));
+        assert_eq!(last.bounds.x, 50.0);
+        assert_eq!(last.content.x, 52.0);
+        assert_eq!(last.content.width, 12.0);
+    }
+
+    #[test]
+    fn test_colspan_cell() {
+        let table = table(vec![
+            vec![cell("a wide heading cell", 2, 1), cell("x", 1, 1)],
+            vec![cell("aa", 1, 1), cell("bb", 1, 1), cell("cc", 1, 1)],
+        ]);
+        let layout = TableLayout::layout(&table, &TableStyle::new(), constraints(500.0)).unwrap();
+
+        // The heading needs 114pt; its two columns share the extra width.
+        assert_eq!(layout.column_widths, vec![57.0, 57.0, 12.0]);
+        let heading = &layout.cells[0];
+        assert_eq!(heading.col_span, 2);
+        assert_eq!(heading.bounds.width, 114.0);
+        assert_eq!(layout.cells[1].col, 2);
+        assert_eq!(layout.cells[4].bounds.x, 114.0);
+    }
+
+    #[test]
+    fn test_rowspan_cell() {
+        let table = table(vec![
+            vec![cell("tall\ncell\nhere", 1, 2), cell("a", 1, 1)],
+            vec![cell("b", 1, 1)],
+        ]);
+        let layout = TableLayout::layout(&table, &TableStyle::new(), constraints(500.0)).unwrap();
+        // The second row's cell is placed beside the spanning cell, and the
+        // rows share its three lines.
+        assert_eq!(layout.cells[2].col, 1);
+        assert_eq!(layout.cells[2].row, 1);
+        let total: f32 = layout.row_heights.iter().sum();
+        assert!((total - 3.0 * 14.4).abs() < 1e-3);
+        assert_eq!(layout.cells[0].bounds.height, total);
+    }
+
+    #[test]
+    fn test_fixed_and_percent_columns() {
+        let table = table(vec![vec![
+            cell("a", 1, 1),
+            cell("b", 1, 1),
+            cell("c", 1, 1),
+        ]]);
+        let style = TableStyle::new()
+            .with_columns(vec![ColumnWidth::Fixed(100.0), ColumnWidth::Percent(25.0)]);
+        let layout = TableLayout::layout(&table, &style, constraints(400.0)).unwrap();
+        assert_eq!(layout.column_widths, vec![100.0, 100.0, 6.0]);
+    }
+
+    #[test]
+    fn test_wide_table_wraps_then_scales_or_clips() {
+        let words = "lorem ipsum dolor sit amet";
+        let table = table(vec![vec![cell(words, 1, 1), cell(words, 1, 1)]]);
+
+        // Auto columns wrap their text before anything is scaled.
+        let layout = TableLayout::layout(&table, &TableStyle::new(), constraints(200.0)).unwrap();
+        assert!((layout.column_widths.iter().sum::<f32>() - 200.0).abs() < 1e-3);
+        assert!(layout.cells[0].lines.len() > 1);
+        assert!(!layout.clipped);
+
+        let fixed = TableStyle::new().with_columns(vec![ColumnWidth::Fixed(150.0); 2]);
+        let scaled = TableLayout::layout(&table, &fixed, constraints(200.0)).unwrap();
+        assert_eq!(scaled.column_widths, vec![100.0, 100.0]);
+        assert!(!scaled.clipped);
+
+        let clip = fixed.with_overflow(TableOverflow::Clip);
+        let clipped = TableLayout::layout(&table, &clip, constraints(200.0)).unwrap();
+        assert_eq!(clipped.column_widths, vec![150.0, 150.0]);
+        assert_eq!(clipped.bounds.width, 200.0);
+        assert!(clipped.clipped);
+    }
+
+    #[test]
+    fn test_rejects_non_table() {
+        let node = Node::paragraph(Text::new("x"));
+        assert!(TableLayout::layout(&node, &TableStyle::new(), constraints(100.0)).is_err());
+    }
+
+    #[test]
+    fn test_cells_carry_their_first_block_id() {
+        let first = cell("a", 1, 1);
+        let id = first.children[0].id;
+        let empty = Node::new(NodeKind::TableCell {
+            col_span: 1,
+            row_span: 1,
+        });
+        let table = table(vec![vec![first, empty]]);
+        let layout = TableLayout::layout(&table, &TableStyle::new(), constraints(500.0)).unwrap();
+        assert_eq!(layout.cells[0].source_id, id);
+        assert!(layout.cells[1].source_id.is_nil());
+    }
+
+    #[test]
+    fn test_cell_links_follow_their_block() {
+        let mut linked = Text::new("docs");
+        linked.add_span(Span::new(
+            0,
+            4,
+            wolia_core::style::TextStyle {
+                link: Some("https://example.com".to_string()),
+                ..Default::default()
+            },
+        ));
+        let mut first = cell("see", 1, 1);
+        first.add_child(Node::paragraph(linked));
+        let table = table(vec![vec![first]]);
+        let layout = TableLayout::layout(&table, &TableStyle::new(), constraints(500.0)).unwrap();
+        assert_eq!(layout.cells[0].text, "see\ndocs");
+        assert_eq!(
+            layout.cells[0].links,
+            vec![(4..8, "https://example.com".to_string())]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Tab stops.
+//!
+//! A tab moves the pen to the next tab stop to its right, and the text up
+//! to the following tab is aligned at that stop: it starts there, ends
+//! there, is centered on it, or has its decimal separator there. Text that
+//! would have to start left of the pen to do so starts at the pen instead,
+//! and a tab already past a stop moves on to the next one. Past the last
+//! stop, default stops follow at a fixed interval.
+
+use std::ops::Range;
+
+use wolia_core::style::{DEFAULT_TAB_INTERVAL, ParagraphStyle, TabAlignment, TabStop};
+
+/// Separator decimal tab stops align on.
+pub const DECIMAL_SEPARATOR: char = '.';
+
+/// How far right of the pen a stop must be for a tab to move to it.
+const EPSILON: f32 = 1e-3;
+
+/// The tab stops of a paragraph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabStops {
+    /// Stops ordered by position.
+    stops: Vec<TabStop>,
+    /// Distance between default stops.
+    interval: f32,
+}
+
+/// A run of text between tabs, placed on its line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabSegment {
+    /// Byte range of the text in the line, without the tabs around it.
+    pub range: Range<usize>,
+    /// Offset of the text from the start of the line.
+    pub x: f32,
+    /// Width of the text.
+    pub width: f32,
+    /// Offset of the pen at the tab before the text, which is `x` for the
+    /// first segment of a line.
+    pub tab_x: f32,
+    /// Character to fill the space between `tab_x` and `x` with.
+    pub leader: Option<char>,
+}
+
+impl TabStops {
+    /// Create tab stops, followed by default stops every `interval` points.
+    pub fn new(mut stops: Vec<TabStop>, interval: f32) -> Self {
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self {
+            stops,
+            interval: if interval > 0.0 {
+                interval
+            } else {
+                DEFAULT_TAB_INTERVAL
+            },
+        }
+    }
+
+    /// The tab stops a paragraph style sets.
+    pub fn from_style(style: &ParagraphStyle) -> Self {
+        Self::new(
+            style.tab_stops.clone().unwrap_or_default(),
+            style.default_tab_interval.unwrap_or(DEFAULT_TAB_INTERVAL),
+        )
+    }
+
+    /// The stop a tab at offset `x` moves to.
+    pub fn next(&self, x: f32) -> TabStop {
+        if let Some(stop) = self.stops.iter().find(|stop| stop.position > x + EPSILON) {
+            return stop.clone();
+        }
+        let position = ((x + EPSILON) / self.interval).floor() + 1.0;
+        TabStop::new(position * self.interval, TabAlignment::Left)
+    }
+
+    /// Place the tab-separated runs of a line of text, measured with
+    /// `measure`.
+    pub fn place(&self, line: &str, measure: impl Fn(&str) -> f32) -> Vec<TabSegment> {
+        let mut segments = Vec::new();
+        let mut pen = 0.0;
+        let mut start = 0;
+        let mut stop: Option<TabStop> = None;
+        for (index, part) in line.split('\t').enumerate() {
+            let range = start..start + part.len();
+            start = range.end + 1;
+            let width = measure(part);
+            let x = match &stop {
+                None => pen,
+                Some(stop) => {
+                    let anchor = match stop.alignment {
+                        TabAlignment::Left => 0.0,
+                        TabAlignment::Center => width / 2.0,
+                        TabAlignment::Right => width,
+                        TabAlignment::Decimal => match part.find(DECIMAL_SEPARATOR) {
+                            Some(separator) => measure(&part[..separator]),
+                            None => width,
+                        },
+                    };
+                    f32::max(pen, stop.position - anchor)
+                }
+            };
+            segments.push(TabSegment {
+                range,
+                x,
+                width,
+                tab_x: if index == 0 { x } else { pen },
+                leader: stop.as_ref().and_then(|stop| stop.leader),
+            });
+            pen = x + width;
+            stop = Some(self.next(pen));
+        }
+        segments
+    }
+
+    /// Width of a line of text holding tabs, from its start to the end of
+    /// its last run.
+    pub fn width(&self, line: &str, measure: impl Fn(&str) -> f32) -> f32 {
+        self.place(line, measure)
+            .last()
+            .map_or(0.0, |segment| segment.x + segment.width)
+    }
+}
+
+impl Default for TabStops {
+    fn default() -> Self {
+        Self::new(Vec::new(), DEFAULT_TAB_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(run: &str) -> f32 {
+        run.chars().count() as f32 * 10.0
+    }
+
+    fn xs(segments: &[TabSegment]) -> Vec<f32> {
+        segments.iter().map(|segment| segment.x).collect()
+    }
+
+    #[test]
+    fn test_default_stops() {
+        let tabs = TabStops::default();
+        assert_eq!(tabs.next(0.0).position, 36.0);
+        assert_eq!(tabs.next(36.0).position, 72.0);
+        assert_eq!(tabs.next(50.0).position, 72.0);
+        // "abcde" ends at 86, so the tab after it skips the stop at 72.
+        assert_eq!(
+            xs(&tabs.place("a\tabcde\tb", chars)),
+            vec![0.0, 36.0, 108.0]
+        );
+    }
+
+    #[test]
+    fn test_left_stop_and_overrun() {
+        let tabs = TabStops::new(vec![TabStop::new(100.0, TabAlignment::Left)], 36.0);
+        let segments = tabs.place("Name\tValue", chars);
+        assert_eq!(segments[1].range, 5..10);
+        assert_eq!(segments[1].x, 100.0);
+        assert_eq!(segments[1].tab_x, 40.0);
+
+        // Text running past the stop moves to the next default stop.
+        let segments = tabs.place("A longer name\tValue", chars);
+        assert_eq!(segments[1].x, 144.0);
+    }
+
+    #[test]
+    fn test_right_and_center_stops() {
+        let tabs = TabStops::new(
+            vec![
+                TabStop::new(100.0, TabAlignment::Center),
+                TabStop::new(200.0, TabAlignment::Right),
+            ],
+            36.0,
+        );
+        let segments = tabs.place("a\tmid\tend", chars);
+        assert_eq!(xs(&segments), vec![0.0, 85.0, 170.0]);
+        assert_eq!(tabs.width("a\tmid\tend", chars), 200.0);
+
+        // Right-aligned text wider than the room left starts at the pen.
+        let segments = tabs.place("a\tmid\tthe whole way", chars);
+        assert_eq!(segments[2].x, 115.0);
+    }
+
+    #[test]
+    fn test_decimal_stop() {
+        let tabs = TabStops::new(vec![TabStop::new(100.0, TabAlignment::Decimal)], 36.0);
+        for (text, x) in [("\t3.5", 90.0), ("\t1234.25", 60.0), ("\t42", 80.0)] {
+            let segments = tabs.place(text, chars);
+            assert_eq!(segments[1].x, x, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_leader() {
+        let stop = TabStop::new(200.0, TabAlignment::Right).with_leader('.');
+        let tabs = TabStops::new(vec![stop], 36.0);
+        let segments = tabs.place("Introduction\t12", chars);
+        assert_eq!(segments[0].leader, None);
+        assert_eq!(segments[1].leader, Some('.'));
+        assert_eq!((segments[1].tab_x, segments[1].x), (120.0, 180.0));
+    }
+}
@@ -2,7 +2,23 @@
 //!
 //! This module provides text layout, measurement, and line breaking.
 
-use wolia_core::style::{ParagraphStyle, TextStyle};
+use std::ops::Range;
+use std::sync::Arc;
+
+use unicode_segmentation::UnicodeSegmentation;
+use wolia_core::style::{Alignment, ParagraphStyle, TextStyle};
+use wolia_math::Rect;
+
+use crate::bidi::{BidiText, TextDirection};
+use crate::hyphenate::Hyphenation;
+use crate::line::{Line, LineFragment, TabLeader};
+use crate::linebreak::{BrokenLine, HYPHEN, break_lines_hyphenated, break_lines_varying};
+use crate::measure::{EstimatedMeasurer, TextMeasurer};
+use crate::tabs::TabStops;
+
+/// Default for [`TextLayout::with_max_expansion`]: a justified gap may grow
+/// to three times the width of a space.
+pub const DEFAULT_MAX_EXPANSION: f32 = 2.0;
 
 /// Text layout metrics.
 #[derive(Debug, Clone, Copy)]
@@ -38,8 +54,10 @@ impl LayoutMetrics {
 /// A line of text with position and metrics.
 #[derive(Debug, Clone)]
 pub struct TextLine {
-    /// The text content of this line.
+    /// The text content of this line, without trailing whitespace.
     pub text: String,
+    /// Byte offset of the line in the source text.
+    pub start: usize,
     /// Y offset of this line from the top.
     pub y_offset: f32,
     /// Height of this line.
@@ -55,6 +73,7 @@ impl TextLine {
     pub fn new(text: String, y_offset: f32, height: f32) -> Self {
         Self {
             text,
+            start: 0,
             y_offset,
             height,
             baseline: height * 0.8, // Approximate baseline
@@ -63,17 +82,69 @@ impl TextLine {
     }
 }
 
+/// Approximate the width of a run from its length, for callers without font
+/// metrics.
+pub fn estimate_width(run: &str, font_size: f32) -> f32 {
+    // Approximate character width based on font size
+    run.len() as f32 * font_size * 0.5
+}
+
 /// Text layout engine.
 #[allow(dead_code)]
 pub struct TextLayout {
     /// Maximum width for wrapping.
     max_width: f32,
+    /// How words are hyphenated when lines wrap.
+    hyphenation: Hyphenation,
+    /// Most that a justified gap may grow, in widths of a space.
+    max_expansion: f32,
+    /// Base direction of paragraphs.
+    direction: TextDirection,
+    /// Measures runs and lines of text.
+    measurer: Arc<dyn TextMeasurer>,
 }
 
 impl TextLayout {
     /// Create a new text layout engine.
     pub fn new(max_width: f32) -> Self {
-        Self { max_width }
+        Self {
+            max_width,
+            hyphenation: Hyphenation::disabled(),
+            max_expansion: DEFAULT_MAX_EXPANSION,
+            direction: TextDirection::Auto,
+            measurer: Arc::new(EstimatedMeasurer),
+        }
+    }
+
+    /// Set what text is measured with. Without one, widths are estimated
+    /// from the length of the text.
+    pub fn with_measurer(mut self, measurer: Arc<dyn TextMeasurer>) -> Self {
+        self.measurer = measurer;
+        self
+    }
+
+    /// What text is measured with.
+    pub fn measurer(&self) -> &Arc<dyn TextMeasurer> {
+        &self.measurer
+    }
+
+    /// Set the base direction of paragraphs.
+    pub fn with_direction(mut self, direction: TextDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the most that a justified line's gaps may grow, as a multiple of
+    /// the width of a space. Lines that would need more are left ragged.
+    pub fn with_max_expansion(mut self, ratio: f32) -> Self {
+        self.max_expansion = ratio.max(0.0);
+        self
+    }
+
+    /// Set how words are hyphenated when lines wrap.
+    pub fn with_hyphenation(mut self, hyphenation: Hyphenation) -> Self {
+        self.hyphenation = hyphenation;
+        self
     }
 
     /// Layout text with the given constraints and styles.
@@ -96,60 +167,51 @@ impl TextLayout {
         paragraph_style: &ParagraphStyle,
     ) -> crate::Result<(LayoutMetrics, Vec<TextLine>)> {
         let font_size = text_style.font_size.unwrap_or(12.0);
-        let line_height = font_size * paragraph_style.line_height.unwrap_or(1.2);
-
-        // Simple line breaking algorithm (words)
-        let mut lines = Vec::new();
-        let mut current_line = String::new();
-        let mut total_height = 0.0f32;
-        let mut max_width_found = 0.0f32;
-
-        // Approximate character width based on font size
-        let char_width = font_size * 0.5;
-
-        for word in text.split_whitespace() {
-            let word_with_space = if current_line.is_empty() {
-                word.to_string()
-            } else {
-                format!("{} {}", current_line, word)
-            };
+        let measurer = self.measurer.clone();
+        self.layout_text_with(text, width, text_style, paragraph_style, |run| {
+            measurer.advance(run, font_size)
+        })
+    }
 
-            let estimated_width = word_with_space.len() as f32 * char_width;
-
-            if estimated_width > width && !current_line.is_empty() {
-                // Start new line
-                let text_line = TextLine {
-                    text: current_line.clone(),
-                    y_offset: total_height,
-                    height: line_height,
-                    baseline: line_height * 0.8,
-                    width: (current_line.len() as f32) * char_width,
-                };
-                lines.push(text_line);
-                total_height += line_height;
-                current_line = word.to_string();
-                max_width_found = max_width_found.max((word.len() as f32) * char_width);
-            } else {
-                current_line = word_with_space;
-                max_width_found = max_width_found.max(estimated_width);
-            }
-        }
+    /// Layout text, measuring runs with `measure` instead of the layout's
+    /// measurer.
+    ///
+    /// `measure` receives a run of text and returns its advance width at the
+    /// style's font size. Callers with real font metrics use this so that
+    /// lines wrap where the glyphs will actually end. The text of hyphenated
+    /// lines ends with the hyphen.
+    pub fn layout_text_with(
+        &mut self,
+        text: &str,
+        width: f32,
+        text_style: &TextStyle,
+        paragraph_style: &ParagraphStyle,
+        measure: impl Fn(&str) -> f32,
+    ) -> crate::Result<(LayoutMetrics, Vec<TextLine>)> {
+        let font_size = text_style.font_size.unwrap_or(12.0);
+        let line_height = font_size * paragraph_style.line_height.unwrap_or(1.2);
 
-        // Add last line
-        if !current_line.is_empty() {
-            let text_line = TextLine {
-                text: current_line.clone(),
-                y_offset: total_height,
+        let measure = with_tabs(text, paragraph_style, measure);
+        let lines: Vec<TextLine> = break_lines_hyphenated(text, width, &self.hyphenation, &measure)
+            .iter()
+            .enumerate()
+            .map(|(index, line)| TextLine {
+                text: if line.hyphenated {
+                    format!("{}{}", line.content(text), HYPHEN)
+                } else {
+                    line.content(text).to_string()
+                },
+                start: line.range.start,
+                y_offset: index as f32 * line_height,
                 height: line_height,
                 baseline: line_height * 0.8,
-                width: (current_line.len() as f32) * char_width,
-            };
-            lines.push(text_line);
-            total_height += line_height;
-        }
+                width: line.width,
+            })
+            .collect();
+        let total_height = lines.len() as f32 * line_height;
 
         let mut layout_metrics = LayoutMetrics::new(width, total_height);
-        layout_metrics.measured_width = max_width_found;
+        layout_metrics.measured_width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
         layout_metrics.measured_height = total_height;
         layout_metrics.line_count = lines.len();
         layout_metrics.baseline = if !lines.is_empty() {
@@ -161,20 +223,198 @@ impl TextLayout {
         Ok((layout_metrics, lines))
     }
 
+    /// Break text into positioned [`Line`]s.
+    ///
+    /// Line bounds are relative to the top-left corner of the text block and
+    /// offset according to the paragraph's alignment. Each line holds a
+    /// single fragment that spans its visible text, except justified lines,
+    /// which hold one fragment per word (or per character, for CJK text
+    /// without spaces) spread out to fill `width`. Lines ending a paragraph
+    /// or at a hard break are never justified. A hyphenated line's fragments
+    /// exclude the hyphen, which the line's bounds include.
+    ///
+    /// A line holding tabs is laid out from the left of its band, whatever
+    /// the alignment, with one fragment per run of text between tabs placed
+    /// at the paragraph's tab stops; see [`TabStops`].
+    ///
+    /// Lines are broken in logical order. A line holding right-to-left text
+    /// is then split into one fragment per directional run, in visual order
+    /// from left to right; such lines are not justified, and in
+    /// right-to-left paragraphs without an explicit alignment they align to
+    /// the right.
+    pub fn layout_lines(
+        &mut self,
+        text: &str,
+        width: f32,
+        text_style: &TextStyle,
+        paragraph_style: &ParagraphStyle,
+        measure: impl Fn(&str) -> f32,
+    ) -> Vec<Line> {
+        self.layout_lines_in(
+            text,
+            |_, _| (0.0, width),
+            text_style,
+            paragraph_style,
+            measure,
+        )
+    }
+
+    /// Break text into positioned [`Line`]s whose horizontal extent varies,
+    /// as when text flows around floats.
+    ///
+    /// `band` receives the top and height of a line, relative to the top of
+    /// the text block, and returns the x offset and width available to it.
+    /// Lines are otherwise laid out as by [`layout_lines`](Self::layout_lines),
+    /// with alignment applied within each line's band.
+    pub fn layout_lines_in(
+        &mut self,
+        text: &str,
+        band: impl Fn(f32, f32) -> (f32, f32),
+        text_style: &TextStyle,
+        paragraph_style: &ParagraphStyle,
+        measure: impl Fn(&str) -> f32,
+    ) -> Vec<Line> {
+        let font_size = text_style.font_size.unwrap_or(12.0);
+        let line_height = font_size * paragraph_style.line_height.unwrap_or(1.2);
+        let band_of = |index: usize| band(index as f32 * line_height, line_height);
+        let bidi = BidiText::new(text, self.direction);
+        let tabs = text
+            .contains('\t')
+            .then(|| TabStops::from_style(paragraph_style));
+        let measure_line = with_tabs(text, paragraph_style, &measure);
+
+        break_lines_varying(
+            text,
+            |index| band_of(index).1,
+            &self.hyphenation,
+            &measure_line,
+        )
+        .iter()
+        .enumerate()
+        .map(|(index, broken)| {
+            let y = index as f32 * line_height;
+            let (left, width) = band_of(index);
+            let content = broken.range.start..broken.content_end;
+            if let Some(tabs) = tabs
+                .as_ref()
+                .filter(|_| text[content.clone()].contains('\t'))
+            {
+                return tab_line(text, broken, tabs, (left, y), line_height, &measure);
+            }
+            let mixed = bidi.is_mixed(content.clone());
+            let alignment =
+                paragraph_style
+                    .alignment
+                    .unwrap_or(if bidi.is_rtl(broken.range.start) {
+                        Alignment::Right
+                    } else {
+                        Alignment::Left
+                    });
+            let justified = (alignment == Alignment::Justify && !broken.mandatory && !mixed)
+                .then(|| self.justify(text, broken, (left, width), y, line_height, &measure))
+                .flatten();
+            if let Some(line) = justified {
+                return line;
+            }
+
+            let x = match alignment {
+                Alignment::Center => (width - broken.width) / 2.0,
+                Alignment::Right => width - broken.width,
+                Alignment::Left | Alignment::Justify => 0.0,
+            };
+            let bounds = Rect::new(left + x.max(0.0), y, broken.width, line_height);
+            let mut line = Line::new(bounds, line_height * 0.8);
+            line.hyphenated = broken.hyphenated;
+            if mixed {
+                let mut x = bounds.x;
+                for run in bidi.visual_runs(content) {
+                    let run_width = measure(&text[run.range.clone()]);
+                    line.fragments.push(LineFragment {
+                        bounds: Rect::new(x, y, run_width, line_height),
+                        text_start: run.range.start,
+                        text_len: run.range.len(),
+                        rtl: run.rtl,
+                        glyphs: Vec::new(),
+                    });
+                    x += run_width;
+                }
+                return line;
+            }
+            line.fragments.push(LineFragment {
+                bounds,
+                text_start: broken.range.start,
+                text_len: broken.content_end - broken.range.start,
+                rtl: false,
+                glyphs: Vec::new(),
+            });
+            line
+        })
+        .collect()
+    }
+
+    /// Stretch a line across its band, given as x offset and width, by
+    /// widening the gaps between its words, or failing that between CJK
+    /// characters.
+    ///
+    /// Returns `None` if the line has nothing to stretch or its gaps would
+    /// grow by more than the maximum expansion.
+    fn justify(
+        &self,
+        text: &str,
+        broken: &BrokenLine,
+        (left, width): (f32, f32),
+        y: f32,
+        line_height: f32,
+        measure: &impl Fn(&str) -> f32,
+    ) -> Option<Line> {
+        let content = broken.content(text);
+        let mut pieces = words(content);
+        if pieces.len() < 2 && content.chars().any(is_cjk) {
+            pieces = content
+                .grapheme_indices(true)
+                .map(|(offset, grapheme)| offset..offset + grapheme.len())
+                .collect();
+        }
+        if pieces.len() < 2 {
+            return None;
+        }
+
+        let extra = (width - broken.width) / (pieces.len() - 1) as f32;
+        if extra < 0.0 || extra > self.max_expansion * measure(" ") {
+            return None;
+        }
+
+        let mut line = Line::new(Rect::new(left, y, width, line_height), line_height * 0.8);
+        line.hyphenated = broken.hyphenated;
+        for (index, piece) in pieces.iter().enumerate() {
+            // Pieces keep their natural offsets, plus the extra space of
+            // every gap before them.
+            let x = left + measure(&content[..piece.start]) + extra * index as f32;
+            let mut piece_width = measure(&content[piece.clone()]);
+            if broken.hyphenated && index == pieces.len() - 1 {
+                piece_width += measure(HYPHEN);
+            }
+            line.fragments.push(LineFragment {
+                bounds: Rect::new(x, y, piece_width, line_height),
+                text_start: broken.range.start + piece.start,
+                text_len: piece.len(),
+                rtl: false,
+                glyphs: Vec::new(),
+            });
+        }
+        Some(line)
+    }
+
     /// Measure text without laying it out.
     ///
-    /// Returns (width, height) of the text.
+    /// Returns (width, height) of the text: the width of its widest line
+    /// and the height of its lines.
     pub fn measure_text(&mut self, text: &str, font_size: f32) -> crate::Result<(f32, f32)> {
-        let char_width = font_size * 0.5;
-        let _line_height = font_size * 1.2;
-
         let width = text
             .lines()
-            .map(|line| (line.len() as f32) * char_width)
-            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-            .unwrap_or(0.0);
-
-        let height = (text.lines().count() as f32) * (font_size * 1.2);
+            .map(|line| self.measurer.advance(line, font_size))
+            .fold(0.0, f32::max);
+        let height = text.lines().count() as f32 * self.measurer.line_metrics(font_size).height();
 
         Ok((width, height))
     }
@@ -198,8 +438,98 @@ impl TextLayout {
     }
 }
 
+/// Wrap `measure` to measure runs holding tabs to the end of their last
+/// tab stop, if `text` holds any. Runs are measured from the start of a
+/// line.
+fn with_tabs<'a>(
+    text: &str,
+    paragraph_style: &ParagraphStyle,
+    measure: impl Fn(&str) -> f32 + 'a,
+) -> impl Fn(&str) -> f32 + 'a {
+    let tabs = text
+        .contains('\t')
+        .then(|| TabStops::from_style(paragraph_style));
+    move |run: &str| match &tabs {
+        Some(tabs) if run.contains('\t') => tabs.width(run, &measure),
+        _ => measure(run),
+    }
+}
+
+/// Lay out a line holding tabs, with a fragment per run of text between
+/// them and a leader before each run at a stop that has one.
+fn tab_line(
+    text: &str,
+    broken: &BrokenLine,
+    tabs: &TabStops,
+    (left, y): (f32, f32),
+    line_height: f32,
+    measure: &impl Fn(&str) -> f32,
+) -> Line {
+    let bounds = Rect::new(left, y, broken.width, line_height);
+    let mut line = Line::new(bounds, line_height * 0.8);
+    line.hyphenated = broken.hyphenated;
+    for segment in tabs.place(broken.content(text), measure) {
+        if let Some(character) = segment.leader.filter(|_| segment.x > segment.tab_x) {
+            line.leaders.push(TabLeader {
+                bounds: Rect::new(
+                    left + segment.tab_x,
+                    y,
+                    segment.x - segment.tab_x,
+                    line_height,
+                ),
+                character,
+            });
+        }
+        if !segment.range.is_empty() {
+            line.fragments.push(LineFragment {
+                bounds: Rect::new(left + segment.x, y, segment.width, line_height),
+                text_start: broken.range.start + segment.range.start,
+                text_len: segment.range.len(),
+                rtl: false,
+                glyphs: Vec::new(),
+            });
+        }
+    }
+    line
+}
+
+/// Byte ranges of the words of `content`, separated by whitespace. Any
+/// leading whitespace belongs to the first word.
+fn words(content: &str) -> Vec<Range<usize>> {
+    let mut words: Vec<Range<usize>> = Vec::new();
+    let mut word_start = Some(0);
+    for (offset, c) in content.char_indices() {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(start)) if start < offset && !content[start..offset].trim().is_empty() => {
+                words.push(start..offset);
+                word_start = None;
+            }
+            (false, None) => word_start = Some(offset),
+            _ => {}
+        }
+    }
+    if let Some(start) = word_start {
+        if start < content.len() {
+            words.push(start..content.len());
+        }
+    }
+    words
+}
+
+/// Whether `c` is a CJK character, which may be spaced apart when
+/// justifying text that has no spaces.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2E80}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}' | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF00}'..='\u{FFEF}' | '\u{20000}'..='\u{2FFFF}'
+    )
+}
+
 #[cfg(test)]
 mod tests {
+    use wolia_core::style::{TabAlignment, TabStop};
+
     use super::*;
 
     #[test]
@@ -223,4 +553,291 @@ mod tests {
         let layout = TextLayout::new(100.0);
         assert_eq!(layout.max_width, 100.0);
     }
+
+    #[test]
+    fn test_layout_text_with_measurer() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let style = TextStyle::default();
+        let paragraph = ParagraphStyle::default();
+        let mut estimated = TextLayout::new(100.0);
+        let mut measured = TextLayout::new(100.0).with_measurer(crate::measure::tests::tuffy());
+
+        let (width, height) = measured.measure_text("Hello, world", 12.0).unwrap();
+        assert!((width - 57.0).abs() < 0.01);
+        let line_height = measured.measurer().line_metrics(12.0).height();
+        assert_eq!(height, line_height);
+        assert_eq!(estimated.measure_text("Hello", 10.0).unwrap(), (25.0, 12.0));
+
+        // Lines wrap where the font's glyphs end, not where the estimate
+        // puts them.
+        let (_, fitted) = measured
+            .layout_text(text, 100.0, &style, &paragraph)
+            .unwrap();
+        let (_, guessed) = estimated
+            .layout_text(text, 100.0, &style, &paragraph)
+            .unwrap();
+        assert_eq!(fitted[1].text, "fox jumps over the");
+        assert_eq!(guessed[1].text, "fox jumps over");
+        for line in &fitted {
+            assert!(line.width <= 100.0);
+            let width = measured.measurer().advance(&line.text, 12.0);
+            assert!((line.width - width).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_layout_text_with_custom_measure() {
+        let mut layout = TextLayout::new(100.0);
+        // Every character is 30 wide, so only three fit on a line.
+        let (metrics, lines) = layout
+            .layout_text_with(
+                "ab cd ef",
+                100.0,
+                &TextStyle::default(),
+                &ParagraphStyle::default(),
+                |run| run.chars().count() as f32 * 30.0,
+            )
+            .unwrap();
+        assert_eq!(metrics.line_count, 3);
+        assert_eq!(lines[0].text, "ab");
+        assert_eq!(lines[0].width, 60.0);
+        assert_eq!(lines[2].start, 6);
+    }
+
+    #[test]
+    fn test_layout_lines_fragments() {
+        let mut layout = TextLayout::new(50.0);
+        let text = "alpha beta";
+        let lines = layout.layout_lines(
+            text,
+            50.0,
+            &TextStyle::default(),
+            &ParagraphStyle::default(),
+            |run| run.chars().count() as f32 * 10.0,
+        );
+        assert_eq!(lines.len(), 2);
+        let fragment = &lines[1].fragments[0];
+        assert_eq!(
+            &text[fragment.text_start..fragment.text_start + fragment.text_len],
+            "beta"
+        );
+        assert_eq!(lines[1].bounds.y, 12.0 * 1.2);
+        assert_eq!(lines[1].bounds.width, 40.0);
+    }
+
+    fn justified() -> ParagraphStyle {
+        ParagraphStyle {
+            alignment: Some(Alignment::Justify),
+            ..ParagraphStyle::default()
+        }
+    }
+
+    fn chars(run: &str) -> f32 {
+        run.chars().count() as f32 * 10.0
+    }
+
+    #[test]
+    fn test_justify_fills_width() {
+        let mut layout = TextLayout::new(200.0);
+        let text = "one two three four five six";
+        let lines = layout.layout_lines(text, 200.0, &TextStyle::default(), &justified(), chars);
+        assert_eq!(lines.len(), 2);
+
+        // "one two three four" is 180 wide: the 20 left over is shared
+        // between its three gaps.
+        let first = &lines[0];
+        assert_eq!(first.fragments.len(), 4);
+        assert_eq!(first.bounds.width, 200.0);
+        let fragments = &first.fragments;
+        let words: f32 = fragments.iter().map(|f| f.bounds.width).sum();
+        let gaps: f32 = fragments
+            .windows(2)
+            .map(|pair| pair[1].bounds.x - pair[0].bounds.right())
+            .sum();
+        assert!((words + gaps - 200.0).abs() < 1e-3);
+        assert!((fragments[3].bounds.right() - 200.0).abs() < 1e-3);
+        let word = &fragments[2];
+        assert_eq!(
+            &text[word.text_start..word.text_start + word.text_len],
+            "three"
+        );
+    }
+
+    #[test]
+    fn test_justify_leaves_final_lines() {
+        let mut layout = TextLayout::new(200.0);
+        let text = "one two\nthree four five six seven";
+        let lines = layout.layout_lines(text, 200.0, &TextStyle::default(), &justified(), chars);
+        // The line before the hard break and the paragraph's last line keep
+        // their natural width.
+        assert_eq!(lines[0].fragments.len(), 1);
+        assert_eq!(lines[0].bounds.width, 70.0);
+        let last = lines.last().unwrap();
+        assert_eq!(last.fragments.len(), 1);
+        assert_eq!(last.bounds.x, 0.0);
+        assert!(last.bounds.width < 200.0);
+    }
+
+    #[test]
+    fn test_justify_limits_expansion() {
+        let text = "a b extraordinarily";
+        // "a b" would need a gap of 120 to fill the line.
+        let mut layout = TextLayout::new(150.0);
+        let lines = layout.layout_lines(text, 150.0, &TextStyle::default(), &justified(), chars);
+        assert_eq!(lines[0].fragments.len(), 1);
+        assert_eq!(lines[0].bounds.width, 30.0);
+
+        let mut layout = TextLayout::new(150.0).with_max_expansion(20.0);
+        let lines = layout.layout_lines(text, 150.0, &TextStyle::default(), &justified(), chars);
+        assert_eq!(lines[0].fragments.len(), 2);
+        assert_eq!(lines[0].fragments[1].bounds.right(), 150.0);
+    }
+
+    #[test]
+    fn test_justify_single_word_and_cjk() {
+        let mut layout = TextLayout::new(100.0);
+        let text = "abcdefg hijklmnopq";
+        let lines = layout.layout_lines(text, 100.0, &TextStyle::default(), &justified(), chars);
+        // A lone word is not spaced out letter by letter.
+        assert_eq!(lines[0].fragments.len(), 1);
+
+        let text = "日本語のテキストを折り返す";
+        let lines = layout.layout_lines(text, 45.0, &TextStyle::default(), &justified(), chars);
+        let first = &lines[0];
+        assert_eq!(first.fragments.len(), 4);
+        assert!((first.fragments[3].bounds.right() - 45.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_alignment_offsets() {
+        let mut layout = TextLayout::new(100.0);
+        let style = ParagraphStyle {
+            alignment: Some(Alignment::Right),
+            ..ParagraphStyle::default()
+        };
+        let lines = layout.layout_lines("abc", 100.0, &TextStyle::default(), &style, chars);
+        assert_eq!(lines[0].bounds.x, 70.0);
+        assert_eq!(lines[0].fragments[0].bounds.x, 70.0);
+    }
+
+    fn fragment_texts<'a>(text: &'a str, line: &Line) -> Vec<(&'a str, bool, f32)> {
+        line.fragments
+            .iter()
+            .map(|f| {
+                (
+                    &text[f.text_start..f.text_start + f.text_len],
+                    f.rtl,
+                    f.bounds.x,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bidi_visual_order() {
+        let mut layout = TextLayout::new(300.0);
+        let text = "Read مرحبا بكم now";
+        let lines = layout.layout_lines(
+            text,
+            300.0,
+            &TextStyle::default(),
+            &ParagraphStyle::default(),
+            chars,
+        );
+        // The Arabic words keep their logical order inside one right-to-left
+        // run, which sits between the English runs.
+        assert_eq!(
+            fragment_texts(text, &lines[0]),
+            vec![
+                ("Read ", false, 0.0),
+                ("مرحبا بكم", true, 50.0),
+                (" now", false, 140.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rtl_paragraph_breaks_logically_and_aligns_right() {
+        let mut layout = TextLayout::new(150.0);
+        let text = "مرحبا بكم في Wolia 2024";
+        let lines = layout.layout_lines(
+            text,
+            150.0,
+            &TextStyle::default(),
+            &ParagraphStyle::default(),
+            chars,
+        );
+        // The first line holds the logically first words, aligned right.
+        assert_eq!(
+            fragment_texts(text, &lines[0]),
+            vec![("مرحبا بكم في", true, 30.0)]
+        );
+        // The space between the name and the number resolves to the
+        // surrounding left-to-right text, so they form a single run.
+        assert_eq!(
+            fragment_texts(text, &lines[1]),
+            vec![("Wolia 2024", false, 50.0)]
+        );
+
+        let mut ltr = TextLayout::new(150.0).with_direction(TextDirection::Ltr);
+        let lines = ltr.layout_lines(
+            text,
+            150.0,
+            &TextStyle::default(),
+            &ParagraphStyle::default(),
+            chars,
+        );
+        assert_eq!(lines[0].bounds.x, 0.0);
+    }
+
+    fn tabbed(stops: Vec<TabStop>) -> ParagraphStyle {
+        ParagraphStyle {
+            tab_stops: Some(stops),
+            ..ParagraphStyle::default()
+        }
+    }
+
+    #[test]
+    fn test_left_tab_stop() {
+        let mut layout = TextLayout::new(300.0);
+        let text = "Name\tValue\nA much longer name\tValue";
+        let style = tabbed(vec![TabStop::new(100.0, TabAlignment::Left)]);
+        let lines = layout.layout_lines(text, 300.0, &TextStyle::default(), &style, chars);
+        assert_eq!(
+            fragment_texts(text, &lines[0]),
+            vec![("Name", false, 0.0), ("Value", false, 100.0)]
+        );
+        assert_eq!(lines[0].bounds.width, 150.0);
+        // The longer name overruns the stop, so its value moves on to the
+        // next default stop.
+        assert_eq!(
+            fragment_texts(text, &lines[1]),
+            vec![("A much longer name", false, 0.0), ("Value", false, 216.0)]
+        );
+    }
+
+    #[test]
+    fn test_decimal_tab_stop_with_leader() {
+        let mut layout = TextLayout::new(300.0);
+        let stop = TabStop::new(200.0, TabAlignment::Decimal).with_leader('.');
+        let style = tabbed(vec![stop]);
+        let text = "Total\t1234.50";
+        let lines = layout.layout_lines(text, 300.0, &TextStyle::default(), &style, chars);
+        // "1234" ends at the stop, where the decimal point starts.
+        assert_eq!(
+            fragment_texts(text, &lines[0]),
+            vec![("Total", false, 0.0), ("1234.50", false, 160.0)]
+        );
+        let leader = lines[0].leaders[0];
+        assert_eq!(leader.character, '.');
+        assert_eq!((leader.bounds.x, leader.bounds.right()), (50.0, 160.0));
+
+        // Tabs count toward the width lines wrap at.
+        let lines = layout.layout_lines(text, 220.0, &TextStyle::default(), &style, chars);
+        assert_eq!(lines.len(), 2);
+        let (_, lines) = layout
+            .layout_text_with(text, 300.0, &TextStyle::default(), &style, chars)
+            .unwrap();
+        assert_eq!(lines[0].width, 230.0);
+    }
 }
@@ -1,8 +1,12 @@
 //! Layout tree.
 
+use std::ops::Range;
+
 use uuid::Uuid;
-use wolia_math::{Rect, Size};
+use wolia_core::style::RevisionKind;
+use wolia_math::{Point, Rect, Size};
 
+use crate::ParagraphLayout;
 use crate::page::Page;
 
 /// The result of laying out a document.
@@ -32,6 +36,291 @@ impl LayoutTree {
     pub fn page(&self, number: usize) -> Option<&Page> {
         self.pages.get(number.saturating_sub(1))
     }
+
+    /// The text position nearest to `point`, in document coordinates with
+    /// pages stacked top to bottom.
+    ///
+    /// The point hits the page it lies on, and within that page the
+    /// paragraph nearest to it, preferring one it is level with.
+    pub fn hit_test(&self, point: Point) -> Option<TextHit> {
+        let mut page_y = 0.0;
+        let mut target = None;
+        for page in &self.pages {
+            target = Some((page, page_y));
+            if point.y < page_y + page.size.height {
+                break;
+            }
+            page_y += page.size.height;
+        }
+        let (page, page_y) = target?;
+
+        let mut paragraphs = Vec::new();
+        for node in &page.nodes {
+            collect_paragraphs(node, Point::new(0.0, page_y), &mut paragraphs);
+        }
+        let distance = |(_, paragraph, origin): &(Uuid, &ParagraphLayout, Point)| {
+            let local = point - *origin;
+            let extent = paragraph
+                .lines
+                .iter()
+                .map(|line| line.bounds)
+                .reduce(|a, b| a.union(&b))
+                .unwrap_or(Rect::ZERO);
+            let dy = (extent.y - local.y).max(local.y - extent.bottom()).max(0.0);
+            let dx = (extent.x - local.x).max(local.x - extent.right()).max(0.0);
+            (dy, dx)
+        };
+        let (source_id, paragraph, origin) = paragraphs.into_iter().min_by(|a, b| {
+            let (a, b) = (distance(a), distance(b));
+            a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+        })?;
+        Some(TextHit {
+            source_id,
+            offset: paragraph.offset_at(point - origin),
+        })
+    }
+
+    /// A zero-width rectangle, in document coordinates, at the caret before
+    /// `offset` in the text of the paragraph laid out from `source_id`.
+    pub fn caret_rect(&self, source_id: Uuid, offset: usize) -> Option<Rect> {
+        self.paragraphs_of(source_id)
+            .into_iter()
+            .find_map(|(paragraph, origin)| {
+                let rect = paragraph.caret_rect(offset)?;
+                Some(Rect::new(
+                    rect.x + origin.x,
+                    rect.y + origin.y,
+                    0.0,
+                    rect.height,
+                ))
+            })
+    }
+
+    /// Where the caret before `offset` in the text of the paragraph laid
+    /// out from `source_id` is on its page.
+    pub fn caret_position(&self, source_id: Uuid, offset: usize) -> Option<CaretPosition> {
+        for page in &self.pages {
+            let mut paragraphs = Vec::new();
+            for node in &page.nodes {
+                collect_paragraphs(node, Point::ZERO, &mut paragraphs);
+            }
+            let mut lines_before = 0;
+            for (id, paragraph, _) in paragraphs {
+                if id == source_id {
+                    // An empty paragraph has no lines, but the caret is on
+                    // the line it would take.
+                    let line = match paragraph.line_of(offset) {
+                        Some(line) => Some(line),
+                        None if paragraph.text.is_empty() && offset == 0 => Some((0, 0)),
+                        None => None,
+                    };
+                    if let Some((index, start)) = line {
+                        let column = paragraph.text.get(start..offset).unwrap_or("");
+                        return Some(CaretPosition {
+                            page: page.number,
+                            line: lines_before + index + 1,
+                            column: column.chars().count() + 1,
+                        });
+                    }
+                }
+                lines_before += paragraph.lines.len();
+            }
+        }
+        None
+    }
+
+    /// Rectangles, in document coordinates, covering `range` of the text
+    /// of the paragraph laid out from `source_id` on every line it spans.
+    pub fn selection_rects(&self, source_id: Uuid, range: Range<usize>) -> Vec<Rect> {
+        self.paragraphs_of(source_id)
+            .into_iter()
+            .flat_map(|(paragraph, origin)| {
+                paragraph
+                    .selection_rects(range.clone())
+                    .into_iter()
+                    .map(move |rect| {
+                        Rect::new(
+                            rect.x + origin.x,
+                            rect.y + origin.y,
+                            rect.width,
+                            rect.height,
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Rectangles, in document coordinates, underlining the linked text of
+    /// every paragraph.
+    pub fn link_underlines(&self) -> Vec<Rect> {
+        self.paragraphs()
+            .into_iter()
+            .flat_map(|(_, paragraph, origin)| {
+                paragraph.link_underlines().into_iter().map(move |rect| {
+                    Rect::new(
+                        rect.x + origin.x,
+                        rect.y + origin.y,
+                        rect.width,
+                        rect.height,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Rectangles, in document coordinates, underlining the text suggested
+    /// for insertion and striking through the text suggested for deletion
+    /// in every paragraph, with the kind of suggestion each marks.
+    pub fn revision_marks(&self) -> Vec<(Rect, RevisionKind)> {
+        self.paragraphs()
+            .into_iter()
+            .flat_map(|(_, paragraph, origin)| {
+                paragraph
+                    .revision_marks()
+                    .into_iter()
+                    .map(move |(rect, kind)| {
+                        let rect = Rect::new(
+                            rect.x + origin.x,
+                            rect.y + origin.y,
+                            rect.width,
+                            rect.height,
+                        );
+                        (rect, kind)
+                    })
+            })
+            .collect()
+    }
+
+    /// The rectangle, in document coordinates, of the table cell laid out
+    /// from `source_id`, if there is one.
+    pub fn cell_rect(&self, source_id: Uuid) -> Option<Rect> {
+        let mut page_y = 0.0;
+        for page in &self.pages {
+            for node in &page.nodes {
+                if let Some(rect) = find_cell(node, Point::new(0.0, page_y), source_id) {
+                    return Some(rect);
+                }
+            }
+            page_y += page.size.height;
+        }
+        None
+    }
+
+    /// The `src` of every image with the rectangle it is drawn in, in
+    /// document coordinates with pages stacked top to bottom.
+    pub fn images(&self) -> Vec<(&str, Rect)> {
+        let mut images = Vec::new();
+        let mut page_y = 0.0;
+        for page in &self.pages {
+            for node in &page.nodes {
+                if let LayoutContent::Image { src } = &node.content {
+                    let bounds = node.bounds;
+                    let rect = Rect::new(bounds.x, bounds.y + page_y, bounds.width, bounds.height);
+                    images.push((src.as_str(), rect));
+                }
+            }
+            page_y += page.size.height;
+        }
+        images
+    }
+
+    /// The parts of the paragraph laid out from `source_id`, which may be
+    /// split across pages, with the origins their lines are relative to.
+    fn paragraphs_of(&self, source_id: Uuid) -> Vec<(&ParagraphLayout, Point)> {
+        self.paragraphs()
+            .into_iter()
+            .filter(|(id, _, _)| *id == source_id)
+            .map(|(_, paragraph, origin)| (paragraph, origin))
+            .collect()
+    }
+
+    /// Every paragraph on every page, with its source node and the origin
+    /// its lines are relative to.
+    fn paragraphs(&self) -> Vec<(Uuid, &ParagraphLayout, Point)> {
+        let mut paragraphs = Vec::new();
+        let mut page_y = 0.0;
+        for page in &self.pages {
+            for node in &page.nodes {
+                collect_paragraphs(node, Point::new(0.0, page_y), &mut paragraphs);
+            }
+            page_y += page.size.height;
+        }
+        paragraphs
+    }
+}
+
+/// Where a caret is on the page, as a word processor reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaretPosition {
+    /// Page number, counting from 1.
+    pub page: usize,
+    /// Line on the page, counting from 1.
+    pub line: usize,
+    /// Character on the line, counting from 1.
+    pub column: usize,
+}
+
+/// A position in the text of a laid-out paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextHit {
+    /// Source node of the paragraph.
+    pub source_id: Uuid,
+    /// Byte offset into the paragraph's text.
+    pub offset: usize,
+}
+
+/// Gather the paragraphs of a node whose bounds are relative to `offset`,
+/// with the origin their lines are relative to.
+fn collect_paragraphs<'a>(
+    node: &'a LayoutNode,
+    offset: Point,
+    paragraphs: &mut Vec<(Uuid, &'a ParagraphLayout, Point)>,
+) {
+    let origin = offset + Point::new(node.bounds.x, node.bounds.y);
+    match &node.content {
+        LayoutContent::Paragraph(paragraph) => {
+            paragraphs.push((node.source_id, paragraph, origin));
+        }
+        // Cell lines are relative to the cell's content rectangle, which
+        // is relative to the table.
+        LayoutContent::Table { cells } => {
+            for cell in cells {
+                if let LayoutContent::Paragraph(paragraph) = &cell.content {
+                    let content = Point::new(paragraph.bounds.x, paragraph.bounds.y);
+                    paragraphs.push((cell.source_id, paragraph, origin + content));
+                }
+            }
+        }
+        LayoutContent::Container { children } => {
+            for child in children {
+                collect_paragraphs(child, origin, paragraphs);
+            }
+        }
+        LayoutContent::Image { .. } | LayoutContent::Drawing(_) => {}
+    }
+}
+
+/// The rectangle of the cell laid out from `source_id` among the tables of
+/// a node whose bounds are relative to `offset`.
+fn find_cell(node: &LayoutNode, offset: Point, source_id: Uuid) -> Option<Rect> {
+    let origin = offset + Point::new(node.bounds.x, node.bounds.y);
+    match &node.content {
+        LayoutContent::Table { cells } => cells
+            .iter()
+            .find(|cell| cell.source_id == source_id)
+            .map(|cell| {
+                Rect::new(
+                    cell.bounds.x + origin.x,
+                    cell.bounds.y + origin.y,
+                    cell.bounds.width,
+                    cell.bounds.height,
+                )
+            }),
+        LayoutContent::Container { children } => children
+            .iter()
+            .find_map(|child| find_cell(child, origin, source_id)),
+        _ => None,
+    }
 }
 
 /// A node in the layout tree.
@@ -56,4 +345,7 @@ pub enum LayoutContent {
     Table { cells: Vec<LayoutNode> },
     /// Container for other nodes.
     Container { children: Vec<LayoutNode> },
+    /// Glyphs and rules placed by a plugin, relative to the left end of
+    /// its baseline, which lies the drawing's ascent below the node's top.
+    Drawing(wolia_plugin::Drawing),
 }
@@ -40,6 +40,9 @@ impl Color {
         Self { r, g, b, a }
     }
 
+    /// Create a color from 8-bit channels that are already linear. Colors
+    /// written as hex codes or picked in design tools are sRGB encoded and
+    /// belong in [`from_srgb8`](Self::from_srgb8) instead.
     pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
         Self {
             r: r as f32 / 255.0,
@@ -49,6 +52,7 @@ impl Color {
         }
     }
 
+    /// The channels as 8-bit values, without encoding them as sRGB.
     pub fn to_rgba8(self) -> [u8; 4] {
         [
             (self.r * 255.0) as u8,
@@ -57,6 +61,74 @@ impl Color {
             (self.a * 255.0) as u8,
         ]
     }
+
+    /// Create a color from sRGB-encoded 8-bit channels, decoding them to
+    /// linear. Alpha is linear in sRGB too, so it is only rescaled.
+    pub fn from_srgb8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self {
+            r: srgb_to_linear(r as f32 / 255.0),
+            g: srgb_to_linear(g as f32 / 255.0),
+            b: srgb_to_linear(b as f32 / 255.0),
+            a: a as f32 / 255.0,
+        }
+    }
+
+    /// The color as sRGB-encoded 8-bit channels, the inverse of
+    /// [`from_srgb8`](Self::from_srgb8).
+    pub fn to_srgb8(self) -> [u8; 4] {
+        let quantize = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [
+            quantize(linear_to_srgb(self.r)),
+            quantize(linear_to_srgb(self.g)),
+            quantize(linear_to_srgb(self.b)),
+            quantize(self.a),
+        ]
+    }
+
+    /// Interpolate linearly from `a` at `t = 0` to `b` at `t = 1`.
+    pub fn lerp(a: Self, b: Self, t: f32) -> Self {
+        Self {
+            r: a.r + (b.r - a.r) * t,
+            g: a.g + (b.g - a.g) * t,
+            b: a.b + (b.b - a.b) * t,
+            a: a.a + (b.a - a.a) * t,
+        }
+    }
+
+    /// Composite this color over `background` (Porter-Duff source-over).
+    pub fn over(self, background: Self) -> Self {
+        let a = self.a + background.a * (1.0 - self.a);
+        if a <= 0.0 {
+            return Self::TRANSPARENT;
+        }
+        let blend = |source: f32, destination: f32| {
+            (source * self.a + destination * background.a * (1.0 - self.a)) / a
+        };
+        Self {
+            r: blend(self.r, background.r),
+            g: blend(self.g, background.g),
+            b: blend(self.b, background.b),
+            a,
+        }
+    }
+}
+
+/// Decode an sRGB channel to linear.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear channel as sRGB.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 impl Default for Color {
@@ -64,3 +136,52 @@ impl Default for Color {
         Self::BLACK
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_srgb_mid_gray_is_darker_in_linear() {
+        let gray = Color::from_srgb8(128, 128, 128, 255);
+        assert_near(gray.r, 0.215_860_5);
+        assert_eq!(gray.a, 1.0);
+        assert_near(Color::from_rgba8(128, 128, 128, 255).r, 128.0 / 255.0);
+    }
+
+    #[test]
+    fn test_srgb_round_trips() {
+        for value in 0..=255 {
+            let color = Color::from_srgb8(value, value, value, value);
+            assert_eq!(color.to_srgb8(), [value; 4]);
+        }
+    }
+
+    #[test]
+    fn test_lerp() {
+        let mid = Color::lerp(Color::BLACK, Color::rgba(1.0, 0.5, 0.0, 0.0), 0.5);
+        assert_eq!(mid, Color::rgba(0.5, 0.25, 0.0, 0.5));
+    }
+
+    #[test]
+    fn test_over_matches_porter_duff() {
+        let source = Color::rgba(1.0, 0.0, 0.0, 0.5);
+        let background = Color::rgba(0.0, 0.0, 1.0, 0.5);
+        let result = source.over(background);
+        // αo = αs + αb(1 − αs); Co = (Csαs + Cbαb(1 − αs)) / αo
+        assert_near(result.a, 0.75);
+        assert_near(result.r, 0.5 / 0.75);
+        assert_near(result.b, 0.25 / 0.75);
+
+        assert_eq!(source.over(Color::WHITE).a, 1.0);
+        assert_eq!(Color::WHITE.over(source), Color::WHITE);
+        assert_eq!(
+            Color::TRANSPARENT.over(Color::TRANSPARENT),
+            Color::TRANSPARENT
+        );
+    }
+}
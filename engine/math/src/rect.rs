@@ -77,11 +77,43 @@ impl Rect {
         self.y + self.height
     }
 
-    /// Check if a point is inside the rectangle.
+    /// Whether the rectangle has no area.
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+
+    /// Check if a point is inside the rectangle, counting the top and left
+    /// edges but not the bottom and right ones, so that rectangles sharing
+    /// an edge never both contain a point.
     pub fn contains(&self, point: Vec2) -> bool {
         point.x >= self.x && point.x < self.right() && point.y >= self.y && point.y < self.bottom()
     }
 
+    /// Check if a point is inside the rectangle or on any of its edges.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.x >= self.x
+            && point.x <= self.right()
+            && point.y >= self.y
+            && point.y <= self.bottom()
+    }
+
+    /// Check if another rectangle lies entirely inside this one, edges
+    /// included.
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.right() <= self.right()
+            && other.y >= self.y
+            && other.bottom() <= self.bottom()
+    }
+
+    /// The point inside the rectangle, edges included, closest to `point`.
+    pub fn clamp_point(&self, point: Vec2) -> Vec2 {
+        Vec2::new(
+            point.x.max(self.x).min(self.right()),
+            point.y.max(self.y).min(self.bottom()),
+        )
+    }
+
     /// Check if this rectangle intersects another.
     pub fn intersects(&self, other: &Rect) -> bool {
         self.x < other.right()
@@ -90,7 +122,8 @@ impl Rect {
             && self.bottom() > other.y
     }
 
-    /// Compute the intersection of two rectangles.
+    /// Compute the intersection of two rectangles, or `None` if they do not
+    /// overlap. Rectangles that only touch along an edge do not overlap.
     pub fn intersection(&self, other: &Rect) -> Option<Rect> {
         if !self.intersects(other) {
             return None;
@@ -104,7 +137,8 @@ impl Rect {
         Some(Rect::new(x, y, right - x, bottom - y))
     }
 
-    /// Compute the union (bounding box) of two rectangles.
+    /// Compute the union (bounding box) of two rectangles. A zero-size
+    /// rectangle counts as the point at its origin.
     pub fn union(&self, other: &Rect) -> Rect {
         let x = self.x.min(other.x);
         let y = self.y.min(other.y);
@@ -124,6 +158,19 @@ impl Rect {
         )
     }
 
+    /// Grow the rectangle by `dx` on the left and right and by `dy` on the
+    /// top and bottom, or shrink it for negative amounts. Returns `None` if
+    /// it shrinks past zero size.
+    pub fn inflate(&self, dx: f32, dy: f32) -> Option<Rect> {
+        let rect = Rect::new(
+            self.x - dx,
+            self.y - dy,
+            self.width + dx * 2.0,
+            self.height + dy * 2.0,
+        );
+        (rect.width >= 0.0 && rect.height >= 0.0).then_some(rect)
+    }
+
     /// Translate the rectangle by an offset.
     pub fn translate(&self, offset: Vec2) -> Rect {
         Rect::new(
@@ -134,3 +181,94 @@ impl Rect {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersection() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(
+            a.intersection(&Rect::new(5.0, 5.0, 10.0, 10.0)),
+            Some(Rect::new(5.0, 5.0, 5.0, 5.0))
+        );
+        assert_eq!(
+            a.intersection(&Rect::new(2.0, 3.0, 4.0, 4.0)),
+            Some(Rect::new(2.0, 3.0, 4.0, 4.0))
+        );
+        // Touching along an edge or at a corner.
+        assert_eq!(a.intersection(&Rect::new(10.0, 0.0, 5.0, 10.0)), None);
+        assert_eq!(a.intersection(&Rect::new(10.0, 10.0, 5.0, 5.0)), None);
+        // Disjoint.
+        assert_eq!(a.intersection(&Rect::new(20.0, 20.0, 5.0, 5.0)), None);
+        // A zero-size rectangle inside another is a point.
+        assert_eq!(
+            a.intersection(&Rect::new(4.0, 4.0, 0.0, 0.0)),
+            Some(Rect::new(4.0, 4.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(
+            a.union(&Rect::new(20.0, 5.0, 5.0, 10.0)),
+            Rect::new(0.0, 0.0, 25.0, 15.0)
+        );
+        assert_eq!(
+            a.union(&Rect::new(-5.0, 5.0, 0.0, 0.0)),
+            Rect::new(-5.0, 0.0, 15.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn test_point_containment_on_edges() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        for point in [
+            Vec2::ZERO,
+            Vec2::new(10.0, 10.0),
+            Vec2::new(10.0, 5.0),
+            Vec2::new(5.0, 0.0),
+        ] {
+            assert!(rect.contains_point(point), "{point}");
+        }
+        assert!(!rect.contains_point(Vec2::new(10.1, 5.0)));
+        assert!(rect.contains(Vec2::ZERO));
+        assert!(!rect.contains(Vec2::new(10.0, 5.0)));
+
+        let empty = Rect::new(3.0, 3.0, 0.0, 0.0);
+        assert!(empty.is_empty());
+        assert!(empty.contains_point(Vec2::new(3.0, 3.0)));
+        assert!(!empty.contains(Vec2::new(3.0, 3.0)));
+    }
+
+    #[test]
+    fn test_contains_rect() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert!(rect.contains_rect(&rect));
+        assert!(rect.contains_rect(&Rect::new(10.0, 10.0, 0.0, 0.0)));
+        assert!(!rect.contains_rect(&Rect::new(5.0, 5.0, 6.0, 1.0)));
+    }
+
+    #[test]
+    fn test_inflate() {
+        let rect = Rect::new(10.0, 10.0, 10.0, 4.0);
+        assert_eq!(rect.inflate(1.0, 2.0), Some(Rect::new(9.0, 8.0, 12.0, 8.0)));
+        assert_eq!(
+            rect.inflate(-5.0, -2.0),
+            Some(Rect::new(15.0, 12.0, 0.0, 0.0))
+        );
+        assert_eq!(rect.inflate(0.0, -3.0), None);
+    }
+
+    #[test]
+    fn test_clamp_point() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(
+            rect.clamp_point(Vec2::new(-4.0, 15.0)),
+            Vec2::new(0.0, 10.0)
+        );
+        assert_eq!(rect.clamp_point(Vec2::new(3.0, 4.0)), Vec2::new(3.0, 4.0));
+    }
+}
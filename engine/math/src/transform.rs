@@ -1,6 +1,8 @@
 //! 2D transforms.
 
 use glam::{Affine2, Mat3, Vec2};
+
+use crate::Rect;
 use serde::{Deserialize, Serialize};
 
 /// A 2D transformation.
@@ -48,10 +50,11 @@ impl Transform2D {
         Self::rotate(degrees.to_radians())
     }
 
-    /// Combine with another transform (self * other).
+    /// Combine with another transform applied after this one, so that
+    /// `a.then(&b)` maps a point through `a` and then through `b`.
     pub fn then(&self, other: &Transform2D) -> Self {
         Self {
-            affine: self.affine * other.affine,
+            affine: other.affine * self.affine,
         }
     }
 
@@ -65,11 +68,31 @@ impl Transform2D {
         self.affine.transform_vector2(vector)
     }
 
-    /// Get the inverse transform.
-    pub fn inverse(&self) -> Self {
-        Self {
-            affine: self.affine.inverse(),
+    /// Transform a rectangle, giving the tightest axis-aligned rectangle
+    /// around its transformed corners.
+    pub fn transform_rect(&self, rect: Rect) -> Rect {
+        let corners = [
+            Vec2::new(rect.x, rect.y),
+            Vec2::new(rect.right(), rect.y),
+            Vec2::new(rect.x, rect.bottom()),
+            Vec2::new(rect.right(), rect.bottom()),
+        ]
+        .map(|corner| self.transform_point(corner));
+        let min = corners.iter().copied().fold(Vec2::INFINITY, Vec2::min);
+        let max = corners.iter().copied().fold(Vec2::NEG_INFINITY, Vec2::max);
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// Get the inverse transform, or `None` if the transform collapses the
+    /// plane onto a line or point and so cannot be undone.
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.affine.matrix2.determinant();
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
         }
+        Some(Self {
+            affine: self.affine.inverse(),
+        })
     }
 
     /// Convert to a 3x3 matrix.
@@ -84,11 +107,12 @@ impl Default for Transform2D {
     }
 }
 
+/// Matrix product: `a * b` applies `b` first, like `b.then(&a)`.
 impl std::ops::Mul for Transform2D {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self {
-        self.then(&rhs)
+        rhs.then(&self)
     }
 }
 
@@ -125,3 +149,69 @@ mod affine2_serde {
         ]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_then_applies_in_order() {
+        let moved_then_scaled =
+            Transform2D::translate(1.0, 0.0).then(&Transform2D::uniform_scale(2.0));
+        assert_near(
+            moved_then_scaled.transform_point(Vec2::ZERO),
+            Vec2::new(2.0, 0.0),
+        );
+        assert_eq!(
+            Transform2D::uniform_scale(2.0) * Transform2D::translate(1.0, 0.0),
+            moved_then_scaled
+        );
+    }
+
+    #[test]
+    fn test_composition_is_associative() {
+        let a = Transform2D::translate(3.0, -2.0);
+        let b = Transform2D::rotate_degrees(30.0);
+        let c = Transform2D::scale(2.0, 0.5);
+        let point = Vec2::new(1.5, 4.0);
+        assert_near(
+            a.then(&b).then(&c).transform_point(point),
+            a.then(&b.then(&c)).transform_point(point),
+        );
+    }
+
+    #[test]
+    fn test_inverse_round_trips_points() {
+        let transform = Transform2D::rotate_degrees(37.0)
+            .then(&Transform2D::scale(3.0, 0.25))
+            .then(&Transform2D::translate(-5.0, 8.0));
+        let inverse = transform.inverse().unwrap();
+        let point = Vec2::new(12.0, -7.5);
+        assert_near(
+            inverse.transform_point(transform.transform_point(point)),
+            point,
+        );
+
+        assert_eq!(Transform2D::scale(0.0, 1.0).inverse(), None);
+        assert_eq!(Transform2D::scale(f32::INFINITY, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn test_rotated_rect_bounds() {
+        let bounds =
+            Transform2D::rotate_degrees(45.0).transform_rect(Rect::new(0.0, 0.0, 2.0, 2.0));
+        let diagonal = 2.0 * std::f32::consts::SQRT_2;
+        assert_near(bounds.origin(), Vec2::new(-diagonal / 2.0, 0.0));
+        assert_near(
+            Vec2::new(bounds.width, bounds.height),
+            Vec2::splat(diagonal),
+        );
+
+        let moved = Transform2D::translate(1.0, 2.0).transform_rect(Rect::new(0.0, 0.0, 3.0, 4.0));
+        assert_eq!(moved, Rect::new(1.0, 2.0, 3.0, 4.0));
+    }
+}
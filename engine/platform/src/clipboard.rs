@@ -0,0 +1,219 @@
+//! System clipboard access.
+//!
+//! Text goes through the system clipboard, so other applications can paste
+//! it. The system clipboard only carries text portably, so payloads of other
+//! MIME types, such as the native Wolia fragment, stay in the process along
+//! with the text they were copied with. They are offered only while the
+//! system clipboard still holds that text: once another application copies
+//! something, only its text is available.
+//!
+//! Where there is no system clipboard, such as on a headless machine, the
+//! clipboard is kept entirely in the process.
+//!
+//! Clipboards must be created and used on the main thread; [`Clipboard`] is
+//! neither `Send` nor `Sync`.
+
+use std::marker::PhantomData;
+
+use crate::{Error, Result};
+
+/// MIME type of plain text.
+pub const MIME_TEXT: &str = "text/plain";
+
+/// A MIME type and the data in that representation.
+type Representation = (String, Vec<u8>);
+
+/// The system clipboard.
+pub struct Clipboard {
+    /// The system clipboard, if there is one.
+    system: Option<arboard::Clipboard>,
+    /// The text on the clipboard when there is no system clipboard.
+    text: Option<String>,
+    /// Payloads other than text from the last copy, with the text copied
+    /// alongside them.
+    payloads: Option<(String, Vec<Representation>)>,
+    /// Ties the clipboard to the thread that created it.
+    _main_thread: PhantomData<*const ()>,
+}
+
+impl std::fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Clipboard")
+            .field("system", &self.system.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Clipboard {
+    /// Open the system clipboard, or an in-process one if it is not
+    /// available.
+    pub fn new() -> Self {
+        let system = match arboard::Clipboard::new() {
+            Ok(system) => Some(system),
+            Err(e) => {
+                tracing::debug!("System clipboard unavailable: {}", e);
+                None
+            }
+        };
+        Self {
+            system,
+            ..Self::in_memory()
+        }
+    }
+
+    /// A clipboard kept entirely in the process.
+    pub fn in_memory() -> Self {
+        Self {
+            system: None,
+            text: None,
+            payloads: None,
+            _main_thread: PhantomData,
+        }
+    }
+
+    /// Whether this clipboard is shared with other applications.
+    pub fn is_system(&self) -> bool {
+        self.system.is_some()
+    }
+
+    /// Get the text on the clipboard.
+    pub fn get_text(&mut self) -> Option<String> {
+        match &mut self.system {
+            Some(system) => system.get_text().ok(),
+            None => self.text.clone(),
+        }
+    }
+
+    /// Replace the clipboard contents with `text`.
+    pub fn set_text(&mut self, text: &str) -> Result<()> {
+        self.payloads = None;
+        self.put_text(text)
+    }
+
+    /// Replace the clipboard contents with every (MIME type, data)
+    /// representation given. Plain text goes on the system clipboard; the
+    /// rest are only offered back to this process.
+    pub fn set_data(&mut self, representations: &[(&str, &[u8])]) -> Result<()> {
+        let text = representations
+            .iter()
+            .find(|(mime, _)| *mime == MIME_TEXT)
+            .map(|(_, data)| String::from_utf8_lossy(data).into_owned())
+            .unwrap_or_default();
+        let payloads: Vec<Representation> = representations
+            .iter()
+            .filter(|(mime, _)| *mime != MIME_TEXT)
+            .map(|(mime, data)| (mime.to_string(), data.to_vec()))
+            .collect();
+        self.put_text(&text)?;
+        self.payloads = (!payloads.is_empty()).then_some((text, payloads));
+        Ok(())
+    }
+
+    /// Get the representation of MIME type `mime`, if the clipboard holds
+    /// one.
+    pub fn get_data(&mut self, mime: &str) -> Option<Vec<u8>> {
+        if mime == MIME_TEXT {
+            return self.get_text().map(String::into_bytes);
+        }
+        self.current_payloads()?
+            .iter()
+            .find(|(m, _)| m == mime)
+            .map(|(_, data)| data.clone())
+    }
+
+    /// MIME types of the representations on the clipboard, plain text
+    /// first.
+    pub fn available_mimes(&mut self) -> Vec<String> {
+        let mut mimes = Vec::new();
+        if self.get_text().is_some_and(|text| !text.is_empty()) {
+            mimes.push(MIME_TEXT.to_string());
+        }
+        if let Some(payloads) = self.current_payloads() {
+            mimes.extend(payloads.iter().map(|(mime, _)| mime.clone()));
+        }
+        mimes
+    }
+
+    /// Whether the clipboard holds a representation of MIME type `mime`.
+    pub fn has(&mut self, mime: &str) -> bool {
+        self.available_mimes().iter().any(|m| m == mime)
+    }
+
+    fn put_text(&mut self, text: &str) -> Result<()> {
+        match &mut self.system {
+            Some(system) => system
+                .set_text(text)
+                .map_err(|e| Error::Clipboard(e.to_string())),
+            None => {
+                self.text = Some(text.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// The payloads from the last copy, unless the text has changed since.
+    fn current_payloads(&mut self) -> Option<&[Representation]> {
+        let text = self.get_text().unwrap_or_default();
+        match &self.payloads {
+            Some((copied, payloads)) if *copied == text => Some(payloads),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIME_NATIVE: &str = "application/x-wolia-fragment";
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut clipboard = Clipboard::in_memory();
+        assert_eq!(clipboard.get_text(), None);
+        clipboard.set_text("héllo").unwrap();
+        assert_eq!(clipboard.get_text().as_deref(), Some("héllo"));
+        assert_eq!(
+            clipboard.get_data(MIME_TEXT),
+            Some("héllo".as_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_available_mimes() {
+        let mut clipboard = Clipboard::in_memory();
+        assert!(clipboard.available_mimes().is_empty());
+
+        clipboard
+            .set_data(&[(MIME_NATIVE, b"{}"), (MIME_TEXT, b"copied")])
+            .unwrap();
+        assert_eq!(clipboard.available_mimes(), [MIME_TEXT, MIME_NATIVE]);
+        assert!(clipboard.has(MIME_NATIVE));
+        assert_eq!(clipboard.get_data(MIME_NATIVE), Some(b"{}".to_vec()));
+
+        // Copying text elsewhere leaves only text.
+        clipboard.put_text("from another app").unwrap();
+        assert_eq!(clipboard.available_mimes(), [MIME_TEXT]);
+        assert_eq!(clipboard.get_data(MIME_NATIVE), None);
+    }
+
+    #[test]
+    fn test_system_text_round_trip() {
+        let mut clipboard = Clipboard::new();
+        if !clipboard.is_system() {
+            // No display to hold a clipboard.
+            return;
+        }
+        clipboard.set_text("wolia clipboard test").unwrap();
+        assert_eq!(
+            clipboard.get_text().as_deref(),
+            Some("wolia clipboard test")
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! Native file open and save dialogs, questions about unsaved changes and
+//! recovered work, and prompts for a line of text.
+//!
+//! File dialogs are shown without blocking: they return a [`PendingFile`]
+//! to poll for the answer, so the event loop keeps running while they are
+//! open. Questions and prompts are modal and block until the user answers.
+//!
+//! Dialogs start in the directory of the last file picked in either kind of
+//! dialog, so opening a file and then saving another lands in the same
+//! place. Cancelling a dialog returns `None` and leaves that directory
+//! alone.
+//!
+//! Text prompts are shown by the platform's own tools: `osascript` on
+//! macOS, PowerShell on Windows, and `zenity` or `kdialog` elsewhere.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::task::Poll;
+use std::thread;
+
+use rfd::{AsyncFileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+
+/// The directory of the last file picked.
+static LAST_DIRECTORY: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// A group of file types the user can pick from, such as
+/// "Markdown (md, markdown)".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFilter {
+    /// Label shown in the dialog.
+    pub label: String,
+    /// Extensions matched, without the leading dot.
+    pub extensions: Vec<String>,
+}
+
+impl FileFilter {
+    /// Create a filter matching `extensions`, given with or without the
+    /// leading dot.
+    pub fn new(label: impl Into<String>, extensions: &[&str]) -> Self {
+        Self {
+            label: label.into(),
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_ascii_lowercase())
+                .collect(),
+        }
+    }
+}
+
+/// Filters for formats given as (name, extension) pairs, as a format
+/// registry lists them.
+///
+/// Extensions of formats sharing a name are grouped into one filter, in the
+/// order the names first appear. When there is more than one filter, one
+/// matching every supported extension comes first.
+pub fn filters<'a>(formats: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<FileFilter> {
+    let mut filters: Vec<FileFilter> = Vec::new();
+    for (name, extension) in formats {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        match filters.iter_mut().find(|filter| filter.label == name) {
+            Some(filter) if filter.extensions.contains(&extension) => {}
+            Some(filter) => filter.extensions.push(extension),
+            None => filters.push(FileFilter {
+                label: name.to_string(),
+                extensions: vec![extension],
+            }),
+        }
+    }
+
+    if filters.len() > 1 {
+        let mut all = Vec::new();
+        for extension in filters.iter().flat_map(|filter| &filter.extensions) {
+            if !all.contains(extension) {
+                all.push(extension.clone());
+            }
+        }
+        filters.insert(
+            0,
+            FileFilter {
+                label: "All supported files".to_string(),
+                extensions: all,
+            },
+        );
+    }
+    filters
+}
+
+/// The answer to a file dialog that may still be open.
+pub struct PendingFile {
+    receiver: Receiver<Option<PathBuf>>,
+}
+
+impl PendingFile {
+    /// Wait for `dialog` on a thread of its own.
+    fn spawn(dialog: impl Future<Output = Option<PathBuf>> + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let path = pollster::block_on(dialog);
+            if let Some(path) = &path {
+                remember_directory(path);
+            }
+            let _ = sender.send(path);
+        });
+        Self { receiver }
+    }
+
+    /// A dialog already answered with `path`, as a cancelled one is with
+    /// `None`.
+    pub fn answered(path: Option<PathBuf>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let _ = sender.send(path);
+        Self { receiver }
+    }
+
+    /// The file picked, or `None` if the user cancelled, once the dialog is
+    /// closed. Each answer is returned once.
+    pub fn poll(&self) -> Poll<Option<PathBuf>> {
+        match self.receiver.try_recv() {
+            Ok(path) => Poll::Ready(path),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+        }
+    }
+}
+
+/// Ask the user for a file to open. Answers `None` if they cancel.
+pub fn open_file(filters: &[FileFilter]) -> PendingFile {
+    let dialog = dialog(filters).pick_file();
+    PendingFile::spawn(async move { Some(dialog.await?.path().to_path_buf()) })
+}
+
+/// Ask the user where to save a file, suggesting `suggested_name`. Answers
+/// `None` if they cancel.
+pub fn save_file(suggested_name: &str, filters: &[FileFilter]) -> PendingFile {
+    let dialog = dialog(filters).set_file_name(suggested_name).save_file();
+    PendingFile::spawn(async move { Some(dialog.await?.path().to_path_buf()) })
+}
+
+/// What to do with a document's unsaved changes before closing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsavedChanges {
+    /// Save the document first.
+    Save,
+    /// Close the document without saving.
+    Discard,
+    /// Keep the document open.
+    Cancel,
+}
+
+/// Ask the user whether to save the changes to the document called `name`
+/// before closing it. Closing the dialog counts as cancelling.
+pub fn ask_unsaved_changes(name: &str) -> UnsavedChanges {
+    let result = MessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("Unsaved changes")
+        .set_description(format!(
+            "Save the changes to \"{}\" before closing it?",
+            name
+        ))
+        .set_buttons(MessageButtons::YesNoCancel)
+        .show();
+    match result {
+        MessageDialogResult::Yes => UnsavedChanges::Save,
+        MessageDialogResult::No => UnsavedChanges::Discard,
+        _ => UnsavedChanges::Cancel,
+    }
+}
+
+/// What to do with a recovery copy left by a session that ended without
+/// saving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryChoice {
+    /// Open the copy as unsaved changes.
+    Restore,
+    /// Delete the copy.
+    Discard,
+    /// Keep the copy and ask again next time.
+    Later,
+}
+
+/// Ask the user whether to restore the unsaved changes to the document
+/// called `name`. Closing the dialog counts as deciding later.
+pub fn ask_restore(name: &str) -> RecoveryChoice {
+    let result = MessageDialog::new()
+        .set_level(MessageLevel::Info)
+        .set_title("Recover unsaved changes")
+        .set_description(format!(
+            "\"{}\" was not saved when Wolia last closed. Restore the unsaved \
+             changes? Choosing No deletes them.",
+            name
+        ))
+        .set_buttons(MessageButtons::YesNoCancel)
+        .show();
+    match result {
+        MessageDialogResult::Yes => RecoveryChoice::Restore,
+        MessageDialogResult::No => RecoveryChoice::Discard,
+        _ => RecoveryChoice::Later,
+    }
+}
+
+/// Ask the user for a line of text, offering `default`. Returns `None` if
+/// they cancel, or if no prompt can be shown.
+///
+/// Windows reports cancelling as an empty answer.
+pub fn prompt_text(title: &str, message: &str, default: &str) -> Option<String> {
+    prompt_commands(title, message, default)
+        .into_iter()
+        .find_map(|mut command| {
+            let output = command.output().ok()?;
+            Some(output.status.success().then(|| {
+                String::from_utf8_lossy(&output.stdout)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string()
+            }))
+        })
+        .flatten()
+}
+
+/// The commands that can show a text prompt on this platform, in the order
+/// to try them.
+fn prompt_commands(title: &str, message: &str, default: &str) -> Vec<Command> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "text returned of (display dialog (item 1 of argv) \
+             default answer (item 2 of argv) with title (item 3 of argv))",
+            "-e",
+            "end run",
+            message,
+            default,
+            title,
+        ]);
+        vec![command]
+    } else if cfg!(target_os = "windows") {
+        // The text is passed in the environment so it is never parsed as
+        // part of the script.
+        let mut command = Command::new("powershell");
+        command
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Add-Type -AssemblyName Microsoft.VisualBasic; \
+                 [Microsoft.VisualBasic.Interaction]::InputBox(\
+                 $env:WOLIA_PROMPT_MESSAGE, $env:WOLIA_PROMPT_TITLE, $env:WOLIA_PROMPT_DEFAULT)",
+            ])
+            .env("WOLIA_PROMPT_MESSAGE", message)
+            .env("WOLIA_PROMPT_TITLE", title)
+            .env("WOLIA_PROMPT_DEFAULT", default);
+        vec![command]
+    } else {
+        let mut zenity = Command::new("zenity");
+        zenity.args([
+            "--entry",
+            "--title",
+            title,
+            "--text",
+            message,
+            "--entry-text",
+            default,
+        ]);
+        let mut kdialog = Command::new("kdialog");
+        kdialog.args(["--title", title, "--inputbox", message, default]);
+        vec![zenity, kdialog]
+    }
+}
+
+/// The directory the next dialog starts in, if a file has been picked.
+pub fn last_directory() -> Option<PathBuf> {
+    LAST_DIRECTORY
+        .lock()
+        .ok()
+        .and_then(|directory| directory.clone())
+}
+
+/// Start the next dialog in the directory of `path`, such as a file opened
+/// some other way.
+pub fn remember_directory(path: &Path) {
+    let directory = if path.is_dir() {
+        Some(path)
+    } else {
+        path.parent()
+    };
+    if let (Some(directory), Ok(mut last)) = (directory, LAST_DIRECTORY.lock()) {
+        if !directory.as_os_str().is_empty() {
+            *last = Some(directory.to_path_buf());
+        }
+    }
+}
+
+fn dialog(filters: &[FileFilter]) -> AsyncFileDialog {
+    let mut dialog = AsyncFileDialog::new();
+    for filter in filters {
+        dialog = dialog.add_filter(&filter.label, &filter.extensions);
+    }
+    if let Some(directory) = last_directory() {
+        dialog = dialog.set_directory(directory);
+    }
+    dialog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_group_by_format() {
+        let filters = filters([
+            ("Wolia Document", "wolia"),
+            ("Markdown", "md"),
+            ("Markdown", ".markdown"),
+            ("Markdown", "MD"),
+        ]);
+        assert_eq!(
+            filters,
+            vec![
+                FileFilter::new("All supported files", &["wolia", "md", "markdown"]),
+                FileFilter::new("Wolia Document", &["wolia"]),
+                FileFilter::new("Markdown", &["md", "markdown"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_format_has_no_catch_all() {
+        assert_eq!(
+            filters([("Wolia Document", "wolia")]),
+            vec![FileFilter::new("Wolia Document", &["wolia"])]
+        );
+        assert!(filters([]).is_empty());
+    }
+
+    #[test]
+    fn test_remembers_directory_of_picked_file() {
+        let directory = std::env::temp_dir();
+        remember_directory(&directory.join("report.wolia"));
+        assert_eq!(last_directory(), Some(directory.clone()));
+        // A bare file name has no directory to remember.
+        remember_directory(Path::new("notes.md"));
+        assert_eq!(last_directory(), Some(directory));
+    }
+
+    #[test]
+    fn test_pending_file_answers_once() {
+        let path = std::env::temp_dir().join("answer.wolia");
+        let pending = PendingFile::spawn(std::future::ready(Some(path.clone())));
+        let answer = loop {
+            if let Poll::Ready(answer) = pending.poll() {
+                break answer;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(answer, Some(path));
+        // Once answered, the dialog is gone.
+        assert_eq!(pending.poll(), Poll::Ready(None));
+        assert_eq!(PendingFile::answered(None).poll(), Poll::Ready(None));
+    }
+
+    #[test]
+    fn test_prompt_passes_text_as_arguments() {
+        let commands = prompt_commands("Link", "Address \"quoted\"", "https://");
+        assert!(!commands.is_empty());
+        for command in &commands {
+            let mut text: Vec<_> = command.get_args().collect();
+            text.extend(command.get_envs().filter_map(|(_, value)| value));
+            assert!(text.contains(&std::ffi::OsStr::new("Address \"quoted\"")));
+            assert!(text.contains(&std::ffi::OsStr::new("https://")));
+        }
+    }
+}
@@ -5,14 +5,23 @@
 //! This crate provides:
 //! - Window management
 //! - Event handling
-//! - OS integration (file dialogs, notifications, etc.)
+//! - OS integration (file dialogs, notifications, opening links, etc.)
 //! - System clipboard access
 
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+#[cfg(feature = "dialogs")]
+pub mod dialog;
 pub mod event;
+#[cfg(feature = "notifications")]
+pub mod notify;
+mod os_version;
+pub mod url;
 pub mod window;
 
 pub use event::{Event, KeyEvent, MouseEvent};
-pub use window::Window;
+pub use url::open_url;
+pub use window::{ScaleFactor, Window};
 
 /// Result type for platform operations.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -28,6 +37,15 @@ pub enum Error {
 
     #[error("Platform not supported: {0}")]
     Unsupported(String),
+
+    #[error("Clipboard error: {0}")]
+    Clipboard(String),
+
+    #[error("Notification error: {0}")]
+    Notification(String),
+
+    #[error("Open error: {0}")]
+    Open(String),
 }
 
 /// Platform information.
@@ -46,7 +64,7 @@ impl Platform {
     pub fn current() -> Self {
         Self {
             os: std::env::consts::OS,
-            os_version: None, // TODO: Get actual version
+            os_version: os_version::detect(),
             is_desktop: cfg!(any(
                 target_os = "windows",
                 target_os = "macos",
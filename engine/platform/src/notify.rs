@@ -0,0 +1,129 @@
+//! Desktop notifications.
+//!
+//! Notifications are handed to the OS notification service on a background
+//! thread, so showing one never blocks the event loop; a notification the
+//! service rejects is logged rather than reported. Where there is no
+//! service to show them, as on a headless machine or in CI, showing a
+//! notification does nothing and succeeds.
+
+use crate::{Error, Result};
+
+/// The application name notifications are shown under.
+const APP_NAME: &str = "Wolia";
+
+/// A desktop notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Title, shown in bold.
+    pub title: String,
+    /// Body text.
+    pub body: String,
+    /// Icon name from the icon theme, or path to an image.
+    pub icon: Option<String>,
+}
+
+impl Notification {
+    /// Create a notification.
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            icon: None,
+        }
+    }
+
+    /// Set the icon, by name from the icon theme or by path.
+    pub fn with_icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    /// Show the notification without waiting for it to appear.
+    ///
+    /// Fails on platforms without a notification service.
+    pub fn show(&self) -> Result<()> {
+        if !is_supported() {
+            return Err(Error::Unsupported(format!(
+                "notifications on {}",
+                std::env::consts::OS
+            )));
+        }
+        if is_headless() {
+            tracing::debug!("No notification service, not showing {:?}", self.title);
+            return Ok(());
+        }
+
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .appname(APP_NAME)
+            .summary(&self.title)
+            .body(&self.body);
+        if let Some(icon) = &self.icon {
+            notification.icon(icon);
+        }
+        std::thread::Builder::new()
+            .name("notification".to_string())
+            .spawn(move || {
+                if let Err(e) = notification.show() {
+                    tracing::warn!("Notification failed: {}", e);
+                }
+            })
+            .map_err(|e| Error::Notification(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Whether the platform has a notification service at all.
+fn is_supported() -> bool {
+    cfg!(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))
+}
+
+/// Whether there is nothing to show notifications on: in CI, or on a Unix
+/// desktop without a session bus to reach the notification daemon through.
+fn is_headless() -> bool {
+    if std::env::var_os("CI").is_some() {
+        return true;
+    }
+    cfg!(not(any(target_os = "windows", target_os = "macos")))
+        && std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_construct() {
+        let notification = Notification::new("Saved", "report.wolia").with_icon("document-save");
+        assert_eq!(notification.title, "Saved");
+        assert_eq!(notification.body, "report.wolia");
+        assert_eq!(notification.icon.as_deref(), Some("document-save"));
+    }
+
+    #[test]
+    fn test_show_headless_is_ok() {
+        if is_supported() && is_headless() {
+            assert!(Notification::new("Saved", "report.wolia").show().is_ok());
+        }
+    }
+
+    /// Shows a real notification; run with `--ignored` and look for it.
+    #[test]
+    #[ignore = "shows a desktop notification"]
+    fn test_show_manual_check() {
+        Notification::new("Wolia", "Notifications work")
+            .with_icon("dialog-information")
+            .show()
+            .unwrap();
+        // Give the background thread time to deliver it.
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
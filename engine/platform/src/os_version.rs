@@ -0,0 +1,151 @@
+//! Operating system version detection.
+//!
+//! Each platform is asked in the way its own tools answer: Windows through
+//! `RtlGetVersion`, which unlike `GetVersionEx` is not subject to
+//! compatibility shims, macOS through `sw_vers`, and Linux through
+//! `/etc/os-release`, falling back to the kernel release. Anything missing
+//! or unreadable leaves the version unknown.
+
+use std::process::Command;
+
+/// A readable version of the running OS, such as "Windows 11 (22631)",
+/// "macOS 14.4 (23E214)" or "Ubuntu 24.04 LTS".
+#[cfg(target_os = "windows")]
+pub(crate) fn detect() -> Option<String> {
+    use windows_sys::Wdk::System::SystemServices::RtlGetVersion;
+    use windows_sys::Win32::System::SystemInformation::OSVERSIONINFOW;
+
+    let mut info = OSVERSIONINFOW {
+        dwOSVersionInfoSize: std::mem::size_of::<OSVERSIONINFOW>() as u32,
+        ..Default::default()
+    };
+    // SAFETY: `info` is a valid OSVERSIONINFOW with its size filled in, as
+    // the call requires, and outlives it.
+    let status = unsafe { RtlGetVersion(&mut info) };
+    (status == 0)
+        .then(|| format_windows(info.dwMajorVersion, info.dwMinorVersion, info.dwBuildNumber))
+}
+
+/// A readable version of the running OS, such as "Windows 11 (22631)",
+/// "macOS 14.4 (23E214)" or "Ubuntu 24.04 LTS".
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn detect() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        let product = run("sw_vers", &["-productVersion"])?;
+        Some(format_macos(
+            &product,
+            run("sw_vers", &["-buildVersion"]).as_deref(),
+        ))
+    } else if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/etc/os-release")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/usr/lib/os-release").ok())
+            .and_then(|release| parse_os_release(&release))
+            .or_else(|| {
+                let kernel = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+                let kernel = kernel.trim();
+                (!kernel.is_empty()).then(|| format!("Linux {}", kernel))
+            })
+    } else {
+        None
+    }
+}
+
+/// The trimmed output of a command, if it ran and printed something.
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// The marketing name of a Windows version with its build. Windows 11
+/// still reports itself as version 10, and is told apart by its build
+/// number.
+#[cfg(any(target_os = "windows", test))]
+fn format_windows(major: u32, minor: u32, build: u32) -> String {
+    let name = match (major, minor) {
+        (10, _) if build >= 22000 => "11".to_string(),
+        (6, 1) => "7".to_string(),
+        (6, 2) => "8".to_string(),
+        (6, 3) => "8.1".to_string(),
+        (6, _) => "Vista".to_string(),
+        (major, _) => major.to_string(),
+    };
+    format!("Windows {} ({})", name, build)
+}
+
+/// The macOS product version with its build, if known.
+fn format_macos(product: &str, build: Option<&str>) -> String {
+    match build {
+        Some(build) => format!("macOS {} ({})", product, build),
+        None => format!("macOS {}", product),
+    }
+}
+
+/// The distribution name and version in an os-release file: its pretty
+/// name, or its name and version.
+fn parse_os_release(release: &str) -> Option<String> {
+    let field = |key: &str| {
+        release.lines().find_map(|line| {
+            let value = line.trim().strip_prefix(key)?.strip_prefix('=')?;
+            let value = value.trim().trim_matches('"').trim_matches('\'').trim();
+            (!value.is_empty()).then(|| value.to_string())
+        })
+    };
+    field("PRETTY_NAME").or_else(|| {
+        let name = field("NAME")?;
+        Some(match field("VERSION").or_else(|| field("VERSION_ID")) {
+            Some(version) => format!("{} {}", name, version),
+            None => name,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_windows() {
+        assert_eq!(format_windows(10, 0, 22631), "Windows 11 (22631)");
+        assert_eq!(format_windows(10, 0, 19045), "Windows 10 (19045)");
+        assert_eq!(format_windows(6, 1, 7601), "Windows 7 (7601)");
+        assert_eq!(format_windows(6, 0, 6002), "Windows Vista (6002)");
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let release = "NAME=\"Ubuntu\"\nVERSION_ID=\"24.04\"\nPRETTY_NAME=\"Ubuntu 24.04 LTS\"\n";
+        assert_eq!(
+            parse_os_release(release).as_deref(),
+            Some("Ubuntu 24.04 LTS")
+        );
+        assert_eq!(
+            parse_os_release("NAME=Arch\nVERSION_ID=rolling\n").as_deref(),
+            Some("Arch rolling")
+        );
+        assert_eq!(parse_os_release("NAME=Gentoo\n").as_deref(), Some("Gentoo"));
+        assert_eq!(parse_os_release("# empty\n"), None);
+    }
+
+    #[test]
+    fn test_format_macos() {
+        assert_eq!(format_macos("14.4", Some("23E214")), "macOS 14.4 (23E214)");
+        assert_eq!(format_macos("14.4", None), "macOS 14.4");
+    }
+
+    #[test]
+    fn test_detect_current_os() {
+        if cfg!(any(
+            target_os = "windows",
+            target_os = "macos",
+            target_os = "linux"
+        )) {
+            let version = detect().expect("OS version should be detected");
+            assert!(!version.trim().is_empty());
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Opening links in the applications the OS picks for them.
+//!
+//! The URL is handed to the platform's own opener as a single argument,
+//! never through a shell: `open` on macOS, the URL protocol handler run by
+//! `rundll32` on Windows, and `xdg-open` elsewhere. Only web and mail links
+//! are opened, so a link in a document cannot run a program, whether by
+//! pointing at a local file or by smuggling shell syntax in, or pass
+//! options to the opener.
+
+use std::process::Command;
+
+use crate::{Error, Result};
+
+/// URL schemes that are opened.
+const SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Open `url` in the application the OS picks for it, such as the web
+/// browser, without waiting for it to start.
+pub fn open_url(url: &str) -> Result<()> {
+    let url = url.trim();
+    if !is_openable(url) {
+        return Err(Error::Open(format!("cannot open {:?}", url)));
+    }
+    open_command(std::env::consts::OS, url)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| Error::Open(format!("{}: {}", url, e)))
+}
+
+/// The command opening `url` on the OS named `os`, as
+/// [`std::env::consts::OS`] names it.
+fn open_command(os: &str, url: &str) -> Command {
+    let mut command = match os {
+        "macos" => Command::new("open"),
+        // Unlike `cmd /C start`, this parses no shell syntax.
+        "windows" => {
+            let mut command = Command::new("rundll32");
+            command.arg("url.dll,FileProtocolHandler");
+            command
+        }
+        _ => Command::new("xdg-open"),
+    };
+    command.arg(url);
+    command
+}
+
+/// Whether `url` has a scheme that is opened, and no spaces or control
+/// characters, which a URL never needs.
+pub fn is_openable(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+    !rest.is_empty()
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+        && SCHEMES
+            .iter()
+            .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_known_schemes_open() {
+        assert!(is_openable("https://example.com/docs"));
+        assert!(is_openable("HTTP://example.com"));
+        assert!(is_openable("mailto:someone@example.com"));
+        assert!(!is_openable("example.com"));
+        assert!(!is_openable("javascript:alert(1)"));
+        assert!(!is_openable("--help"));
+        assert!(!is_openable("https:"));
+        assert!(!is_openable("file:///C:/Windows/System32/calc.exe"));
+        assert!(!is_openable("https://example.com/a b"));
+        assert!(open_url("ftp://example.com").is_err());
+    }
+
+    #[test]
+    fn test_url_never_reaches_a_shell() {
+        let url = "https://example.com/?a=1&calc.exe^|x%PATH%";
+        assert!(is_openable(url));
+        for os in ["macos", "windows", "linux"] {
+            let command = open_command(os, url);
+            let program = command.get_program().to_string_lossy().to_lowercase();
+            for shell in ["cmd", "sh", "bash", "powershell"] {
+                assert_ne!(program, shell);
+            }
+            // The URL is one argument, exactly as given.
+            let args: Vec<_> = command.get_args().collect();
+            assert_eq!(args.last(), Some(&std::ffi::OsStr::new(url)));
+            assert_eq!(
+                args.iter()
+                    .filter(|arg| arg.to_string_lossy().contains("calc"))
+                    .count(),
+                1
+            );
+        }
+    }
+}
@@ -1,7 +1,9 @@
 //! Window management.
 
 use winit::window::{Icon, WindowAttributes};
-use wolia_math::Size;
+use wolia_math::{Point, Rect, Size};
+
+use crate::{Error, Result};
 
 /// Window configuration.
 #[derive(Debug, Clone)]
@@ -54,13 +56,51 @@ impl WindowConfig {
         self
     }
 
+    /// Set the smallest size the window can be resized to.
+    pub fn with_min_size(mut self, width: f32, height: f32) -> Self {
+        self.min_size = Some(Size::new(width, height));
+        self
+    }
+
+    /// Set the largest size the window can be resized to.
+    pub fn with_max_size(mut self, width: f32, height: f32) -> Self {
+        self.max_size = Some(Size::new(width, height));
+        self
+    }
+
+    /// Set whether the user can resize the window.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Set whether the window has a title bar and borders.
+    pub fn with_decorations(mut self, decorated: bool) -> Self {
+        self.decorated = decorated;
+        self
+    }
+
     /// Set the window icon path.
     pub fn with_icon(mut self, icon_path: impl Into<String>) -> Self {
         self.icon_path = Some(icon_path.into());
         self
     }
 
-    /// Convert to winit window attributes.
+    /// Check that the minimum size is no larger than the maximum size.
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
+            if min.width > max.width || min.height > max.height {
+                return Err(Error::WindowCreation(format!(
+                    "minimum size {}x{} is larger than maximum size {}x{}",
+                    min.width, min.height, max.width, max.height
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert to winit window attributes. A maximum size smaller than the
+    /// minimum size is left out, as [`validate`](Self::validate) reports.
     pub fn to_window_attributes(&self) -> WindowAttributes {
         let mut attrs = WindowAttributes::default()
             .with_title(&self.title)
@@ -79,7 +119,9 @@ impl WindowConfig {
             ));
         }
 
-        if let Some(max) = self.max_size {
+        if let Err(e) = self.validate() {
+            tracing::warn!("Ignoring maximum window size: {}", e);
+        } else if let Some(max) = self.max_size {
             attrs = attrs.with_max_inner_size(winit::dpi::LogicalSize::new(
                 max.width as f64,
                 max.height as f64,
@@ -113,6 +155,82 @@ fn load_icon(path: &str) -> Option<Icon> {
     }
 }
 
+/// The ratio of physical pixels to logical units on a display.
+///
+/// The UI is laid out and hit-tested in logical units, so it is the same
+/// size on every display; the surface is drawn in physical pixels, of which
+/// there are `scale` per logical unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleFactor(f64);
+
+impl ScaleFactor {
+    /// One physical pixel per logical unit.
+    pub const ONE: Self = Self(1.0);
+
+    /// A scale factor of `scale`. Factors that are not positive and finite
+    /// are taken as 1.
+    pub fn new(scale: f64) -> Self {
+        if scale.is_finite() && scale > 0.0 {
+            Self(scale)
+        } else {
+            Self::ONE
+        }
+    }
+
+    /// Physical pixels per logical unit.
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// A length in logical units as physical pixels.
+    pub fn to_physical(self, logical: f32) -> f32 {
+        (logical as f64 * self.0) as f32
+    }
+
+    /// A length in physical pixels as logical units.
+    pub fn to_logical(self, physical: f32) -> f32 {
+        (physical as f64 / self.0) as f32
+    }
+
+    /// A point in physical pixels, such as a cursor position, in logical
+    /// units.
+    pub fn point_to_logical(self, physical: Point) -> Point {
+        Point::new(self.to_logical(physical.x), self.to_logical(physical.y))
+    }
+
+    /// A rectangle in logical units as physical pixels.
+    pub fn rect_to_physical(self, logical: Rect) -> Rect {
+        Rect::new(
+            self.to_physical(logical.x),
+            self.to_physical(logical.y),
+            self.to_physical(logical.width),
+            self.to_physical(logical.height),
+        )
+    }
+
+    /// The size in logical units of a surface `width` by `height` physical
+    /// pixels.
+    pub fn logical_size(self, width: u32, height: u32) -> Size {
+        Size::new(
+            self.to_logical(width as f32),
+            self.to_logical(height as f32),
+        )
+    }
+
+    /// The size in whole physical pixels of an area of `logical` size,
+    /// rounded to the nearest pixel and at least one pixel each way.
+    pub fn physical_size(self, logical: Size) -> (u32, u32) {
+        let pixels = |length: f32| (self.to_physical(length).round() as u32).max(1);
+        (pixels(logical.width), pixels(logical.height))
+    }
+}
+
+impl Default for ScaleFactor {
+    fn default() -> Self {
+        Self::ONE
+    }
+}
+
 /// A platform window.
 pub struct Window {
     /// The winit window.
@@ -120,17 +238,29 @@ pub struct Window {
 }
 
 impl Window {
-    /// Get the window size.
+    /// Get the window size in physical pixels.
     pub fn size(&self) -> Size {
         let size = self.inner.inner_size();
         Size::new(size.width as f32, size.height as f32)
     }
 
+    /// Get the window size in logical units.
+    pub fn logical_size(&self) -> Size {
+        let size = self.inner.inner_size();
+        self.scale().logical_size(size.width, size.height)
+    }
+
     /// Get the scale factor.
     pub fn scale_factor(&self) -> f64 {
         self.inner.scale_factor()
     }
 
+    /// Get the scale factor for converting between logical units and
+    /// physical pixels.
+    pub fn scale(&self) -> ScaleFactor {
+        ScaleFactor::new(self.inner.scale_factor())
+    }
+
     /// Request a redraw.
     pub fn request_redraw(&self) {
         self.inner.request_redraw();
@@ -141,3 +271,83 @@ impl Window {
         self.inner.set_title(title);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::{LogicalSize, Size as DpiSize};
+
+    use super::*;
+
+    fn logical(width: f64, height: f64) -> Option<DpiSize> {
+        Some(DpiSize::Logical(LogicalSize::new(width, height)))
+    }
+
+    #[test]
+    fn test_default_attributes() {
+        let attrs = WindowConfig::new("Wolia").to_window_attributes();
+        assert_eq!(attrs.inner_size, logical(1280.0, 720.0));
+        assert_eq!(attrs.min_inner_size, logical(400.0, 300.0));
+        assert_eq!(attrs.max_inner_size, None);
+        assert!(attrs.resizable);
+        assert!(attrs.decorations);
+    }
+
+    #[test]
+    fn test_builder_options_reach_attributes() {
+        let config = WindowConfig::new("Wolia")
+            .with_size(1000.0, 700.0)
+            .with_min_size(800.0, 500.0)
+            .with_max_size(1600.0, 1000.0)
+            .with_resizable(false)
+            .with_decorations(false);
+        assert!(config.validate().is_ok());
+        let attrs = config.to_window_attributes();
+        assert_eq!(attrs.inner_size, logical(1000.0, 700.0));
+        assert_eq!(attrs.min_inner_size, logical(800.0, 500.0));
+        assert_eq!(attrs.max_inner_size, logical(1600.0, 1000.0));
+        assert!(!attrs.resizable);
+        assert!(!attrs.decorations);
+    }
+
+    #[test]
+    fn test_min_larger_than_max_is_invalid() {
+        let config = WindowConfig::new("Wolia")
+            .with_min_size(800.0, 500.0)
+            .with_max_size(1600.0, 400.0);
+        assert!(matches!(config.validate(), Err(Error::WindowCreation(_))));
+        assert_eq!(config.to_window_attributes().max_inner_size, None);
+    }
+
+    #[test]
+    fn test_scale_factor_conversions() {
+        let scale = ScaleFactor::new(2.0);
+        assert_eq!(scale.to_physical(12.5), 25.0);
+        assert_eq!(scale.to_logical(25.0), 12.5);
+        assert_eq!(
+            scale.point_to_logical(Point::new(300.0, 101.0)),
+            Point::new(150.0, 50.5)
+        );
+        assert_eq!(
+            scale.rect_to_physical(Rect::new(10.0, 20.0, 30.0, 40.0)),
+            Rect::new(20.0, 40.0, 60.0, 80.0)
+        );
+        assert_eq!(scale.logical_size(2800, 1800), Size::new(1400.0, 900.0));
+        assert_eq!(scale.physical_size(Size::new(1400.0, 900.0)), (2800, 1800));
+    }
+
+    #[test]
+    fn test_fractional_scale_round_trips() {
+        let scale = ScaleFactor::new(1.25);
+        assert_eq!(scale.physical_size(Size::new(1280.0, 720.0)), (1600, 900));
+        let size = scale.logical_size(1600, 900);
+        assert_eq!(scale.physical_size(size), (1600, 900));
+        assert_eq!(scale.physical_size(Size::new(0.1, 0.0)), (1, 1));
+    }
+
+    #[test]
+    fn test_invalid_scale_is_one() {
+        assert_eq!(ScaleFactor::new(0.0), ScaleFactor::ONE);
+        assert_eq!(ScaleFactor::new(f64::NAN), ScaleFactor::ONE);
+        assert_eq!(ScaleFactor::new(-2.0).to_physical(3.0), 3.0);
+    }
+}
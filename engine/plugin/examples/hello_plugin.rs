@@ -0,0 +1,46 @@
+//! A minimal plugin library, loaded by the loader tests.
+//!
+//! Records its lifecycle in `HELLO_PLUGIN_EVENTS` as decimal digits: 1 for
+//! `init`, 2 for `shutdown`, and 3 for drop.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use wolia_plugin::{Plugin, Result};
+
+#[unsafe(no_mangle)]
+pub static HELLO_PLUGIN_EVENTS: AtomicU32 = AtomicU32::new(0);
+
+fn record(event: u32) {
+    let _ = HELLO_PLUGIN_EVENTS.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |events| {
+        Some(events * 10 + event)
+    });
+}
+
+struct HelloPlugin;
+
+impl Plugin for HelloPlugin {
+    fn name(&self) -> &str {
+        "hello"
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn init(&mut self) -> Result<()> {
+        record(1);
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        record(2);
+    }
+}
+
+impl Drop for HelloPlugin {
+    fn drop(&mut self) {
+        record(3);
+    }
+}
+
+wolia_plugin::declare_plugin!(HelloPlugin);
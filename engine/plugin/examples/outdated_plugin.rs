@@ -0,0 +1,13 @@
+//! A plugin library built for another API version, loaded by the loader
+//! tests.
+
+use wolia_plugin::Plugin;
+
+#[unsafe(no_mangle)]
+pub static WOLIA_PLUGIN_API_VERSION: u32 = wolia_plugin::API_VERSION + 1;
+
+#[unsafe(no_mangle)]
+pub extern "C" fn wolia_plugin_create() -> *mut Box<dyn Plugin> {
+    // The host must not call into a plugin built for another API version.
+    std::process::abort()
+}
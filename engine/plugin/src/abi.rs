@@ -0,0 +1,40 @@
+//! Plugin ABI.
+//!
+//! A plugin library is a `cdylib` exporting two symbols, both defined by
+//! [`declare_plugin!`](crate::declare_plugin): the API version it was built
+//! against, and a function creating the plugin. The host checks the version
+//! before calling anything in the library.
+//!
+//! Plugins are passed as Rust trait objects, so a plugin has to be built with
+//! the same compiler and `wolia-plugin` version as the host.
+
+use crate::Plugin;
+
+/// Name of the `u32` static holding the API version of a plugin library.
+pub const VERSION_SYMBOL: &[u8] = b"WOLIA_PLUGIN_API_VERSION\0";
+
+/// Name of the [`CreatePlugin`] function of a plugin library.
+pub const CREATE_SYMBOL: &[u8] = b"wolia_plugin_create\0";
+
+/// Creates a plugin, handing ownership of the box to the host.
+pub type CreatePlugin = unsafe extern "C" fn() -> *mut Box<dyn Plugin>;
+
+/// Export a plugin from a `cdylib`, given an expression creating it.
+///
+/// ```ignore
+/// wolia_plugin::declare_plugin!(MyPlugin::new());
+/// ```
+#[macro_export]
+macro_rules! declare_plugin {
+    ($constructor:expr) => {
+        #[unsafe(no_mangle)]
+        pub static WOLIA_PLUGIN_API_VERSION: u32 = $crate::API_VERSION;
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn wolia_plugin_create() -> *mut ::std::boxed::Box<dyn $crate::Plugin> {
+            let plugin: ::std::boxed::Box<dyn $crate::Plugin> =
+                ::std::boxed::Box::new($constructor);
+            ::std::boxed::Box::into_raw(::std::boxed::Box::new(plugin))
+        }
+    };
+}
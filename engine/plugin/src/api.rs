@@ -1,5 +1,9 @@
 //! Plugin API traits.
 
+use std::sync::Arc;
+
+use wolia_core::text::Text;
+
 /// A loaded plugin instance.
 pub trait Plugin: Send + Sync {
     /// Get the plugin name.
@@ -13,6 +17,12 @@ pub trait Plugin: Send + Sync {
 
     /// Shut down the plugin.
     fn shutdown(&mut self);
+
+    /// Register the plugin's handlers with the host. Called after `init`.
+    fn register(&mut self, api: &mut dyn PluginApi) -> crate::Result<()> {
+        let _ = api;
+        Ok(())
+    }
 }
 
 /// API provided to plugins by the host.
@@ -23,6 +33,15 @@ pub trait PluginApi {
     /// Register a content type.
     fn register_content_type(&mut self, type_id: &str, handler: Box<dyn ContentHandler>);
 
+    /// Claim block kinds, such as `"code"` or `"latex"`, for a handler.
+    /// Fails without claiming any of them if another plugin already handles
+    /// one.
+    fn register_block_handler(
+        &mut self,
+        kinds: &[&str],
+        handler: Arc<dyn BlockHandler>,
+    ) -> crate::Result<()>;
+
     /// Log a message.
     fn log(&self, level: LogLevel, message: &str);
 }
@@ -42,6 +61,70 @@ pub trait ContentHandler: Send + Sync {
     fn type_name(&self) -> &str;
 }
 
+/// Block handler, rendering document blocks of the kinds it claimed.
+pub trait BlockHandler: Send + Sync {
+    /// Render a block, or return `None` to leave it to the host.
+    fn handle_block(&self, block: &BlockSource<'_>) -> Option<RenderedBlock>;
+}
+
+/// A document block passed to a [`BlockHandler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockSource<'a> {
+    /// Block kind, such as `"code"`.
+    pub kind: &'a str,
+    /// Language of a code block, from its fence.
+    pub language: Option<&'a str>,
+    /// Block content.
+    pub source: &'a str,
+}
+
+/// A block as rendered by a plugin.
+#[derive(Debug, Clone)]
+pub enum RenderedBlock {
+    /// Styled text, laid out like a paragraph.
+    Text(Box<Text>),
+    /// Placed glyphs and rules, such as typeset math.
+    Drawing(Drawing),
+}
+
+/// Glyphs and rules placed by a plugin. Positions are in points from the
+/// left end of the baseline, with y growing downwards.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Drawing {
+    /// Glyphs, each placed by the left end of its baseline.
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Filled rectangles, such as fraction bars, as `[x, y, width, height]`.
+    pub rules: Vec<[f32; 4]>,
+    /// Width of the drawing.
+    pub width: f32,
+    /// Extent above the baseline.
+    pub ascent: f32,
+    /// Extent below the baseline.
+    pub descent: f32,
+}
+
+impl Drawing {
+    /// Total height of the drawing.
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+}
+
+/// A glyph in a [`Drawing`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedGlyph {
+    /// The character to draw.
+    pub ch: char,
+    /// Horizontal position of the glyph's origin.
+    pub x: f32,
+    /// Vertical position of the glyph's baseline.
+    pub y: f32,
+    /// Font size in points.
+    pub size: f32,
+    /// Whether the glyph is drawn in italics.
+    pub italic: bool,
+}
+
 /// Log level.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
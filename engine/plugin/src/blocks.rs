@@ -0,0 +1,71 @@
+//! Routing of document blocks to plugins.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::api::{BlockHandler, BlockSource, RenderedBlock};
+use crate::{Error, Result};
+
+/// The block handlers plugins registered, by the kinds they claimed.
+#[derive(Default)]
+pub struct BlockRegistry {
+    /// The owning plugin and handler of each claimed kind.
+    handlers: HashMap<String, (String, Arc<dyn BlockHandler>)>,
+}
+
+impl BlockRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim `kinds` for a plugin's handler. Fails without claiming any of
+    /// them if another plugin already handles one.
+    pub fn claim(
+        &mut self,
+        plugin: &str,
+        kinds: &[&str],
+        handler: Arc<dyn BlockHandler>,
+    ) -> Result<()> {
+        for kind in kinds {
+            if let Some((owner, _)) = self.handlers.get(*kind) {
+                if owner != plugin {
+                    return Err(Error::KindClaimed {
+                        kind: kind.to_string(),
+                        owner: owner.clone(),
+                    });
+                }
+            }
+        }
+        for kind in kinds {
+            self.handlers
+                .insert(kind.to_string(), (plugin.to_string(), Arc::clone(&handler)));
+        }
+        Ok(())
+    }
+
+    /// Drop every claim a plugin made.
+    pub fn release(&mut self, plugin: &str) {
+        self.handlers.retain(|_, (owner, _)| owner != plugin);
+    }
+
+    /// The plugin handling a block kind.
+    pub fn owner(&self, kind: &str) -> Option<&str> {
+        self.handlers.get(kind).map(|(owner, _)| owner.as_str())
+    }
+
+    /// Render a block with the handler that claimed its kind. `None` if no
+    /// plugin claimed it or its plugin declined it.
+    pub fn render(&self, block: &BlockSource<'_>) -> Option<RenderedBlock> {
+        let (_, handler) = self.handlers.get(block.kind)?;
+        handler.handle_block(block)
+    }
+}
+
+impl std::fmt::Debug for BlockRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.handlers.iter().map(|(kind, (owner, _))| (kind, owner)))
+            .finish()
+    }
+}
@@ -7,11 +7,14 @@
 //! - Plugin loading and management
 //! - Plugin API traits
 
+pub mod abi;
 pub mod api;
+pub mod blocks;
 pub mod loader;
 pub mod manifest;
 
-pub use api::{Plugin, PluginApi};
+pub use api::{BlockHandler, BlockSource, Drawing, PlacedGlyph, Plugin, PluginApi, RenderedBlock};
+pub use blocks::BlockRegistry;
 pub use loader::PluginLoader;
 pub use manifest::PluginManifest;
 
@@ -35,6 +38,9 @@ pub enum Error {
 
     #[error("Plugin initialization failed: {0}")]
     InitFailed(String),
+
+    #[error("Block kind {kind} is already handled by plugin {owner}")]
+    KindClaimed { kind: String, owner: String },
 }
 
 /// Plugin API version.
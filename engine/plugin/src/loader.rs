@@ -2,13 +2,39 @@
 
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
-use crate::{Error, Plugin, PluginManifest, Result};
+use libloading::Library;
+
+use crate::abi::{self, CreatePlugin};
+use crate::api::{BlockHandler, CommandHandler, ContentHandler, LogLevel};
+use crate::{BlockRegistry, Error, Plugin, PluginApi, PluginManifest, Result};
+
+/// A running plugin and the library holding its code, if any.
+struct LoadedPlugin {
+    // Declared first so the plugin is dropped before its library is unloaded.
+    plugin: Box<dyn Plugin>,
+    _library: Option<Library>,
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        self.plugin.shutdown();
+    }
+}
 
 /// Plugin loader and manager.
 pub struct PluginLoader {
+    // The handlers are declared before the plugins so that they are dropped
+    // before the libraries holding their code are unloaded.
+    /// Registered commands and the plugins that registered them.
+    commands: HashMap<String, (String, Box<dyn CommandHandler>)>,
+    /// Registered content types and the plugins that registered them.
+    content_types: HashMap<String, (String, Box<dyn ContentHandler>)>,
+    /// Registered block handlers.
+    blocks: BlockRegistry,
     /// Loaded plugins.
-    plugins: HashMap<String, Box<dyn Plugin>>,
+    plugins: HashMap<String, LoadedPlugin>,
     /// Search paths for plugins.
     search_paths: Vec<std::path::PathBuf>,
 }
@@ -17,6 +43,9 @@ impl PluginLoader {
     /// Create a new plugin loader.
     pub fn new() -> Self {
         Self {
+            commands: HashMap::new(),
+            content_types: HashMap::new(),
+            blocks: BlockRegistry::new(),
             plugins: HashMap::new(),
             search_paths: Vec::new(),
         }
@@ -31,7 +60,7 @@ impl PluginLoader {
     pub fn load(&mut self, name: &str) -> Result<()> {
         // Search for the plugin manifest
         for search_path in &self.search_paths {
-            let manifest_path = search_path.join(name).join("plugin.json");
+            let manifest_path = search_path.join(name).join("plugin.toml");
             if manifest_path.exists() {
                 return self.load_from_manifest(&manifest_path);
             }
@@ -40,41 +69,137 @@ impl PluginLoader {
         Err(Error::NotFound(name.to_string()))
     }
 
-    /// Load a plugin from a manifest file.
+    /// Load a plugin from a manifest file. The entry point names a library
+    /// next to the manifest, without the platform's prefix and extension.
     pub fn load_from_manifest(&mut self, path: &Path) -> Result<()> {
         let manifest_str = std::fs::read_to_string(path).map_err(|e| Error::Load(e.to_string()))?;
 
-        let manifest: PluginManifest = serde_json::from_str(&manifest_str)
-            .map_err(|e| Error::InvalidManifest(e.to_string()))?;
+        let manifest = PluginManifest::from_toml(&manifest_str)?;
+
+        let library = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(libloading::library_filename(&manifest.entry));
+        self.load_library(&library).map(|_| ())
+    }
 
-        // Check API version
-        if manifest.api_version != crate::API_VERSION {
+    /// Load and initialize the plugin in a dynamic library, returning its
+    /// name. The library is only called into if it was built for this API
+    /// version.
+    pub fn load_library(&mut self, path: &Path) -> Result<String> {
+        let not_a_plugin =
+            |e: libloading::Error| Error::Load(format!("{} is not a plugin: {e}", path.display()));
+
+        // SAFETY: Loading a library runs its initializers, so plugins are
+        // trusted the same as the host's own code.
+        let library = unsafe { Library::new(path) }.map_err(|e| Error::Load(e.to_string()))?;
+        // SAFETY: The symbol is the `u32` static exported by `declare_plugin!`.
+        let version = unsafe {
+            let symbol = library
+                .get::<*const u32>(abi::VERSION_SYMBOL)
+                .map_err(not_a_plugin)?;
+            **symbol
+        };
+        if version != crate::API_VERSION {
             return Err(Error::VersionMismatch);
         }
 
-        // TODO: Load the actual plugin library
+        // SAFETY: The version matches, so the function has the signature
+        // `declare_plugin!` gives it and returns a box it gives up.
+        let plugin = unsafe {
+            let create = library
+                .get::<CreatePlugin>(abi::CREATE_SYMBOL)
+                .map_err(not_a_plugin)?;
+            let plugin = create();
+            if plugin.is_null() {
+                return Err(Error::Load(format!(
+                    "{} did not create a plugin",
+                    path.display()
+                )));
+            }
+            *Box::from_raw(plugin)
+        };
+
+        self.start(plugin, Some(library))
+    }
+
+    /// Initialize and add a plugin linked into the host, returning its name.
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) -> Result<String> {
+        self.start(plugin, None)
+    }
+
+    fn start(&mut self, mut plugin: Box<dyn Plugin>, library: Option<Library>) -> Result<String> {
+        let name = plugin.name().to_string();
+        let result = if self.plugins.contains_key(&name) {
+            Err(Error::Load(format!("plugin {name} is already loaded")))
+        } else {
+            plugin.init()
+        };
+        if let Err(e) = result {
+            // The plugin's code lives in the library, so it goes first.
+            drop(plugin);
+            drop(library);
+            return Err(e);
+        }
+
+        let mut loaded = LoadedPlugin {
+            plugin,
+            _library: library,
+        };
+        let mut registrar = Registrar {
+            plugin: &name,
+            commands: &mut self.commands,
+            content_types: &mut self.content_types,
+            blocks: &mut self.blocks,
+        };
+        if let Err(e) = loaded.plugin.register(&mut registrar) {
+            self.release(&name);
+            return Err(e);
+        }
+        self.plugins.insert(name.clone(), loaded);
+        Ok(name)
+    }
 
-        Ok(())
+    /// Drop the handlers a plugin registered.
+    fn release(&mut self, plugin: &str) {
+        self.commands.retain(|_, (owner, _)| owner != plugin);
+        self.content_types.retain(|_, (owner, _)| owner != plugin);
+        self.blocks.release(plugin);
+    }
+
+    /// The block handlers of the loaded plugins, for routing blocks during
+    /// layout.
+    pub fn blocks(&self) -> &BlockRegistry {
+        &self.blocks
+    }
+
+    /// Get a registered command.
+    pub fn command(&self, name: &str) -> Option<&dyn CommandHandler> {
+        self.commands.get(name).map(|(_, handler)| handler.as_ref())
+    }
+
+    /// Get the handler of a registered content type.
+    pub fn content_type(&self, type_id: &str) -> Option<&dyn ContentHandler> {
+        self.content_types
+            .get(type_id)
+            .map(|(_, handler)| handler.as_ref())
     }
 
     /// Get a loaded plugin.
     pub fn get(&self, name: &str) -> Option<&dyn Plugin> {
-        self.plugins.get(name).map(|p| p.as_ref())
+        self.plugins.get(name).map(|p| p.plugin.as_ref())
     }
 
     /// Get a mutable reference to a loaded plugin.
     pub fn get_mut(&mut self, name: &str) -> Option<&mut Box<dyn Plugin>> {
-        self.plugins.get_mut(name)
+        self.plugins.get_mut(name).map(|p| &mut p.plugin)
     }
 
-    /// Unload a plugin.
-    pub fn unload(&mut self, name: &str) -> Option<Box<dyn Plugin>> {
-        if let Some(mut plugin) = self.plugins.remove(name) {
-            plugin.shutdown();
-            Some(plugin)
-        } else {
-            None
-        }
+    /// Unload a plugin: drop its handlers, shut it down, drop it, then
+    /// unload its library. Returns whether it was loaded.
+    pub fn unload(&mut self, name: &str) -> bool {
+        self.release(name);
+        self.plugins.remove(name).is_some()
     }
 
     /// Get all loaded plugin names.
@@ -83,8 +208,196 @@ impl PluginLoader {
     }
 }
 
+/// The host API as seen by one plugin while it registers its handlers.
+struct Registrar<'a> {
+    plugin: &'a str,
+    commands: &'a mut HashMap<String, (String, Box<dyn CommandHandler>)>,
+    content_types: &'a mut HashMap<String, (String, Box<dyn ContentHandler>)>,
+    blocks: &'a mut BlockRegistry,
+}
+
+impl PluginApi for Registrar<'_> {
+    fn register_command(&mut self, name: &str, handler: Box<dyn CommandHandler>) {
+        self.commands
+            .insert(name.to_string(), (self.plugin.to_string(), handler));
+    }
+
+    fn register_content_type(&mut self, type_id: &str, handler: Box<dyn ContentHandler>) {
+        self.content_types
+            .insert(type_id.to_string(), (self.plugin.to_string(), handler));
+    }
+
+    fn register_block_handler(
+        &mut self,
+        kinds: &[&str],
+        handler: Arc<dyn BlockHandler>,
+    ) -> Result<()> {
+        self.blocks.claim(self.plugin, kinds, handler)
+    }
+
+    fn log(&self, level: LogLevel, message: &str) {
+        let plugin = self.plugin;
+        match level {
+            LogLevel::Debug => tracing::debug!(plugin, "{message}"),
+            LogLevel::Info => tracing::info!(plugin, "{message}"),
+            LogLevel::Warn => tracing::warn!(plugin, "{message}"),
+            LogLevel::Error => tracing::error!(plugin, "{message}"),
+        }
+    }
+}
+
 impl Default for PluginLoader {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockSource, RenderedBlock};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// An example plugin library, which `cargo test` builds alongside the
+    /// test binary.
+    fn example(name: &str) -> PathBuf {
+        let deps = std::env::current_exe().unwrap();
+        let dir = deps.parent().unwrap().parent().unwrap().join("examples");
+        let path = dir.join(libloading::library_filename(name));
+        assert!(path.exists(), "build the {name} example first");
+        path
+    }
+
+    #[test]
+    fn test_loads_initializes_and_unloads_library() {
+        let path = example("hello_plugin");
+        // A second handle keeps the library mapped so its events stay readable.
+        let observer = unsafe { Library::new(&path) }.unwrap();
+        let events: &AtomicU32 = unsafe {
+            &**observer
+                .get::<*const AtomicU32>(b"HELLO_PLUGIN_EVENTS\0")
+                .unwrap()
+        };
+        let events = || events.load(Ordering::SeqCst);
+
+        let mut loader = PluginLoader::new();
+        assert_eq!(loader.load_library(&path).unwrap(), "hello");
+        assert_eq!(
+            loader.get("hello").unwrap().version(),
+            env!("CARGO_PKG_VERSION")
+        );
+        assert_eq!(events(), 1);
+
+        assert!(matches!(loader.load_library(&path), Err(Error::Load(_))));
+        assert_eq!(events(), 13);
+
+        assert!(loader.unload("hello"));
+        assert!(!loader.unload("hello"));
+        assert_eq!(events(), 1323);
+        assert_eq!(loader.loaded_plugins().count(), 0);
+    }
+
+    /// Upper-cases code blocks, declining empty ones.
+    struct ShoutingCode;
+
+    impl BlockHandler for ShoutingCode {
+        fn handle_block(&self, block: &BlockSource<'_>) -> Option<RenderedBlock> {
+            (!block.source.is_empty())
+                .then(|| RenderedBlock::Text(Box::new(block.source.to_uppercase().into())))
+        }
+    }
+
+    struct MockPlugin {
+        name: &'static str,
+        kinds: &'static [&'static str],
+    }
+
+    impl Plugin for MockPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+
+        fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) {}
+
+        fn register(&mut self, api: &mut dyn PluginApi) -> Result<()> {
+            api.register_block_handler(self.kinds, Arc::new(ShoutingCode))
+        }
+    }
+
+    fn render(loader: &PluginLoader, kind: &str, source: &str) -> Option<String> {
+        let block = BlockSource {
+            kind,
+            language: Some("rust"),
+            source,
+        };
+        match loader.blocks().render(&block)? {
+            RenderedBlock::Text(text) => Some(text.content),
+            RenderedBlock::Drawing(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_routes_blocks_to_claiming_plugin() {
+        let mut loader = PluginLoader::new();
+        let code = MockPlugin {
+            name: "code",
+            kinds: &["code"],
+        };
+        loader.register(Box::new(code)).unwrap();
+
+        assert_eq!(loader.blocks().owner("code"), Some("code"));
+        assert_eq!(
+            render(&loader, "code", "fn main() {}"),
+            Some("FN MAIN() {}".to_string())
+        );
+        assert_eq!(render(&loader, "code", ""), None);
+        assert_eq!(render(&loader, "latex", "x^2"), None);
+
+        loader.unload("code");
+        assert_eq!(render(&loader, "code", "fn main() {}"), None);
+    }
+
+    #[test]
+    fn test_rejects_second_claim_on_kind() {
+        let mut loader = PluginLoader::new();
+        let first = MockPlugin {
+            name: "first",
+            kinds: &["code"],
+        };
+        loader.register(Box::new(first)).unwrap();
+
+        let second = MockPlugin {
+            name: "second",
+            kinds: &["mermaid", "code"],
+        };
+        let Err(Error::KindClaimed { kind, owner }) = loader.register(Box::new(second)) else {
+            panic!("expected a conflicting claim");
+        };
+        assert_eq!((kind.as_str(), owner.as_str()), ("code", "first"));
+        assert_eq!(loader.blocks().owner("mermaid"), None);
+        assert!(loader.get("second").is_none());
+    }
+
+    #[test]
+    fn test_rejects_other_api_versions_without_calling_in() {
+        let mut loader = PluginLoader::new();
+        // The outdated plugin aborts if it is asked to create a plugin.
+        assert!(matches!(
+            loader.load_library(&example("outdated_plugin")),
+            Err(Error::VersionMismatch)
+        ));
+        assert!(matches!(
+            loader.load_library(Path::new("missing-plugin.so")),
+            Err(Error::Load(_))
+        ));
+    }
+}
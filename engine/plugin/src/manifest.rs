@@ -2,12 +2,17 @@
 
 use serde::{Deserialize, Serialize};
 
-/// Plugin manifest (plugin.json).
+use crate::{Error, Result};
+
+/// Plugin manifest (plugin.toml).
+///
+/// Keys this version does not know are ignored, so manifests can gain new
+/// keys without breaking older hosts.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginManifest {
     /// Plugin name.
     pub name: String,
-    /// Plugin version.
+    /// Plugin version, in semver form.
     pub version: String,
     /// Plugin API version.
     pub api_version: u32,
@@ -19,14 +24,61 @@ pub struct PluginManifest {
     pub license: Option<String>,
     /// Entry point (library name).
     pub entry: String,
-    /// Plugin capabilities.
+    /// Plugin capabilities, such as the block kinds it handles.
     #[serde(default)]
     pub capabilities: Vec<String>,
+    /// Permissions the plugin asks the host for, such as `"network"`.
+    #[serde(default)]
+    pub permissions: Vec<String>,
     /// Plugin dependencies.
     #[serde(default)]
     pub dependencies: Vec<Dependency>,
 }
 
+impl PluginManifest {
+    /// Parse and validate a manifest.
+    pub fn from_toml(source: &str) -> Result<Self> {
+        let manifest: Self =
+            toml::from_str(source).map_err(|e| Error::InvalidManifest(e.message().to_string()))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Check that the fields hold usable values and that the plugin was
+    /// built for this host's API version.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(Error::InvalidManifest(message));
+        if self.name.trim().is_empty() {
+            return invalid("`name` is empty".to_string());
+        }
+        if self.entry.trim().is_empty() {
+            return invalid("`entry` is empty".to_string());
+        }
+        if let Err(e) = semver::Version::parse(&self.version) {
+            return invalid(format!(
+                "`version` {:?} is not a semver version: {e}",
+                self.version
+            ));
+        }
+        if self.api_version != crate::API_VERSION {
+            return invalid(format!(
+                "`api_version` {} is not supported; this host supports {}",
+                self.api_version,
+                crate::API_VERSION
+            ));
+        }
+        for dependency in &self.dependencies {
+            if let Err(e) = semver::VersionReq::parse(&dependency.version) {
+                return invalid(format!(
+                    "dependency {:?} has an invalid version requirement {:?}: {e}",
+                    dependency.name, dependency.version
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Plugin dependency.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dependency {
@@ -35,3 +87,65 @@ pub struct Dependency {
     /// Version requirement.
     pub version: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        name = "code-blocks"
+        version = "0.3.1-beta.2"
+        api_version = 1
+        entry = "plugin_code_blocks"
+        capabilities = ["code"]
+        permissions = ["clipboard"]
+        homepage = "https://example.com"
+
+        [[dependencies]]
+        name = "themes"
+        version = "^1.2"
+
+        [future]
+        key = true
+    "#;
+
+    fn error(source: &str) -> String {
+        match PluginManifest::from_toml(source) {
+            Err(Error::InvalidManifest(message)) => message,
+            other => panic!("expected an invalid manifest, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parses_valid_manifest() {
+        let manifest = PluginManifest::from_toml(MANIFEST).unwrap();
+        assert_eq!(manifest.name, "code-blocks");
+        assert_eq!(manifest.version, "0.3.1-beta.2");
+        assert_eq!(manifest.entry, "plugin_code_blocks");
+        assert_eq!(manifest.capabilities, ["code"]);
+        assert_eq!(manifest.permissions, ["clipboard"]);
+        assert_eq!(manifest.dependencies[0].version, "^1.2");
+        assert_eq!(manifest.description, None);
+    }
+
+    #[test]
+    fn test_reports_missing_fields() {
+        let source = MANIFEST.replace("entry = \"plugin_code_blocks\"", "");
+        assert_eq!(error(&source), "missing field `entry`");
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        let source = MANIFEST.replace("api_version = 1", "api_version = 2");
+        assert_eq!(
+            error(&source),
+            "`api_version` 2 is not supported; this host supports 1"
+        );
+
+        let source = MANIFEST.replace("0.3.1-beta.2", "1.0");
+        assert!(error(&source).starts_with("`version` \"1.0\" is not a semver version"));
+
+        let source = MANIFEST.replace("^1.2", "one");
+        assert!(error(&source).starts_with("dependency \"themes\""));
+    }
+}
@@ -0,0 +1,549 @@
+//! Glyph atlas.
+//!
+//! Rasterized glyphs are packed into square RGBA pages on shelves: rows as
+//! tall as the first glyph placed on them, filled left to right. Every glyph
+//! is followed by a pixel of transparent padding to its right and below it,
+//! so sampling at a glyph's edge never picks up its neighbours. Coverage
+//! masks are stored as white with the coverage in alpha, so tinting by the
+//! text color in the shader colors them, while color glyphs such as emoji
+//! keep their own pixels.
+//!
+//! Glyphs are keyed by their cosmic-text [`CacheKey`]: font, glyph id,
+//! subpixel bins and size. When no page has room a new one is started, up
+//! to a memory budget; past it the least recently used glyphs are evicted
+//! to make room instead, and are rasterized again the next time they are
+//! needed. Glyphs used in the current frame are never evicted, so if a
+//! single frame needs more than the budget the atlas grows past it.
+//! Changed pages are marked dirty for the text renderer to upload.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use cosmic_text::{CacheKey, SwashContent, SwashImage};
+use wolia_math::Rect;
+
+/// Default width and height of an atlas page, in pixels.
+pub const DEFAULT_PAGE_SIZE: u32 = 1024;
+
+/// Default memory budget for the atlas pages, in bytes.
+pub const DEFAULT_BUDGET: usize = 64 * 1024 * 1024;
+
+/// Transparent pixels left after each glyph, horizontally and vertically.
+pub const PADDING: u32 = 1;
+
+/// A glyph's place in the atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasGlyph {
+    /// Index of the page holding the glyph.
+    pub page: usize,
+    /// Left edge of the glyph on the page.
+    pub x: u32,
+    /// Top edge of the glyph on the page.
+    pub y: u32,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Offset of the left edge from the pen position.
+    pub left: i32,
+    /// Offset of the top edge above the baseline.
+    pub top: i32,
+    /// Whether the glyph has its own colors rather than a coverage mask.
+    pub color: bool,
+}
+
+impl AtlasGlyph {
+    /// The glyph's part of its page, in texture coordinates.
+    pub fn uv(&self, page_size: u32) -> Rect {
+        let page_size = page_size as f32;
+        Rect::new(
+            self.x as f32 / page_size,
+            self.y as f32 / page_size,
+            self.width as f32 / page_size,
+            self.height as f32 / page_size,
+        )
+    }
+}
+
+/// A row of glyphs on a page.
+#[derive(Debug, Clone)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// End of the space used so far; everything after it is free.
+    x: u32,
+    /// Free gaps before `x` left by evicted glyphs, in order.
+    free: Vec<Range<u32>>,
+    /// Number of glyphs on the shelf.
+    live: usize,
+}
+
+impl Shelf {
+    /// Take `width` pixels from a gap or the free end of the shelf.
+    fn take(&mut self, width: u32, page_size: u32) -> Option<u32> {
+        if let Some(index) = self.free.iter().position(|gap| gap.len() as u32 >= width) {
+            let x = self.free[index].start;
+            self.free[index].start += width;
+            if self.free[index].is_empty() {
+                self.free.remove(index);
+            }
+            self.live += 1;
+            return Some(x);
+        }
+        if self.x + width > page_size {
+            return None;
+        }
+        let x = self.x;
+        self.x += width;
+        self.live += 1;
+        Some(x)
+    }
+
+    /// Whether `width` pixels are free on the shelf.
+    fn fits(&self, width: u32, page_size: u32) -> bool {
+        self.x + width <= page_size || self.free.iter().any(|gap| gap.len() as u32 >= width)
+    }
+
+    /// Return `width` pixels at `x`, merging them with neighbouring gaps.
+    fn give_back(&mut self, x: u32, width: u32) {
+        self.live -= 1;
+        if self.live == 0 {
+            self.x = 0;
+            self.free.clear();
+            return;
+        }
+        self.free.push(x..x + width);
+        self.free.sort_by_key(|gap| gap.start);
+        let mut merged: Vec<Range<u32>> = Vec::with_capacity(self.free.len());
+        for gap in self.free.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.end == gap.start => last.end = gap.end,
+                _ => merged.push(gap),
+            }
+        }
+        if merged.last().is_some_and(|gap| gap.end == self.x) {
+            self.x = merged.pop().map_or(self.x, |gap| gap.start);
+        }
+        self.free = merged;
+    }
+}
+
+/// One page of the atlas.
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    /// RGBA pixels, row by row.
+    pub pixels: Vec<u8>,
+    /// Width and height in pixels.
+    pub size: u32,
+    /// Whether the pixels changed since the page was last uploaded.
+    pub dirty: bool,
+    shelves: Vec<Shelf>,
+}
+
+impl AtlasPage {
+    fn new(size: u32) -> Self {
+        Self {
+            pixels: vec![0; (size * size * 4) as usize],
+            size,
+            dirty: true,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// Size of the page's pixels in bytes.
+    pub fn bytes(&self) -> usize {
+        self.pixels.len()
+    }
+
+    /// Reserve a `width` by `height` rectangle, returning its top-left
+    /// corner.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.size || height > self.size {
+            return None;
+        }
+        // The shelf tall enough with room left that wastes the least height.
+        let size = self.size;
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.fits(width, size))
+            .min_by_key(|shelf| shelf.height - height);
+        if let Some(shelf) = best {
+            let x = shelf.take(width, size)?;
+            return Some((x, shelf.y));
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if y + height > self.size {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            x: width,
+            free: Vec::new(),
+            live: 1,
+        });
+        Some((0, y))
+    }
+
+    /// Release the `width` by `height` rectangle at `(x, y)`, clearing its
+    /// pixels.
+    fn release(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        if let Some(shelf) = self.shelves.iter_mut().find(|shelf| shelf.y == y) {
+            shelf.give_back(x, width);
+        }
+        while self.shelves.last().is_some_and(|shelf| shelf.live == 0) {
+            self.shelves.pop();
+        }
+        for row in y..y + height {
+            let start = ((row * self.size + x) * 4) as usize;
+            self.pixels[start..start + (width * 4) as usize].fill(0);
+        }
+        self.dirty = true;
+    }
+
+    /// Copy a rasterized glyph into the rectangle at `(x, y)`.
+    fn write(&mut self, x: u32, y: u32, image: &SwashImage) {
+        let width = image.placement.width as usize;
+        let channels = match image.content {
+            SwashContent::Mask => 1,
+            SwashContent::SubpixelMask | SwashContent::Color => 4,
+        };
+        for (row, source) in image.data.chunks(width * channels).enumerate() {
+            let start = (((y as usize + row) * self.size as usize) + x as usize) * 4;
+            let target = &mut self.pixels[start..start + width * 4];
+            for (pixel, texel) in source.chunks(channels).zip(target.chunks_mut(4)) {
+                match image.content {
+                    SwashContent::Mask => texel.copy_from_slice(&[255, 255, 255, pixel[0]]),
+                    SwashContent::SubpixelMask => {
+                        let coverage = pixel[0].max(pixel[1]).max(pixel[2]);
+                        texel.copy_from_slice(&[255, 255, 255, coverage]);
+                    }
+                    SwashContent::Color => texel.copy_from_slice(pixel),
+                }
+            }
+        }
+        self.dirty = true;
+    }
+}
+
+/// A glyph in the atlas and when it was last used.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    glyph: Option<AtlasGlyph>,
+    /// Position of the last use among all uses, for eviction order.
+    last_used: u64,
+    /// Frame of the last use.
+    frame: u64,
+}
+
+/// Rasterized glyphs packed into texture pages.
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    page_size: u32,
+    budget: usize,
+    pages: Vec<AtlasPage>,
+    glyphs: HashMap<CacheKey, Entry>,
+    frame: u64,
+    uses: u64,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAGE_SIZE)
+    }
+}
+
+impl GlyphAtlas {
+    /// Create an empty atlas with pages `page_size` pixels square.
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            page_size,
+            budget: DEFAULT_BUDGET,
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            frame: 0,
+            uses: 0,
+        }
+    }
+
+    /// Set the memory budget for the pages, in bytes.
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Width and height of each page.
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// The memory budget for the pages, in bytes.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Memory used by the pages, in bytes.
+    pub fn memory(&self) -> usize {
+        self.pages.iter().map(AtlasPage::bytes).sum()
+    }
+
+    /// The pages, in the order they were created.
+    pub fn pages(&self) -> &[AtlasPage] {
+        &self.pages
+    }
+
+    /// Mutable access to the pages, to clear their dirty flags on upload.
+    pub fn pages_mut(&mut self) -> &mut [AtlasPage] {
+        &mut self.pages
+    }
+
+    /// Number of glyphs held, including ones with nothing to draw.
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    /// Whether the atlas holds no glyphs.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Start a new frame. Glyphs used before it may be evicted to make room
+    /// for the frame's glyphs.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// A glyph already in the atlas, marking it used in this frame. The
+    /// inner value is `None` for glyphs with nothing to draw, such as
+    /// spaces.
+    pub fn get(&mut self, key: &CacheKey) -> Option<Option<AtlasGlyph>> {
+        self.uses += 1;
+        let (uses, frame) = (self.uses, self.frame);
+        self.glyphs.get_mut(key).map(|entry| {
+            entry.last_used = uses;
+            entry.frame = frame;
+            entry.glyph
+        })
+    }
+
+    /// Add a rasterized glyph, or record that there is nothing to draw for
+    /// it when `image` is `None` or empty. Returns `None` as well when the
+    /// glyph is larger than a page.
+    pub fn insert(&mut self, key: CacheKey, image: Option<&SwashImage>) -> Option<AtlasGlyph> {
+        let glyph = image
+            .filter(|image| image.placement.width > 0 && image.placement.height > 0)
+            .and_then(|image| self.place(image));
+        self.uses += 1;
+        self.glyphs.insert(
+            key,
+            Entry {
+                glyph,
+                last_used: self.uses,
+                frame: self.frame,
+            },
+        );
+        glyph
+    }
+
+    fn place(&mut self, image: &SwashImage) -> Option<AtlasGlyph> {
+        let (width, height) = (image.placement.width, image.placement.height);
+        let (page, x, y) = self.allocate(width + PADDING, height + PADDING)?;
+        self.pages[page].write(x, y, image);
+        Some(AtlasGlyph {
+            page,
+            x,
+            y,
+            width,
+            height,
+            left: image.placement.left,
+            top: image.placement.top,
+            color: image.content == SwashContent::Color,
+        })
+    }
+
+    /// Find room for a rectangle: on an existing page, on a new page while
+    /// within budget, or in space freed by evicting old glyphs.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        if width > self.page_size || height > self.page_size {
+            return None;
+        }
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some((x, y)) = page.allocate(width, height) {
+                return Some((index, x, y));
+            }
+        }
+
+        let page_bytes = (self.page_size * self.page_size * 4) as usize;
+        if self.memory() + page_bytes <= self.budget || self.pages.is_empty() {
+            return self.grow(width, height);
+        }
+        while let Some(page) = self.evict_oldest() {
+            if let Some((x, y)) = self.pages[page].allocate(width, height) {
+                return Some((page, x, y));
+            }
+        }
+        self.grow(width, height)
+    }
+
+    fn grow(&mut self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        let mut page = AtlasPage::new(self.page_size);
+        let (x, y) = page.allocate(width, height)?;
+        self.pages.push(page);
+        Some((self.pages.len() - 1, x, y))
+    }
+
+    /// Evict the least recently used glyph not used in this frame,
+    /// returning the page it was on.
+    fn evict_oldest(&mut self) -> Option<usize> {
+        let (key, glyph) = self
+            .glyphs
+            .iter()
+            .filter(|(_, entry)| entry.frame < self.frame)
+            .filter_map(|(key, entry)| Some((entry.last_used, *key, entry.glyph?)))
+            .min_by_key(|(last_used, _, _)| *last_used)
+            .map(|(_, key, glyph)| (key, glyph))?;
+        self.glyphs.remove(&key);
+        self.pages[glyph.page].release(
+            glyph.x,
+            glyph.y,
+            glyph.width + PADDING,
+            glyph.height + PADDING,
+        );
+        Some(glyph.page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmic_text::{CacheKeyFlags, Placement, SubpixelBin, fontdb};
+
+    use super::*;
+
+    fn key(glyph_id: u16) -> CacheKey {
+        CacheKey {
+            font_id: fontdb::ID::dummy(),
+            glyph_id,
+            font_size_bits: 16f32.to_bits(),
+            x_bin: SubpixelBin::Zero,
+            y_bin: SubpixelBin::Zero,
+            flags: CacheKeyFlags::empty(),
+        }
+    }
+
+    fn mask(width: u32, height: u32) -> SwashImage {
+        SwashImage {
+            source: Default::default(),
+            content: SwashContent::Mask,
+            placement: Placement {
+                left: 1,
+                top: height as i32,
+                width,
+                height,
+            },
+            data: vec![200; (width * height) as usize],
+        }
+    }
+
+    /// Whether two glyphs, with their padding, share any pixel.
+    fn overlap(a: &AtlasGlyph, b: &AtlasGlyph) -> bool {
+        a.page == b.page
+            && a.x < b.x + b.width + PADDING
+            && b.x < a.x + a.width + PADDING
+            && a.y < b.y + b.height + PADDING
+            && b.y < a.y + a.height + PADDING
+    }
+
+    #[test]
+    fn test_mask_is_white_with_coverage() {
+        let mut atlas = GlyphAtlas::new(16);
+        let glyph = atlas.insert(key(1), Some(&mask(2, 2))).unwrap();
+        assert_eq!((glyph.page, glyph.x, glyph.y), (0, 0, 0));
+        assert_eq!(&atlas.pages()[0].pixels[..4], &[255, 255, 255, 200]);
+        // The padding column after the glyph stays transparent.
+        assert_eq!(&atlas.pages()[0].pixels[8..12], &[0, 0, 0, 0]);
+        assert_eq!(atlas.get(&key(1)), Some(Some(glyph)));
+        assert_eq!(glyph.uv(16), Rect::new(0.0, 0.0, 0.125, 0.125));
+        assert_eq!(atlas.insert(key(2), None), None);
+        assert_eq!(atlas.get(&key(2)), Some(None));
+    }
+
+    #[test]
+    fn test_packs_many_glyphs_apart() {
+        let mut atlas = GlyphAtlas::new(128);
+        let glyphs: Vec<AtlasGlyph> = (0..200)
+            .map(|id| {
+                let (width, height) = (3 + id as u32 % 7, 5 + id as u32 % 11);
+                atlas.insert(key(id), Some(&mask(width, height))).unwrap()
+            })
+            .collect();
+        assert_eq!(atlas.len(), 200);
+        for (index, glyph) in glyphs.iter().enumerate() {
+            assert!(glyph.x + glyph.width + PADDING <= 128);
+            assert!(glyph.y + glyph.height + PADDING <= 128);
+            assert!(
+                glyphs[index + 1..]
+                    .iter()
+                    .all(|other| !overlap(glyph, other))
+            );
+        }
+    }
+
+    #[test]
+    fn test_full_page_starts_another() {
+        let mut atlas = GlyphAtlas::new(18);
+        let glyphs: Vec<AtlasGlyph> = (0..5)
+            .map(|id| atlas.insert(key(id), Some(&mask(8, 8))).unwrap())
+            .collect();
+        // Four padded 8x8 glyphs fill an 18x18 page on two shelves.
+        assert_eq!((glyphs[1].x, glyphs[1].y), (9, 0));
+        assert_eq!((glyphs[2].x, glyphs[2].y), (0, 9));
+        assert_eq!(glyphs[3].page, 0);
+        assert_eq!((glyphs[4].page, glyphs[4].x, glyphs[4].y), (1, 0, 0));
+        assert_eq!(atlas.pages().len(), 2);
+        assert_eq!(atlas.memory(), 2 * 18 * 18 * 4);
+        // A glyph larger than a page is never placed.
+        assert_eq!(atlas.insert(key(9), Some(&mask(18, 4))), None);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let page_bytes = 18 * 18 * 4;
+        let mut atlas = GlyphAtlas::new(18).with_budget(page_bytes);
+        for id in 0..4 {
+            atlas.insert(key(id), Some(&mask(8, 8)));
+        }
+
+        // Glyph 0 is used again in the next frame, so glyph 1 is the
+        // oldest and makes way for glyph 4 on the same page.
+        atlas.begin_frame();
+        assert!(atlas.get(&key(0)).is_some());
+        let glyph = atlas.insert(key(4), Some(&mask(8, 8))).unwrap();
+        assert_eq!((glyph.page, glyph.x, glyph.y), (0, 9, 0));
+        assert_eq!(atlas.get(&key(1)), None);
+        assert!(atlas.get(&key(0)).is_some());
+        assert_eq!(atlas.memory(), page_bytes);
+
+        // An evicted glyph is simply placed again when it is needed.
+        atlas.begin_frame();
+        assert!(atlas.insert(key(1), Some(&mask(8, 8))).is_some());
+        assert_eq!(atlas.pages().len(), 1);
+        assert!(atlas.pages()[0].dirty);
+    }
+
+    #[test]
+    fn test_glyphs_of_the_current_frame_are_kept() {
+        let mut atlas = GlyphAtlas::new(18).with_budget(18 * 18 * 4);
+        // Five glyphs in one frame cannot fit the budget; the atlas grows
+        // rather than evict glyphs that frame still draws.
+        for id in 0..5 {
+            atlas.insert(key(id), Some(&mask(8, 8)));
+        }
+        assert_eq!(atlas.pages().len(), 2);
+        assert!((0..5).all(|id| atlas.get(&key(id)).is_some()));
+    }
+}
@@ -0,0 +1,92 @@
+//! Draw batching.
+//!
+//! Items are drawn in order, so later items cover earlier ones. To draw
+//! many items with few state changes, items sharing a texture are gathered
+//! into one batch. An item may join an earlier batch only if it overlaps
+//! nothing in the batches after it, so drawing the batches in order gives
+//! the same image as drawing the items one by one.
+
+use wolia_math::Rect;
+
+/// Counts of the GPU work issued for a draw.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    /// Render passes begun.
+    pub passes: usize,
+    /// Times a pipeline was bound.
+    pub pipeline_binds: usize,
+    /// Times a bind group was bound.
+    pub bind_group_binds: usize,
+    /// Draw calls issued.
+    pub draw_calls: usize,
+}
+
+/// Items drawn together with one texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch<K> {
+    /// What the items share, such as a texture name.
+    pub key: K,
+    /// Indices of the items, in drawing order.
+    pub items: Vec<usize>,
+}
+
+/// Group items, given as their key and bounds in drawing order, into
+/// batches that preserve the order of overlapping items.
+pub fn batch<K: PartialEq>(items: impl IntoIterator<Item = (K, Rect)>) -> Vec<Batch<K>> {
+    let mut batches: Vec<(Batch<K>, Vec<Rect>)> = Vec::new();
+    for (index, (key, bounds)) in items.into_iter().enumerate() {
+        let mut target = None;
+        for (position, (batch, rects)) in batches.iter().enumerate().rev() {
+            if batch.key == key {
+                target = Some(position);
+                break;
+            }
+            if rects.iter().any(|rect| rect.intersects(&bounds)) {
+                break;
+            }
+        }
+        match target {
+            Some(position) => {
+                let (batch, rects) = &mut batches[position];
+                batch.items.push(index);
+                rects.push(bounds);
+            }
+            None => batches.push((
+                Batch {
+                    key,
+                    items: vec![index],
+                },
+                vec![bounds],
+            )),
+        }
+    }
+    batches.into_iter().map(|(batch, _)| batch).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_key_items_share_a_batch() {
+        let items = (0..50).map(|i| (i % 5, Rect::new(i as f32 * 30.0, 0.0, 24.0, 24.0)));
+        let batches = batch(items);
+        assert_eq!(batches.len(), 5);
+        assert_eq!(batches[0].items, vec![0, 5, 10, 15, 20, 25, 30, 35, 40, 45]);
+    }
+
+    #[test]
+    fn test_overlap_keeps_order() {
+        // "b" covers the first "a", so the second "a", which overlaps "b",
+        // cannot be drawn with the first.
+        let batches = batch([
+            ("a", Rect::new(0.0, 0.0, 10.0, 10.0)),
+            ("b", Rect::new(5.0, 5.0, 10.0, 10.0)),
+            ("a", Rect::new(8.0, 8.0, 10.0, 10.0)),
+            ("a", Rect::new(50.0, 0.0, 10.0, 10.0)),
+        ]);
+        let keys: Vec<&str> = batches.iter().map(|batch| batch.key).collect();
+        assert_eq!(keys, vec!["a", "b", "a"]);
+        assert_eq!(batches[2].items, vec![2, 3]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Clipping to rectangles.
+//!
+//! Clip rectangles are in the same space quads are given in, with the
+//! origin at the top left: pixels of the render target, or logical units
+//! on a target with a scale factor. They are applied by the GPU as scissor
+//! rectangles, so only whole pixels are clipped: a pixel is kept when its
+//! center lies inside the clip.
+
+use wolia_math::Rect;
+
+/// A scissor rectangle in whole target pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scissor {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Scissor {
+    /// The scissor for `clip` on a target `width` by `height` pixels, or
+    /// `None` if no pixel of the target lies inside the clip.
+    pub fn from_clip(clip: Rect, width: f32, height: f32) -> Option<Self> {
+        let left = clip.x.max(0.0).round();
+        let top = clip.y.max(0.0).round();
+        let right = clip.right().min(width).round();
+        let bottom = clip.bottom().min(height).round();
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(Self {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+
+    /// The scissor for `clip` in logical units on a target `width` by
+    /// `height` logical units, with `scale` pixels per unit.
+    pub fn from_scaled_clip(clip: Rect, width: f32, height: f32, scale: f32) -> Option<Self> {
+        let clip = Rect::new(
+            clip.x * scale,
+            clip.y * scale,
+            clip.width * scale,
+            clip.height * scale,
+        );
+        Self::from_clip(clip, width * scale, height * scale)
+    }
+
+    /// Set the scissor on a render pass.
+    pub fn apply(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_scissor_rect(self.x, self.y, self.width, self.height);
+    }
+}
+
+/// Nested clip rectangles, each limited to the ones it is inside.
+#[derive(Debug, Clone, Default)]
+pub struct ClipStack {
+    clips: Vec<Rect>,
+}
+
+impl ClipStack {
+    /// Create an empty stack, which clips nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clip to `rect` within the current clip, returning the new clip. A
+    /// clip that misses the current one is empty.
+    pub fn push(&mut self, rect: Rect) -> Rect {
+        let clip = match self.clips.last() {
+            Some(current) => current
+                .intersection(&rect)
+                .unwrap_or(Rect::new(rect.x, rect.y, 0.0, 0.0)),
+            None => rect,
+        };
+        self.clips.push(clip);
+        clip
+    }
+
+    /// Return to the clip before the last [`push`](Self::push).
+    pub fn pop(&mut self) -> Option<Rect> {
+        self.clips.pop()
+    }
+
+    /// The current clip, or `None` when nothing is clipped.
+    pub fn current(&self) -> Option<Rect> {
+        self.clips.last().copied()
+    }
+
+    /// Number of clips pushed.
+    pub fn depth(&self) -> usize {
+        self.clips.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_clips_intersect() {
+        let mut stack = ClipStack::new();
+        assert_eq!(stack.current(), None);
+        stack.push(Rect::new(0.0, 0.0, 100.0, 100.0));
+        assert_eq!(
+            stack.push(Rect::new(50.0, 20.0, 100.0, 30.0)),
+            Rect::new(50.0, 20.0, 50.0, 30.0)
+        );
+        let empty = stack.push(Rect::new(200.0, 0.0, 10.0, 10.0));
+        assert_eq!((empty.width, empty.height), (0.0, 0.0));
+        assert_eq!(Scissor::from_clip(empty, 400.0, 400.0), None);
+
+        stack.pop();
+        stack.pop();
+        assert_eq!(stack.current(), Some(Rect::new(0.0, 0.0, 100.0, 100.0)));
+        assert_eq!(stack.depth(), 1);
+    }
+
+    #[test]
+    fn test_scissor_stays_on_target() {
+        let scissor = Scissor::from_clip(Rect::new(-10.0, 5.4, 50.0, 200.0), 32.0, 64.0);
+        assert_eq!(
+            scissor,
+            Some(Scissor {
+                x: 0,
+                y: 5,
+                width: 32,
+                height: 59
+            })
+        );
+        assert_eq!(
+            Scissor::from_clip(Rect::new(40.0, 0.0, 10.0, 10.0), 32.0, 32.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_scaled_scissor_is_in_pixels() {
+        let clip = Rect::new(10.0, 20.5, 30.0, 100.0);
+        assert_eq!(
+            Scissor::from_scaled_clip(clip, 64.0, 64.0, 2.0),
+            Some(Scissor {
+                x: 20,
+                y: 41,
+                width: 60,
+                height: 87
+            })
+        );
+        assert_eq!(
+            Scissor::from_scaled_clip(clip, 64.0, 64.0, 1.0),
+            Scissor::from_clip(clip, 64.0, 64.0)
+        );
+    }
+}
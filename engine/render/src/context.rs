@@ -1,5 +1,6 @@
 //! Render context and GPU resources.
 
+use crate::msaa;
 use crate::{Error, Result};
 
 /// GPU render context.
@@ -17,6 +18,22 @@ pub struct RenderContext {
 impl RenderContext {
     /// Create a new render context.
     pub async fn new() -> Result<Self> {
+        Self::with_adapter(false).await
+    }
+
+    /// Create a render context for drawing into offscreen targets only,
+    /// such as in tests or when making thumbnails without a window.
+    ///
+    /// Uses a GPU when there is one, and the software fallback adapter
+    /// otherwise.
+    pub async fn headless() -> Result<Self> {
+        match Self::with_adapter(false).await {
+            Ok(context) => Ok(context),
+            Err(_) => Self::with_adapter(true).await,
+        }
+    }
+
+    async fn with_adapter(force_fallback_adapter: bool) -> Result<Self> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
@@ -26,17 +43,23 @@ impl RenderContext {
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::HighPerformance,
                 compatible_surface: None,
-                force_fallback_adapter: false,
+                force_fallback_adapter,
             })
             .await
             .ok_or_else(|| Error::Gpu("No suitable GPU adapter found".to_string()))?;
 
+        // Software adapters may not reach the default limits.
+        let required_limits = if force_fallback_adapter {
+            adapter.limits()
+        } else {
+            wgpu::Limits::default()
+        };
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Wolia Device"),
                     required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
+                    required_limits,
                     memory_hints: Default::default(),
                 },
                 None,
@@ -61,4 +84,10 @@ impl RenderContext {
     pub fn queue(&self) -> &wgpu::Queue {
         &self.queue
     }
+
+    /// The sample count to draw targets of `format` with: `requested` if
+    /// supported, otherwise 1.
+    pub fn sample_count(&self, format: wgpu::TextureFormat, requested: u32) -> u32 {
+        msaa::supported_sample_count(&self.adapter, &self.device, format, requested)
+    }
 }
@@ -0,0 +1,224 @@
+//! Damage tracking.
+//!
+//! Instead of redrawing every frame, the application records the rectangles
+//! whose content changed, such as a button whose hover state flipped or a
+//! blinking cursor, and redraws only when some damage exists. Overlapping
+//! rectangles are merged into their bounding box so each region is drawn
+//! once, and a resize damages the whole target. The regions are in target
+//! pixels and can be passed as clips to limit drawing to them.
+//!
+//! Drawing only the damage needs a target that keeps the rest of the last
+//! frame, such as an [`OffscreenTarget`](crate::OffscreenTarget) copied to
+//! the surface once drawn, since surface textures start each frame with
+//! undefined contents. A [`Redraw`] taken from the tracker clips each draw
+//! to the damage. Clearing ignores clips, so frames drawn this way paint
+//! their background instead of clearing.
+
+use wolia_math::{Rect, Size};
+
+/// Accumulates the regions of a render target that need redrawing.
+#[derive(Debug, Clone)]
+pub struct DamageTracker {
+    bounds: Rect,
+    regions: Vec<Rect>,
+    full: bool,
+}
+
+impl DamageTracker {
+    /// Create a tracker for a target of `size`. Everything starts damaged,
+    /// so the first frame is drawn in full.
+    pub fn new(size: Size) -> Self {
+        Self {
+            bounds: Rect::from_size(size),
+            regions: Vec::new(),
+            full: true,
+        }
+    }
+
+    /// Mark a rectangle as needing a redraw.
+    pub fn add(&mut self, rect: Rect) {
+        if self.full || rect.width <= 0.0 || rect.height <= 0.0 {
+            return;
+        }
+        // Absorb every region the growing rectangle overlaps, since a merge
+        // can make it reach regions it did not overlap before.
+        let mut merged = rect;
+        while let Some(index) = self
+            .regions
+            .iter()
+            .position(|region| region.intersects(&merged))
+        {
+            merged = merged.union(&self.regions.swap_remove(index));
+        }
+        self.regions.push(merged);
+    }
+
+    /// Mark the whole target as needing a redraw.
+    pub fn invalidate_all(&mut self) {
+        self.full = true;
+        self.regions.clear();
+    }
+
+    /// Change the size of the target, which damages all of it.
+    pub fn resize(&mut self, size: Size) {
+        self.bounds = Rect::from_size(size);
+        self.invalidate_all();
+    }
+
+    /// Whether anything needs redrawing.
+    pub fn is_dirty(&self) -> bool {
+        self.full || !self.regions.is_empty()
+    }
+
+    /// Whether the whole target needs redrawing.
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// The damaged regions, limited to the target.
+    pub fn regions(&self) -> Vec<Rect> {
+        if self.full {
+            return vec![self.bounds];
+        }
+        self.regions
+            .iter()
+            .filter_map(|region| region.intersection(&self.bounds))
+            .collect()
+    }
+
+    /// The damaged regions, leaving nothing damaged.
+    pub fn take(&mut self) -> Vec<Rect> {
+        let regions = self.regions();
+        self.full = false;
+        self.regions.clear();
+        regions
+    }
+
+    /// What the next frame must draw: the bounding box of the damage, or
+    /// the whole target. Leaves nothing damaged.
+    pub fn take_redraw(&mut self) -> Redraw {
+        let full = self.full;
+        let area = self
+            .take()
+            .into_iter()
+            .reduce(|area, region| area.union(&region))
+            .unwrap_or_default();
+        Redraw {
+            area: (!full).then_some(area),
+        }
+    }
+}
+
+/// The part of a target a frame redraws.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Redraw {
+    /// The area to draw, or `None` for the whole target.
+    area: Option<Rect>,
+}
+
+impl Redraw {
+    /// Redraw the whole target, as when the last frame was not kept.
+    pub fn full() -> Self {
+        Self { area: None }
+    }
+
+    /// Whether the whole target is redrawn.
+    pub fn is_full(&self) -> bool {
+        self.area.is_none()
+    }
+
+    /// The clip for a draw clipped to `clip`, if anything, that also keeps
+    /// it inside the area redrawn. A clip outside that area is empty, so
+    /// the draw leaves every pixel as it was.
+    pub fn clip(&self, clip: Option<Rect>) -> Option<Rect> {
+        match (self.area, clip) {
+            (None, clip) => clip,
+            (Some(area), None) => Some(area),
+            (Some(area), Some(clip)) => Some(
+                area.intersection(&clip)
+                    .unwrap_or(Rect::new(clip.x, clip.y, 0.0, 0.0)),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> DamageTracker {
+        let mut damage = DamageTracker::new(Size::new(800.0, 600.0));
+        damage.take();
+        damage
+    }
+
+    #[test]
+    fn test_starts_fully_damaged() {
+        let mut damage = DamageTracker::new(Size::new(800.0, 600.0));
+        assert!(damage.is_full());
+        assert_eq!(damage.take(), vec![Rect::new(0.0, 0.0, 800.0, 600.0)]);
+        // An idle frame has nothing to draw.
+        assert!(!damage.is_dirty());
+        assert!(damage.take().is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_regions_merge() {
+        let mut damage = tracker();
+        damage.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        damage.add(Rect::new(100.0, 0.0, 10.0, 10.0));
+        assert_eq!(damage.regions().len(), 2);
+
+        // A rectangle bridging both merges them all into one.
+        damage.add(Rect::new(5.0, 5.0, 100.0, 2.0));
+        assert_eq!(damage.regions(), vec![Rect::new(0.0, 0.0, 110.0, 10.0)]);
+
+        // Empty rectangles are ignored and regions are kept on the target.
+        damage.add(Rect::new(300.0, 300.0, 0.0, 10.0));
+        damage.add(Rect::new(790.0, 590.0, 50.0, 50.0));
+        assert_eq!(damage.regions()[1], Rect::new(790.0, 590.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn test_redraw_clips_to_damage() {
+        let mut damage = DamageTracker::new(Size::new(800.0, 600.0));
+        let redraw = damage.take_redraw();
+        assert!(redraw.is_full());
+        let panel = Rect::new(0.0, 100.0, 200.0, 500.0);
+        assert_eq!(redraw.clip(Some(panel)), Some(panel));
+        assert_eq!(redraw.clip(None), None);
+
+        damage.add(Rect::new(10.0, 10.0, 10.0, 10.0));
+        damage.add(Rect::new(100.0, 150.0, 20.0, 20.0));
+        let redraw = damage.take_redraw();
+        assert!(!redraw.is_full());
+        assert_eq!(redraw.clip(None), Some(Rect::new(10.0, 10.0, 110.0, 160.0)));
+        assert_eq!(
+            redraw.clip(Some(panel)),
+            Some(Rect::new(10.0, 100.0, 110.0, 70.0))
+        );
+        // Damage outside a clip leaves nothing to draw there.
+        let corner = Rect::new(700.0, 0.0, 100.0, 100.0);
+        assert_eq!(
+            redraw
+                .clip(Some(corner))
+                .map(|clip| clip.width * clip.height),
+            Some(0.0)
+        );
+
+        // Nothing damaged draws nothing.
+        assert_eq!(
+            damage.take_redraw().clip(None).map(|clip| clip.width),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn test_resize_damages_everything() {
+        let mut damage = tracker();
+        damage.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        damage.resize(Size::new(1024.0, 768.0));
+        damage.add(Rect::new(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(damage.take(), vec![Rect::new(0.0, 0.0, 1024.0, 768.0)]);
+    }
+}
@@ -0,0 +1,82 @@
+//! Document export to images.
+//!
+//! A document is laid out on the layout engine's default pages and the
+//! requested page drawn by a headless renderer on white paper, then
+//! encoded. No window or display is needed, but a GPU adapter is.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use wolia_core::Document;
+use wolia_layout::LayoutEngine;
+
+use crate::{Error, Renderer, Result};
+
+/// Draw page `page_index` of a document as a PNG image at `scale` pixels
+/// per point.
+pub fn export_png(document: &Document, page_index: usize, scale: f32) -> Result<Vec<u8>> {
+    let layout = LayoutEngine::new()
+        .layout(document)
+        .map_err(|e| Error::Export(e.to_string()))?;
+    // Checked before looking for an adapter, which takes a while.
+    if page_index >= layout.pages.len() {
+        return Err(Error::PageOutOfRange {
+            index: page_index,
+            count: layout.pages.len(),
+        });
+    }
+
+    let mut renderer = pollster::block_on(Renderer::headless())?;
+    let image = renderer.render_page_to_image(&layout, page_index, scale)?;
+    let pixels = RgbaImage::from_raw(image.width, image.height, image.pixels)
+        .ok_or_else(|| Error::Export("rendered image has the wrong size".to_string()))?;
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(pixels)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| Error::Export(e.to_string()))?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::node::Node;
+    use wolia_core::text::Text;
+
+    use super::*;
+
+    fn document() -> Document {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("A single paragraph.")));
+        document
+    }
+
+    #[test]
+    fn test_export_first_page() {
+        let png = match export_png(&document(), 0, 0.5) {
+            Ok(png) => png,
+            Err(Error::Gpu(_)) => {
+                eprintln!("skipping: no GPU adapter");
+                return;
+            }
+            Err(e) => panic!("export failed: {e}"),
+        };
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        // A4 is 595 by 842 points.
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (298, 421));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(297, 420).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_page_out_of_range() {
+        let error = export_png(&document(), 1, 1.0).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::PageOutOfRange { index: 1, count: 1 }
+        ));
+    }
+}
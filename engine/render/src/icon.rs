@@ -2,6 +2,13 @@
 
 use std::collections::HashMap;
 
+use wgpu::util::DeviceExt;
+use wolia_math::Rect;
+
+use crate::batch::{self, DrawStats};
+use crate::clip::Scissor;
+use crate::msaa::{self, ColorTarget};
+
 /// A rasterized icon ready for GPU rendering.
 pub struct RasterizedIcon {
     /// RGBA pixel data.
@@ -62,6 +69,44 @@ impl TexturedVertex {
     }
 }
 
+/// An icon to draw.
+#[derive(Debug, Clone, Copy)]
+pub struct IconInstance<'a> {
+    /// Name the icon was loaded under.
+    pub name: &'a str,
+    /// Left edge, in pixels.
+    pub x: f32,
+    /// Top edge, in pixels.
+    pub y: f32,
+    /// Width, in pixels.
+    pub size: f32,
+    /// Height, in pixels.
+    pub height: f32,
+    /// Tint multiplied with the icon's pixels.
+    pub tint: [f32; 4],
+}
+
+impl<'a> IconInstance<'a> {
+    /// Create an icon instance, `size` pixels square.
+    pub fn new(name: &'a str, x: f32, y: f32, size: f32, tint: [f32; 4]) -> Self {
+        Self {
+            name,
+            x,
+            y,
+            size,
+            height: size,
+            tint,
+        }
+    }
+
+    /// Stretch the icon to `height` pixels tall, as for images that are not
+    /// square.
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+}
+
 /// A cached icon texture on the GPU.
 pub struct IconTexture {
     /// The GPU texture.
@@ -88,11 +133,23 @@ pub struct IconRenderer {
     sampler: wgpu::Sampler,
     /// Cached icon textures by name.
     icon_cache: HashMap<String, IconTexture>,
+    /// Physical pixels per logical pixel, for clipping.
+    scale_factor: f32,
 }
 
 impl IconRenderer {
     /// Create a new icon renderer.
     pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self::new_multisampled(device, format, 1)
+    }
+
+    /// Create an icon renderer drawing into targets with `sample_count`
+    /// samples per pixel.
+    pub fn new_multisampled(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Icon Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("icon.wgsl").into()),
@@ -155,7 +212,7 @@ impl IconRenderer {
                 conservative: false,
             },
             depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: msaa::multisample_state(sample_count),
             multiview: None,
             cache: None,
         });
@@ -185,9 +242,22 @@ impl IconRenderer {
             vertex_buffer,
             sampler,
             icon_cache: HashMap::new(),
+            scale_factor: 1.0,
         }
     }
 
+    /// Set the physical pixels per logical pixel that clip rectangles are
+    /// scaled by.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Drop every cached icon, so they can be loaded again at another size,
+    /// as when the display scale factor changes.
+    pub fn clear_icons(&mut self) {
+        self.icon_cache.clear();
+    }
+
     /// Load an SVG icon and cache it as a GPU texture.
     pub fn load_icon(
         &mut self,
@@ -204,7 +274,47 @@ impl IconRenderer {
         let Some(rasterized) = RasterizedIcon::from_svg(svg_data, target_size) else {
             return false;
         };
+        self.upload(device, queue, name, &rasterized);
+        true
+    }
+
+    /// Cache RGBA pixels, `width` by `height`, as a GPU texture drawn like
+    /// an icon under `name`, replacing any texture with that name. Returns
+    /// false if the pixels do not fill the size, or the image is larger
+    /// than a texture can be.
+    pub fn load_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> bool {
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|len| len.checked_mul(4));
+        let max = device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max || height > max || len != Some(pixels.len()) {
+            return false;
+        }
+        let image = RasterizedIcon {
+            pixels: pixels.to_vec(),
+            width,
+            height,
+        };
+        self.upload(device, queue, name, &image);
+        true
+    }
 
+    /// Upload pixels to a texture cached under `name`.
+    fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        rasterized: &RasterizedIcon,
+    ) {
         let bytes_per_row = rasterized.width * 4;
         let padded_bytes_per_row = (bytes_per_row + 255) & !255;
 
@@ -289,8 +399,6 @@ impl IconRenderer {
                 height: rasterized.height,
             },
         );
-
-        true
     }
 
     /// Check if an icon is loaded.
@@ -298,12 +406,15 @@ impl IconRenderer {
         self.icon_cache.contains_key(name)
     }
 
-    /// Render an icon at the specified position.
+    /// Render an icon at the specified position, in a render pass of its
+    /// own. The vertices are written to a shared buffer, so submit the
+    /// encoder before rendering another icon this way; to draw several
+    /// icons use [`render_icons`](Self::render_icons).
     #[allow(clippy::too_many_arguments)]
-    pub fn render_icon(
+    pub fn render_icon<'a>(
         &self,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
+        target: impl Into<ColorTarget<'a>>,
         queue: &wgpu::Queue,
         icon_name: &str,
         x: f32,
@@ -324,14 +435,7 @@ impl IconRenderer {
 
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Icon Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
+            color_attachments: &[Some(target.into().attachment(wgpu::LoadOp::Load))],
             depth_stencil_attachment: None,
             timestamp_writes: None,
             occlusion_query_set: None,
@@ -343,31 +447,114 @@ impl IconRenderer {
         render_pass.draw(0..6, 0..1);
     }
 
-    /// Render multiple icons in a single batch.
+    /// Render icons in order in a single pass, drawing icons that share a
+    /// texture together where that keeps overlapping icons in order. Icons
+    /// that are not loaded are skipped.
+    pub fn render_icons<'a>(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: impl Into<ColorTarget<'a>>,
+        icons: &[IconInstance<'_>],
+        screen_width: f32,
+        screen_height: f32,
+    ) -> DrawStats {
+        self.render_icons_clipped(
+            device,
+            encoder,
+            target,
+            icons,
+            screen_width,
+            screen_height,
+            None,
+        )
+    }
+
+    /// Render icons as [`render_icons`](Self::render_icons) does, drawing
+    /// only inside `clip`, in logical pixels, if given.
     #[allow(clippy::too_many_arguments)]
-    pub fn render_icons(
+    pub fn render_icons_clipped<'a>(
         &self,
+        device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
-        queue: &wgpu::Queue,
-        icons: &[(&str, f32, f32, f32, [f32; 4])], // (name, x, y, size, tint)
+        target: impl Into<ColorTarget<'a>>,
+        icons: &[IconInstance<'_>],
         screen_width: f32,
         screen_height: f32,
-    ) {
-        for (name, x, y, size, tint) in icons {
-            self.render_icon(
-                encoder,
-                view,
-                queue,
-                name,
-                *x,
-                *y,
-                *size,
-                screen_width,
-                screen_height,
-                *tint,
-            );
+        clip: Option<Rect>,
+    ) -> DrawStats {
+        let loaded: Vec<(&IconInstance<'_>, &IconTexture)> = icons
+            .iter()
+            .filter_map(|icon| Some((icon, self.icon_cache.get(icon.name)?)))
+            .collect();
+        if loaded.is_empty() {
+            return DrawStats::default();
+        }
+
+        let batches = batch::batch(
+            loaded
+                .iter()
+                .map(|(icon, _)| (icon.name, Rect::new(icon.x, icon.y, icon.size, icon.height))),
+        );
+        let vertices: Vec<TexturedVertex> = batches
+            .iter()
+            .flat_map(|batch| &batch.items)
+            .flat_map(|&index| {
+                let icon = loaded[index].0;
+                self.create_quad_vertices(
+                    icon.x,
+                    icon.y,
+                    icon.size,
+                    icon.height,
+                    screen_width,
+                    screen_height,
+                    icon.tint,
+                )
+            })
+            .collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Icon Batch Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Icon Batch Render Pass"),
+            color_attachments: &[Some(target.into().attachment(wgpu::LoadOp::Load))],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        if let Some(clip) = clip {
+            match Scissor::from_scaled_clip(clip, screen_width, screen_height, self.scale_factor) {
+                Some(scissor) => scissor.apply(&mut render_pass),
+                None => {
+                    return DrawStats {
+                        passes: 1,
+                        ..DrawStats::default()
+                    };
+                }
+            }
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        let mut stats = DrawStats {
+            passes: 1,
+            pipeline_binds: 1,
+            ..DrawStats::default()
+        };
+        let mut start = 0;
+        for batch in &batches {
+            let end = start + batch.items.len() as u32 * 6;
+            let texture = loaded[batch.items[0]].1;
+            render_pass.set_bind_group(0, &texture.bind_group, &[]);
+            render_pass.draw(start..end, 0..1);
+            stats.bind_group_binds += 1;
+            stats.draw_calls += 1;
+            start = end;
         }
+        stats
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -421,3 +608,131 @@ impl IconRenderer {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FORMAT, Target};
+
+    const COLORS: [&str; 5] = ["#d00", "#0a0", "#00c", "#c80", "#808"];
+
+    fn svg(color: &str) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24">
+                <circle cx="12" cy="12" r="10" fill="{color}"/>
+            </svg>"#
+        )
+    }
+
+    /// Fifty icons of five kinds in a grid where neighbours overlap.
+    fn toolbar(names: &[String]) -> Vec<IconInstance<'_>> {
+        (0..50)
+            .map(|i| {
+                let (x, y) = ((i % 10) as f32 * 20.0, (i / 10) as f32 * 20.0);
+                IconInstance::new(&names[i % names.len()], x, y, 24.0, [1.0, 1.0, 1.0, 0.8])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batched_icons_match_one_by_one() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let (device, queue) = (&context.device, &context.queue);
+        let mut renderer = IconRenderer::new(device, FORMAT);
+        let names: Vec<String> = (0..COLORS.len()).map(|i| format!("icon{i}")).collect();
+        for (name, color) in names.iter().zip(COLORS) {
+            assert!(renderer.load_icon(device, queue, name, &svg(color), 24));
+        }
+        let icons = toolbar(&names);
+
+        let batched = Target::new(device, 256, 128);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        batched.clear(&mut encoder);
+        let stats =
+            renderer.render_icons(device, &mut encoder, &batched.view, &icons, 256.0, 128.0);
+        queue.submit([encoder.finish()]);
+
+        // One pass and one pipeline for all fifty icons.
+        assert_eq!(stats.passes, 1);
+        assert_eq!(stats.pipeline_binds, 1);
+        assert!(stats.draw_calls < icons.len());
+
+        let single = Target::new(device, 256, 128);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        single.clear(&mut encoder);
+        queue.submit([encoder.finish()]);
+        for icon in &icons {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            renderer.render_icon(
+                &mut encoder,
+                &single.view,
+                queue,
+                icon.name,
+                icon.x,
+                icon.y,
+                icon.size,
+                256.0,
+                128.0,
+                icon.tint,
+            );
+            queue.submit([encoder.finish()]);
+        }
+
+        assert!(batched.read(&context) == single.read(&context));
+    }
+
+    #[test]
+    fn test_unloaded_icons_are_skipped() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let device = &context.device;
+        let renderer = IconRenderer::new(device, FORMAT);
+        let target = Target::new(device, 64, 64);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let icons = [IconInstance::new("missing", 0.0, 0.0, 24.0, [1.0; 4])];
+        let stats = renderer.render_icons(device, &mut encoder, &target.view, &icons, 64.0, 64.0);
+        assert_eq!(stats, DrawStats::default());
+    }
+
+    #[test]
+    fn test_images_stretch_and_clip() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let (device, queue) = (&context.device, &context.queue);
+        let mut renderer = IconRenderer::new(device, FORMAT);
+        let black = [0, 0, 0, 255].repeat(4);
+        assert!(!renderer.load_image(device, queue, "image", 2, 3, &black));
+        // 65536 × 65536 × 4 wraps to 0 in 32 bits.
+        assert!(!renderer.load_image(device, queue, "huge", 65536, 65536, &[]));
+        assert!(!renderer.has_icon("huge"));
+        assert!(renderer.load_image(device, queue, "image", 2, 2, &black));
+
+        // A 2x2 image stretched to 48x16, of which the right half shows.
+        let target = Target::new(device, 64, 32);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        target.clear(&mut encoder);
+        let icons = [IconInstance::new("image", 8.0, 8.0, 48.0, [1.0; 4]).with_height(16.0)];
+        let clip = Rect::new(32.0, 0.0, 32.0, 32.0);
+        renderer.render_icons_clipped(
+            device,
+            &mut encoder,
+            &target.view,
+            &icons,
+            64.0,
+            32.0,
+            Some(clip),
+        );
+        queue.submit([encoder.finish()]);
+
+        let pixels = target.read(&context);
+        let red = |x: usize, y: usize| pixels[(y * 64 + x) * 4];
+        assert_eq!(red(40, 16), 0);
+        assert_eq!(red(20, 16), 255);
+        assert_eq!(red(40, 4), 255);
+        assert_eq!(red(40, 26), 255);
+    }
+}
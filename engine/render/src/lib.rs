@@ -6,29 +6,51 @@
 //! - GPU-accelerated rendering via wgpu
 //! - Text rendering with cosmic-text
 //! - Image rendering
+//! - SVG output for print and embedding
 //! - Shape and path rendering
 //! - Compositing and effects
 
 #![allow(dead_code, unused_imports, unused_variables)]
 
+pub mod atlas;
+pub mod batch;
+pub mod clip;
 pub mod context;
+pub mod damage;
+pub mod export;
 pub mod icon;
+pub mod msaa;
+pub mod offscreen;
 pub mod pipeline;
 pub mod quad;
+pub mod svg;
 pub mod text;
 pub mod texture;
 pub mod ui;
 
-pub use icon::{IconRenderer, IconTexture, RasterizedIcon, TexturedVertex};
-pub use quad::{Quad, QuadRenderer, Vertex};
+#[cfg(test)]
+mod testing;
+
+pub use batch::DrawStats;
+pub use clip::{ClipStack, Scissor};
+pub use damage::{DamageTracker, Redraw};
+pub use export::export_png;
+pub use icon::{IconInstance, IconRenderer, IconTexture, RasterizedIcon, TexturedVertex};
+pub use msaa::{ColorTarget, DEFAULT_SAMPLE_COUNT, MsaaTarget};
+pub use offscreen::OffscreenTarget;
+pub use quad::{Quad, QuadInstance, QuadRenderer, Vertex};
+pub use svg::{SvgOptions, SvgRenderer, render_svg};
 pub use ui::{RenderRect, colors, dimensions};
 
-use wolia_layout::LayoutTree;
-use wolia_math::{Color, Rect, Size};
+use wolia_assets::{DecodedImage, SupportedFormat};
+use wolia_core::style::RevisionKind;
+use wolia_layout::{LayoutContent, LayoutNode, LayoutTree};
+use wolia_math::{Color, Point, Rect, Size};
 
+pub use atlas::GlyphAtlas;
 pub use context::RenderContext;
 pub use pipeline::RenderPipeline;
-pub use text::TextRenderer;
+pub use text::{GlyphQuad, TextRenderer, TextRun};
 pub use texture::TextureAtlas;
 
 /// Result type for render operations.
@@ -48,29 +70,62 @@ pub enum Error {
 
     #[error("Font error: {0}")]
     Font(String),
+
+    #[error("Page {index} is out of range for a document of {count} pages")]
+    PageOutOfRange { index: usize, count: usize },
+
+    #[error("Export error: {0}")]
+    Export(String),
 }
 
 /// The main renderer.
 pub struct Renderer {
     /// Render context.
     context: RenderContext,
+    /// Format of the targets drawn into.
+    format: wgpu::TextureFormat,
     /// Text renderer.
     text_renderer: TextRenderer,
+    /// Renderer for rules and other filled rectangles.
+    quad_renderer: QuadRenderer,
     /// Clear color.
     clear_color: Color,
 }
 
 impl Renderer {
-    /// Create a new renderer.
-    pub async fn new() -> Result<Self> {
-        let context = RenderContext::new().await?;
-        let text_renderer = TextRenderer::new(&context)?;
+    /// Format of the targets a headless renderer draws into.
+    pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Create a new renderer drawing into targets of `format`.
+    pub async fn new(format: wgpu::TextureFormat) -> Result<Self> {
+        Ok(Self::with_context(RenderContext::new().await?, format))
+    }
+
+    /// Create a renderer without a window, for drawing images with
+    /// [`render_to_image`](Self::render_to_image).
+    pub async fn headless() -> Result<Self> {
+        Ok(Self::with_context(
+            RenderContext::headless().await?,
+            Self::HEADLESS_FORMAT,
+        ))
+    }
 
-        Ok(Self {
+    /// Create a renderer on `context` drawing into targets of `format`.
+    pub fn with_context(context: RenderContext, format: wgpu::TextureFormat) -> Self {
+        let text_renderer = TextRenderer::new(&context.device, format);
+        let quad_renderer = QuadRenderer::new(&context.device, format);
+        Self {
             context,
+            format,
             text_renderer,
+            quad_renderer,
             clear_color: Color::WHITE,
-        })
+        }
+    }
+
+    /// Get the render context.
+    pub fn context(&self) -> &RenderContext {
+        &self.context
     }
 
     /// Set the clear color.
@@ -78,14 +133,271 @@ impl Renderer {
         self.clear_color = color;
     }
 
-    /// Render a layout tree.
-    pub fn render(&mut self, _layout: &LayoutTree, _viewport: Rect) -> Result<()> {
-        // TODO: Implement full rendering
+    /// Render the `viewport` region of a layout tree into `target`. Pages
+    /// are stacked top to bottom in document coordinates.
+    pub fn render(
+        &mut self,
+        layout: &LayoutTree,
+        viewport: Rect,
+        target: &wgpu::TextureView,
+    ) -> Result<()> {
+        let mut runs = Vec::new();
+        let mut rules = Vec::new();
+        let mut page_y = 0.0;
+        for page in &layout.pages {
+            for node in page.all_nodes() {
+                collect_runs(node, Point::new(0.0, page_y), &mut runs);
+                collect_rules(node, Point::new(0.0, page_y), &mut rules);
+            }
+            page_y += page.size.height;
+        }
+        let quads: Vec<Quad> = rules
+            .iter()
+            .filter(|(rule, _)| rule.intersects(&viewport))
+            .map(|(rule, color)| {
+                Quad::new(
+                    rule.x - viewport.x,
+                    rule.y - viewport.y,
+                    rule.width,
+                    rule.height,
+                    [color.r, color.g, color.b, color.a],
+                )
+            })
+            .collect();
+
+        let device = &self.context.device;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+        let clear = self.clear_color;
+        self.quad_renderer.render(
+            &mut encoder,
+            target,
+            &self.context.queue,
+            &quads,
+            viewport.width,
+            viewport.height,
+            Some(wgpu::Color {
+                r: clear.r as f64,
+                g: clear.g as f64,
+                b: clear.b as f64,
+                a: clear.a as f64,
+            }),
+            None,
+        );
+        self.text_renderer.render(
+            device,
+            &self.context.queue,
+            &mut encoder,
+            target,
+            &runs,
+            viewport,
+        );
+        self.context.queue.submit([encoder.finish()]);
         Ok(())
     }
 
+    /// Render the top `size` region of a layout tree into an image, one
+    /// pixel per unit.
+    ///
+    /// The renderer's format must be 8-bit RGBA or BGRA, as that of a
+    /// [headless](Self::headless) renderer is.
+    pub fn render_to_image(&mut self, layout: &LayoutTree, size: Size) -> Result<DecodedImage> {
+        let (width, height) = (size.width.ceil() as u32, size.height.ceil() as u32);
+        let target = OffscreenTarget::new(&self.context.device, width, height, self.format);
+        let viewport = Rect::new(0.0, 0.0, target.width as f32, target.height as f32);
+        self.render(layout, viewport, &target.view)?;
+        Ok(DecodedImage {
+            width: target.width,
+            height: target.height,
+            // Rendered images are lossless, as PNG is.
+            format: SupportedFormat::Png,
+            pixels: target.read(&self.context)?,
+        })
+    }
+
+    /// Render page `index` of a layout tree into an image at `scale`
+    /// pixels per unit, on white paper whatever the clear color.
+    pub fn render_page_to_image(
+        &mut self,
+        layout: &LayoutTree,
+        index: usize,
+        scale: f32,
+    ) -> Result<DecodedImage> {
+        let page = layout.pages.get(index).ok_or(Error::PageOutOfRange {
+            index,
+            count: layout.pages.len(),
+        })?;
+        let (width, height) = (
+            (page.size.width * scale).ceil() as u32,
+            (page.size.height * scale).ceil() as u32,
+        );
+        let max = self.context.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max || height > max {
+            return Err(Error::Texture(format!(
+                "cannot draw a page at {} by {} pixels",
+                width, height
+            )));
+        }
+
+        // The viewport is in document units, so a target of more pixels
+        // draws it larger.
+        let top: f32 = layout.pages[..index]
+            .iter()
+            .map(|page| page.size.height)
+            .sum();
+        let viewport = Rect::new(0.0, top, page.size.width, page.size.height);
+        let target = OffscreenTarget::new(&self.context.device, width, height, self.format);
+        let clear = std::mem::replace(&mut self.clear_color, Color::WHITE);
+        let rendered = self.render(layout, viewport, &target.view);
+        self.clear_color = clear;
+        rendered?;
+        Ok(DecodedImage {
+            width: target.width,
+            height: target.height,
+            format: SupportedFormat::Png,
+            pixels: target.read(&self.context)?,
+        })
+    }
+
     /// Resize the render surface.
     pub fn resize(&mut self, _size: Size) {
         // TODO: Handle resize
     }
 }
+
+/// Gather the rules of drawings, the link underlines and the lines marking
+/// suggested changes in a node whose bounds are relative to `offset`, with
+/// the color of each.
+fn collect_rules(node: &LayoutNode, offset: Point, rules: &mut Vec<(Rect, Color)>) {
+    let origin = offset + Point::new(node.bounds.x, node.bounds.y);
+    let mut underline = |paragraph: &wolia_layout::ParagraphLayout, origin: Point| {
+        let links = paragraph
+            .link_underlines()
+            .into_iter()
+            .map(|rect| (rect, colors::LINK));
+        let revisions = paragraph
+            .revision_marks()
+            .into_iter()
+            .map(|(rect, kind)| match kind {
+                RevisionKind::Insertion => (rect, colors::INSERTION),
+                RevisionKind::Deletion => (rect, colors::DELETION),
+            });
+        rules.extend(links.chain(revisions).map(|(rect, color)| {
+            let rect = Rect::new(
+                rect.x + origin.x,
+                rect.y + origin.y,
+                rect.width,
+                rect.height,
+            );
+            (rect, color)
+        }));
+    };
+    match &node.content {
+        LayoutContent::Drawing(drawing) => {
+            let baseline = origin + Point::new(0.0, drawing.ascent);
+            rules.extend(drawing.rules.iter().map(|&[x, y, width, height]| {
+                let rule = Rect::new(baseline.x + x, baseline.y + y, width, height);
+                (rule, Color::BLACK)
+            }));
+        }
+        LayoutContent::Paragraph(paragraph) => underline(paragraph, origin),
+        // Cell lines are relative to the cell's content rectangle, which
+        // is relative to the table.
+        LayoutContent::Table { cells } => {
+            for cell in cells {
+                if let LayoutContent::Paragraph(paragraph) = &cell.content {
+                    let content = Point::new(paragraph.bounds.x, paragraph.bounds.y);
+                    underline(paragraph, origin + content);
+                }
+            }
+        }
+        LayoutContent::Container { children } => {
+            for child in children {
+                collect_rules(child, origin, rules);
+            }
+        }
+        LayoutContent::Image { .. } => {}
+    }
+}
+
+/// Gather the text runs of a node whose bounds are relative to `offset`.
+fn collect_runs<'a>(node: &'a LayoutNode, offset: Point, runs: &mut Vec<TextRun<'a>>) {
+    let origin = offset + Point::new(node.bounds.x, node.bounds.y);
+    match &node.content {
+        LayoutContent::Paragraph(paragraph) => {
+            runs.push(
+                TextRun::new(&paragraph.text, &paragraph.lines, origin)
+                    .with_links(&paragraph.links)
+                    .with_revisions(&paragraph.revisions),
+            );
+        }
+        // Cell lines are relative to the cell's content rectangle, which
+        // is relative to the table.
+        LayoutContent::Table { cells } => {
+            for cell in cells {
+                if let LayoutContent::Paragraph(paragraph) = &cell.content {
+                    let content = Point::new(paragraph.bounds.x, paragraph.bounds.y);
+                    runs.push(
+                        TextRun::new(&paragraph.text, &paragraph.lines, origin + content)
+                            .with_links(&paragraph.links)
+                            .with_revisions(&paragraph.revisions),
+                    );
+                }
+            }
+        }
+        LayoutContent::Container { children } => {
+            for child in children {
+                collect_runs(child, origin, runs);
+            }
+        }
+        LayoutContent::Image { .. } | LayoutContent::Drawing(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wolia_layout::Page;
+    use wolia_plugin::Drawing;
+
+    #[test]
+    fn test_render_to_image_headless() {
+        let Ok(mut renderer) = pollster::block_on(Renderer::headless()) else {
+            eprintln!("skipping: no GPU adapter");
+            return;
+        };
+        // A 20 by 10 rule whose top left is at (10, 30) on the page.
+        let drawing = Drawing {
+            rules: vec![[0.0, -5.0, 20.0, 10.0]],
+            width: 20.0,
+            ascent: 5.0,
+            descent: 5.0,
+            ..Drawing::default()
+        };
+        let size = Size::new(65.0, 50.0);
+        let mut page = Page::new(1, size, Rect::from_size(size));
+        page.nodes.push(LayoutNode {
+            source_id: uuid::Uuid::nil(),
+            bounds: Rect::new(10.0, 30.0, 20.0, 10.0),
+            content: LayoutContent::Drawing(drawing),
+        });
+        let layout = LayoutTree {
+            pages: vec![page],
+            total_height: size.height,
+        };
+
+        // The odd width leaves padding at the end of every row read back.
+        let image = renderer.render_to_image(&layout, size).unwrap();
+        assert_eq!((image.width, image.height), (65, 50));
+        assert_eq!(image.pixels.len(), 65 * 50 * 4);
+        let pixel = |x: u32, y: u32| {
+            let i = ((y * image.width + x) * 4) as usize;
+            &image.pixels[i..i + 4]
+        };
+        assert_eq!(pixel(20, 35), [0, 0, 0, 255]);
+        assert_eq!(pixel(5, 35), [255, 255, 255, 255]);
+        assert_eq!(pixel(20, 45), [255, 255, 255, 255]);
+        assert_eq!(pixel(64, 49), [255, 255, 255, 255]);
+    }
+}
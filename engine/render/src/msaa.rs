@@ -0,0 +1,244 @@
+//! Multisample anti-aliasing.
+//!
+//! With MSAA, passes draw into a multisampled texture and its samples are
+//! averaged into the surface texture when each pass ends, which smooths
+//! edges that do not fall on pixel boundaries. Pipelines used in those
+//! passes must be created with the same sample count as the texture. Not
+//! every adapter supports every count for every format, so the count is
+//! checked first and falls back to 1, which draws straight into the
+//! surface.
+
+/// Sample count used for anti-aliasing when the adapter supports it.
+pub const DEFAULT_SAMPLE_COUNT: u32 = 4;
+
+/// The sample count to use for targets of `format`: `requested` if the
+/// adapter and device support it, otherwise 1.
+pub fn supported_sample_count(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    requested: u32,
+) -> u32 {
+    let adapter_flags = adapter.get_texture_format_features(format).flags;
+    // Without this feature the device only allows the counts WebGPU
+    // guarantees, whatever the adapter could do.
+    let device_flags = if device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        adapter_flags
+    } else {
+        format.guaranteed_format_features(device.features()).flags
+    };
+    if adapter_flags.sample_count_supported(requested)
+        && device_flags.sample_count_supported(requested)
+    {
+        requested
+    } else {
+        1
+    }
+}
+
+/// The multisample state for pipelines drawing with `sample_count`.
+pub(crate) fn multisample_state(sample_count: u32) -> wgpu::MultisampleState {
+    wgpu::MultisampleState {
+        count: sample_count,
+        ..wgpu::MultisampleState::default()
+    }
+}
+
+/// Where a render pass draws: a texture and, when the texture is
+/// multisampled, the texture its samples are resolved into.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorTarget<'a> {
+    /// The texture drawn into.
+    pub view: &'a wgpu::TextureView,
+    /// The texture receiving the resolved samples.
+    pub resolve_target: Option<&'a wgpu::TextureView>,
+}
+
+impl<'a> ColorTarget<'a> {
+    /// The color attachment for a pass that starts with `load` and keeps
+    /// what it draws for later passes.
+    pub fn attachment(
+        self,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        wgpu::RenderPassColorAttachment {
+            view: self.view,
+            resolve_target: self.resolve_target,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a wgpu::TextureView> for ColorTarget<'a> {
+    fn from(view: &'a wgpu::TextureView) -> Self {
+        Self {
+            view,
+            resolve_target: None,
+        }
+    }
+}
+
+/// A multisampled texture the size of the surface, resolved into the
+/// surface texture.
+#[derive(Debug)]
+pub struct MsaaTarget {
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    width: u32,
+    height: u32,
+    /// The multisampled texture, absent when drawing with one sample.
+    texture: Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl MsaaTarget {
+    /// Create a target for a `width` by `height` surface of `format`. The
+    /// sample count should come from [`supported_sample_count`].
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let mut target = Self {
+            format,
+            sample_count,
+            width,
+            height,
+            texture: None,
+        };
+        target.texture = target.create_texture(device);
+        target
+    }
+
+    /// Number of samples per pixel.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Size of the target in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The multisampled texture, or `None` at one sample per pixel.
+    pub fn texture(&self) -> Option<&wgpu::Texture> {
+        self.texture.as_ref().map(|(texture, _)| texture)
+    }
+
+    /// Recreate the multisampled texture for a surface of a new size.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        self.texture = self.create_texture(device);
+    }
+
+    /// Where passes should draw to end up in `surface`.
+    pub fn target<'a>(&'a self, surface: &'a wgpu::TextureView) -> ColorTarget<'a> {
+        match &self.texture {
+            Some((_, view)) => ColorTarget {
+                view,
+                resolve_target: Some(surface),
+            },
+            None => ColorTarget::from(surface),
+        }
+    }
+
+    fn create_texture(&self, device: &wgpu::Device) -> Option<(wgpu::Texture, wgpu::TextureView)> {
+        if self.sample_count <= 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Target"),
+            size: wgpu::Extent3d {
+                width: self.width.max(1),
+                height: self.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: self.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Some((texture, view))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon::IconRenderer;
+    use crate::quad::{Quad, QuadRenderer};
+    use crate::testing::{self, FORMAT, Target};
+    use crate::text::TextRenderer;
+
+    #[test]
+    fn test_pipelines_at_each_sample_count() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let device = &context.device;
+        // 3 is never a valid count, and 64 is more than any adapter has.
+        for requested in [1, 4, 3, 64] {
+            let samples = context.sample_count(FORMAT, requested);
+            match requested {
+                1 | 4 => assert!(samples == requested || samples == 1),
+                _ => assert_eq!(samples, 1),
+            }
+            QuadRenderer::new_multisampled(device, FORMAT, samples);
+            IconRenderer::new_multisampled(device, FORMAT, samples);
+            TextRenderer::new_multisampled(device, FORMAT, samples);
+        }
+    }
+
+    #[test]
+    fn test_resolves_into_surface() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let (device, queue) = (&context.device, &context.queue);
+        let samples = context.sample_count(FORMAT, DEFAULT_SAMPLE_COUNT);
+        let renderer = QuadRenderer::new_multisampled(device, FORMAT, samples);
+        let surface = Target::new(device, 16, 16);
+        let mut msaa = MsaaTarget::new(device, FORMAT, 8, 8, samples);
+        msaa.resize(device, 16, 16);
+        if let Some(texture) = msaa.texture() {
+            assert_eq!((texture.width(), texture.height()), (16, 16));
+        }
+
+        // A quad covering the left half of a pixel column blends it.
+        let quads = [Quad::new(0.0, 0.0, 4.5, 16.0, [0.0, 0.0, 0.0, 1.0])];
+        let mut encoder = device.create_command_encoder(&Default::default());
+        renderer.render(
+            &mut encoder,
+            msaa.target(&surface.view),
+            queue,
+            &quads,
+            16.0,
+            16.0,
+            Some(wgpu::Color::WHITE),
+            None,
+        );
+        queue.submit([encoder.finish()]);
+
+        let pixels = surface.read(&context);
+        assert_eq!(pixels[0], 0);
+        assert_eq!(pixels[8 * 4], 255);
+        if samples > 1 {
+            let edge = pixels[4 * 4];
+            assert!(edge > 0 && edge < 255, "edge pixel {edge}");
+        }
+    }
+}
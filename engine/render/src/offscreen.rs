@@ -0,0 +1,174 @@
+//! Offscreen render targets.
+//!
+//! An offscreen target is a texture owned by the renderer rather than a
+//! window surface, so it can be drawn without a display, as in tests or
+//! when making thumbnails, and read back into memory afterwards.
+
+use crate::context::RenderContext;
+use crate::{Error, Result};
+
+/// A texture to render into and read back.
+#[derive(Debug)]
+pub struct OffscreenTarget {
+    /// The texture drawn into.
+    pub texture: wgpu::Texture,
+    /// A view of the whole texture.
+    pub view: wgpu::TextureView,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+impl OffscreenTarget {
+    /// Create a `width` by `height` target of `format`. Only 8-bit RGBA and
+    /// BGRA formats can be read back.
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            width: width.max(1),
+            height: height.max(1),
+        }
+    }
+
+    /// The target's RGBA pixels, row by row from the top.
+    pub fn read(&self, context: &RenderContext) -> Result<Vec<u8>> {
+        read_texture(context, &self.texture)
+    }
+
+    /// Copy the target into `destination`, such as a surface texture,
+    /// which must be as large, of the same format and created with
+    /// `COPY_DST` usage.
+    pub fn copy_to(&self, encoder: &mut wgpu::CommandEncoder, destination: &wgpu::Texture) {
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            destination.as_image_copy(),
+            self.texture.size(),
+        );
+    }
+}
+
+/// The RGBA pixels of `texture`, row by row from the top. The texture must
+/// have been created with `COPY_SRC` usage.
+///
+/// Texture copies lay rows out at multiples of 256 bytes; the padding is
+/// dropped, and BGRA pixels are swapped to RGBA.
+pub fn read_texture(context: &RenderContext, texture: &wgpu::Texture) -> Result<Vec<u8>> {
+    let bgra = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => {
+            return Err(Error::Texture(format!(
+                "cannot read back {:?} textures",
+                format
+            )));
+        }
+    };
+    let (width, height) = (texture.width(), texture.height());
+    let row = width * 4;
+    let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = context
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    context.queue.submit([encoder.finish()]);
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    context.device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .map_err(|e| Error::Gpu(e.to_string()))?
+        .map_err(|e| Error::Gpu(e.to_string()))?;
+
+    let data = buffer.slice(..).get_mapped_range();
+    let mut pixels = Vec::with_capacity((row * height) as usize);
+    for line in data.chunks(padded_row as usize) {
+        pixels.extend_from_slice(&line[..row as usize]);
+    }
+    if bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_read_drops_row_padding() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        // 3 pixels make a 12-byte row, padded to 256 bytes in the copy.
+        let target = OffscreenTarget::new(&context.device, 3, 2, wgpu::TextureFormat::Bgra8Unorm);
+        let mut encoder = context.device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        context.queue.submit([encoder.finish()]);
+
+        let pixels = target.read(&context).unwrap();
+        assert_eq!(pixels.len(), 3 * 2 * 4);
+        assert!(pixels.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+    }
+}
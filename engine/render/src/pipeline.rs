@@ -1,6 +1,7 @@
 //! Render pipelines.
 
 use crate::context::RenderContext;
+use crate::msaa;
 
 /// A render pipeline for a specific type of content.
 pub struct RenderPipeline {
@@ -11,6 +12,16 @@ pub struct RenderPipeline {
 impl RenderPipeline {
     /// Create a basic render pipeline.
     pub fn new(context: &RenderContext, shader: &wgpu::ShaderModule) -> Self {
+        Self::new_multisampled(context, shader, 1)
+    }
+
+    /// Create a basic render pipeline drawing with `sample_count` samples
+    /// per pixel.
+    pub fn new_multisampled(
+        context: &RenderContext,
+        shader: &wgpu::ShaderModule,
+        sample_count: u32,
+    ) -> Self {
         let layout = context
             .device
             .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -50,7 +61,7 @@ impl RenderPipeline {
                     conservative: false,
                 },
                 depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
+                multisample: msaa::multisample_state(sample_count),
                 multiview: None,
                 cache: None,
             });
@@ -2,6 +2,12 @@
 
 use wgpu::util::DeviceExt;
 
+use wolia_math::Rect;
+
+use crate::batch::DrawStats;
+use crate::clip::Scissor;
+use crate::msaa::{self, ColorTarget};
+
 /// Vertex for 2D quads.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -25,6 +31,30 @@ impl Vertex {
     }
 }
 
+/// Per-instance data for instanced quads.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct QuadInstance {
+    /// Top-left and bottom-right corners in NDC.
+    pub corners: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl QuadInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
+        0 => Float32x4,
+        1 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<QuadInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
 /// A simple quad (rectangle) to render.
 #[derive(Debug, Clone, Copy)]
 pub struct Quad {
@@ -81,17 +111,43 @@ impl Quad {
             },
         ]
     }
+
+    /// Convert to instance data.
+    pub fn to_instance(&self, screen_width: f32, screen_height: f32) -> QuadInstance {
+        QuadInstance {
+            corners: [
+                (self.x / screen_width) * 2.0 - 1.0,
+                1.0 - (self.y / screen_height) * 2.0,
+                ((self.x + self.width) / screen_width) * 2.0 - 1.0,
+                1.0 - ((self.y + self.height) / screen_height) * 2.0,
+            ],
+            color: self.color,
+        }
+    }
 }
 
 /// Simple 2D quad renderer.
 pub struct QuadRenderer {
     pipeline: wgpu::RenderPipeline,
+    instanced_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     max_quads: usize,
+    /// Target pixels per unit of the screen size quads are given in.
+    scale_factor: f32,
 }
 
 impl QuadRenderer {
     pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self::new_multisampled(device, format, 1)
+    }
+
+    /// Create a renderer drawing into targets with `sample_count` samples
+    /// per pixel.
+    pub fn new_multisampled(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Quad Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("quad.wgsl").into()),
@@ -103,39 +159,24 @@ impl QuadRenderer {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Quad Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            "vs_main",
+            Vertex::desc(),
+            format,
+            sample_count,
+        );
+        let instanced_pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            "vs_instanced",
+            QuadInstance::desc(),
+            format,
+            sample_count,
+        );
 
         let max_quads = 1000;
         let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -147,21 +188,34 @@ impl QuadRenderer {
 
         Self {
             pipeline,
+            instanced_pipeline,
             vertex_buffer,
             max_quads,
+            scale_factor: 1.0,
         }
     }
 
+    /// Set how many target pixels there are per unit of the screen size,
+    /// quads and clips are given in. Quads scale with the screen size on
+    /// their own; clips need this to land on the right pixels.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Render quads, clearing the target first if `clear_color` is given.
+    /// With a `clip`, in screen units, only pixels inside it are drawn;
+    /// a clip outside the target draws nothing.
     #[allow(clippy::too_many_arguments)]
-    pub fn render(
+    pub fn render<'a>(
         &self,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
+        target: impl Into<ColorTarget<'a>>,
         queue: &wgpu::Queue,
         quads: &[Quad],
         screen_width: f32,
         screen_height: f32,
         clear_color: Option<wgpu::Color>,
+        clip: Option<Rect>,
     ) {
         if quads.is_empty() && clear_color.is_none() {
             return;
@@ -184,23 +238,309 @@ impl QuadRenderer {
 
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Quad Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: load_op,
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
+            color_attachments: &[Some(target.into().attachment(load_op))],
             depth_stencil_attachment: None,
             timestamp_writes: None,
             occlusion_query_set: None,
         });
 
+        let scissor = match clip {
+            Some(clip) => match Scissor::from_scaled_clip(
+                clip,
+                screen_width,
+                screen_height,
+                self.scale_factor,
+            ) {
+                Some(scissor) => Some(scissor),
+                None => return,
+            },
+            None => None,
+        };
         if !vertices.is_empty() {
+            if let Some(scissor) = scissor {
+                scissor.apply(&mut render_pass);
+            }
             render_pass.set_pipeline(&self.pipeline);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.draw(0..vertices.len() as u32, 0..1);
         }
     }
+
+    /// Render quads in order with a single instanced draw call, clearing
+    /// and clipping as [`render`](Self::render) does.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_instanced<'a>(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: impl Into<ColorTarget<'a>>,
+        quads: &[Quad],
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: Option<wgpu::Color>,
+        clip: Option<Rect>,
+    ) -> DrawStats {
+        if quads.is_empty() && clear_color.is_none() {
+            return DrawStats::default();
+        }
+
+        let load_op = match clear_color {
+            Some(color) => wgpu::LoadOp::Clear(color),
+            None => wgpu::LoadOp::Load,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Quad Instanced Render Pass"),
+            color_attachments: &[Some(target.into().attachment(load_op))],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let mut stats = DrawStats {
+            passes: 1,
+            ..DrawStats::default()
+        };
+        if quads.is_empty() {
+            return stats;
+        }
+        if let Some(clip) = clip {
+            match Scissor::from_scaled_clip(clip, screen_width, screen_height, self.scale_factor) {
+                Some(scissor) => scissor.apply(&mut render_pass),
+                None => return stats,
+            }
+        }
+
+        let instances: Vec<QuadInstance> = quads
+            .iter()
+            .map(|quad| quad.to_instance(screen_width, screen_height))
+            .collect();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Quad Instance Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        render_pass.set_pipeline(&self.instanced_pipeline);
+        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..instances.len() as u32);
+        stats.pipeline_binds = 1;
+        stats.draw_calls = 1;
+        stats
+    }
+
+    /// Render triangles, three vertices each, with positions in screen
+    /// units, for shapes quads cannot draw. Clears and clips as
+    /// [`render`](Self::render) does.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_triangles<'a>(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: impl Into<ColorTarget<'a>>,
+        vertices: &[Vertex],
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: Option<wgpu::Color>,
+        clip: Option<Rect>,
+    ) {
+        let count = vertices.len() - vertices.len() % 3;
+        if count == 0 && clear_color.is_none() {
+            return;
+        }
+
+        let load_op = match clear_color {
+            Some(color) => wgpu::LoadOp::Clear(color),
+            None => wgpu::LoadOp::Load,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Triangle Render Pass"),
+            color_attachments: &[Some(target.into().attachment(load_op))],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if count == 0 {
+            return;
+        }
+        if let Some(clip) = clip {
+            match Scissor::from_scaled_clip(clip, screen_width, screen_height, self.scale_factor) {
+                Some(scissor) => scissor.apply(&mut render_pass),
+                None => return,
+            }
+        }
+
+        let vertices: Vec<Vertex> = vertices[..count]
+            .iter()
+            .map(|vertex| Vertex {
+                position: [
+                    (vertex.position[0] / screen_width) * 2.0 - 1.0,
+                    1.0 - (vertex.position[1] / screen_height) * 2.0,
+                ],
+                color: vertex.color,
+            })
+            .collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Triangle Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..count as u32, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    vertex_entry: &str,
+    buffer: wgpu::VertexBufferLayout<'static>,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Quad Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some(vertex_entry),
+            buffers: &[buffer],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: msaa::multisample_state(sample_count),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FORMAT, Target};
+
+    #[test]
+    fn test_instanced_matches_vertices() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let (device, queue) = (&context.device, &context.queue);
+        let renderer = QuadRenderer::new(device, FORMAT);
+        // Overlapping translucent quads, whose blend depends on order.
+        let quads: Vec<Quad> = (0..20)
+            .map(|i| {
+                let shade = i as f32 / 20.0;
+                Quad::new(
+                    i as f32 * 8.0,
+                    i as f32 * 3.0,
+                    30.0,
+                    30.0,
+                    [shade, 0.2, 1.0 - shade, 0.6],
+                )
+            })
+            .collect();
+
+        let render = |instanced: bool| {
+            let target = Target::new(device, 192, 96);
+            let mut encoder = device.create_command_encoder(&Default::default());
+            target.clear(&mut encoder);
+            if instanced {
+                let stats = renderer.render_instanced(
+                    device,
+                    &mut encoder,
+                    &target.view,
+                    &quads,
+                    192.0,
+                    96.0,
+                    None,
+                    None,
+                );
+                assert_eq!((stats.passes, stats.draw_calls), (1, 1));
+            } else {
+                renderer.render(
+                    &mut encoder,
+                    &target.view,
+                    queue,
+                    &quads,
+                    192.0,
+                    96.0,
+                    None,
+                    None,
+                );
+            }
+            queue.submit([encoder.finish()]);
+            target.read(&context)
+        };
+
+        assert!(render(true) == render(false));
+    }
+
+    #[test]
+    fn test_clip_limits_drawing() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let (device, queue) = (&context.device, &context.queue);
+        let renderer = QuadRenderer::new(device, FORMAT);
+        let target = Target::new(device, 64, 64);
+        let black = [0.0, 0.0, 0.0, 1.0];
+        // The quad covers 10..50 both ways; the clip only 0..30 by 20..40.
+        let quads = [Quad::new(10.0, 10.0, 40.0, 40.0, black)];
+        let clip = Rect::new(0.0, 20.0, 30.0, 20.0);
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        target.clear(&mut encoder);
+        renderer.render(
+            &mut encoder,
+            &target.view,
+            queue,
+            &quads,
+            64.0,
+            64.0,
+            None,
+            Some(clip),
+        );
+        // A clip off the target draws nothing.
+        let outside = Rect::new(100.0, 100.0, 10.0, 10.0);
+        renderer.render(
+            &mut encoder,
+            &target.view,
+            queue,
+            &quads,
+            64.0,
+            64.0,
+            None,
+            Some(outside),
+        );
+        queue.submit([encoder.finish()]);
+
+        let pixels = target.read(&context);
+        for y in 0..64 {
+            for x in 0..64 {
+                let drawn = pixels[(y * 64 + x) * 4] == 0;
+                let inside = (10..30).contains(&x) && (20..40).contains(&y);
+                assert_eq!(drawn, inside, "pixel ({x}, {y})");
+            }
+        }
+    }
 }
@@ -22,3 +22,27 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color;
 }
+
+// Instanced quads: each instance is a rectangle given by its corners in
+// NDC, and the six vertices of its two triangles are generated here.
+
+struct InstanceInput {
+    @location(0) corners: vec4<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_instanced(@builtin(vertex_index) index: u32, in: InstanceInput) -> VertexOutput {
+    // Same corner order as Quad::to_vertices.
+    let right = index == 1u || index == 4u || index == 5u;
+    let bottom = index == 2u || index == 3u || index == 5u;
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(
+        select(in.corners.x, in.corners.z, right),
+        select(in.corners.y, in.corners.w, bottom),
+        0.0,
+        1.0,
+    );
+    out.color = in.color;
+    return out;
+}
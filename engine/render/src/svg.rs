@@ -0,0 +1,519 @@
+//! SVG output.
+//!
+//! Draws a [`LayoutTree`] as SVG from the same layout the GPU renderer
+//! draws, for printing and embedding at any resolution. Layout units are
+//! points, and the SVG's user units are too: the document's width and
+//! height are given in `pt` over a view box of the same numbers, and every
+//! element is placed at absolute coordinates, with pages stacked top to
+//! bottom as on screen.
+//!
+//! Text is written as `<text>`, which stays selectable and searchable but
+//! is drawn in whatever font the viewer has. [Outlined](SvgOptions::outline_text)
+//! text is shaped here and written as glyph paths instead, which look the
+//! same everywhere.
+
+use std::fmt::Write;
+
+use cosmic_text::fontdb::Family;
+use cosmic_text::{Attrs, Buffer, Command, FontSystem, Metrics, Shaping, Style, SwashCache};
+use wolia_layout::{LayoutContent, LayoutNode, LayoutTree, Line};
+use wolia_math::{Color, Point, Rect};
+
+use crate::text::DEFAULT_FONT_SIZE;
+use crate::{Error, Result};
+
+/// Width of the strokes leaders are drawn with.
+const LEADER_WIDTH: f32 = 0.75;
+
+/// How SVG output is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    /// Font family text is set in, by name or as a generic family such as
+    /// `sans-serif`.
+    pub font_family: String,
+    /// Font size of text, in points.
+    pub font_size: f32,
+    /// Color of text and rules.
+    pub color: Color,
+    /// Color of the paper, or `None` to leave pages transparent.
+    pub background: Option<Color>,
+    /// Whether text is drawn as glyph outlines rather than `<text>`.
+    pub outline_text: bool,
+}
+
+impl SvgOptions {
+    /// Set the font family text is set in.
+    pub fn with_font_family(mut self, family: impl Into<String>) -> Self {
+        self.font_family = family.into();
+        self
+    }
+
+    /// Set the font size of text.
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Set the color of text and rules.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the color of the paper.
+    pub fn with_background(mut self, background: Option<Color>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Draw text as glyph outlines.
+    pub fn with_outlined_text(mut self, outline: bool) -> Self {
+        self.outline_text = outline;
+        self
+    }
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            font_family: "sans-serif".to_string(),
+            font_size: DEFAULT_FONT_SIZE,
+            color: Color::BLACK,
+            background: Some(Color::WHITE),
+            outline_text: false,
+        }
+    }
+}
+
+/// Renders layout trees as SVG documents.
+pub struct SvgRenderer {
+    options: SvgOptions,
+    /// Fonts for outlining text, loaded when first needed.
+    fonts: Option<(FontSystem, SwashCache)>,
+}
+
+impl SvgRenderer {
+    /// Create a renderer writing SVG with `options`.
+    pub fn new(options: SvgOptions) -> Self {
+        Self {
+            options,
+            fonts: None,
+        }
+    }
+
+    /// Outline text with the fonts of `font_system` instead of the system's.
+    pub fn with_font_system(mut self, font_system: FontSystem) -> Self {
+        self.fonts = Some((font_system, SwashCache::new()));
+        self
+    }
+
+    /// Options output is written with.
+    pub fn options(&self) -> &SvgOptions {
+        &self.options
+    }
+
+    /// Render every page of a layout tree, stacked top to bottom.
+    pub fn render(&mut self, layout: &LayoutTree) -> String {
+        let width = layout
+            .pages
+            .iter()
+            .map(|page| page.size.width)
+            .fold(0.0, f32::max);
+        let height = layout.pages.iter().map(|page| page.size.height).sum();
+        let mut svg = header(width, height);
+        let mut page_y = 0.0;
+        for page in &layout.pages {
+            self.page(&mut svg, page, page_y);
+            page_y += page.size.height;
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Render page `index` of a layout tree on its own.
+    pub fn render_page(&mut self, layout: &LayoutTree, index: usize) -> Result<String> {
+        let page = layout.pages.get(index).ok_or(Error::PageOutOfRange {
+            index,
+            count: layout.pages.len(),
+        })?;
+        let mut svg = header(page.size.width, page.size.height);
+        self.page(&mut svg, page, 0.0);
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    fn page(&mut self, svg: &mut String, page: &wolia_layout::Page, y: f32) {
+        if let Some(background) = self.options.background {
+            let area = Rect::new(0.0, y, page.size.width, page.size.height);
+            rect(svg, area, background);
+        }
+        for node in page.all_nodes() {
+            self.node(svg, node, Point::new(0.0, y));
+        }
+    }
+
+    /// Write a node whose bounds are relative to `offset`.
+    fn node(&mut self, svg: &mut String, node: &LayoutNode, offset: Point) {
+        let origin = offset + Point::new(node.bounds.x, node.bounds.y);
+        match &node.content {
+            LayoutContent::Paragraph(paragraph) => {
+                self.lines(svg, &paragraph.text, &paragraph.lines, origin);
+            }
+            // Cell lines are relative to the cell's content rectangle, which
+            // is relative to the table.
+            LayoutContent::Table { cells } => {
+                for cell in cells {
+                    if let LayoutContent::Paragraph(paragraph) = &cell.content {
+                        let content = Point::new(paragraph.bounds.x, paragraph.bounds.y);
+                        self.lines(svg, &paragraph.text, &paragraph.lines, origin + content);
+                    }
+                }
+            }
+            LayoutContent::Image { src } => {
+                let _ = writeln!(
+                    svg,
+                    r#"<image x="{}" y="{}" width="{}" height="{}" href="{}" preserveAspectRatio="none"/>"#,
+                    num(origin.x),
+                    num(origin.y),
+                    num(node.bounds.width),
+                    num(node.bounds.height),
+                    escape(src)
+                );
+            }
+            LayoutContent::Drawing(drawing) => {
+                let baseline = origin + Point::new(0.0, drawing.ascent);
+                for glyph in &drawing.glyphs {
+                    let position = baseline + Point::new(glyph.x, glyph.y);
+                    let text = glyph.ch.to_string();
+                    self.text(svg, &text, position, glyph.size, glyph.italic);
+                }
+                for &[x, y, width, height] in &drawing.rules {
+                    let area = Rect::new(baseline.x + x, baseline.y + y, width, height);
+                    rect(svg, area, self.options.color);
+                }
+            }
+            LayoutContent::Container { children } => {
+                for child in children {
+                    self.node(svg, child, origin);
+                }
+            }
+        }
+    }
+
+    /// Write lines of `text` laid out from `origin`.
+    fn lines(&mut self, svg: &mut String, text: &str, lines: &[Line], origin: Point) {
+        let font_size = self.options.font_size;
+        for line in lines {
+            let baseline = origin.y + line.bounds.y + line.baseline;
+            for leader in &line.leaders {
+                let x = origin.x + leader.bounds.x;
+                leader_line(
+                    svg,
+                    leader.character,
+                    (x, x + leader.bounds.width),
+                    baseline,
+                    self.options.color,
+                );
+            }
+            let last = line.fragments.len().saturating_sub(1);
+            for (index, fragment) in line.fragments.iter().enumerate() {
+                let range = fragment.text_start..fragment.text_start + fragment.text_len;
+                let Some(run) = text.get(range) else {
+                    continue;
+                };
+                let run = if line.hyphenated && index == last {
+                    format!("{run}-")
+                } else {
+                    run.to_string()
+                };
+                let x = origin.x + fragment.bounds.x;
+                self.text(svg, &run, Point::new(x, baseline), font_size, false);
+            }
+        }
+    }
+
+    /// Write `text` with the left end of its baseline at `position`.
+    fn text(&mut self, svg: &mut String, text: &str, position: Point, size: f32, italic: bool) {
+        if text.trim().is_empty() {
+            return;
+        }
+        if self.options.outline_text {
+            self.outline(svg, text, position, size, italic);
+            return;
+        }
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="{}" font-size="{}"{} fill="{}"{} xml:space="preserve">{}</text>"#,
+            num(position.x),
+            num(position.y),
+            escape(&self.options.font_family),
+            num(size),
+            if italic {
+                r#" font-style="italic""#
+            } else {
+                ""
+            },
+            hex(self.options.color),
+            opacity("fill-opacity", self.options.color),
+            escape(text)
+        );
+    }
+
+    /// Write `text` as a path of glyph outlines.
+    fn outline(&mut self, svg: &mut String, text: &str, position: Point, size: f32, italic: bool) {
+        let family = match self.options.font_family.as_str() {
+            "serif" => Family::Serif,
+            "sans-serif" => Family::SansSerif,
+            "monospace" => Family::Monospace,
+            "cursive" => Family::Cursive,
+            "fantasy" => Family::Fantasy,
+            name => Family::Name(name),
+        };
+        let attrs =
+            Attrs::new()
+                .family(family)
+                .style(if italic { Style::Italic } else { Style::Normal });
+        let (font_system, cache) = self
+            .fonts
+            .get_or_insert_with(|| (FontSystem::new(), SwashCache::new()));
+        let mut buffer = Buffer::new(font_system, Metrics::new(size, size * 1.2));
+        buffer.set_size(font_system, None, None);
+        buffer.set_text(font_system, text, attrs, Shaping::Advanced);
+
+        let mut path = String::new();
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                // Outlines are in points up from the pen, which the glyph's
+                // offsets move from the fragment's.
+                let pen_x = position.x + glyph.x + glyph.font_size * glyph.x_offset;
+                let pen_y = position.y + glyph.y - glyph.font_size * glyph.y_offset;
+                let key = glyph.physical((0.0, 0.0), 1.0).cache_key;
+                let Some(commands) = cache.get_outline_commands(font_system, key) else {
+                    continue;
+                };
+                for command in commands {
+                    let at = |x: f32, y: f32| format!("{} {}", num(pen_x + x), num(pen_y - y));
+                    let _ = match command {
+                        Command::MoveTo(p) => write!(path, "M{}", at(p.x, p.y)),
+                        Command::LineTo(p) => write!(path, "L{}", at(p.x, p.y)),
+                        Command::CurveTo(a, b, p) => {
+                            write!(path, "C{} {} {}", at(a.x, a.y), at(b.x, b.y), at(p.x, p.y))
+                        }
+                        Command::QuadTo(a, p) => write!(path, "Q{} {}", at(a.x, a.y), at(p.x, p.y)),
+                        Command::Close => write!(path, "Z"),
+                    };
+                }
+            }
+        }
+        if !path.is_empty() {
+            let _ = writeln!(
+                svg,
+                r#"<path d="{}" fill="{}"{}/>"#,
+                path,
+                hex(self.options.color),
+                opacity("fill-opacity", self.options.color)
+            );
+        }
+    }
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new(SvgOptions::default())
+    }
+}
+
+/// Render every page of a layout tree as SVG with default options.
+pub fn render_svg(layout: &LayoutTree) -> String {
+    SvgRenderer::default().render(layout)
+}
+
+fn header(width: f32, height: f32) -> String {
+    let (width, height) = (num(width), num(height));
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+         width=\"{width}pt\" height=\"{height}pt\" viewBox=\"0 0 {width} {height}\">\n"
+    )
+}
+
+fn rect(svg: &mut String, area: Rect, color: Color) {
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"{}/>"#,
+        num(area.x),
+        num(area.y),
+        num(area.width),
+        num(area.height),
+        hex(color),
+        opacity("fill-opacity", color)
+    );
+}
+
+/// Write a leader across `x` on `baseline`: dotted for `.`, dashed for `-`,
+/// and solid otherwise.
+fn leader_line(
+    svg: &mut String,
+    character: char,
+    (x1, x2): (f32, f32),
+    baseline: f32,
+    color: Color,
+) {
+    let dashes = match character {
+        '.' | '·' => r#" stroke-dasharray="0 3" stroke-linecap="round""#,
+        '-' => r#" stroke-dasharray="3 2""#,
+        _ => "",
+    };
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{}" y1="{y}" x2="{}" y2="{y}" stroke="{}"{} stroke-width="{}"{}/>"#,
+        num(x1),
+        num(x2),
+        hex(color),
+        opacity("stroke-opacity", color),
+        num(LEADER_WIDTH),
+        dashes,
+        y = num(baseline)
+    );
+}
+
+/// A color as an sRGB hex code.
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.to_srgb8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// An opacity attribute for a color that is not opaque.
+fn opacity(attribute: &str, color: Color) -> String {
+    if color.a >= 1.0 {
+        String::new()
+    } else {
+        format!(r#" {attribute}="{}""#, num(color.a))
+    }
+}
+
+/// A coordinate, to a hundredth of a point.
+fn num(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    // Avoid writing "-0".
+    format!("{}", rounded + 0.0)
+}
+
+/// Escape text for use in XML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::{Document, Node, NodeKind, Text};
+    use wolia_layout::LayoutEngine;
+
+    use super::*;
+
+    fn document() -> Document {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("Fish & chips")));
+        document.root.add_child(Node::new(NodeKind::Image {
+            src: "asset:logo".to_string(),
+            alt: None,
+        }));
+        document.root.add_child(Node::paragraph(Text::new("Tea")));
+        document
+    }
+
+    fn count(svg: &str, element: &str) -> usize {
+        svg.matches(&format!("<{element} ")).count()
+    }
+
+    #[test]
+    fn test_small_document() {
+        let layout = LayoutEngine::new().layout(&document()).unwrap();
+        let svg = SvgRenderer::default().render(&layout);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"width="595pt" height="842pt" viewBox="0 0 595 842""#));
+        assert_eq!(count(&svg, "text"), 2);
+        assert_eq!(count(&svg, "image"), 1);
+        // The page.
+        assert_eq!(count(&svg, "rect"), 1);
+        assert!(svg.contains(">Fish &amp; chips</text>"));
+        assert!(svg.contains(r#"href="asset:logo""#));
+
+        // The first line sits in the content area, on its baseline.
+        let page = &layout.pages[0];
+        let LayoutContent::Paragraph(paragraph) = &page.nodes[0].content else {
+            panic!("expected a paragraph");
+        };
+        let line = &paragraph.lines[0];
+        let x = page.nodes[0].bounds.x + line.fragments[0].bounds.x;
+        let y = page.nodes[0].bounds.y + line.bounds.y + line.baseline;
+        assert_eq!(x, page.content_rect.x);
+        let expected = format!(
+            r##"<text x="{}" y="{}" font-family="sans-serif" font-size="12" fill="#000000" xml:space="preserve">Fish &amp; chips</text>"##,
+            num(x),
+            num(y)
+        );
+        assert!(svg.contains(&expected), "{svg}");
+    }
+
+    #[test]
+    fn test_pages_and_options() {
+        let mut document = Document::new();
+        for _ in 0..120 {
+            document
+                .root
+                .add_child(Node::paragraph(Text::new("A line of text")));
+        }
+        let layout = LayoutEngine::new().layout(&document).unwrap();
+        assert!(layout.pages.len() > 1);
+
+        let options = SvgOptions::default()
+            .with_background(None)
+            .with_color(Color::from_srgb8(0x33, 0x66, 0x99, 255));
+        let mut renderer = SvgRenderer::new(options);
+        let all = renderer.render(&layout);
+        assert_eq!(count(&all, "text"), 120);
+        assert_eq!(count(&all, "rect"), 0);
+        assert!(all.contains(r##"fill="#336699""##));
+
+        let second = renderer.render_page(&layout, 1).unwrap();
+        assert!(second.contains(r#"viewBox="0 0 595 842""#));
+        assert!(count(&second, "text") < 120);
+        assert!(matches!(
+            renderer.render_page(&layout, 99),
+            Err(Error::PageOutOfRange { index: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn test_outlined_text() {
+        let data = include_bytes!("../../../test-suite/fonts/Tuffy.ttf");
+        let mut db = cosmic_text::fontdb::Database::new();
+        db.load_font_data(data.to_vec());
+        let font_system = FontSystem::new_with_locale_and_db("en-US".to_string(), db);
+        let mut renderer = SvgRenderer::new(
+            SvgOptions::default()
+                .with_font_family("Tuffy")
+                .with_outlined_text(true),
+        )
+        .with_font_system(font_system);
+
+        let layout = LayoutEngine::new().layout(&document()).unwrap();
+        let svg = renderer.render(&layout);
+        assert_eq!(count(&svg, "text"), 0);
+        assert_eq!(count(&svg, "path"), 2);
+        assert!(svg.contains(r#"<path d="M"#));
+    }
+}
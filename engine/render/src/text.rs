@@ -1,12 +1,125 @@
 //! Text rendering.
 //!
-//! This module provides GPU-accelerated text rendering using cosmic-text.
+//! Text arrives already broken into lines by the layout engine. Each line
+//! fragment is shaped with cosmic-text and its glyphs are placed from the
+//! fragment's position: horizontally at subpixel precision, with the
+//! fractional offset binned into the glyph's cache key so that the same
+//! glyph at different offsets is rasterized separately, and vertically on
+//! whole pixels, which together with hinting keeps baselines crisp. The
+//! rasterized glyphs are packed into a [`GlyphAtlas`] and drawn as textured
+//! quads, one draw call per atlas page.
 
-use cosmic_text::{FontSystem, SwashCache};
+use std::borrow::Cow;
+use std::ops::Range;
+
+use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, Shaping, SwashCache};
 use parking_lot::Mutex;
+use wgpu::util::DeviceExt;
+use wolia_core::style::RevisionKind;
+use wolia_layout::Line;
+use wolia_math::{Color, Point, Rect};
+
+use crate::Result;
+use crate::atlas::{AtlasGlyph, GlyphAtlas};
+use crate::icon::TexturedVertex;
+use crate::msaa::{self, ColorTarget};
+use crate::ui::colors;
+
+/// Font size used when a run does not set one, in pixels.
+pub const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+/// Laid-out text to draw.
+#[derive(Debug, Clone, Copy)]
+pub struct TextRun<'a> {
+    /// The source text the line fragments index into.
+    pub text: &'a str,
+    /// Lines from the layout engine.
+    pub lines: &'a [Line],
+    /// Position of the lines' origin, in document coordinates.
+    pub origin: Point,
+    /// Font size in pixels.
+    pub font_size: f32,
+    /// Text color.
+    pub color: Color,
+    /// Linked parts of the text, drawn in the link color.
+    pub links: &'a [(Range<usize>, String)],
+    /// Parts of the text suggested for insertion or deletion, drawn in the
+    /// color of their kind of suggestion.
+    pub revisions: &'a [(Range<usize>, RevisionKind)],
+}
+
+impl<'a> TextRun<'a> {
+    /// Create a run of black text at the default font size.
+    pub fn new(text: &'a str, lines: &'a [Line], origin: Point) -> Self {
+        Self {
+            text,
+            lines,
+            origin,
+            font_size: DEFAULT_FONT_SIZE,
+            color: Color::BLACK,
+            links: &[],
+            revisions: &[],
+        }
+    }
+
+    /// Set the font size.
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Set the text color.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Draw the linked parts of the text in the link color.
+    pub fn with_links(mut self, links: &'a [(Range<usize>, String)]) -> Self {
+        self.links = links;
+        self
+    }
+
+    /// Draw the parts of the text suggested for insertion or deletion in
+    /// the color of their kind of suggestion.
+    pub fn with_revisions(mut self, revisions: &'a [(Range<usize>, RevisionKind)]) -> Self {
+        self.revisions = revisions;
+        self
+    }
+
+    /// The color of the text `offset` bytes into the source text.
+    fn color_at(&self, offset: usize) -> Color {
+        let revision = self
+            .revisions
+            .iter()
+            .find(|(range, _)| range.contains(&offset));
+        match revision {
+            Some((_, RevisionKind::Insertion)) => colors::INSERTION,
+            Some((_, RevisionKind::Deletion)) => colors::DELETION,
+            None if self.links.iter().any(|(range, _)| range.contains(&offset)) => colors::LINK,
+            None => self.color,
+        }
+    }
+}
 
-use crate::context::RenderContext;
-use crate::{Error, Result};
+/// A glyph ready to draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    /// Atlas page holding the glyph.
+    pub page: usize,
+    /// Where the glyph is drawn, in target pixels.
+    pub rect: Rect,
+    /// The glyph's part of the atlas page, in texture coordinates.
+    pub uv: Rect,
+    /// Tint applied to the glyph.
+    pub color: [f32; 4],
+}
+
+/// An atlas page on the GPU.
+struct PageTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
 
 /// Text renderer using cosmic-text.
 pub struct TextRenderer {
@@ -14,18 +127,119 @@ pub struct TextRenderer {
     font_system: Mutex<FontSystem>,
     /// Glyph cache.
     swash_cache: Mutex<SwashCache>,
+    /// Rasterized glyphs.
+    atlas: GlyphAtlas,
+    /// Render pipeline for glyph quads.
+    pipeline: wgpu::RenderPipeline,
+    /// Bind group layout for atlas pages.
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// Atlas page sampler.
+    sampler: wgpu::Sampler,
+    /// Atlas pages uploaded so far.
+    pages: Vec<PageTexture>,
 }
 
 impl TextRenderer {
-    /// Create a new text renderer.
-    pub fn new(_context: &RenderContext) -> Result<Self> {
-        let font_system = FontSystem::new();
-        let swash_cache = SwashCache::new();
+    /// Create a new text renderer drawing into targets of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self::new_multisampled(device, format, 1)
+    }
+
+    /// Create a text renderer drawing into targets of `format` with
+    /// `sample_count` samples per pixel.
+    pub fn new_multisampled(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("text.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Text Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[TexturedVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: msaa::multisample_state(sample_count),
+            multiview: None,
+            cache: None,
+        });
 
-        Ok(Self {
-            font_system: Mutex::new(font_system),
-            swash_cache: Mutex::new(swash_cache),
-        })
+        // Glyphs are drawn at whole pixels, texel for texel.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Text Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            font_system: Mutex::new(FontSystem::new()),
+            swash_cache: Mutex::new(SwashCache::new()),
+            atlas: GlyphAtlas::default(),
+            pipeline,
+            bind_group_layout,
+            sampler,
+            pages: Vec::new(),
+        }
     }
 
     /// Get mutable access to the font system.
@@ -43,15 +257,377 @@ impl TextRenderer {
     pub fn swash_cache(&self) -> parking_lot::MutexGuard<'_, SwashCache> {
         self.swash_cache.lock()
     }
+
+    /// The glyph atlas.
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    /// Shape the runs and rasterize any new glyphs, returning a quad for
+    /// every glyph visible in `viewport`.
+    pub fn prepare(&mut self, runs: &[TextRun<'_>], viewport: Rect) -> Vec<GlyphQuad> {
+        layout_glyphs(
+            &mut self.font_system.lock(),
+            &mut self.swash_cache.lock(),
+            &mut self.atlas,
+            runs,
+            viewport,
+        )
+    }
+
+    /// Draw the runs into `target`, which shows the `viewport` region of the
+    /// document.
+    pub fn render<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: impl Into<ColorTarget<'a>>,
+        runs: &[TextRun<'_>],
+        viewport: Rect,
+    ) {
+        let mut quads = self.prepare(runs, viewport);
+        if quads.is_empty() {
+            return;
+        }
+        self.upload(device, queue);
+
+        quads.sort_by_key(|quad| quad.page);
+        let vertices: Vec<TexturedVertex> = quads
+            .iter()
+            .flat_map(|quad| quad_vertices(quad, viewport.width, viewport.height))
+            .collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Text Render Pass"),
+            color_attachments: &[Some(target.into().attachment(wgpu::LoadOp::Load))],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        let mut start = 0;
+        for batch in quads.chunk_by(|a, b| a.page == b.page) {
+            let end = start + batch.len() as u32 * 6;
+            render_pass.set_bind_group(0, &self.pages[batch[0].page].bind_group, &[]);
+            render_pass.draw(start..end, 0..1);
+            start = end;
+        }
+    }
+
+    /// Create textures for new atlas pages and upload the changed ones.
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let size = self.atlas.page_size();
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        };
+        for index in self.pages.len()..self.atlas.pages().len() {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&format!("Glyph Atlas Page {}", index)),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("Glyph Atlas Bind Group {}", index)),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            self.pages.push(PageTexture {
+                texture,
+                bind_group,
+            });
+        }
+
+        for (page, texture) in self.atlas.pages_mut().iter_mut().zip(&self.pages) {
+            if !page.dirty {
+                continue;
+            }
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &page.pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(size * 4),
+                    rows_per_image: Some(size),
+                },
+                extent,
+            );
+            page.dirty = false;
+        }
+    }
+}
+
+/// Shape every line fragment of the runs and place its glyphs, rasterizing
+/// glyphs not yet in the atlas. Each call is a new atlas frame.
+fn layout_glyphs(
+    font_system: &mut FontSystem,
+    swash_cache: &mut SwashCache,
+    atlas: &mut GlyphAtlas,
+    runs: &[TextRun<'_>],
+    viewport: Rect,
+) -> Vec<GlyphQuad> {
+    atlas.begin_frame();
+    let target = Rect::new(0.0, 0.0, viewport.width, viewport.height);
+    let mut quads = Vec::new();
+    for run in runs {
+        let metrics = Metrics::new(run.font_size, run.font_size * 1.2);
+        let mut buffer = Buffer::new(font_system, metrics);
+        buffer.set_size(font_system, None, None);
+
+        for line in run.lines {
+            let top = run.origin.y + line.bounds.y - viewport.y;
+            if top > viewport.height || top + line.bounds.height < 0.0 {
+                continue;
+            }
+            let baseline = top + line.baseline;
+            let last = line.fragments.len().saturating_sub(1);
+            for (index, fragment) in line.fragments.iter().enumerate() {
+                let range = fragment.text_start..fragment.text_start + fragment.text_len;
+                let Some(text) = run.text.get(range) else {
+                    continue;
+                };
+                let text = if line.hyphenated && index == last {
+                    Cow::Owned(format!("{text}-"))
+                } else {
+                    Cow::Borrowed(text)
+                };
+                buffer.set_text(font_system, &text, Attrs::new(), Shaping::Advanced);
+
+                let x = run.origin.x + fragment.bounds.x - viewport.x;
+                for layout_run in buffer.layout_runs() {
+                    for glyph in layout_run.glyphs {
+                        let physical = glyph.physical((x, baseline), 1.0);
+                        let placed = match atlas.get(&physical.cache_key) {
+                            Some(placed) => placed,
+                            None => {
+                                let image =
+                                    swash_cache.get_image_uncached(font_system, physical.cache_key);
+                                atlas.insert(physical.cache_key, image.as_ref())
+                            }
+                        };
+                        let Some(placed) = placed else {
+                            continue;
+                        };
+                        let quad = glyph_quad(
+                            &placed,
+                            physical.x,
+                            physical.y,
+                            atlas.page_size(),
+                            run.color_at(fragment.text_start + glyph.start),
+                        );
+                        if quad.rect.intersects(&target) {
+                            quads.push(quad);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    quads
+}
+
+/// The quad drawing `glyph` with its pen position at `(x, y)`.
+fn glyph_quad(glyph: &AtlasGlyph, x: i32, y: i32, page_size: u32, color: Color) -> GlyphQuad {
+    let color = if glyph.color {
+        [1.0, 1.0, 1.0, color.a]
+    } else {
+        [color.r, color.g, color.b, color.a]
+    };
+    GlyphQuad {
+        page: glyph.page,
+        rect: Rect::new(
+            (x + glyph.left) as f32,
+            (y - glyph.top) as f32,
+            glyph.width as f32,
+            glyph.height as f32,
+        ),
+        uv: glyph.uv(page_size),
+        color,
+    }
+}
+
+/// Two triangles covering a quad, in normalized device coordinates.
+fn quad_vertices(quad: &GlyphQuad, screen_width: f32, screen_height: f32) -> [TexturedVertex; 6] {
+    let x1 = (quad.rect.x / screen_width) * 2.0 - 1.0;
+    let y1 = 1.0 - (quad.rect.y / screen_height) * 2.0;
+    let x2 = (quad.rect.right() / screen_width) * 2.0 - 1.0;
+    let y2 = 1.0 - (quad.rect.bottom() / screen_height) * 2.0;
+    let (u1, v1, u2, v2) = (quad.uv.x, quad.uv.y, quad.uv.right(), quad.uv.bottom());
+    let vertex = |position, tex_coords| TexturedVertex {
+        position,
+        tex_coords,
+        color: quad.color,
+    };
+    [
+        vertex([x1, y1], [u1, v1]),
+        vertex([x2, y1], [u2, v1]),
+        vertex([x1, y2], [u1, v2]),
+        vertex([x1, y2], [u1, v2]),
+        vertex([x2, y1], [u2, v1]),
+        vertex([x2, y2], [u2, v2]),
+    ]
 }
 
 #[cfg(test)]
 mod tests {
+    use wolia_core::text::Text;
+    use wolia_layout::{Constraints, ParagraphLayout};
+    use wolia_math::Size;
+
     use super::*;
+    use crate::testing::{self, Target};
+
+    fn paragraph(text: &str) -> ParagraphLayout {
+        ParagraphLayout::layout(
+            &Text::new(text),
+            Constraints::loose(Size::new(400.0, 400.0)),
+        )
+    }
+
+    fn glyphs(atlas: &mut GlyphAtlas, runs: &[TextRun<'_>], viewport: Rect) -> Vec<GlyphQuad> {
+        layout_glyphs(
+            &mut FontSystem::new(),
+            &mut SwashCache::new(),
+            atlas,
+            runs,
+            viewport,
+        )
+    }
 
     #[test]
-    fn test_text_renderer_creation() {
-        // Note: This test doesn't require a real RenderContext in this simplified version
-        // In a real scenario, you'd need to set up proper GPU resources
+    fn test_glyphs_follow_layout() {
+        let layout = paragraph("Hi there");
+        let run =
+            TextRun::new(&layout.text, &layout.lines, Point::new(10.0, 20.0)).with_font_size(16.0);
+        let viewport = Rect::new(0.0, 0.0, 400.0, 200.0);
+        let quads = glyphs(&mut GlyphAtlas::default(), &[run], viewport);
+
+        // Seven inked glyphs; the space has none.
+        assert_eq!(quads.len(), 7);
+        assert!(quads.windows(2).all(|pair| pair[0].rect.x < pair[1].rect.x));
+        let baseline = 20.0 + layout.lines[0].bounds.y + layout.lines[0].baseline;
+        assert!(quads[0].rect.x >= 10.0);
+        assert!(quads[0].rect.bottom() <= baseline.ceil() + 1.0);
+        assert!(quads[0].rect.y < baseline);
+
+        // Scrolling the viewport moves the glyphs, and text outside it is
+        // culled.
+        let scrolled = Rect::new(0.0, 10.0, 400.0, 200.0);
+        let moved = glyphs(&mut GlyphAtlas::default(), &[run], scrolled);
+        assert_eq!(moved[0].rect.y, quads[0].rect.y - 10.0);
+        let below = Rect::new(0.0, 500.0, 400.0, 200.0);
+        assert!(glyphs(&mut GlyphAtlas::default(), &[run], below).is_empty());
+    }
+
+    #[test]
+    fn test_links_are_drawn_in_link_color() {
+        let layout = paragraph("Hi there");
+        let links = [(3..8, "https://example.com".to_string())];
+        let run = TextRun::new(&layout.text, &layout.lines, Point::ZERO).with_links(&links);
+        let quads = glyphs(
+            &mut GlyphAtlas::default(),
+            &[run],
+            Rect::new(0.0, 0.0, 400.0, 200.0),
+        );
+        let ink = |color: Color| [color.r, color.g, color.b, color.a];
+        assert_eq!(quads.len(), 7);
+        assert!(
+            quads[..2]
+                .iter()
+                .all(|quad| quad.color == ink(Color::BLACK))
+        );
+        assert!(
+            quads[2..]
+                .iter()
+                .all(|quad| quad.color == ink(colors::LINK))
+        );
+    }
+
+    #[test]
+    fn test_full_atlas_page_spills() {
+        let layout = paragraph("abcdefghijklmnopqrstuvwxyz");
+        let run = TextRun::new(&layout.text, &layout.lines, Point::ZERO).with_font_size(24.0);
+        let mut atlas = GlyphAtlas::new(48);
+        let quads = glyphs(&mut atlas, &[run], Rect::new(0.0, 0.0, 800.0, 100.0));
+
+        assert!(atlas.pages().len() > 1);
+        assert_eq!(quads.last().unwrap().page, atlas.pages().len() - 1);
+        assert!(quads.iter().all(|quad| quad.uv.right() <= 1.0));
+    }
+
+    #[test]
+    fn test_render_to_offscreen_target() {
+        let Some(context) = testing::context() else {
+            return;
+        };
+        let (device, queue) = (&context.device, &context.queue);
+        let target = Target::new(device, 128, 32);
+        let (width, height) = (target.width, target.height);
+
+        let layout = paragraph("Hello");
+        let runs = [
+            TextRun::new(&layout.text, &layout.lines, Point::new(4.0, 4.0)).with_font_size(20.0),
+        ];
+        let viewport = Rect::new(0.0, 0.0, width as f32, height as f32);
+        let mut renderer = TextRenderer::new(device, testing::FORMAT);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        target.clear(&mut encoder);
+        renderer.render(device, queue, &mut encoder, &target.view, &runs, viewport);
+        queue.submit([encoder.finish()]);
+        let pixels = target.read(&context);
+        let inked = |rect: Rect| {
+            let (x0, y0) = (rect.x.max(0.0) as u32, rect.y.max(0.0) as u32);
+            let (x1, y1) = (
+                rect.right().min(width as f32),
+                rect.bottom().min(height as f32),
+            );
+            (y0..y1 as u32)
+                .any(|y| (x0..x1 as u32).any(|x| pixels[((y * width + x) * 4) as usize] < 128))
+        };
+
+        // Every glyph leaves ink in its quad, and nothing is drawn past the
+        // end of the text.
+        let quads = renderer.prepare(&runs, viewport);
+        assert_eq!(quads.len(), 5);
+        assert!(quads.iter().all(|quad| inked(quad.rect)));
+        let end = quads
+            .iter()
+            .map(|quad| quad.rect.right())
+            .fold(0.0, f32::max);
+        assert!(!inked(Rect::new(
+            end + 1.0,
+            0.0,
+            width as f32,
+            height as f32
+        )));
     }
 }
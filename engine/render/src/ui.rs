@@ -63,6 +63,9 @@ pub mod colors {
     pub const TEXT_PRIMARY: Color = Color::rgba(0.13, 0.13, 0.13, 1.0);
     pub const TEXT_SECONDARY: Color = Color::rgba(0.45, 0.45, 0.45, 1.0);
     pub const TEXT_LIGHT: Color = Color::rgba(0.95, 0.95, 0.95, 1.0);
+    pub const LINK: Color = Color::rgba(0.02, 0.39, 0.76, 1.0);
+    pub const INSERTION: Color = Color::rgba(0.0, 0.5, 0.25, 1.0);
+    pub const DELETION: Color = Color::rgba(0.78, 0.16, 0.16, 1.0);
 
     // Accent colors
     pub const ACCENT: Color = Color::rgba(0.26, 0.52, 0.96, 1.0);
@@ -0,0 +1,109 @@
+//! # CSV Format
+//!
+//! Comma-separated values import/export for spreadsheets, following
+//! RFC 4180.
+
+use grid_engine::Spreadsheet;
+
+mod reader;
+mod writer;
+
+/// How fields are separated and quoted.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Byte separating fields in a record.
+    pub delimiter: u8,
+    /// Byte enclosing fields that contain delimiters, quotes, or line
+    /// breaks.
+    pub quote: u8,
+    /// Whether the first record holds column names. When reading, they are
+    /// kept as text and the row is frozen.
+    pub has_headers: bool,
+    /// Sheet to write, or the active sheet if `None`.
+    pub sheet: Option<usize>,
+}
+
+impl CsvOptions {
+    /// Comma-separated, double-quoted fields without headers.
+    pub fn new() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            has_headers: false,
+            sheet: None,
+        }
+    }
+
+    /// Set the field delimiter.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set the quote character.
+    pub fn with_quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Set whether the first record holds column names.
+    pub fn with_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Set the sheet to write.
+    pub fn with_sheet(mut self, sheet: usize) -> Self {
+        self.sheet = Some(sheet);
+        self
+    }
+
+    /// Check that the delimiter and quote are distinct ASCII characters
+    /// other than line breaks, as fields are split on characters rather
+    /// than bytes.
+    pub fn validate(&self) -> Result<(), Error> {
+        let usable = |byte: u8| byte.is_ascii() && byte != b'\r' && byte != b'\n';
+        if !usable(self.delimiter) {
+            return Err(Error::InvalidDelimiter(self.delimiter));
+        }
+        if !usable(self.quote) || self.quote == self.delimiter {
+            return Err(Error::InvalidQuote(self.quote));
+        }
+        Ok(())
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read a spreadsheet with a single sheet from CSV.
+pub fn read(data: &[u8], options: &CsvOptions) -> Result<Spreadsheet, Error> {
+    reader::read(data, options)
+}
+
+/// Write one sheet of a spreadsheet to CSV.
+pub fn write(spreadsheet: &Spreadsheet, options: &CsvOptions) -> Result<Vec<u8>, Error> {
+    writer::write(spreadsheet, options)
+}
+
+/// Format errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+
+    #[error("Unterminated quoted field starting on line {0}")]
+    UnterminatedQuote(usize),
+
+    #[error("No sheet at index {0}")]
+    NoSuchSheet(usize),
+
+    #[error("Delimiter {0:#04x} is not an ASCII character other than a line break")]
+    InvalidDelimiter(u8),
+
+    #[error("Quote {0:#04x} is not an ASCII character other than a line break or the delimiter")]
+    InvalidQuote(u8),
+}
@@ -0,0 +1,212 @@
+//! CSV parsing.
+//!
+//! Records are split by a small state machine over the characters, so
+//! quoted fields may hold delimiters, doubled quotes, and line breaks.
+//! Line breaks may be CRLF, LF, or CR. Unquoted fields that read as numbers
+//! become numbers; quoted fields always stay text.
+
+use grid_engine::{Cell, CellRef, CellValue, Spreadsheet};
+
+use crate::{CsvOptions, Error};
+
+/// Parse CSV data into a spreadsheet with a single sheet.
+pub fn read(data: &[u8], options: &CsvOptions) -> Result<Spreadsheet, Error> {
+    options.validate()?;
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    let text = std::str::from_utf8(data)?;
+
+    let mut spreadsheet = Spreadsheet::new();
+    let sheet = spreadsheet.active_mut();
+    for (row, record) in records(text, options)?.into_iter().enumerate() {
+        for (col, field) in record.into_iter().enumerate() {
+            let value = if (row == 0 && options.has_headers) || field.quoted {
+                text_value(field.text)
+            } else {
+                value(field.text)
+            };
+            if !value.is_empty() {
+                sheet.set(CellRef::new(row, col), Cell::with_value(value));
+            }
+        }
+    }
+    if options.has_headers {
+        sheet.frozen_rows = 1;
+    }
+    Ok(spreadsheet)
+}
+
+/// A field of a record.
+#[derive(Debug, Default)]
+struct Field {
+    /// The field's text, unquoted.
+    text: String,
+    /// Whether the field was quoted.
+    quoted: bool,
+}
+
+/// Split text into records of fields, unquoting quoted fields.
+fn records(text: &str, options: &CsvOptions) -> Result<Vec<Vec<Field>>, Error> {
+    let (delimiter, quote) = (options.delimiter as char, options.quote as char);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = Field::default();
+    // Whether the next character starts a field, and whether anything of
+    // the current record has been read.
+    let (mut field_start, mut in_record) = (true, false);
+    let mut line = 1;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        in_record = true;
+        if c == quote && field_start {
+            let start = line;
+            loop {
+                match chars.next() {
+                    None => return Err(Error::UnterminatedQuote(start)),
+                    Some(c) if c == quote => {
+                        if chars.next_if_eq(&quote).is_none() {
+                            break;
+                        }
+                        field.text.push(quote);
+                    }
+                    Some(c) => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        field.text.push(c);
+                    }
+                }
+            }
+            field.quoted = true;
+            field_start = false;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+            field_start = true;
+        } else if c == '\r' || c == '\n' {
+            if c == '\r' {
+                chars.next_if_eq(&'\n');
+            }
+            line += 1;
+            record.push(std::mem::take(&mut field));
+            records.push(std::mem::take(&mut record));
+            (field_start, in_record) = (true, false);
+        } else {
+            // A quote inside an unquoted field is taken literally.
+            field.text.push(c);
+            field_start = false;
+        }
+    }
+    if in_record {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// The value of an unquoted field: a number if it reads as one, otherwise
+/// text.
+fn value(field: String) -> CellValue {
+    match number(&field) {
+        Some(number) => CellValue::Number(number),
+        None => text_value(field),
+    }
+}
+
+/// The number an unquoted field reads as, if any.
+pub(crate) fn number(field: &str) -> Option<f64> {
+    field
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+}
+
+fn text_value(field: String) -> CellValue {
+    if field.is_empty() {
+        CellValue::Empty
+    } else {
+        CellValue::Text(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value_at(spreadsheet: &Spreadsheet, a1: &str) -> CellValue {
+        let cell = spreadsheet.active().get(CellRef::parse(a1).unwrap());
+        cell.map(|cell| cell.value.clone()).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let data = b"name,amount,note\r\n\"Smith, J.\",12.5,\"said \"\"hi\"\"\"\r\nLee,-3,";
+        let spreadsheet = read(data, &CsvOptions::new()).unwrap();
+        assert_eq!(
+            value_at(&spreadsheet, "A2"),
+            CellValue::Text("Smith, J.".into())
+        );
+        assert_eq!(value_at(&spreadsheet, "B2"), CellValue::Number(12.5));
+        assert_eq!(
+            value_at(&spreadsheet, "C2"),
+            CellValue::Text("said \"hi\"".into())
+        );
+        assert_eq!(value_at(&spreadsheet, "B3"), CellValue::Number(-3.0));
+        assert_eq!(value_at(&spreadsheet, "C3"), CellValue::Empty);
+        assert!(matches!(
+            read(b"a,\"open\nfield", &CsvOptions::new()),
+            Err(Error::UnterminatedQuote(1))
+        ));
+    }
+
+    #[test]
+    fn test_embedded_newlines() {
+        let data = "\u{FEFF}id,address\n1,\"12 Main St\nSpringfield\"\n2,Elm\n";
+        let spreadsheet = read(data.as_bytes(), &CsvOptions::new()).unwrap();
+        assert_eq!(
+            value_at(&spreadsheet, "B2"),
+            CellValue::Text("12 Main St\nSpringfield".into())
+        );
+        assert_eq!(value_at(&spreadsheet, "A3"), CellValue::Number(2.0));
+        assert_eq!(
+            spreadsheet.active().used_range().unwrap().1,
+            CellRef::new(2, 1)
+        );
+    }
+
+    #[test]
+    fn test_custom_delimiter_and_headers() {
+        let options = CsvOptions::new()
+            .with_delimiter(b';')
+            .with_quote(b'\'')
+            .with_headers(true);
+        let spreadsheet = read(b"2024;total\n'1;5';7\n", &options).unwrap();
+        // Header names that look like numbers stay text.
+        assert_eq!(value_at(&spreadsheet, "A1"), CellValue::Text("2024".into()));
+        assert_eq!(value_at(&spreadsheet, "A2"), CellValue::Text("1;5".into()));
+        assert_eq!(value_at(&spreadsheet, "B2"), CellValue::Number(7.0));
+        assert_eq!(spreadsheet.active().frozen_rows, 1);
+    }
+
+    #[test]
+    fn test_quoted_numbers_stay_text() {
+        let spreadsheet = read(b"\"007\",007,\"1e3\"\n", &CsvOptions::new()).unwrap();
+        assert_eq!(value_at(&spreadsheet, "A1"), CellValue::Text("007".into()));
+        assert_eq!(value_at(&spreadsheet, "B1"), CellValue::Number(7.0));
+        assert_eq!(value_at(&spreadsheet, "C1"), CellValue::Text("1e3".into()));
+    }
+
+    #[test]
+    fn test_non_ascii_delimiter_refused() {
+        let options = CsvOptions::new().with_delimiter(0xA7);
+        assert!(matches!(
+            read(b"a\xC2\xA7b", &options),
+            Err(Error::InvalidDelimiter(0xA7))
+        ));
+        let options = CsvOptions::new().with_quote(b',');
+        assert!(matches!(
+            read(b"a,b", &options),
+            Err(Error::InvalidQuote(b','))
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+//! CSV generation.
+//!
+//! The sheet is written from A1 to the bottom right of its used range, so
+//! cells keep their positions. Each cell is written as its displayed value,
+//! quoted only where needed, with records ending in CRLF. Text that would
+//! read back as a number is always quoted, so it stays text.
+
+use grid_engine::{CellRef, CellValue, Spreadsheet};
+
+use crate::reader::number;
+use crate::{CsvOptions, Error};
+
+/// Serialize one sheet of a spreadsheet to CSV.
+pub fn write(spreadsheet: &Spreadsheet, options: &CsvOptions) -> Result<Vec<u8>, Error> {
+    options.validate()?;
+    let index = options.sheet.unwrap_or(spreadsheet.active_sheet);
+    let sheet = spreadsheet.sheet(index).ok_or(Error::NoSuchSheet(index))?;
+
+    let mut output = String::new();
+    let Some((_, end)) = sheet.used_range() else {
+        return Ok(Vec::new());
+    };
+    for row in 0..=end.row {
+        for col in 0..=end.col {
+            if col > 0 {
+                output.push(options.delimiter as char);
+            }
+            if let Some(cell) = sheet.get(CellRef::new(row, col)) {
+                let field = cell.value.to_display_string();
+                let text = matches!(cell.value, CellValue::Text(_));
+                push_field(&mut output, &field, text, options);
+            }
+        }
+        output.push_str("\r\n");
+    }
+    Ok(output.into_bytes())
+}
+
+/// Append a field, quoted if it holds a delimiter, quote, or line break, or
+/// if it is `text` that would read back as a number.
+fn push_field(output: &mut String, field: &str, text: bool, options: &CsvOptions) {
+    let (delimiter, quote) = (options.delimiter as char, options.quote as char);
+    let numeric_text = text && number(field).is_some();
+    if !numeric_text && !field.contains([delimiter, quote, '\r', '\n']) {
+        output.push_str(field);
+        return;
+    }
+    output.push(quote);
+    for c in field.chars() {
+        if c == quote {
+            output.push(quote);
+        }
+        output.push(c);
+    }
+    output.push(quote);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read;
+    use grid_engine::Cell;
+
+    fn set(spreadsheet: &mut Spreadsheet, sheet: usize, a1: &str, value: CellValue) {
+        spreadsheet
+            .sheet_mut(sheet)
+            .unwrap()
+            .set(CellRef::parse(a1).unwrap(), Cell::with_value(value));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut spreadsheet = Spreadsheet::new();
+        set(&mut spreadsheet, 0, "A1", CellValue::Text("Item".into()));
+        set(
+            &mut spreadsheet,
+            0,
+            "B1",
+            CellValue::Text("Notes, etc.".into()),
+        );
+        set(&mut spreadsheet, 0, "A2", CellValue::Number(1.25));
+        set(
+            &mut spreadsheet,
+            0,
+            "B2",
+            CellValue::Text("two\nlines \"quoted\"".into()),
+        );
+        set(&mut spreadsheet, 0, "C3", CellValue::Boolean(true));
+
+        let csv = write(&spreadsheet, &CsvOptions::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "Item,\"Notes, etc.\",\r\n1.25,\"two\nlines \"\"quoted\"\"\",\r\n,,TRUE\r\n"
+        );
+
+        let again = read(&csv, &CsvOptions::new()).unwrap();
+        for a1 in ["A1", "B1", "A2", "B2"] {
+            let cell_ref = CellRef::parse(a1).unwrap();
+            assert_eq!(
+                again.active().get(cell_ref).unwrap().value,
+                spreadsheet.active().get(cell_ref).unwrap().value
+            );
+        }
+    }
+
+    #[test]
+    fn test_numeric_text_roundtrip() {
+        let mut spreadsheet = Spreadsheet::new();
+        for (a1, text) in [("A1", "007"), ("B1", "1e3"), ("C1", " 42 "), ("D1", "-0.5")] {
+            set(&mut spreadsheet, 0, a1, CellValue::Text(text.into()));
+        }
+        set(&mut spreadsheet, 0, "E1", CellValue::Number(7.0));
+
+        let csv = write(&spreadsheet, &CsvOptions::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "\"007\",\"1e3\",\" 42 \",\"-0.5\",7\r\n"
+        );
+        let again = read(&csv, &CsvOptions::new()).unwrap();
+        for a1 in ["A1", "B1", "C1", "D1", "E1"] {
+            let cell_ref = CellRef::parse(a1).unwrap();
+            assert_eq!(
+                again.active().get(cell_ref).unwrap().value,
+                spreadsheet.active().get(cell_ref).unwrap().value
+            );
+        }
+    }
+
+    #[test]
+    fn test_writes_chosen_sheet() {
+        let mut spreadsheet = Spreadsheet::new();
+        let second = spreadsheet.add_sheet("Data");
+        set(&mut spreadsheet, 0, "A1", CellValue::Text("first".into()));
+        set(
+            &mut spreadsheet,
+            second,
+            "B1",
+            CellValue::Text("a\tb".into()),
+        );
+
+        let options = CsvOptions::new().with_delimiter(b'\t').with_sheet(second);
+        assert_eq!(write(&spreadsheet, &options).unwrap(), b"\t\"a\tb\"\r\n");
+        assert!(matches!(
+            write(&spreadsheet, &CsvOptions::new().with_sheet(5)),
+            Err(Error::NoSuchSheet(5))
+        ));
+    }
+}
@@ -4,6 +4,13 @@
 
 use wolia_core::Document;
 
+mod writer;
+
+/// WordprocessingML main namespace.
+pub(crate) const NS_W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+/// Office document relationships namespace.
+pub(crate) const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
 /// Read a document from .docx format.
 pub fn read(_data: &[u8]) -> Result<Document, Error> {
     // TODO: Implement OOXML parsing
@@ -11,9 +18,8 @@ pub fn read(_data: &[u8]) -> Result<Document, Error> {
 }
 
 /// Write a document to .docx format.
-pub fn write(_document: &Document) -> Result<Vec<u8>, Error> {
-    // TODO: Implement OOXML generation
-    Ok(Vec::new())
+pub fn write(document: &Document) -> Result<Vec<u8>, Error> {
+    writer::write(document)
 }
 
 /// Format errors.
@@ -31,3 +37,68 @@ pub enum Error {
     #[error("Invalid format")]
     InvalidFormat,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use wolia_core::Node;
+    use wolia_core::style::TextStyle;
+    use wolia_core::text::{Span, Text};
+
+    use super::*;
+
+    /// The text of part `name` of a package.
+    fn part(package: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_links_survive_export() {
+        let link = |url: &str| TextStyle {
+            link: Some(url.to_string()),
+            ..TextStyle::default()
+        };
+        let mut text = Text::new("Read the docs & the FAQ");
+        text.add_span(Span::new(5, 13, link("https://example.com/?a=1&b=2")));
+        text.add_span(Span::new(
+            9,
+            13,
+            TextStyle {
+                font_weight: Some(700),
+                ..TextStyle::default()
+            },
+        ));
+        text.add_span(Span::new(20, 23, link("https://example.com/faq")));
+        let mut document = Document::new();
+        document.root.add_child(Node::heading(1, Text::new("Help")));
+        document.root.add_child(Node::paragraph(text));
+
+        let package = write(&document).unwrap();
+        let body = part(&package, "word/document.xml");
+        assert!(body.contains(r#"<w:pStyle w:val="Heading1"/>"#));
+        // A link split into differently styled runs stays one hyperlink.
+        assert!(body.contains(concat!(
+            r#"<w:hyperlink r:id="rId2"><w:r><w:rPr><w:rStyle w:val="Hyperlink"/></w:rPr>"#,
+            r#"<w:t xml:space="preserve">the </w:t></w:r><w:r><w:rPr><w:rStyle w:val="Hyperlink"/><w:b/></w:rPr>"#,
+            r#"<w:t xml:space="preserve">docs</w:t></w:r></w:hyperlink>"#,
+        )));
+        assert!(body.contains(r#"<w:hyperlink r:id="rId3">"#));
+        assert!(body.contains("&amp; the"));
+
+        let rels = part(&package, "word/_rels/document.xml.rels");
+        assert!(rels.contains(concat!(
+            r#"<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" "#,
+            r#"Target="https://example.com/?a=1&amp;b=2" TargetMode="External"/>"#,
+        )));
+        assert!(rels.contains(r#"Target="https://example.com/faq""#));
+        assert!(part(&package, "[Content_Types].xml").contains("/word/document.xml"));
+    }
+}
@@ -0,0 +1,412 @@
+//! DOCX package generation.
+//!
+//! Produces a minimal WordprocessingML package: content types, package and
+//! document relationships, core properties, a style sheet with the heading
+//! and hyperlink styles, and the document body. Hyperlinks are external
+//! relationships of the body part, one per distinct URL.
+
+use std::io::{Cursor, Write};
+
+use quick_xml::escape::escape;
+use wolia_core::Document;
+use wolia_core::document::Metadata;
+use wolia_core::node::{Node, NodeKind};
+use wolia_core::style::TextStyle;
+use wolia_core::text::Text;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::{Error, NS_R, NS_W};
+
+const XML_DECL: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+const REL_OFFICE_DOCUMENT: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+const REL_CORE_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+const REL_STYLES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles";
+const REL_HYPERLINK: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+
+const CT_DOCUMENT: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml";
+const CT_STYLES: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml";
+const CT_CORE_PROPERTIES: &str = "application/vnd.openxmlformats-package.core-properties+xml";
+
+/// Font code blocks are set in.
+const CODE_FONT: &str = "Courier New";
+
+/// Serialize a document into a `.docx` package.
+pub fn write(document: &Document) -> Result<Vec<u8>, Error> {
+    let mut links = Vec::new();
+    let mut body = String::new();
+    for child in &document.root.children {
+        write_block(&mut body, child, &mut links);
+    }
+
+    let mut package = Package::new();
+    package.part("[Content_Types].xml", &content_types())?;
+    package.part(
+        "_rels/.rels",
+        &relationships(&[
+            ("rId1", REL_OFFICE_DOCUMENT, "word/document.xml", false),
+            ("rId2", REL_CORE_PROPERTIES, "docProps/core.xml", false),
+        ]),
+    )?;
+    package.part("docProps/core.xml", &core_properties(&document.metadata))?;
+    package.part("word/document.xml", &document_xml(&body))?;
+    package.part("word/styles.xml", &styles())?;
+
+    let ids: Vec<String> = (0..links.len()).map(link_rel_id).collect();
+    let mut rels = vec![("rId1", REL_STYLES, "styles.xml", false)];
+    for (id, url) in ids.iter().zip(&links) {
+        rels.push((id, REL_HYPERLINK, url, true));
+    }
+    package.part("word/_rels/document.xml.rels", &relationships(&rels))?;
+
+    package.finish()
+}
+
+/// A ZIP archive being filled with package parts.
+struct Package {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+    options: SimpleFileOptions,
+}
+
+impl Package {
+    fn new() -> Self {
+        Self {
+            zip: ZipWriter::new(Cursor::new(Vec::new())),
+            options: SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated),
+        }
+    }
+
+    /// Add a part to the package.
+    fn part(&mut self, name: &str, content: &str) -> Result<(), Error> {
+        self.zip.start_file(name, self.options)?;
+        self.zip.write_all(content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Finish the archive and return its bytes.
+    fn finish(self) -> Result<Vec<u8>, Error> {
+        Ok(self.zip.finish()?.into_inner())
+    }
+}
+
+fn link_rel_id(index: usize) -> String {
+    // rId1 is taken by the style sheet.
+    format!("rId{}", index + 2)
+}
+
+fn content_types() -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#);
+    xml.push_str(r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#);
+    xml.push_str(r#"<Default Extension="xml" ContentType="application/xml"/>"#);
+    for (part, content_type) in [
+        ("/word/document.xml", CT_DOCUMENT),
+        ("/word/styles.xml", CT_STYLES),
+        ("/docProps/core.xml", CT_CORE_PROPERTIES),
+    ] {
+        xml.push_str(&format!(
+            r#"<Override PartName="{}" ContentType="{}"/>"#,
+            part, content_type
+        ));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+/// Relationships given as (id, type, target, external).
+fn relationships(rels: &[(&str, &str, &str, bool)]) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(
+        r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    );
+    for (id, kind, target, external) in rels {
+        xml.push_str(&format!(
+            r#"<Relationship Id="{}" Type="{}" Target="{}"{}/>"#,
+            id,
+            kind,
+            escape(*target),
+            if *external {
+                r#" TargetMode="External""#
+            } else {
+                ""
+            }
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn core_properties(metadata: &Metadata) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(concat!(
+        r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" "#,
+        r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" "#,
+        r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+    ));
+    if let Some(title) = &metadata.title {
+        xml.push_str(&format!("<dc:title>{}</dc:title>", escape(title)));
+    }
+    if let Some(author) = &metadata.author {
+        xml.push_str(&format!("<dc:creator>{}</dc:creator>", escape(author)));
+    }
+    if let Some(description) = &metadata.description {
+        xml.push_str(&format!(
+            "<dc:description>{}</dc:description>",
+            escape(description)
+        ));
+    }
+    xml.push_str("</cp:coreProperties>");
+    xml
+}
+
+fn document_xml(body: &str) -> String {
+    format!(
+        r#"{}<w:document xmlns:w="{}" xmlns:r="{}"><w:body>{}<w:sectPr/></w:body></w:document>"#,
+        XML_DECL, NS_W, NS_R, body
+    )
+}
+
+fn styles() -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(&format!(r#"<w:styles xmlns:w="{}">"#, NS_W));
+    xml.push_str(concat!(
+        r#"<w:style w:type="paragraph" w:default="1" w:styleId="Normal">"#,
+        r#"<w:name w:val="Normal"/><w:qFormat/></w:style>"#,
+    ));
+    for (level, size) in (1..=6).zip([32, 26, 24, 22, 22, 22]) {
+        xml.push_str(&format!(
+            concat!(
+                r#"<w:style w:type="paragraph" w:styleId="Heading{0}">"#,
+                r#"<w:name w:val="heading {0}"/><w:basedOn w:val="Normal"/>"#,
+                r#"<w:next w:val="Normal"/><w:qFormat/>"#,
+                r#"<w:pPr><w:keepNext/><w:spacing w:before="240" w:after="60"/>"#,
+                r#"<w:outlineLvl w:val="{1}"/></w:pPr>"#,
+                r#"<w:rPr><w:b/><w:sz w:val="{2}"/></w:rPr></w:style>"#,
+            ),
+            level,
+            level - 1,
+            size
+        ));
+    }
+    xml.push_str(concat!(
+        r#"<w:style w:type="character" w:styleId="Hyperlink">"#,
+        r#"<w:name w:val="Hyperlink"/><w:rPr><w:color w:val="0563C1"/>"#,
+        r#"<w:u w:val="single"/></w:rPr></w:style>"#,
+    ));
+    xml.push_str("</w:styles>");
+    xml
+}
+
+/// Write a block node, gathering the URLs it links to in `links`.
+fn write_block(xml: &mut String, node: &Node, links: &mut Vec<String>) {
+    match &node.kind {
+        NodeKind::Paragraph(text) => write_paragraph(xml, text, None, links),
+        NodeKind::Heading { level, text } => {
+            let style = format!("Heading{}", level.clamp(&1, &6));
+            write_paragraph(xml, text, Some(&style), links);
+        }
+        NodeKind::CodeBlock { code, .. } => {
+            for line in code.split('\n') {
+                xml.push_str(&format!(
+                    r#"<w:p><w:r><w:rPr><w:rFonts w:ascii="{0}" w:hAnsi="{0}"/></w:rPr>"#,
+                    CODE_FONT
+                ));
+                write_text(xml, line);
+                xml.push_str("</w:r></w:p>");
+            }
+        }
+        NodeKind::Table { .. } => write_table(xml, node, links),
+        NodeKind::PageBreak => xml.push_str(r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#),
+        NodeKind::HorizontalRule => xml.push_str(concat!(
+            r#"<w:p><w:pPr><w:pBdr><w:bottom w:val="single" w:sz="6" w:space="1" "#,
+            r#"w:color="auto"/></w:pBdr></w:pPr></w:p>"#,
+        )),
+        // Images need media parts, and custom content has no Word
+        // equivalent; both are left out.
+        NodeKind::Image { .. } | NodeKind::Custom { .. } => {}
+        _ => {
+            for child in &node.children {
+                write_block(xml, child, links);
+            }
+        }
+    }
+}
+
+fn write_table(xml: &mut String, table: &Node, links: &mut Vec<String>) {
+    xml.push_str(concat!(
+        r#"<w:tbl><w:tblPr><w:tblW w:w="0" w:type="auto"/><w:tblBorders>"#,
+        r#"<w:top w:val="single" w:sz="4"/><w:left w:val="single" w:sz="4"/>"#,
+        r#"<w:bottom w:val="single" w:sz="4"/><w:right w:val="single" w:sz="4"/>"#,
+        r#"<w:insideH w:val="single" w:sz="4"/><w:insideV w:val="single" w:sz="4"/>"#,
+        r#"</w:tblBorders></w:tblPr>"#,
+    ));
+    for row in &table.children {
+        if !matches!(row.kind, NodeKind::TableRow) {
+            continue;
+        }
+        xml.push_str("<w:tr>");
+        for cell in &row.children {
+            let NodeKind::TableCell { col_span, .. } = cell.kind else {
+                continue;
+            };
+            xml.push_str("<w:tc>");
+            if col_span > 1 {
+                xml.push_str(&format!(
+                    r#"<w:tcPr><w:gridSpan w:val="{}"/></w:tcPr>"#,
+                    col_span
+                ));
+            }
+            let start = xml.len();
+            for child in &cell.children {
+                write_block(xml, child, links);
+            }
+            // Every cell must end with a paragraph.
+            if !xml[start..].ends_with("</w:p>") {
+                xml.push_str("<w:p/>");
+            }
+            xml.push_str("</w:tc>");
+        }
+        xml.push_str("</w:tr>");
+    }
+    xml.push_str("</w:tbl>");
+}
+
+/// Write text as paragraphs, one per line, splitting runs at span
+/// boundaries and wrapping linked runs in hyperlinks.
+fn write_paragraph(xml: &mut String, text: &Text, style: Option<&str>, links: &mut Vec<String>) {
+    let mut line_start = 0;
+    for line in text.content.split('\n') {
+        let line_end = line_start + line.len();
+        xml.push_str("<w:p>");
+        if let Some(style) = style {
+            xml.push_str(&format!(r#"<w:pPr><w:pStyle w:val="{}"/></w:pPr>"#, style));
+        }
+
+        let mut boundaries = vec![line_start, line_end];
+        for span in &text.spans {
+            for offset in [span.start, span.end] {
+                if offset > line_start && offset < line_end {
+                    boundaries.push(offset);
+                }
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut open_link: Option<String> = None;
+        for window in boundaries.windows(2) {
+            let (start, end) = (window[0], window[1]);
+            let Some(segment) = text.content.get(start..end) else {
+                continue;
+            };
+            let mut run_style = TextStyle::default();
+            for span in &text.spans {
+                if span.start <= start && span.end >= end {
+                    run_style.merge(&span.style);
+                }
+            }
+            if run_style.link != open_link {
+                if open_link.is_some() {
+                    xml.push_str("</w:hyperlink>");
+                }
+                if let Some(url) = &run_style.link {
+                    let index = match links.iter().position(|known| known == url) {
+                        Some(index) => index,
+                        None => {
+                            links.push(url.clone());
+                            links.len() - 1
+                        }
+                    };
+                    xml.push_str(&format!(r#"<w:hyperlink r:id="{}">"#, link_rel_id(index)));
+                }
+                open_link = run_style.link.clone();
+            }
+            xml.push_str("<w:r>");
+            write_run_properties(xml, &run_style);
+            write_text(xml, segment);
+            xml.push_str("</w:r>");
+        }
+        if open_link.is_some() {
+            xml.push_str("</w:hyperlink>");
+        }
+
+        xml.push_str("</w:p>");
+        line_start = line_end + 1;
+    }
+}
+
+fn write_text(xml: &mut String, text: &str) {
+    xml.push_str(&format!(
+        r#"<w:t xml:space="preserve">{}</w:t>"#,
+        escape(text)
+    ));
+}
+
+fn write_run_properties(xml: &mut String, style: &TextStyle) {
+    let mut properties = String::new();
+    if style.link.is_some() {
+        properties.push_str(r#"<w:rStyle w:val="Hyperlink"/>"#);
+    }
+    if let Some(family) = &style.font_family {
+        properties.push_str(&format!(
+            r#"<w:rFonts w:ascii="{0}" w:hAnsi="{0}"/>"#,
+            escape(family)
+        ));
+    }
+    if let Some(weight) = style.font_weight {
+        properties.push_str(if weight >= 600 {
+            "<w:b/>"
+        } else {
+            r#"<w:b w:val="0"/>"#
+        });
+    }
+    if let Some(italic) = style.italic {
+        properties.push_str(if italic {
+            "<w:i/>"
+        } else {
+            r#"<w:i w:val="0"/>"#
+        });
+    }
+    if let Some(strike) = style.strikethrough {
+        properties.push_str(if strike {
+            "<w:strike/>"
+        } else {
+            r#"<w:strike w:val="0"/>"#
+        });
+    }
+    if let Some([r, g, b, _]) = style.color {
+        properties.push_str(&format!(
+            r#"<w:color w:val="{:02X}{:02X}{:02X}"/>"#,
+            r, g, b
+        ));
+    }
+    if let Some(size) = style.font_size {
+        // Sizes are in half-points.
+        properties.push_str(&format!(
+            r#"<w:sz w:val="{}"/>"#,
+            (size * 2.0).round() as i64
+        ));
+    }
+    if let Some(underline) = style.underline {
+        properties.push_str(if underline {
+            r#"<w:u w:val="single"/>"#
+        } else {
+            r#"<w:u w:val="none"/>"#
+        });
+    }
+    if style.superscript == Some(true) {
+        properties.push_str(r#"<w:vertAlign w:val="superscript"/>"#);
+    } else if style.subscript == Some(true) {
+        properties.push_str(r#"<w:vertAlign w:val="subscript"/>"#);
+    }
+    if !properties.is_empty() {
+        xml.push_str(&format!("<w:rPr>{}</w:rPr>", properties));
+    }
+}
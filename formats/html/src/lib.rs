@@ -0,0 +1,30 @@
+//! # HTML Format
+//!
+//! HTML import/export for Wolia documents.
+
+use wolia_core::Document;
+
+pub use self::writer::HtmlWriter;
+
+mod reader;
+mod writer;
+
+/// Font family used to mark inline code.
+pub const CODE_FONT: &str = "monospace";
+
+/// Read a document from an HTML page or fragment.
+pub fn read(data: &str) -> Result<Document, Error> {
+    reader::read(data)
+}
+
+/// Export a document to a standalone HTML page.
+pub fn write(document: &Document) -> Result<String, Error> {
+    HtmlWriter::new().write(document)
+}
+
+/// Format errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Parse error: {0}")]
+    Parse(String),
+}
@@ -0,0 +1,572 @@
+//! HTML parsing.
+//!
+//! The page is parsed with an HTML5 parser and its element tree walked to
+//! build core document nodes. Block containers (quotes, lists, items,
+//! tables) are kept on a stack; text and inline elements are accumulated
+//! into the currently open paragraph or heading, with whitespace collapsed
+//! as a browser would. Elements without a counterpart in the document
+//! model contribute their text.
+
+use scraper::{ElementRef, Html};
+use wolia_core::style::TextStyle;
+use wolia_core::text::{Span, Text};
+use wolia_core::{Document, Node, NodeKind};
+
+use crate::{CODE_FONT, Error};
+
+/// Parse an HTML page or fragment into a document.
+pub fn read(source: &str) -> Result<Document, Error> {
+    let html = Html::parse_document(source);
+    let root = html.root_element();
+    let find = |name| {
+        root.descendants()
+            .filter_map(ElementRef::wrap)
+            .find(|element| element.value().name() == name)
+    };
+    let mut document = Document::new();
+    document.metadata.title = find("title")
+        .map(|title| collapse(&title.text().collect::<String>()))
+        .filter(|title| !title.is_empty());
+
+    let body = find("body").unwrap_or(root);
+    let mut builder = Builder::new();
+    builder.children(body, &TextStyle::default());
+    document.root = builder.finish();
+    Ok(document)
+}
+
+/// Which text-bearing block is being filled.
+enum InlineKind {
+    Paragraph,
+    Heading(u8),
+}
+
+/// An open paragraph or heading.
+struct Inline {
+    kind: InlineKind,
+    text: Text,
+    /// Whether it comes from an element, and so is kept even when empty.
+    explicit: bool,
+}
+
+struct Builder {
+    /// Open container nodes; the first entry is the document root.
+    containers: Vec<Node>,
+    /// Open text block, if any.
+    inline: Option<Inline>,
+    /// Whether whitespace was skipped since the last character, to be
+    /// written as a single space before the next one.
+    space: bool,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            containers: vec![Node::root()],
+            inline: None,
+            space: false,
+        }
+    }
+
+    fn finish(mut self) -> Node {
+        self.end_inline();
+        while self.containers.len() > 1 {
+            self.close();
+        }
+        self.containers.pop().unwrap_or_else(Node::root)
+    }
+
+    fn children(&mut self, parent: ElementRef, style: &TextStyle) {
+        for child in parent.children() {
+            if let scraper::Node::Text(text) = child.value() {
+                self.text(text, style);
+            } else if let Some(element) = ElementRef::wrap(child) {
+                self.element(element, style);
+            }
+        }
+    }
+
+    fn element(&mut self, node: ElementRef, style: &TextStyle) {
+        let element = node.value();
+        let mut style = style.clone();
+        if let Some(css) = element.attr("style") {
+            apply_css(&mut style, css);
+        }
+
+        match element.name() {
+            "script" | "style" | "template" | "noscript" | "head" | "title" | "meta" | "link" => {}
+            "p" => {
+                self.begin_inline(InlineKind::Paragraph, true);
+                self.children(node, &style);
+                self.end_inline();
+            }
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = name.as_bytes()[1] - b'0';
+                self.begin_inline(InlineKind::Heading(level), true);
+                self.children(node, &style);
+                self.end_inline();
+            }
+            "blockquote" => self.container(NodeKind::BlockQuote, node, &style),
+            "ul" | "menu" => self.container(NodeKind::List { ordered: false }, node, &style),
+            "ol" => self.container(NodeKind::List { ordered: true }, node, &style),
+            "li" => self.container(NodeKind::ListItem, node, &style),
+            "table" => self.container(NodeKind::Table { rows: 0, cols: 0 }, node, &style),
+            "tr" => self.container(NodeKind::TableRow, node, &style),
+            "td" | "th" => {
+                let span = |name| {
+                    element
+                        .attr(name)
+                        .and_then(|value: &str| value.trim().parse().ok())
+                        .filter(|&span: &usize| span > 0)
+                        .unwrap_or(1)
+                };
+                let kind = NodeKind::TableCell {
+                    col_span: span("colspan"),
+                    row_span: span("rowspan"),
+                };
+                self.container(kind, node, &style);
+            }
+            "pre" => {
+                let code: String = node.text().collect();
+                let language = node
+                    .descendants()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|element| element.value().name() == "code")
+                    .flat_map(|code| code.value().classes())
+                    .find_map(|class| class.strip_prefix("language-"))
+                    .map(str::to_string);
+                // A newline right after the start tag is not part of the
+                // content.
+                let code = code.strip_prefix('\n').unwrap_or(&code).to_string();
+                self.block(NodeKind::CodeBlock { language, code });
+            }
+            "img" => {
+                let src = element.attr("src").unwrap_or("").to_string();
+                let alt = element.attr("alt").map(str::to_string);
+                self.block(NodeKind::Image { src, alt });
+            }
+            "hr" => self.block(NodeKind::HorizontalRule),
+            "br" => {
+                let inline = self.inline();
+                inline.text.content.push('\n');
+                self.space = false;
+            }
+            "div" | "section" | "article" | "main" | "header" | "footer" | "nav" | "aside"
+            | "figure" | "figcaption" | "address" | "center" | "dl" | "dt" | "dd" => {
+                self.end_inline();
+                self.children(node, &style);
+                self.end_inline();
+                if element.attr("style").is_some_and(breaks_page) {
+                    self.block(NodeKind::PageBreak);
+                }
+            }
+            name => {
+                match name {
+                    "b" | "strong" => style.font_weight = Some(700),
+                    "i" | "em" | "cite" | "dfn" | "var" => style.italic = Some(true),
+                    "u" | "ins" => style.underline = Some(true),
+                    "s" | "strike" | "del" => style.strikethrough = Some(true),
+                    "sup" => style.superscript = Some(true),
+                    "sub" => style.subscript = Some(true),
+                    "code" | "kbd" | "samp" | "tt" => {
+                        style.font_family = Some(CODE_FONT.to_string())
+                    }
+                    "a" => {
+                        if let Some(href) = element.attr("href") {
+                            style.link = Some(href.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+                self.children(node, &style);
+            }
+        }
+    }
+
+    /// Append text, collapsing whitespace.
+    fn text(&mut self, text: &str, style: &TextStyle) {
+        if !text.contains(|c: char| !c.is_ascii_whitespace()) {
+            // Whitespace between blocks starts nothing.
+            self.space |= !text.is_empty() && self.inline.is_some();
+            return;
+        }
+
+        let mut space = self.space;
+        let inline = self.inline();
+        let content = &mut inline.text.content;
+        let mut start = None;
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                space = true;
+                continue;
+            }
+            if space && !content.is_empty() && !content.ends_with('\n') {
+                content.push(' ');
+            }
+            space = false;
+            start.get_or_insert(content.len());
+            content.push(c);
+        }
+        let end = content.len();
+
+        if let Some(start) = start.filter(|_| *style != TextStyle::default()) {
+            // Runs split only by markup carry on the same span.
+            let spans = &mut inline.text.spans;
+            match spans.last_mut() {
+                Some(last) if last.end == start && last.style == *style => last.end = end,
+                _ => spans.push(Span::new(start, end, style.clone())),
+            }
+        }
+        self.space = space;
+    }
+
+    /// The open paragraph or heading, starting a paragraph if there is
+    /// none.
+    fn inline(&mut self) -> &mut Inline {
+        self.inline.get_or_insert_with(|| Inline {
+            kind: InlineKind::Paragraph,
+            text: Text::empty(),
+            explicit: false,
+        })
+    }
+
+    fn begin_inline(&mut self, kind: InlineKind, explicit: bool) {
+        self.end_inline();
+        self.inline = Some(Inline {
+            kind,
+            text: Text::empty(),
+            explicit,
+        });
+    }
+
+    fn end_inline(&mut self) {
+        self.space = false;
+        let Some(mut inline) = self.inline.take() else {
+            return;
+        };
+        // Trailing line breaks are not part of the content.
+        let trimmed = inline.text.content.trim_end_matches('\n').len();
+        inline.text.content.truncate(trimmed);
+        for span in &mut inline.text.spans {
+            span.end = span.end.min(trimmed);
+        }
+        inline.text.spans.retain(|span| span.start < span.end);
+
+        if inline.text.content.is_empty() && !inline.explicit {
+            return;
+        }
+        let node = match inline.kind {
+            InlineKind::Paragraph => Node::paragraph(inline.text),
+            InlineKind::Heading(level) => Node::heading(level, inline.text),
+        };
+        self.add(node);
+    }
+
+    /// Add a leaf block.
+    fn block(&mut self, kind: NodeKind) {
+        self.end_inline();
+        self.add(Node::new(kind));
+    }
+
+    /// Add a container and everything in it.
+    fn container(&mut self, kind: NodeKind, node: ElementRef, style: &TextStyle) {
+        self.end_inline();
+        self.containers.push(Node::new(kind));
+        self.children(node, style);
+        self.end_inline();
+        self.close();
+    }
+
+    /// Close the innermost container and add it to its parent.
+    fn close(&mut self) {
+        let Some(mut node) = self.containers.pop() else {
+            return;
+        };
+        if let NodeKind::Table { rows, cols } = &mut node.kind {
+            *rows = node.children.len();
+            *cols = node
+                .children
+                .iter()
+                .map(|row| {
+                    row.children
+                        .iter()
+                        .map(|cell| match cell.kind {
+                            NodeKind::TableCell { col_span, .. } => col_span,
+                            _ => 1,
+                        })
+                        .sum()
+                })
+                .max()
+                .unwrap_or(0);
+        }
+        self.add(node);
+    }
+
+    fn add(&mut self, node: Node) {
+        if let Some(parent) = self.containers.last_mut() {
+            parent.add_child(node);
+        }
+    }
+}
+
+fn collapse(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether a `style` attribute starts a new page after the element.
+fn breaks_page(css: &str) -> bool {
+    let css = css.replace(' ', "").to_ascii_lowercase();
+    css.contains("break-after:page") || css.contains("page-break-after:always")
+}
+
+/// Apply the declarations of a `style` attribute that have a text style
+/// counterpart.
+fn apply_css(style: &mut TextStyle, css: &str) {
+    for declaration in css.split(';') {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let property = property.trim().to_ascii_lowercase();
+        let value = value.trim().trim_end_matches("!important").trim();
+        let lower = value.to_ascii_lowercase();
+        match property.as_str() {
+            "color" => style.color = parse_color(&lower).or(style.color),
+            "background-color" | "background" => {
+                style.background = parse_color(&lower).or(style.background)
+            }
+            "font-family" => {
+                let family = value.split(',').next().unwrap_or("");
+                let family = family.trim().trim_matches(['\'', '"']);
+                if !family.is_empty() {
+                    style.font_family = Some(family.to_string());
+                }
+            }
+            "font-size" => style.font_size = parse_length(&lower).or(style.font_size),
+            "font-weight" => {
+                style.font_weight = match lower.as_str() {
+                    "bold" | "bolder" => Some(700),
+                    "normal" | "lighter" => None,
+                    weight => weight
+                        .parse()
+                        .ok()
+                        .filter(|&w| w >= 600)
+                        .or(style.font_weight),
+                }
+            }
+            "font-style" => style.italic = Some(lower == "italic" || lower == "oblique"),
+            "text-decoration" | "text-decoration-line" => {
+                style.underline = Some(lower.contains("underline"));
+                style.strikethrough = Some(lower.contains("line-through"));
+            }
+            "font-variant" => style.small_caps = Some(lower.contains("small-caps")),
+            "letter-spacing" => {
+                style.letter_spacing = lower
+                    .strip_suffix("em")
+                    .and_then(|em| em.trim().parse().ok())
+                    .or(style.letter_spacing)
+            }
+            "vertical-align" => {
+                style.superscript = Some(lower == "super");
+                style.subscript = Some(lower == "sub");
+            }
+            _ => {}
+        }
+    }
+    // Explicitly normal text is the same as unstyled text.
+    for flag in [
+        &mut style.italic,
+        &mut style.underline,
+        &mut style.strikethrough,
+        &mut style.small_caps,
+        &mut style.superscript,
+        &mut style.subscript,
+    ] {
+        if *flag == Some(false) {
+            *flag = None;
+        }
+    }
+}
+
+/// A CSS length in points.
+fn parse_length(value: &str) -> Option<f32> {
+    let (number, scale) = if let Some(pt) = value.strip_suffix("pt") {
+        (pt, 1.0)
+    } else if let Some(px) = value.strip_suffix("px") {
+        (px, 0.75)
+    } else {
+        return None;
+    };
+    number.trim().parse::<f32>().ok().map(|n| n * scale)
+}
+
+/// A CSS color as RGBA.
+fn parse_color(value: &str) -> Option<[u8; 4]> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+        return match hex.len() {
+            3 => Some([digit(0, 1)? * 17, digit(1, 1)? * 17, digit(2, 1)? * 17, 255]),
+            6 => Some([digit(0, 2)?, digit(2, 2)?, digit(4, 2)?, 255]),
+            8 => Some([digit(0, 2)?, digit(2, 2)?, digit(4, 2)?, digit(6, 2)?]),
+            _ => None,
+        };
+    }
+    if let Some(arguments) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let parts: Vec<&str> = arguments
+            .split([',', ' ', '/'])
+            .filter(|p| !p.is_empty())
+            .collect();
+        let channel = |part: &str| part.parse::<f32>().ok().map(|c| c.clamp(0.0, 255.0) as u8);
+        let alpha = match parts.get(3) {
+            Some(alpha) => (alpha.parse::<f32>().ok()?.clamp(0.0, 1.0) * 255.0).round() as u8,
+            None => 255,
+        };
+        return Some([
+            channel(parts.first()?)?,
+            channel(parts.get(1)?)?,
+            channel(parts.get(2)?)?,
+            alpha,
+        ]);
+    }
+    match value {
+        "black" => Some([0, 0, 0, 255]),
+        "white" => Some([255, 255, 255, 255]),
+        "red" => Some([255, 0, 0, 255]),
+        "green" => Some([0, 128, 0, 255]),
+        "blue" => Some([0, 0, 255, 255]),
+        "yellow" => Some([255, 255, 0, 255]),
+        "gray" | "grey" => Some([128, 128, 128, 255]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HtmlWriter;
+
+    fn text(node: &Node) -> &Text {
+        match &node.kind {
+            NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } => text,
+            kind => panic!("expected text, got {:?}", kind),
+        }
+    }
+
+    fn bold() -> TextStyle {
+        TextStyle {
+            font_weight: Some(700),
+            ..TextStyle::default()
+        }
+    }
+
+    #[test]
+    fn test_reads_blocks() {
+        let document = read(
+            "<html><head><title>Notes</title><style>p { color: red }</style></head>\
+             <body><h1>Title</h1>\
+             <p>Some <b>bold</b> and <strong>strong</strong>   text,\n  <i>italic</i>.</p>\
+             <script>alert(1)</script>\
+             <pre><code class=\"language-rust\">fn main() {\n    run();\n}</code></pre>\
+             <blink>Old <em>markup</em></blink></body></html>",
+        )
+        .unwrap();
+        assert_eq!(document.metadata.title.as_deref(), Some("Notes"));
+
+        let children = &document.root.children;
+        assert_eq!(children.len(), 4);
+        assert!(matches!(
+            children[0].kind,
+            NodeKind::Heading { level: 1, .. }
+        ));
+
+        let paragraph = text(&children[1]);
+        assert_eq!(paragraph.content, "Some bold and strong text, italic.");
+        assert_eq!(paragraph.spans.len(), 3);
+        assert_eq!((paragraph.spans[0].start, paragraph.spans[0].end), (5, 9));
+        assert_eq!(paragraph.spans[0].style, bold());
+        assert_eq!(paragraph.spans[1].style, bold());
+        assert_eq!(paragraph.spans[2].style.italic, Some(true));
+
+        let NodeKind::CodeBlock { language, code } = &children[2].kind else {
+            panic!("expected code block");
+        };
+        assert_eq!(language.as_deref(), Some("rust"));
+        assert_eq!(code, "fn main() {\n    run();\n}");
+
+        // An unknown element keeps its text.
+        assert_eq!(text(&children[3]).content, "Old markup");
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let document = read(
+            "<ul>\n  <li>one\n    <ol><li>inner</li></ol>\n  </li>\n  <li><p>two</p></li>\n</ul>",
+        )
+        .unwrap();
+        let list = &document.root.children[0];
+        assert!(matches!(list.kind, NodeKind::List { ordered: false }));
+        assert_eq!(list.children.len(), 2);
+
+        let first = &list.children[0];
+        assert_eq!(text(&first.children[0]).content, "one");
+        let nested = &first.children[1];
+        assert!(matches!(nested.kind, NodeKind::List { ordered: true }));
+        assert_eq!(text(&nested.children[0].children[0]).content, "inner");
+        assert_eq!(text(&list.children[1].children[0]).content, "two");
+    }
+
+    #[test]
+    fn test_pasted_fragment() {
+        // What a browser puts on the clipboard when copying a selection.
+        let document = read(
+            "<meta charset='utf-8'><span style=\"color: rgb(32, 33, 36); \
+             font-family: Arial, sans-serif; font-size: 16px; font-weight: 700;\">Hello</span>\
+             <span style=\"color: rgb(32, 33, 36); font-family: Arial, sans-serif; \
+             font-size: 16px; font-weight: 400;\"> world</span><br>\
+             <span style=\"text-decoration: underline;\">next line</span>",
+        )
+        .unwrap();
+        assert_eq!(document.root.children.len(), 1);
+        let pasted = text(&document.root.children[0]);
+        assert_eq!(pasted.content, "Hello world\nnext line");
+
+        let hello = &pasted.spans[0];
+        assert_eq!((hello.start, hello.end), (0, 5));
+        assert_eq!(hello.style.font_weight, Some(700));
+        assert_eq!(hello.style.color, Some([32, 33, 36, 255]));
+        assert_eq!(hello.style.font_family.as_deref(), Some("Arial"));
+        assert_eq!(hello.style.font_size, Some(12.0));
+
+        let world = &pasted.spans[1];
+        assert_eq!((world.start, world.end), (6, 11));
+        assert_eq!(world.style.font_weight, None);
+        assert_eq!(pasted.spans[2].style.underline, Some(true));
+    }
+
+    #[test]
+    fn test_roundtrip_through_writer() {
+        let source = read(
+            "<h2>Report</h2>\
+             <p>A <a href=\"https://example.com\"><em>link</em></a> &amp; <code>code</code></p>\
+             <table><tr><th>a</th><th>b</th></tr><tr><td colspan=\"2\">wide</td></tr></table>\
+             <hr>",
+        )
+        .unwrap();
+        let html = HtmlWriter::new()
+            .with_standalone(false)
+            .write(&source)
+            .unwrap();
+        let again = HtmlWriter::new()
+            .with_standalone(false)
+            .write(&read(&html).unwrap())
+            .unwrap();
+        assert_eq!(html, again);
+        assert!(html.contains("<a href=\"https://example.com\"><em>link</em></a> &amp; "));
+
+        let table = &source.root.children[2];
+        assert!(matches!(table.kind, NodeKind::Table { rows: 2, cols: 2 }));
+    }
+}
@@ -0,0 +1,590 @@
+//! HTML generation.
+//!
+//! Each block node maps to its HTML element. Inline formatting is rebuilt
+//! from text spans: the text is cut wherever a span starts or ends, and
+//! elements are closed and reopened as the styles change, so they nest
+//! properly even where spans overlap.
+
+use std::collections::HashMap;
+
+use wolia_core::style::TextStyle;
+use wolia_core::text::Text;
+use wolia_core::{Document, Node, NodeKind};
+
+use crate::{CODE_FONT, Error};
+
+/// Writes documents as HTML.
+#[derive(Debug, Clone)]
+pub struct HtmlWriter {
+    /// Whether to write a whole page rather than just the body content.
+    standalone: bool,
+    /// Image data to embed, by the `src` of the images using it.
+    images: HashMap<String, Vec<u8>>,
+}
+
+impl HtmlWriter {
+    /// Create a writer for standalone pages.
+    pub fn new() -> Self {
+        Self {
+            standalone: true,
+            images: HashMap::new(),
+        }
+    }
+
+    /// Write a whole page with a head and body, or only the content for
+    /// pasting into another page.
+    pub fn with_standalone(mut self, standalone: bool) -> Self {
+        self.standalone = standalone;
+        self
+    }
+
+    /// Embed `data` as a data URI for images whose source is `src`. Images
+    /// in formats browsers cannot show keep their source.
+    pub fn with_image(mut self, src: impl Into<String>, data: Vec<u8>) -> Self {
+        self.images.insert(src.into(), data);
+        self
+    }
+
+    /// Serialize a document to HTML.
+    pub fn write(&self, document: &Document) -> Result<String, Error> {
+        let mut body = String::new();
+        self.blocks(&document.root.children, &mut body);
+        if !self.standalone {
+            return Ok(body);
+        }
+
+        let title = document.metadata.title.as_deref().unwrap_or("");
+        Ok(format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape_text(title),
+            body
+        ))
+    }
+
+    fn blocks(&self, nodes: &[Node], output: &mut String) {
+        for node in nodes {
+            self.block(node, output);
+        }
+    }
+
+    fn block(&self, node: &Node, output: &mut String) {
+        match &node.kind {
+            NodeKind::Paragraph(text) => {
+                output.push_str(&format!("<p>{}</p>\n", render_inline(text)));
+            }
+            NodeKind::Heading { level, text } => {
+                let level = (*level).clamp(1, 6);
+                output.push_str(&format!(
+                    "<h{}>{}</h{}>\n",
+                    level,
+                    render_inline(text),
+                    level
+                ));
+            }
+            NodeKind::BlockQuote => self.container("blockquote", node, output),
+            NodeKind::Section => self.container("section", node, output),
+            NodeKind::List { ordered } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                output.push_str(&format!("<{}>\n", tag));
+                for item in &node.children {
+                    self.list_item(item, output);
+                }
+                output.push_str(&format!("</{}>\n", tag));
+            }
+            NodeKind::ListItem => self.list_item(node, output),
+            NodeKind::Table { .. } => {
+                output.push_str("<table>\n");
+                for row in &node.children {
+                    self.table_row(row, output);
+                }
+                output.push_str("</table>\n");
+            }
+            NodeKind::TableRow => self.table_row(node, output),
+            NodeKind::TableCell { .. } => self.table_cell(node, output),
+            NodeKind::Image { src, alt } => {
+                let src = self
+                    .images
+                    .get(src)
+                    .and_then(|data| data_uri(data))
+                    .unwrap_or_else(|| src.clone());
+                output.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\">\n",
+                    escape_attribute(&src),
+                    escape_attribute(alt.as_deref().unwrap_or(""))
+                ));
+            }
+            NodeKind::CodeBlock { language, code } => {
+                let class = language
+                    .as_deref()
+                    .map(|language| format!(" class=\"language-{}\"", escape_attribute(language)))
+                    .unwrap_or_default();
+                output.push_str(&format!(
+                    "<pre><code{}>{}</code></pre>\n",
+                    class,
+                    escape_text(code)
+                ));
+            }
+            NodeKind::HorizontalRule => output.push_str("<hr>\n"),
+            NodeKind::PageBreak => output.push_str("<div style=\"break-after: page\"></div>\n"),
+            NodeKind::Custom { .. } => {}
+            NodeKind::Root => self.blocks(&node.children, output),
+        }
+    }
+
+    fn container(&self, tag: &str, node: &Node, output: &mut String) {
+        output.push_str(&format!("<{}>\n", tag));
+        self.blocks(&node.children, output);
+        output.push_str(&format!("</{}>\n", tag));
+    }
+
+    fn list_item(&self, item: &Node, output: &mut String) {
+        output.push_str("<li>");
+        self.compact(&item.children, output);
+        output.push_str("</li>\n");
+    }
+
+    fn table_row(&self, row: &Node, output: &mut String) {
+        output.push_str("<tr>");
+        for cell in &row.children {
+            self.table_cell(cell, output);
+        }
+        output.push_str("</tr>\n");
+    }
+
+    fn table_cell(&self, cell: &Node, output: &mut String) {
+        output.push_str("<td");
+        if let NodeKind::TableCell { col_span, row_span } = cell.kind {
+            if col_span > 1 {
+                output.push_str(&format!(" colspan=\"{}\"", col_span));
+            }
+            if row_span > 1 {
+                output.push_str(&format!(" rowspan=\"{}\"", row_span));
+            }
+        }
+        output.push('>');
+        self.compact(&cell.children, output);
+        output.push_str("</td>");
+    }
+
+    /// Write the content of a list item or table cell. A lone paragraph
+    /// leading it is written without its `<p>`, as in a tight list.
+    fn compact(&self, children: &[Node], output: &mut String) {
+        let paragraphs = children
+            .iter()
+            .filter(|child| matches!(child.kind, NodeKind::Paragraph(_)))
+            .count();
+        let rest = match children.first() {
+            Some(Node {
+                kind: NodeKind::Paragraph(text),
+                ..
+            }) if paragraphs == 1 => {
+                output.push_str(&render_inline(text));
+                &children[1..]
+            }
+            _ => children,
+        };
+        if !rest.is_empty() {
+            output.push('\n');
+            self.blocks(rest, output);
+        }
+    }
+}
+
+impl Default for HtmlWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Inline elements, in the order they are opened (outermost first).
+#[derive(Debug, Clone, PartialEq)]
+enum Mark {
+    Link(String),
+    /// A `<span>` with these CSS declarations.
+    Style(String),
+    Strong,
+    Emphasis,
+    Underline,
+    Strikethrough,
+    Superscript,
+    Subscript,
+    Code,
+}
+
+impl Mark {
+    fn open(&self) -> String {
+        match self {
+            Mark::Link(url) => format!("<a href=\"{}\">", escape_attribute(url)),
+            Mark::Style(css) => format!("<span style=\"{}\">", escape_attribute(css)),
+            _ => format!("<{}>", self.tag()),
+        }
+    }
+
+    fn close(&self) -> String {
+        format!("</{}>", self.tag())
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Mark::Link(_) => "a",
+            Mark::Style(_) => "span",
+            Mark::Strong => "strong",
+            Mark::Emphasis => "em",
+            Mark::Underline => "u",
+            Mark::Strikethrough => "s",
+            Mark::Superscript => "sup",
+            Mark::Subscript => "sub",
+            Mark::Code => "code",
+        }
+    }
+}
+
+/// Render text with its spans as inline HTML.
+fn render_inline(text: &Text) -> String {
+    let content = &text.content;
+    let mut boundaries = vec![0, content.len()];
+    for span in &text.spans {
+        boundaries.push(span.start.min(content.len()));
+        boundaries.push(span.end.min(content.len()));
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut output = String::new();
+    let mut open: Vec<Mark> = Vec::new();
+    for window in boundaries.windows(2) {
+        let Some(segment) = content.get(window[0]..window[1]) else {
+            continue;
+        };
+
+        let mut style = TextStyle::default();
+        for span in &text.spans {
+            if span.start <= window[0] && span.end >= window[1] {
+                overlay(&mut style, &span.style);
+            }
+        }
+        let marks = marks_for(&style);
+
+        // Close everything above the first open mark that no longer applies.
+        if let Some(keep) = open.iter().position(|mark| !marks.contains(mark)) {
+            for mark in open.drain(keep..).rev() {
+                output.push_str(&mark.close());
+            }
+        }
+        for mark in marks {
+            if !open.contains(&mark) {
+                output.push_str(&mark.open());
+                open.push(mark);
+            }
+        }
+        output.push_str(&escape_text(segment).replace('\n', "<br>"));
+    }
+    for mark in open.iter().rev() {
+        output.push_str(&mark.close());
+    }
+    output
+}
+
+fn marks_for(style: &TextStyle) -> Vec<Mark> {
+    let mut marks = Vec::new();
+    if let Some(url) = &style.link {
+        marks.push(Mark::Link(url.clone()));
+    }
+    let css = css_for(style);
+    if !css.is_empty() {
+        marks.push(Mark::Style(css));
+    }
+    let flags = [
+        (
+            style.font_weight.is_some_and(|weight| weight >= 600),
+            Mark::Strong,
+        ),
+        (style.italic == Some(true), Mark::Emphasis),
+        (style.underline == Some(true), Mark::Underline),
+        (style.strikethrough == Some(true), Mark::Strikethrough),
+        (style.superscript == Some(true), Mark::Superscript),
+        (style.subscript == Some(true), Mark::Subscript),
+        (style.font_family.as_deref() == Some(CODE_FONT), Mark::Code),
+    ];
+    marks.extend(
+        flags
+            .into_iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, mark)| mark),
+    );
+    marks
+}
+
+/// CSS declarations for the parts of a style without an element of their
+/// own.
+fn css_for(style: &TextStyle) -> String {
+    let mut declarations = Vec::new();
+    if let Some(color) = style.color {
+        declarations.push(format!("color: {}", css_color(color)));
+    }
+    if let Some(color) = style.background {
+        declarations.push(format!("background-color: {}", css_color(color)));
+    }
+    if let Some(family) = style.font_family.as_deref().filter(|&f| f != CODE_FONT) {
+        if family.contains(' ') {
+            declarations.push(format!("font-family: '{}'", family.replace('\'', "")));
+        } else {
+            declarations.push(format!("font-family: {}", family));
+        }
+    }
+    if let Some(size) = style.font_size {
+        declarations.push(format!("font-size: {}pt", size));
+    }
+    if let Some(spacing) = style.letter_spacing {
+        declarations.push(format!("letter-spacing: {}em", spacing));
+    }
+    if style.small_caps == Some(true) {
+        declarations.push("font-variant: small-caps".to_string());
+    }
+    declarations.join("; ")
+}
+
+fn css_color([r, g, b, a]: [u8; 4]) -> String {
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("rgba({}, {}, {}, {:.3})", r, g, b, a as f32 / 255.0)
+    }
+}
+
+fn overlay(base: &mut TextStyle, top: &TextStyle) {
+    if top.font_family.is_some() {
+        base.font_family = top.font_family.clone();
+    }
+    if top.font_size.is_some() {
+        base.font_size = top.font_size;
+    }
+    if top.font_weight.is_some() {
+        base.font_weight = top.font_weight;
+    }
+    if top.italic.is_some() {
+        base.italic = top.italic;
+    }
+    if top.underline.is_some() {
+        base.underline = top.underline;
+    }
+    if top.strikethrough.is_some() {
+        base.strikethrough = top.strikethrough;
+    }
+    if top.color.is_some() {
+        base.color = top.color;
+    }
+    if top.background.is_some() {
+        base.background = top.background;
+    }
+    if top.superscript.is_some() {
+        base.superscript = top.superscript;
+    }
+    if top.subscript.is_some() {
+        base.subscript = top.subscript;
+    }
+    if top.small_caps.is_some() {
+        base.small_caps = top.small_caps;
+    }
+    if top.letter_spacing.is_some() {
+        base.letter_spacing = top.letter_spacing;
+    }
+    if top.link.is_some() {
+        base.link = top.link.clone();
+    }
+}
+
+/// A data URI holding an image, if it is in a format browsers show.
+fn data_uri(data: &[u8]) -> Option<String> {
+    let mime_type = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else if data.starts_with(b"<svg") || data.starts_with(b"<?xml") {
+        "image/svg+xml"
+    } else {
+        return None;
+    };
+    Some(format!("data:{};base64,{}", mime_type, base64(data)))
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Escape text for an element's content.
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escape text for a double-quoted attribute value.
+fn escape_attribute(text: &str) -> String {
+    escape_text(text).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wolia_core::text::Span;
+
+    fn fragment(nodes: Vec<Node>) -> String {
+        let mut document = Document::new();
+        document.root.children = nodes;
+        HtmlWriter::new()
+            .with_standalone(false)
+            .write(&document)
+            .unwrap()
+    }
+
+    fn styled(apply: impl FnOnce(&mut TextStyle)) -> TextStyle {
+        let mut style = TextStyle::default();
+        apply(&mut style);
+        style
+    }
+
+    fn paragraph(content: &str) -> Node {
+        Node::paragraph(Text::new(content))
+    }
+
+    #[test]
+    fn test_styled_paragraph() {
+        let mut text = Text::new("Bold, red <text> & a link");
+        text.add_span(Span::new(0, 4, styled(|s| s.font_weight = Some(700))));
+        text.add_span(Span::new(
+            6,
+            9,
+            styled(|s| {
+                s.color = Some([255, 0, 0, 255]);
+                s.font_family = Some("Times New Roman".to_string());
+            }),
+        ));
+        text.add_span(Span::new(
+            21,
+            25,
+            styled(|s| s.link = Some("https://example.com/?a=1&b=\"2\"".to_string())),
+        ));
+        assert_eq!(
+            fragment(vec![
+                Node::heading(2, Text::new("Title")),
+                Node::paragraph(text)
+            ]),
+            "<h2>Title</h2>\n\
+             <p><strong>Bold</strong>, <span style=\"color: #ff0000; \
+             font-family: 'Times New Roman'\">red</span> &lt;text&gt; &amp; a \
+             <a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">link</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_overlapping_spans_nest() {
+        let mut text = Text::new("bold both italic");
+        text.add_span(Span::new(0, 9, styled(|s| s.font_weight = Some(700))));
+        text.add_span(Span::new(5, 16, styled(|s| s.italic = Some(true))));
+        text.add_span(Span::new(5, 9, styled(|s| s.underline = Some(true))));
+        assert_eq!(
+            render_inline(&text),
+            "<strong>bold <em><u>both</u></em></strong><em> italic</em>"
+        );
+    }
+
+    #[test]
+    fn test_nested_list() {
+        let mut nested = Node::new(NodeKind::List { ordered: true });
+        let mut item = Node::new(NodeKind::ListItem);
+        item.add_child(paragraph("inner"));
+        nested.add_child(item);
+
+        let mut list = Node::new(NodeKind::List { ordered: false });
+        let mut first = Node::new(NodeKind::ListItem);
+        first.add_child(paragraph("one"));
+        first.add_child(nested);
+        let mut second = Node::new(NodeKind::ListItem);
+        second.add_child(paragraph("two"));
+        list.add_child(first);
+        list.add_child(second);
+
+        assert_eq!(
+            fragment(vec![list]),
+            "<ul>\n<li>one\n<ol>\n<li>inner</li>\n</ol>\n</li>\n<li>two</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let mut table = Node::new(NodeKind::Table { rows: 2, cols: 2 });
+        for cells in [vec![("a", 1), ("b", 1)], vec![("wide", 2)]] {
+            let mut row = Node::new(NodeKind::TableRow);
+            for (content, col_span) in cells {
+                let mut cell = Node::new(NodeKind::TableCell {
+                    col_span,
+                    row_span: 1,
+                });
+                cell.add_child(paragraph(content));
+                row.add_child(cell);
+            }
+            table.add_child(row);
+        }
+        assert_eq!(
+            fragment(vec![table]),
+            "<table>\n<tr><td>a</td><td>b</td></tr>\n\
+             <tr><td colspan=\"2\">wide</td></tr>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn test_standalone_page() {
+        let mut document = Document::new();
+        document.metadata.title = Some("Q&A".to_string());
+        document.root.add_child(paragraph("Hi"));
+        assert_eq!(
+            crate::write(&document).unwrap(),
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Q&amp;A</title>\n</head>\n<body>\n<p>Hi</p>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn test_embeds_images() {
+        let image = Node::new(NodeKind::Image {
+            src: "dot.gif".to_string(),
+            alt: Some("A dot".to_string()),
+        });
+        let mut document = Document::new();
+        document.root.add_child(image);
+        let html = HtmlWriter::new()
+            .with_standalone(false)
+            .with_image("dot.gif", b"GIF89a".to_vec())
+            .write(&document)
+            .unwrap();
+        assert_eq!(
+            html,
+            "<img src=\"data:image/gif;base64,R0lGODlh\" alt=\"A dot\">\n"
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+    }
+}
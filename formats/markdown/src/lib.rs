@@ -4,16 +4,20 @@
 
 use wolia_core::Document;
 
+mod reader;
+mod writer;
+
+/// Font family used to mark inline code spans.
+pub const CODE_FONT: &str = "monospace";
+
 /// Read a document from Markdown.
-pub fn read(_data: &str) -> Result<Document, Error> {
-    // TODO: Implement Markdown parsing
-    Ok(Document::new())
+pub fn read(data: &str) -> Result<Document, Error> {
+    reader::read(data)
 }
 
 /// Export a document to Markdown.
-pub fn write(_document: &Document) -> Result<String, Error> {
-    // TODO: Implement Markdown generation
-    Ok(String::new())
+pub fn write(document: &Document) -> Result<String, Error> {
+    writer::write(document)
 }
 
 /// Format errors.
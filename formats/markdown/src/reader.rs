@@ -0,0 +1,394 @@
+//! Markdown parsing.
+//!
+//! Walks CommonMark events and builds core document nodes. Block containers
+//! (quotes, lists, items, tables) are kept on a stack; inline content is
+//! accumulated into the currently open paragraph or heading.
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use wolia_core::style::TextStyle;
+use wolia_core::text::{Span, Text};
+use wolia_core::{Document, Node, NodeKind};
+
+use crate::{CODE_FONT, Error};
+
+/// Parse Markdown source into a document.
+pub fn read(source: &str) -> Result<Document, Error> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut builder = Builder::new();
+    for event in Parser::new_ext(source, options) {
+        builder.event(event);
+    }
+    Ok(builder.finish())
+}
+
+/// Which text-bearing block is being filled.
+enum InlineKind {
+    Paragraph,
+    Heading(u8),
+}
+
+/// An open paragraph or heading.
+struct Inline {
+    kind: InlineKind,
+    text: Text,
+}
+
+/// State for an image whose alt text is being collected.
+struct PendingImage {
+    src: String,
+    alt: String,
+}
+
+struct Builder {
+    /// Open container nodes; the first entry is the document root.
+    containers: Vec<Node>,
+    /// Open text block, if any.
+    inline: Option<Inline>,
+    /// Styles opened by emphasis, strong, strikethrough, and links, with the
+    /// offset at which each started.
+    styles: Vec<(usize, TextStyle)>,
+    /// Code block being collected.
+    code: Option<(Option<String>, String)>,
+    image: Option<PendingImage>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            containers: vec![Node::root()],
+            inline: None,
+            styles: Vec::new(),
+            code: None,
+            image: None,
+        }
+    }
+
+    fn finish(mut self) -> Document {
+        self.close_inline();
+        while self.containers.len() > 1 {
+            self.close_container();
+        }
+
+        let mut document = Document::new();
+        document.root = self.containers.pop().unwrap_or_else(Node::root);
+        document
+    }
+
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text),
+            Event::Code(code) => {
+                let style = TextStyle {
+                    font_family: Some(CODE_FONT.to_string()),
+                    ..TextStyle::default()
+                };
+                let text = &mut self.open_inline().text;
+                let start = text.content.len();
+                text.content.push_str(&code);
+                text.add_span(Span::new(start, text.content.len(), style));
+            }
+            Event::InlineHtml(html) | Event::Html(html) => self.text(&html),
+            Event::SoftBreak => self.text(" "),
+            Event::HardBreak => self.text("\n"),
+            Event::Rule => {
+                self.close_inline();
+                self.push_node(Node::new(NodeKind::HorizontalRule));
+            }
+            Event::TaskListMarker(checked) => self.text(if checked { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph => {
+                self.close_inline();
+                self.inline = Some(Inline {
+                    kind: InlineKind::Paragraph,
+                    text: Text::empty(),
+                });
+            }
+            Tag::Heading { level, .. } => {
+                self.close_inline();
+                self.inline = Some(Inline {
+                    kind: InlineKind::Heading(heading_level(level)),
+                    text: Text::empty(),
+                });
+            }
+            Tag::BlockQuote(_) => self.open_container(NodeKind::BlockQuote),
+            Tag::List(start) => self.open_container(NodeKind::List {
+                ordered: start.is_some(),
+            }),
+            Tag::Item => self.open_container(NodeKind::ListItem),
+            Tag::Table(alignments) => self.open_container(NodeKind::Table {
+                rows: 0,
+                cols: alignments.len(),
+            }),
+            Tag::TableHead | Tag::TableRow => self.open_container(NodeKind::TableRow),
+            Tag::TableCell => self.open_container(NodeKind::TableCell {
+                col_span: 1,
+                row_span: 1,
+            }),
+            Tag::CodeBlock(kind) => {
+                self.close_inline();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(|lang| lang.to_string())
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::Emphasis => self.open_style(TextStyle {
+                italic: Some(true),
+                ..TextStyle::default()
+            }),
+            Tag::Strong => self.open_style(TextStyle {
+                font_weight: Some(700),
+                ..TextStyle::default()
+            }),
+            Tag::Strikethrough => self.open_style(TextStyle {
+                strikethrough: Some(true),
+                ..TextStyle::default()
+            }),
+            Tag::Link { dest_url, .. } => self.open_style(TextStyle {
+                link: Some(dest_url.to_string()),
+                ..TextStyle::default()
+            }),
+            Tag::Image { dest_url, .. } => {
+                self.image = Some(PendingImage {
+                    src: dest_url.to_string(),
+                    alt: String::new(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Heading(_) => self.close_inline(),
+            TagEnd::BlockQuote(_)
+            | TagEnd::List(_)
+            | TagEnd::Item
+            | TagEnd::TableHead
+            | TagEnd::TableRow
+            | TagEnd::TableCell => {
+                self.close_inline();
+                self.close_container();
+            }
+            TagEnd::Table => {
+                self.close_inline();
+                if let Some(table) = self.containers.last_mut() {
+                    let row_count = table.children.len();
+                    if let NodeKind::Table { rows, .. } = &mut table.kind {
+                        *rows = row_count;
+                    }
+                }
+                self.close_container();
+            }
+            TagEnd::CodeBlock => {
+                if let Some((language, mut code)) = self.code.take() {
+                    if code.ends_with('\n') {
+                        code.pop();
+                    }
+                    self.push_node(Node::new(NodeKind::CodeBlock { language, code }));
+                }
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link => {
+                self.close_style()
+            }
+            TagEnd::Image => {
+                if let Some(image) = self.image.take() {
+                    // Images become block-level nodes, splitting the paragraph
+                    // they appear in.
+                    let continuation = self.inline.as_ref().map(|inline| match inline.kind {
+                        InlineKind::Paragraph => InlineKind::Paragraph,
+                        InlineKind::Heading(level) => InlineKind::Heading(level),
+                    });
+                    self.close_inline();
+                    let alt = (!image.alt.is_empty()).then_some(image.alt);
+                    self.push_node(Node::new(NodeKind::Image {
+                        src: image.src,
+                        alt,
+                    }));
+                    if let Some(kind) = continuation {
+                        self.inline = Some(Inline {
+                            kind,
+                            text: Text::empty(),
+                        });
+                        for (start, _) in &mut self.styles {
+                            *start = 0;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some((_, code)) = self.code.as_mut() {
+            code.push_str(text);
+        } else if let Some(image) = self.image.as_mut() {
+            image.alt.push_str(text);
+        } else {
+            self.open_inline().text.content.push_str(text);
+        }
+    }
+
+    /// The open text block, starting an implicit paragraph if needed (tight
+    /// list items and table cells carry inline content without one).
+    fn open_inline(&mut self) -> &mut Inline {
+        self.inline.get_or_insert_with(|| Inline {
+            kind: InlineKind::Paragraph,
+            text: Text::empty(),
+        })
+    }
+
+    fn close_inline(&mut self) {
+        let Some(inline) = self.inline.take() else {
+            return;
+        };
+        match inline.kind {
+            InlineKind::Paragraph => {
+                if !inline.text.is_empty() {
+                    self.push_node(Node::paragraph(inline.text));
+                }
+            }
+            InlineKind::Heading(level) => self.push_node(Node::heading(level, inline.text)),
+        }
+    }
+
+    fn open_style(&mut self, style: TextStyle) {
+        let start = self.open_inline().text.len();
+        self.styles.push((start, style));
+    }
+
+    fn close_style(&mut self) {
+        let Some((start, style)) = self.styles.pop() else {
+            return;
+        };
+        if let Some(inline) = self.inline.as_mut() {
+            let end = inline.text.len();
+            if end > start {
+                inline.text.add_span(Span::new(start, end, style));
+            }
+        }
+    }
+
+    fn open_container(&mut self, kind: NodeKind) {
+        self.close_inline();
+        self.containers.push(Node::new(kind));
+    }
+
+    fn close_container(&mut self) {
+        if self.containers.len() > 1 {
+            let node = self.containers.pop().unwrap_or_else(Node::root);
+            self.push_node(node);
+        }
+    }
+
+    fn push_node(&mut self, node: Node) {
+        if let Some(parent) = self.containers.last_mut() {
+            parent.add_child(node);
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(document: &Document) -> &[Node] {
+        &document.root.children
+    }
+
+    #[test]
+    fn test_heading_with_emphasis() {
+        let document = read("## Hello *big* **world**").unwrap();
+        let NodeKind::Heading { level, text } = &blocks(&document)[0].kind else {
+            panic!("expected heading");
+        };
+        assert_eq!(*level, 2);
+        assert_eq!(text.content, "Hello big world");
+        assert_eq!(text.spans.len(), 2);
+        assert_eq!(text.spans[0].style.italic, Some(true));
+        assert_eq!(
+            &text.content[text.spans[1].start..text.spans[1].end],
+            "world"
+        );
+        assert_eq!(text.spans[1].style.font_weight, Some(700));
+    }
+
+    #[test]
+    fn test_nested_list() {
+        let source = "- one\n- two\n\n  second paragraph\n\n  1. inner\n";
+        let document = read(source).unwrap();
+        let list = &blocks(&document)[0];
+        assert!(matches!(list.kind, NodeKind::List { ordered: false }));
+        assert_eq!(list.children.len(), 2);
+
+        let second = &list.children[1];
+        assert!(matches!(second.kind, NodeKind::ListItem));
+        assert_eq!(second.children.len(), 3);
+        assert!(matches!(second.children[1].kind, NodeKind::Paragraph(_)));
+        let inner = &second.children[2];
+        assert!(matches!(inner.kind, NodeKind::List { ordered: true }));
+        assert!(matches!(inner.children[0].kind, NodeKind::ListItem));
+    }
+
+    #[test]
+    fn test_fenced_code_language() {
+        let document = read("```rust\nfn main() {}\n```\n").unwrap();
+        let NodeKind::CodeBlock { language, code } = &blocks(&document)[0].kind else {
+            panic!("expected code block");
+        };
+        assert_eq!(language.as_deref(), Some("rust"));
+        assert_eq!(code, "fn main() {}");
+    }
+
+    #[test]
+    fn test_table() {
+        let source = "| a | b |\n|---|---|\n| 1 | 2 |\n| 3 | 4 |\n";
+        let document = read(source).unwrap();
+        let table = &blocks(&document)[0];
+        assert!(matches!(table.kind, NodeKind::Table { rows: 3, cols: 2 }));
+        let cell = &table.children[2].children[1];
+        assert!(matches!(cell.kind, NodeKind::TableCell { .. }));
+        let NodeKind::Paragraph(text) = &cell.children[0].kind else {
+            panic!("expected cell paragraph");
+        };
+        assert_eq!(text.content, "4");
+    }
+
+    #[test]
+    fn test_link_and_image() {
+        let document = read("See [docs](https://example.com) ![logo](logo.png)").unwrap();
+        let NodeKind::Paragraph(text) = &blocks(&document)[0].kind else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(
+            text.spans[0].style.link.as_deref(),
+            Some("https://example.com")
+        );
+        let NodeKind::Image { src, alt } = &blocks(&document)[1].kind else {
+            panic!("expected image");
+        };
+        assert_eq!(src, "logo.png");
+        assert_eq!(alt.as_deref(), Some("logo"));
+    }
+}
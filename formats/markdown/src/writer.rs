@@ -0,0 +1,478 @@
+//! Markdown generation.
+//!
+//! Each block node renders to a string; containers (quotes, list items)
+//! prefix or indent the lines of their rendered children. Inline formatting
+//! is reconstructed from text spans by opening and closing delimiters as the
+//! set of active styles changes.
+
+use wolia_core::style::TextStyle;
+use wolia_core::text::Text;
+use wolia_core::{Document, Node, NodeKind};
+
+use crate::{CODE_FONT, Error};
+
+/// Serialize a document to Markdown.
+pub fn write(document: &Document) -> Result<String, Error> {
+    let mut output = render_blocks(&document.root.children, "\n\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+fn render_blocks(nodes: &[Node], separator: &str) -> String {
+    nodes
+        .iter()
+        .filter_map(render_block)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn render_block(node: &Node) -> Option<String> {
+    let rendered = match &node.kind {
+        NodeKind::Paragraph(text) => render_inline(text, "\\\n"),
+        NodeKind::Heading { level, text } => {
+            format!(
+                "{} {}",
+                "#".repeat((*level).clamp(1, 6) as usize),
+                render_inline(text, " ")
+            )
+        }
+        NodeKind::BlockQuote => render_blocks(&node.children, "\n\n")
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        NodeKind::List { ordered } => render_list(node, *ordered),
+        NodeKind::CodeBlock { language, code } => {
+            let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+            format!(
+                "{}{}\n{}\n{}",
+                fence,
+                language.as_deref().unwrap_or(""),
+                code,
+                fence
+            )
+        }
+        NodeKind::Table { .. } => render_table(node),
+        NodeKind::Image { src, alt } => {
+            format!(
+                "![{}]({})",
+                escape(alt.as_deref().unwrap_or("")),
+                escape_url(src)
+            )
+        }
+        NodeKind::HorizontalRule | NodeKind::PageBreak => "---".to_string(),
+        NodeKind::Custom { .. } => return None,
+        NodeKind::Root
+        | NodeKind::Section
+        | NodeKind::ListItem
+        | NodeKind::TableRow
+        | NodeKind::TableCell { .. } => render_blocks(&node.children, "\n\n"),
+    };
+    (!rendered.is_empty()).then_some(rendered)
+}
+
+fn render_list(list: &Node, ordered: bool) -> String {
+    // A list is loose when an item holds several blocks that are not just a
+    // leading paragraph followed by nested lists.
+    let loose = list.children.iter().any(|item| {
+        item.children
+            .iter()
+            .skip(1)
+            .any(|child| !matches!(child.kind, NodeKind::List { .. }))
+    });
+    let separator = if loose { "\n\n" } else { "\n" };
+
+    list.children
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let marker = if ordered {
+                format!("{}. ", index + 1)
+            } else {
+                "- ".to_string()
+            };
+            let body = render_blocks(&item.children, separator);
+            if body.is_empty() {
+                return marker.trim_end().to_string();
+            }
+
+            let indent = " ".repeat(marker.len());
+            body.lines()
+                .enumerate()
+                .map(|(line_index, line)| {
+                    if line_index == 0 {
+                        format!("{}{}", marker, line)
+                    } else if line.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{}{}", indent, line)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn render_table(table: &Node) -> String {
+    let rows: Vec<Vec<String>> = table
+        .children
+        .iter()
+        .map(|row| {
+            row.children
+                .iter()
+                .map(|cell| {
+                    cell.children
+                        .iter()
+                        .filter_map(|child| match &child.kind {
+                            NodeKind::Paragraph(text) => Some(render_inline(text, " ")),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect()
+        })
+        .collect();
+
+    let declared = match table.kind {
+        NodeKind::Table { cols, .. } => cols,
+        _ => 0,
+    };
+    let cols = rows.iter().map(Vec::len).max().unwrap_or(0).max(declared);
+    if rows.is_empty() || cols == 0 {
+        return String::new();
+    }
+
+    let format_row = |cells: &[String]| {
+        let mut line = String::from("|");
+        for index in 0..cols {
+            line.push(' ');
+            line.push_str(cells.get(index).map(String::as_str).unwrap_or(""));
+            line.push_str(" |");
+        }
+        line
+    };
+
+    let mut lines = vec![format_row(&rows[0])];
+    lines.push(format!("|{}", " --- |".repeat(cols)));
+    lines.extend(rows[1..].iter().map(|row| format_row(row)));
+    lines.join("\n")
+}
+
+/// Inline marks, in the order they are opened (outermost first).
+#[derive(Debug, Clone, PartialEq)]
+enum Mark {
+    Link(String),
+    Strong,
+    Emphasis,
+    Strikethrough,
+}
+
+impl Mark {
+    fn open(&self) -> &str {
+        match self {
+            Mark::Link(_) => "[",
+            Mark::Strong => "**",
+            Mark::Emphasis => "*",
+            Mark::Strikethrough => "~~",
+        }
+    }
+
+    fn close(&self) -> String {
+        match self {
+            Mark::Link(url) => format!("]({})", escape_url(url)),
+            Mark::Strong => "**".to_string(),
+            Mark::Emphasis => "*".to_string(),
+            Mark::Strikethrough => "~~".to_string(),
+        }
+    }
+}
+
+/// Render text with its spans as inline Markdown. Newlines are replaced with
+/// `line_break`.
+fn render_inline(text: &Text, line_break: &str) -> String {
+    let content = &text.content;
+    let mut boundaries = vec![0, content.len()];
+    for span in &text.spans {
+        boundaries.push(span.start.min(content.len()));
+        boundaries.push(span.end.min(content.len()));
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut output = String::new();
+    let mut open: Vec<Mark> = Vec::new();
+    // Whitespace held back so that closing delimiters hug the text before it
+    // and opening delimiters hug the text after it.
+    let mut pending = String::new();
+
+    for window in boundaries.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let Some(segment) = content.get(start..end) else {
+            continue;
+        };
+
+        let mut style = TextStyle::default();
+        for span in &text.spans {
+            if span.start <= start && span.end >= end {
+                overlay(&mut style, &span.style);
+            }
+        }
+        let marks = marks_for(&style);
+
+        // Close everything above the first open mark that no longer applies.
+        if let Some(keep) = open.iter().position(|mark| !marks.contains(mark)) {
+            for mark in open.drain(keep..).rev() {
+                output.push_str(&mark.close());
+            }
+        }
+
+        let is_code = style.font_family.as_deref() == Some(CODE_FONT);
+        let (leading, core, trailing) = if is_code {
+            ("", segment, "")
+        } else {
+            split_whitespace_edges(segment)
+        };
+        pending.push_str(leading);
+        if core.is_empty() {
+            pending.push_str(trailing);
+            continue;
+        }
+        output.push_str(&pending);
+        pending.clear();
+
+        for mark in marks {
+            if !open.contains(&mark) {
+                output.push_str(mark.open());
+                open.push(mark);
+            }
+        }
+
+        if is_code {
+            output.push_str(&code_span(core));
+        } else {
+            output.push_str(&escape(core).replace('\n', line_break));
+        }
+        pending.push_str(trailing);
+    }
+
+    for mark in open.iter().rev() {
+        output.push_str(&mark.close());
+    }
+    output.push_str(&pending);
+    output
+}
+
+/// Split a segment into leading spaces, its content, and trailing spaces.
+fn split_whitespace_edges(segment: &str) -> (&str, &str, &str) {
+    let is_space = |c: char| c == ' ' || c == '\t';
+    let core_start = segment.len() - segment.trim_start_matches(is_space).len();
+    let core_end = segment.trim_end_matches(is_space).len().max(core_start);
+    (
+        &segment[..core_start],
+        &segment[core_start..core_end],
+        &segment[core_end..],
+    )
+}
+
+fn marks_for(style: &TextStyle) -> Vec<Mark> {
+    let mut marks = Vec::new();
+    if let Some(url) = &style.link {
+        marks.push(Mark::Link(url.clone()));
+    }
+    if style.font_weight.is_some_and(|weight| weight >= 600) {
+        marks.push(Mark::Strong);
+    }
+    if style.italic == Some(true) {
+        marks.push(Mark::Emphasis);
+    }
+    if style.strikethrough == Some(true) {
+        marks.push(Mark::Strikethrough);
+    }
+    marks
+}
+
+fn overlay(base: &mut TextStyle, top: &TextStyle) {
+    if top.font_family.is_some() {
+        base.font_family = top.font_family.clone();
+    }
+    if top.font_weight.is_some() {
+        base.font_weight = top.font_weight;
+    }
+    if top.italic.is_some() {
+        base.italic = top.italic;
+    }
+    if top.strikethrough.is_some() {
+        base.strikethrough = top.strikethrough;
+    }
+    if top.link.is_some() {
+        base.link = top.link.clone();
+    }
+}
+
+fn code_span(code: &str) -> String {
+    let ticks = "`".repeat(longest_run(code, '`') + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{} {} {}", ticks, code, ticks)
+    } else {
+        format!("{}{}{}", ticks, code, ticks)
+    }
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == ch {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+/// Backslash-escape characters that Markdown would otherwise interpret.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut at_line_start = true;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let significant = matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '&'
+        ) || (at_line_start && matches!(c, '-' | '+' | '='));
+        if significant {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+
+        // "1." at the start of a line would begin an ordered list.
+        if at_line_start && c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_ascii_digit() {
+                    digits.push(next);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            escaped.push_str(&digits);
+            if chars.peek().is_some_and(|next| matches!(next, '.' | ')')) {
+                escaped.push('\\');
+            }
+        }
+        at_line_start = c == '\n';
+    }
+    escaped
+}
+
+fn escape_url(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read;
+    use wolia_core::text::Span;
+
+    const SAMPLE: &str = "\
+# Title
+
+Some *italic*, **bold**, `code`, and a [link](https://example.com).
+
+> Quoted text
+> continues here
+
+- one
+- two
+  - nested
+
+1. first
+
+   more of first
+
+2. second
+
+```rust
+fn main() {}
+```
+
+| a | b |
+| --- | --- |
+| 1 | 2 |
+
+![logo](logo.png)
+
+---
+";
+
+    #[test]
+    fn test_roundtrip_is_stable() {
+        let first = write(&read(SAMPLE).unwrap()).unwrap();
+        let second = write(&read(&first).unwrap()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            SAMPLE.replace(
+                "> Quoted text\n> continues here",
+                "> Quoted text continues here"
+            )
+        );
+    }
+
+    #[test]
+    fn test_escapes_plain_text() {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("#tag 2 * 3 = snake_case")));
+        let markdown = write(&document).unwrap();
+        assert_eq!(markdown, "\\#tag 2 \\* 3 = snake\\_case\n");
+
+        let NodeKind::Paragraph(text) = &read(&markdown).unwrap().root.children[0].kind else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(text.content, "#tag 2 * 3 = snake_case");
+    }
+
+    #[test]
+    fn test_overlapping_spans() {
+        let mut text = Text::new("bold both italic");
+        text.add_span(Span::new(
+            0,
+            9,
+            TextStyle {
+                font_weight: Some(700),
+                ..TextStyle::default()
+            },
+        ));
+        text.add_span(Span::new(
+            5,
+            16,
+            TextStyle {
+                italic: Some(true),
+                ..TextStyle::default()
+            },
+        ));
+        assert_eq!(render_inline(&text, "\n"), "**bold *both*** *italic*");
+    }
+}
@@ -0,0 +1,701 @@
+//! Page composition.
+//!
+//! Walks the document tree, lays out text blocks with the layout engine and
+//! turns the resulting lines into PDF content streams, one per page. Layout
+//! works top-down from the page's top-left corner while PDF user space has
+//! its origin at the bottom-left, so every y coordinate is flipped when it
+//! is written out.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use wolia_core::style::{Alignment, ParagraphStyle, TextStyle};
+use wolia_core::text::Text;
+use wolia_core::{Document, Node, NodeKind};
+use wolia_layout::PageLayout;
+use wolia_layout::text::TextLayout;
+
+use crate::error::Error;
+use crate::font::{EmbeddedFont, FontUsage};
+use crate::image::PdfImage;
+
+/// Body text size used when neither the stylesheet nor the text sets one.
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+/// Indentation applied per level of block quote or list nesting.
+const INDENT: f32 = 18.0;
+
+/// Advance width of a Courier glyph, as a fraction of the font size.
+const COURIER_ADVANCE: f32 = 0.6;
+
+/// The standard 14 fonts referenced by generated pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StandardFont {
+    Helvetica,
+    HelveticaBold,
+    HelveticaOblique,
+    HelveticaBoldOblique,
+    Courier,
+}
+
+impl StandardFont {
+    /// Every font, in resource order.
+    pub(crate) const ALL: [StandardFont; 5] = [
+        StandardFont::Helvetica,
+        StandardFont::HelveticaBold,
+        StandardFont::HelveticaOblique,
+        StandardFont::HelveticaBoldOblique,
+        StandardFont::Courier,
+    ];
+
+    /// PostScript name of the font.
+    pub(crate) fn base_font(self) -> &'static str {
+        match self {
+            StandardFont::Helvetica => "Helvetica",
+            StandardFont::HelveticaBold => "Helvetica-Bold",
+            StandardFont::HelveticaOblique => "Helvetica-Oblique",
+            StandardFont::HelveticaBoldOblique => "Helvetica-BoldOblique",
+            StandardFont::Courier => "Courier",
+        }
+    }
+
+    /// Name of the font in page resource dictionaries.
+    pub(crate) fn resource_name(self) -> String {
+        let index = Self::ALL.iter().position(|&font| font == self).unwrap_or(0);
+        format!("F{}", index + 1)
+    }
+
+    fn for_style(style: &TextStyle) -> Self {
+        let bold = style.font_weight.is_some_and(|weight| weight >= 600);
+        let italic = style.italic == Some(true);
+        match (bold, italic) {
+            (false, false) => StandardFont::Helvetica,
+            (true, false) => StandardFont::HelveticaBold,
+            (false, true) => StandardFont::HelveticaOblique,
+            (true, true) => StandardFont::HelveticaBoldOblique,
+        }
+    }
+}
+
+/// The font a run of text is set in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Standard(StandardFont),
+    /// Index into the embedded fonts.
+    Embedded(usize),
+}
+
+/// Resource name of the embedded font at `index`.
+pub(crate) fn embedded_resource_name(index: usize) -> String {
+    format!("E{}", index + 1)
+}
+
+/// Resource name of the image at `index`.
+pub(crate) fn image_resource_name(index: usize) -> String {
+    format!("Im{}", index + 1)
+}
+
+/// A position on a page, in PDF user space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Destination {
+    /// Zero-based page index.
+    pub(crate) page: usize,
+    /// Distance from the bottom of the page.
+    pub(crate) top: f32,
+}
+
+/// A heading and where it was placed.
+#[derive(Debug, Clone)]
+pub(crate) struct HeadingAnchor {
+    pub(crate) level: u8,
+    pub(crate) title: String,
+    pub(crate) destination: Destination,
+}
+
+/// Laid-out pages and the embedded glyphs they use.
+pub(crate) struct Composition<'a> {
+    /// Content stream of each page.
+    pub(crate) pages: Vec<String>,
+    /// Usage of each embedded font, in registration order.
+    pub(crate) fonts: Vec<FontUsage<'a>>,
+    /// Headings in document order.
+    pub(crate) headings: Vec<HeadingAnchor>,
+    /// Decoded images, in order of first use.
+    pub(crate) images: Vec<PdfImage>,
+}
+
+/// Lay out a document into page content streams.
+pub(crate) fn compose<'a>(
+    document: &Document,
+    page_layout: &PageLayout,
+    fonts: &'a [EmbeddedFont],
+    images: &'a BTreeMap<String, Vec<u8>>,
+) -> Result<Composition<'a>, Error> {
+    let mut composer = Composer::new(page_layout, fonts, images);
+    composer.blocks(document, &document.root.children, 0.0)?;
+    Ok(composer.finish())
+}
+
+struct Composer<'a> {
+    page_height: f32,
+    content_left: f32,
+    content_top: f32,
+    content_bottom: f32,
+    content_width: f32,
+    /// Finished page content streams.
+    pages: Vec<String>,
+    /// Content stream of the current page.
+    ops: String,
+    /// Top of the next block, in layout coordinates.
+    y: f32,
+    fonts: Vec<FontUsage<'a>>,
+    headings: Vec<HeadingAnchor>,
+    /// Encoded image data by `src`.
+    image_sources: &'a BTreeMap<String, Vec<u8>>,
+    /// Decoded images and the `src` each was decoded from.
+    images: Vec<(String, PdfImage)>,
+}
+
+impl<'a> Composer<'a> {
+    fn new(
+        page_layout: &PageLayout,
+        fonts: &'a [EmbeddedFont],
+        image_sources: &'a BTreeMap<String, Vec<u8>>,
+    ) -> Self {
+        let content = page_layout.content_rect();
+        Self {
+            page_height: page_layout.size.height,
+            content_left: content.x,
+            content_top: content.y,
+            content_bottom: content.bottom(),
+            content_width: content.width,
+            pages: Vec::new(),
+            ops: String::new(),
+            y: content.y,
+            fonts: fonts.iter().map(FontUsage::new).collect(),
+            headings: Vec::new(),
+            image_sources,
+            images: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> Composition<'a> {
+        self.pages.push(self.ops);
+        Composition {
+            pages: self.pages,
+            fonts: self.fonts,
+            headings: self.headings,
+            images: self.images.into_iter().map(|(_, image)| image).collect(),
+        }
+    }
+
+    /// Pick the font for a text block: an embedded font named by the style,
+    /// else a standard font when the text fits its encoding, else the first
+    /// embedded font that has glyphs for the text.
+    fn font_for(&self, style: &TextStyle, content: &str) -> Font {
+        if let Some(family) = &style.font_family {
+            if let Some(index) = self
+                .fonts
+                .iter()
+                .position(|usage| &usage.font.name == family)
+            {
+                return Font::Embedded(index);
+            }
+        }
+        let encodable = content
+            .chars()
+            .all(|c| (' '..='~').contains(&c) || win_ansi(c).is_some() || c.is_whitespace());
+        if !encodable && !self.fonts.is_empty() {
+            let index = self
+                .fonts
+                .iter()
+                .position(|usage| usage.font.covers(content))
+                .unwrap_or(0);
+            return Font::Embedded(index);
+        }
+        Font::Standard(StandardFont::for_style(style))
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.ops));
+        self.y = self.content_top;
+    }
+
+    /// Whether anything has been placed on the current page yet.
+    fn at_page_top(&self) -> bool {
+        self.y <= self.content_top
+    }
+
+    /// Move down by `space`, unless at the top of a page where leading space
+    /// would only waste room.
+    fn skip(&mut self, space: f32) {
+        if !self.at_page_top() {
+            self.y += space;
+        }
+    }
+
+    /// The current position on the current page.
+    fn destination(&self) -> Destination {
+        Destination {
+            page: self.pages.len(),
+            top: self.flip(self.y),
+        }
+    }
+
+    /// Flip a layout y coordinate into PDF user space.
+    fn flip(&self, y: f32) -> f32 {
+        self.page_height - y
+    }
+
+    fn blocks(&mut self, document: &Document, nodes: &[Node], indent: f32) -> Result<(), Error> {
+        for node in nodes {
+            self.block(document, node, indent)?;
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, document: &Document, node: &Node, indent: f32) -> Result<(), Error> {
+        match &node.kind {
+            NodeKind::Paragraph(text) => {
+                let (text_style, paragraph_style) = block_style(document, "Normal", None);
+                self.paragraph(text, &text_style, &paragraph_style, indent)?;
+                Ok(())
+            }
+            NodeKind::Heading { level, text } => {
+                let name = format!("Heading {}", level);
+                let (text_style, paragraph_style) = block_style(document, &name, Some(*level));
+                let destination = self.paragraph(text, &text_style, &paragraph_style, indent)?;
+                self.headings.push(HeadingAnchor {
+                    level: *level,
+                    title: text
+                        .content
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    destination,
+                });
+                Ok(())
+            }
+            NodeKind::CodeBlock { code, .. } => {
+                self.code(code, indent);
+                Ok(())
+            }
+            NodeKind::HorizontalRule => {
+                self.rule(indent);
+                Ok(())
+            }
+            NodeKind::PageBreak => {
+                if !self.at_page_top() {
+                    self.new_page();
+                }
+                Ok(())
+            }
+            NodeKind::BlockQuote | NodeKind::List { .. } => {
+                self.blocks(document, &node.children, indent + INDENT)
+            }
+            NodeKind::Image { src, .. } => self.image(src, indent),
+            NodeKind::Custom { .. } => Ok(()),
+            NodeKind::Root
+            | NodeKind::Section
+            | NodeKind::ListItem
+            | NodeKind::Table { .. }
+            | NodeKind::TableRow
+            | NodeKind::TableCell { .. } => self.blocks(document, &node.children, indent),
+        }
+    }
+
+    fn paragraph(
+        &mut self,
+        text: &Text,
+        text_style: &TextStyle,
+        paragraph_style: &ParagraphStyle,
+        indent: f32,
+    ) -> Result<Destination, Error> {
+        let left = self.content_left + indent + paragraph_style.margin_left.unwrap_or(0.0);
+        let width = (self.content_width
+            - indent
+            - paragraph_style.margin_left.unwrap_or(0.0)
+            - paragraph_style.margin_right.unwrap_or(0.0))
+        .max(1.0);
+
+        // Lines are laid out at a single size, so use the largest one set on
+        // the text to keep lines from overlapping.
+        let mut text_style = text_style.clone();
+        for span in &text.spans {
+            if let Some(size) = span.style.font_size {
+                text_style.font_size = Some(text_style.font_size.map_or(size, |s| s.max(size)));
+            }
+            if text_style.font_family.is_none() {
+                text_style.font_family = span.style.font_family.clone();
+            }
+        }
+        let font_size = text_style.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+        let font = self.font_for(&text_style, &text.content);
+
+        let mut layout = TextLayout::new(width);
+        let (_, lines) = match font {
+            Font::Standard(_) => {
+                layout.layout_text(&text.content, width, &text_style, paragraph_style)
+            }
+            Font::Embedded(index) => {
+                let embedded = self.fonts[index].font;
+                layout.layout_text_with(&text.content, width, &text_style, paragraph_style, |run| {
+                    embedded.measure(run, font_size)
+                })
+            }
+        }
+        .map_err(|e| Error::generation(e.to_string()))?;
+
+        self.skip(paragraph_style.space_before.unwrap_or(0.0));
+        let mut start = self.destination();
+        if lines.is_empty() {
+            self.y += font_size * paragraph_style.line_height.unwrap_or(1.2);
+        }
+
+        // Position of the previous line's origin on this page, for relative
+        // `Td` moves within one text object.
+        let mut previous: Option<(f32, f32)> = None;
+        for (index, line) in lines.iter().enumerate() {
+            if self.y + line.height > self.content_bottom && !self.at_page_top() {
+                if previous.take().is_some() {
+                    self.ops.push_str("ET\n");
+                }
+                self.new_page();
+                if index == 0 {
+                    start = self.destination();
+                }
+            }
+
+            let offset = match paragraph_style.alignment.unwrap_or_default() {
+                Alignment::Left | Alignment::Justify => 0.0,
+                Alignment::Center => (width - line.width) / 2.0,
+                Alignment::Right => width - line.width,
+            };
+            let x = left + offset.max(0.0);
+            let y = self.flip(self.y + line.baseline);
+
+            match previous {
+                None => {
+                    let resource = match font {
+                        Font::Standard(font) => font.resource_name(),
+                        Font::Embedded(index) => embedded_resource_name(index),
+                    };
+                    let _ = write!(
+                        self.ops,
+                        "BT\n/{} {} Tf\n{} {} Td\n",
+                        resource,
+                        number(font_size),
+                        number(x),
+                        number(y)
+                    );
+                }
+                Some((previous_x, previous_y)) => {
+                    let _ = writeln!(
+                        self.ops,
+                        "{} {} Td",
+                        number(x - previous_x),
+                        number(y - previous_y)
+                    );
+                }
+            }
+            let shown = match font {
+                Font::Standard(_) => literal(&line.text),
+                Font::Embedded(index) => self.fonts[index].encode(&line.text),
+            };
+            let _ = writeln!(self.ops, "{} Tj", shown);
+
+            previous = Some((x, y));
+            self.y += line.height;
+        }
+        if previous.is_some() {
+            self.ops.push_str("ET\n");
+        }
+
+        self.y += paragraph_style.space_after.unwrap_or(font_size * 0.5);
+        Ok(start)
+    }
+
+    /// Place an image at its natural size, scaled down to fit the content
+    /// area if needed.
+    fn image(&mut self, src: &str, indent: f32) -> Result<(), Error> {
+        let index = match self.images.iter().position(|(decoded, _)| decoded == src) {
+            Some(index) => index,
+            None => {
+                let Some(data) = self.image_sources.get(src) else {
+                    return Ok(());
+                };
+                self.images.push((src.to_string(), PdfImage::decode(data)?));
+                self.images.len() - 1
+            }
+        };
+
+        let (natural_width, natural_height) = self.images[index].1.size();
+        if natural_width <= 0.0 || natural_height <= 0.0 {
+            return Ok(());
+        }
+        let max_width = (self.content_width - indent).max(1.0);
+        let max_height = self.content_bottom - self.content_top;
+        let scale = (max_width / natural_width)
+            .min(max_height / natural_height)
+            .min(1.0);
+        let (width, height) = (natural_width * scale, natural_height * scale);
+
+        self.skip(DEFAULT_FONT_SIZE * 0.5);
+        if self.y + height > self.content_bottom && !self.at_page_top() {
+            self.new_page();
+        }
+        // Images are drawn as a unit square, so the matrix scales it to the
+        // target size and moves its bottom-left corner into place.
+        let _ = write!(
+            self.ops,
+            "q\n{} 0 0 {} {} {} cm\n/{} Do\nQ\n",
+            number(width),
+            number(height),
+            number(self.content_left + indent),
+            number(self.flip(self.y + height)),
+            image_resource_name(index)
+        );
+        self.y += height + DEFAULT_FONT_SIZE * 0.5;
+        Ok(())
+    }
+
+    /// Code keeps its own line breaks and indentation, wrapping only lines
+    /// that are wider than the content area.
+    fn code(&mut self, code: &str, indent: f32) {
+        let font_size = DEFAULT_FONT_SIZE * 0.9;
+        let line_height = font_size * 1.2;
+        let columns = ((self.content_width - indent) / (font_size * COURIER_ADVANCE))
+            .floor()
+            .max(1.0) as usize;
+
+        self.skip(font_size * 0.5);
+        for source_line in code.split('\n') {
+            let chars: Vec<char> = source_line.chars().collect();
+            let chunks: Vec<String> = if chars.is_empty() {
+                vec![String::new()]
+            } else {
+                chars
+                    .chunks(columns)
+                    .map(|chunk| chunk.iter().collect())
+                    .collect()
+            };
+
+            for chunk in chunks {
+                if self.y + line_height > self.content_bottom && !self.at_page_top() {
+                    self.new_page();
+                }
+                let y = self.flip(self.y + line_height * 0.8);
+                if !chunk.is_empty() {
+                    let _ = write!(
+                        self.ops,
+                        "BT\n/{} {} Tf\n{} {} Td\n{} Tj\nET\n",
+                        StandardFont::Courier.resource_name(),
+                        number(font_size),
+                        number(self.content_left + indent),
+                        number(y),
+                        literal(&chunk)
+                    );
+                }
+                self.y += line_height;
+            }
+        }
+        self.y += font_size * 0.5;
+    }
+
+    fn rule(&mut self, indent: f32) {
+        let space = DEFAULT_FONT_SIZE * 0.5;
+        if self.y + space * 2.0 > self.content_bottom {
+            self.new_page();
+        }
+        self.skip(space);
+        let y = self.flip(self.y);
+        let _ = write!(
+            self.ops,
+            "0.5 w\n{} {} m\n{} {} l\nS\n",
+            number(self.content_left + indent),
+            number(y),
+            number(self.content_left + self.content_width),
+            number(y)
+        );
+        self.y += space;
+    }
+}
+
+/// Resolve the text and paragraph style of a block from the stylesheet,
+/// falling back to built-in heading sizes.
+fn block_style(
+    document: &Document,
+    name: &str,
+    heading_level: Option<u8>,
+) -> (TextStyle, ParagraphStyle) {
+    let mut text_style = TextStyle::default();
+    let mut paragraph_style = ParagraphStyle::default();
+
+    if let Some(level) = heading_level {
+        text_style.font_size = Some(match level {
+            1 => 24.0,
+            2 => 18.0,
+            3 => 14.0,
+            _ => DEFAULT_FONT_SIZE,
+        });
+        text_style.font_weight = Some(700);
+        paragraph_style.space_before = Some(DEFAULT_FONT_SIZE);
+    }
+
+    if let Some(style) = document.styles.get(name) {
+        let text = &style.text;
+        if text.font_family.is_some() {
+            text_style.font_family = text.font_family.clone();
+        }
+        if text.font_size.is_some() {
+            text_style.font_size = text.font_size;
+        }
+        if text.font_weight.is_some() {
+            text_style.font_weight = text.font_weight;
+        }
+        if text.italic.is_some() {
+            text_style.italic = text.italic;
+        }
+        let paragraph = &style.paragraph;
+        if paragraph.alignment.is_some() {
+            paragraph_style.alignment = paragraph.alignment;
+        }
+        if paragraph.line_height.is_some() {
+            paragraph_style.line_height = paragraph.line_height;
+        }
+        if paragraph.space_before.is_some() {
+            paragraph_style.space_before = paragraph.space_before;
+        }
+        if paragraph.space_after.is_some() {
+            paragraph_style.space_after = paragraph.space_after;
+        }
+        if paragraph.margin_left.is_some() {
+            paragraph_style.margin_left = paragraph.margin_left;
+        }
+        if paragraph.margin_right.is_some() {
+            paragraph_style.margin_right = paragraph.margin_right;
+        }
+    }
+
+    (text_style, paragraph_style)
+}
+
+/// Format a coordinate with at most two decimals and no trailing zeros.
+fn number(value: f32) -> String {
+    let formatted = format!("{:.2}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "" | "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Encode text as a PDF literal string in WinAnsiEncoding. Characters the
+/// encoding cannot represent are replaced with `?`.
+fn literal(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() + 2);
+    output.push('(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                output.push('\\');
+                output.push(c);
+            }
+            ' '..='~' => output.push(c),
+            _ => match win_ansi(c) {
+                Some(byte) => {
+                    let _ = write!(output, "\\{:03o}", byte);
+                }
+                None => output.push('?'),
+            },
+        }
+    }
+    output.push(')');
+    output
+}
+
+/// Map a character to its WinAnsiEncoding code outside the ASCII range.
+fn win_ansi(c: char) -> Option<u8> {
+    let code = match c {
+        '\u{A0}'..='\u{FF}' => c as u32 as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8A,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9A,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_escaping() {
+        assert_eq!(literal("a (b) \\ c"), "(a \\(b\\) \\\\ c)");
+        assert_eq!(literal("café – ok"), "(caf\\351 \\226 ok)");
+        assert_eq!(literal("日本"), "(??)");
+    }
+
+    #[test]
+    fn test_coordinates_are_flipped() {
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("First line")));
+        let pages = compose(&document, &PageLayout::a4(), &[], &BTreeMap::new())
+            .unwrap()
+            .pages;
+        assert_eq!(pages.len(), 1);
+
+        // The first baseline sits just below the top margin, measured from
+        // the bottom of the page.
+        let expected = format!("72 {} Td", number(842.0 - 72.0 - 12.0 * 1.2 * 0.8));
+        assert!(pages[0].contains(&expected), "{}", pages[0]);
+        assert!(pages[0].contains("(First line) Tj"));
+    }
+
+    #[test]
+    fn test_alignment_offsets_lines() {
+        let mut document = Document::new();
+        let mut normal = wolia_core::style::Style::default();
+        normal.paragraph.alignment = Some(Alignment::Right);
+        document.styles.insert(normal);
+        document.root.add_child(Node::paragraph(Text::new("abcd")));
+
+        let pages = compose(&document, &PageLayout::a4(), &[], &BTreeMap::new())
+            .unwrap()
+            .pages;
+        // Four characters at the layout engine's half-em estimate.
+        let x = 595.0 - 72.0 - 4.0 * 6.0;
+        assert!(
+            pages[0].contains(&format!("{} ", number(x))),
+            "{}",
+            pages[0]
+        );
+    }
+}
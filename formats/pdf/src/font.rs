@@ -0,0 +1,277 @@
+//! Embedded TrueType/OpenType fonts.
+//!
+//! Embedded fonts are written as composite (Type0) fonts with the
+//! `Identity-H` encoding, so text is shown as a sequence of two-byte CIDs.
+//! Only the glyphs a document uses are kept: glyphs are renumbered as text is
+//! encoded and the font program is subset to that set when it is written, so
+//! each CID is the glyph's ID in the subset font.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use subsetter::GlyphRemapper;
+use ttf_parser::{Face, GlyphId, name_id};
+
+use crate::error::Error;
+
+/// A font registered with the generator.
+#[derive(Debug, Clone)]
+pub(crate) struct EmbeddedFont {
+    /// Family name that text styles use to select the font.
+    pub(crate) name: String,
+    data: Vec<u8>,
+    postscript_name: String,
+    units_per_em: f32,
+    ascent: f32,
+    descent: f32,
+    cap_height: f32,
+    bbox: [f32; 4],
+    italic_angle: f32,
+    flags: u32,
+    /// Whether the outlines are CFF rather than TrueType.
+    cff: bool,
+}
+
+impl EmbeddedFont {
+    /// Parse a TTF/OTF font.
+    pub(crate) fn parse(name: String, data: &[u8]) -> Result<Self, Error> {
+        let face = Face::parse(data, 0)
+            .map_err(|e| Error::encoding(format!("Cannot parse font {}: {}", name, e)))?;
+
+        let units_per_em = face.units_per_em() as f32;
+        let scale = |value: i16| value as f32 * 1000.0 / units_per_em;
+        let bbox = face.global_bounding_box();
+
+        let postscript_name = face
+            .names()
+            .into_iter()
+            .filter(|entry| entry.name_id == name_id::POST_SCRIPT_NAME)
+            .find_map(|entry| entry.to_string())
+            .unwrap_or_else(|| name.clone());
+
+        // Symbolic, so viewers use the font's own glyphs rather than a
+        // standard Latin character set.
+        let mut flags = 1 << 2;
+        if face.is_monospaced() {
+            flags |= 1;
+        }
+        if face.is_italic() {
+            flags |= 1 << 6;
+        }
+
+        Ok(Self {
+            postscript_name: pdf_name(&postscript_name),
+            units_per_em,
+            ascent: scale(face.ascender()),
+            descent: scale(face.descender()),
+            cap_height: scale(face.capital_height().unwrap_or(face.ascender())),
+            bbox: [
+                scale(bbox.x_min),
+                scale(bbox.y_min),
+                scale(bbox.x_max),
+                scale(bbox.y_max),
+            ],
+            italic_angle: face.italic_angle(),
+            flags,
+            cff: face.tables().cff.is_some(),
+            data: data.to_vec(),
+            name,
+        })
+    }
+
+    fn face(&self) -> Option<Face<'_>> {
+        Face::parse(&self.data, 0).ok()
+    }
+
+    /// Whether the font has a glyph for every character of `text`.
+    pub(crate) fn covers(&self, text: &str) -> bool {
+        let Some(face) = self.face() else {
+            return false;
+        };
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .all(|c| face.glyph_index(c).is_some())
+    }
+
+    /// Advance width of `text` at `font_size`, in points.
+    pub(crate) fn measure(&self, text: &str, font_size: f32) -> f32 {
+        let Some(face) = self.face() else {
+            return 0.0;
+        };
+        let units: u32 = text
+            .chars()
+            .map(|c| {
+                let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+                face.glyph_hor_advance(glyph).unwrap_or(0) as u32
+            })
+            .sum();
+        units as f32 * font_size / self.units_per_em
+    }
+}
+
+/// Glyphs of one embedded font used by the document.
+pub(crate) struct FontUsage<'a> {
+    pub(crate) font: &'a EmbeddedFont,
+    remapper: GlyphRemapper,
+    /// Character and advance width (in thousandths of an em) of each CID.
+    glyphs: BTreeMap<u16, (char, f32)>,
+}
+
+impl<'a> FontUsage<'a> {
+    pub(crate) fn new(font: &'a EmbeddedFont) -> Self {
+        Self {
+            font,
+            remapper: GlyphRemapper::new(),
+            glyphs: BTreeMap::new(),
+        }
+    }
+
+    /// Whether any text has been encoded with this font.
+    pub(crate) fn is_used(&self) -> bool {
+        !self.glyphs.is_empty()
+    }
+
+    /// Encode text as a hex string of CIDs, recording the glyphs it uses.
+    pub(crate) fn encode(&mut self, text: &str) -> String {
+        let face = self.font.face();
+        let mut output = String::with_capacity(text.len() * 4 + 2);
+        output.push('<');
+        for c in text.chars() {
+            let (glyph, advance) = match &face {
+                Some(face) => {
+                    let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+                    (glyph.0, face.glyph_hor_advance(glyph).unwrap_or(0))
+                }
+                None => (0, 0),
+            };
+            let cid = self.remapper.remap(glyph);
+            self.glyphs
+                .entry(cid)
+                .or_insert((c, advance as f32 * 1000.0 / self.font.units_per_em));
+            let _ = write!(output, "{:04X}", cid);
+        }
+        output.push('>');
+        output
+    }
+
+    /// Subset tag prefixed to the font name, derived from the glyph set so
+    /// different subsets of one font get different names.
+    fn tag(&self) -> String {
+        let mut hash: u32 = 0x811C_9DC5;
+        for glyph in self.remapper.remapped_gids() {
+            for byte in glyph.to_be_bytes() {
+                hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+            }
+        }
+        (0..6)
+            .map(|_| {
+                let letter = (b'A' + (hash % 26) as u8) as char;
+                hash /= 26;
+                letter
+            })
+            .collect()
+    }
+
+    /// Name of the subset font.
+    pub(crate) fn base_font(&self) -> String {
+        format!("{}+{}", self.tag(), self.font.postscript_name)
+    }
+
+    /// Subset the font program to the used glyphs.
+    ///
+    /// Returns the font data and whether it must be embedded as an OpenType
+    /// (CFF) program rather than a TrueType one.
+    pub(crate) fn subset(&self) -> Result<(Vec<u8>, bool), Error> {
+        let data = subsetter::subset(&self.font.data, 0, &self.remapper).map_err(|e| {
+            Error::encoding(format!("Cannot subset font {}: {}", self.font.name, e))
+        })?;
+        Ok((data, self.font.cff))
+    }
+
+    /// The `/W` array giving each CID's advance width.
+    pub(crate) fn widths(&self) -> String {
+        // CIDs are handed out consecutively from zero, so one run covers
+        // them all.
+        let mut widths = String::from("[0 [");
+        for cid in 0..self.remapper.num_gids() {
+            let width = self.glyphs.get(&cid).map_or(0.0, |&(_, width)| width);
+            if cid > 0 {
+                widths.push(' ');
+            }
+            let _ = write!(widths, "{}", width.round() as i32);
+        }
+        widths.push_str("]]");
+        widths
+    }
+
+    /// Font descriptor dictionary entries, without the font file reference.
+    pub(crate) fn descriptor(&self) -> String {
+        let font = self.font;
+        format!(
+            "/Type /FontDescriptor /FontName /{} /Flags {} /FontBBox [{} {} {} {}] \
+             /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV 80",
+            self.base_font(),
+            font.flags,
+            font.bbox[0].round(),
+            font.bbox[1].round(),
+            font.bbox[2].round(),
+            font.bbox[3].round(),
+            font.italic_angle,
+            font.ascent.round(),
+            font.descent.round(),
+            font.cap_height.round()
+        )
+    }
+
+    /// A `ToUnicode` CMap mapping each CID back to the text it came from.
+    pub(crate) fn to_unicode(&self) -> String {
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n\
+             12 dict begin\n\
+             begincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n\
+             /CMapType 2 def\n\
+             1 begincodespacerange\n\
+             <0000> <FFFF>\n\
+             endcodespacerange\n",
+        );
+
+        let entries: Vec<(u16, char)> = self
+            .glyphs
+            .iter()
+            .filter(|&(&cid, _)| cid != 0)
+            .map(|(&cid, &(c, _))| (cid, c))
+            .collect();
+        // bfchar sections are limited to 100 entries each.
+        for chunk in entries.chunks(100) {
+            let _ = writeln!(cmap, "{} beginbfchar", chunk.len());
+            for (cid, c) in chunk {
+                let mut units = [0u16; 2];
+                let utf16 = c
+                    .encode_utf16(&mut units)
+                    .iter()
+                    .map(|unit| format!("{:04X}", unit))
+                    .collect::<String>();
+                let _ = writeln!(cmap, "<{:04X}> <{}>", cid, utf16);
+            }
+            cmap.push_str("endbfchar\n");
+        }
+
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap
+    }
+}
+
+/// Strip characters that are not allowed unescaped in a PDF name.
+fn pdf_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !"()<>[]{}/%#".contains(*c))
+        .collect();
+    if cleaned.is_empty() {
+        "Font".to_string()
+    } else {
+        cleaned
+    }
+}
@@ -1,10 +1,20 @@
 //! PDF generator implementation.
 
+use crate::compose::{self, HeadingAnchor, StandardFont};
 use crate::error::Error;
+use crate::font::{EmbeddedFont, FontUsage};
+use crate::image::{ImageFilter, PdfImage};
+use crate::outline;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use std::collections::BTreeMap;
 use std::io::Write;
 use wolia_core::Document;
+use wolia_layout::PageLayout;
 
-const PDF_HEADER: &[u8] = b"%PDF-1.4\n";
+/// File header. The comment line of high-bit bytes marks the file as binary
+/// for transfer tools, since embedded font programs are.
+const PDF_HEADER: &[u8] = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n";
 
 /// PDF object representing basic elements.
 #[derive(Debug, Clone)]
@@ -13,18 +23,46 @@ struct PdfObject {
     id: u32,
     /// Object content.
     content: String,
+    /// Stream data, for stream objects. `content` holds the stream
+    /// dictionary without its `/Length`.
+    stream: Option<Vec<u8>>,
 }
 
 impl PdfObject {
     /// Create a new PDF object.
     fn new(id: u32, content: String) -> Self {
-        Self { id, content }
+        Self {
+            id,
+            content,
+            stream: None,
+        }
+    }
+
+    /// Create a stream object. `entries` are extra stream dictionary entries.
+    fn stream(id: u32, entries: &str, data: Vec<u8>) -> Self {
+        let separator = if entries.is_empty() { "" } else { " " };
+        Self {
+            id,
+            content: format!("<< /Length {}{}{} >>", data.len(), separator, entries),
+            stream: Some(data),
+        }
     }
 
     /// Serialize the object for the PDF file.
     fn serialize(&self) -> String {
         format!("{} 0 obj\n{}\nendobj\n", self.id, self.content)
     }
+
+    /// Serialize the object, including any binary stream data.
+    fn to_bytes(&self) -> Vec<u8> {
+        let Some(data) = &self.stream else {
+            return self.serialize().into_bytes();
+        };
+        let mut bytes = format!("{} 0 obj\n{}\nstream\n", self.id, self.content).into_bytes();
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(b"\nendstream\nendobj\n");
+        bytes
+    }
 }
 
 /// PDF generator for Wolia documents.
@@ -35,105 +73,482 @@ pub struct PdfGenerator {
     offsets: Vec<u64>,
     /// Current object ID counter.
     next_id: u32,
+    /// Page size and margins used to lay out the document.
+    page_layout: PageLayout,
+    /// Fonts embedded on demand, in registration order.
+    fonts: Vec<EmbeddedFont>,
+    /// Document information set explicitly with `set_metadata`.
+    info: Info,
+    /// Encoded image data, keyed by the `src` of image nodes.
+    images: BTreeMap<String, Vec<u8>>,
+}
+
+/// Entries of the document information dictionary. Empty fields fall back
+/// to the document's own metadata.
+#[derive(Debug, Clone, Default)]
+struct Info {
+    title: String,
+    author: String,
+    subject: String,
+    keywords: String,
 }
 
 impl PdfGenerator {
-    /// Create a new PDF generator.
+    /// Create a new PDF generator producing A4 pages.
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
             offsets: Vec::new(),
             next_id: 1,
+            page_layout: PageLayout::a4(),
+            fonts: Vec::new(),
+            info: Info::default(),
+            images: BTreeMap::new(),
         }
     }
 
-    /// Generate PDF from a document.
-    pub fn generate(&mut self, _document: &Document) -> Result<Vec<u8>, Error> {
-        // Create PDF catalog
-        self.create_catalog()?;
+    /// Use the given page size and margins.
+    pub fn with_page_layout(mut self, page_layout: PageLayout) -> Self {
+        self.page_layout = page_layout;
+        self
+    }
 
-        // Create PDF page structure
-        self.create_pages()?;
+    /// Register a TrueType or OpenType font for embedding.
+    ///
+    /// Text whose style names `name` as its font family is set in this font.
+    /// Text the standard fonts cannot encode also falls back to the first
+    /// registered font that covers it. Only the glyphs a document uses are
+    /// embedded.
+    pub fn with_font(mut self, name: impl Into<String>, data: &[u8]) -> Result<Self, Error> {
+        self.fonts.push(EmbeddedFont::parse(name.into(), data)?);
+        Ok(self)
+    }
 
-        // Create a blank page
-        self.create_page()?;
+    /// Provide the PNG or JPEG data for image nodes whose `src` is `src`.
+    ///
+    /// Image nodes without data are left out of the export.
+    pub fn with_image(mut self, src: impl Into<String>, data: Vec<u8>) -> Self {
+        self.images.insert(src.into(), data);
+        self
+    }
 
-        // Create content stream
-        self.create_content_stream()?;
+    /// Set the title, author, subject and keywords written to the document
+    /// information dictionary.
+    ///
+    /// Empty values are left out, except that the title and author default to
+    /// those in the document's metadata and the subject to its description.
+    pub fn set_metadata(
+        &mut self,
+        title: impl Into<String>,
+        author: impl Into<String>,
+        subject: impl Into<String>,
+        keywords: impl Into<String>,
+    ) {
+        self.info = Info {
+            title: title.into(),
+            author: author.into(),
+            subject: subject.into(),
+            keywords: keywords.into(),
+        };
+    }
 
-        // Serialize to bytes
-        self.serialize()
+    /// Generate PDF from a document.
+    pub fn generate(&mut self, document: &Document) -> Result<Vec<u8>, Error> {
+        self.objects.clear();
+        self.next_id = 1;
+
+        // Composition borrows the fonts and images while objects are being
+        // added.
+        let fonts = std::mem::take(&mut self.fonts);
+        let images = std::mem::take(&mut self.images);
+        let result = self.build(document, &fonts, &images);
+        self.fonts = fonts;
+        self.images = images;
+        let info_id = result?;
+
+        self.serialize(info_id)
     }
 
-    /// Create PDF catalog object.
-    fn create_catalog(&mut self) -> Result<(), Error> {
-        let id = self.next_id;
-        self.next_id += 1;
+    /// Lay out the document and create all of its objects. Returns the ID of
+    /// the document information dictionary.
+    fn build(
+        &mut self,
+        document: &Document,
+        fonts: &[EmbeddedFont],
+        images: &BTreeMap<String, Vec<u8>>,
+    ) -> Result<u32, Error> {
+        let composition = compose::compose(document, &self.page_layout, fonts, images)?;
+        let pages = composition.pages;
+
+        let catalog_id = self.alloc_id();
+        let pages_id = self.alloc_id();
+        let info_id = self.create_info(document)?;
+        let font_ids = self.create_fonts()?;
+        let mut embedded_ids = Vec::new();
+        for (index, usage) in composition.fonts.iter().enumerate() {
+            if usage.is_used() {
+                embedded_ids.push((index, self.create_embedded_font(usage)?));
+            }
+        }
 
-        let content = format!("<< /Type /Catalog /Pages {} 0 R >>", id + 1);
+        // Each page is directly followed by its content stream.
+        let first_page_id = self.next_id;
+        let page_ids: Vec<u32> = (0..pages.len() as u32)
+            .map(|index| first_page_id + index * 2)
+            .collect();
+        let image_ids = composition
+            .images
+            .iter()
+            .map(|image| self.create_image(image))
+            .collect::<Result<Vec<_>, _>>()?;
+        let resources = self.resources(&font_ids, &embedded_ids, &image_ids);
+        for content in &pages {
+            self.create_page(pages_id, &resources)?;
+            self.create_content_stream(content)?;
+        }
+        self.create_pages(pages_id, &page_ids)?;
 
-        self.objects.push(PdfObject::new(id, content));
-        Ok(())
+        let outlines_id = self.create_outlines(&composition.headings, &page_ids)?;
+        self.create_catalog(catalog_id, pages_id, outlines_id)?;
+        Ok(info_id)
     }
 
-    /// Create PDF pages object.
-    fn create_pages(&mut self) -> Result<(), Error> {
+    fn alloc_id(&mut self) -> u32 {
         let id = self.next_id;
         self.next_id += 1;
+        id
+    }
 
-        let content = format!("<< /Type /Pages /Kids [{}] /Count 1 >>", id + 1);
-
+    /// Create PDF catalog object.
+    fn create_catalog(
+        &mut self,
+        id: u32,
+        pages_id: u32,
+        outlines_id: Option<u32>,
+    ) -> Result<(), Error> {
+        let content = match outlines_id {
+            Some(outlines_id) => format!(
+                "<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R /PageMode /UseOutlines >>",
+                pages_id, outlines_id
+            ),
+            None => format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id),
+        };
         self.objects.push(PdfObject::new(id, content));
         Ok(())
     }
 
-    /// Create a page object.
-    fn create_page(&mut self) -> Result<(), Error> {
-        let id = self.next_id;
-        self.next_id += 1;
+    /// Create the document information dictionary.
+    fn create_info(&mut self, document: &Document) -> Result<u32, Error> {
+        let id = self.alloc_id();
+        let metadata = &document.metadata;
+        let or_metadata = |value: &str, fallback: &Option<String>| {
+            if value.is_empty() {
+                fallback.clone().unwrap_or_default()
+            } else {
+                value.to_string()
+            }
+        };
+
+        let mut entries = Vec::new();
+        let fields = [
+            ("Title", or_metadata(&self.info.title, &metadata.title)),
+            ("Author", or_metadata(&self.info.author, &metadata.author)),
+            (
+                "Subject",
+                or_metadata(&self.info.subject, &metadata.description),
+            ),
+            ("Keywords", self.info.keywords.clone()),
+        ];
+        for (key, value) in fields {
+            if !value.is_empty() {
+                entries.push(format!("/{} {}", key, text_string(&value)));
+            }
+        }
+        entries.push(format!("/Producer {}", text_string("Wolia")));
+        if let Some(created) = metadata.created {
+            entries.push(format!("/CreationDate ({})", pdf_date(created)));
+        }
+        if let Some(modified) = metadata.modified {
+            entries.push(format!("/ModDate ({})", pdf_date(modified)));
+        }
+
+        self.objects
+            .push(PdfObject::new(id, format!("<< {} >>", entries.join(" "))));
+        Ok(id)
+    }
 
+    /// Create the outline tree from the document's headings. Returns `None`
+    /// when there is nothing to bookmark.
+    fn create_outlines(
+        &mut self,
+        headings: &[HeadingAnchor],
+        page_ids: &[u32],
+    ) -> Result<Option<u32>, Error> {
+        let items = outline::build(headings);
+        if items.is_empty() {
+            return Ok(None);
+        }
+
+        let root_id = self.alloc_id();
+        let ids: Vec<u32> = items.iter().map(|_| self.alloc_id()).collect();
+        let roots = outline::roots(&items);
+
+        // Links to the previous and next sibling of an item.
+        let siblings = |list: &[usize], position: usize| {
+            let mut links = String::new();
+            if position > 0 {
+                links.push_str(&format!(" /Prev {} 0 R", ids[list[position - 1]]));
+            }
+            if let Some(&next) = list.get(position + 1) {
+                links.push_str(&format!(" /Next {} 0 R", ids[next]));
+            }
+            links
+        };
+
+        for (index, item) in items.iter().enumerate() {
+            let (parent_id, list) = match item.parent {
+                Some(parent) => (ids[parent], items[parent].children.as_slice()),
+                None => (root_id, roots.as_slice()),
+            };
+            let position = list.iter().position(|&i| i == index).unwrap_or(0);
+
+            let mut content = format!(
+                "<< /Title {} /Parent {} 0 R{}",
+                text_string(&item.title),
+                parent_id,
+                siblings(list, position)
+            );
+            if let (Some(&first), Some(&last)) = (item.children.first(), item.children.last()) {
+                content.push_str(&format!(
+                    " /First {} 0 R /Last {} 0 R /Count {}",
+                    ids[first],
+                    ids[last],
+                    outline::descendant_count(&items, index)
+                ));
+            }
+            let page_id = page_ids
+                .get(item.destination.page)
+                .or(page_ids.last())
+                .ok_or_else(|| Error::generation("Outline item points past the last page"))?;
+            content.push_str(&format!(
+                " /Dest [{} 0 R /XYZ null {} null] >>",
+                page_id,
+                item.destination.top.round()
+            ));
+            self.objects.push(PdfObject::new(ids[index], content));
+        }
+
+        let (first, last) = (roots[0], roots[roots.len() - 1]);
+        self.objects.push(PdfObject::new(
+            root_id,
+            format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                ids[first],
+                ids[last],
+                items.len()
+            ),
+        ));
+        Ok(Some(root_id))
+    }
+
+    /// Create the page tree root.
+    fn create_pages(&mut self, id: u32, page_ids: &[u32]) -> Result<(), Error> {
+        let kids = page_ids
+            .iter()
+            .map(|page_id| format!("{} 0 R", page_id))
+            .collect::<Vec<_>>()
+            .join(" ");
         let content = format!(
-            "<<\n  /Type /Page\n  /Parent {} 0 R\n  /MediaBox [0 0 612 792]\n  /Contents {} 0 R\n>>",
-            id - 1,
-            id + 1
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids,
+            page_ids.len()
         );
-
         self.objects.push(PdfObject::new(id, content));
         Ok(())
     }
 
-    /// Create a content stream with basic PDF content.
-    fn create_content_stream(&mut self) -> Result<(), Error> {
-        let id = self.next_id;
-        self.next_id += 1;
+    /// Create a font dictionary for each standard font.
+    fn create_fonts(&mut self) -> Result<Vec<u32>, Error> {
+        let ids = StandardFont::ALL
+            .iter()
+            .map(|font| {
+                let id = self.alloc_id();
+                let content = format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    font.base_font()
+                );
+                self.objects.push(PdfObject::new(id, content));
+                id
+            })
+            .collect();
+        Ok(ids)
+    }
 
-        // Create a simple content stream with text
-        let stream_content = "BT\n/F1 12 Tf\n100 700 Td\n(Wolia Document) Tj\nET\n";
-        let stream_length = stream_content.len();
+    /// Create the objects of an embedded font subset and return the ID of
+    /// its top-level Type0 font.
+    fn create_embedded_font(&mut self, usage: &FontUsage<'_>) -> Result<u32, Error> {
+        let type0_id = self.alloc_id();
+        let cid_font_id = self.alloc_id();
+        let descriptor_id = self.alloc_id();
+        let file_id = self.alloc_id();
+        let to_unicode_id = self.alloc_id();
+        let base_font = usage.base_font();
+
+        self.objects.push(PdfObject::new(
+            type0_id,
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+                 /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+                base_font, cid_font_id, to_unicode_id
+            ),
+        ));
+
+        let (program, cff) = usage.subset()?;
+        let (cid_subtype, file_key) = if cff {
+            ("CIDFontType0", "FontFile3")
+        } else {
+            ("CIDFontType2", "FontFile2")
+        };
+        // CIDs are glyph IDs in the subset font.
+        let gid_map = if cff { "" } else { " /CIDToGIDMap /Identity" };
+        self.objects.push(PdfObject::new(
+            cid_font_id,
+            format!(
+                "<< /Type /Font /Subtype /{} /BaseFont /{} \
+                 /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+                 /FontDescriptor {} 0 R /W {}{} >>",
+                cid_subtype,
+                base_font,
+                descriptor_id,
+                usage.widths(),
+                gid_map
+            ),
+        ));
+        self.objects.push(PdfObject::new(
+            descriptor_id,
+            format!("<< {} /{} {} 0 R >>", usage.descriptor(), file_key, file_id),
+        ));
+
+        let entries = if cff {
+            "/Filter /FlateDecode /Subtype /OpenType".to_string()
+        } else {
+            format!("/Filter /FlateDecode /Length1 {}", program.len())
+        };
+        self.objects
+            .push(PdfObject::stream(file_id, &entries, deflate(&program)?));
+        self.objects.push(PdfObject::stream(
+            to_unicode_id,
+            "/Filter /FlateDecode",
+            deflate(usage.to_unicode().as_bytes())?,
+        ));
+
+        Ok(type0_id)
+    }
 
-        let content = format!(
-            "<< /Length {} >>\nstream\n{}\nendstream",
-            stream_length, stream_content
+    /// Create an image XObject, and its soft mask if it has one.
+    fn create_image(&mut self, image: &PdfImage) -> Result<u32, Error> {
+        let id = self.alloc_id();
+        let mut entries = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} \
+             /BitsPerComponent 8",
+            image.width, image.height, image.color_space
         );
+        entries.push_str(match image.filter {
+            ImageFilter::Dct => " /Filter /DCTDecode",
+            ImageFilter::Flate => " /Filter /FlateDecode",
+        });
+        if image.inverted {
+            entries.push_str(" /Decode [1 0 1 0 1 0 1 0]");
+        }
+
+        if let Some(alpha) = &image.soft_mask {
+            let mask_id = self.alloc_id();
+            entries.push_str(&format!(" /SMask {} 0 R", mask_id));
+            self.objects.push(PdfObject::stream(
+                mask_id,
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                     /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+                    image.width, image.height
+                ),
+                alpha.clone(),
+            ));
+        }
+
+        self.objects
+            .push(PdfObject::stream(id, &entries, image.data.clone()));
+        Ok(id)
+    }
+
+    fn resources(
+        &self,
+        font_ids: &[u32],
+        embedded_ids: &[(usize, u32)],
+        image_ids: &[u32],
+    ) -> String {
+        let standard = StandardFont::ALL
+            .iter()
+            .zip(font_ids)
+            .map(|(font, id)| format!("/{} {} 0 R", font.resource_name(), id));
+        let embedded = embedded_ids
+            .iter()
+            .map(|&(index, id)| format!("/{} {} 0 R", compose::embedded_resource_name(index), id));
+        let fonts = standard.chain(embedded).collect::<Vec<_>>().join(" ");
+        if image_ids.is_empty() {
+            return format!("<< /Font << {} >> >>", fonts);
+        }
+
+        let images = image_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| format!("/{} {} 0 R", compose::image_resource_name(index), id))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!("<< /Font << {} >> /XObject << {} >> >>", fonts, images)
+    }
 
+    /// Create a page object whose content stream is the next object.
+    fn create_page(&mut self, parent_id: u32, resources: &str) -> Result<(), Error> {
+        let id = self.alloc_id();
+        let size = self.page_layout.size;
+        let content = format!(
+            "<<\n  /Type /Page\n  /Parent {} 0 R\n  /MediaBox [0 0 {} {}]\n  /Resources {}\n  /Contents {} 0 R\n>>",
+            parent_id,
+            size.width,
+            size.height,
+            resources,
+            id + 1
+        );
         self.objects.push(PdfObject::new(id, content));
         Ok(())
     }
 
+    /// Create a content stream object.
+    fn create_content_stream(&mut self, stream_content: &str) -> Result<(), Error> {
+        let id = self.alloc_id();
+        self.objects.push(PdfObject::stream(
+            id,
+            "",
+            stream_content.as_bytes().to_vec(),
+        ));
+        Ok(())
+    }
+
     /// Serialize the PDF to bytes.
-    fn serialize(&mut self) -> Result<Vec<u8>, Error> {
+    fn serialize(&mut self, info_id: u32) -> Result<Vec<u8>, Error> {
         let mut output = Vec::new();
 
         // Write PDF header
         output.write_all(PDF_HEADER).map_err(Error::Io)?;
 
+        // Objects may be created out of order; the xref table lists them by ID.
+        self.objects.sort_by_key(|obj| obj.id);
+
         // Track offsets for xref
         self.offsets.clear();
         for obj in &self.objects {
             self.offsets.push(output.len() as u64);
-            output
-                .write_all(obj.serialize().as_bytes())
-                .map_err(Error::Io)?;
+            output.write_all(&obj.to_bytes()).map_err(Error::Io)?;
         }
 
         // Write xref table
@@ -145,8 +560,9 @@ impl PdfGenerator {
 
         // Write trailer
         let trailer = format!(
-            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
             self.objects.len() + 1,
+            info_id,
             xref_offset
         );
         output.write_all(trailer.as_bytes()).map_err(Error::Io)?;
@@ -168,6 +584,60 @@ impl PdfGenerator {
     }
 }
 
+/// Encode a PDF text string: a literal string when the text is printable
+/// ASCII, otherwise UTF-16BE with a byte order mark.
+fn text_string(text: &str) -> String {
+    if text.chars().all(|c| (' '..='~').contains(&c)) {
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('(', "\\(")
+            .replace(')', "\\)");
+        format!("({})", escaped)
+    } else {
+        let hex: String = text
+            .encode_utf16()
+            .map(|unit| format!("{:04X}", unit))
+            .collect();
+        format!("<FEFF{}>", hex)
+    }
+}
+
+/// Format milliseconds since the Unix epoch as a PDF date in UTC.
+fn pdf_date(millis: i64) -> String {
+    let seconds = millis.div_euclid(1000);
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Compress stream data with zlib for `/FlateDecode`.
+pub(crate) fn deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
 impl Default for PdfGenerator {
     fn default() -> Self {
         Self::new()
@@ -199,4 +669,74 @@ mod tests {
         assert!(xref.contains("xref"));
         assert!(xref.contains("f"));
     }
+
+    const TUFFY: &[u8] = include_bytes!("../../../test-suite/fonts/Tuffy.ttf");
+
+    #[test]
+    fn test_embeds_font_subset() {
+        use wolia_core::{Node, Text};
+
+        let mut document = Document::new();
+        document
+            .root
+            .add_child(Node::paragraph(Text::new("Ωμέγα café")));
+
+        let mut generator = PdfGenerator::new().with_font("Tuffy", TUFFY).unwrap();
+        let bytes = generator.generate(&document).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+
+        assert!(pdf.contains("/Subtype /CIDFontType2"));
+        assert!(pdf.contains("/FontFile2"));
+        assert!(pdf.contains("/ToUnicode"));
+        assert!(pdf.contains("/Encoding /Identity-H"));
+        assert!(pdf.contains("+Tuffy"));
+
+        // Only the used glyphs are embedded, so the subset is much smaller
+        // than the font.
+        assert!(bytes.len() < TUFFY.len() / 4);
+
+        // Every CID has a real advance width.
+        let widths = pdf.split("/W [0 [").nth(1).unwrap();
+        let widths: Vec<i32> = widths
+            .split("]]")
+            .next()
+            .unwrap()
+            .split(' ')
+            .map(|w| w.parse().unwrap())
+            .collect();
+        assert!(widths[1..].iter().all(|&w| w > 0), "{:?}", widths);
+    }
+
+    #[test]
+    fn test_to_unicode_maps_cids_back_to_text() {
+        let font = EmbeddedFont::parse("Tuffy".to_string(), TUFFY).unwrap();
+        assert!(font.covers("Ωμέγα café"));
+        let mut usage = FontUsage::new(&font);
+        assert_eq!(usage.encode("aba"), "<000100020001>");
+        let cmap = usage.to_unicode();
+        assert!(cmap.contains("2 beginbfchar"));
+        assert!(cmap.contains("<0001> <0061>"));
+        assert!(cmap.contains("<0002> <0062>"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_font() {
+        assert!(
+            PdfGenerator::new()
+                .with_font("Broken", b"not a font")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_pdf_date() {
+        assert_eq!(pdf_date(0), "D:19700101000000Z");
+        assert_eq!(pdf_date(1_709_210_096_000), "D:20240229123456Z");
+    }
+
+    #[test]
+    fn test_text_string() {
+        assert_eq!(text_string("A (draft)"), "(A \\(draft\\))");
+        assert_eq!(text_string("Ω"), "<FEFF03A9>");
+    }
 }
@@ -0,0 +1,254 @@
+//! Raster images.
+//!
+//! JPEG data is embedded as is and decoded by the viewer (`DCTDecode`);
+//! anything else is decoded here and embedded as compressed samples
+//! (`FlateDecode`), with the alpha channel split out into a soft mask.
+
+use crate::error::Error;
+use crate::generator::deflate;
+
+/// Resolution assumed for images that do not record one.
+pub(crate) const DEFAULT_DPI: f32 = 96.0;
+
+/// How the image samples are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageFilter {
+    /// Baseline or progressive JPEG data.
+    Dct,
+    /// zlib-compressed samples.
+    Flate,
+}
+
+/// An image ready to be written as an image XObject.
+#[derive(Debug, Clone)]
+pub(crate) struct PdfImage {
+    /// Width in pixels.
+    pub(crate) width: u32,
+    /// Height in pixels.
+    pub(crate) height: u32,
+    /// Horizontal and vertical resolution in dots per inch.
+    pub(crate) dpi: (f32, f32),
+    /// PDF color space name.
+    pub(crate) color_space: &'static str,
+    /// Whether the samples are stored inverted (Adobe CMYK JPEGs).
+    pub(crate) inverted: bool,
+    pub(crate) filter: ImageFilter,
+    /// Encoded sample data.
+    pub(crate) data: Vec<u8>,
+    /// zlib-compressed 8-bit alpha samples, if the image is not opaque.
+    pub(crate) soft_mask: Option<Vec<u8>>,
+}
+
+impl PdfImage {
+    /// Decode PNG or JPEG data.
+    pub(crate) fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.starts_with(&[0xFF, 0xD8]) {
+            Self::from_jpeg(data)
+        } else {
+            Self::from_raster(data)
+        }
+    }
+
+    /// Natural size in points, from the pixel size and resolution.
+    pub(crate) fn size(&self) -> (f32, f32) {
+        (
+            self.width as f32 * 72.0 / self.dpi.0,
+            self.height as f32 * 72.0 / self.dpi.1,
+        )
+    }
+
+    fn from_jpeg(data: &[u8]) -> Result<Self, Error> {
+        let header = jpeg_header(data)
+            .ok_or_else(|| Error::encoding("Cannot read JPEG header".to_string()))?;
+        let color_space = match header.components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            4 => "DeviceCMYK",
+            n => {
+                return Err(Error::encoding(format!(
+                    "Unsupported JPEG with {} components",
+                    n
+                )));
+            }
+        };
+        Ok(Self {
+            width: header.width,
+            height: header.height,
+            dpi: header.dpi.unwrap_or((DEFAULT_DPI, DEFAULT_DPI)),
+            color_space,
+            inverted: header.components == 4 && header.adobe,
+            filter: ImageFilter::Dct,
+            data: data.to_vec(),
+            soft_mask: None,
+        })
+    }
+
+    fn from_raster(data: &[u8]) -> Result<Self, Error> {
+        let image = image::load_from_memory(data)
+            .map_err(|e| Error::encoding(format!("Cannot decode image: {}", e)))?;
+        let (width, height) = (image.width(), image.height());
+        let dpi = png_dpi(data).unwrap_or((DEFAULT_DPI, DEFAULT_DPI));
+        let has_alpha = image.color().has_alpha();
+        let grayscale = !image.color().has_color();
+
+        let (color_space, samples, alpha) = if grayscale {
+            let pixels = image.to_luma_alpha8();
+            let (gray, alpha) = split_alpha(pixels.as_raw(), 1);
+            ("DeviceGray", gray, alpha)
+        } else {
+            let pixels = image.to_rgba8();
+            let (rgb, alpha) = split_alpha(pixels.as_raw(), 3);
+            ("DeviceRGB", rgb, alpha)
+        };
+
+        // Fully opaque alpha channels are dropped rather than masked.
+        let soft_mask = if has_alpha && alpha.iter().any(|&a| a != u8::MAX) {
+            Some(deflate(&alpha)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            width,
+            height,
+            dpi,
+            color_space,
+            inverted: false,
+            filter: ImageFilter::Flate,
+            data: deflate(&samples)?,
+            soft_mask,
+        })
+    }
+}
+
+/// Split interleaved color+alpha samples into color and alpha planes.
+fn split_alpha(pixels: &[u8], color_channels: usize) -> (Vec<u8>, Vec<u8>) {
+    let stride = color_channels + 1;
+    let mut color = Vec::with_capacity(pixels.len() / stride * color_channels);
+    let mut alpha = Vec::with_capacity(pixels.len() / stride);
+    for pixel in pixels.chunks_exact(stride) {
+        color.extend_from_slice(&pixel[..color_channels]);
+        alpha.push(pixel[color_channels]);
+    }
+    (color, alpha)
+}
+
+/// What the PDF needs from a JPEG's headers.
+struct JpegHeader {
+    width: u32,
+    height: u32,
+    components: u8,
+    dpi: Option<(f32, f32)>,
+    /// Whether an Adobe APP14 segment is present.
+    adobe: bool,
+}
+
+/// Scan JPEG marker segments up to the frame header.
+fn jpeg_header(data: &[u8]) -> Option<JpegHeader> {
+    let mut position = 2;
+    let mut dpi = None;
+    let mut adobe = false;
+
+    loop {
+        // Markers may be preceded by any number of fill bytes.
+        while *data.get(position)? == 0xFF && *data.get(position + 1)? == 0xFF {
+            position += 1;
+        }
+        if *data.get(position)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(position + 1)?;
+        let length = u16::from_be_bytes([*data.get(position + 2)?, *data.get(position + 3)?]);
+        let segment = data.get(position + 4..position + 2 + length as usize)?;
+
+        match marker {
+            // JFIF: density units, then x and y density.
+            0xE0 if segment.starts_with(b"JFIF\0") && segment.len() >= 12 => {
+                let x = u16::from_be_bytes([segment[8], segment[9]]) as f32;
+                let y = u16::from_be_bytes([segment[10], segment[11]]) as f32;
+                dpi = match segment[7] {
+                    1 if x > 0.0 && y > 0.0 => Some((x, y)),
+                    2 if x > 0.0 && y > 0.0 => Some((x * 2.54, y * 2.54)),
+                    _ => dpi,
+                };
+            }
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            // Start of frame (any kind except DHT, JPG and DAC).
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if segment.len() < 6 {
+                    return None;
+                }
+                return Some(JpegHeader {
+                    height: u16::from_be_bytes([segment[1], segment[2]]) as u32,
+                    width: u16::from_be_bytes([segment[3], segment[4]]) as u32,
+                    components: segment[5],
+                    dpi,
+                    adobe,
+                });
+            }
+            // Start of scan before any frame header.
+            0xDA => return None,
+            _ => {}
+        }
+        position += 2 + length as usize;
+    }
+}
+
+/// Read the resolution from a PNG's `pHYs` chunk, if it is in meters.
+fn png_dpi(data: &[u8]) -> Option<(f32, f32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) {
+        return None;
+    }
+
+    let mut position = SIGNATURE.len();
+    while let Some(header) = data.get(position..position + 8) {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = &header[4..8];
+        let body = data.get(position + 8..position + 8 + length)?;
+        match kind {
+            b"pHYs" if body.len() >= 9 && body[8] == 1 => {
+                let x = u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as f32;
+                let y = u32::from_be_bytes([body[4], body[5], body[6], body[7]]) as f32;
+                if x > 0.0 && y > 0.0 {
+                    return Some((x * 0.0254, y * 0.0254));
+                }
+                return None;
+            }
+            // pHYs must come before the image data.
+            b"IDAT" | b"IEND" => return None,
+            _ => {}
+        }
+        // Length, type, body and CRC.
+        position += 12 + length;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jpeg_header() {
+        // SOI, JFIF APP0 at 300 dpi, then a baseline frame header of 40x20
+        // with three components.
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0");
+        jpeg.extend_from_slice(&[1, 1, 1, 0x01, 0x2C, 0x01, 0x2C, 0, 0]);
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 8, 0, 20, 0, 40, 3]);
+        jpeg.extend_from_slice(&[1, 0x11, 0, 2, 0x11, 1, 3, 0x11, 1]);
+
+        let image = PdfImage::decode(&jpeg).unwrap();
+        assert_eq!((image.width, image.height), (40, 20));
+        assert_eq!(image.color_space, "DeviceRGB");
+        assert_eq!(image.filter, ImageFilter::Dct);
+        assert_eq!(image.size(), (9.6, 4.8));
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(PdfImage::decode(b"not an image").is_err());
+        assert!(PdfImage::decode(&[0xFF, 0xD8, 0xFF]).is_err());
+    }
+}
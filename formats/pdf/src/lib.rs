@@ -8,8 +8,12 @@ use wolia_core::Document;
 pub use self::error::Error;
 pub use self::generator::PdfGenerator;
 
+mod compose;
 mod error;
+mod font;
 mod generator;
+mod image;
+mod outline;
 
 /// Export a document to PDF format.
 ///
@@ -51,4 +55,130 @@ mod tests {
         let result = export(&doc);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_export_paginates_long_document() {
+        use wolia_core::{Node, Text};
+
+        let mut doc = Document::new();
+        doc.root
+            .add_child(Node::heading(1, Text::new("Quarterly report")));
+        let sentence = "The quick brown fox jumps over the lazy dog. ";
+        for _ in 0..40 {
+            doc.root
+                .add_child(Node::paragraph(Text::new(sentence.repeat(8))));
+        }
+
+        let bytes = export(&doc).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        let pages = pdf.matches("/Type /Page\n").count();
+        assert!(pages > 1, "expected several pages, got {}", pages);
+        assert!(pdf.contains(&format!("/Count {}", pages)));
+        assert!(pdf.contains("/BaseFont /Helvetica-Bold"));
+        assert!(pdf.contains("(Quarterly report) Tj"));
+    }
+
+    #[test]
+    fn test_export_page_breaks() {
+        use wolia_core::{Node, NodeKind, Text};
+
+        let mut doc = Document::new();
+        for text in ["One", "Two", "Three"] {
+            if !doc.root.children.is_empty() {
+                doc.root.add_child(Node::new(NodeKind::PageBreak));
+            }
+            doc.root.add_child(Node::paragraph(Text::new(text)));
+        }
+
+        let bytes = export(&doc).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert_eq!(pdf.matches("/Type /Page\n").count(), 3);
+        assert!(pdf.contains("/Count 3"));
+    }
+
+    #[test]
+    fn test_export_metadata_and_outline() {
+        use wolia_core::{Node, NodeKind, Text};
+
+        let mut doc = Document::new();
+        doc.root
+            .add_child(Node::heading(1, Text::new("Introduction")));
+        doc.root.add_child(Node::paragraph(Text::new("Opening.")));
+        doc.root
+            .add_child(Node::heading(2, Text::new("Background")));
+        doc.root.add_child(Node::new(NodeKind::PageBreak));
+        doc.root.add_child(Node::heading(1, Text::new("Results")));
+
+        let mut generator = PdfGenerator::new();
+        generator.set_metadata("Annual Report", "Jo Doe", "Finance", "report, 2024");
+        let bytes = generator.generate(&doc).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+
+        assert!(pdf.contains("/Title (Annual Report)"));
+        assert!(pdf.contains("/Author (Jo Doe)"));
+        assert!(pdf.contains("/Keywords (report, 2024)"));
+        assert!(pdf.contains("/Type /Outlines"));
+        assert!(pdf.contains("/Count 3 >>"));
+        assert!(pdf.contains("/Title (Background)"));
+        // "Background" nests under "Introduction".
+        assert!(pdf.contains("/Count 1 /Dest"));
+
+        // "Results" starts the second page.
+        let second_page = pdf
+            .split("/Kids [")
+            .nth(1)
+            .and_then(|kids| kids.split(" 0 R").nth(1))
+            .unwrap()
+            .trim()
+            .to_string();
+        let results = pdf.split("/Title (Results)").nth(1).unwrap();
+        let results = results.split(">>").next().unwrap();
+        assert!(results.contains(&format!("/Dest [{} 0 R /XYZ", second_page)));
+    }
+
+    #[test]
+    fn test_export_png_image() {
+        use wolia_core::{Node, NodeKind};
+
+        // A 4x2 PNG whose right half is translucent.
+        let mut pixels = ::image::RgbaImage::new(4, 2);
+        for (x, _, pixel) in pixels.enumerate_pixels_mut() {
+            *pixel = ::image::Rgba([255, 0, 0, if x < 2 { 255 } else { 128 }]);
+        }
+        let mut png = Vec::new();
+        pixels
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                ::image::ImageFormat::Png,
+            )
+            .unwrap();
+
+        let mut doc = Document::new();
+        doc.root.add_child(Node::new(NodeKind::Image {
+            src: "chart.png".to_string(),
+            alt: None,
+        }));
+        doc.root.add_child(Node::new(NodeKind::Image {
+            src: "missing.png".to_string(),
+            alt: None,
+        }));
+
+        let bytes = PdfGenerator::new()
+            .with_image("chart.png", png)
+            .generate(&doc)
+            .unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+
+        assert_eq!(
+            pdf.matches("/Subtype /Image /Width 4 /Height 2 /ColorSpace /DeviceRGB")
+                .count(),
+            1
+        );
+        assert!(pdf.contains("/SMask"));
+        assert!(pdf.contains("/XObject << /Im1"));
+        // 96 dpi pixels are three quarters of a point, keeping the 2:1
+        // aspect ratio.
+        assert!(pdf.contains("3 0 0 1.5 72 "));
+        assert_eq!(pdf.matches("/Im1 Do").count(), 1);
+    }
 }
@@ -0,0 +1,112 @@
+//! Document outline (bookmarks).
+//!
+//! Headings become outline items nested by level: an item's parent is the
+//! closest preceding heading of a lower level. A document that skips levels
+//! or starts with a deeper heading still nests sensibly, since a heading
+//! with no shallower predecessor is simply placed at the top level.
+
+use crate::compose::{Destination, HeadingAnchor};
+
+/// Deepest heading level that gets a bookmark.
+pub(crate) const MAX_LEVEL: u8 = 2;
+
+/// An item of the outline tree.
+#[derive(Debug, Clone)]
+pub(crate) struct OutlineItem {
+    pub(crate) title: String,
+    pub(crate) destination: Destination,
+    /// Index of the parent item, if any.
+    pub(crate) parent: Option<usize>,
+    /// Indices of the child items, in order.
+    pub(crate) children: Vec<usize>,
+}
+
+/// Build the outline from a document's headings.
+///
+/// Items are returned in document order, so a parent always comes before
+/// its children. Headings with no text are left out.
+pub(crate) fn build(headings: &[HeadingAnchor]) -> Vec<OutlineItem> {
+    let mut items: Vec<OutlineItem> = Vec::new();
+    // Open ancestors of the next item, as (level, index).
+    let mut stack: Vec<(u8, usize)> = Vec::new();
+
+    for heading in headings {
+        if heading.level > MAX_LEVEL || heading.title.is_empty() {
+            continue;
+        }
+        while stack
+            .last()
+            .is_some_and(|&(level, _)| level >= heading.level)
+        {
+            stack.pop();
+        }
+
+        let index = items.len();
+        let parent = stack.last().map(|&(_, parent)| parent);
+        if let Some(parent) = parent {
+            items[parent].children.push(index);
+        }
+        items.push(OutlineItem {
+            title: heading.title.clone(),
+            destination: heading.destination,
+            parent,
+            children: Vec::new(),
+        });
+        stack.push((heading.level, index));
+    }
+
+    items
+}
+
+/// Indices of the top-level items.
+pub(crate) fn roots(items: &[OutlineItem]) -> Vec<usize> {
+    (0..items.len())
+        .filter(|&index| items[index].parent.is_none())
+        .collect()
+}
+
+/// Number of descendants of an item, all of which are shown open.
+pub(crate) fn descendant_count(items: &[OutlineItem], index: usize) -> usize {
+    items[index]
+        .children
+        .iter()
+        .map(|&child| 1 + descendant_count(items, child))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(level: u8, title: &str) -> HeadingAnchor {
+        HeadingAnchor {
+            level,
+            title: title.to_string(),
+            destination: Destination { page: 0, top: 0.0 },
+        }
+    }
+
+    #[test]
+    fn test_nests_by_level() {
+        let items = build(&[
+            heading(1, "Intro"),
+            heading(2, "Scope"),
+            heading(3, "Too deep"),
+            heading(2, "Terms"),
+            heading(1, "Body"),
+        ]);
+        assert_eq!(items.len(), 4);
+        assert_eq!(roots(&items), vec![0, 3]);
+        assert_eq!(items[0].children, vec![1, 2]);
+        assert_eq!(descendant_count(&items, 0), 2);
+        assert_eq!(items[2].parent, Some(0));
+    }
+
+    #[test]
+    fn test_out_of_order_and_empty_headings() {
+        let items = build(&[heading(2, "Preface"), heading(1, ""), heading(1, "One")]);
+        assert_eq!(items.len(), 2);
+        assert_eq!(roots(&items), vec![0, 1]);
+        assert!(items.iter().all(|item| item.children.is_empty()));
+    }
+}
@@ -4,16 +4,37 @@
 
 use deck_engine::Presentation;
 
+mod reader;
+mod writer;
+
+/// DrawingML main namespace.
+pub(crate) const NS_A: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+/// Office document relationships namespace.
+pub(crate) const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+/// PresentationML main namespace.
+pub(crate) const NS_P: &str = "http://schemas.openxmlformats.org/presentationml/2006/main";
+
+/// English Metric Units per typographic point.
+const EMU_PER_POINT: f32 = 12_700.0;
+
+/// Convert points to EMUs.
+pub(crate) fn pt_to_emu(points: f32) -> i64 {
+    (points * EMU_PER_POINT).round() as i64
+}
+
+/// Convert EMUs to points.
+pub(crate) fn emu_to_pt(emu: i64) -> f32 {
+    emu as f32 / EMU_PER_POINT
+}
+
 /// Read a presentation from .pptx format.
-pub fn read(_data: &[u8]) -> Result<Presentation, Error> {
-    // TODO: Implement OOXML parsing
-    Ok(Presentation::new())
+pub fn read(data: &[u8]) -> Result<Presentation, Error> {
+    reader::read(data)
 }
 
 /// Write a presentation to .pptx format.
-pub fn write(_presentation: &Presentation) -> Result<Vec<u8>, Error> {
-    // TODO: Implement OOXML generation
-    Ok(Vec::new())
+pub fn write(presentation: &Presentation) -> Result<Vec<u8>, Error> {
+    writer::write(presentation)
 }
 
 /// Format errors.
@@ -31,3 +52,114 @@ pub enum Error {
     #[error("Invalid format")]
     InvalidFormat,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use deck_engine::shape::{Shape, ShapeKind};
+    use wolia_core::style::TextStyle;
+    use wolia_core::text::{Span, Text};
+    use wolia_math::Rect;
+
+    use super::*;
+
+    fn two_slide_presentation() -> Presentation {
+        let mut presentation = Presentation::new();
+        presentation.metadata.title = Some("Quarterly <Review>".to_string());
+
+        let first = presentation.slide_mut(0).unwrap();
+        first.add_shape(Shape::text_box(
+            Rect::new(72.0, 36.0, 400.0, 60.0),
+            Text::new("Hello & welcome\nSecond line"),
+        ));
+        // Notes keep their formatting.
+        let mut notes = Text::new("Greet the audience");
+        notes.add_span(Span::new(
+            0,
+            5,
+            TextStyle {
+                font_weight: Some(700),
+                ..TextStyle::default()
+            },
+        ));
+        first.set_notes(notes);
+
+        let index = presentation.add_slide();
+        let second = presentation.slide_mut(index).unwrap();
+        let mut ellipse = Shape::ellipse(Rect::new(100.0, 100.0, 200.0, 150.0));
+        ellipse.rotation = 45.0;
+        ellipse.style.fill = Some([255, 0, 0, 255]);
+        second.add_shape(ellipse);
+
+        presentation
+    }
+
+    #[test]
+    fn test_write_contains_expected_parts() {
+        let bytes = write(&two_slide_presentation()).unwrap();
+        let archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+
+        for part in [
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "ppt/presentation.xml",
+            "ppt/_rels/presentation.xml.rels",
+            "ppt/slideMasters/slideMaster1.xml",
+            "ppt/slideLayouts/slideLayout1.xml",
+            "ppt/theme/theme1.xml",
+            "ppt/slides/slide1.xml",
+            "ppt/slides/slide2.xml",
+            "ppt/slides/_rels/slide1.xml.rels",
+            "ppt/notesSlides/notesSlide1.xml",
+            "ppt/notesMasters/notesMaster1.xml",
+        ] {
+            assert!(names.contains(&part), "missing part {}", part);
+        }
+        assert!(!names.contains(&"ppt/notesSlides/notesSlide2.xml"));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bytes = write(&two_slide_presentation()).unwrap();
+        let presentation = read(&bytes).unwrap();
+
+        assert_eq!(presentation.slide_count(), 2);
+        assert_eq!(
+            presentation.metadata.title.as_deref(),
+            Some("Quarterly <Review>")
+        );
+        assert_eq!(presentation.slide_size.width, 1920.0);
+
+        let first = presentation.slide(0).unwrap();
+        assert_eq!(first.notes().content, "Greet the audience");
+        let bold = first
+            .notes()
+            .spans
+            .iter()
+            .find(|span| span.start == 0)
+            .unwrap();
+        assert_eq!((bold.end, bold.style.font_weight), (5, Some(700)));
+        assert_eq!(first.shapes.len(), 1);
+        match &first.shapes[0].kind {
+            ShapeKind::TextBox(text) => assert_eq!(text.content, "Hello & welcome\nSecond line"),
+            other => panic!("expected text box, got {:?}", other),
+        }
+        assert_eq!(first.shapes[0].bounds, Rect::new(72.0, 36.0, 400.0, 60.0));
+
+        let second = presentation.slide(1).unwrap();
+        assert!(second.notes().is_empty());
+        let ellipse = &second.shapes[0];
+        assert!(matches!(ellipse.kind, ShapeKind::Ellipse));
+        assert_eq!(ellipse.rotation, 45.0);
+        assert_eq!(ellipse.style.fill, Some([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_emu_conversion() {
+        assert_eq!(pt_to_emu(1.0), 12_700);
+        assert_eq!(pt_to_emu(72.0), 914_400);
+        assert_eq!(emu_to_pt(914_400), 72.0);
+    }
+}
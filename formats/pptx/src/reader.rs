@@ -0,0 +1,583 @@
+//! PPTX package parsing.
+//!
+//! Reads the presentation part, follows its relationships to each slide in
+//! `sldIdLst` order, and rebuilds shapes, backgrounds, and speaker notes.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+use deck_engine::Presentation;
+use deck_engine::shape::{Shape, ShapeKind, ShapeStyle};
+use deck_engine::slide::{Background, Slide};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use wolia_core::style::TextStyle;
+use wolia_core::text::{Span, Text};
+use wolia_math::Rect;
+use zip::ZipArchive;
+
+use crate::{Error, emu_to_pt};
+
+type Archive = ZipArchive<Cursor<Vec<u8>>>;
+
+/// Parse a `.pptx` package into a presentation.
+pub fn read(data: &[u8]) -> Result<Presentation, Error> {
+    let mut archive = ZipArchive::new(Cursor::new(data.to_vec()))?;
+
+    let rels = read_relationships(&mut archive, "ppt/_rels/presentation.xml.rels")?;
+    let info = parse_presentation(&read_part(&mut archive, "ppt/presentation.xml")?)?;
+
+    let mut presentation = match info.size {
+        Some((width, height)) => Presentation::with_size(emu_to_pt(width), emu_to_pt(height)),
+        None => Presentation::new(),
+    };
+    read_core_properties(&mut archive, &mut presentation)?;
+
+    for (index, rel_id) in info.slide_rel_ids.iter().enumerate() {
+        let target = rels
+            .get(rel_id)
+            .map(|rel| resolve_target("ppt", &rel.target))
+            .ok_or(Error::InvalidFormat)?;
+        let slide = read_slide(&mut archive, &target)?;
+
+        // A new presentation already holds one empty slide to replace.
+        let slot = if index == 0 {
+            0
+        } else {
+            presentation.add_slide()
+        };
+        if let Some(existing) = presentation.slide_mut(slot) {
+            *existing = slide;
+        }
+    }
+
+    Ok(presentation)
+}
+
+/// A package relationship.
+struct Relationship {
+    kind: String,
+    target: String,
+}
+
+/// Facts gathered from `ppt/presentation.xml`.
+struct PresentationInfo {
+    size: Option<(i64, i64)>,
+    slide_rel_ids: Vec<String>,
+}
+
+fn read_part(archive: &mut Archive, name: &str) -> Result<String, Error> {
+    let mut file = archive.by_name(name)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn read_relationships(
+    archive: &mut Archive,
+    name: &str,
+) -> Result<HashMap<String, Relationship>, Error> {
+    let xml = read_part(archive, name)?;
+    let mut reader = Reader::from_str(&xml);
+    let mut rels = HashMap::new();
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                let id = attribute(&e, b"Id");
+                let kind = attribute(&e, b"Type");
+                let target = attribute(&e, b"Target");
+                if let (Some(id), Some(kind), Some(target)) = (id, kind, target) {
+                    rels.insert(id, Relationship { kind, target });
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(rels)
+}
+
+/// Resolve a relationship target relative to the directory of its source part.
+fn resolve_target(base_dir: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+
+    let mut parts: Vec<&str> = base_dir.split('/').filter(|p| !p.is_empty()).collect();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            other => parts.push(other),
+        }
+    }
+    parts.join("/")
+}
+
+fn parse_presentation(xml: &str) -> Result<PresentationInfo, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut info = PresentationInfo {
+        size: None,
+        slide_rel_ids: Vec::new(),
+    };
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"sldSz" => {
+                    let cx = int_attribute(&e, b"cx");
+                    let cy = int_attribute(&e, b"cy");
+                    if let (Some(cx), Some(cy)) = (cx, cy) {
+                        info.size = Some((cx, cy));
+                    }
+                }
+                b"sldId" => {
+                    if let Some(id) = relationship_id(&e) {
+                        info.slide_rel_ids.push(id);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+fn read_core_properties(
+    archive: &mut Archive,
+    presentation: &mut Presentation,
+) -> Result<(), Error> {
+    // Core properties are optional in OPC packages.
+    let xml = match read_part(archive, "docProps/core.xml") {
+        Ok(xml) => xml,
+        Err(Error::Zip(zip::result::ZipError::FileNotFound)) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let mut reader = Reader::from_str(&xml);
+    let mut current: Option<Vec<u8>> = None;
+    let metadata = &mut presentation.metadata;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) => current = Some(e.local_name().as_ref().to_vec()),
+            Event::End(_) => current = None,
+            Event::Text(t) => {
+                let value = t.unescape().map_err(xml_error)?.into_owned();
+                match current.as_deref() {
+                    Some(b"title") => metadata.title = Some(value),
+                    Some(b"creator") => metadata.author = Some(value),
+                    Some(b"subject") => metadata.subject = Some(value),
+                    Some(b"keywords") => {
+                        metadata.keywords = value
+                            .split([',', ';'])
+                            .map(str::trim)
+                            .filter(|k| !k.is_empty())
+                            .map(String::from)
+                            .collect();
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn read_slide(archive: &mut Archive, path: &str) -> Result<Slide, Error> {
+    let part = parse_shape_tree(&read_part(archive, path)?)?;
+
+    let mut slide = Slide::new();
+    if let Some(background) = part.background {
+        slide.background = background;
+    }
+    slide.shapes = part
+        .shapes
+        .into_iter()
+        .filter(|shape| shape.placeholder.is_none() || !shape.text.is_empty())
+        .map(ParsedShape::into_shape)
+        .collect();
+
+    let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+    let rels_path = format!("{}/_rels/{}.rels", dir, file);
+    let rels = match read_relationships(archive, &rels_path) {
+        Ok(rels) => rels,
+        Err(Error::Zip(zip::result::ZipError::FileNotFound)) => HashMap::new(),
+        Err(e) => return Err(e),
+    };
+    if let Some(notes) = rels.values().find(|rel| rel.kind.ends_with("/notesSlide")) {
+        let notes_path = resolve_target(dir, &notes.target);
+        let notes_part = parse_shape_tree(&read_part(archive, &notes_path)?)?;
+        if let Some(body) = notes_part
+            .shapes
+            .into_iter()
+            .find(|shape| shape.placeholder.as_deref() == Some("body"))
+        {
+            slide.set_notes(body.text);
+        }
+    }
+
+    Ok(slide)
+}
+
+/// A shape tree parsed from a slide or notes slide.
+#[derive(Default)]
+struct ShapeTree {
+    shapes: Vec<ParsedShape>,
+    background: Option<Background>,
+}
+
+/// Raw shape properties collected while walking a `<p:sp>` element.
+#[derive(Default)]
+struct ParsedShape {
+    text_box: bool,
+    has_text_body: bool,
+    placeholder: Option<String>,
+    geometry: Option<String>,
+    adjust: Option<i64>,
+    offset: (i64, i64),
+    extent: (i64, i64),
+    rotation: i64,
+    hidden: bool,
+    locked: bool,
+    fill: Option<[u8; 4]>,
+    stroke: Option<[u8; 4]>,
+    stroke_width: i64,
+    text: Text,
+}
+
+impl ParsedShape {
+    fn into_shape(self) -> Shape {
+        let bounds = Rect::new(
+            emu_to_pt(self.offset.0),
+            emu_to_pt(self.offset.1),
+            emu_to_pt(self.extent.0),
+            emu_to_pt(self.extent.1),
+        );
+
+        // Placeholders carry no geometry of their own but may hold text.
+        let treat_as_text = self.text_box || (self.geometry.is_none() && self.has_text_body);
+        let kind = if treat_as_text {
+            ShapeKind::TextBox(Box::new(self.text))
+        } else {
+            match self.geometry.as_deref() {
+                Some("roundRect") => {
+                    let adjust = self.adjust.unwrap_or(16_667) as f32 / 100_000.0;
+                    ShapeKind::RoundedRectangle {
+                        radius: adjust * bounds.width.min(bounds.height),
+                    }
+                }
+                Some("ellipse") => ShapeKind::Ellipse,
+                Some("triangle") => ShapeKind::Triangle,
+                Some("line") | Some("straightConnector1") => ShapeKind::Line,
+                Some("rightArrow") | Some("leftArrow") | Some("upArrow") | Some("downArrow") => {
+                    ShapeKind::Arrow
+                }
+                _ => ShapeKind::Rectangle,
+            }
+        };
+
+        let mut shape = Shape::new(kind, bounds);
+        shape.rotation = self.rotation as f32 / 60_000.0;
+        shape.hidden = self.hidden;
+        shape.locked = self.locked;
+        shape.style = ShapeStyle {
+            fill: self.fill,
+            stroke: self.stroke,
+            stroke_width: emu_to_pt(self.stroke_width),
+            ..ShapeStyle::default()
+        };
+        shape
+    }
+}
+
+/// Where a color element found inside `<a:solidFill>` belongs.
+fn color_target(stack: &[Vec<u8>]) -> Option<&[u8]> {
+    let len = stack.len();
+    if len >= 2 && stack[len - 1] == b"solidFill" {
+        Some(stack[len - 2].as_slice())
+    } else {
+        None
+    }
+}
+
+fn parse_shape_tree(xml: &str) -> Result<ShapeTree, Error> {
+    let mut reader = Reader::from_str(xml);
+    let mut tree = ShapeTree::default();
+    let mut stack: Vec<Vec<u8>> = Vec::new();
+    let mut shape: Option<ParsedShape> = None;
+    let mut paragraphs = 0usize;
+    let mut run: Option<(usize, TextStyle, bool)> = None;
+    let mut in_text = false;
+
+    loop {
+        let event = reader.read_event().map_err(xml_error)?;
+        let (element, is_empty) = match &event {
+            Event::Start(e) => (Some(e), false),
+            Event::Empty(e) => (Some(e), true),
+            _ => (None, false),
+        };
+
+        if let Some(e) = element {
+            let name = e.local_name().as_ref().to_vec();
+            let parent = stack.last().map(Vec::as_slice);
+
+            match name.as_slice() {
+                b"sp" => {
+                    shape = Some(ParsedShape::default());
+                    paragraphs = 0;
+                }
+                b"srgbClr" => {
+                    if let Some(color) = attribute(e, b"val").and_then(|v| parse_hex_color(&v)) {
+                        apply_color(&stack, &mut shape, &mut run, &mut tree, color);
+                    }
+                }
+                b"alpha" if parent == Some(b"srgbClr") => {
+                    if let Some(alpha) = int_attribute(e, b"val") {
+                        let alpha = (alpha.clamp(0, 100_000) * 255 / 100_000) as u8;
+                        apply_alpha(
+                            &stack[..stack.len() - 1],
+                            &mut shape,
+                            &mut run,
+                            &mut tree,
+                            alpha,
+                        );
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(current) = shape.as_mut() {
+                match name.as_slice() {
+                    b"cNvPr" => current.hidden = bool_attribute(e, b"hidden"),
+                    b"cNvSpPr" => current.text_box = bool_attribute(e, b"txBox"),
+                    b"spLocks" => current.locked = bool_attribute(e, b"noMove"),
+                    b"ph" => {
+                        current.placeholder =
+                            Some(attribute(e, b"type").unwrap_or_else(|| "body".to_string()))
+                    }
+                    b"xfrm" if parent == Some(b"spPr") => {
+                        current.rotation = int_attribute(e, b"rot").unwrap_or(0);
+                    }
+                    b"off" if parent == Some(b"xfrm") => {
+                        current.offset = (
+                            int_attribute(e, b"x").unwrap_or(0),
+                            int_attribute(e, b"y").unwrap_or(0),
+                        );
+                    }
+                    b"ext" if parent == Some(b"xfrm") => {
+                        current.extent = (
+                            int_attribute(e, b"cx").unwrap_or(0),
+                            int_attribute(e, b"cy").unwrap_or(0),
+                        );
+                    }
+                    b"prstGeom" => current.geometry = attribute(e, b"prst"),
+                    b"gd" => {
+                        current.adjust = attribute(e, b"fmla")
+                            .and_then(|f| f.strip_prefix("val ").and_then(|v| v.parse().ok()));
+                    }
+                    b"ln" if parent == Some(b"spPr") => {
+                        current.stroke_width = int_attribute(e, b"w").unwrap_or(12_700);
+                    }
+                    b"txBody" => current.has_text_body = true,
+                    b"p" => {
+                        if paragraphs > 0 {
+                            current.text.content.push('\n');
+                        }
+                        paragraphs += 1;
+                    }
+                    b"br" => current.text.content.push('\n'),
+                    b"r" => run = Some((current.text.content.len(), TextStyle::default(), false)),
+                    b"rPr" => {
+                        if let Some((_, style, styled)) = run.as_mut() {
+                            *styled |= parse_run_properties(e, style);
+                        }
+                    }
+                    b"latin" if parent == Some(b"rPr") => {
+                        if let Some((_, style, styled)) = run.as_mut() {
+                            style.font_family = attribute(e, b"typeface");
+                            *styled = true;
+                        }
+                    }
+                    b"t" => in_text = !is_empty,
+                    _ => {}
+                }
+            }
+
+            if !is_empty {
+                stack.push(name);
+            }
+            continue;
+        }
+
+        match event {
+            Event::Text(t) if in_text => {
+                if let Some(current) = shape.as_mut() {
+                    let text = t.unescape().map_err(xml_error)?;
+                    current.text.content.push_str(&text);
+                }
+            }
+            Event::End(e) => {
+                stack.pop();
+                match e.local_name().as_ref() {
+                    b"t" => in_text = false,
+                    b"r" => {
+                        if let (Some(current), Some((start, style, styled))) =
+                            (shape.as_mut(), run.take())
+                        {
+                            let end = current.text.content.len();
+                            if styled && end > start {
+                                current.text.add_span(Span::new(start, end, style));
+                            }
+                        }
+                    }
+                    b"sp" => {
+                        if let Some(finished) = shape.take() {
+                            tree.shapes.push(finished);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(tree)
+}
+
+fn apply_color(
+    stack: &[Vec<u8>],
+    shape: &mut Option<ParsedShape>,
+    run: &mut Option<(usize, TextStyle, bool)>,
+    tree: &mut ShapeTree,
+    color: [u8; 4],
+) {
+    match color_target(stack) {
+        Some(b"rPr") => {
+            if let Some((_, style, styled)) = run.as_mut() {
+                style.color = Some(color);
+                *styled = true;
+            }
+        }
+        Some(b"spPr") => {
+            if let Some(current) = shape.as_mut() {
+                current.fill = Some(color);
+            }
+        }
+        Some(b"ln") => {
+            if let Some(current) = shape.as_mut() {
+                current.stroke = Some(color);
+            }
+        }
+        Some(b"bgPr") => tree.background = Some(Background::Solid(color)),
+        _ => {}
+    }
+}
+
+fn apply_alpha(
+    stack: &[Vec<u8>],
+    shape: &mut Option<ParsedShape>,
+    run: &mut Option<(usize, TextStyle, bool)>,
+    tree: &mut ShapeTree,
+    alpha: u8,
+) {
+    let slot = match color_target(stack) {
+        Some(b"rPr") => run.as_mut().and_then(|(_, style, _)| style.color.as_mut()),
+        Some(b"spPr") => shape.as_mut().and_then(|s| s.fill.as_mut()),
+        Some(b"ln") => shape.as_mut().and_then(|s| s.stroke.as_mut()),
+        Some(b"bgPr") => match tree.background.as_mut() {
+            Some(Background::Solid(color)) => Some(color),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(color) = slot {
+        color[3] = alpha;
+    }
+}
+
+/// Parse `<a:rPr>` attributes into a text style, returning whether any were set.
+fn parse_run_properties(e: &BytesStart<'_>, style: &mut TextStyle) -> bool {
+    let mut styled = false;
+    if let Some(size) = int_attribute(e, b"sz") {
+        style.font_size = Some(size as f32 / 100.0);
+        styled = true;
+    }
+    if let Some(bold) = attribute(e, b"b") {
+        style.font_weight = Some(if is_true(&bold) { 700 } else { 400 });
+        styled = true;
+    }
+    if let Some(italic) = attribute(e, b"i") {
+        style.italic = Some(is_true(&italic));
+        styled = true;
+    }
+    if let Some(underline) = attribute(e, b"u") {
+        style.underline = Some(underline != "none");
+        styled = true;
+    }
+    if let Some(strike) = attribute(e, b"strike") {
+        style.strikethrough = Some(strike != "noStrike");
+        styled = true;
+    }
+    if let Some(baseline) = int_attribute(e, b"baseline") {
+        if baseline > 0 {
+            style.superscript = Some(true);
+            styled = true;
+        } else if baseline < 0 {
+            style.subscript = Some(true);
+            styled = true;
+        }
+    }
+    styled
+}
+
+fn parse_hex_color(value: &str) -> Option<[u8; 4]> {
+    if value.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&value[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?, 255])
+}
+
+fn attribute(e: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+fn int_attribute(e: &BytesStart<'_>, name: &[u8]) -> Option<i64> {
+    attribute(e, name).and_then(|v| v.parse().ok())
+}
+
+fn bool_attribute(e: &BytesStart<'_>, name: &[u8]) -> bool {
+    attribute(e, name).is_some_and(|v| is_true(&v))
+}
+
+fn is_true(value: &str) -> bool {
+    value == "1" || value == "true"
+}
+
+/// The namespaced `r:id` attribute, whatever prefix the producer chose.
+fn relationship_id(e: &BytesStart<'_>) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.prefix().is_some() && a.key.local_name().as_ref() == b"id")
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+fn xml_error(e: impl std::fmt::Display) -> Error {
+    Error::Xml(e.to_string())
+}
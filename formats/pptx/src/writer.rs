@@ -0,0 +1,779 @@
+//! PPTX package generation.
+//!
+//! Produces a minimal but complete Open Packaging Conventions (OPC) package:
+//! content types, package and presentation relationships, one slide master,
+//! one blank layout, a notes master, themes, and one part per slide (plus a
+//! notes slide for every slide with speaker notes).
+
+use std::io::{Cursor, Write};
+
+use deck_engine::Presentation;
+use deck_engine::shape::{Shape, ShapeKind, ShapeStyle};
+use deck_engine::slide::{Background, Slide};
+use quick_xml::escape::escape;
+use wolia_core::style::TextStyle;
+use wolia_core::text::Text;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::{Error, NS_A, NS_P, NS_R, pt_to_emu};
+
+const XML_DECL: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+const REL_OFFICE_DOCUMENT: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+const REL_CORE_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+const REL_EXTENDED_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties";
+const REL_SLIDE_MASTER: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideMaster";
+const REL_SLIDE_LAYOUT: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideLayout";
+const REL_SLIDE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide";
+const REL_NOTES_MASTER: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/notesMaster";
+const REL_NOTES_SLIDE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/notesSlide";
+const REL_THEME: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/theme";
+const REL_PRES_PROPS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/presProps";
+
+const CT_PRESENTATION: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml";
+const CT_SLIDE_MASTER: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.slideMaster+xml";
+const CT_SLIDE_LAYOUT: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.slideLayout+xml";
+const CT_SLIDE: &str = "application/vnd.openxmlformats-officedocument.presentationml.slide+xml";
+const CT_NOTES_MASTER: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.notesMaster+xml";
+const CT_NOTES_SLIDE: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.notesSlide+xml";
+const CT_THEME: &str = "application/vnd.openxmlformats-officedocument.theme+xml";
+const CT_PRES_PROPS: &str =
+    "application/vnd.openxmlformats-officedocument.presentationml.presProps+xml";
+const CT_CORE_PROPERTIES: &str = "application/vnd.openxmlformats-package.core-properties+xml";
+const CT_EXTENDED_PROPERTIES: &str =
+    "application/vnd.openxmlformats-officedocument.extended-properties+xml";
+
+/// Notes page size (portrait US letter) in EMUs.
+const NOTES_SIZE: (i64, i64) = (6_858_000, 9_144_000);
+
+/// First slide ID; PresentationML requires IDs of at least 256.
+const FIRST_SLIDE_ID: usize = 256;
+
+/// Empty group shape properties that open every shape tree.
+const SP_TREE_HEADER: &str = concat!(
+    r#"<p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr>"#,
+    r#"<p:grpSpPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="0" cy="0"/>"#,
+    r#"<a:chOff x="0" y="0"/><a:chExt cx="0" cy="0"/></a:xfrm></p:grpSpPr>"#,
+);
+
+const COLOR_MAP: &str = concat!(
+    r#"<p:clrMap bg1="lt1" tx1="dk1" bg2="lt2" tx2="dk2" accent1="accent1" "#,
+    r#"accent2="accent2" accent3="accent3" accent4="accent4" accent5="accent5" "#,
+    r#"accent6="accent6" hlink="hlink" folHlink="folHlink"/>"#,
+);
+
+/// Serialize a presentation into a `.pptx` package.
+pub fn write(presentation: &Presentation) -> Result<Vec<u8>, Error> {
+    let mut package = Package::new();
+    let slide_count = presentation.slide_count();
+    let slides: Vec<&Slide> = (0..slide_count)
+        .filter_map(|index| presentation.slide(index))
+        .collect();
+
+    // Notes slides are numbered densely, only for slides that have notes.
+    let mut notes_numbers = Vec::with_capacity(slides.len());
+    let mut next_notes = 1;
+    for slide in &slides {
+        if slide.notes().is_empty() {
+            notes_numbers.push(None);
+        } else {
+            notes_numbers.push(Some(next_notes));
+            next_notes += 1;
+        }
+    }
+
+    package.part(
+        "[Content_Types].xml",
+        &content_types(slides.len(), next_notes - 1),
+    )?;
+    package.part(
+        "_rels/.rels",
+        &relationships(&[
+            ("rId1", REL_OFFICE_DOCUMENT, "ppt/presentation.xml"),
+            ("rId2", REL_CORE_PROPERTIES, "docProps/core.xml"),
+            ("rId3", REL_EXTENDED_PROPERTIES, "docProps/app.xml"),
+        ]),
+    )?;
+    package.part("docProps/core.xml", &core_properties(presentation))?;
+    package.part(
+        "docProps/app.xml",
+        &app_properties(slides.len(), next_notes - 1),
+    )?;
+
+    package.part("ppt/presentation.xml", &presentation_xml(presentation))?;
+    let mut presentation_rels = vec![
+        (
+            "rId1".to_string(),
+            REL_SLIDE_MASTER,
+            "slideMasters/slideMaster1.xml".to_string(),
+        ),
+        (
+            "rId2".to_string(),
+            REL_NOTES_MASTER,
+            "notesMasters/notesMaster1.xml".to_string(),
+        ),
+        (
+            "rId3".to_string(),
+            REL_THEME,
+            "theme/theme1.xml".to_string(),
+        ),
+        (
+            "rId4".to_string(),
+            REL_PRES_PROPS,
+            "presProps.xml".to_string(),
+        ),
+    ];
+    for number in 1..=slides.len() {
+        presentation_rels.push((
+            slide_rel_id(number),
+            REL_SLIDE,
+            format!("slides/slide{}.xml", number),
+        ));
+    }
+    package.part(
+        "ppt/_rels/presentation.xml.rels",
+        &relationships_owned(&presentation_rels),
+    )?;
+    package.part("ppt/presProps.xml", &presentation_properties())?;
+
+    package.part("ppt/slideMasters/slideMaster1.xml", &slide_master())?;
+    package.part(
+        "ppt/slideMasters/_rels/slideMaster1.xml.rels",
+        &relationships(&[
+            ("rId1", REL_SLIDE_LAYOUT, "../slideLayouts/slideLayout1.xml"),
+            ("rId2", REL_THEME, "../theme/theme1.xml"),
+        ]),
+    )?;
+    package.part("ppt/slideLayouts/slideLayout1.xml", &slide_layout())?;
+    package.part(
+        "ppt/slideLayouts/_rels/slideLayout1.xml.rels",
+        &relationships(&[("rId1", REL_SLIDE_MASTER, "../slideMasters/slideMaster1.xml")]),
+    )?;
+    package.part("ppt/notesMasters/notesMaster1.xml", &notes_master())?;
+    package.part(
+        "ppt/notesMasters/_rels/notesMaster1.xml.rels",
+        &relationships(&[("rId1", REL_THEME, "../theme/theme2.xml")]),
+    )?;
+    package.part("ppt/theme/theme1.xml", &theme("Wolia"))?;
+    package.part("ppt/theme/theme2.xml", &theme("Wolia Notes"))?;
+
+    for (index, slide) in slides.iter().enumerate() {
+        let number = index + 1;
+        package.part(
+            &format!("ppt/slides/slide{}.xml", number),
+            &slide_xml(slide),
+        )?;
+
+        let mut rels = vec![(
+            "rId1".to_string(),
+            REL_SLIDE_LAYOUT,
+            "../slideLayouts/slideLayout1.xml".to_string(),
+        )];
+        if let Some(notes_number) = notes_numbers[index] {
+            rels.push((
+                "rId2".to_string(),
+                REL_NOTES_SLIDE,
+                format!("../notesSlides/notesSlide{}.xml", notes_number),
+            ));
+
+            package.part(
+                &format!("ppt/notesSlides/notesSlide{}.xml", notes_number),
+                &notes_slide(slide.notes()),
+            )?;
+            package.part(
+                &format!("ppt/notesSlides/_rels/notesSlide{}.xml.rels", notes_number),
+                &relationships_owned(&[
+                    (
+                        "rId1".to_string(),
+                        REL_NOTES_MASTER,
+                        "../notesMasters/notesMaster1.xml".to_string(),
+                    ),
+                    (
+                        "rId2".to_string(),
+                        REL_SLIDE,
+                        format!("../slides/slide{}.xml", number),
+                    ),
+                ]),
+            )?;
+        }
+        package.part(
+            &format!("ppt/slides/_rels/slide{}.xml.rels", number),
+            &relationships_owned(&rels),
+        )?;
+    }
+
+    package.finish()
+}
+
+/// A ZIP archive being filled with package parts.
+struct Package {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+    options: SimpleFileOptions,
+}
+
+impl Package {
+    fn new() -> Self {
+        Self {
+            zip: ZipWriter::new(Cursor::new(Vec::new())),
+            options: SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated),
+        }
+    }
+
+    /// Add a part to the package.
+    fn part(&mut self, name: &str, content: &str) -> Result<(), Error> {
+        self.zip.start_file(name, self.options)?;
+        self.zip.write_all(content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Finish the archive and return its bytes.
+    fn finish(self) -> Result<Vec<u8>, Error> {
+        Ok(self.zip.finish()?.into_inner())
+    }
+}
+
+fn slide_rel_id(number: usize) -> String {
+    // rId1-rId4 are taken by the master, notes master, theme, and properties.
+    format!("rId{}", number + 4)
+}
+
+fn content_types(slides: usize, notes: usize) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#);
+    xml.push_str(r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#);
+    xml.push_str(r#"<Default Extension="xml" ContentType="application/xml"/>"#);
+
+    let mut overrides = vec![
+        ("/ppt/presentation.xml".to_string(), CT_PRESENTATION),
+        ("/ppt/presProps.xml".to_string(), CT_PRES_PROPS),
+        (
+            "/ppt/slideMasters/slideMaster1.xml".to_string(),
+            CT_SLIDE_MASTER,
+        ),
+        (
+            "/ppt/slideLayouts/slideLayout1.xml".to_string(),
+            CT_SLIDE_LAYOUT,
+        ),
+        (
+            "/ppt/notesMasters/notesMaster1.xml".to_string(),
+            CT_NOTES_MASTER,
+        ),
+        ("/ppt/theme/theme1.xml".to_string(), CT_THEME),
+        ("/ppt/theme/theme2.xml".to_string(), CT_THEME),
+        ("/docProps/core.xml".to_string(), CT_CORE_PROPERTIES),
+        ("/docProps/app.xml".to_string(), CT_EXTENDED_PROPERTIES),
+    ];
+    for number in 1..=slides {
+        overrides.push((format!("/ppt/slides/slide{}.xml", number), CT_SLIDE));
+    }
+    for number in 1..=notes {
+        overrides.push((
+            format!("/ppt/notesSlides/notesSlide{}.xml", number),
+            CT_NOTES_SLIDE,
+        ));
+    }
+
+    for (part, content_type) in overrides {
+        xml.push_str(&format!(
+            r#"<Override PartName="{}" ContentType="{}"/>"#,
+            part, content_type
+        ));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn relationships(rels: &[(&str, &str, &str)]) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(
+        r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    );
+    for (id, kind, target) in rels {
+        xml.push_str(&format!(
+            r#"<Relationship Id="{}" Type="{}" Target="{}"/>"#,
+            id, kind, target
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn relationships_owned(rels: &[(String, &str, String)]) -> String {
+    let borrowed: Vec<(&str, &str, &str)> = rels
+        .iter()
+        .map(|(id, kind, target)| (id.as_str(), *kind, target.as_str()))
+        .collect();
+    relationships(&borrowed)
+}
+
+fn core_properties(presentation: &Presentation) -> String {
+    let metadata = &presentation.metadata;
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(concat!(
+        r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" "#,
+        r#"xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" "#,
+        r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+    ));
+    if let Some(title) = &metadata.title {
+        xml.push_str(&format!("<dc:title>{}</dc:title>", escape(title)));
+    }
+    if let Some(subject) = &metadata.subject {
+        xml.push_str(&format!("<dc:subject>{}</dc:subject>", escape(subject)));
+    }
+    if let Some(author) = &metadata.author {
+        xml.push_str(&format!("<dc:creator>{}</dc:creator>", escape(author)));
+    }
+    if !metadata.keywords.is_empty() {
+        xml.push_str(&format!(
+            "<cp:keywords>{}</cp:keywords>",
+            escape(metadata.keywords.join(", "))
+        ));
+    }
+    xml.push_str("</cp:coreProperties>");
+    xml
+}
+
+fn app_properties(slides: usize, notes: usize) -> String {
+    format!(
+        concat!(
+            "{}",
+            r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties">"#,
+            "<Application>Wolia Deck</Application><Slides>{}</Slides><Notes>{}</Notes>",
+            "</Properties>",
+        ),
+        XML_DECL, slides, notes
+    )
+}
+
+fn presentation_xml(presentation: &Presentation) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(&format!(
+        r#"<p:presentation xmlns:a="{}" xmlns:r="{}" xmlns:p="{}" saveSubsetFonts="1">"#,
+        NS_A, NS_R, NS_P
+    ));
+    xml.push_str(
+        r#"<p:sldMasterIdLst><p:sldMasterId id="2147483648" r:id="rId1"/></p:sldMasterIdLst>"#,
+    );
+    xml.push_str(r#"<p:notesMasterIdLst><p:notesMasterId r:id="rId2"/></p:notesMasterIdLst>"#);
+
+    xml.push_str("<p:sldIdLst>");
+    for number in 1..=presentation.slide_count() {
+        xml.push_str(&format!(
+            r#"<p:sldId id="{}" r:id="{}"/>"#,
+            FIRST_SLIDE_ID + number - 1,
+            slide_rel_id(number)
+        ));
+    }
+    xml.push_str("</p:sldIdLst>");
+
+    xml.push_str(&format!(
+        r#"<p:sldSz cx="{}" cy="{}"/><p:notesSz cx="{}" cy="{}"/>"#,
+        pt_to_emu(presentation.slide_size.width),
+        pt_to_emu(presentation.slide_size.height),
+        NOTES_SIZE.0,
+        NOTES_SIZE.1
+    ));
+    xml.push_str("</p:presentation>");
+    xml
+}
+
+fn presentation_properties() -> String {
+    format!(
+        r#"{}<p:presentationPr xmlns:a="{}" xmlns:r="{}" xmlns:p="{}"/>"#,
+        XML_DECL, NS_A, NS_R, NS_P
+    )
+}
+
+fn slide_master() -> String {
+    format!(
+        concat!(
+            r#"{}<p:sldMaster xmlns:a="{}" xmlns:r="{}" xmlns:p="{}">"#,
+            r#"<p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg>"#,
+            "<p:spTree>{}</p:spTree></p:cSld>{}",
+            r#"<p:sldLayoutIdLst><p:sldLayoutId id="2147483649" r:id="rId1"/></p:sldLayoutIdLst>"#,
+            "</p:sldMaster>",
+        ),
+        XML_DECL, NS_A, NS_R, NS_P, SP_TREE_HEADER, COLOR_MAP
+    )
+}
+
+fn slide_layout() -> String {
+    format!(
+        concat!(
+            r#"{}<p:sldLayout xmlns:a="{}" xmlns:r="{}" xmlns:p="{}" type="blank" preserve="1">"#,
+            r#"<p:cSld name="Blank"><p:spTree>{}</p:spTree></p:cSld>"#,
+            "<p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sldLayout>",
+        ),
+        XML_DECL, NS_A, NS_R, NS_P, SP_TREE_HEADER
+    )
+}
+
+fn notes_master() -> String {
+    format!(
+        concat!(
+            r#"{}<p:notesMaster xmlns:a="{}" xmlns:r="{}" xmlns:p="{}">"#,
+            r#"<p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg>"#,
+            "<p:spTree>{}",
+            r#"<p:sp><p:nvSpPr><p:cNvPr id="2" name="Notes Placeholder 1"/>"#,
+            r#"<p:cNvSpPr><a:spLocks noGrp="1"/></p:cNvSpPr><p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr>"#,
+            r#"<p:spPr><a:xfrm><a:off x="685800" y="4400550"/><a:ext cx="5486400" cy="3600450"/></a:xfrm>"#,
+            r#"<a:prstGeom prst="rect"><a:avLst/></a:prstGeom></p:spPr>"#,
+            "<p:txBody><a:bodyPr/><a:lstStyle/><a:p><a:endParaRPr lang=\"en-US\"/></a:p></p:txBody></p:sp>",
+            "</p:spTree></p:cSld>{}</p:notesMaster>",
+        ),
+        XML_DECL, NS_A, NS_R, NS_P, SP_TREE_HEADER, COLOR_MAP
+    )
+}
+
+fn theme(name: &str) -> String {
+    let solid = r#"<a:solidFill><a:schemeClr val="phClr"/></a:solidFill>"#;
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(&format!(r#"<a:theme xmlns:a="{}" name="{}">"#, NS_A, name));
+    xml.push_str("<a:themeElements>");
+    xml.push_str(&format!(r#"<a:clrScheme name="{}">"#, name));
+    xml.push_str(r#"<a:dk1><a:sysClr val="windowText" lastClr="000000"/></a:dk1>"#);
+    xml.push_str(r#"<a:lt1><a:sysClr val="window" lastClr="FFFFFF"/></a:lt1>"#);
+    for (slot, color) in [
+        ("dk2", "44546A"),
+        ("lt2", "E7E6E6"),
+        ("accent1", "4472C4"),
+        ("accent2", "ED7D31"),
+        ("accent3", "A5A5A5"),
+        ("accent4", "FFC000"),
+        ("accent5", "5B9BD5"),
+        ("accent6", "70AD47"),
+        ("hlink", "0563C1"),
+        ("folHlink", "954F72"),
+    ] {
+        xml.push_str(&format!(
+            r#"<a:{slot}><a:srgbClr val="{color}"/></a:{slot}>"#
+        ));
+    }
+    xml.push_str("</a:clrScheme>");
+    xml.push_str(&format!(r#"<a:fontScheme name="{}">"#, name));
+    xml.push_str(r#"<a:majorFont><a:latin typeface="Calibri Light"/><a:ea typeface=""/><a:cs typeface=""/></a:majorFont>"#);
+    xml.push_str(r#"<a:minorFont><a:latin typeface="Calibri"/><a:ea typeface=""/><a:cs typeface=""/></a:minorFont>"#);
+    xml.push_str("</a:fontScheme>");
+    xml.push_str(&format!(r#"<a:fmtScheme name="{}">"#, name));
+    xml.push_str(&format!(
+        "<a:fillStyleLst>{0}{0}{0}</a:fillStyleLst>",
+        solid
+    ));
+    xml.push_str("<a:lnStyleLst>");
+    for width in [6350, 12700, 19050] {
+        xml.push_str(&format!(r#"<a:ln w="{}">{}</a:ln>"#, width, solid));
+    }
+    xml.push_str("</a:lnStyleLst>");
+    xml.push_str("<a:effectStyleLst>");
+    for _ in 0..3 {
+        xml.push_str("<a:effectStyle><a:effectLst/></a:effectStyle>");
+    }
+    xml.push_str("</a:effectStyleLst>");
+    xml.push_str(&format!(
+        "<a:bgFillStyleLst>{0}{0}{0}</a:bgFillStyleLst>",
+        solid
+    ));
+    xml.push_str("</a:fmtScheme></a:themeElements></a:theme>");
+    xml
+}
+
+fn slide_xml(slide: &Slide) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(&format!(
+        r#"<p:sld xmlns:a="{}" xmlns:r="{}" xmlns:p="{}"><p:cSld>"#,
+        NS_A, NS_R, NS_P
+    ));
+    write_background(&mut xml, &slide.background);
+    xml.push_str("<p:spTree>");
+    xml.push_str(SP_TREE_HEADER);
+    for (index, shape) in slide.shapes.iter().enumerate() {
+        // Shape ID 1 belongs to the group shape that roots the tree.
+        write_shape(&mut xml, shape, index as u32 + 2);
+    }
+    xml.push_str("</p:spTree></p:cSld>");
+    xml.push_str("<p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sld>");
+    xml
+}
+
+fn write_background(xml: &mut String, background: &Background) {
+    match background {
+        Background::Solid(color) => {
+            xml.push_str("<p:bg><p:bgPr>");
+            write_solid_fill(xml, *color);
+            xml.push_str("<a:effectLst/></p:bgPr></p:bg>");
+        }
+        Background::Gradient { start, end, angle } => {
+            xml.push_str(r#"<p:bg><p:bgPr><a:gradFill rotWithShape="1"><a:gsLst>"#);
+            xml.push_str(r#"<a:gs pos="0">"#);
+            write_color(xml, *start);
+            xml.push_str(r#"</a:gs><a:gs pos="100000">"#);
+            write_color(xml, *end);
+            xml.push_str(&format!(
+                r#"</a:gs></a:gsLst><a:lin ang="{}" scaled="0"/></a:gradFill>"#,
+                angle_to_ooxml(*angle)
+            ));
+            xml.push_str("<a:effectLst/></p:bgPr></p:bg>");
+        }
+        // Image backgrounds need media parts; fall back to the master background.
+        Background::Image { .. } => {}
+    }
+}
+
+fn write_shape(xml: &mut String, shape: &Shape, id: u32) {
+    let (geometry, is_text_box) = match &shape.kind {
+        ShapeKind::TextBox(_) => ("rect", true),
+        ShapeKind::RoundedRectangle { .. } => ("roundRect", false),
+        ShapeKind::Ellipse => ("ellipse", false),
+        ShapeKind::Triangle => ("triangle", false),
+        ShapeKind::Line => ("line", false),
+        ShapeKind::Arrow => ("rightArrow", false),
+        // Media, tables, charts, and custom paths have no preset geometry;
+        // they are written as plain rectangles that keep their frame.
+        _ => ("rect", false),
+    };
+
+    xml.push_str("<p:sp><p:nvSpPr>");
+    xml.push_str(&format!(r#"<p:cNvPr id="{}" name="Shape {}""#, id, id - 1));
+    if shape.hidden {
+        xml.push_str(r#" hidden="1""#);
+    }
+    xml.push_str("/>");
+    xml.push_str(if is_text_box {
+        r#"<p:cNvSpPr txBox="1">"#
+    } else {
+        "<p:cNvSpPr>"
+    });
+    if shape.locked {
+        xml.push_str(r#"<a:spLocks noMove="1" noResize="1" noRot="1"/>"#);
+    }
+    xml.push_str("</p:cNvSpPr><p:nvPr/></p:nvSpPr>");
+
+    xml.push_str("<p:spPr><a:xfrm");
+    if shape.rotation != 0.0 {
+        xml.push_str(&format!(r#" rot="{}""#, angle_to_ooxml(shape.rotation)));
+    }
+    xml.push_str(&format!(
+        r#"><a:off x="{}" y="{}"/><a:ext cx="{}" cy="{}"/></a:xfrm>"#,
+        pt_to_emu(shape.bounds.x),
+        pt_to_emu(shape.bounds.y),
+        pt_to_emu(shape.bounds.width),
+        pt_to_emu(shape.bounds.height)
+    ));
+    xml.push_str(&format!(r#"<a:prstGeom prst="{}"><a:avLst>"#, geometry));
+    if let ShapeKind::RoundedRectangle { radius } = shape.kind {
+        let shortest = shape.bounds.width.min(shape.bounds.height);
+        if shortest > 0.0 {
+            let adjust = (radius / shortest * 100_000.0).round().clamp(0.0, 50_000.0);
+            xml.push_str(&format!(r#"<a:gd name="adj" fmla="val {}"/>"#, adjust));
+        }
+    }
+    xml.push_str("</a:avLst></a:prstGeom>");
+    write_shape_style(xml, &shape.style, is_text_box);
+    xml.push_str("</p:spPr>");
+
+    if let ShapeKind::TextBox(text) = &shape.kind {
+        xml.push_str(r#"<p:txBody><a:bodyPr wrap="square" rtlCol="0"/><a:lstStyle/>"#);
+        write_paragraphs(xml, text);
+        xml.push_str("</p:txBody>");
+    }
+    xml.push_str("</p:sp>");
+}
+
+fn write_shape_style(xml: &mut String, style: &ShapeStyle, is_text_box: bool) {
+    match style.fill {
+        Some(color) => write_solid_fill(xml, apply_opacity(color, style.opacity)),
+        None if is_text_box => xml.push_str("<a:noFill/>"),
+        None => {}
+    }
+    if let Some(color) = style.stroke {
+        xml.push_str(&format!(r#"<a:ln w="{}">"#, pt_to_emu(style.stroke_width)));
+        write_solid_fill(xml, apply_opacity(color, style.opacity));
+        xml.push_str("</a:ln>");
+    }
+}
+
+/// Fold the shape opacity into a color's alpha. An opacity of zero is the
+/// unset default and leaves the color untouched.
+fn apply_opacity(color: [u8; 4], opacity: f32) -> [u8; 4] {
+    if opacity > 0.0 && opacity < 1.0 {
+        let alpha = (color[3] as f32 * opacity).round() as u8;
+        [color[0], color[1], color[2], alpha]
+    } else {
+        color
+    }
+}
+
+fn write_solid_fill(xml: &mut String, color: [u8; 4]) {
+    xml.push_str("<a:solidFill>");
+    write_color(xml, color);
+    xml.push_str("</a:solidFill>");
+}
+
+fn write_color(xml: &mut String, [r, g, b, a]: [u8; 4]) {
+    if a == 255 {
+        xml.push_str(&format!(
+            r#"<a:srgbClr val="{:02X}{:02X}{:02X}"/>"#,
+            r, g, b
+        ));
+    } else {
+        xml.push_str(&format!(
+            r#"<a:srgbClr val="{:02X}{:02X}{:02X}"><a:alpha val="{}"/></a:srgbClr>"#,
+            r,
+            g,
+            b,
+            a as u32 * 100_000 / 255
+        ));
+    }
+}
+
+/// Convert degrees to DrawingML angle units (60000ths of a degree).
+fn angle_to_ooxml(degrees: f32) -> i64 {
+    (degrees.rem_euclid(360.0) * 60_000.0).round() as i64
+}
+
+/// Write text as DrawingML paragraphs, one per line, splitting runs at span
+/// boundaries.
+fn write_paragraphs(xml: &mut String, text: &Text) {
+    let mut line_start = 0;
+    for line in text.content.split('\n') {
+        let line_end = line_start + line.len();
+        xml.push_str("<a:p>");
+
+        let mut boundaries = vec![line_start, line_end];
+        for span in &text.spans {
+            for offset in [span.start, span.end] {
+                if offset > line_start && offset < line_end {
+                    boundaries.push(offset);
+                }
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        for window in boundaries.windows(2) {
+            let (start, end) = (window[0], window[1]);
+            let Some(segment) = text.content.get(start..end) else {
+                continue;
+            };
+            let mut style = TextStyle::default();
+            for span in &text.spans {
+                if span.start <= start && span.end >= end {
+                    merge_style(&mut style, &span.style);
+                }
+            }
+            xml.push_str("<a:r>");
+            write_run_properties(xml, &style);
+            xml.push_str(&format!("<a:t>{}</a:t></a:r>", escape(segment)));
+        }
+
+        xml.push_str(r#"<a:endParaRPr lang="en-US"/></a:p>"#);
+        line_start = line_end + 1;
+    }
+}
+
+fn notes_slide(notes: &Text) -> String {
+    let mut xml = String::from(XML_DECL);
+    xml.push_str(&format!(
+        r#"<p:notes xmlns:a="{}" xmlns:r="{}" xmlns:p="{}"><p:cSld><p:spTree>{}"#,
+        NS_A, NS_R, NS_P, SP_TREE_HEADER
+    ));
+    xml.push_str(concat!(
+        r#"<p:sp><p:nvSpPr><p:cNvPr id="2" name="Notes Placeholder 1"/>"#,
+        r#"<p:cNvSpPr><a:spLocks noGrp="1"/></p:cNvSpPr>"#,
+        r#"<p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr><p:spPr/>"#,
+        "<p:txBody><a:bodyPr/><a:lstStyle/>",
+    ));
+    write_paragraphs(&mut xml, notes);
+    xml.push_str("</p:txBody></p:sp></p:spTree></p:cSld>");
+    xml.push_str("<p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:notes>");
+    xml
+}
+
+/// Overlay the fields set in `top` onto `base`.
+fn merge_style(base: &mut TextStyle, top: &TextStyle) {
+    macro_rules! overlay {
+        ($($field:ident),*) => {
+            $(if top.$field.is_some() {
+                base.$field = top.$field.clone();
+            })*
+        };
+    }
+    overlay!(
+        font_family,
+        font_size,
+        font_weight,
+        italic,
+        underline,
+        strikethrough,
+        color,
+        background,
+        superscript,
+        subscript,
+        small_caps,
+        letter_spacing
+    );
+}
+
+fn write_run_properties(xml: &mut String, style: &TextStyle) {
+    xml.push_str(r#"<a:rPr lang="en-US""#);
+    if let Some(size) = style.font_size {
+        xml.push_str(&format!(r#" sz="{}""#, (size * 100.0).round() as i64));
+    }
+    if let Some(weight) = style.font_weight {
+        xml.push_str(if weight >= 600 {
+            r#" b="1""#
+        } else {
+            r#" b="0""#
+        });
+    }
+    if let Some(italic) = style.italic {
+        xml.push_str(if italic { r#" i="1""# } else { r#" i="0""# });
+    }
+    if let Some(underline) = style.underline {
+        xml.push_str(if underline {
+            r#" u="sng""#
+        } else {
+            r#" u="none""#
+        });
+    }
+    if let Some(strike) = style.strikethrough {
+        xml.push_str(if strike {
+            r#" strike="sngStrike""#
+        } else {
+            r#" strike="noStrike""#
+        });
+    }
+    if style.superscript == Some(true) {
+        xml.push_str(r#" baseline="30000""#);
+    } else if style.subscript == Some(true) {
+        xml.push_str(r#" baseline="-25000""#);
+    }
+
+    if style.color.is_none() && style.font_family.is_none() {
+        xml.push_str("/>");
+        return;
+    }
+    xml.push('>');
+    if let Some(color) = style.color {
+        write_solid_fill(xml, color);
+    }
+    if let Some(family) = &style.font_family {
+        xml.push_str(&format!(r#"<a:latin typeface="{}"/>"#, escape(family)));
+    }
+    xml.push_str("</a:rPr>");
+}
@@ -0,0 +1,328 @@
+//! Binary container layout.
+//!
+//! All integers are little-endian.
+//!
+//! ```text
+//! magic          5 bytes   "WOLIA"
+//! version        u8
+//! flags          u16       bit 0: encrypted; other bits reserved, zero
+//! min_app_len    u16       (v2+) oldest Wolia release able to read the file
+//! min_app        UTF-8
+//! body           plain or sealed, below
+//! ```
+//!
+//! The body of a plain file:
+//!
+//! ```text
+//! document_len   u64
+//! document       zstd-compressed JSON of the document model
+//! asset_count    u32
+//! asset*         id_len u16, id, media_type_len u16, media_type,
+//!                data_len u64, data
+//! ```
+//!
+//! An encrypted file seals the same body, assets included, with
+//! XChaCha20-Poly1305 under a key derived from the password with Argon2id.
+//! The header up to the nonce is authenticated along with it:
+//!
+//! ```text
+//! kdf_memory     u32       Argon2id memory cost, in KiB
+//! kdf_passes     u32       Argon2id time cost
+//! kdf_lanes      u32       Argon2id parallelism
+//! salt           16 bytes
+//! nonce          24 bytes
+//! sealed_len     u64
+//! sealed         encrypted body and 16-byte tag
+//! ```
+//!
+//! Every version from 2 on keeps the fields up to `min_app` in place, so a
+//! build that is too old to read a file can still tell the user which
+//! release it needs.
+
+use std::collections::BTreeMap;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{Key, KeyInit, XChaCha20Poly1305, XNonce};
+use wolia_core::Document;
+
+use crate::migrate::{CURRENT_VERSION, migrate};
+use crate::{Asset, Error, Package};
+
+/// Magic bytes that open every .wolia file.
+pub const MAGIC: &[u8; 5] = b"WOLIA";
+
+/// Oldest release able to read files written at [`CURRENT_VERSION`].
+pub const MIN_APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Flag set on encrypted files.
+pub const FLAG_ENCRYPTED: u16 = 1;
+
+/// zstd level used for the document blob (0 selects the library default).
+const COMPRESSION_LEVEL: i32 = 0;
+
+/// Argon2id memory cost of new files, in KiB.
+const KDF_MEMORY: u32 = 19 * 1024;
+
+/// Argon2id time cost of new files.
+const KDF_PASSES: u32 = 2;
+
+/// Argon2id parallelism of new files.
+const KDF_LANES: u32 = 1;
+
+/// Most memory a file may ask key derivation for, in KiB, so that a
+/// damaged or hostile header cannot exhaust memory.
+const KDF_MAX_MEMORY: u32 = 1024 * 1024;
+
+/// Most passes a file may ask key derivation for, so that a damaged or
+/// hostile header cannot keep it running for hours.
+const KDF_MAX_PASSES: u32 = 16;
+
+/// Most lanes a file may ask key derivation for.
+const KDF_MAX_LANES: u32 = 16;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Encode a package into container bytes.
+pub fn encode(package: &Package) -> Result<Vec<u8>, Error> {
+    let mut data = header(0)?;
+    data.extend_from_slice(&encode_body(package)?);
+    Ok(data)
+}
+
+/// Encode a package into container bytes encrypted with `password`.
+pub fn encode_encrypted(package: &Package, password: &str) -> Result<Vec<u8>, Error> {
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    getrandom::fill(&mut salt)
+        .and_then(|()| getrandom::fill(&mut nonce))
+        .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))?;
+
+    let mut data = header(FLAG_ENCRYPTED)?;
+    for cost in [KDF_MEMORY, KDF_PASSES, KDF_LANES] {
+        data.extend_from_slice(&cost.to_le_bytes());
+    }
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+
+    let key = derive_key(password, &salt, KDF_MEMORY, KDF_PASSES, KDF_LANES)?;
+    let sealed = XChaCha20Poly1305::new(&key)
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &encode_body(package)?,
+                aad: &data,
+            },
+        )
+        .map_err(|_| Error::Parse("Encryption failed".to_string()))?;
+    data.extend_from_slice(&(sealed.len() as u64).to_le_bytes());
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+/// Check if container bytes are encrypted, without decoding them.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+        && data
+            .get(6..8)
+            .is_some_and(|flags| u16::from_le_bytes([flags[0], flags[1]]) & FLAG_ENCRYPTED != 0)
+}
+
+fn header(flags: u16) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(MAGIC);
+    data.push(CURRENT_VERSION);
+    data.extend_from_slice(&flags.to_le_bytes());
+    write_short_str(&mut data, MIN_APP_VERSION)?;
+    Ok(data)
+}
+
+fn encode_body(package: &Package) -> Result<Vec<u8>, Error> {
+    let json = serde_json::to_vec(&package.document).map_err(|e| Error::Parse(e.to_string()))?;
+    let compressed = zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL)?;
+
+    let mut data = Vec::with_capacity(compressed.len() + 16);
+    data.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+    data.extend_from_slice(&compressed);
+
+    data.extend_from_slice(&(package.assets.len() as u32).to_le_bytes());
+    for (id, asset) in &package.assets {
+        write_short_str(&mut data, id)?;
+        write_short_str(&mut data, &asset.media_type)?;
+        data.extend_from_slice(&(asset.data.len() as u64).to_le_bytes());
+        data.extend_from_slice(&asset.data);
+    }
+
+    Ok(data)
+}
+
+/// Decode container bytes into a package. Encrypted files fail with
+/// [`Error::PasswordRequired`].
+pub fn decode(data: &[u8]) -> Result<Package, Error> {
+    decode_with(data, None)
+}
+
+/// Decode container bytes, encrypted or not, into a package. `password` is
+/// only used if the file is encrypted.
+pub fn decode_encrypted(data: &[u8], password: &str) -> Result<Package, Error> {
+    decode_with(data, Some(password))
+}
+
+fn decode_with(data: &[u8], password: Option<&str>) -> Result<Package, Error> {
+    let mut cursor = Cursor { data, position: 0 };
+
+    if cursor.take(MAGIC.len())? != MAGIC {
+        return Err(Error::InvalidFormat);
+    }
+    let version = cursor.u8()?;
+    if version == 0 {
+        return Err(Error::InvalidFormat);
+    }
+    let flags = cursor.u16()?;
+    if version > CURRENT_VERSION {
+        return Err(Error::UnsupportedVersion {
+            version,
+            min_app_version: cursor.short_str().ok(),
+        });
+    }
+    if flags & !FLAG_ENCRYPTED != 0 {
+        return Err(Error::InvalidFormat);
+    }
+    if version >= 2 {
+        cursor.short_str()?;
+    }
+    if flags & FLAG_ENCRYPTED == 0 {
+        return decode_body(cursor, version);
+    }
+
+    let [memory, passes, lanes] = [cursor.u32()?, cursor.u32()?, cursor.u32()?];
+    let salt = cursor.take(SALT_LEN)?;
+    let nonce = cursor.take(NONCE_LEN)?;
+    let header = &data[..cursor.position];
+    let sealed_len = cursor.length()?;
+    let sealed = cursor.take(sealed_len)?;
+    if cursor.position != data.len()
+        || memory > KDF_MAX_MEMORY
+        || passes > KDF_MAX_PASSES
+        || lanes > KDF_MAX_LANES
+    {
+        return Err(Error::InvalidFormat);
+    }
+    let password = password.ok_or(Error::PasswordRequired)?;
+
+    // A wrong password and a damaged file fail the same check, and are
+    // reported alike.
+    let key = derive_key(password, salt, memory, passes, lanes)?;
+    let body = XChaCha20Poly1305::new(&key)
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: sealed,
+                aad: header,
+            },
+        )
+        .map_err(|_| Error::WrongPassword)?;
+    decode_body(
+        Cursor {
+            data: &body,
+            position: 0,
+        },
+        version,
+    )
+}
+
+fn decode_body(mut cursor: Cursor<'_>, version: u8) -> Result<Package, Error> {
+    let document_len = cursor.length()?;
+    let compressed = cursor.take(document_len)?;
+    let json = zstd::decode_all(compressed)?;
+    let json = serde_json::from_slice(&json).map_err(|e| Error::Parse(e.to_string()))?;
+    let document: Document =
+        serde_json::from_value(migrate(json, version)?).map_err(|e| Error::Parse(e.to_string()))?;
+
+    let asset_count = cursor.u32()?;
+    let mut assets = BTreeMap::new();
+    for _ in 0..asset_count {
+        let id = cursor.short_str()?;
+        let media_type = cursor.short_str()?;
+        let data_len = cursor.length()?;
+        let data = cursor.take(data_len)?.to_vec();
+        assets.insert(id, Asset { media_type, data });
+    }
+
+    if cursor.position != cursor.data.len() {
+        return Err(Error::InvalidFormat);
+    }
+
+    Ok(Package { document, assets })
+}
+
+fn derive_key(
+    password: &str,
+    salt: &[u8],
+    memory: u32,
+    passes: u32,
+    lanes: u32,
+) -> Result<Key, Error> {
+    let params = Params::new(memory, passes, lanes, Some(32)).map_err(|_| Error::InvalidFormat)?;
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|_| Error::InvalidFormat)?;
+    Ok(key)
+}
+
+fn write_short_str(data: &mut Vec<u8>, value: &str) -> Result<(), Error> {
+    let len = u16::try_from(value.len())
+        .map_err(|_| Error::Parse(format!("String too long for container: {}", value)))?;
+    data.extend_from_slice(&len.to_le_bytes());
+    data.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+/// Bounds-checked reader over the container bytes. Any read past the end
+/// means the file is truncated or corrupt.
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(Error::InvalidFormat)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn length(&mut self) -> Result<usize, Error> {
+        usize::try_from(u64::from_le_bytes(self.array()?)).map_err(|_| Error::InvalidFormat)
+    }
+
+    fn short_str(&mut self) -> Result<String, Error> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| Error::InvalidFormat)
+    }
+}
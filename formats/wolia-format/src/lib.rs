@@ -2,20 +2,120 @@
 //!
 //! The native .wolia file format implementation.
 //!
-//! The format is a zstd-compressed JSON document with embedded binary assets.
+//! The format is a zstd-compressed JSON document with embedded binary assets,
+//! optionally encrypted with a password. See [`container`] for the byte
+//! layout.
+
+use std::collections::BTreeMap;
 
 use wolia_core::Document;
 
-/// Read a document from .wolia format.
-pub fn read(_data: &[u8]) -> Result<Document, Error> {
-    // TODO: Implement
-    Ok(Document::new())
+pub mod container;
+pub mod migrate;
+
+pub use container::is_encrypted;
+pub use migrate::{CURRENT_VERSION, migrate};
+
+/// URI scheme used by image nodes to reference embedded assets.
+pub const ASSET_SCHEME: &str = "asset:";
+
+/// Read a document from .wolia format, discarding embedded assets.
+pub fn read(data: &[u8]) -> Result<Document, Error> {
+    Ok(read_package(data)?.document)
+}
+
+/// Write a document without embedded assets to .wolia format.
+pub fn write(document: &Document) -> Result<Vec<u8>, Error> {
+    container::encode(&Package {
+        document: document.clone(),
+        assets: BTreeMap::new(),
+    })
+}
+
+/// Read a document and its embedded assets from .wolia format.
+pub fn read_package(data: &[u8]) -> Result<Package, Error> {
+    container::decode(data)
+}
+
+/// Write a document and its embedded assets to .wolia format.
+pub fn write_package(package: &Package) -> Result<Vec<u8>, Error> {
+    container::encode(package)
+}
+
+/// Read a document, encrypted or not, from .wolia format, discarding
+/// embedded assets.
+pub fn read_with_password(data: &[u8], password: &str) -> Result<Document, Error> {
+    Ok(read_package_with_password(data, password)?.document)
 }
 
-/// Write a document to .wolia format.
-pub fn write(_document: &Document) -> Result<Vec<u8>, Error> {
-    // TODO: Implement
-    Ok(Vec::new())
+/// Write a document without embedded assets to .wolia format, encrypted
+/// with `password`.
+pub fn write_with_password(document: &Document, password: &str) -> Result<Vec<u8>, Error> {
+    container::encode_encrypted(&Package::new(document.clone()), password)
+}
+
+/// Read a document and its embedded assets, encrypted or not, from .wolia
+/// format.
+pub fn read_package_with_password(data: &[u8], password: &str) -> Result<Package, Error> {
+    container::decode_encrypted(data, password)
+}
+
+/// Write a document and its embedded assets to .wolia format, encrypted
+/// with `password`.
+pub fn write_package_with_password(package: &Package, password: &str) -> Result<Vec<u8>, Error> {
+    container::encode_encrypted(package, password)
+}
+
+/// A binary asset (image, font, ...) embedded in a document file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    /// MIME type of the data.
+    pub media_type: String,
+    /// Raw asset bytes.
+    pub data: Vec<u8>,
+}
+
+/// A document together with the assets it embeds, keyed by asset id.
+#[derive(Debug, Clone, Default)]
+pub struct Package {
+    /// The document model.
+    pub document: Document,
+    /// Embedded assets.
+    pub assets: BTreeMap<String, Asset>,
+}
+
+impl Package {
+    /// Create a package for a document with no assets.
+    pub fn new(document: Document) -> Self {
+        Self {
+            document,
+            assets: BTreeMap::new(),
+        }
+    }
+
+    /// Embed an asset and return the `src` that image nodes use to refer to it.
+    pub fn embed(
+        &mut self,
+        id: impl Into<String>,
+        media_type: impl Into<String>,
+        data: Vec<u8>,
+    ) -> String {
+        let id = id.into();
+        let src = format!("{}{}", ASSET_SCHEME, id);
+        self.assets.insert(
+            id,
+            Asset {
+                media_type: media_type.into(),
+                data,
+            },
+        );
+        src
+    }
+
+    /// Resolve an image `src` to an embedded asset.
+    pub fn resolve(&self, src: &str) -> Option<&Asset> {
+        self.assets.get(src.strip_prefix(ASSET_SCHEME)?)
+    }
 }
 
 /// Format errors.
@@ -29,4 +129,173 @@ pub enum Error {
 
     #[error("Invalid format")]
     InvalidFormat,
+
+    #[error(
+        "Document format version {version} is newer than this build supports \
+         (version {}); {}",
+        CURRENT_VERSION,
+        match min_app_version {
+            Some(app) => format!("Wolia {} or newer is required to open it", app),
+            None => "a newer Wolia release is required to open it".to_string(),
+        }
+    )]
+    UnsupportedVersion {
+        version: u8,
+        min_app_version: Option<String>,
+    },
+
+    #[error("Document is encrypted; a password is required to open it")]
+    PasswordRequired,
+
+    #[error("Wrong password, or the document is damaged")]
+    WrongPassword,
+}
+
+#[cfg(test)]
+mod tests {
+    use wolia_core::{Node, NodeKind, Text};
+
+    use super::*;
+
+    /// A 1x1 transparent PNG.
+    const PNG: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F,
+        0x15, 0xC4, 0x89, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0D, 0x0A, 0x2D, 0xB4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_roundtrip_with_embedded_png() {
+        let mut package = Package::new(Document::new());
+        package.document.metadata.title = Some("Report".to_string());
+        package
+            .document
+            .root
+            .add_child(Node::paragraph(Text::new("Hello")));
+        let src = package.embed("logo", "image/png", PNG.to_vec());
+        package.document.root.add_child(Node::new(NodeKind::Image {
+            src: src.clone(),
+            alt: Some("Logo".to_string()),
+        }));
+
+        let bytes = write_package(&package).unwrap();
+        assert!(bytes.starts_with(container::MAGIC));
+
+        let loaded = read_package(&bytes).unwrap();
+        assert_eq!(loaded.document.id, package.document.id);
+        assert_eq!(loaded.document.metadata.title.as_deref(), Some("Report"));
+        let NodeKind::Image {
+            src: loaded_src, ..
+        } = &loaded.document.root.children[1].kind
+        else {
+            panic!("expected image node");
+        };
+        let asset = loaded.resolve(loaded_src).unwrap();
+        assert_eq!(asset.media_type, "image/png");
+        assert_eq!(asset.data, PNG);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_header() {
+        assert!(matches!(read(b"NOTWOLIA"), Err(Error::InvalidFormat)));
+        assert!(matches!(read(b"WOL"), Err(Error::InvalidFormat)));
+
+        let mut bytes = write(&Document::new()).unwrap();
+        bytes[5] = 0;
+        assert!(matches!(read(&bytes), Err(Error::InvalidFormat)));
+    }
+
+    #[test]
+    fn test_newer_version_names_required_app() {
+        let mut bytes = write(&Document::new()).unwrap();
+        bytes[5] = CURRENT_VERSION + 1;
+        let error = read(&bytes).unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::UnsupportedVersion { min_app_version: Some(app), .. }
+                if app == container::MIN_APP_VERSION
+        ));
+        assert!(error.to_string().contains("or newer is required"));
+    }
+
+    #[test]
+    fn test_rejects_truncated_body() {
+        let bytes = write(&Document::new()).unwrap();
+        assert!(matches!(
+            read(&bytes[..bytes.len() - 2]),
+            Err(Error::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let mut package = Package::new(Document::new());
+        package
+            .document
+            .root
+            .add_child(Node::paragraph(Text::new("Salary review")));
+        package.embed("logo", "image/png", PNG.to_vec());
+
+        let bytes = write_package_with_password(&package, "hunter2").unwrap();
+        assert!(is_encrypted(&bytes));
+        assert!(!is_encrypted(&write(&Document::new()).unwrap()));
+        // Neither the text nor the assets are stored in the clear.
+        let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+        assert!(!contains(b"Salary review"));
+        assert!(!contains(b"image/png"));
+        assert!(!contains(&PNG[..8]));
+
+        let loaded = read_package_with_password(&bytes, "hunter2").unwrap();
+        assert_eq!(loaded.document.id, package.document.id);
+        assert_eq!(loaded.resolve("asset:logo").unwrap().data, PNG);
+
+        // Plain files open with or without a password.
+        let plain = write(&package.document).unwrap();
+        assert!(read_with_password(&plain, "hunter2").is_ok());
+    }
+
+    #[test]
+    fn test_wrong_password_rejected() {
+        let bytes = write_with_password(&Document::new(), "hunter2").unwrap();
+        assert!(matches!(read(&bytes), Err(Error::PasswordRequired)));
+        assert!(matches!(
+            read_with_password(&bytes, "Hunter2"),
+            Err(Error::WrongPassword)
+        ));
+
+        // Damage to the sealed body or the header it authenticates reads
+        // the same as a wrong password.
+        let last = bytes.len() - 1;
+        let mut damaged = bytes.clone();
+        damaged[last] ^= 1;
+        assert!(matches!(
+            read_with_password(&damaged, "hunter2"),
+            Err(Error::WrongPassword)
+        ));
+        let mut damaged = bytes.clone();
+        damaged[10] ^= 1;
+        assert!(matches!(
+            read_with_password(&damaged, "hunter2"),
+            Err(Error::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_hostile_key_derivation_costs() {
+        let bytes = write_with_password(&Document::new(), "hunter2").unwrap();
+        // The costs follow the header's name of the oldest app.
+        let costs = 10 + u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        for field in 0..3 {
+            let mut hostile = bytes.clone();
+            let at = costs + 4 * field;
+            hostile[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            // Refused before any key is derived.
+            assert!(matches!(
+                read_with_password(&hostile, "hunter2"),
+                Err(Error::InvalidFormat)
+            ));
+        }
+    }
 }
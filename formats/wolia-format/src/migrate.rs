@@ -0,0 +1,107 @@
+//! Format version migrations.
+//!
+//! Documents written by older builds are upgraded one version at a time, as
+//! untyped JSON, until they match the current schema. Only then are they
+//! deserialized into [`wolia_core::Document`].
+
+use serde_json::Value;
+
+use crate::Error;
+
+/// Format version written by this build.
+pub const CURRENT_VERSION: u8 = 2;
+
+/// A single upgrade step from one version to the next.
+type Migration = fn(Value) -> Result<Value, Error>;
+
+/// `MIGRATIONS[n]` upgrades version `n + 1` to version `n + 2`.
+const MIGRATIONS: &[Migration] = &[v1_to_v2];
+
+const _: () = assert!(MIGRATIONS.len() == CURRENT_VERSION as usize - 1);
+
+/// Upgrade document JSON written at `from_version` to [`CURRENT_VERSION`].
+pub fn migrate(mut doc_json: Value, from_version: u8) -> Result<Value, Error> {
+    if from_version == 0 {
+        return Err(Error::InvalidFormat);
+    }
+    if from_version > CURRENT_VERSION {
+        return Err(Error::UnsupportedVersion {
+            version: from_version,
+            min_app_version: None,
+        });
+    }
+
+    for step in &MIGRATIONS[from_version as usize - 1..] {
+        doc_json = step(doc_json)?;
+    }
+    Ok(doc_json)
+}
+
+/// v2 stores metadata timestamps in milliseconds instead of seconds.
+fn v1_to_v2(mut doc_json: Value) -> Result<Value, Error> {
+    let Some(metadata) = doc_json.get_mut("metadata").and_then(Value::as_object_mut) else {
+        return Err(Error::Parse("Document has no metadata object".to_string()));
+    };
+
+    for key in ["created", "modified"] {
+        if let Some(seconds) = metadata.get(key).and_then(Value::as_i64) {
+            metadata.insert(key.to_string(), Value::from(seconds.saturating_mul(1000)));
+        }
+    }
+    Ok(doc_json)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wolia_core::{Document, NodeKind};
+
+    use super::*;
+
+    #[test]
+    fn test_v1_document_loads() {
+        let v1 = json!({
+            "id": "7c1f1d3e-5a4b-4c8e-9d2f-0a1b2c3d4e5f",
+            "metadata": {
+                "title": "Old notes",
+                "author": null,
+                "created": 1_600_000_000,
+                "modified": null,
+                "description": null,
+                "properties": {}
+            },
+            "root": {
+                "id": "0e7d9a52-3b1c-4f5e-8a6d-9c8b7a6f5e4d",
+                "kind": { "type": "root" },
+                "children": [{
+                    "id": "1f2e3d4c-5b6a-4978-8695-a4b3c2d1e0f9",
+                    "kind": {
+                        "type": "heading",
+                        "level": 1,
+                        "text": { "content": "Hello", "spans": [] }
+                    },
+                    "children": []
+                }]
+            },
+            "styles": { "styles": {} }
+        });
+
+        let migrated = migrate(v1, 1).unwrap();
+        let document: Document = serde_json::from_value(migrated).unwrap();
+
+        assert_eq!(document.metadata.title.as_deref(), Some("Old notes"));
+        assert_eq!(document.metadata.created, Some(1_600_000_000_000));
+        assert!(matches!(
+            &document.root.children[0].kind,
+            NodeKind::Heading { level: 1, text } if text.content == "Hello"
+        ));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        assert!(matches!(
+            migrate(json!({}), CURRENT_VERSION + 1),
+            Err(Error::UnsupportedVersion { .. })
+        ));
+    }
+}
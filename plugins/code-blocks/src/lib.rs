@@ -1,13 +1,28 @@
 //! # Code Blocks Plugin
 //!
 //! Syntax-highlighted code blocks for Wolia.
+//!
+//! The plugin handles `"code"` blocks, picking the syntax from the block's
+//! language and coloring its tokens with a theme.
+
+use std::sync::Arc;
 
-use wolia_plugin::{Plugin, Result};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, FontStyle, Style, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+use wolia_core::style::TextStyle;
+use wolia_core::text::{Span, Text};
+use wolia_plugin::{BlockHandler, BlockSource, Plugin, PluginApi, RenderedBlock, Result};
+
+/// Theme used unless another is chosen.
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
 
 /// Code blocks plugin.
 pub struct CodeBlocksPlugin {
     name: String,
     version: String,
+    highlighter: Arc<Highlighter>,
 }
 
 impl CodeBlocksPlugin {
@@ -15,8 +30,20 @@ impl CodeBlocksPlugin {
         Self {
             name: "code-blocks".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            highlighter: Arc::new(Highlighter::new()),
         }
     }
+
+    /// Highlight with one of the [built-in themes](Highlighter::themes).
+    pub fn with_theme(mut self, theme: &str) -> std::result::Result<Self, Error> {
+        self.highlighter = Arc::new(Highlighter::with_theme(theme)?);
+        Ok(self)
+    }
+
+    /// The highlighter the plugin renders blocks with.
+    pub fn highlighter(&self) -> &Highlighter {
+        &self.highlighter
+    }
 }
 
 impl Default for CodeBlocksPlugin {
@@ -41,4 +68,193 @@ impl Plugin for CodeBlocksPlugin {
     fn shutdown(&mut self) {
         // Cleanup
     }
+
+    fn register(&mut self, api: &mut dyn PluginApi) -> Result<()> {
+        api.register_block_handler(&["code"], self.highlighter.clone())
+    }
+}
+
+/// Syntax highlighter for source code.
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+impl Highlighter {
+    /// Create a highlighter with the default theme.
+    pub fn new() -> Self {
+        Self::with_theme(DEFAULT_THEME).expect("default theme is built in")
+    }
+
+    /// Create a highlighter with one of the [built-in themes](Self::themes).
+    pub fn with_theme(theme: &str) -> std::result::Result<Self, Error> {
+        let theme = ThemeSet::load_defaults()
+            .themes
+            .remove(theme)
+            .ok_or_else(|| Error::UnknownTheme(theme.to_string()))?;
+        Ok(Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+        })
+    }
+
+    /// Names of the built-in themes.
+    pub fn themes() -> Vec<String> {
+        ThemeSet::load_defaults().themes.into_keys().collect()
+    }
+
+    /// Highlight source code, choosing the syntax by a language name or
+    /// file extension such as `"rust"` or `"rs"`. Unknown languages are
+    /// plain text.
+    ///
+    /// Every byte of the result is covered by a span with its color, and
+    /// neighbouring tokens of the same style share a span.
+    pub fn highlight(&self, language: Option<&str>, source: &str) -> Text {
+        let syntax = language
+            .and_then(|language| self.syntaxes.find_syntax_by_token(language))
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+
+        let mut text = Text::new(source);
+        let mut offset = 0;
+        for line in LinesWithEndings::from(source) {
+            // Source the syntax cannot make sense of keeps the plain style.
+            let Ok(tokens) = highlighter.highlight_line(line, &self.syntaxes) else {
+                break;
+            };
+            for (style, token) in tokens {
+                push_span(&mut text, offset..offset + token.len(), text_style(style));
+                offset += token.len();
+            }
+        }
+        if offset < source.len() {
+            let plain = Style {
+                foreground: self.theme.settings.foreground.unwrap_or(Color::BLACK),
+                ..Style::default()
+            };
+            push_span(&mut text, offset..source.len(), text_style(plain));
+        }
+        text
+    }
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockHandler for Highlighter {
+    fn handle_block(&self, block: &BlockSource<'_>) -> Option<RenderedBlock> {
+        Some(RenderedBlock::Text(Box::new(
+            self.highlight(block.language, block.source),
+        )))
+    }
+}
+
+/// Add a span to `text`, extending the last one if it has the same style.
+fn push_span(text: &mut Text, range: std::ops::Range<usize>, style: TextStyle) {
+    if range.is_empty() {
+        return;
+    }
+    match text.spans.last_mut() {
+        Some(last) if last.end == range.start && last.style == style => last.end = range.end,
+        _ => text.add_span(Span::new(range.start, range.end, style)),
+    }
+}
+
+fn text_style(style: Style) -> TextStyle {
+    let color = style.foreground;
+    let font = style.font_style;
+    TextStyle {
+        color: Some([color.r, color.g, color.b, color.a]),
+        font_weight: font.contains(FontStyle::BOLD).then_some(700),
+        italic: font.contains(FontStyle::ITALIC).then_some(true),
+        underline: font.contains(FontStyle::UNDERLINE).then_some(true),
+        ..TextStyle::default()
+    }
+}
+
+/// Errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Unknown theme: {0}")]
+    UnknownTheme(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const RUST: &str =
+        "fn main() {\n    let answer = 42; // the answer\n    println!(\"{answer}\");\n}\n";
+
+    /// Whether the spans cover the whole text in order, without gaps.
+    fn covers(text: &Text) -> bool {
+        let mut offset = 0;
+        for span in &text.spans {
+            if span.start != offset {
+                return false;
+            }
+            offset = span.end;
+        }
+        offset == text.len()
+    }
+
+    #[test]
+    fn test_highlights_rust() {
+        let text = Highlighter::new().highlight(Some("rust"), RUST);
+        assert_eq!(text.content, RUST);
+        assert!(covers(&text));
+        let colors: HashSet<_> = text.spans.iter().map(|span| span.style.color).collect();
+        assert!(colors.len() > 2, "{colors:?}");
+    }
+
+    #[test]
+    fn test_unknown_language_is_one_plain_span() {
+        let highlighter = Highlighter::new();
+        for language in [Some("no-such-language"), None] {
+            let text = highlighter.highlight(language, RUST);
+            assert_eq!(text.spans.len(), 1);
+            assert!(covers(&text));
+        }
+    }
+
+    #[test]
+    fn test_partial_source_is_covered() {
+        let source = "fn broken( {\n    let s = \"unterminated";
+        let text = Highlighter::new().highlight(Some("rs"), source);
+        assert!(covers(&text));
+    }
+
+    #[test]
+    fn test_themes() {
+        assert!(Highlighter::themes().contains(&DEFAULT_THEME.to_string()));
+        let dark = Highlighter::with_theme("base16-ocean.dark").unwrap();
+        let light = Highlighter::new();
+        assert_ne!(
+            dark.highlight(Some("rust"), RUST).spans[0].style.color,
+            light.highlight(Some("rust"), RUST).spans[0].style.color
+        );
+        assert!(matches!(
+            CodeBlocksPlugin::new().with_theme("missing"),
+            Err(Error::UnknownTheme(_))
+        ));
+    }
+
+    #[test]
+    fn test_handles_code_blocks() {
+        let block = BlockSource {
+            kind: "code",
+            language: Some("rust"),
+            source: RUST,
+        };
+        let Some(RenderedBlock::Text(text)) =
+            CodeBlocksPlugin::new().highlighter().handle_block(&block)
+        else {
+            panic!("expected highlighted text");
+        };
+        assert!(text.spans.len() > 1);
+    }
 }
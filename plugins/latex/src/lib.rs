@@ -1,13 +1,94 @@
 //! # LaTeX Plugin
 //!
 //! LaTeX/math equation support for Wolia.
+//!
+//! The plugin handles `"latex"` blocks, typesetting their source as a
+//! [`Drawing`]. Inline math in text is found with [`split_inline`].
+//!
+//! ## Supported subset
+//!
+//! - Letters (in italics), digits, and `+ - * / = < > ( ) [ ] , ; : . | ' ! ?`
+//! - Groups in braces, `x^{...}` superscripts, and `x_{...}` subscripts
+//! - `\frac{a}{b}` and `\sqrt{x}`
+//! - Greek letters, such as `\alpha` and `\Omega`
+//! - Operators and relations: `\pm \mp \times \div \cdot \leq \le \geq \ge
+//!   \neq \ne \approx \equiv \in \to \rightarrow \leftarrow`
+//! - Symbols: `\infty \partial \nabla \ldots \cdots \sum \prod \int \{ \}`
+//! - Spaces: `\, \: \; \  \quad \qquad`
+//!
+//! Any other command or character is an error.
+
+mod parse;
+mod typeset;
+
+use std::sync::Arc;
+
+use wolia_core::text::Text;
+use wolia_plugin::{BlockHandler, BlockSource, Drawing, Plugin, PluginApi, RenderedBlock, Result};
 
-use wolia_plugin::{Plugin, Result};
+/// Font size math is set at unless another is chosen.
+pub const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+/// Typeset a formula at `font_size` points.
+pub fn layout(source: &str, font_size: f32) -> std::result::Result<Drawing, Error> {
+    let atoms = parse::parse(source)?;
+    Ok(typeset::typeset(&atoms, font_size))
+}
+
+/// A piece of text with inline math.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Plain text.
+    Text(&'a str),
+    /// The source of a formula, without its dollar signs.
+    Math(&'a str),
+}
+
+/// Split text into plain text and the `$...$` formulas in it.
+///
+/// `\$` is a literal dollar sign, and a `$` without a closing one is plain
+/// text.
+pub fn split_inline(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut math_start = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|&(_, next)| next == '$') => {
+                if math_start.is_none() {
+                    // Drop the backslash and keep the dollar sign as text.
+                    if start < i {
+                        segments.push(Segment::Text(&text[start..i]));
+                    }
+                    start = i + 1;
+                }
+                chars.next();
+            }
+            '$' => match math_start.take() {
+                Some(open) => {
+                    if start < open {
+                        segments.push(Segment::Text(&text[start..open]));
+                    }
+                    segments.push(Segment::Math(&text[open + 1..i]));
+                    start = i + 1;
+                }
+                None => math_start = Some(i),
+            },
+            _ => {}
+        }
+    }
+    if start < text.len() {
+        segments.push(Segment::Text(&text[start..]));
+    }
+    segments
+}
 
 /// LaTeX plugin.
 pub struct LatexPlugin {
     name: String,
     version: String,
+    typesetter: Arc<Typesetter>,
 }
 
 impl LatexPlugin {
@@ -15,8 +96,22 @@ impl LatexPlugin {
         Self {
             name: "latex".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            typesetter: Arc::new(Typesetter {
+                font_size: DEFAULT_FONT_SIZE,
+            }),
         }
     }
+
+    /// Set math at `font_size` points.
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.typesetter = Arc::new(Typesetter { font_size });
+        self
+    }
+
+    /// Font size math is set at.
+    pub fn font_size(&self) -> f32 {
+        self.typesetter.font_size
+    }
 }
 
 impl Default for LatexPlugin {
@@ -41,4 +136,164 @@ impl Plugin for LatexPlugin {
     fn shutdown(&mut self) {
         // Cleanup
     }
+
+    fn register(&mut self, api: &mut dyn PluginApi) -> Result<()> {
+        api.register_block_handler(&["latex"], self.typesetter.clone())
+    }
+}
+
+/// Renders `"latex"` blocks.
+struct Typesetter {
+    font_size: f32,
+}
+
+impl BlockHandler for Typesetter {
+    fn handle_block(&self, block: &BlockSource<'_>) -> Option<RenderedBlock> {
+        // Source that cannot be typeset is shown as it is rather than lost.
+        Some(match layout(block.source, self.font_size) {
+            Ok(drawing) => RenderedBlock::Drawing(drawing),
+            Err(_) => RenderedBlock::Text(Box::new(Text::new(block.source))),
+        })
+    }
+}
+
+/// Errors.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Unsupported command: \\{0}")]
+    UnsupportedCommand(String),
+    #[error("Unsupported character: {0:?}")]
+    UnsupportedCharacter(char),
+    #[error("Missing argument for {0}")]
+    MissingArgument(String),
+    #[error("Unbalanced braces")]
+    UnbalancedBraces,
+    #[error("Double script: {0}")]
+    DoubleScript(char),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wolia_plugin::PlacedGlyph;
+
+    const SIZE: f32 = 10.0;
+
+    fn glyph(drawing: &Drawing, ch: char) -> PlacedGlyph {
+        *drawing
+            .glyphs
+            .iter()
+            .find(|glyph| glyph.ch == ch)
+            .unwrap_or_else(|| panic!("no {ch:?} in {drawing:?}"))
+    }
+
+    #[test]
+    fn test_layout_sum_of_power_and_fraction() {
+        let drawing = layout(r"x^2 + \frac{1}{2}", SIZE).unwrap();
+        let x = glyph(&drawing, 'x');
+        let two = drawing.glyphs[1];
+        let plus = glyph(&drawing, '+');
+        let one = glyph(&drawing, '1');
+        let half = drawing.glyphs[4];
+        assert_eq!(drawing.glyphs.len(), 5);
+
+        // The superscript is raised, smaller, and after its base.
+        assert_eq!((x.x, x.y), (0.0, 0.0));
+        assert!(x.italic);
+        assert_eq!(two.ch, '2');
+        assert!(two.y < -0.3 * SIZE, "{two:?}");
+        assert!(two.size < SIZE && two.x > x.x);
+
+        // The operator sits on the baseline with space on both sides.
+        assert_eq!(plus.y, 0.0);
+        assert!(plus.x > two.x + 0.35 * SIZE);
+
+        // The fraction's parts are over and under its bar, centered on it.
+        assert_eq!(drawing.rules.len(), 1);
+        let [bar_x, bar_y, bar_width, bar_height] = drawing.rules[0];
+        assert!(bar_x > plus.x + 0.78 * SIZE);
+        assert!(bar_y < 0.0 && bar_height > 0.0);
+        assert!(one.y < bar_y && half.y > bar_y + bar_height);
+        assert_eq!(one.x, half.x);
+        assert!((one.x + 0.25 * one.size - (bar_x + bar_width / 2.0)).abs() < 1e-4);
+
+        // The bounds enclose everything.
+        assert!((drawing.width - (bar_x + bar_width)).abs() < 1e-4);
+        assert!(drawing.ascent > -one.y + 0.6 * one.size);
+        assert!(drawing.descent >= half.y - 1e-4);
+        assert!(drawing.height() > SIZE);
+    }
+
+    #[test]
+    fn test_scripts_on_one_base() {
+        let drawing = layout("a_i^2", SIZE).unwrap();
+        let i = glyph(&drawing, 'i');
+        let two = glyph(&drawing, '2');
+        assert_eq!(i.x, two.x);
+        assert!(i.y > 0.0 && two.y < 0.0);
+        assert!(i.y - two.y > 0.5 * SIZE);
+    }
+
+    #[test]
+    fn test_sqrt_covers_body() {
+        let drawing = layout(r"\sqrt{\alpha}", SIZE).unwrap();
+        let radical = glyph(&drawing, '√');
+        let alpha = glyph(&drawing, 'α');
+        assert!(alpha.x > radical.x);
+        assert_eq!(drawing.rules.len(), 1);
+        let [x, y, width, _] = drawing.rules[0];
+        assert!(x <= alpha.x && x + width > alpha.x + 0.5 * SIZE);
+        assert!(y < -0.44 * SIZE);
+    }
+
+    #[test]
+    fn test_rejects_unsupported_input() {
+        assert_eq!(
+            layout(r"\matrix{1}", SIZE),
+            Err(Error::UnsupportedCommand("matrix".to_string()))
+        );
+        assert_eq!(layout("x & y", SIZE), Err(Error::UnsupportedCharacter('&')));
+        assert_eq!(
+            layout(r"\frac{1}", SIZE),
+            Err(Error::MissingArgument(r"\frac".into()))
+        );
+        assert_eq!(layout("{x", SIZE), Err(Error::UnbalancedBraces));
+        assert_eq!(layout("x}", SIZE), Err(Error::UnbalancedBraces));
+        assert_eq!(layout("x^2^3", SIZE), Err(Error::DoubleScript('^')));
+    }
+
+    #[test]
+    fn test_split_inline() {
+        assert_eq!(
+            split_inline(r"Area $\pi r^2$ costs \$5, $x$"),
+            [
+                Segment::Text("Area "),
+                Segment::Math(r"\pi r^2"),
+                Segment::Text(" costs "),
+                Segment::Text("$5, "),
+                Segment::Math("x"),
+            ]
+        );
+        assert_eq!(split_inline("a $b"), [Segment::Text("a $b")]);
+    }
+
+    #[test]
+    fn test_handles_latex_blocks() {
+        let plugin = LatexPlugin::new().with_font_size(20.0);
+        let block = |source| BlockSource {
+            kind: "latex",
+            language: None,
+            source,
+        };
+        let Some(RenderedBlock::Drawing(drawing)) =
+            plugin.typesetter.handle_block(&block("E = mc^2"))
+        else {
+            panic!("expected a drawing");
+        };
+        assert_eq!(drawing.glyphs[0].size, 20.0);
+        assert!(matches!(
+            plugin.typesetter.handle_block(&block(r"\unknown")),
+            Some(RenderedBlock::Text(text)) if text.content == r"\unknown"
+        ));
+    }
 }
@@ -0,0 +1,289 @@
+//! Parsing of LaTeX math into atoms.
+
+use crate::Error;
+
+/// How a symbol is spaced against its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Class {
+    /// Letters, digits, and symbols like `\infty`.
+    Ordinary,
+    /// Binary operators like `+` and `\times`.
+    Binary,
+    /// Relations like `=` and `\leq`.
+    Relation,
+    /// Large operators like `\sum`.
+    Operator,
+    /// Opening brackets.
+    Open,
+    /// Closing brackets.
+    Close,
+    /// Commas and semicolons.
+    Punctuation,
+}
+
+/// A piece of a formula.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Atom {
+    Symbol {
+        ch: char,
+        class: Class,
+        italic: bool,
+    },
+    Group(Vec<Atom>),
+    Fraction(Vec<Atom>, Vec<Atom>),
+    Sqrt(Vec<Atom>),
+    Scripts {
+        base: Box<Atom>,
+        sup: Option<Vec<Atom>>,
+        sub: Option<Vec<Atom>>,
+    },
+    /// Horizontal space, in ems.
+    Space(f32),
+}
+
+/// Parse a formula.
+pub(crate) fn parse(source: &str) -> Result<Vec<Atom>, Error> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+    };
+    let atoms = parser.list()?;
+    match parser.peek() {
+        Some('}') => Err(Error::UnbalancedBraces),
+        _ => Ok(atoms),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Atoms up to the end of the source or a closing brace, which is left
+    /// unread.
+    fn list(&mut self) -> Result<Vec<Atom>, Error> {
+        let mut atoms = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some('}') => return Ok(atoms),
+                Some(c @ ('^' | '_')) => {
+                    self.pos += 1;
+                    let script = self.argument(&c.to_string())?;
+                    let (base, mut sup, mut sub) = match atoms.pop() {
+                        Some(Atom::Scripts { base, sup, sub }) => (base, sup, sub),
+                        Some(atom) => (Box::new(atom), None, None),
+                        None => (Box::new(Atom::Group(Vec::new())), None, None),
+                    };
+                    let slot = if c == '^' { &mut sup } else { &mut sub };
+                    if slot.is_some() {
+                        return Err(Error::DoubleScript(c));
+                    }
+                    *slot = Some(script);
+                    atoms.push(Atom::Scripts { base, sup, sub });
+                }
+                Some(_) => {
+                    let atom = self.atom()?;
+                    atoms.push(atom);
+                }
+            }
+        }
+    }
+
+    /// A single atom: a group, a command, or a character.
+    fn atom(&mut self) -> Result<Atom, Error> {
+        match self.next() {
+            Some('{') => {
+                let atoms = self.list()?;
+                match self.next() {
+                    Some('}') => Ok(Atom::Group(atoms)),
+                    _ => Err(Error::UnbalancedBraces),
+                }
+            }
+            Some('\\') => self.command(),
+            Some(c) => symbol(c).ok_or(Error::UnsupportedCharacter(c)),
+            None => Err(Error::UnbalancedBraces),
+        }
+    }
+
+    /// The argument of a command or script: a group or a single atom.
+    fn argument(&mut self, command: &str) -> Result<Vec<Atom>, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            None | Some('}' | '^' | '_') => Err(Error::MissingArgument(command.to_string())),
+            Some(_) => match self.atom()? {
+                Atom::Group(atoms) => Ok(atoms),
+                atom => Ok(vec![atom]),
+            },
+        }
+    }
+
+    fn command(&mut self) -> Result<Atom, Error> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            // A single non-letter, like `\,` or `\{`.
+            let c = self
+                .next()
+                .ok_or(Error::UnsupportedCommand(String::new()))?;
+            return match c {
+                ',' => Ok(Atom::Space(3.0 / 18.0)),
+                ':' => Ok(Atom::Space(4.0 / 18.0)),
+                ';' => Ok(Atom::Space(5.0 / 18.0)),
+                ' ' => Ok(Atom::Space(1.0 / 3.0)),
+                '{' => Ok(sym('{', Class::Open)),
+                '}' => Ok(sym('}', Class::Close)),
+                _ => Err(Error::UnsupportedCommand(c.to_string())),
+            };
+        }
+
+        let name: String = self.chars[start..self.pos].iter().collect();
+        match name.as_str() {
+            "frac" => {
+                let numerator = self.argument("\\frac")?;
+                let denominator = self.argument("\\frac")?;
+                Ok(Atom::Fraction(numerator, denominator))
+            }
+            "sqrt" => Ok(Atom::Sqrt(self.argument("\\sqrt")?)),
+            "quad" => Ok(Atom::Space(1.0)),
+            "qquad" => Ok(Atom::Space(2.0)),
+            _ => command_symbol(&name).ok_or(Error::UnsupportedCommand(name)),
+        }
+    }
+}
+
+fn sym(ch: char, class: Class) -> Atom {
+    Atom::Symbol {
+        ch,
+        class,
+        italic: false,
+    }
+}
+
+/// The atom for a character typed directly.
+fn symbol(c: char) -> Option<Atom> {
+    let class = match c {
+        'a'..='z' | 'A'..='Z' => {
+            return Some(Atom::Symbol {
+                ch: c,
+                class: Class::Ordinary,
+                italic: true,
+            });
+        }
+        '0'..='9' | '.' | '/' | '|' | '\'' | '!' | '?' => Class::Ordinary,
+        '+' | '*' => Class::Binary,
+        // A hyphen is typeset as a minus sign.
+        '-' => return Some(sym('−', Class::Binary)),
+        '=' | '<' | '>' | ':' => Class::Relation,
+        '(' | '[' => Class::Open,
+        ')' | ']' => Class::Close,
+        ',' | ';' => Class::Punctuation,
+        _ => return None,
+    };
+    Some(sym(c, class))
+}
+
+/// The atom for a command naming a symbol.
+fn command_symbol(name: &str) -> Option<Atom> {
+    const LOWER_GREEK: &[(&str, char)] = &[
+        ("alpha", 'α'),
+        ("beta", 'β'),
+        ("gamma", 'γ'),
+        ("delta", 'δ'),
+        ("epsilon", 'ϵ'),
+        ("varepsilon", 'ε'),
+        ("zeta", 'ζ'),
+        ("eta", 'η'),
+        ("theta", 'θ'),
+        ("iota", 'ι'),
+        ("kappa", 'κ'),
+        ("lambda", 'λ'),
+        ("mu", 'μ'),
+        ("nu", 'ν'),
+        ("xi", 'ξ'),
+        ("pi", 'π'),
+        ("rho", 'ρ'),
+        ("sigma", 'σ'),
+        ("tau", 'τ'),
+        ("upsilon", 'υ'),
+        ("phi", 'ϕ'),
+        ("varphi", 'φ'),
+        ("chi", 'χ'),
+        ("psi", 'ψ'),
+        ("omega", 'ω'),
+    ];
+    const UPPER_GREEK: &[(&str, char)] = &[
+        ("Gamma", 'Γ'),
+        ("Delta", 'Δ'),
+        ("Theta", 'Θ'),
+        ("Lambda", 'Λ'),
+        ("Xi", 'Ξ'),
+        ("Pi", 'Π'),
+        ("Sigma", 'Σ'),
+        ("Upsilon", 'Υ'),
+        ("Phi", 'Φ'),
+        ("Psi", 'Ψ'),
+        ("Omega", 'Ω'),
+    ];
+    const SYMBOLS: &[(&str, char, Class)] = &[
+        ("pm", '±', Class::Binary),
+        ("mp", '∓', Class::Binary),
+        ("times", '×', Class::Binary),
+        ("div", '÷', Class::Binary),
+        ("cdot", '⋅', Class::Binary),
+        ("leq", '≤', Class::Relation),
+        ("le", '≤', Class::Relation),
+        ("geq", '≥', Class::Relation),
+        ("ge", '≥', Class::Relation),
+        ("neq", '≠', Class::Relation),
+        ("ne", '≠', Class::Relation),
+        ("approx", '≈', Class::Relation),
+        ("equiv", '≡', Class::Relation),
+        ("in", '∈', Class::Relation),
+        ("to", '→', Class::Relation),
+        ("rightarrow", '→', Class::Relation),
+        ("leftarrow", '←', Class::Relation),
+        ("infty", '∞', Class::Ordinary),
+        ("partial", '∂', Class::Ordinary),
+        ("nabla", '∇', Class::Ordinary),
+        ("ldots", '…', Class::Ordinary),
+        ("cdots", '⋯', Class::Ordinary),
+        ("sum", '∑', Class::Operator),
+        ("prod", '∏', Class::Operator),
+        ("int", '∫', Class::Operator),
+    ];
+
+    if let Some(&(_, ch)) = LOWER_GREEK.iter().find(|(n, _)| *n == name) {
+        return Some(Atom::Symbol {
+            ch,
+            class: Class::Ordinary,
+            italic: true,
+        });
+    }
+    if let Some(&(_, ch)) = UPPER_GREEK.iter().find(|(n, _)| *n == name) {
+        return Some(sym(ch, Class::Ordinary));
+    }
+    SYMBOLS
+        .iter()
+        .find(|(n, ..)| *n == name)
+        .map(|&(_, ch, class)| sym(ch, class))
+}
@@ -0,0 +1,248 @@
+//! Placement of parsed atoms as glyphs and rules.
+//!
+//! Glyph metrics are approximations in ems, the same for every font, so
+//! sizes are close to but not exactly those of the font the renderer uses.
+
+use wolia_plugin::{Drawing, PlacedGlyph};
+
+use crate::parse::{Atom, Class};
+
+/// Size of scripts relative to their base.
+const SCRIPT_SCALE: f32 = 0.7;
+/// Size of fraction parts relative to the fraction.
+const FRACTION_SCALE: f32 = 0.8;
+/// Height of the math axis, where fraction bars sit, above the baseline.
+const AXIS: f32 = 0.25;
+/// Thickness of fraction bars and radical overlines.
+const RULE: f32 = 0.05;
+
+/// Place a formula at `size` points.
+pub(crate) fn typeset(atoms: &[Atom], size: f32) -> Drawing {
+    list(atoms, size, false)
+}
+
+/// Place atoms side by side. Scripts are set without the spacing around
+/// operators, like TeX does.
+fn list(atoms: &[Atom], size: f32, script: bool) -> Drawing {
+    let mut out = Drawing::default();
+    let mut previous: Option<Class> = None;
+    for atom in atoms {
+        let mut class = class_of(atom);
+        // A binary operator with nothing to its left is a sign, like `-1`.
+        if class == Class::Binary
+            && matches!(
+                previous,
+                None | Some(
+                    Class::Binary
+                        | Class::Relation
+                        | Class::Open
+                        | Class::Punctuation
+                        | Class::Operator
+                )
+            )
+        {
+            class = Class::Ordinary;
+        }
+        let space = match previous {
+            Some(previous) if !script => spacing(previous, class) * size,
+            _ => 0.0,
+        };
+        let x = out.width + space;
+        place(&mut out, self::atom(atom, size, script), x, 0.0);
+        previous = Some(class);
+    }
+    out
+}
+
+fn class_of(atom: &Atom) -> Class {
+    match atom {
+        Atom::Symbol { class, .. } => *class,
+        Atom::Scripts { base, .. } => class_of(base),
+        _ => Class::Ordinary,
+    }
+}
+
+/// Space between atoms of two classes, in ems.
+fn spacing(left: Class, right: Class) -> f32 {
+    const THIN: f32 = 3.0 / 18.0;
+    const MEDIUM: f32 = 4.0 / 18.0;
+    const THICK: f32 = 5.0 / 18.0;
+    match (left, right) {
+        (Class::Binary, _) | (_, Class::Binary) => MEDIUM,
+        (Class::Relation, Class::Relation) => 0.0,
+        (Class::Open, _) | (_, Class::Close | Class::Punctuation) => 0.0,
+        (Class::Relation, _) | (_, Class::Relation) => THICK,
+        (Class::Punctuation, _) => THIN,
+        (Class::Operator, Class::Ordinary | Class::Operator) => THIN,
+        _ => 0.0,
+    }
+}
+
+fn atom(atom: &Atom, size: f32, script: bool) -> Drawing {
+    match atom {
+        &Atom::Symbol { ch, class, italic } => glyph(ch, class, italic, size),
+        Atom::Group(atoms) => list(atoms, size, script),
+        Atom::Space(ems) => Drawing {
+            width: ems * size,
+            ..Drawing::default()
+        },
+        Atom::Fraction(numerator, denominator) => fraction(numerator, denominator, size),
+        Atom::Sqrt(body) => sqrt(list(body, size, script), size),
+        Atom::Scripts { base, sup, sub } => scripts(
+            self::atom(base, size, script),
+            sup.as_deref(),
+            sub.as_deref(),
+            size,
+        ),
+    }
+}
+
+fn glyph(ch: char, class: Class, italic: bool, size: f32) -> Drawing {
+    let (width, ascent, descent) = metrics(ch, class);
+    Drawing {
+        glyphs: vec![PlacedGlyph {
+            ch,
+            x: 0.0,
+            y: 0.0,
+            size,
+            italic,
+        }],
+        rules: Vec::new(),
+        width: width * size,
+        ascent: ascent * size,
+        descent: descent * size,
+    }
+}
+
+/// Width, ascent, and descent of a glyph, in ems.
+fn metrics(ch: char, class: Class) -> (f32, f32, f32) {
+    match class {
+        Class::Operator if ch == '∫' => (0.55, 0.8, 0.3),
+        Class::Operator => (1.0, 0.75, 0.25),
+        Class::Open | Class::Close => (0.39, 0.75, 0.25),
+        Class::Binary | Class::Relation => (0.78, 0.58, 0.08),
+        Class::Punctuation => (0.28, 0.1, 0.2),
+        Class::Ordinary => match ch {
+            '0'..='9' => (0.5, 0.65, 0.0),
+            'b' | 'd' | 'f' | 'h' | 'k' | 'l' | 't' | 'δ' | 'θ' | 'λ' | '∂' => {
+                (0.5, 0.69, 0.0)
+            }
+            'g' | 'j' | 'p' | 'q' | 'y' | 'β' | 'γ' | 'ζ' | 'η' | 'μ' | 'ξ' | 'ρ' | 'ϕ' | 'φ'
+            | 'χ' | 'ψ' => (0.5, 0.44, 0.2),
+            'a'..='z' | 'α'..='ω' | 'ϵ' => (0.5, 0.44, 0.0),
+            'A'..='Z' | 'Γ'..='Ω' => (0.68, 0.68, 0.0),
+            '.' => (0.28, 0.1, 0.0),
+            _ => (0.55, 0.7, 0.1),
+        },
+    }
+}
+
+/// Add `drawing` to `out` with its baseline origin at (`x`, `y`).
+fn place(out: &mut Drawing, drawing: Drawing, x: f32, y: f32) {
+    out.glyphs
+        .extend(drawing.glyphs.into_iter().map(|glyph| PlacedGlyph {
+            x: glyph.x + x,
+            y: glyph.y + y,
+            ..glyph
+        }));
+    out.rules.extend(
+        drawing
+            .rules
+            .into_iter()
+            .map(|[rx, ry, width, height]| [rx + x, ry + y, width, height]),
+    );
+    out.width = out.width.max(x + drawing.width);
+    out.ascent = out.ascent.max(drawing.ascent - y);
+    out.descent = out.descent.max(drawing.descent + y);
+}
+
+/// Add a filled rectangle to `out`.
+fn rule(out: &mut Drawing, x: f32, y: f32, width: f32, height: f32) {
+    out.rules.push([x, y, width, height]);
+    out.width = out.width.max(x + width);
+    out.ascent = out.ascent.max(-y);
+    out.descent = out.descent.max(y + height);
+}
+
+/// A fraction, its bar centered on the math axis and its parts centered
+/// over each other.
+fn fraction(numerator: &[Atom], denominator: &[Atom], size: f32) -> Drawing {
+    let numerator = list(numerator, size * FRACTION_SCALE, false);
+    let denominator = list(denominator, size * FRACTION_SCALE, false);
+    let (axis, thickness) = (AXIS * size, RULE * size);
+    let (gap, padding) = (0.12 * size, 0.1 * size);
+    let width = numerator.width.max(denominator.width) + 2.0 * padding;
+
+    let mut out = Drawing::default();
+    rule(&mut out, 0.0, -axis - thickness / 2.0, width, thickness);
+    let numerator_y = -(axis + thickness / 2.0 + gap + numerator.descent);
+    let denominator_y = -axis + thickness / 2.0 + gap + denominator.ascent;
+    let numerator_x = (width - numerator.width) / 2.0;
+    let denominator_x = (width - denominator.width) / 2.0;
+    place(&mut out, numerator, numerator_x, numerator_y);
+    place(&mut out, denominator, denominator_x, denominator_y);
+    out
+}
+
+/// A radical sign grown to the height of the body, with a bar over it.
+fn sqrt(body: Drawing, size: f32) -> Drawing {
+    let (clearance, thickness) = (0.1 * size, RULE * size);
+    // The radical glyph is an em tall, a fifth of it below the baseline.
+    let radical_size = size.max(body.ascent + clearance + thickness + body.descent);
+    let radical_width = 0.55 * radical_size;
+    let top = body.ascent + clearance + thickness;
+
+    let mut out = glyph('√', Class::Ordinary, false, radical_size);
+    out.width = radical_width;
+    let mut shifted = Drawing::default();
+    place(&mut shifted, out, 0.0, -(top - 0.8 * radical_size));
+    let mut out = shifted;
+    rule(
+        &mut out,
+        radical_width,
+        -top,
+        body.width + 0.05 * size,
+        thickness,
+    );
+    place(&mut out, body, radical_width, 0.0);
+    out
+}
+
+/// A base with a superscript raised and a subscript lowered beside it,
+/// kept apart when it has both.
+fn scripts(base: Drawing, sup: Option<&[Atom]>, sub: Option<&[Atom]>, size: f32) -> Drawing {
+    let script_size = size * SCRIPT_SCALE;
+    let x = base.width;
+    let mut out = Drawing::default();
+    place(&mut out, base.clone(), 0.0, 0.0);
+
+    let sup = sup.map(|atoms| list(atoms, script_size, true));
+    let sub = sub.map(|atoms| list(atoms, script_size, true));
+    let mut sup_shift = sup.as_ref().map_or(0.0, |sup| {
+        (base.ascent - 0.3 * size)
+            .max(0.4 * size)
+            .max(sup.descent + 0.11 * size)
+    });
+    let mut sub_shift = sub.as_ref().map_or(0.0, |sub| {
+        (base.descent + 0.05 * size)
+            .max(0.15 * size)
+            .max(sub.ascent - 0.35 * size)
+    });
+    if let (Some(sup), Some(sub)) = (&sup, &sub) {
+        let gap = (sub_shift - sub.ascent) - (sup.descent - sup_shift);
+        let min_gap = 0.16 * size;
+        if gap < min_gap {
+            sub_shift += (min_gap - gap) / 2.0;
+            sup_shift += (min_gap - gap) / 2.0;
+        }
+    }
+
+    if let Some(sup) = sup {
+        place(&mut out, sup, x, -sup_shift);
+    }
+    if let Some(sub) = sub {
+        place(&mut out, sub, x, sub_shift);
+    }
+    out.width += 0.05 * size;
+    out
+}
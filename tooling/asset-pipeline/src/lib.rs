@@ -1,22 +1,347 @@
 //! # Asset Pipeline
 //!
 //! Asset processing pipeline for Wolia builds.
+//!
+//! Raster images are re-encoded without their metadata and scaled down to a
+//! maximum dimension, fonts are validated and copied, and anything else is
+//! copied as is. The output directory gets a manifest mapping each source to
+//! its output and content hashes, which later runs use to skip sources that
+//! have not changed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+/// Name of the manifest written to the output directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Images larger than this in either dimension are scaled down by default.
+pub const DEFAULT_MAX_DIMENSION: u32 = 4096;
+
+/// Process assets for packaging with the default settings.
+pub fn process_assets(input_dir: &Path, output_dir: &Path) -> Result<Report, Error> {
+    Pipeline::new().run(input_dir, output_dir)
+}
+
+/// Asset processing settings.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    max_dimension: u32,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pipeline {
+    /// Create a pipeline with the default settings.
+    pub fn new() -> Self {
+        Self {
+            max_dimension: DEFAULT_MAX_DIMENSION,
+        }
+    }
+
+    /// Scale images down to fit within `max_dimension` pixels.
+    pub fn with_max_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_dimension = max_dimension.max(1);
+        self
+    }
+
+    /// Process every file under `input_dir` into `output_dir`, keeping the
+    /// relative paths, and write the manifest.
+    pub fn run(&self, input_dir: &Path, output_dir: &Path) -> Result<Report, Error> {
+        fs::create_dir_all(output_dir)?;
+        // Outputs processed with other settings are stale.
+        let previous = Manifest::load(output_dir)?
+            .filter(|manifest| manifest.max_dimension == self.max_dimension)
+            .unwrap_or_default();
+
+        let mut sources = Vec::new();
+        collect_files(input_dir, &output_dir.canonicalize()?, &mut sources)?;
+        sources.sort();
 
-/// Process assets for packaging.
-pub fn process_assets(
-    _input_dir: &std::path::Path,
-    _output_dir: &std::path::Path,
-) -> Result<(), Error> {
-    // TODO: Implement asset processing
+        let mut report = Report {
+            manifest: Manifest {
+                max_dimension: self.max_dimension,
+                assets: BTreeMap::new(),
+            },
+            processed: 0,
+            skipped: 0,
+        };
+        for path in sources {
+            let relative = path.strip_prefix(input_dir).unwrap_or(&path);
+            let key = manifest_path(relative);
+            let data = fs::read(&path)?;
+            let source_hash = content_hash(&data);
+
+            match previous.assets.get(&key) {
+                Some(entry) if entry.source_hash == source_hash && entry.is_current(output_dir) => {
+                    report.manifest.assets.insert(key, entry.clone());
+                    report.skipped += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let output = self
+                .process(relative, data)
+                .map_err(|reason| Error::Processing(format!("{key}: {reason}")))?;
+            let target = output_dir.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, &output)?;
+
+            report.manifest.assets.insert(
+                key.clone(),
+                ManifestEntry {
+                    output: key,
+                    source_hash,
+                    hash: content_hash(&output),
+                },
+            );
+            report.processed += 1;
+        }
+
+        report.manifest.save(output_dir)?;
+        Ok(report)
+    }
+
+    /// The processed contents of the asset at `path`.
+    fn process(&self, path: &Path, data: Vec<u8>) -> Result<Vec<u8>, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "ttf" | "otf" | "ttc" | "otc" => {
+                font_processor::validate_faces(&data).map_err(|e| e.to_string())?;
+                Ok(data)
+            }
+            _ => match ImageFormat::from_extension(&extension) {
+                // Other image formats may be animated, so they are copied.
+                Some(
+                    format @ (ImageFormat::Png
+                    | ImageFormat::Jpeg
+                    | ImageFormat::Bmp
+                    | ImageFormat::Tiff),
+                ) => self.process_image(&data, format).map_err(|e| e.to_string()),
+                _ => Ok(data),
+            },
+        }
+    }
+
+    /// Decode an image and encode it again in the same format, which drops
+    /// its metadata, after scaling it down to the maximum dimension.
+    fn process_image(&self, data: &[u8], format: ImageFormat) -> image::ImageResult<Vec<u8>> {
+        let mut image = image::load_from_memory_with_format(data, format)?;
+        if image.width() > self.max_dimension || image.height() > self.max_dimension {
+            image = image.resize(self.max_dimension, self.max_dimension, FilterType::Lanczos3);
+        }
+        if format == ImageFormat::Jpeg {
+            image = DynamicImage::ImageRgb8(image.to_rgb8());
+        } else if format == ImageFormat::Bmp {
+            image = DynamicImage::ImageRgba8(image.to_rgba8());
+        }
+
+        let mut output = Cursor::new(Vec::new());
+        image.write_to(&mut output, format)?;
+        Ok(output.into_inner())
+    }
+}
+
+/// Add the files under `dir` to `files`, leaving out the output directory.
+fn collect_files(dir: &Path, output_dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.canonicalize()? != output_dir {
+                collect_files(&path, output_dir, files)?;
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
     Ok(())
 }
 
+/// A relative path with `/` separators on every platform.
+fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Hex FNV-1a hash of `data`, stable across runs and platforms.
+fn content_hash(data: &[u8]) -> String {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// The outcome of a pipeline run.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The manifest written to the output directory.
+    pub manifest: Manifest,
+    /// Number of assets processed.
+    pub processed: usize,
+    /// Number of assets skipped because they had not changed.
+    pub skipped: usize,
+}
+
+/// Record of the processed assets, keyed by source path relative to the
+/// input directory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The maximum image dimension the assets were processed with.
+    pub max_dimension: u32,
+    pub assets: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Load the manifest from an output directory, if it has a readable one.
+    pub fn load(output_dir: &Path) -> Result<Option<Self>, Error> {
+        match fs::read(output_dir.join(MANIFEST_FILE)) {
+            // An unreadable manifest just means everything is processed again.
+            Ok(data) => Ok(serde_json::from_slice(&data).ok()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the manifest to an output directory.
+    pub fn save(&self, output_dir: &Path) -> Result<(), Error> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        fs::write(output_dir.join(MANIFEST_FILE), json)?;
+        Ok(())
+    }
+}
+
+/// A processed asset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Output path relative to the output directory.
+    pub output: String,
+    /// Hash of the source contents.
+    pub source_hash: String,
+    /// Hash of the output contents.
+    pub hash: String,
+}
+
+impl ManifestEntry {
+    /// Whether the output is still in place as it was written.
+    fn is_current(&self, output_dir: &Path) -> bool {
+        fs::read(output_dir.join(&self.output)).is_ok_and(|data| content_hash(&data) == self.hash)
+    }
+}
+
 /// Errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Manifest error: {0}")]
+    Manifest(#[from] serde_json::Error),
+
     #[error("Processing error: {0}")]
     Processing(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    const TUFFY: &[u8] = include_bytes!("../../../test-suite/fonts/Tuffy.ttf");
+
+    /// An input directory with a 64×32 PNG and a font in subdirectories.
+    fn input() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("images")).unwrap();
+        fs::create_dir_all(dir.path().join("fonts")).unwrap();
+
+        let image = RgbaImage::from_fn(64, 32, |x, y| Rgba([x as u8 * 4, y as u8 * 8, 0, 255]));
+        image.save(dir.path().join("images/banner.png")).unwrap();
+        fs::write(dir.path().join("fonts/Tuffy.ttf"), TUFFY).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_processes_assets_and_writes_manifest() {
+        let input = input();
+        let output = tempfile::tempdir().unwrap();
+        let pipeline = Pipeline::new().with_max_dimension(16);
+
+        let report = pipeline.run(input.path(), output.path()).unwrap();
+        assert_eq!((report.processed, report.skipped), (2, 0));
+
+        let manifest = Manifest::load(output.path()).unwrap().unwrap();
+        assert_eq!(manifest, report.manifest);
+        let paths: Vec<_> = manifest.assets.keys().map(String::as_str).collect();
+        assert_eq!(paths, ["fonts/Tuffy.ttf", "images/banner.png"]);
+
+        let font = &manifest.assets["fonts/Tuffy.ttf"];
+        assert_eq!(font.hash, content_hash(TUFFY));
+        assert_eq!(font.source_hash, font.hash);
+
+        let banner = &manifest.assets["images/banner.png"];
+        let image = image::open(output.path().join(&banner.output)).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+
+        // A second run into a fresh directory produces identical hashes.
+        let again = tempfile::tempdir().unwrap();
+        let report = pipeline.run(input.path(), again.path()).unwrap();
+        assert_eq!(report.manifest, manifest);
+    }
+
+    #[test]
+    fn test_skips_unchanged_assets() {
+        let input = input();
+        let output = tempfile::tempdir().unwrap();
+        process_assets(input.path(), output.path()).unwrap();
+
+        let report = process_assets(input.path(), output.path()).unwrap();
+        assert_eq!((report.processed, report.skipped), (0, 2));
+
+        fs::write(input.path().join("notes.txt"), "hello").unwrap();
+        fs::remove_file(output.path().join("fonts/Tuffy.ttf")).unwrap();
+        let report = process_assets(input.path(), output.path()).unwrap();
+        assert_eq!((report.processed, report.skipped), (2, 1));
+        assert_eq!(
+            fs::read_to_string(output.path().join("notes.txt")).unwrap(),
+            "hello"
+        );
+
+        // Different settings make every output stale.
+        let report = Pipeline::new()
+            .with_max_dimension(8)
+            .run(input.path(), output.path())
+            .unwrap();
+        assert_eq!((report.processed, report.skipped), (3, 0));
+    }
+
+    #[test]
+    fn test_rejects_invalid_fonts() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::write(input.path().join("broken.ttf"), b"not a font").unwrap();
+
+        let Err(Error::Processing(reason)) = process_assets(input.path(), output.path()) else {
+            panic!("expected a processing error");
+        };
+        assert!(reason.starts_with("broken.ttf: "));
+    }
+}
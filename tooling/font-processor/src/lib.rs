@@ -2,25 +2,162 @@
 //!
 //! Font processing tools for Wolia development.
 
-/// Process and validate font files.
-pub fn validate_font(_data: &[u8]) -> Result<FontInfo, Error> {
-    // TODO: Implement font validation
-    Err(Error::NotImplemented)
+use ttf_parser::{Face, Tag, name_id};
+
+/// Tables a font needs to be laid out and rendered: the header, the
+/// character map, horizontal metrics, and outlines in either format.
+const REQUIRED_TABLES: &[&[&[u8; 4]]] = &[
+    &[b"head"],
+    &[b"cmap"],
+    &[b"hmtx"],
+    &[b"glyf", b"CFF ", b"CFF2"],
+];
+
+/// Process and validate a font file. For a collection, this is the first
+/// face; see [`validate_faces`] for all of them.
+pub fn validate_font(data: &[u8]) -> Result<FontInfo, Error> {
+    validate_face(data, 0)
+}
+
+/// Validate every face in a font file: the one face of a TrueType or
+/// OpenType font, or each face of a TrueType collection.
+pub fn validate_faces(data: &[u8]) -> Result<Vec<FontInfo>, Error> {
+    let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+    (0..count).map(|index| validate_face(data, index)).collect()
+}
+
+/// Validate the face at `index` in a font file.
+pub fn validate_face(data: &[u8], index: u32) -> Result<FontInfo, Error> {
+    let face = Face::parse(data, index).map_err(|e| Error::InvalidFont(e.to_string()))?;
+    let raw = face.raw_face();
+    for alternatives in REQUIRED_TABLES {
+        if !alternatives
+            .iter()
+            .any(|tag| raw.table(Tag::from_bytes(tag)).is_some())
+        {
+            let names: Vec<_> = alternatives
+                .iter()
+                .map(|tag| String::from_utf8_lossy(&tag[..]).trim_end().to_string())
+                .collect();
+            return Err(Error::InvalidFont(format!(
+                "missing {} table",
+                names.join(" or ")
+            )));
+        }
+    }
+
+    Ok(FontInfo {
+        index,
+        family: name(&face, &[name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]).unwrap_or_default(),
+        style: name(&face, &[name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY])
+            .unwrap_or_else(|| "Regular".to_string()),
+        num_glyphs: face.number_of_glyphs(),
+        units_per_em: face.units_per_em(),
+        ascent: face.ascender(),
+        descent: face.descender(),
+        monospaced: face.is_monospaced(),
+    })
+}
+
+/// The first of the names with `ids` that can be decoded, in that order of
+/// preference.
+fn name(face: &Face, ids: &[u16]) -> Option<String> {
+    ids.iter().find_map(|&id| {
+        face.names()
+            .into_iter()
+            .filter(|name| name.name_id == id)
+            .find_map(|name| name.to_string())
+            .filter(|name| !name.is_empty())
+    })
 }
 
 /// Font information.
+#[derive(Debug, Clone, PartialEq)]
 pub struct FontInfo {
+    /// Index of the face in its file; 0 unless the file is a collection.
+    pub index: u32,
     pub family: String,
     pub style: String,
     pub num_glyphs: u16,
+    /// Design units per em square.
+    pub units_per_em: u16,
+    /// Distance from the baseline to the top of the line, in design units.
+    pub ascent: i16,
+    /// Distance from the baseline to the bottom of the line, in design
+    /// units; negative below the baseline.
+    pub descent: i16,
+    /// Whether every glyph has the same advance width.
+    pub monospaced: bool,
 }
 
 /// Errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Invalid font data")]
-    InvalidFont,
+    #[error("Invalid font data: {0}")]
+    InvalidFont(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TUFFY: &[u8] = include_bytes!("../../../test-suite/fonts/Tuffy.ttf");
+
+    /// A collection holding `font` twice, with its table offsets moved past
+    /// the collection header.
+    fn collection(font: &[u8]) -> Vec<u8> {
+        const HEADER: u32 = 20;
+        let mut data = b"ttcf".to_vec();
+        data.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&HEADER.to_be_bytes());
+        data.extend_from_slice(&HEADER.to_be_bytes());
+
+        let mut font = font.to_vec();
+        let tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for record in 0..tables {
+            let at = 12 + record * 16 + 8;
+            let offset = u32::from_be_bytes(font[at..at + 4].try_into().unwrap());
+            font[at..at + 4].copy_from_slice(&(offset + HEADER).to_be_bytes());
+        }
+        data.extend_from_slice(&font);
+        data
+    }
+
+    #[test]
+    fn test_parses_font() {
+        let info = validate_font(TUFFY).unwrap();
+        assert_eq!(info.family, "Tuffy");
+        assert_eq!(info.style, "Regular");
+        assert_eq!(info.num_glyphs, 1502);
+        assert_eq!(info.units_per_em, 2048);
+        assert!(info.ascent > 0 && info.descent < 0);
+        assert!(!info.monospaced);
+    }
+
+    #[test]
+    fn test_reports_each_face_of_collection() {
+        let faces = validate_faces(&collection(TUFFY)).unwrap();
+        assert_eq!(faces.len(), 2);
+        assert_eq!(faces[1].index, 1);
+        assert_eq!(faces[1].family, "Tuffy");
+        assert_eq!(validate_faces(TUFFY).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_invalid_fonts() {
+        assert!(matches!(
+            validate_font(b"not a font"),
+            Err(Error::InvalidFont(_))
+        ));
 
-    #[error("Not implemented")]
-    NotImplemented,
+        // Rename the character map so the font has none.
+        let mut font = TUFFY.to_vec();
+        let at = font.windows(4).position(|tag| tag == b"cmap").unwrap();
+        font[at + 3] = b'q';
+        let Err(Error::InvalidFont(reason)) = validate_font(&font) else {
+            panic!("expected an invalid font");
+        };
+        assert_eq!(reason, "missing cmap table");
+    }
 }
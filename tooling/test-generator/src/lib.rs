@@ -1,15 +1,369 @@
 //! # Test Generator
 //!
 //! Generate test documents for Wolia testing.
+//!
+//! Content comes from a small seeded random number generator, so the same
+//! seed always gives the same text and structure, while different seeds
+//! give different text for the same structure.
+
+use std::sync::LazyLock;
+
+use wolia_core::{Document, Node, NodeKind, Span, Text, TextStyle};
+
+/// Seed used when none is given.
+pub const DEFAULT_SEED: u64 = 0x5EED;
+
+/// Words paragraphs are made of.
+const WORDS: &str = "
+    the document layout engine flows text across pages while each paragraph keeps its own
+    style and spacing a quick brown fox jumps over lazy dog reports often include tables
+    figures with captions that explain results in detail readers scan headings first then
+    read sections closely when they matter numbers like revenue growth margins are shown for
+    every quarter of year international collaboration requires careful coordination between
+    teams simple sentences mix short long words to test line breaking
+";
+
+static WORD_LIST: LazyLock<Vec<&str>> = LazyLock::new(|| WORDS.split_whitespace().collect());
 
-use wolia_core::Document;
+/// Link targets for linked spans.
+const LINKS: &[&str] = &[
+    "https://example.com",
+    "https://example.org/docs",
+    "https://example.net/report",
+];
+
+/// Paragraphs between headings in stress documents.
+const STRESS_SECTION_LENGTH: usize = 10;
 
 /// Generate a test document with various content types.
 pub fn generate_test_document() -> Document {
-    Document::new()
+    Generator::new(DEFAULT_SEED).test_document()
 }
 
 /// Generate a stress-test document.
-pub fn generate_stress_document(_paragraphs: usize) -> Document {
-    Document::new()
+pub fn generate_stress_document(paragraphs: usize) -> Document {
+    Generator::new(DEFAULT_SEED).stress_document(paragraphs)
+}
+
+/// A seeded generator of document content.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    /// Create a generator. The same seed always generates the same content.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A document with every common kind of content: headings at three
+    /// levels, paragraphs with bold, italic and linked text, a bulleted
+    /// and a numbered list, a table, and an image.
+    pub fn test_document(&mut self) -> Document {
+        let mut document = Document::new();
+        document.metadata.title = Some(self.sentence(3, 6));
+        let root = &mut document.root;
+
+        root.add_child(Node::heading(1, self.plain_text(3, 6)));
+        root.add_child(Node::paragraph(self.rich_text(30, 60)));
+
+        for _ in 0..2 {
+            root.add_child(Node::heading(2, self.plain_text(2, 5)));
+            root.add_child(Node::paragraph(self.rich_text(40, 90)));
+            root.add_child(Node::heading(3, self.plain_text(2, 5)));
+            root.add_child(Node::paragraph(self.rich_text(20, 50)));
+        }
+
+        root.add_child(self.list(false, 3));
+        root.add_child(self.list(true, 4));
+        root.add_child(self.table(4, 3));
+        root.add_child(Node::new(NodeKind::Image {
+            src: "placeholder.png".to_string(),
+            alt: Some(self.sentence(2, 4)),
+        }));
+        root.add_child(Node::paragraph(self.rich_text(20, 40)));
+
+        document
+    }
+
+    /// A document of `paragraphs` paragraphs of 20 to 120 words, with a
+    /// heading before every tenth, for layout and performance testing.
+    pub fn stress_document(&mut self, paragraphs: usize) -> Document {
+        let mut document = Document::new();
+        for index in 0..paragraphs {
+            if index % STRESS_SECTION_LENGTH == 0 {
+                let level = if index % (STRESS_SECTION_LENGTH * 5) == 0 {
+                    1
+                } else {
+                    2
+                };
+                document
+                    .root
+                    .add_child(Node::heading(level, self.plain_text(2, 6)));
+            }
+            document
+                .root
+                .add_child(Node::paragraph(self.rich_text(20, 120)));
+        }
+        document
+    }
+
+    /// A document of `sections` headings, cycling through the six levels,
+    /// each followed by one short paragraph.
+    pub fn heading_heavy_document(&mut self, sections: usize) -> Document {
+        let mut document = Document::new();
+        for index in 0..sections {
+            let level = (index % 6) as u8 + 1;
+            document
+                .root
+                .add_child(Node::heading(level, self.plain_text(2, 8)));
+            document
+                .root
+                .add_child(Node::paragraph(self.rich_text(10, 30)));
+        }
+        document
+    }
+
+    /// A document of `tables` tables of 8 rows and 5 columns, each after
+    /// a short paragraph.
+    pub fn table_heavy_document(&mut self, tables: usize) -> Document {
+        let mut document = Document::new();
+        for _ in 0..tables {
+            document
+                .root
+                .add_child(Node::paragraph(self.rich_text(10, 30)));
+            document.root.add_child(self.table(8, 5));
+        }
+        document
+    }
+
+    /// A list of `items` items of one paragraph each.
+    pub fn list(&mut self, ordered: bool, items: usize) -> Node {
+        let mut list = Node::new(NodeKind::List { ordered });
+        for _ in 0..items {
+            let mut item = Node::new(NodeKind::ListItem);
+            item.add_child(Node::paragraph(self.rich_text(4, 14)));
+            list.add_child(item);
+        }
+        list
+    }
+
+    /// A table with a bold header row and `rows - 1` rows of figures.
+    pub fn table(&mut self, rows: usize, cols: usize) -> Node {
+        let mut table = Node::new(NodeKind::Table { rows, cols });
+        for row in 0..rows {
+            let mut table_row = Node::new(NodeKind::TableRow);
+            for _ in 0..cols {
+                let text = if row == 0 {
+                    let mut text = self.plain_text(1, 2);
+                    let end = text.len();
+                    text.add_span(Span::new(0, end, bold()));
+                    text
+                } else {
+                    Text::new(format!("{}.{:02}", self.below(10_000), self.below(100)))
+                };
+                let mut cell = Node::new(NodeKind::TableCell {
+                    col_span: 1,
+                    row_span: 1,
+                });
+                cell.add_child(Node::paragraph(text));
+                table_row.add_child(cell);
+            }
+            table.add_child(table_row);
+        }
+        table
+    }
+
+    /// Text of `min` to `max` words with some of them bold, italic or
+    /// linked.
+    pub fn rich_text(&mut self, min: usize, max: usize) -> Text {
+        let words = self.between(min, max);
+        let mut text = Text::empty();
+        for index in 0..words {
+            if index > 0 {
+                text.content.push(' ');
+            }
+            let start = text.content.len();
+            text.content.push_str(self.word());
+            let end = text.content.len();
+            let style = match self.below(20) {
+                0 | 1 => Some(bold()),
+                2 | 3 => Some(TextStyle {
+                    italic: Some(true),
+                    ..Default::default()
+                }),
+                4 => Some(TextStyle {
+                    link: Some(LINKS[self.below(LINKS.len())].to_string()),
+                    underline: Some(true),
+                    ..Default::default()
+                }),
+                _ => None,
+            };
+            if let Some(style) = style {
+                text.add_span(Span::new(start, end, style));
+            }
+        }
+        text.content.push('.');
+        capitalize(&mut text.content);
+        text
+    }
+
+    /// Unformatted text of `min` to `max` words.
+    pub fn plain_text(&mut self, min: usize, max: usize) -> Text {
+        let mut text = Text::new(self.sentence(min, max));
+        text.content.pop();
+        text
+    }
+
+    /// A capitalized sentence of `min` to `max` words, with a full stop.
+    fn sentence(&mut self, min: usize, max: usize) -> String {
+        let words = self.between(min, max);
+        let mut sentence = (0..words)
+            .map(|_| self.word())
+            .collect::<Vec<_>>()
+            .join(" ");
+        sentence.push('.');
+        capitalize(&mut sentence);
+        sentence
+    }
+
+    fn word(&mut self) -> &'static str {
+        WORD_LIST[self.below(WORD_LIST.len())]
+    }
+
+    /// A number from `min` to `max` inclusive.
+    fn between(&mut self, min: usize, max: usize) -> usize {
+        min + self.below(max.saturating_sub(min) + 1)
+    }
+
+    /// A number below `bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound.max(1) as u64) as usize
+    }
+
+    /// The next number from SplitMix64.
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+fn bold() -> TextStyle {
+    TextStyle {
+        font_weight: Some(700),
+        ..Default::default()
+    }
+}
+
+/// Uppercase the first letter. The words are all ASCII.
+fn capitalize(text: &mut str) {
+    if let Some(first) = text.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use wolia_layout::LayoutEngine;
+
+    use super::*;
+
+    /// Count the nodes of each kind, by their serialized type name.
+    fn kind_counts(document: &Document) -> HashMap<&'static str, usize> {
+        fn visit(node: &Node, counts: &mut HashMap<&'static str, usize>) {
+            let name = match &node.kind {
+                NodeKind::Heading { .. } => "heading",
+                NodeKind::Paragraph(_) => "paragraph",
+                NodeKind::List { ordered: true } => "ordered_list",
+                NodeKind::List { ordered: false } => "bullet_list",
+                NodeKind::ListItem => "list_item",
+                NodeKind::Table { .. } => "table",
+                NodeKind::TableCell { .. } => "table_cell",
+                NodeKind::Image { .. } => "image",
+                _ => "other",
+            };
+            *counts.entry(name).or_default() += 1;
+            for child in &node.children {
+                visit(child, counts);
+            }
+        }
+        let mut counts = HashMap::new();
+        visit(&document.root, &mut counts);
+        counts
+    }
+
+    fn span_styles(document: &Document) -> Vec<TextStyle> {
+        fn visit(node: &Node, styles: &mut Vec<TextStyle>) {
+            if let NodeKind::Paragraph(text) | NodeKind::Heading { text, .. } = &node.kind {
+                styles.extend(text.spans.iter().map(|span| span.style.clone()));
+            }
+            for child in &node.children {
+                visit(child, styles);
+            }
+        }
+        let mut styles = Vec::new();
+        visit(&document.root, &mut styles);
+        styles
+    }
+
+    #[test]
+    fn test_document_node_counts() {
+        let document = Generator::new(42).test_document();
+        let counts = kind_counts(&document);
+        assert_eq!(counts["heading"], 5);
+        // 6 body paragraphs, 7 list items, 12 table cells.
+        assert_eq!(counts["paragraph"], 6 + 7 + 12);
+        assert_eq!(counts["bullet_list"], 1);
+        assert_eq!(counts["ordered_list"], 1);
+        assert_eq!(counts["list_item"], 7);
+        assert_eq!(counts["table"], 1);
+        assert_eq!(counts["table_cell"], 12);
+        assert_eq!(counts["image"], 1);
+
+        let styles = span_styles(&document);
+        assert!(styles.iter().any(|style| style.font_weight == Some(700)));
+        assert!(styles.iter().any(|style| style.italic == Some(true)));
+        assert!(styles.iter().any(|style| style.link.is_some()));
+    }
+
+    #[test]
+    fn test_seed_is_reproducible() {
+        let text = |seed| {
+            let document = Generator::new(seed).test_document();
+            document.root.children[1].kind.clone()
+        };
+        let (NodeKind::Paragraph(a), NodeKind::Paragraph(b), NodeKind::Paragraph(c)) =
+            (text(7), text(7), text(8))
+        else {
+            panic!("expected paragraphs");
+        };
+        assert_eq!(a.content, b.content);
+        assert_eq!(a.spans, b.spans);
+        assert_ne!(a.content, c.content);
+    }
+
+    #[test]
+    fn test_stress_document_paragraph_count() {
+        let document = generate_stress_document(95);
+        let counts = kind_counts(&document);
+        assert_eq!(counts["paragraph"], 95);
+        assert_eq!(counts["heading"], 10);
+    }
+
+    #[test]
+    fn test_serializes_and_lays_out() {
+        for document in [generate_test_document(), generate_stress_document(40)] {
+            let json = document.to_json().unwrap();
+            let restored = Document::from_json(&json).unwrap();
+            assert_eq!(restored.root.children.len(), document.root.children.len());
+            let layout = LayoutEngine::new().layout(&document).unwrap();
+            assert!(!layout.pages.is_empty());
+        }
+    }
 }